@@ -0,0 +1,191 @@
+use config::AuditConfig;
+use redis::AsyncTypedCommands;
+use redis::streams::StreamRangeReply;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+/// How many entries of one notification ID are indexed, older ones can only be
+/// found by reading the whole stream
+const INDEX_LEN: usize = 64;
+
+/// How long the index of a notification ID is kept after its last transition
+const INDEX_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Append the entry in ARGV[2] to the stream KEYS[1], trimmed to about ARGV[1]
+/// entries, and its ID to the index KEYS[2] of the notification ID
+const RECORD_SCRIPT: &str = r#"
+    local entry = redis.call('XADD', KEYS[1], 'MAXLEN', '~', ARGV[1], '*', 'entry', ARGV[2])
+    redis.call('RPUSH', KEYS[2], entry)
+    redis.call('LTRIM', KEYS[2], -tonumber(ARGV[3]), -1)
+    redis.call('EXPIRE', KEYS[2], ARGV[4])
+"#;
+
+/// Stream entry IDs of the transitions recorded for notification `id`
+fn index_key(stream: &str, id: u32) -> String {
+    format!("{stream}:{id}")
+}
+
+/// A transition in the lifecycle of a notification
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Received,
+    /// Dropped by a control plane filter
+    Filtered,
+    Displayed,
+    Selected,
+    Action {
+        key: String,
+    },
+    Closed {
+        reason: String,
+    },
+    Expired,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Received => write!(f, "received"),
+            Self::Filtered => write!(f, "filtered"),
+            Self::Displayed => write!(f, "displayed"),
+            Self::Selected => write!(f, "selected"),
+            Self::Action { key } => write!(f, "action ({key})"),
+            Self::Closed { reason } => write!(f, "closed ({reason})"),
+            Self::Expired => write!(f, "expired"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Entry {
+    pub id: u32,
+    pub uuid: String,
+    pub service: String,
+    /// Milliseconds since the Unix epoch
+    pub timestamp: i64,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+/// Appends lifecycle transitions to the audit stream,
+/// does nothing unless the audit log is enabled
+#[derive(Clone)]
+pub struct AuditLog {
+    con: Option<redis::aio::MultiplexedConnection>,
+    service: &'static str,
+    stream: String,
+    max_len: usize,
+}
+
+impl AuditLog {
+    pub fn new(
+        con: redis::aio::MultiplexedConnection,
+        service: &'static str,
+        stream: String,
+        config: &AuditConfig,
+    ) -> Self {
+        Self {
+            con: config.enabled.then_some(con),
+            service,
+            stream,
+            max_len: config.max_len,
+        }
+    }
+
+    pub async fn record(&self, id: u32, uuid: &str, event: Event) {
+        let Some(mut con) = self.con.clone() else {
+            return;
+        };
+
+        let entry = Entry {
+            id,
+            uuid: uuid.to_string(),
+            service: self.service.to_string(),
+            timestamp: config::now_ms(),
+            event,
+        };
+
+        let json = match serde_json::to_string(&entry) {
+            Ok(json) => json,
+            Err(e) => {
+                log::warn!("Failed to serialize audit entry: {}", e);
+                return;
+            }
+        };
+
+        if let Err(e) = redis::Script::new(RECORD_SCRIPT)
+            .key(&self.stream)
+            .key(index_key(&self.stream, id))
+            .arg(self.max_len)
+            .arg(json)
+            .arg(INDEX_LEN)
+            .arg(INDEX_TTL.as_secs())
+            .invoke_async::<()>(&mut con)
+            .await
+        {
+            log::warn!("Failed to write audit entry for {}: {}", id, e);
+        }
+    }
+}
+
+fn parse(reply: &StreamRangeReply) -> impl Iterator<Item = Entry> + '_ {
+    reply
+        .ids
+        .iter()
+        .filter_map(|stream_id| match stream_id.map.get("entry") {
+            Some(redis::Value::BulkString(json)) => serde_json::from_slice::<Entry>(json).ok(),
+            _ => None,
+        })
+}
+
+/// All recorded transitions, oldest first
+pub async fn entries(
+    con: &mut redis::aio::MultiplexedConnection,
+    stream: &str,
+) -> redis::RedisResult<Vec<Entry>> {
+    let reply = AsyncTypedCommands::xrange_all(con, stream).await?;
+
+    Ok(parse(&reply).collect())
+}
+
+/// Recorded transitions of notification `id` with `uuid`, oldest first. IDs are
+/// reused, so without `uuid` only those of the latest notification with the ID are
+/// returned. Read through the index of the ID rather than the whole stream
+pub async fn history(
+    con: &mut redis::aio::MultiplexedConnection,
+    stream: &str,
+    id: u32,
+    uuid: Option<&str>,
+) -> redis::RedisResult<Vec<Entry>> {
+    let stream_ids: Vec<String> =
+        AsyncTypedCommands::lrange(&mut *con, index_key(stream, id), 0, -1).await?;
+    if stream_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut pipe = redis::pipe();
+    stream_ids.iter().for_each(|stream_id| {
+        pipe.xrange(stream, stream_id, stream_id);
+    });
+    // Entries trimmed from the stream come back empty
+    let replies: Vec<StreamRangeReply> = pipe.query_async(con).await?;
+    let entries: Vec<_> = replies
+        .iter()
+        .flat_map(parse)
+        .filter(|entry| entry.id == id)
+        .collect();
+
+    let Some(uuid) = uuid
+        .map(str::to_string)
+        .or_else(|| entries.last().map(|entry| entry.uuid.clone()))
+    else {
+        return Ok(entries);
+    };
+
+    Ok(entries
+        .into_iter()
+        .filter(|entry| entry.uuid == uuid)
+        .collect())
+}
@@ -0,0 +1,203 @@
+use clap::Parser;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+use zbus::zvariant::Value;
+
+#[zbus::proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    async fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    async fn close_notification(&self, id: u32) -> zbus::Result<()>;
+}
+
+#[derive(Parser)]
+#[command(
+    author,
+    version,
+    about = "Flood moxnotify with synthetic notifications and measure their latency",
+    long_about = None
+)]
+struct Cli {
+    #[arg(short, long, value_name = "FILE", help = "Path to the config file")]
+    config: Option<Box<Path>>,
+
+    #[arg(
+        short = 'n',
+        long,
+        default_value_t = 100,
+        help = "Number of notifications to send"
+    )]
+    count: u32,
+
+    #[arg(
+        short,
+        long,
+        default_value_t = 10.,
+        help = "Notifications sent per second, 0 sends them as fast as possible"
+    )]
+    rate: f64,
+
+    #[arg(short, long, default_value_t = 64, help = "Size of the body in bytes")]
+    body_size: usize,
+
+    #[arg(
+        short,
+        long,
+        value_name = "SIZE",
+        help = "Attach a SIZExSIZE image to every notification"
+    )]
+    image: Option<i32>,
+
+    #[arg(
+        short,
+        long,
+        default_value_t = 0,
+        help = "Number of actions on every notification"
+    )]
+    actions: usize,
+
+    #[arg(
+        short,
+        long,
+        default_value_t = 5,
+        help = "Seconds to wait for the last notifications to get through"
+    )]
+    wait: u64,
+}
+
+/// RGBA gradient in the layout of the image-data hint
+fn image(size: i32) -> Value<'static> {
+    let data = (0..size)
+        .flat_map(|y| (0..size).flat_map(move |x| [x as u8, y as u8, 0x80, 0xff]))
+        .collect::<Vec<_>>();
+
+    Value::from((size, size, size * 4, true, 8, 4, data))
+}
+
+fn report(stage: &str, mut latencies: Vec<i64>, sent: usize) {
+    if latencies.is_empty() {
+        println!("{stage:<10} 0/{sent}");
+        return;
+    }
+
+    latencies.sort_unstable();
+    let percentile = |p: f64| latencies[((latencies.len() - 1) as f64 * p).round() as usize];
+
+    println!(
+        "{stage:<10} {}/{sent}  min {}ms  p50 {}ms  p95 {}ms  p99 {}ms  max {}ms",
+        latencies.len(),
+        latencies[0],
+        percentile(0.5),
+        percentile(0.95),
+        percentile(0.99),
+        latencies[latencies.len() - 1],
+    );
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    let config = config::Config::load(cli.config.as_deref()).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        config::Config::default()
+    });
+
+    // Every service records when it handled a notification there, which is what's measured
+    if !config.audit.enabled {
+        anyhow::bail!("Latency is read from the audit log, enable it with audit.enabled = true");
+    }
+
+    let conn = zbus::Connection::session().await?;
+    let notifications = NotificationsProxy::new(&conn).await?;
+
+    let body = "x".repeat(cli.body_size);
+    let actions = (0..cli.actions)
+        .flat_map(|i| [format!("action-{i}"), format!("Action {i}")])
+        .collect::<Vec<_>>();
+    let actions = actions.iter().map(String::as_str).collect::<Vec<_>>();
+    let image = cli.image.map(image);
+
+    let mut interval =
+        (cli.rate > 0.).then(|| tokio::time::interval(Duration::from_secs_f64(1. / cli.rate)));
+
+    let mut sent = HashMap::new();
+    for i in 0..cli.count {
+        if let Some(interval) = interval.as_mut() {
+            interval.tick().await;
+        }
+
+        let mut hints = HashMap::new();
+        if let Some(image) = image.as_ref() {
+            hints.insert("image-data", image.try_clone()?);
+        }
+
+        let timestamp = config::now_ms();
+        let id = notifications
+            .notify(
+                "moxnotify-bench",
+                0,
+                "",
+                &format!("Benchmark {i}"),
+                &body,
+                &actions,
+                hints,
+                0,
+            )
+            .await?;
+        sent.insert(id, timestamp);
+    }
+
+    tokio::time::sleep(Duration::from_secs(cli.wait)).await;
+
+    let mut con = redis::Client::open(&*config.redis.address)?
+        .get_multiplexed_async_connection()
+        .await?;
+    let entries = audit::entries(&mut con, &config.redis.keys().audit).await?;
+
+    let mut received = HashMap::new();
+    let mut displayed = HashMap::new();
+    entries.iter().for_each(|entry| {
+        // IDs are reused across runs, older entries belong to other notifications
+        let Some(&timestamp) = sent
+            .get(&entry.id)
+            .filter(|&&timestamp| entry.timestamp >= timestamp)
+        else {
+            return;
+        };
+
+        let latencies = match entry.event {
+            audit::Event::Received => &mut received,
+            audit::Event::Displayed => &mut displayed,
+            _ => return,
+        };
+        latencies
+            .entry(entry.id)
+            .or_insert(entry.timestamp - timestamp);
+    });
+
+    report("received", received.into_values().collect(), sent.len());
+    report("displayed", displayed.into_values().collect(), sent.len());
+
+    for id in sent.keys() {
+        notifications.close_notification(*id).await?;
+    }
+
+    Ok(())
+}
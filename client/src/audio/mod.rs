@@ -1,72 +1,46 @@
+#[cfg(feature = "audio")]
+mod backend;
+#[cfg(feature = "audio")]
 mod playback;
 
-use pipewire::{self as pw, sys::PW_ID_CORE};
+#[cfg(feature = "audio")]
+pub use backend::Audio;
+
 use std::path::Path;
+use std::sync::Arc;
+
+/// Resolve a sound name from the freedesktop sound theme
+#[cfg(feature = "audio")]
+pub fn lookup(name: &str) -> Option<Arc<Path>> {
+    freedesktop_sound::lookup(name)
+        .with_cache()
+        .find()
+        .map(std::convert::Into::into)
+}
 
+#[cfg(not(feature = "audio"))]
+pub fn lookup(_: &str) -> Option<Arc<Path>> {
+    None
+}
+
+/// Stand-in used when built without the `audio` feature, keeps track of the
+/// mute state so it can still be queried and toggled but never plays anything
+#[cfg(not(feature = "audio"))]
 pub struct Audio {
     muted: bool,
-    playback: Option<playback::Playback<playback::Played>>,
-    thread_loop: pw::thread_loop::ThreadLoopRc,
-    _context: pw::context::ContextRc,
-    core: pw::core::CoreRc,
 }
 
+#[cfg(not(feature = "audio"))]
 impl Audio {
     pub fn try_new() -> anyhow::Result<Self> {
-        pw::init();
-        let thread_loop =
-            unsafe { pw::thread_loop::ThreadLoopRc::new(Some("audio-manager"), None)? };
-        let lock = thread_loop.lock();
-        thread_loop.start();
-        let context = pw::context::ContextRc::new(&thread_loop, None)?;
-        let core = context.connect_rc(None)?;
-
-        let thread_clone = thread_loop.clone();
-        let pending = core.sync(0).expect("sync failed");
-        let _listener_core = core
-            .add_listener_local()
-            .done(move |id, seq| {
-                if id == PW_ID_CORE && seq == pending {
-                    thread_clone.signal(false);
-                }
-            })
-            .register();
-
-        thread_loop.wait();
-        lock.unlock();
-
-        Ok(Self {
-            muted: false,
-            playback: None,
-            thread_loop,
-            _context: context,
-            core,
-        })
+        log::info!("Built without audio support, notification sounds are disabled");
+        Ok(Self { muted: false })
     }
 
-    pub fn play<T>(&mut self, path: T) -> anyhow::Result<()>
+    pub fn play<T>(&mut self, _: T) -> anyhow::Result<()>
     where
         T: AsRef<Path>,
     {
-        if self.muted {
-            return Ok(());
-        }
-
-        if let Some(playback) = self.playback.take() {
-            if let Some(cooldown) = playback.cooldown.as_ref()
-                && cooldown.elapsed() > std::time::Duration::from_millis(20)
-            {
-                let lock = self.thread_loop.lock();
-                playback.stop();
-                lock.unlock();
-            } else {
-                self.playback = Some(playback);
-                return Ok(());
-            }
-        }
-
-        let playback = playback::Playback::new(self.thread_loop.clone(), self.core.clone(), &path)?;
-        self.playback = Some(playback.start());
         Ok(())
     }
 
@@ -1,10 +1,11 @@
 use super::{Button, ButtonType, Hint, State};
 use crate::components;
 use crate::components::{Bounds, Component};
+use crate::rendering::glyphon;
+use crate::rendering::moxui::{shape_renderer, texture_renderer};
 use crate::rendering::text::Text;
 use crate::styles::ButtonState;
 use config::client::Urgency;
-use moxui::{shape_renderer, texture_renderer};
 use std::sync::atomic::Ordering;
 
 // Hardcoded layout constants (previously configurable)
@@ -13,6 +14,15 @@ const ACTION_BUTTON_PADDING_BOTTOM: f32 = 5.0;
 const ACTION_BUTTON_MARGIN_LEFT: f32 = 5.0;
 const ACTION_BUTTON_MARGIN_RIGHT: f32 = 5.0;
 const ACTION_BUTTON_BORDER_SIZE: f32 = 1.0;
+/// Added above and below the text of the accept and decline buttons of calls
+const ANSWER_BUTTON_PADDING: f32 = 8.0;
+
+/// Role of an action that answers a call
+#[derive(Clone, Copy, PartialEq)]
+pub enum Answer {
+    Accept,
+    Decline,
+}
 
 pub struct ActionButton {
     pub context: components::Context,
@@ -25,6 +35,7 @@ pub struct ActionButton {
     pub width: f32,
     pub tx: Option<calloop::channel::Sender<crate::Event>>,
     pub uuid: String,
+    pub answer: Option<Answer>,
 }
 
 impl Component for ActionButton {
@@ -80,11 +91,16 @@ impl Component for ActionButton {
     }
 
     fn get_style(&self) -> &Self::Style {
-        let style = self.get_notification_style();
+        let buttons = &self.get_notification_style().buttons;
+        let button = match self.answer {
+            Some(Answer::Accept) => &buttons.accept,
+            Some(Answer::Decline) => &buttons.decline,
+            None => &buttons.action,
+        };
 
         match self.state() {
-            State::Unhovered => &style.buttons.action.default,
-            State::Hovered => &style.buttons.action.hover,
+            State::Unhovered => &button.default,
+            State::Hovered => &button.hover,
         }
     }
 
@@ -96,10 +112,16 @@ impl Component for ActionButton {
             + ACTION_BUTTON_MARGIN_LEFT
             + ACTION_BUTTON_MARGIN_RIGHT;
 
+        let answer_padding = if self.answer.is_some() {
+            ANSWER_BUTTON_PADDING * 2.0
+        } else {
+            0.0
+        };
         let height = text_extents.height
             + ACTION_BUTTON_BORDER_SIZE * 2.0
             + ACTION_BUTTON_PADDING_TOP
-            + ACTION_BUTTON_PADDING_BOTTOM;
+            + ACTION_BUTTON_PADDING_BOTTOM
+            + answer_padding;
 
         Bounds {
             x: self.x,
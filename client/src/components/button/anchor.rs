@@ -2,10 +2,11 @@ use super::{Button, Component, Hint, State};
 use crate::components;
 use crate::components::Bounds;
 use crate::components::text::body::Anchor;
+use crate::rendering::glyphon;
+use crate::rendering::moxui::{shape_renderer, texture_renderer};
 use crate::rendering::text::Text;
 use crate::styles::ButtonState;
 use config::client::Urgency;
-use moxui::{shape_renderer, texture_renderer};
 use std::sync::Arc;
 
 pub struct AnchorButton {
@@ -1,10 +1,11 @@
 use super::{Button, ButtonType, Hint, State};
 use crate::components;
 use crate::components::{Bounds, Component};
+use crate::rendering::glyphon;
+use crate::rendering::moxui::{shape_renderer, texture_renderer};
 use crate::rendering::text::Text;
 use crate::styles::ButtonState;
 use config::client::Urgency;
-use moxui::{shape_renderer, texture_renderer};
 use std::sync::atomic::Ordering;
 
 // Hardcoded layout constants (previously configurable)
@@ -1,21 +1,27 @@
 mod action;
 mod anchor;
 mod dismiss;
+mod role;
 
 use super::text::body;
 use crate::components::{self, Bounds, Component, Data};
+use crate::dbus::mpris::Control;
 use crate::moxnotify::types::Action;
+use crate::rendering::glyphon::{self, FontSystem, TextArea};
+use crate::rendering::moxui::{shape_renderer, texture_renderer};
 use crate::rendering::text::Text;
-use action::ActionButton;
-use anchor::AnchorButton;
 use crate::styles::{ButtonState, Hint as HintStyle};
-use config::client::Urgency;
+use action::{ActionButton, Answer};
+use anchor::AnchorButton;
 use config::client::keymaps;
+use config::client::{Calls, Urgency};
 use dismiss::DismissButton;
-use glyphon::{FontSystem, TextArea};
-use moxui::{shape_renderer, texture_renderer};
+use role::{Role, RoleButton};
 use std::sync::{Arc, atomic::Ordering};
 
+/// Space on either side of the label of the cancel button
+const CANCEL_BUTTON_PADDING: f32 = 10.0;
+
 #[derive(Clone, Copy, Debug)]
 pub enum State {
     Unhovered,
@@ -45,6 +51,20 @@ pub enum ButtonType {
     Dismiss,
     Action,
     Anchor,
+    /// Copies a one-time code, laid out along with the actions
+    Code,
+    /// Controls the player of a media notification, laid out along with the actions
+    Media,
+    /// Cancel action laid out next to the progress bar
+    Cancel,
+}
+
+impl ButtonType {
+    /// Whether buttons of this type share the row of actions at the bottom
+    #[must_use]
+    pub fn is_action(&self) -> bool {
+        matches!(self, Self::Action | Self::Code | Self::Media)
+    }
 }
 
 pub struct NotReady;
@@ -132,12 +152,183 @@ impl ButtonManager<Ready> {
         self.internal_add_anchors(anchors, font_system)
     }
 
-    pub fn finish(mut self, font_system: &mut FontSystem) -> ButtonManager<Finished> {
+    /// Add a button copying `code`, if there is one
+    pub fn add_code(mut self, code: Option<&str>, font_system: &mut FontSystem) -> Self {
+        let Some(code) = code else {
+            return self;
+        };
+
+        let urgency_styles = match self.context.urgency {
+            Urgency::Low => &self.context.styles.urgency_low,
+            Urgency::Normal => &self.context.styles.urgency_normal,
+            Urgency::Critical => &self.context.styles.urgency_critical,
+        };
+        let font = &urgency_styles.unfocused.buttons.action.default.font;
+        let text = Text::new(font, font_system, &i18n::tr!("copy-code", code = code));
+
+        self.buttons.push(Box::new(RoleButton {
+            button: ActionButton {
+                uuid: String::new(),
+                context: self.context.clone(),
+                hint: Hint::new(self.context.clone(), "", font_system),
+                text,
+                x: 0.,
+                y: 0.,
+                action: String::new(),
+                state: State::Unhovered,
+                width: 0.,
+                tx: None,
+                answer: None,
+            },
+            role: Role::Code(code.into()),
+            tx: self.sender.clone(),
+        }));
+
+        self
+    }
+
+    /// Add `action` as a button next to the progress bar, if there is one
+    pub fn add_cancel(
+        mut self,
+        action: Option<&Action>,
+        font_system: &mut FontSystem,
+        uuid: String,
+    ) -> Self {
+        let Some(action) = action else {
+            return self;
+        };
+
+        let urgency_styles = match self.context.urgency {
+            Urgency::Low => &self.context.styles.urgency_low,
+            Urgency::Normal => &self.context.styles.urgency_normal,
+            Urgency::Critical => &self.context.styles.urgency_critical,
+        };
+        let font = &urgency_styles.unfocused.buttons.action.default.font;
+        let text = Text::new(font, font_system, &action.label);
+        let width = text.get_bounds().width + CANCEL_BUTTON_PADDING * 2.0;
+
+        self.buttons.push(Box::new(RoleButton {
+            button: ActionButton {
+                uuid,
+                context: self.context.clone(),
+                hint: Hint::new(self.context.clone(), "", font_system),
+                text,
+                x: 0.,
+                y: 0.,
+                action: action.key.clone(),
+                state: State::Unhovered,
+                width,
+                tx: self.sender.clone(),
+                answer: None,
+            },
+            role: Role::Cancel,
+            tx: self.sender.clone(),
+        }));
+
+        self
+    }
+
+    /// Draw the actions answering a call as accept and decline buttons
+    pub fn add_answers(mut self, calls: Option<&Calls>) -> Self {
+        let Some(calls) = calls else {
+            return self;
+        };
+
+        self.buttons.iter_mut().for_each(|button| {
+            if let Some(action) = button.as_any_mut().downcast_mut::<ActionButton>() {
+                action.answer = if calls.accept.contains(&action.action) {
+                    Some(Answer::Accept)
+                } else if calls.decline.contains(&action.action) {
+                    Some(Answer::Decline)
+                } else {
+                    None
+                };
+            }
+        });
+
+        self
+    }
+
+    /// Add buttons controlling `player`, if there is one
+    pub fn add_media(mut self, player: Option<&Arc<str>>, font_system: &mut FontSystem) -> Self {
+        let Some(player) = player else {
+            return self;
+        };
+
+        let urgency_styles = match self.context.urgency {
+            Urgency::Low => &self.context.styles.urgency_low,
+            Urgency::Normal => &self.context.styles.urgency_normal,
+            Urgency::Critical => &self.context.styles.urgency_critical,
+        };
+        let font = &urgency_styles.unfocused.buttons.action.default.font;
+
+        Control::ALL.into_iter().for_each(|control| {
+            let text = Text::new(font, font_system, control.label());
+
+            self.buttons.push(Box::new(RoleButton {
+                button: ActionButton {
+                    uuid: String::new(),
+                    context: self.context.clone(),
+                    hint: Hint::new(self.context.clone(), "", font_system),
+                    text,
+                    x: 0.,
+                    y: 0.,
+                    action: String::new(),
+                    state: State::Unhovered,
+                    width: 0.,
+                    tx: None,
+                    answer: None,
+                },
+                role: Role::Media {
+                    player: Arc::clone(player),
+                    control,
+                },
+                tx: self.sender.clone(),
+            }));
+        });
+
+        self
+    }
+
+    pub fn finish(self, font_system: &mut FontSystem) -> ButtonManager<Finished> {
+        let mut buttons = ButtonManager {
+            buttons: self.buttons,
+            urgency: self.urgency,
+            sender: self.sender,
+            context: self.context,
+            _state: std::marker::PhantomData,
+        };
+        buttons.assign_hints(font_system);
+
+        buttons
+    }
+}
+
+impl ButtonManager<Finished> {
+    /// Keep hovered whatever was hovered in `previous`, so rebuilding the same buttons
+    /// for a replaced notification doesn't flicker
+    pub fn keep_hover(&mut self, previous: &Self) {
+        if self.buttons.len() != previous.buttons.len() {
+            return;
+        }
+
+        self.buttons
+            .iter_mut()
+            .zip(previous.buttons.iter())
+            .filter(|(button, previous)| {
+                button.button_type() == previous.button_type()
+                    && matches!(previous.state(), State::Hovered)
+            })
+            .for_each(|(button, _)| button.hover());
+    }
+
+    /// Label every button with a hint made of the active layout's hint characters
+    pub fn assign_hints(&mut self, font_system: &mut FontSystem) {
         let hint_chars: Vec<char> = self
             .context
             .config
             .general
-            .hint_characters
+            .hint_characters(self.context.ui_state.layout.read().unwrap().as_deref())
             .chars()
             .collect();
         let n = hint_chars.len() as i32;
@@ -161,18 +352,8 @@ impl ButtonManager<Ready> {
 
             button.set_hint(hint);
         });
-
-        ButtonManager {
-            buttons: self.buttons,
-            urgency: self.urgency,
-            sender: self.sender,
-            context: self.context,
-            _state: std::marker::PhantomData,
-        }
     }
-}
 
-impl ButtonManager<Finished> {
     pub fn click(&self, x: f64, y: f64) -> bool {
         self.buttons
             .iter()
@@ -292,12 +473,16 @@ impl ButtonManager<Finished> {
     }
 
     pub fn set_action_widths(&mut self, width: f32) {
-        self.buttons
-            .iter_mut()
-            .filter_map(|button| button.as_any_mut().downcast_mut::<ActionButton>())
-            .for_each(|action| {
+        self.buttons.iter_mut().for_each(|button| {
+            let button = button.as_any_mut();
+            if let Some(action) = button.downcast_mut::<ActionButton>() {
                 action.width = width;
-            });
+            } else if let Some(role) = button.downcast_mut::<RoleButton>()
+                && !matches!(role.role, Role::Cancel)
+            {
+                role.button.width = width;
+            }
+        });
     }
 }
 
@@ -368,6 +553,7 @@ impl<S> ButtonManager<S> {
                     state: State::Unhovered,
                     width: 0.,
                     tx: self.sender.clone(),
+                    answer: None,
                 }) as Box<dyn Button<Style = ButtonState>>
             })
             .collect();
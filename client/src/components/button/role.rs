@@ -0,0 +1,120 @@
+use super::action::ActionButton;
+use super::{Button, ButtonType, Hint, State};
+use crate::components;
+use crate::components::{Bounds, Component};
+use crate::dbus::mpris::Control;
+use crate::rendering::glyphon;
+use crate::rendering::moxui::{shape_renderer, texture_renderer};
+use crate::styles::ButtonState;
+use config::client::Urgency;
+use std::sync::Arc;
+
+/// What a button drawn like an action does besides being laid out with them
+pub enum Role {
+    /// Invokes the cancel action of a notification with a progress bar, drawn next
+    /// to the bar with a width of its own
+    Cancel,
+    /// Copies a one-time code found in the notification
+    Code(Arc<str>),
+    /// Controls the MPRIS player a media notification is about
+    Media { player: Arc<str>, control: Control },
+}
+
+/// Button drawn like an action, doing whatever its [`Role`] says when clicked
+pub struct RoleButton {
+    pub button: ActionButton,
+    pub role: Role,
+    pub tx: Option<calloop::channel::Sender<crate::Event>>,
+}
+
+impl Component for RoleButton {
+    type Style = ButtonState;
+
+    fn get_context(&self) -> &components::Context {
+        self.button.get_context()
+    }
+
+    fn get_instances(&self, urgency: Urgency) -> Vec<shape_renderer::ShapeInstance> {
+        self.button.get_instances(urgency)
+    }
+
+    fn get_text_areas(&self, urgency: Urgency) -> Vec<glyphon::TextArea<'_>> {
+        self.button.get_text_areas(urgency)
+    }
+
+    fn get_style(&self) -> &Self::Style {
+        self.button.get_style()
+    }
+
+    fn get_bounds(&self) -> Bounds {
+        self.button.get_bounds()
+    }
+
+    fn get_render_bounds(&self) -> Bounds {
+        self.button.get_render_bounds()
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.button.set_position(x, y);
+    }
+
+    fn get_textures(&self) -> Vec<texture_renderer::TextureArea<'_>> {
+        self.button.get_textures()
+    }
+}
+
+impl Button for RoleButton {
+    fn hint(&self) -> &Hint {
+        self.button.hint()
+    }
+
+    fn click(&self) {
+        let Some(tx) = self.tx.as_ref() else {
+            return;
+        };
+
+        let event = match &self.role {
+            Role::Cancel => {
+                self.button.click();
+                return;
+            }
+            Role::Code(code) => crate::Event::CopyCode {
+                id: self.get_id(),
+                code: Arc::clone(code),
+            },
+            Role::Media { player, control } => crate::Event::MediaControl {
+                player: Arc::clone(player),
+                control: *control,
+            },
+        };
+        _ = tx.send(event);
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn button_type(&self) -> ButtonType {
+        match self.role {
+            Role::Cancel => ButtonType::Cancel,
+            Role::Code(_) => ButtonType::Code,
+            Role::Media { .. } => ButtonType::Media,
+        }
+    }
+
+    fn state(&self) -> State {
+        self.button.state()
+    }
+
+    fn hover(&mut self) {
+        self.button.hover();
+    }
+
+    fn unhover(&mut self) {
+        self.button.unhover();
+    }
+
+    fn set_hint(&mut self, hint: Hint) {
+        self.button.set_hint(hint);
+    }
+}
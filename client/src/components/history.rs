@@ -0,0 +1,140 @@
+use crate::components;
+use crate::components::{Bounds, Component, Data};
+use crate::rendering::glyphon::{self, Attrs, Buffer, Family, FontSystem, Shaping};
+use crate::rendering::moxui::{shape_renderer, texture_renderer};
+use crate::styles::TextStyle;
+use config::client::Urgency;
+use config::client::length::DPI;
+use std::collections::VecDeque;
+use std::sync::{Arc, atomic::Ordering};
+
+/// Contents a notification had before it was replaced
+pub struct Revision {
+    pub summary: String,
+    pub body: String,
+}
+
+/// Prior revisions of a notification, shown below its current content when expanded
+pub struct History {
+    context: components::Context,
+    buffer: Buffer,
+    x: f32,
+    y: f32,
+}
+
+impl Component for History {
+    type Style = TextStyle;
+
+    fn get_context(&self) -> &components::Context {
+        &self.context
+    }
+
+    fn get_style(&self) -> &Self::Style {
+        &self.get_notification_style().body
+    }
+
+    fn get_instances(&self, _: Urgency) -> Vec<shape_renderer::ShapeInstance> {
+        Vec::new()
+    }
+
+    fn get_text_areas(&self, urgency: Urgency) -> Vec<glyphon::TextArea<'_>> {
+        let style = self.get_style();
+        let bounds = self.get_render_bounds();
+
+        vec![glyphon::TextArea {
+            buffer: &self.buffer,
+            left: bounds.x,
+            top: bounds.y,
+            scale: self.get_ui_state().scale.load(Ordering::Relaxed),
+            bounds: glyphon::TextBounds {
+                left: bounds.x as i32,
+                top: bounds.y as i32,
+                right: (bounds.x + bounds.width) as i32,
+                bottom: (bounds.y + bounds.height) as i32,
+            },
+            default_color: style.color.into_glyphon(urgency),
+            custom_glyphs: &[],
+        }]
+    }
+
+    fn get_textures(&self) -> Vec<texture_renderer::TextureArea<'_>> {
+        Vec::new()
+    }
+
+    fn get_bounds(&self) -> Bounds {
+        let (width, total_lines) = self
+            .buffer
+            .layout_runs()
+            .fold((0.0, 0.0), |(width, total_lines), run| {
+                (run.line_w.max(width), total_lines + 1.0)
+            });
+
+        Bounds {
+            x: self.x,
+            y: self.y,
+            width,
+            height: total_lines * self.buffer.metrics().line_height,
+        }
+    }
+
+    fn get_render_bounds(&self) -> Bounds {
+        self.get_bounds()
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    fn get_data(&self, urgency: Urgency) -> Vec<Data<'_>> {
+        self.get_text_areas(urgency)
+            .into_iter()
+            .map(Data::TextArea)
+            .collect()
+    }
+}
+
+impl History {
+    pub fn new(
+        context: components::Context,
+        font_system: &mut FontSystem,
+        revisions: &VecDeque<Revision>,
+        width: f32,
+    ) -> Self {
+        let style = &context.styles.urgency_normal.unfocused.body;
+        let font_size = style.size * DPI / 72.0;
+        let family = Arc::clone(&style.family);
+
+        let text = revisions
+            .iter()
+            .map(
+                |revision| match (revision.summary.is_empty(), revision.body.is_empty()) {
+                    (_, true) => revision.summary.clone(),
+                    (true, false) => revision.body.clone(),
+                    (false, false) => format!("{}: {}", revision.summary, revision.body),
+                },
+            )
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut buffer = Buffer::new(
+            font_system,
+            glyphon::Metrics::new(font_size, font_size * 1.2),
+        );
+        buffer.set_size(font_system, Some(width), None);
+        buffer.set_text(
+            font_system,
+            &text,
+            &Attrs::new().family(Family::Name(&family)),
+            Shaping::Advanced,
+            None,
+        );
+
+        Self {
+            context,
+            buffer,
+            x: 0.,
+            y: 0.,
+        }
+    }
+}
@@ -1,10 +1,12 @@
 use crate::components;
 use crate::components::{Bounds, Component};
+use crate::moxnotify::types::ImageEncoding;
+use crate::rendering::glyphon;
+use crate::rendering::moxui::image::Image;
+use crate::rendering::moxui::texture_renderer::{Buffer, TextureArea, TextureBounds};
+use crate::rendering::moxui::{shape_renderer, texture_renderer};
 use crate::styles::StyleState;
 use config::client::Urgency;
-use moxui::image::Image;
-use moxui::texture_renderer::{Buffer, TextureArea, TextureBounds};
-use moxui::{shape_renderer, texture_renderer};
 use resvg::usvg;
 use std::{
     collections::BTreeMap,
@@ -54,28 +56,19 @@ impl Icons {
         context: components::Context,
         image: Option<&crate::moxnotify::types::Image>,
         app_icon: Option<&str>,
+        size: u32,
     ) -> Self {
         let icon = match image.and_then(|img| img.image.as_ref()) {
             Some(crate::moxnotify::types::image::Image::Data(proto_image_data)) => {
-                let image_data = Image::from_raw(
-                    proto_image_data.width,
-                    proto_image_data.height,
-                    proto_image_data.data.clone(),
-                )
-                .unwrap();
-                image_data
-                    .resize_to_fit(
-                        context.config.general.icon_size,
-                        context.config.general.icon_size,
-                    )
-                    .ok()
+                decode_image_data(proto_image_data)
+                    .and_then(|image_data| image_data.resize_to_fit(size, size).ok())
             }
             Some(crate::moxnotify::types::image::Image::FilePath(file_path)) => {
-                get_icon(file_path, context.config.general.icon_size as u16)
+                get_icon(file_path, size as u16)
             }
             Some(crate::moxnotify::types::image::Image::Name(name)) => find_icon(
                 name,
-                context.config.general.icon_size as u16,
+                size as u16,
                 context
                     .config
                     .general
@@ -90,7 +83,7 @@ impl Icons {
         let app_icon = app_icon.as_ref().and_then(|icon| {
             find_icon(
                 icon,
-                context.config.general.icon_size as u16,
+                size as u16,
                 context.config.general.theme.as_deref().as_ref(),
             )
         });
@@ -235,6 +228,26 @@ impl Component for Icons {
     }
 }
 
+/// Collectors may ship image-data hints compressed, decode them back to raw RGBA
+fn decode_image_data(image_data: &crate::moxnotify::types::ImageData) -> Option<Image> {
+    match image_data.encoding() {
+        ImageEncoding::Raw => {
+            Image::from_raw(image_data.width, image_data.height, image_data.data.clone())
+        }
+        ImageEncoding::Png | ImageEncoding::Webp => {
+            let decoded = match image::load_from_memory(&image_data.data) {
+                Ok(decoded) => decoded.into_rgba8(),
+                Err(e) => {
+                    log::warn!("Failed to decode image data: {e}");
+                    return None;
+                }
+            };
+            let (width, height) = decoded.dimensions();
+            Image::from_raw(width, height, decoded.into_raw())
+        }
+    }
+}
+
 fn find_icon<T>(name: T, icon_size: u16, theme: Option<T>) -> Option<Image>
 where
     T: AsRef<str>,
@@ -0,0 +1,192 @@
+use crate::components;
+use crate::components::{Bounds, Component, Data};
+use crate::moxnotify::types::{NewNotification, image};
+use crate::rendering::glyphon::{self, Attrs, Buffer, Family, FontSystem, Shaping};
+use crate::rendering::moxui::{shape_renderer, texture_renderer};
+use crate::styles::TextStyle;
+use config::client::Urgency;
+use config::client::length::DPI;
+use std::fmt::Write;
+use std::sync::atomic::Ordering;
+
+/// Debug overlay listing the internals of a notification
+pub struct Inspector {
+    context: components::Context,
+    buffer: Buffer,
+    x: f32,
+    y: f32,
+}
+
+impl Component for Inspector {
+    type Style = TextStyle;
+
+    fn get_context(&self) -> &components::Context {
+        &self.context
+    }
+
+    fn get_style(&self) -> &Self::Style {
+        &self.get_notification_style().body
+    }
+
+    fn get_instances(&self, urgency: Urgency) -> Vec<shape_renderer::ShapeInstance> {
+        let style = self.get_style();
+        let bounds = self.get_render_bounds();
+
+        vec![shape_renderer::ShapeInstance {
+            rect_pos: [bounds.x, bounds.y],
+            rect_size: [bounds.width, bounds.height],
+            rect_color: style.background.color(urgency),
+            border_radius: style.border.radius.into(),
+            border_size: [1.0; 4],
+            border_color: style.color.color(urgency),
+            scale: self.get_ui_state().scale.load(Ordering::Relaxed),
+            depth: 0.8,
+        }]
+    }
+
+    fn get_text_areas(&self, urgency: Urgency) -> Vec<glyphon::TextArea<'_>> {
+        let style = self.get_style();
+        let bounds = self.get_render_bounds();
+
+        vec![glyphon::TextArea {
+            buffer: &self.buffer,
+            left: bounds.x,
+            top: bounds.y,
+            scale: self.get_ui_state().scale.load(Ordering::Relaxed),
+            bounds: glyphon::TextBounds {
+                left: bounds.x as i32,
+                top: bounds.y as i32,
+                right: (bounds.x + bounds.width) as i32,
+                bottom: (bounds.y + bounds.height) as i32,
+            },
+            default_color: style.color.into_glyphon(urgency),
+            custom_glyphs: &[],
+        }]
+    }
+
+    fn get_textures(&self) -> Vec<texture_renderer::TextureArea<'_>> {
+        Vec::new()
+    }
+
+    fn get_bounds(&self) -> Bounds {
+        let (width, total_lines) = self
+            .buffer
+            .layout_runs()
+            .fold((0.0, 0.0), |(width, total_lines), run| {
+                (run.line_w.max(width), total_lines + 1.0)
+            });
+
+        Bounds {
+            x: self.x,
+            y: self.y,
+            width,
+            height: total_lines * self.buffer.metrics().line_height,
+        }
+    }
+
+    fn get_render_bounds(&self) -> Bounds {
+        self.get_bounds()
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    fn get_data(&self, urgency: Urgency) -> Vec<Data<'_>> {
+        self.get_instances(urgency)
+            .into_iter()
+            .map(Data::Instance)
+            .chain(self.get_text_areas(urgency).into_iter().map(Data::TextArea))
+            .collect()
+    }
+}
+
+impl Inspector {
+    pub fn new(
+        context: components::Context,
+        font_system: &mut FontSystem,
+        data: &NewNotification,
+        layout: &[(&str, Bounds)],
+        width: f32,
+    ) -> Self {
+        let font_size = context.styles.urgency_normal.unfocused.font.size * DPI / 72.0;
+        let mut buffer = Buffer::new(
+            font_system,
+            glyphon::Metrics::new(font_size, font_size * 1.2),
+        );
+        buffer.set_size(font_system, Some(width), None);
+        buffer.set_text(
+            font_system,
+            &Self::describe(data, layout),
+            &Attrs::new().family(Family::Monospace),
+            Shaping::Advanced,
+            None,
+        );
+
+        Self {
+            context,
+            buffer,
+            x: 0.,
+            y: 0.,
+        }
+    }
+
+    fn describe(data: &NewNotification, layout: &[(&str, Bounds)]) -> String {
+        let mut text = String::new();
+
+        _ = writeln!(text, "id: {}", data.id);
+        _ = writeln!(text, "uuid: {}", data.uuid);
+        _ = writeln!(text, "app: {}", data.app_name);
+        if let Some(display_name) = data.display_name.as_ref() {
+            _ = writeln!(text, "name: {display_name}");
+        }
+        if data.client_timestamp != 0 {
+            // How far the collector's clock is ahead, less the time it took to reach the control plane
+            _ = writeln!(
+                text,
+                "collector clock: {:+}ms",
+                data.client_timestamp - data.timestamp
+            );
+        }
+
+        let now = config::now_ms();
+        // Timers are owned by the scheduler and only start once a notification
+        // becomes visible, so measuring from its arrival gives a lower bound
+        let timeout = match data.timeout {
+            0 => "never".to_string(),
+            timeout if timeout < 0 => "server default".to_string(),
+            timeout => {
+                let remaining = (data.timestamp + i64::from(timeout) - now).max(0);
+                format!("{timeout}ms (~{remaining}ms left)")
+            }
+        };
+        _ = writeln!(text, "timeout: {timeout}");
+
+        if let Some(hints) = data.hints.as_ref() {
+            let mut hints = hints.clone();
+            let image_bytes = match hints.image.as_mut().and_then(|image| image.image.as_mut()) {
+                Some(image::Image::Data(image_data)) => std::mem::take(&mut image_data.data).len(),
+                _ => 0,
+            };
+
+            _ = writeln!(text, "urgency: {:?}", hints.urgency());
+            if let Ok(json) = serde_json::to_string(&hints) {
+                _ = writeln!(text, "hints: {json}");
+            }
+            if image_bytes > 0 {
+                _ = writeln!(text, "image data: {image_bytes} bytes");
+            }
+        }
+
+        layout.iter().for_each(|(name, bounds)| {
+            _ = writeln!(
+                text,
+                "{name}: {}x{} @ {},{}",
+                bounds.width, bounds.height, bounds.x, bounds.y
+            );
+        });
+
+        text.trim_end().to_string()
+    }
+}
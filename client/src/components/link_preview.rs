@@ -0,0 +1,174 @@
+use crate::components;
+use crate::components::{Bounds, Component, Data};
+use crate::link_preview::{FAVICON_SIZE, Preview};
+use crate::rendering::glyphon::{self, Attrs, Buffer, FontSystem, Shaping};
+use crate::rendering::moxui::image::Image;
+use crate::rendering::moxui::texture_renderer::{TextureArea, TextureBounds};
+use crate::rendering::moxui::{shape_renderer, texture_renderer};
+use crate::styles::TextStyle;
+use config::client::Urgency;
+use config::client::length::DPI;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+
+/// Space between the favicon and the title
+const FAVICON_MARGIN_RIGHT: f32 = 6.0;
+
+/// Favicon and title of a link in the body
+pub struct LinkPreview {
+    context: components::Context,
+    pub url: Arc<str>,
+    favicon: Option<Image>,
+    buffer: Buffer,
+    x: f32,
+    y: f32,
+}
+
+impl LinkPreview {
+    pub fn new(
+        context: components::Context,
+        font_system: &mut FontSystem,
+        preview: Preview,
+        width: f32,
+    ) -> Self {
+        let favicon = preview.favicon.and_then(|favicon| {
+            let (width, height) = favicon.dimensions();
+            Image::from_raw(width, height, favicon.into_raw())
+        });
+
+        let font_size = context.styles.urgency_normal.unfocused.font.size * DPI / 72.0;
+        let line_height = font_size * 1.2;
+        let mut buffer = Buffer::new(font_system, glyphon::Metrics::new(font_size, line_height));
+        // A single line is enough to recognize the page
+        buffer.set_size(
+            font_system,
+            Some(width - FAVICON_SIZE as f32 - FAVICON_MARGIN_RIGHT),
+            Some(line_height),
+        );
+        buffer.set_text(
+            font_system,
+            &preview.title,
+            &Attrs::new(),
+            Shaping::Advanced,
+            None,
+        );
+        buffer.shape_until_scroll(font_system, true);
+
+        Self {
+            context,
+            url: preview.url,
+            favicon,
+            buffer,
+            x: 0.,
+            y: 0.,
+        }
+    }
+
+    /// Take the styles of `context`, keeping the title that's already shaped
+    pub fn restyle(&mut self, font_system: &mut FontSystem, context: components::Context) {
+        let font_size = context.styles.urgency_normal.unfocused.font.size * DPI / 72.0;
+        let line_height = font_size * 1.2;
+        self.buffer
+            .set_metrics(font_system, glyphon::Metrics::new(font_size, line_height));
+        let (width, _) = self.buffer.size();
+        self.buffer.set_size(font_system, width, Some(line_height));
+        self.buffer.shape_until_scroll(font_system, true);
+        self.context = context;
+    }
+
+    fn text_x(&self) -> f32 {
+        self.x + FAVICON_SIZE as f32 + FAVICON_MARGIN_RIGHT
+    }
+}
+
+impl Component for LinkPreview {
+    type Style = TextStyle;
+
+    fn get_context(&self) -> &components::Context {
+        &self.context
+    }
+
+    fn get_style(&self) -> &Self::Style {
+        &self.get_notification_style().body
+    }
+
+    fn get_instances(&self, _: Urgency) -> Vec<shape_renderer::ShapeInstance> {
+        Vec::new()
+    }
+
+    fn get_text_areas(&self, urgency: Urgency) -> Vec<glyphon::TextArea<'_>> {
+        let style = self.get_style();
+        let bounds = self.get_render_bounds();
+
+        vec![glyphon::TextArea {
+            buffer: &self.buffer,
+            left: self.text_x(),
+            top: bounds.y,
+            scale: self.get_ui_state().scale.load(Ordering::Relaxed),
+            bounds: glyphon::TextBounds {
+                left: self.text_x() as i32,
+                top: bounds.y as i32,
+                right: (bounds.x + bounds.width) as i32,
+                bottom: (bounds.y + bounds.height) as i32,
+            },
+            default_color: style.color.into_glyphon(urgency),
+            custom_glyphs: &[],
+        }]
+    }
+
+    fn get_textures(&self) -> Vec<texture_renderer::TextureArea<'_>> {
+        let Some(favicon) = self.favicon.as_ref() else {
+            return Vec::new();
+        };
+
+        // Centered on the line of text
+        let y = self.y + (self.buffer.metrics().line_height - FAVICON_SIZE as f32).max(0.) / 2.;
+        vec![TextureArea::simple(
+            favicon.data(),
+            self.x,
+            y,
+            favicon.width() as f32,
+            favicon.height() as f32,
+            TextureBounds {
+                left: self.x as u32,
+                top: y as u32,
+                right: (self.x + FAVICON_SIZE as f32) as u32,
+                bottom: (y + FAVICON_SIZE as f32) as u32,
+            },
+            [0.0; 4],
+            [0.0; 4],
+            0.8,
+        )]
+    }
+
+    fn get_bounds(&self) -> Bounds {
+        let width = self
+            .buffer
+            .layout_runs()
+            .fold(0.0_f32, |width, run| run.line_w.max(width));
+
+        Bounds {
+            x: self.x,
+            y: self.y,
+            width: width + FAVICON_SIZE as f32 + FAVICON_MARGIN_RIGHT,
+            height: self.buffer.metrics().line_height.max(FAVICON_SIZE as f32),
+        }
+    }
+
+    fn get_render_bounds(&self) -> Bounds {
+        self.get_bounds()
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    fn get_data(&self, urgency: Urgency) -> Vec<Data<'_>> {
+        self.get_text_areas(urgency)
+            .into_iter()
+            .map(Data::TextArea)
+            .chain(self.get_textures().into_iter().map(Data::Texture))
+            .collect()
+    }
+}
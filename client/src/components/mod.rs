@@ -1,13 +1,18 @@
 pub mod button;
+pub mod history;
 pub mod icons;
+pub mod inspector;
+pub mod link_preview;
 pub mod notification;
 pub mod progress;
 pub mod text;
+pub mod timestamp;
 
 use crate::manager::UiState;
+use crate::rendering::glyphon;
+use crate::rendering::moxui::{shape_renderer, texture_renderer};
 use crate::styles::{StyleState, Styles};
 use config::client::{ClientConfig as Config, Urgency};
-use moxui::{shape_renderer, texture_renderer};
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
 
@@ -1,19 +1,27 @@
 use super::button::{ButtonManager, ButtonType, Finished};
+use super::history::{History, Revision};
 use super::icons::Icons;
+use super::inspector::Inspector;
+use super::link_preview::LinkPreview;
 use super::progress::Progress;
 use super::text::Text;
 use super::text::body::Body;
 use super::text::summary::Summary;
+use super::timestamp::Timestamp;
 use super::{Bounds, UiState};
 use crate::components;
 use crate::components::{Component, Data};
-use crate::moxnotify::types::NewNotification;
-use calloop::RegistrationToken;
+use crate::dbus::mpris;
+use crate::link_preview::{self, Preview};
+use crate::moxnotify::types::{Action, NewNotification, image};
+use crate::rendering::glyphon::{self, FontSystem};
+use crate::rendering::moxui::shape_renderer;
+use crate::rendering::moxui::texture_renderer;
 use crate::styles::{StyleState, Styles};
-use config::client::{ClientConfig as Config, Urgency};
-use glyphon::FontSystem;
-use moxui::shape_renderer;
-use moxui::texture_renderer;
+use calloop::RegistrationToken;
+use config::client::keymaps::Keymaps;
+use config::client::{Calls, ClientConfig as Config, Urgency};
+use std::collections::VecDeque;
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
 use taffy::{TaffyTree, prelude::*};
@@ -29,10 +37,130 @@ const NOTIFICATION_PADDING_BOTTOM: f32 = 10.0;
 const NOTIFICATION_WIDTH: f32 = 450.0;
 const PROGRESS_HEIGHT: f32 = 20.0;
 const PROGRESS_MARGIN_TOP: f32 = 10.0;
-const NOTIFICATION_BORDER_SIZE: f32 = 1.0;
+/// How many prior revisions of a replaced notification are kept
+const HISTORY_LEN: usize = 10;
 
 pub type NotificationId = u32;
 
+/// One-time code in the summary or body, gets a button copying it
+fn code<'a>(config: &Config, data: &'a NewNotification) -> Option<&'a str> {
+    let codes = &config.general.codes;
+    codes.find(&data.summary).or_else(|| codes.find(&data.body))
+}
+
+/// Key of the action drawn next to the progress bar instead of along with the others
+const CANCEL_ACTION: &str = "cancel";
+
+/// Actions of the bottom row, and the cancel action of a notification with a progress bar
+fn split_cancel(data: &NewNotification) -> (Vec<Action>, Option<&Action>) {
+    let cancel = data
+        .hints
+        .as_ref()
+        .and_then(|hints| hints.value)
+        .and_then(|_| {
+            data.actions
+                .iter()
+                .find(|action| action.key == CANCEL_ACTION)
+        });
+    let actions = data
+        .actions
+        .iter()
+        .filter(|action| Some(*action) != cancel)
+        .cloned()
+        .collect();
+
+    (actions, cancel)
+}
+
+/// Bar for the `value` hint, `compact` ones have the percentage drawn on them
+fn progress(
+    context: &components::Context,
+    font_system: &mut FontSystem,
+    value: i32,
+    compact: bool,
+) -> Progress {
+    let progress = Progress::new(context.clone(), value);
+    if compact {
+        progress.with_percentage(font_system)
+    } else {
+        progress
+    }
+}
+
+/// Config of the call template, for notifications the collector tagged as calls
+fn calls<'a>(config: &'a Config, data: &NewNotification) -> Option<&'a Calls> {
+    data.hints
+        .as_ref()
+        .is_some_and(|hints| Calls::matches(&hints.tags))
+        .then_some(&config.general.calls)
+}
+
+/// MPRIS player of the app that sent a media notification, matched by desktop entry or
+/// app name
+fn player(context: &components::Context, data: &NewNotification) -> Option<Arc<str>> {
+    let media = &context.config.general.media;
+    let hints = data.hints.as_ref();
+    let category = hints.and_then(|hints| hints.category.as_deref());
+    if !media.category(category) && !(media.enabled && media.any_player) {
+        return None;
+    }
+
+    let desktop_entry = hints.and_then(|hints| hints.desktop_entry.as_deref());
+    let players = context.ui_state.players.read().unwrap();
+    mpris::find(&players, [desktop_entry, Some(data.app_name.as_str())])
+}
+
+/// Media notifications show their artwork at `media.artwork_size`
+fn icon_size(config: &Config, data: &NewNotification, player: Option<&Arc<str>>) -> u32 {
+    let category = data
+        .hints
+        .as_ref()
+        .and_then(|hints| hints.category.as_deref());
+
+    if player.is_some() || config.general.media.category(category) {
+        config.general.media.artwork_size
+    } else {
+        config.general.icon_size
+    }
+}
+
+fn icons(context: &components::Context, data: &NewNotification, size: u32) -> Option<Icons> {
+    match (
+        data.hints.as_ref().unwrap().image.as_ref(),
+        data.app_icon.as_deref(),
+    ) {
+        (None, None) => None,
+        (image, app_icon) => Some(Icons::new(context.clone(), image, app_icon, size)),
+    }
+}
+
+/// Drop keybinds suggested through the `x-moxnotify-keybinds` hint that aren't a
+/// single character, don't name one of the actions or clash with a keymap
+fn validate_keybinds(keymaps: &Keymaps, data: &mut NewNotification) {
+    let Some(hints) = data.hints.as_mut() else {
+        return;
+    };
+
+    hints.keybinds.retain(|key, action| {
+        let mut chars = key.chars();
+        let valid = match (chars.next(), chars.next()) {
+            (Some(key), None) => {
+                !keymaps.binds(key) && data.actions.iter().any(|a| a.key == *action)
+            }
+            _ => false,
+        };
+
+        if !valid {
+            log::warn!(
+                "Ignoring keybind '{key}' for action '{action}' of notification {}",
+                data.id
+            );
+        }
+
+        valid
+    });
+}
+
 pub struct Notification {
     pub y: f32,
     pub x: f32,
@@ -44,6 +172,11 @@ pub struct Notification {
     pub data: NewNotification,
     pub summary: Option<Summary>,
     pub body: Option<Body>,
+    link_preview: Option<LinkPreview>,
+    timestamp: Option<Timestamp>,
+    revisions: VecDeque<Revision>,
+    history: Option<History>,
+    inspector: Option<Inspector>,
     pub uuid: String,
     context: components::Context,
     tree: TaffyTree,
@@ -98,16 +231,17 @@ impl Component for Notification {
     fn get_instances(&self, urgency: Urgency) -> Vec<shape_renderer::ShapeInstance> {
         let extents = self.get_render_bounds();
         let style = self.get_style();
+        let border = style.border.size;
 
         vec![shape_renderer::ShapeInstance {
             rect_pos: [extents.x, extents.y],
             rect_size: [
-                extents.width - NOTIFICATION_BORDER_SIZE * 2.0,
-                extents.height - NOTIFICATION_BORDER_SIZE * 2.0,
+                extents.width - border.left - border.right,
+                extents.height - border.top - border.bottom,
             ],
             rect_color: style.background.color(urgency),
             border_radius: style.border.radius.into(),
-            border_size: [NOTIFICATION_BORDER_SIZE; 4],
+            border_size: style.border.size.into(),
             border_color: style.border.color.color(urgency),
             scale: self.get_ui_state().scale.load(Ordering::Relaxed),
             depth: 0.9,
@@ -135,8 +269,9 @@ impl Component for Notification {
             .styles
             .find_style(self.context.urgency, focused);
 
-        let x_offset = NOTIFICATION_BORDER_SIZE + NOTIFICATION_PADDING_LEFT;
-        let y_offset = NOTIFICATION_BORDER_SIZE + NOTIFICATION_PADDING_TOP;
+        let border = style.border.size;
+        let x_offset = border.left + NOTIFICATION_PADDING_LEFT;
+        let y_offset = border.top + NOTIFICATION_PADDING_TOP;
 
         // Get action buttons for reuse
         let action_buttons_count = self
@@ -146,7 +281,7 @@ impl Component for Notification {
                 buttons
                     .buttons()
                     .iter()
-                    .filter(|button| button.button_type() == ButtonType::Action)
+                    .filter(|button| button.button_type().is_action())
                     .count()
             })
             .unwrap_or_default();
@@ -158,7 +293,7 @@ impl Component for Notification {
                 buttons
                     .buttons()
                     .iter()
-                    .filter(|button| button.button_type() == ButtonType::Action)
+                    .filter(|button| button.button_type().is_action())
                     .map(|button| button.get_bounds().height)
                     .max_by(|a, b| a.partial_cmp(b).unwrap())
             })
@@ -173,14 +308,14 @@ impl Component for Notification {
                 .unwrap_or_default();
 
             let available_height = extents.height
-                - NOTIFICATION_BORDER_SIZE * 2.0
+                - border.top
+                - border.bottom
                 - NOTIFICATION_PADDING_TOP
                 - NOTIFICATION_PADDING_BOTTOM
                 - progress_height
                 - max_action_button_height;
 
-            let vertical_offset =
-                (available_height - self.context.config.general.icon_size as f32) / 2.0;
+            let vertical_offset = (available_height - icons.get_bounds().height) / 2.0;
             let icon_x = extents.x + x_offset;
             let icon_y = extents.y + y_offset + vertical_offset;
 
@@ -201,14 +336,26 @@ impl Component for Notification {
             );
         }
 
-        // Position progress indicator if present
+        // Position progress indicator if present, with the cancel button to its right
         if let Some(progress) = self.progress.as_mut() {
             let available_width = extents.width
-                - NOTIFICATION_BORDER_SIZE * 2.0
+                - border.left
+                - border.right
                 - NOTIFICATION_PADDING_LEFT
                 - NOTIFICATION_PADDING_RIGHT;
 
-            progress.set_width(available_width);
+            let cancel = self.buttons.as_mut().and_then(|buttons| {
+                buttons
+                    .buttons_mut()
+                    .iter_mut()
+                    .find(|button| button.button_type() == ButtonType::Cancel)
+            });
+            let cancel_width = cancel
+                .as_ref()
+                .map(|cancel| cancel.get_bounds().width)
+                .unwrap_or_default();
+
+            progress.set_width(available_width - cancel_width);
 
             let is_selected = self.context.ui_state.selected.load(Ordering::Relaxed)
                 && self.context.ui_state.selected_id.load(Ordering::Relaxed) == self.data.id;
@@ -217,13 +364,21 @@ impl Component for Notification {
                 .styles
                 .find_style(self.context.urgency, is_selected);
 
-            let progress_x = extents.x + NOTIFICATION_BORDER_SIZE + NOTIFICATION_PADDING_LEFT;
+            let progress_x = extents.x + border.left + NOTIFICATION_PADDING_LEFT;
             let progress_y = extents.y + extents.height
-                - NOTIFICATION_BORDER_SIZE
+                - border.bottom
                 - NOTIFICATION_PADDING_BOTTOM
                 - progress.get_bounds().height;
 
             progress.set_position(progress_x, progress_y);
+
+            if let Some(cancel) = cancel {
+                let bounds = progress.get_bounds();
+                cancel.set_position(
+                    progress_x + available_width - cancel_width,
+                    bounds.y + bounds.height - cancel.get_bounds().height,
+                );
+            }
         }
 
         let dismiss_bottom_y = self
@@ -236,13 +391,13 @@ impl Component for Notification {
                     .find(|button| button.button_type() == ButtonType::Dismiss)
                     .map(|button| {
                         let dismiss_x = extents.x + extents.width
-                            - NOTIFICATION_BORDER_SIZE
+                            - border.right
                             - NOTIFICATION_PADDING_RIGHT
                             - button.get_bounds().width;
 
                         let dismiss_y = extents.y
                             + NOTIFICATION_MARGIN_TOP
-                            + NOTIFICATION_BORDER_SIZE
+                            + border.top
                             + NOTIFICATION_PADDING_TOP;
 
                         button.set_position(dismiss_x, dismiss_y);
@@ -264,9 +419,8 @@ impl Component for Notification {
                     |button| button.get_style(),
                 );
 
-            let side_padding = NOTIFICATION_BORDER_SIZE * 2.0
-                + NOTIFICATION_PADDING_LEFT
-                + NOTIFICATION_PADDING_RIGHT;
+            let side_padding =
+                border.left + border.right + NOTIFICATION_PADDING_LEFT + NOTIFICATION_PADDING_RIGHT;
 
             // Hardcoded button margin for action buttons
             const ACTION_BUTTON_MARGIN_LEFT: f32 = 5.0;
@@ -286,14 +440,13 @@ impl Component for Notification {
                 .map(|p| p.get_bounds().height)
                 .unwrap_or_default();
 
-            let base_x = extents.x + NOTIFICATION_BORDER_SIZE + NOTIFICATION_PADDING_LEFT;
-            let bottom_padding =
-                NOTIFICATION_BORDER_SIZE + NOTIFICATION_PADDING_BOTTOM + progress_height;
+            let base_x = extents.x + border.left + NOTIFICATION_PADDING_LEFT;
+            let bottom_padding = border.bottom + NOTIFICATION_PADDING_BOTTOM + progress_height;
 
             buttons
                 .buttons_mut()
                 .iter_mut()
-                .filter(|b| b.button_type() == ButtonType::Action)
+                .filter(|b| b.button_type().is_action())
                 .enumerate()
                 .for_each(|(i, button)| {
                     let x_position = base_x + (button_width + button_margin) * i as f32;
@@ -345,6 +498,50 @@ impl Component for Notification {
                         .unwrap_or_default(),
             );
         }
+
+        let mut text_height = self
+            .summary
+            .as_ref()
+            .map(|summary| summary.get_bounds().height)
+            .unwrap_or_default()
+            + self
+                .body
+                .as_ref()
+                .map(|body| body.get_bounds().height)
+                .unwrap_or_default();
+
+        // Position the timestamp, link preview, history and inspector below the text
+        if let Some(timestamp) = self.timestamp.as_mut() {
+            let icons_width = self
+                .icons
+                .as_ref()
+                .map(|icons| icons.get_bounds().width)
+                .unwrap_or_default();
+            timestamp.set_position(
+                bounds.x + x_offset + icons_width,
+                bounds.y + y_offset + text_height,
+            );
+            text_height += timestamp.get_bounds().height;
+        }
+
+        if let Some(link_preview) = self.link_preview.as_mut() {
+            let body = self
+                .body
+                .as_ref()
+                .map(Component::get_bounds)
+                .unwrap_or_default();
+            link_preview.set_position(body.x, bounds.y + y_offset + text_height);
+            text_height += link_preview.get_bounds().height;
+        }
+
+        if let Some(history) = self.history.as_mut() {
+            history.set_position(bounds.x + x_offset, bounds.y + y_offset + text_height);
+            text_height += history.get_bounds().height;
+        }
+
+        if let Some(inspector) = self.inspector.as_mut() {
+            inspector.set_position(bounds.x + x_offset, bounds.y + y_offset + text_height);
+        }
     }
 
     fn get_data(&self, urgency: Urgency) -> Vec<Data<'_>> {
@@ -371,6 +568,18 @@ impl Component for Notification {
         if let Some(body) = self.body.as_ref() {
             data.extend(body.get_data(urgency));
         }
+        if let Some(timestamp) = self.timestamp.as_ref() {
+            data.extend(timestamp.get_data(urgency));
+        }
+        if let Some(link_preview) = self.link_preview.as_ref() {
+            data.extend(link_preview.get_data(urgency));
+        }
+        if let Some(history) = self.history.as_ref() {
+            data.extend(history.get_data(urgency));
+        }
+        if let Some(inspector) = self.inspector.as_ref() {
+            data.extend(inspector.get_data(urgency));
+        }
 
         data
     }
@@ -392,6 +601,11 @@ impl Notification {
             crate::moxnotify::types::Urgency::Normal => Urgency::Normal,
             crate::moxnotify::types::Urgency::Critical => Urgency::Critical,
         };
+        let styles = styles.for_category(
+            data.hints
+                .as_ref()
+                .and_then(|hints| hints.category.as_deref()),
+        );
         let context = components::Context {
             id: data.id,
             app_name: data.app_name.clone(),
@@ -403,14 +617,21 @@ impl Notification {
 
         let mut tree = TaffyTree::new();
         let node = {
-            let total_height = NOTIFICATION_BORDER_SIZE * 2.0
+            let border = context
+                .styles
+                .find_style(context.urgency, false)
+                .border
+                .size;
+            let total_height = border.top
+                + border.bottom
                 + NOTIFICATION_PADDING_TOP
                 + NOTIFICATION_PADDING_BOTTOM
                 + NOTIFICATION_MARGIN_TOP
                 + NOTIFICATION_MARGIN_BOTTOM;
 
             let total_width = NOTIFICATION_WIDTH
-                + NOTIFICATION_BORDER_SIZE * 2.0
+                + border.left
+                + border.right
                 + NOTIFICATION_PADDING_LEFT
                 + NOTIFICATION_PADDING_RIGHT
                 + NOTIFICATION_MARGIN_LEFT
@@ -453,6 +674,11 @@ impl Notification {
             data,
             summary: Some(Summary::new(context.clone(), font_system)),
             body: None,
+            link_preview: None,
+            timestamp: None,
+            revisions: VecDeque::new(),
+            history: None,
+            inspector: None,
             context,
             tree,
             node,
@@ -466,10 +692,12 @@ impl Notification {
         config: Arc<Config>,
         styles: Arc<Styles>,
         font_system: &mut FontSystem,
-        data: NewNotification,
+        mut data: NewNotification,
         ui_state: UiState,
         sender: Option<calloop::channel::Sender<crate::Event>>,
     ) -> Notification {
+        validate_keybinds(&config.keymaps, &mut data);
+
         let proto_urgency: crate::moxnotify::types::Urgency =
             data.hints.as_ref().unwrap().urgency.try_into().unwrap();
         let urgency: Urgency = match proto_urgency {
@@ -477,6 +705,13 @@ impl Notification {
             crate::moxnotify::types::Urgency::Normal => Urgency::Normal,
             crate::moxnotify::types::Urgency::Critical => Urgency::Critical,
         };
+        let styles = styles
+            .for_category(
+                data.hints
+                    .as_ref()
+                    .and_then(|hints| hints.category.as_deref()),
+            )
+            .with_hints(data.hints.as_ref().unwrap());
         let context = components::Context {
             id: data.id,
             app_name: data.app_name.clone(),
@@ -486,13 +721,12 @@ impl Notification {
             urgency,
         };
 
-        let icons = match (
-            data.hints.as_ref().unwrap().image.as_ref(),
-            data.app_icon.as_deref(),
-        ) {
-            (None, None) => None,
-            (image, app_icon) => Some(Icons::new(context.clone(), image, app_icon)),
-        };
+        let player = player(&context, &data);
+        let icons = icons(
+            &context,
+            &data,
+            icon_size(&context.config, &data, player.as_ref()),
+        );
 
         let proto_urgency: crate::moxnotify::types::Urgency =
             data.hints.as_ref().unwrap().urgency.try_into().unwrap();
@@ -501,9 +735,16 @@ impl Notification {
             crate::moxnotify::types::Urgency::Normal => Urgency::Normal,
             crate::moxnotify::types::Urgency::Critical => Urgency::Critical,
         };
+        let (actions, cancel) = split_cancel(&data);
         let mut buttons = ButtonManager::new(context.clone(), urgency, sender)
             .add_dismiss(font_system)
-            .add_actions(&data.actions, font_system, data.uuid.clone());
+            .add_actions(&actions, font_system, data.uuid.clone())
+            .add_cancel(cancel, font_system, data.uuid.clone())
+            .add_code(code(&context.config, &data), font_system)
+            .add_media(player.as_ref(), font_system)
+            .add_answers(calls(&context.config, &data));
+        // Progress with a cancel action gets the compact layout, percentage on the bar
+        let compact = cancel.is_some();
 
         let dismiss_button = buttons
             .buttons()
@@ -564,17 +805,37 @@ impl Notification {
             Some(summary)
         };
 
+        let timestamp = context.config.general.timestamps.enabled.then(|| {
+            Timestamp::new(
+                context.clone(),
+                font_system,
+                data.timestamp,
+                NOTIFICATION_WIDTH
+                    - icons
+                        .as_ref()
+                        .map(|icons| icons.get_bounds().width)
+                        .unwrap_or_default(),
+            )
+        });
+
         let mut tree = TaffyTree::new();
 
         let node = {
-            let total_height = NOTIFICATION_BORDER_SIZE * 2.0
+            let border = context
+                .styles
+                .find_style(context.urgency, false)
+                .border
+                .size;
+            let total_height = border.top
+                + border.bottom
                 + NOTIFICATION_PADDING_TOP
                 + NOTIFICATION_PADDING_BOTTOM
                 + NOTIFICATION_MARGIN_TOP
                 + NOTIFICATION_MARGIN_BOTTOM;
 
             let total_width = NOTIFICATION_WIDTH
-                + NOTIFICATION_BORDER_SIZE * 2.0
+                + border.left
+                + border.right
                 + NOTIFICATION_PADDING_LEFT
                 + NOTIFICATION_PADDING_RIGHT
                 + NOTIFICATION_MARGIN_LEFT
@@ -613,7 +874,7 @@ impl Notification {
                 .as_ref()
                 .unwrap()
                 .value
-                .map(|value| Progress::new(context.clone(), value)),
+                .map(|value| progress(&context, font_system, value, compact)),
             context,
             y: 0.,
             x: 0.,
@@ -623,6 +884,11 @@ impl Notification {
             hovered: false,
             registration_token: None,
             body,
+            link_preview: None,
+            timestamp,
+            revisions: VecDeque::new(),
+            history: None,
+            inspector: None,
             tree,
             node,
         };
@@ -633,17 +899,25 @@ impl Notification {
     pub fn replace(
         &mut self,
         font_system: &mut FontSystem,
-        data: NewNotification,
+        mut data: NewNotification,
         sender: Option<calloop::channel::Sender<crate::Event>>,
     ) {
+        validate_keybinds(&self.context.config.keymaps, &mut data);
+        let (actions, cancel) = split_cancel(&data);
+
         match (
             self.progress.as_mut(),
             data.hints.as_ref().unwrap().value,
             self.data.hints.as_ref().unwrap().value == data.hints.as_ref().unwrap().value,
         ) {
-            (Some(progress), Some(value), false) => progress.set_value(value),
+            (Some(progress), Some(value), false) => progress.set_value(font_system, value),
             (None, Some(value), _) => {
-                self.progress = Some(Progress::new(self.context.clone(), value));
+                self.progress = Some(progress(
+                    &self.context,
+                    font_system,
+                    value,
+                    cancel.is_some(),
+                ));
             }
             _ => {}
         }
@@ -656,16 +930,52 @@ impl Notification {
             _ => {}
         }
 
-        if self.data.actions != data.actions || self.data.body != data.body {
+        if self
+            .link_preview
+            .as_ref()
+            .is_some_and(|link_preview| self.link() != Some(&link_preview.url))
+        {
+            self.link_preview = None;
+        }
+
+        let player = player(&self.context, &data);
+        // Players replace their notification with the artwork of every new track
+        if self.data.app_icon != data.app_icon
+            || self
+                .data
+                .hints
+                .as_ref()
+                .and_then(|hints| hints.image.as_ref())
+                != data.hints.as_ref().and_then(|hints| hints.image.as_ref())
+        {
+            self.icons = icons(
+                &self.context,
+                &data,
+                icon_size(&self.context.config, &data, player.as_ref()),
+            );
+        }
+
+        if self.data.actions != data.actions
+            || self.data.body != data.body
+            || self.data.summary != data.summary
+        {
             let mut buttons = ButtonManager::new(self.context.clone(), self.urgency(), sender)
                 .add_dismiss(font_system)
-                .add_actions(&data.actions, font_system, self.uuid.clone());
+                .add_actions(&actions, font_system, self.uuid.clone())
+                .add_cancel(cancel, font_system, self.uuid.clone())
+                .add_code(code(&self.context.config, &data), font_system)
+                .add_media(player.as_ref(), font_system)
+                .add_answers(calls(&self.context.config, &data));
 
             if let Some(body) = &self.body {
                 buttons = buttons.add_anchors(&body.anchors, font_system);
             }
 
-            self.buttons = Some(buttons.finish(font_system));
+            let mut buttons = buttons.finish(font_system);
+            if let Some(previous) = self.buttons.as_ref() {
+                buttons.keep_hover(previous);
+            }
+            self.buttons = Some(buttons);
         }
 
         match (self.summary.as_mut(), self.data.summary == data.summary) {
@@ -676,12 +986,152 @@ impl Notification {
             _ => {}
         }
 
+        // Every step of a progress bar isn't worth keeping around, those only move the
+        // value and at most the body along with it, e.g. "42%"
+        let progress_step = self.data.hints.as_ref().unwrap().value
+            != data.hints.as_ref().unwrap().value
+            && self.data.summary == data.summary;
+        if (self.data.summary != data.summary || self.data.body != data.body) && !progress_step {
+            self.revisions.push_front(Revision {
+                summary: std::mem::take(&mut self.data.summary),
+                body: std::mem::take(&mut self.data.body),
+            });
+            self.revisions.truncate(HISTORY_LEN);
+
+            if self.history.is_some() {
+                self.history = Some(History::new(
+                    self.context.clone(),
+                    font_system,
+                    &self.revisions,
+                    NOTIFICATION_WIDTH,
+                ));
+            }
+        }
+
+        // Shows when the notification was last updated
+        if self.data.timestamp != data.timestamp
+            && let Some(timestamp) = self.timestamp.as_mut()
+        {
+            let hovered = timestamp.hovered;
+            *timestamp = Timestamp::new(
+                self.context.clone(),
+                font_system,
+                data.timestamp,
+                NOTIFICATION_WIDTH
+                    - self
+                        .icons
+                        .as_ref()
+                        .map(|icons| icons.get_bounds().width)
+                        .unwrap_or_default(),
+            );
+            timestamp.hovered = hovered;
+        }
+
         self.data = data;
 
         // Update container layout when content changes
         self.update_container_layout();
     }
 
+    /// Lay the notification out again with `styles`, keeping its revisions, timer,
+    /// hover and the panels that are open
+    pub fn restyle(
+        &mut self,
+        font_system: &mut FontSystem,
+        styles: Arc<Styles>,
+        sender: Option<calloop::channel::Sender<crate::Event>>,
+    ) {
+        let hints = self.data.hints.as_ref().unwrap();
+        self.context.styles = styles
+            .for_category(hints.category.as_deref())
+            .with_hints(hints);
+
+        let player = player(&self.context, &self.data);
+        self.icons = icons(
+            &self.context,
+            &self.data,
+            icon_size(&self.context.config, &self.data, player.as_ref()),
+        );
+        let icons_width = self
+            .icons
+            .as_ref()
+            .map(|icons| icons.get_bounds().width)
+            .unwrap_or_default();
+
+        let (actions, cancel) = split_cancel(&self.data);
+        let mut buttons = ButtonManager::new(self.context.clone(), self.urgency(), sender)
+            .add_dismiss(font_system)
+            .add_actions(&actions, font_system, self.uuid.clone())
+            .add_cancel(cancel, font_system, self.uuid.clone())
+            .add_code(code(&self.context.config, &self.data), font_system)
+            .add_media(player.as_ref(), font_system)
+            .add_answers(calls(&self.context.config, &self.data));
+        let dismiss_button = buttons
+            .buttons()
+            .iter()
+            .find(|button| button.button_type() == ButtonType::Dismiss)
+            .map_or(0.0, |button| button.get_render_bounds().width);
+        let text_width = Some(NOTIFICATION_WIDTH - icons_width - dismiss_button);
+
+        self.body = (!self.data.body.is_empty()).then(|| {
+            let mut body = Body::new(self.context.clone(), font_system);
+            body.set_text(font_system, &self.data.body);
+            body.set_size(font_system, text_width, None);
+            body
+        });
+        if let Some(body) = &self.body {
+            buttons = buttons.add_anchors(&body.anchors, font_system);
+        }
+
+        self.summary = (!self.data.summary.is_empty()).then(|| {
+            let mut summary = Summary::new(self.context.clone(), font_system);
+            summary.set_text(font_system, &self.data.summary);
+            summary.set_size(font_system, text_width, None);
+            summary
+        });
+
+        let mut buttons = buttons.finish(font_system);
+        if let Some(previous) = self.buttons.as_ref() {
+            buttons.keep_hover(previous);
+        }
+        self.buttons = Some(buttons);
+
+        self.progress = hints
+            .value
+            .map(|value| progress(&self.context, font_system, value, cancel.is_some()));
+
+        if let Some(timestamp) = self.timestamp.as_mut() {
+            let hovered = timestamp.hovered;
+            *timestamp = Timestamp::new(
+                self.context.clone(),
+                font_system,
+                self.data.timestamp,
+                NOTIFICATION_WIDTH - icons_width,
+            );
+            timestamp.hovered = hovered;
+        }
+
+        if let Some(link_preview) = self.link_preview.as_mut() {
+            link_preview.restyle(font_system, self.context.clone());
+        }
+
+        if self.history.is_some() {
+            self.history = Some(History::new(
+                self.context.clone(),
+                font_system,
+                &self.revisions,
+                NOTIFICATION_WIDTH,
+            ));
+        }
+
+        self.update_container_layout();
+
+        // The inspector shows the layout, so it's opened again once that's settled
+        if self.inspector.take().is_some() {
+            self.toggle_inspector(font_system);
+        }
+    }
+
     #[must_use]
     pub fn width(&self) -> f32 {
         NOTIFICATION_WIDTH
@@ -711,10 +1161,23 @@ impl Notification {
 
     pub fn hover(&mut self) {
         self.hovered = true;
+        if let Some(timestamp) = self.timestamp.as_mut() {
+            timestamp.hovered = true;
+        }
     }
 
     pub fn unhover(&mut self) {
         self.hovered = false;
+        if let Some(timestamp) = self.timestamp.as_mut() {
+            timestamp.hovered = false;
+        }
+    }
+
+    /// Bring the relative time under the text up to date
+    pub fn update_timestamp(&mut self, font_system: &mut FontSystem) {
+        if let Some(timestamp) = self.timestamp.as_mut() {
+            timestamp.update(font_system);
+        }
     }
 
     #[must_use]
@@ -732,6 +1195,130 @@ impl Notification {
         &self.data
     }
 
+    /// The data without the pixels of an image sent inline, which can take megabytes
+    #[must_use]
+    pub fn data_without_pixels(&self) -> NewNotification {
+        let mut data = self.data.clone();
+        if let Some(image::Image::Data(image_data)) = data
+            .hints
+            .as_mut()
+            .and_then(|hints| hints.image.as_mut())
+            .and_then(|image| image.image.as_mut())
+        {
+            std::mem::take(&mut image_data.data);
+        }
+
+        data
+    }
+
+    /// Milliseconds since the notification arrived
+    #[must_use]
+    pub fn age(&self) -> i64 {
+        (config::now_ms() - self.data.timestamp).max(0)
+    }
+
+    /// Milliseconds until the notification expires, `None` when it never does or
+    /// expires after the server default. Timers are owned by the scheduler and
+    /// only start once a notification becomes visible, so this is a lower bound
+    #[must_use]
+    pub fn remaining(&self) -> Option<i64> {
+        (self.data.timeout > 0).then(|| (i64::from(self.data.timeout) - self.age()).max(0))
+    }
+
+    /// Whether the progress bar is still moving to a new value
+    #[must_use]
+    pub fn animating(&self) -> bool {
+        self.progress.as_ref().is_some_and(Progress::animating)
+    }
+
+    #[must_use]
+    pub fn history_visible(&self) -> bool {
+        self.history.is_some()
+    }
+
+    /// Expand or collapse the contents this notification had before it was replaced
+    pub fn toggle_history(&mut self, font_system: &mut FontSystem) {
+        if self.history.take().is_none() && !self.revisions.is_empty() {
+            self.history = Some(History::new(
+                self.context.clone(),
+                font_system,
+                &self.revisions,
+                NOTIFICATION_WIDTH,
+            ));
+        }
+
+        self.update_container_layout();
+    }
+
+    /// First link of the body that previews are fetched for, marked up ones first
+    fn link(&self) -> Option<&Arc<str>> {
+        let body = self.body.as_ref()?;
+
+        body.anchors
+            .iter()
+            .map(|anchor| &anchor.href)
+            .chain(&body.links)
+            .find(|href| link_preview::fetches(href))
+    }
+
+    /// [`Self::link`], unless its preview is already shown
+    #[must_use]
+    pub fn link_without_preview(&self) -> Option<Arc<str>> {
+        let url = self.link()?;
+
+        match self.link_preview.as_ref() {
+            Some(link_preview) if link_preview.url == *url => None,
+            _ => Some(Arc::clone(url)),
+        }
+    }
+
+    /// Show `preview` under the body, unless the body stopped linking to it meanwhile
+    pub fn set_link_preview(&mut self, font_system: &mut FontSystem, preview: Preview) {
+        if self.link_without_preview().as_deref() != Some(&*preview.url) {
+            return;
+        }
+
+        let width = NOTIFICATION_WIDTH
+            - self
+                .icons
+                .as_ref()
+                .map(|icons| icons.get_bounds().width)
+                .unwrap_or_default();
+        self.link_preview = Some(LinkPreview::new(
+            self.context.clone(),
+            font_system,
+            preview,
+            width,
+        ));
+        self.update_container_layout();
+    }
+
+    /// Show or hide the debug inspector overlay
+    pub fn toggle_inspector(&mut self, font_system: &mut FontSystem) {
+        if self.inspector.take().is_none() {
+            let mut layout = vec![("notification", self.get_render_bounds())];
+            if let Some(summary) = self.summary.as_ref() {
+                layout.push(("summary", summary.get_render_bounds()));
+            }
+            if let Some(body) = self.body.as_ref() {
+                layout.push(("body", body.get_render_bounds()));
+            }
+            if let Some(icons) = self.icons.as_ref() {
+                layout.push(("icons", icons.get_render_bounds()));
+            }
+
+            self.inspector = Some(Inspector::new(
+                self.context.clone(),
+                font_system,
+                &self.data,
+                &layout,
+                NOTIFICATION_WIDTH,
+            ));
+        }
+
+        self.update_container_layout();
+    }
+
     #[must_use]
     pub fn buttons(&self) -> Option<&ButtonManager<Finished>> {
         self.buttons.as_ref()
@@ -745,16 +1332,19 @@ impl Notification {
 impl Notification {
     fn update_container_layout(&mut self) {
         let content_height = self.height();
+        let border = self.get_style().border.size;
 
         let total_height = content_height
-            + NOTIFICATION_BORDER_SIZE * 2.0
+            + border.top
+            + border.bottom
             + NOTIFICATION_PADDING_TOP
             + NOTIFICATION_PADDING_BOTTOM
             + NOTIFICATION_MARGIN_TOP
             + NOTIFICATION_MARGIN_BOTTOM;
 
         let total_width = NOTIFICATION_WIDTH
-            + NOTIFICATION_BORDER_SIZE * 2.0
+            + border.left
+            + border.right
             + NOTIFICATION_PADDING_LEFT
             + NOTIFICATION_PADDING_RIGHT
             + NOTIFICATION_MARGIN_LEFT
@@ -816,7 +1406,9 @@ impl Notification {
                     .buttons()
                     .iter()
                     .filter_map(|button| match button.button_type() {
-                        ButtonType::Action => Some(button.get_bounds()),
+                        ButtonType::Action | ButtonType::Code | ButtonType::Media => {
+                            Some(button.get_bounds())
+                        }
                         _ => None,
                     })
                     .max_by(|a, b| {
@@ -844,6 +1436,26 @@ impl Notification {
                 .as_ref()
                 .map(|summary| summary.get_bounds().height)
                 .unwrap_or_default()
+            + self
+                .timestamp
+                .as_ref()
+                .map(|timestamp| timestamp.get_bounds().height)
+                .unwrap_or_default()
+            + self
+                .link_preview
+                .as_ref()
+                .map(|link_preview| link_preview.get_bounds().height)
+                .unwrap_or_default()
+            + self
+                .history
+                .as_ref()
+                .map(|history| history.get_bounds().height)
+                .unwrap_or_default()
+            + self
+                .inspector
+                .as_ref()
+                .map(|inspector| inspector.get_bounds().height)
+                .unwrap_or_default()
             + progress;
         let icon_height = self
             .icons
@@ -1,17 +1,26 @@
 use crate::components;
 use crate::components::{Bounds, Component};
+use crate::rendering::glyphon::{self, FontSystem};
+use crate::rendering::moxui::{shape_renderer, texture_renderer};
+use crate::rendering::text::Text;
 use crate::styles::{BorderRadius, Progress as ProgressStyle};
 use config::client::Urgency;
-use moxui::{shape_renderer, texture_renderer};
 use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
 
 const PROGRESS_HEIGHT: f32 = 20.0;
 const PROGRESS_MARGIN_TOP: f32 = 10.0;
 const PROGRESS_BORDER_SIZE: f32 = 1.0;
+/// How long the bar takes to move to a new value
+const PROGRESS_ANIMATION: Duration = Duration::from_millis(250);
 
 pub struct Progress {
     context: components::Context,
     value: i32,
+    /// Where the bar was drawn when the value last changed, and when that was
+    animation: Option<(f32, Instant)>,
+    /// Value drawn centered on the bar
+    percentage: Option<Text>,
     x: f32,
     y: f32,
     width: f32,
@@ -63,14 +72,41 @@ impl Component for Progress {
         }
     }
 
-    fn get_text_areas(&self, _: Urgency) -> Vec<glyphon::TextArea<'_>> {
-        vec![]
+    fn get_text_areas(&self, urgency: Urgency) -> Vec<glyphon::TextArea<'_>> {
+        let Some(percentage) = self.percentage.as_ref() else {
+            return Vec::new();
+        };
+
+        let extents = self.get_render_bounds();
+        let text_extents = percentage.get_bounds();
+        let left = extents.x + (extents.width - text_extents.width) / 2.;
+        let top = extents.y + (extents.height - text_extents.height) / 2.;
+
+        vec![glyphon::TextArea {
+            buffer: &percentage.buffer,
+            left,
+            top,
+            scale: self.get_ui_state().scale.load(Ordering::Relaxed),
+            bounds: glyphon::TextBounds {
+                left: left as i32,
+                top: top as i32,
+                right: (left + text_extents.width) as i32,
+                bottom: (top + text_extents.height) as i32,
+            },
+            custom_glyphs: &[],
+            default_color: self
+                .get_notification_style()
+                .font
+                .color
+                .into_glyphon(urgency),
+        }]
     }
 
     fn get_instances(&self, urgency: Urgency) -> Vec<shape_renderer::ShapeInstance> {
         let extents = self.get_render_bounds();
 
-        let progress_ratio = (self.value as f32 / 100.0).min(1.0);
+        let value = self.shown();
+        let progress_ratio = (value / 100.0).min(1.0);
 
         let mut instances = Vec::new();
         let complete_width = (extents.width * progress_ratio).max(0.);
@@ -78,13 +114,18 @@ impl Component for Progress {
         let style = self.get_style();
 
         if complete_width > 0.0 {
-            let border_size = if self.value < 100 {
-                [PROGRESS_BORDER_SIZE, 0.0, PROGRESS_BORDER_SIZE, PROGRESS_BORDER_SIZE]
+            let border_size = if value < 100. {
+                [
+                    PROGRESS_BORDER_SIZE,
+                    0.0,
+                    PROGRESS_BORDER_SIZE,
+                    PROGRESS_BORDER_SIZE,
+                ]
             } else {
                 [PROGRESS_BORDER_SIZE; 4]
             };
 
-            let border_radius = if self.value < 100 {
+            let border_radius = if value < 100. {
                 BorderRadius {
                     top_right: 0.0,
                     bottom_right: 0.0,
@@ -106,17 +147,22 @@ impl Component for Progress {
             });
         }
 
-        if self.value < 100 {
+        if value < 100. {
             let incomplete_width = extents.width - complete_width;
 
             if incomplete_width > 0.0 {
-                let border_size = if self.value > 0 {
-                    [0.0, PROGRESS_BORDER_SIZE, PROGRESS_BORDER_SIZE, PROGRESS_BORDER_SIZE]
+                let border_size = if value > 0. {
+                    [
+                        0.0,
+                        PROGRESS_BORDER_SIZE,
+                        PROGRESS_BORDER_SIZE,
+                        PROGRESS_BORDER_SIZE,
+                    ]
                 } else {
                     [PROGRESS_BORDER_SIZE; 4]
                 };
 
-                let border_radius = if self.value > 0 {
+                let border_radius = if value > 0. {
                     BorderRadius {
                         top_left: 0.0,
                         bottom_left: 0.0,
@@ -153,6 +199,8 @@ impl Progress {
         Self {
             context,
             value,
+            animation: None,
+            percentage: None,
             x: 0.,
             y: 0.,
             width: 0.,
@@ -163,7 +211,48 @@ impl Progress {
         self.width = width;
     }
 
-    pub fn set_value(&mut self, value: i32) {
+    /// Draw the value as a percentage on the bar
+    #[must_use]
+    pub fn with_percentage(mut self, font_system: &mut FontSystem) -> Self {
+        self.percentage = Some(self.percentage_text(font_system));
+        self
+    }
+
+    fn percentage_text(&self, font_system: &mut FontSystem) -> Text {
+        Text::new(
+            &self.get_notification_style().font,
+            font_system,
+            format!("{}%", self.value.clamp(0, 100)),
+        )
+    }
+
+    /// Value the bar is drawn at, on its way to the current one after a change
+    fn shown(&self) -> f32 {
+        let Some((from, changed_at)) = self.animation else {
+            return self.value as f32;
+        };
+
+        let t = (changed_at.elapsed().as_secs_f32() / PROGRESS_ANIMATION.as_secs_f32()).min(1.);
+        // Ease out, so the bar slows down as it reaches the value
+        let eased = 1. - (1. - t).powi(3);
+
+        from + (self.value as f32 - from) * eased
+    }
+
+    /// Whether the bar is still moving to its value
+    #[must_use]
+    pub fn animating(&self) -> bool {
+        self.animation
+            .is_some_and(|(_, changed_at)| changed_at.elapsed() < PROGRESS_ANIMATION)
+    }
+
+    pub fn set_value(&mut self, font_system: &mut FontSystem, value: i32) {
+        let reduced_motion = self.get_config().general.reduced_motion
+            || self.get_ui_state().reduced_motion.load(Ordering::Relaxed);
+        self.animation = (!reduced_motion).then(|| (self.shown(), Instant::now()));
         self.value = value;
+        if self.percentage.is_some() {
+            self.percentage = Some(self.percentage_text(font_system));
+        }
     }
 }
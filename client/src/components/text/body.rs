@@ -1,11 +1,15 @@
-use super::Text;
 use super::markup::{Parser, Tag};
+use super::{Text, TextSize};
 use crate::components;
 use crate::components::{Bounds, Component, Data};
+use crate::link_preview;
+use crate::rendering::glyphon::{
+    self, Attrs, Buffer, Color, Family, FontSystem, Shaping, Stretch, Style, Weight,
+};
+use crate::rendering::moxui::{self, shape_renderer};
 use crate::styles::TextStyle;
 use config::client::Urgency;
-use glyphon::{Attrs, Buffer, Color, Family, FontSystem, Shaping, Stretch, Style, Weight};
-use moxui::shape_renderer;
+use config::client::length::DPI;
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
 
@@ -28,7 +32,10 @@ impl Anchor {
 pub struct Body {
     context: components::Context,
     pub anchors: Vec<Arc<Anchor>>,
+    /// Links written out in the text instead of being marked up
+    pub links: Vec<Arc<str>>,
     pub buffer: Buffer,
+    size: TextSize,
     x: f32,
     y: f32,
 }
@@ -125,6 +132,7 @@ fn parse_color(value: &str) -> Option<glyphon::Color> {
 impl Text for Body {
     fn set_size(&mut self, font_system: &mut FontSystem, width: Option<f32>, height: Option<f32>) {
         self.buffer.set_size(font_system, width, height);
+        self.size.invalidate();
     }
 
     fn set_text<T>(&mut self, font_system: &mut FontSystem, text: T)
@@ -141,6 +149,19 @@ impl Text for Body {
 
         let mut parser = Parser::new(text.as_ref().to_string());
         let body = parser.parse();
+        self.links = body
+            .iter()
+            .filter_map(|tag| match tag {
+                Tag::Bold(text)
+                | Tag::Italic(text)
+                | Tag::Underline(text)
+                | Tag::Text(text)
+                | Tag::Span { text, .. } => Some(text),
+                Tag::Image { .. } | Tag::Anchor { .. } => None,
+            })
+            .flat_map(|text| link_preview::plain_links(text))
+            .map(Arc::from)
+            .collect();
         let spans = body
             .iter()
             .map(|tag| match tag {
@@ -175,8 +196,7 @@ impl Text for Body {
 
                             "font_size" | "size" => {
                                 if let Ok(value) = value.parse::<f32>() {
-                                    let dpi = 96.0;
-                                    let font_size = value * dpi / 72.0;
+                                    let font_size = value * DPI / 72.0;
                                     attrs = attrs
                                         .clone()
                                         .metrics(glyphon::Metrics::new(font_size, font_size * 1.2));
@@ -314,6 +334,7 @@ impl Text for Body {
 
         self.buffer
             .set_rich_text(font_system, spans, &attrs, Shaping::Advanced, None);
+        self.size.invalidate();
 
         for anchor in anchors.iter_mut() {
             if let Some(line) = self.buffer.layout_runs().nth(anchor.line) {
@@ -392,18 +413,13 @@ impl Component for Body {
     }
 
     fn get_bounds(&self) -> Bounds {
-        let (width, total_lines) = self
-            .buffer
-            .layout_runs()
-            .fold((0.0, 0.0), |(width, total_lines), run| {
-                (run.line_w.max(width), total_lines + 1.0)
-            });
+        let (width, height) = self.size.get(&self.buffer);
 
         Bounds {
             x: self.x,
             y: self.y,
             width,
-            height: total_lines * self.buffer.metrics().line_height,
+            height,
         }
     }
 
@@ -433,9 +449,7 @@ impl Component for Body {
 
 impl Body {
     pub fn new(context: components::Context, font_system: &mut FontSystem) -> Self {
-        let dpi = 96.0;
-        let font_size =
-            context.styles.urgency_normal.unfocused.font.size as f32 * dpi / 72.0;
+        let font_size = context.styles.urgency_normal.unfocused.font.size * DPI / 72.0;
         let mut buffer = Buffer::new(
             font_system,
             glyphon::Metrics::new(font_size, font_size * 1.2),
@@ -445,9 +459,11 @@ impl Body {
         Self {
             context,
             buffer,
+            size: TextSize::default(),
             x: 0.,
             y: 0.,
             anchors: Vec::new(),
+            links: Vec::new(),
         }
     }
 }
@@ -3,7 +3,8 @@ pub mod markup;
 pub mod summary;
 
 use super::Component;
-use glyphon::FontSystem;
+use crate::rendering::glyphon::{Buffer, FontSystem};
+use std::cell::Cell;
 
 pub trait Text: Component {
     fn set_size(&mut self, font_system: &mut FontSystem, width: Option<f32>, height: Option<f32>);
@@ -12,3 +13,30 @@ pub trait Text: Component {
     where
         T: AsRef<str>;
 }
+
+/// Width of the widest line and total height of a shaped buffer, measured once
+/// and kept until the buffer's text or size changes
+#[derive(Default)]
+pub struct TextSize(Cell<Option<(f32, f32)>>);
+
+impl TextSize {
+    pub fn get(&self, buffer: &Buffer) -> (f32, f32) {
+        if let Some(size) = self.0.get() {
+            return size;
+        }
+
+        let (width, total_lines) = buffer
+            .layout_runs()
+            .fold((0.0, 0.0), |(width, total_lines), run| {
+                (run.line_w.max(width), total_lines + 1.0)
+            });
+        let size = (width, total_lines * buffer.metrics().line_height);
+
+        self.0.set(Some(size));
+        size
+    }
+
+    pub fn invalidate(&self) {
+        self.0.set(None);
+    }
+}
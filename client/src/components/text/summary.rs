@@ -1,16 +1,19 @@
-use super::Text;
+use super::{Text, TextSize};
 use crate::components;
 use crate::components::{Bounds, Component, Data};
+use crate::rendering::glyphon::{self, Attrs, Buffer, FontSystem, Weight};
+use crate::rendering::moxui::{shape_renderer, texture_renderer};
+use crate::rendering::text::{BUFFER_CACHE, BufferKey};
 use crate::styles::TextStyle;
 use config::client::Urgency;
-use glyphon::{Attrs, Buffer, FontSystem, Weight};
-use moxui::{shape_renderer, texture_renderer};
+use config::client::length::DPI;
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
 
 pub struct Summary {
     context: components::Context,
     pub buffer: Buffer,
+    size: TextSize,
     x: f32,
     y: f32,
 }
@@ -18,6 +21,7 @@ pub struct Summary {
 impl Text for Summary {
     fn set_size(&mut self, font_system: &mut FontSystem, width: Option<f32>, height: Option<f32>) {
         self.buffer.set_size(font_system, width, height);
+        self.size.invalidate();
     }
 
     fn set_text<T>(&mut self, font_system: &mut FontSystem, text: T)
@@ -26,19 +30,33 @@ impl Text for Summary {
     {
         let style = &self.get_style();
         let family = Arc::clone(&style.family);
-
-        let attrs = Attrs::new()
-            .metadata(0.7_f32.to_bits() as usize)
-            .family(glyphon::Family::Name(&family))
-            .weight(Weight::BOLD);
-
-        self.buffer.set_text(
-            font_system,
+        let key = BufferKey::new(
+            &family,
+            self.buffer.metrics().font_size,
+            Weight::BOLD,
+            0.7_f32.to_bits() as usize,
             text.as_ref(),
-            &attrs,
-            glyphon::Shaping::Advanced,
-            None,
         );
+
+        let (width, height) = self.buffer.size();
+        self.buffer = BUFFER_CACHE.get_or_shape(key, || {
+            let attrs = Attrs::new()
+                .metadata(0.7_f32.to_bits() as usize)
+                .family(glyphon::Family::Name(&family))
+                .weight(Weight::BOLD);
+
+            let mut buffer = self.buffer.clone();
+            buffer.set_text(
+                font_system,
+                text.as_ref(),
+                &attrs,
+                glyphon::Shaping::Advanced,
+                None,
+            );
+            buffer
+        });
+        self.buffer.set_size(font_system, width, height);
+        self.size.invalidate();
     }
 }
 
@@ -99,14 +117,9 @@ impl Component for Summary {
     }
 
     fn get_bounds(&self) -> Bounds {
-        let (width, total_lines) = self
-            .buffer
-            .layout_runs()
-            .fold((0.0, 0.0), |(width, total_lines), run| {
-                (run.line_w.max(width), total_lines + 1.0)
-            });
-
-        if width == 0. || total_lines == 0. {
+        let (width, height) = self.size.get(&self.buffer);
+
+        if width == 0. || height == 0. {
             return Bounds {
                 x: 0.,
                 y: 0.,
@@ -119,7 +132,7 @@ impl Component for Summary {
             x: self.x,
             y: self.y,
             width,
-            height: total_lines * self.buffer.metrics().line_height,
+            height,
         }
     }
 
@@ -149,9 +162,7 @@ impl Component for Summary {
 
 impl Summary {
     pub fn new(context: components::Context, font_system: &mut FontSystem) -> Self {
-        let dpi = 96.0;
-        let font_size =
-            context.styles.urgency_normal.unfocused.font.size as f32 * dpi / 72.0;
+        let font_size = context.styles.urgency_normal.unfocused.font.size * DPI / 72.0;
         let mut buffer = Buffer::new(
             font_system,
             glyphon::Metrics::new(font_size, font_size * 1.2),
@@ -161,6 +172,7 @@ impl Summary {
 
         Self {
             buffer,
+            size: TextSize::default(),
             x: 0.,
             y: 0.,
             context,
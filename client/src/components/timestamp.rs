@@ -0,0 +1,184 @@
+use crate::components;
+use crate::components::{Bounds, Component, Data};
+use crate::rendering::glyphon::{self, Attrs, Buffer, FontSystem, Shaping};
+use crate::rendering::moxui::{shape_renderer, texture_renderer};
+use crate::styles::TextStyle;
+use config::client::length::DPI;
+use config::client::{Timestamps, Urgency};
+use std::fmt::Write;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+/// `timestamp` in the local time zone, formatted with `format` in `locale`
+fn absolute(timestamp: i64, format: &str, locale: Option<&str>) -> String {
+    let Some(time) = chrono::DateTime::from_timestamp_millis(timestamp) else {
+        return String::new();
+    };
+    let time = time.with_timezone(&chrono::Local);
+    let locale = locale
+        .and_then(|locale| chrono::Locale::try_from(locale).ok())
+        .unwrap_or(chrono::Locale::POSIX);
+
+    // Invalid formats fail to write instead of panicking like `to_string` would
+    let mut text = String::new();
+    if write!(text, "{}", time.format_localized(format, locale)).is_err() {
+        log::warn!("Invalid timestamp format '{format}'");
+        text = time.format("%x %X").to_string();
+    }
+
+    text
+}
+
+/// When the notification arrived, relative to now unless hovered
+pub struct Timestamp {
+    context: components::Context,
+    timestamp: i64,
+    relative: Buffer,
+    absolute: Buffer,
+    pub hovered: bool,
+    x: f32,
+    y: f32,
+}
+
+impl Timestamp {
+    pub fn new(
+        context: components::Context,
+        font_system: &mut FontSystem,
+        timestamp: i64,
+        width: f32,
+    ) -> Self {
+        let font_size = context.styles.urgency_normal.unfocused.font.size * DPI / 72.0 * 0.85;
+        let metrics = glyphon::Metrics::new(font_size, font_size * 1.2);
+        let mut buffer = || {
+            let mut buffer = Buffer::new(font_system, metrics);
+            buffer.set_size(font_system, Some(width), Some(metrics.line_height));
+            buffer
+        };
+        let relative = buffer();
+        let mut absolute_buffer = buffer();
+
+        let text = absolute(
+            timestamp,
+            &context.config.general.timestamps.format,
+            context.config.locale.as_deref(),
+        );
+        set_text(font_system, &mut absolute_buffer, &text);
+
+        let mut timestamp = Self {
+            context,
+            timestamp,
+            relative,
+            absolute: absolute_buffer,
+            hovered: false,
+            x: 0.,
+            y: 0.,
+        };
+        timestamp.update(font_system);
+        timestamp
+    }
+
+    /// Bring the relative time up to date
+    pub fn update(&mut self, font_system: &mut FontSystem) {
+        let elapsed = Duration::from_millis((config::now_ms() - self.timestamp).max(0) as u64);
+        set_text(
+            font_system,
+            &mut self.relative,
+            &Timestamps::relative(elapsed),
+        );
+    }
+
+    fn buffer(&self) -> &Buffer {
+        if self.hovered {
+            &self.absolute
+        } else {
+            &self.relative
+        }
+    }
+}
+
+fn set_text(font_system: &mut FontSystem, buffer: &mut Buffer, text: &str) {
+    buffer.set_text(font_system, text, &Attrs::new(), Shaping::Advanced, None);
+    buffer.shape_until_scroll(font_system, true);
+}
+
+impl Component for Timestamp {
+    type Style = TextStyle;
+
+    fn get_context(&self) -> &components::Context {
+        &self.context
+    }
+
+    fn get_style(&self) -> &Self::Style {
+        &self.get_notification_style().body
+    }
+
+    fn get_instances(&self, _: Urgency) -> Vec<shape_renderer::ShapeInstance> {
+        Vec::new()
+    }
+
+    fn get_text_areas(&self, urgency: Urgency) -> Vec<glyphon::TextArea<'_>> {
+        let style = self.get_style();
+        let bounds = self.get_render_bounds();
+
+        vec![glyphon::TextArea {
+            buffer: self.buffer(),
+            left: bounds.x,
+            top: bounds.y,
+            scale: self.get_ui_state().scale.load(Ordering::Relaxed),
+            bounds: glyphon::TextBounds {
+                left: bounds.x as i32,
+                top: bounds.y as i32,
+                right: (bounds.x + bounds.width) as i32,
+                bottom: (bounds.y + bounds.height) as i32,
+            },
+            default_color: style.color.into_glyphon(urgency),
+            custom_glyphs: &[],
+        }]
+    }
+
+    fn get_textures(&self) -> Vec<texture_renderer::TextureArea<'_>> {
+        Vec::new()
+    }
+
+    fn get_bounds(&self) -> Bounds {
+        let width = self
+            .buffer()
+            .layout_runs()
+            .fold(0.0_f32, |width, run| run.line_w.max(width));
+
+        Bounds {
+            x: self.x,
+            y: self.y,
+            width,
+            height: self.relative.metrics().line_height,
+        }
+    }
+
+    fn get_render_bounds(&self) -> Bounds {
+        self.get_bounds()
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    fn get_data(&self, urgency: Urgency) -> Vec<Data<'_>> {
+        self.get_text_areas(urgency)
+            .into_iter()
+            .map(Data::TextArea)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_format_falls_back() {
+        assert!(!absolute(1_700_000_000_000, "%Q", None).is_empty());
+        // Mid November, the same year in every time zone
+        assert_eq!(absolute(1_700_000_000_000, "%Y", Some("de_DE")), "2023");
+    }
+}
@@ -1,8 +1,12 @@
 use crate::styles::{
-    BorderRadius, ButtonState, Color, Hint, NotificationCounter, Progress, StyleState, Styles,
-    TextStyle,
+    BorderRadius, ButtonState, Color, Hint, Insets, NotificationCounter, Progress, StyleState,
+    Styles, TextStyle,
 };
+use config::client::length::Length;
 use simplecss::{Declaration, StyleSheet};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
 
 /// Parse a color value from CSS (hex, rgb, rgba formats)
 fn parse_color_value(value: &str) -> Option<[u8; 4]> {
@@ -86,12 +90,20 @@ fn parse_hex_color(hex: &str) -> Option<[u8; 4]> {
     }
 }
 
-fn parse_border_radius(value: &str) -> Option<f32> {
-    let value = value.trim();
-    if let Some(px) = value.strip_suffix("px") {
-        return px.trim().parse().ok();
-    }
-    value.parse().ok()
+/// Parse a length into pixels, `em` and `%` are relative to `font_size` in points
+fn parse_length(value: &str, font_size: f32) -> Option<f32> {
+    Length::from_str(value)
+        .ok()
+        .map(|length| length.to_px(font_size))
+}
+
+/// The font size in points set by `declarations`, `em` and `%` are relative to `font_size`
+fn parse_font_size(declarations: &[Declaration<'_>], font_size: f32) -> f32 {
+    declarations
+        .iter()
+        .filter(|decl| decl.name == "font-size")
+        .filter_map(|decl| Length::from_str(decl.value).ok())
+        .fold(font_size, |font_size, length| length.to_pt(font_size))
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -117,9 +129,12 @@ enum Element {
     Button,
     ButtonAction,
     ButtonDismiss,
+    ButtonAccept,
+    ButtonDecline,
     Progress,
     Hint,
     Counter,
+    Palette,
     Icon,
 }
 
@@ -142,6 +157,10 @@ fn parse_selector(selector_str: &str) -> Option<SelectorMatch> {
         Element::ButtonAction
     } else if selector_str.contains(".button.dismiss") {
         Element::ButtonDismiss
+    } else if selector_str.contains(".button.accept") {
+        Element::ButtonAccept
+    } else if selector_str.contains(".button.decline") {
+        Element::ButtonDecline
     } else if selector_str.contains(".button") {
         Element::Button
     } else if selector_str.contains(".progress") {
@@ -150,6 +169,8 @@ fn parse_selector(selector_str: &str) -> Option<SelectorMatch> {
         Element::Hint
     } else if selector_str.contains(".counter") {
         Element::Counter
+    } else if selector_str.contains(".palette") {
+        Element::Palette
     } else if selector_str.contains(".icon") {
         Element::Icon
     } else {
@@ -181,6 +202,15 @@ fn parse_selector(selector_str: &str) -> Option<SelectorMatch> {
     })
 }
 
+/// Value of a `[category="..."]` attribute selector, quotes are optional
+fn parse_category(selector_str: &str) -> Option<&str> {
+    let start = selector_str.find("[category")? + "[category".len();
+    let rest = &selector_str[start..];
+    let value = rest[..rest.find(']')?].trim().strip_prefix('=')?;
+
+    Some(value.trim().trim_matches(|c| c == '"' || c == '\''))
+}
+
 fn apply_color_to_urgency(color: &mut Color, value: [u8; 4], urgency: Urgency) {
     match urgency {
         Urgency::All => {
@@ -199,6 +229,8 @@ fn apply_declarations_to_style_state(
     declarations: &[Declaration<'_>],
     urgency: Urgency,
 ) {
+    style.font.size = parse_font_size(declarations, style.font.size);
+
     for decl in declarations {
         match decl.name {
             "background" | "background-color" => {
@@ -212,7 +244,7 @@ fn apply_declarations_to_style_state(
                 }
             }
             "border-radius" => {
-                if let Some(radius) = parse_border_radius(decl.value) {
+                if let Some(radius) = parse_length(decl.value, style.font.size) {
                     style.border.radius = BorderRadius {
                         top_left: radius,
                         top_right: radius,
@@ -221,6 +253,11 @@ fn apply_declarations_to_style_state(
                     };
                 }
             }
+            "border-width" => {
+                if let Some(width) = parse_length(decl.value, style.font.size) {
+                    style.border.size = Insets::size(width);
+                }
+            }
             "color" => {
                 if let Some(color) = parse_color_value(decl.value) {
                     apply_color_to_urgency(&mut style.font.color, color, urgency);
@@ -236,6 +273,8 @@ fn apply_declarations_to_text_style(
     declarations: &[Declaration<'_>],
     urgency: Urgency,
 ) {
+    style.size = parse_font_size(declarations, style.size);
+
     for decl in declarations {
         match decl.name {
             "background" | "background-color" => {
@@ -249,7 +288,7 @@ fn apply_declarations_to_text_style(
                 }
             }
             "border-radius" => {
-                if let Some(radius) = parse_border_radius(decl.value) {
+                if let Some(radius) = parse_length(decl.value, style.size) {
                     style.border.radius = BorderRadius {
                         top_left: radius,
                         top_right: radius,
@@ -273,6 +312,8 @@ fn apply_declarations_to_button_state(
     declarations: &[Declaration<'_>],
     urgency: Urgency,
 ) {
+    style.font.size = parse_font_size(declarations, style.font.size);
+
     for decl in declarations {
         match decl.name {
             "background" | "background-color" => {
@@ -286,7 +327,7 @@ fn apply_declarations_to_button_state(
                 }
             }
             "border-radius" => {
-                if let Some(radius) = parse_border_radius(decl.value) {
+                if let Some(radius) = parse_length(decl.value, style.font.size) {
                     style.border.radius = BorderRadius {
                         top_left: radius,
                         top_right: radius,
@@ -309,6 +350,7 @@ fn apply_declarations_to_progress(
     style: &mut Progress,
     declarations: &[Declaration<'_>],
     urgency: Urgency,
+    font_size: f32,
 ) {
     for decl in declarations {
         match decl.name {
@@ -328,7 +370,7 @@ fn apply_declarations_to_progress(
                 }
             }
             "border-radius" => {
-                if let Some(radius) = parse_border_radius(decl.value) {
+                if let Some(radius) = parse_length(decl.value, font_size) {
                     style.border.radius = BorderRadius {
                         top_left: radius,
                         top_right: radius,
@@ -347,6 +389,8 @@ fn apply_declarations_to_hint(
     declarations: &[Declaration<'_>],
     urgency: Urgency,
 ) {
+    style.font.size = parse_font_size(declarations, style.font.size);
+
     for decl in declarations {
         match decl.name {
             "background" | "background-color" => {
@@ -360,7 +404,7 @@ fn apply_declarations_to_hint(
                 }
             }
             "border-radius" => {
-                if let Some(radius) = parse_border_radius(decl.value) {
+                if let Some(radius) = parse_length(decl.value, style.font.size) {
                     style.border.radius = BorderRadius {
                         top_left: radius,
                         top_right: radius,
@@ -384,6 +428,8 @@ fn apply_declarations_to_counter(
     declarations: &[Declaration<'_>],
     urgency: Urgency,
 ) {
+    style.font.size = parse_font_size(declarations, style.font.size);
+
     for decl in declarations {
         match decl.name {
             "background" | "background-color" => {
@@ -397,7 +443,7 @@ fn apply_declarations_to_counter(
                 }
             }
             "border-radius" => {
-                if let Some(radius) = parse_border_radius(decl.value) {
+                if let Some(radius) = parse_length(decl.value, style.font.size) {
                     style.border.radius = BorderRadius {
                         top_left: radius,
                         top_right: radius,
@@ -406,6 +452,11 @@ fn apply_declarations_to_counter(
                     };
                 }
             }
+            "border-width" => {
+                if let Some(width) = parse_length(decl.value, style.font.size) {
+                    style.border.size = Insets::size(width);
+                }
+            }
             "color" => {
                 if let Some(color) = parse_color_value(decl.value) {
                     apply_color_to_urgency(&mut style.font.color, color, urgency);
@@ -518,11 +569,24 @@ fn apply_to_urgency_styles(
                         );
                     }
                 },
+                Element::ButtonAccept | Element::ButtonDecline => {
+                    let button = if selector.element == Element::ButtonAccept {
+                        &mut style_state.buttons.accept
+                    } else {
+                        &mut style_state.buttons.decline
+                    };
+                    let button_state = match state {
+                        State::Focused => &mut button.hover,
+                        _ => &mut button.default,
+                    };
+                    apply_declarations_to_button_state(button_state, declarations, *urgency);
+                }
                 Element::Progress => {
                     apply_declarations_to_progress(
                         &mut style_state.progress,
                         declarations,
                         *urgency,
+                        style_state.font.size,
                     );
                 }
                 Element::Hint => {
@@ -531,7 +595,7 @@ fn apply_to_urgency_styles(
                 Element::Icon => {
                     for decl in declarations {
                         if decl.name == "border-radius" {
-                            if let Some(radius) = parse_border_radius(decl.value) {
+                            if let Some(radius) = parse_length(decl.value, style_state.font.size) {
                                 style_state.icon.border.radius = BorderRadius {
                                     top_left: radius,
                                     top_right: radius,
@@ -542,7 +606,7 @@ fn apply_to_urgency_styles(
                         }
                     }
                 }
-                Element::Counter => {}
+                Element::Counter | Element::Palette => {}
             }
         }
     }
@@ -551,6 +615,10 @@ fn apply_to_urgency_styles(
         apply_declarations_to_counter(&mut styles.next, declarations, Urgency::All);
         apply_declarations_to_counter(&mut styles.prev, declarations, Urgency::All);
     }
+
+    if selector.element == Element::Palette {
+        apply_declarations_to_counter(&mut styles.palette, declarations, Urgency::All);
+    }
 }
 
 pub fn parse_css(css: &str) -> Styles {
@@ -561,15 +629,70 @@ pub fn parse_css(css: &str) -> Styles {
     }
 
     let stylesheet = StyleSheet::parse(css);
+    let mut category_rules = Vec::new();
 
     for rule in &stylesheet.rules {
         let selector_str = rule.selector.to_string();
 
-        if let Some(selector) = parse_selector(&selector_str) {
+        if let Some(category) = parse_category(&selector_str) {
+            category_rules.push((category.to_string(), selector_str, &rule.declarations));
+        } else if let Some(selector) = parse_selector(&selector_str) {
             apply_to_urgency_styles(&mut styles, &selector, &rule.declarations);
         }
     }
 
+    // Categories start from the complete base styles, regardless of rule order
+    let mut categories: HashMap<Box<str>, Styles> = HashMap::new();
+    for (category, selector_str, declarations) in category_rules {
+        if let Some(selector) = parse_selector(&selector_str) {
+            let category_styles = categories
+                .entry(category.into())
+                .or_insert_with(|| styles.clone());
+            apply_to_urgency_styles(category_styles, &selector, declarations);
+        }
+    }
+
+    styles.categories = categories
+        .into_iter()
+        .map(|(category, category_styles)| (category, Arc::new(category_styles)))
+        .collect();
+
+    styles
+}
+
+/// Parses `css` and layers the plain rules of `overrides` over the base styles and
+/// every category, so nothing in `css` can win over them
+pub fn parse_css_with_overrides(css: &str, overrides: &str) -> Styles {
+    let mut styles = parse_css(css);
+
+    if overrides.is_empty() {
+        return styles;
+    }
+
+    let stylesheet = StyleSheet::parse(overrides);
+    let selectors: Vec<_> = stylesheet
+        .rules
+        .iter()
+        .filter_map(|rule| {
+            let selector_str = rule.selector.to_string();
+            if parse_category(&selector_str).is_some() {
+                return None;
+            }
+            parse_selector(&selector_str).map(|selector| (selector, &rule.declarations))
+        })
+        .collect();
+
+    for (selector, declarations) in &selectors {
+        apply_to_urgency_styles(&mut styles, selector, declarations);
+    }
+
+    for category_styles in styles.categories.values_mut() {
+        let category_styles = Arc::make_mut(category_styles);
+        for (selector, declarations) in &selectors {
+            apply_to_urgency_styles(category_styles, selector, declarations);
+        }
+    }
+
     styles
 }
 
@@ -620,4 +743,106 @@ mod tests {
             [255, 0, 0, 255]
         );
     }
+
+    #[test]
+    fn test_parse_lengths() {
+        let css = r#"
+            .notification {
+                font-size: 1.5em;
+                border-radius: 1em;
+                border-width: 3px;
+            }
+            .button {
+                border-radius: 6pt;
+            }
+        "#;
+
+        let styles = parse_css(css);
+        let style = &styles.urgency_normal.unfocused;
+
+        assert_eq!(style.font.size, 15.);
+        assert_eq!(style.border.radius.top_left, 20.);
+        assert_eq!(style.border.size.left, 3.);
+        assert_eq!(style.buttons.action.default.border.radius.top_left, 8.);
+    }
+
+    #[test]
+    fn test_parse_category() {
+        assert_eq!(
+            parse_category(r#".notification[category="email.arrived"]"#),
+            Some("email.arrived")
+        );
+        assert_eq!(parse_category("[category='im']:hover"), Some("im"));
+        assert_eq!(parse_category(".notification"), None);
+    }
+
+    #[test]
+    fn test_overrides_apply_to_categories() {
+        let css = r#"
+            .notification[category="im"] {
+                background-color: #ff0000;
+            }
+        "#;
+        let overrides = r#"
+            .notification {
+                background-color: #000000;
+            }
+        "#;
+
+        let styles = parse_css_with_overrides(css, overrides);
+        let im = &styles.categories["im"];
+
+        assert_eq!(
+            styles.urgency_normal.focused.background.urgency_normal,
+            [0, 0, 0, 255]
+        );
+        assert_eq!(
+            im.urgency_normal.focused.background.urgency_normal,
+            [0, 0, 0, 255]
+        );
+    }
+
+    #[test]
+    fn test_parse_palette() {
+        let css = r#"
+            .palette {
+                background-color: #000000;
+                border-width: 2px;
+            }
+        "#;
+
+        let styles = parse_css(css);
+
+        assert_eq!(styles.palette.background.urgency_low, [0, 0, 0, 255]);
+        assert_eq!(styles.palette.border.size.left, 2.);
+        assert_eq!(styles.next.background.urgency_low, [30, 30, 46, 200]);
+    }
+
+    #[test]
+    fn test_parse_call_buttons() {
+        let css = r#"
+            .button.accept {
+                background-color: #00ff00;
+            }
+            .button.decline:hover {
+                background-color: #ff0000;
+            }
+        "#;
+
+        let styles = parse_css(css);
+        let buttons = &styles.urgency_normal.focused.buttons;
+
+        assert_eq!(
+            buttons.accept.default.background.urgency_normal,
+            [0, 255, 0, 255]
+        );
+        assert_eq!(
+            buttons.decline.hover.background.urgency_normal,
+            [255, 0, 0, 255]
+        );
+        assert_eq!(
+            buttons.action.default.background.urgency_normal,
+            [22, 22, 30, 0]
+        );
+    }
 }
@@ -0,0 +1,143 @@
+use futures_lite::stream::StreamExt;
+use std::collections::HashMap;
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+use std::time::Duration;
+use zbus::zvariant::Value;
+
+/// Polkit action installed from pl.mox.notify.policy
+const ACTION_ID: &str = "pl.mox.notify.show-history";
+
+const ALLOW_USER_INTERACTION: u32 = 1;
+
+#[zbus::proxy(
+    interface = "org.freedesktop.login1.Session",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1/session/auto"
+)]
+trait Session {
+    #[zbus(property)]
+    fn locked_hint(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn idle_hint(&self) -> zbus::Result<bool>;
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.PolicyKit1.Authority",
+    default_service = "org.freedesktop.PolicyKit1",
+    default_path = "/org/freedesktop/PolicyKit1/Authority"
+)]
+trait Authority {
+    #[allow(clippy::type_complexity)]
+    fn check_authorization(
+        &self,
+        subject: &(&str, HashMap<&str, Value<'_>>),
+        action_id: &str,
+        details: HashMap<&str, &str>,
+        flags: u32,
+        cancellation_id: &str,
+    ) -> zbus::Result<(bool, bool, HashMap<String, String>)>;
+}
+
+/// Hides history behind polkit authentication once the logind session was locked or idle.
+/// Only advisory: it gates what the client and `moxnotifyctl` show, the history index,
+/// Redis and the searcher stay readable to anyone running as the user
+#[derive(Clone, Default)]
+pub struct HistoryLock {
+    locked: Arc<AtomicBool>,
+}
+
+impl HistoryLock {
+    #[must_use]
+    pub fn locked(&self) -> bool {
+        self.locked.load(Ordering::Relaxed)
+    }
+
+    /// Engage the lock when the session gets locked or stays idle for `lock_after`
+    pub async fn watch(&self, lock_after: Duration) -> zbus::Result<()> {
+        let conn = zbus::Connection::system().await?;
+        let session = SessionProxy::new(&conn).await?;
+
+        if session.locked_hint().await.unwrap_or_default() {
+            log::info!("Session locked, locking history");
+            self.locked.store(true, Ordering::Relaxed);
+        }
+
+        let mut locked_changes = session.receive_locked_hint_changed().await;
+        let locked = Arc::clone(&self.locked);
+        tokio::spawn(async move {
+            while let Some(change) = locked_changes.next().await {
+                if change.get().await.unwrap_or_default() {
+                    log::info!("Session locked, locking history");
+                    locked.store(true, Ordering::Relaxed);
+                }
+            }
+        });
+
+        // IdleHint is only set if the compositor or idle daemon reports it to logind
+        let mut idle = session.idle_hint().await.unwrap_or_default();
+        let mut idle_changes = session.receive_idle_hint_changed().await;
+        let locked = Arc::clone(&self.locked);
+        tokio::spawn(async move {
+            loop {
+                if idle {
+                    // Any change before the timeout means the session became active again
+                    match tokio::time::timeout(lock_after, idle_changes.next()).await {
+                        Ok(Some(_)) => {}
+                        Ok(None) => return,
+                        Err(_) => {
+                            log::info!("Session idle for {lock_after:?}, locking history");
+                            locked.store(true, Ordering::Relaxed);
+                        }
+                    }
+                }
+
+                let Some(change) = idle_changes.next().await else {
+                    return;
+                };
+                idle = change.get().await.unwrap_or_default();
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Ask polkit to authenticate the user, returns whether the history is unlocked
+    pub async fn unlock(&self) -> zbus::Result<bool> {
+        if !self.locked() {
+            return Ok(true);
+        }
+
+        let conn = zbus::Connection::system().await?;
+        let authority = AuthorityProxy::new(&conn).await?;
+
+        let subject = (
+            "unix-process",
+            HashMap::from([
+                ("pid", Value::from(std::process::id())),
+                // Looked up by polkit
+                ("start-time", Value::from(0u64)),
+            ]),
+        );
+
+        let (authorized, _, _) = authority
+            .check_authorization(
+                &subject,
+                ACTION_ID,
+                HashMap::new(),
+                ALLOW_USER_INTERACTION,
+                "",
+            )
+            .await?;
+
+        if authorized {
+            log::info!("History unlocked");
+            self.locked.store(false, Ordering::Relaxed);
+        }
+
+        Ok(authorized)
+    }
+}
@@ -1,2 +1,6 @@
+pub mod history_lock;
 pub mod moxnotify;
+pub mod mpris;
+pub mod notifications;
 pub mod portal;
+pub mod sleep;
@@ -1,4 +1,7 @@
-use crate::{EmitEvent, Event};
+use super::history_lock::HistoryLock;
+use crate::moxnotify::types::Urgency;
+use crate::searcher::Searcher;
+use crate::{EmitEvent, Event, preview};
 #[cfg(not(debug_assertions))]
 use futures_lite::stream::StreamExt;
 use std::sync::Arc;
@@ -10,6 +13,24 @@ use zbus::{fdo::RequestNameFlags, object_server::SignalEmitter};
 struct MoxnotifyInterface {
     event_sender: calloop::channel::Sender<Event>,
     emit_receiver: broadcast::Receiver<EmitEvent>,
+    history_lock: HistoryLock,
+    /// `None` when the searcher's token couldn't be read
+    searcher: Option<Searcher>,
+}
+
+impl MoxnotifyInterface {
+    /// The searcher, if history isn't locked
+    fn searcher(&self) -> zbus::fdo::Result<&Searcher> {
+        if self.history_lock.locked() {
+            return Err(zbus::fdo::Error::AccessDenied(
+                "History is locked, unlock it with UnlockHistory first".to_string(),
+            ));
+        }
+
+        self.searcher
+            .as_ref()
+            .ok_or_else(|| zbus::fdo::Error::Failed("The searcher isn't set up".to_string()))
+    }
 }
 
 #[zbus::interface(name = "pl.mox.Notify")]
@@ -52,8 +73,30 @@ impl MoxnotifyInterface {
         0
     }
 
-    async fn list(&mut self) -> Vec<String> {
-        if let Err(e) = self.event_sender.send(Event::List) {
+    /// Show the notifications waiting while inhibited, new ones keep waiting
+    async fn show_waiting(&self) {
+        if let Err(e) = self.event_sender.send(Event::ShowWaiting) {
+            log::error!("{e}");
+        }
+    }
+
+    /// Show the waiting notification `id`, or the oldest one if `id` is 0
+    async fn pop_waiting(&self, id: u32) {
+        if let Err(e) = self
+            .event_sender
+            .send(Event::PopWaiting((id != 0).then_some(id)))
+        {
+            log::error!("{e}");
+        }
+    }
+
+    #[zbus(signal)]
+    async fn waiting_changed(signal_emitter: &SignalEmitter<'_>, count: u32) -> zbus::Result<()>;
+
+    /// The notifications on screen as JSON, with `age` and `remaining` timeout in
+    /// milliseconds. Pixels of inline images are left out unless `full`
+    async fn list(&mut self, full: bool) -> Vec<String> {
+        if let Err(e) = self.event_sender.send(Event::List { full }) {
             log::error!("{e}");
         }
 
@@ -66,6 +109,80 @@ impl MoxnotifyInterface {
         Vec::new()
     }
 
+    /// Show a synthetic notification without going through the pipeline, replacing the
+    /// previous preview. `progress` below 0 leaves out the progress bar
+    async fn preview(
+        &self,
+        urgency: u8,
+        actions: bool,
+        image: bool,
+        progress: i32,
+    ) -> zbus::fdo::Result<()> {
+        let urgency = Urgency::try_from(i32::from(urgency))
+            .map_err(|_| zbus::fdo::Error::InvalidArgs(format!("Invalid urgency: {urgency}")))?;
+        let notification =
+            preview::notification(urgency, actions, image, (progress >= 0).then_some(progress));
+
+        self.event_sender
+            .send(Event::Preview(Box::new(notification)))
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))
+    }
+
+    /// Apply `css` over the configured styles until the next call, an empty string
+    /// drops the overlay
+    async fn set_style_overlay(&self, css: String) -> zbus::fdo::Result<()> {
+        self.event_sender
+            .send(Event::StyleOverlay(css))
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))
+    }
+
+    /// Style notifications with large fonts, maximum contrast colors and thick
+    /// borders regardless of the theme, until turned off again
+    async fn set_high_contrast(&self, enabled: bool) -> zbus::fdo::Result<()> {
+        self.event_sender
+            .send(Event::SetHighContrast(enabled))
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))
+    }
+
+    async fn high_contrast(&mut self) -> bool {
+        if let Err(e) = self.event_sender.send(Event::GetHighContrast) {
+            log::error!("{e}");
+            return false;
+        }
+
+        while let Ok(event) = self.emit_receiver.recv().await {
+            if let EmitEvent::HighContrast(enabled) = event {
+                return enabled;
+            }
+        }
+
+        false
+    }
+
+    /// Load the config file again, failing with why it couldn't be if it can't
+    async fn reload(&mut self) -> zbus::fdo::Result<()> {
+        self.event_sender
+            .send(Event::ReloadConfig)
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))?;
+
+        while let Ok(event) = self.emit_receiver.recv().await {
+            if let EmitEvent::ConfigReloaded(error) = event {
+                return match error {
+                    Some(error) => Err(zbus::fdo::Error::Failed(error.to_string())),
+                    None => Ok(()),
+                };
+            }
+        }
+
+        Err(zbus::fdo::Error::Failed("The client stopped".to_string()))
+    }
+
+    async fn digest(&self) {
+        if let Err(e) = self.event_sender.send(Event::ShowDigest) {
+            log::error!("{e}");
+        }
+    }
+
     async fn mute(&self) {
         if let Err(e) = self.event_sender.send(Event::Mute) {
             log::error!("{e}");
@@ -120,6 +237,41 @@ impl MoxnotifyInterface {
         }
     }
 
+    /// Authenticate through polkit if history is locked, returns whether it's unlocked
+    async fn unlock_history(&self) -> bool {
+        match self.history_lock.unlock().await {
+            Ok(unlocked) => unlocked,
+            Err(e) => {
+                log::error!("Failed to unlock history: {e}");
+                false
+            }
+        }
+    }
+
+    /// `limit` entries of the notification history starting at `offset`, newest first,
+    /// as JSON. Only the ones of `app_name` unless it's empty
+    async fn get_history(
+        &self,
+        offset: u32,
+        limit: u32,
+        app_name: String,
+    ) -> zbus::fdo::Result<Vec<String>> {
+        let app_name = (!app_name.is_empty()).then_some(app_name.as_str());
+
+        self.searcher()?
+            .history(offset, limit, app_name)
+            .await
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))
+    }
+
+    /// Delete the whole notification history, returns how many entries were deleted
+    async fn clear_history(&self) -> zbus::fdo::Result<u64> {
+        self.searcher()?
+            .clear()
+            .await
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))
+    }
+
     #[zbus(signal)]
     async fn inhibit_changed(
         signal_emitter: &SignalEmitter<'_>,
@@ -130,14 +282,19 @@ impl MoxnotifyInterface {
 pub async fn serve(
     event_sender: calloop::channel::Sender<Event>,
     mut emit_receiver: broadcast::Receiver<EmitEvent>,
+    history_lock: HistoryLock,
+    searcher: Option<Searcher>,
 ) -> zbus::Result<()> {
     let server = MoxnotifyInterface {
         event_sender,
         emit_receiver: emit_receiver.resubscribe(),
+        history_lock,
+        searcher,
     };
 
     let conn = zbus::connection::Builder::session()?
         .serve_at("/pl/mox/Notify", server)?
+        .serve_at(logging::PATH, logging::LogInterface)?
         .build()
         .await?;
 
@@ -189,6 +346,16 @@ pub async fn serve(
                         log::error!("{e}");
                     }
                 }
+                Ok(EmitEvent::WaitingChanged(count)) => {
+                    if let Err(e) = MoxnotifyInterfaceSignals::waiting_changed(
+                        iface.signal_emitter(),
+                        count as u32,
+                    )
+                    .await
+                    {
+                        log::error!("{e}");
+                    }
+                }
                 Err(e) => log::error!("{e}"),
                 _ => {}
             }
@@ -0,0 +1,126 @@
+use futures_lite::stream::StreamExt;
+use std::sync::{Arc, RwLock};
+
+const PLAYER_PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+#[zbus::proxy(
+    interface = "org.mpris.MediaPlayer2.Player",
+    default_path = "/org/mpris/MediaPlayer2"
+)]
+trait Player {
+    fn previous(&self) -> zbus::Result<()>;
+
+    fn play_pause(&self) -> zbus::Result<()>;
+
+    fn next(&self) -> zbus::Result<()>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Control {
+    Previous,
+    PlayPause,
+    Next,
+}
+
+impl Control {
+    pub const ALL: [Self; 3] = [Self::Previous, Self::PlayPause, Self::Next];
+
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Previous => "⏮",
+            Self::PlayPause => "⏯",
+            Self::Next => "⏭",
+        }
+    }
+}
+
+/// Player part of an MPRIS bus name, e.g. "spotify" or "firefox.instance_1_84"
+fn player(name: &str) -> Option<Box<str>> {
+    name.strip_prefix(PLAYER_PREFIX).map(Into::into)
+}
+
+/// Player of the first of `ids` that has one running, the part of the player's name
+/// before an instance suffix has to match the id
+#[must_use]
+pub fn find<'a>(
+    players: &[Box<str>],
+    ids: impl IntoIterator<Item = Option<&'a str>>,
+) -> Option<Arc<str>> {
+    ids.into_iter()
+        .flatten()
+        .filter(|id| !id.is_empty())
+        .find_map(|id| {
+            players.iter().find(|player| {
+                player
+                    .split('.')
+                    .next()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(id))
+            })
+        })
+        .map(|player| Arc::from(&**player))
+}
+
+/// Keep `players` in sync with the MPRIS players on the session bus
+pub async fn watch(players: Arc<RwLock<Vec<Box<str>>>>) -> zbus::Result<()> {
+    let conn = zbus::Connection::session().await?;
+    let dbus = zbus::fdo::DBusProxy::new(&conn).await?;
+    let mut changes = dbus.receive_name_owner_changed().await?;
+
+    *players.write().unwrap() = dbus
+        .list_names()
+        .await?
+        .iter()
+        .filter_map(|name| player(name.as_str()))
+        .collect();
+
+    while let Some(change) = changes.next().await {
+        let args = change.args()?;
+        let Some(name) = player(args.name().as_str()) else {
+            continue;
+        };
+
+        let mut players = players.write().unwrap();
+        players.retain(|player| *player != name);
+        if args.new_owner().is_some() {
+            log::debug!("MPRIS player '{name}' appeared");
+            players.push(name);
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn control(player: &str, control: Control) -> zbus::Result<()> {
+    let conn = zbus::Connection::session().await?;
+    let proxy = PlayerProxy::builder(&conn)
+        .destination(format!("{PLAYER_PREFIX}{player}"))?
+        .build()
+        .await?;
+
+    match control {
+        Control::Previous => proxy.previous().await,
+        Control::PlayPause => proxy.play_pause().await,
+        Control::Next => proxy.next().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_player() {
+        let players = ["spotify".into(), "firefox.instance_1_84".into()];
+
+        assert_eq!(
+            find(&players, [None, Some("Firefox")]).as_deref(),
+            Some("firefox.instance_1_84")
+        );
+        assert_eq!(
+            find(&players, [Some("spotify"), Some("Spotify")]).as_deref(),
+            Some("spotify")
+        );
+        assert_eq!(find(&players, [Some(""), Some("mpv")]), None);
+    }
+}
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+use zbus::zvariant::Value;
+
+/// App name of notifications the client sends itself
+pub const APP_NAME: &str = "moxnotify";
+
+#[zbus::proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+}
+
+/// Send a notification through the notification server, so it's scheduled like any other
+pub async fn send(summary: &str, body: &str) -> zbus::Result<()> {
+    let conn = zbus::Connection::session().await?;
+    NotificationsProxy::new(&conn)
+        .await?
+        .notify(APP_NAME, 0, "", summary, body, &[], HashMap::new(), -1)
+        .await?;
+
+    Ok(())
+}
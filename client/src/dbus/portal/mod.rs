@@ -1 +1,2 @@
 pub mod open_uri;
+pub mod settings;
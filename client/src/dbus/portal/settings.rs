@@ -0,0 +1,52 @@
+use futures_lite::stream::StreamExt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const APPEARANCE: &str = "org.freedesktop.appearance";
+const REDUCED_MOTION: &str = "reduced-motion";
+
+#[zbus::proxy(
+    interface = "org.freedesktop.portal.Settings",
+    default_service = "org.freedesktop.portal.Desktop",
+    default_path = "/org/freedesktop/portal/desktop"
+)]
+trait Settings {
+    fn read_one(&self, namespace: &str, key: &str) -> zbus::Result<zbus::zvariant::OwnedValue>;
+
+    #[zbus(signal)]
+    fn setting_changed(
+        &self,
+        namespace: &str,
+        key: &str,
+        value: zbus::zvariant::Value<'_>,
+    ) -> zbus::Result<()>;
+}
+
+/// The portal's `reduced-motion` is 1 when the user asked for less motion, 0 when
+/// they have no preference
+fn reduces_motion(value: &zbus::zvariant::Value<'_>) -> bool {
+    u32::try_from(value).is_ok_and(|value| value == 1)
+}
+
+/// Keep `reduced_motion` in sync with the desktop's reduced motion preference
+pub async fn watch_reduced_motion(reduced_motion: Arc<AtomicBool>) -> zbus::Result<()> {
+    let conn = zbus::Connection::session().await?;
+    let settings = SettingsProxy::new(&conn).await?;
+    let mut changes = settings
+        .receive_setting_changed_with_args(&[(0, APPEARANCE), (1, REDUCED_MOTION)])
+        .await?;
+
+    // Portals from before the key was added don't have it
+    if let Ok(value) = settings.read_one(APPEARANCE, REDUCED_MOTION).await {
+        reduced_motion.store(reduces_motion(&value), Ordering::Relaxed);
+    }
+
+    while let Some(change) = changes.next().await {
+        let args = change.args()?;
+        let reduces = reduces_motion(args.value());
+        log::info!("Desktop reduced motion preference changed to {reduces}");
+        reduced_motion.store(reduces, Ordering::Relaxed);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use super::notifications::{self, APP_NAME};
+use crate::Event;
+use crate::moxnotify::types::NewNotification;
+use futures_lite::stream::StreamExt;
+use std::collections::BTreeMap;
+
+#[zbus::proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+trait Manager {
+    #[zbus(signal)]
+    fn prepare_for_sleep(&self, start: bool) -> zbus::Result<()>;
+}
+
+/// Notifications received since the system went to sleep
+pub struct SleepDigest {
+    since: i64,
+    counts: BTreeMap<String, usize>,
+}
+
+impl SleepDigest {
+    #[must_use]
+    pub fn new(since: i64) -> Self {
+        Self {
+            since,
+            counts: BTreeMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, data: &NewNotification) {
+        if data.timestamp >= self.since && data.app_name != APP_NAME {
+            *self
+                .counts
+                .entry(data.display_name().to_string())
+                .or_default() += 1;
+        }
+    }
+
+    /// Count per app, `None` if nothing arrived
+    #[must_use]
+    pub fn body(&self) -> Option<String> {
+        if self.counts.is_empty() {
+            return None;
+        }
+
+        Some(
+            self.counts
+                .iter()
+                .map(|(app_name, count)| format!("{app_name}: {count}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+    }
+
+    pub async fn send(self) -> zbus::Result<()> {
+        let Some(body) = self.body() else {
+            return Ok(());
+        };
+
+        let total = self.counts.values().sum::<usize>();
+        let summary = format!("{total} notifications while asleep");
+
+        notifications::send(&summary, &body).await
+    }
+}
+
+/// Forward logind's PrepareForSleep signals to the event loop
+pub async fn watch(event_sender: calloop::channel::Sender<Event>) -> zbus::Result<()> {
+    let conn = zbus::Connection::system().await?;
+    let mut prepare_for_sleep = ManagerProxy::new(&conn)
+        .await?
+        .receive_prepare_for_sleep()
+        .await?;
+
+    while let Some(signal) = prepare_for_sleep.next().await {
+        let start = signal.args()?.start;
+        if let Err(e) = event_sender.send(Event::PrepareForSleep(start)) {
+            log::error!("{e}");
+        }
+    }
+
+    Ok(())
+}
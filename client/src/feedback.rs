@@ -0,0 +1,124 @@
+use config::client::Feedback;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+const LEDS: &str = "/sys/class/leds";
+
+#[zbus::proxy(
+    interface = "org.freedesktop.UPower.KbdBacklight",
+    default_service = "org.freedesktop.UPower",
+    default_path = "/org/freedesktop/UPower/KbdBacklight"
+)]
+trait KbdBacklight {
+    fn get_brightness(&self) -> zbus::Result<i32>;
+    fn get_max_brightness(&self) -> zbus::Result<i32>;
+    fn set_brightness(&self, value: i32) -> zbus::Result<()>;
+}
+
+/// Brightness a LED is toggled to from `brightness`, off when it's fully on
+fn toggled(brightness: i32, max: i32) -> i32 {
+    if brightness >= max { 0 } else { max }
+}
+
+/// Flash the keyboard backlight through UPower, connecting to the system bus the
+/// first time
+async fn flash_kbd_backlight(
+    backlight: &mut Option<KbdBacklightProxy<'static>>,
+    duration: Duration,
+) -> zbus::Result<()> {
+    let backlight = match backlight {
+        Some(backlight) => backlight,
+        None => {
+            let conn = zbus::Connection::system().await?;
+            backlight.insert(KbdBacklightProxy::new(&conn).await?)
+        }
+    };
+    let brightness = backlight.get_brightness().await?;
+    let max = backlight.get_max_brightness().await?;
+
+    backlight.set_brightness(toggled(brightness, max)).await?;
+    tokio::time::sleep(duration).await;
+    backlight.set_brightness(brightness).await
+}
+
+async fn read_brightness(path: &Path) -> anyhow::Result<i32> {
+    Ok(tokio::fs::read_to_string(path).await?.trim().parse()?)
+}
+
+/// Toggle the LED at `led`, returning the brightness to restore
+async fn toggle_led(led: &Path) -> anyhow::Result<i32> {
+    let brightness = read_brightness(&led.join("brightness")).await?;
+    let max = read_brightness(&led.join("max_brightness")).await?;
+    tokio::fs::write(led.join("brightness"), toggled(brightness, max).to_string()).await?;
+
+    Ok(brightness)
+}
+
+async fn blink_leds(leds: &[String], duration: Duration) {
+    let mut toggled: Vec<(PathBuf, i32)> = Vec::new();
+    for name in leds {
+        let led = Path::new(LEDS).join(name);
+        match toggle_led(&led).await {
+            Ok(brightness) => toggled.push((led, brightness)),
+            Err(e) => log::warn!("Failed to blink LED '{name}': {e}"),
+        }
+    }
+
+    tokio::time::sleep(duration).await;
+
+    for (led, brightness) in toggled {
+        if let Err(e) = tokio::fs::write(led.join("brightness"), brightness.to_string()).await {
+            log::warn!("Failed to restore LED '{}': {e}", led.display());
+        }
+    }
+}
+
+/// Blinks one at a time, so a blink never reads the brightness another one
+/// toggled and restores that
+pub struct Blinker {
+    sender: mpsc::UnboundedSender<(Vec<String>, Duration)>,
+}
+
+impl Blinker {
+    /// Start the task blinks are run on
+    #[must_use]
+    pub fn spawn() -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<(Vec<String>, Duration)>();
+
+        tokio::spawn(async move {
+            let mut backlight = None;
+            while let Some((leds, duration)) = receiver.recv().await {
+                if !leds.is_empty() {
+                    blink_leds(&leds, duration).await;
+                } else if let Err(e) = flash_kbd_backlight(&mut backlight, duration).await {
+                    log::warn!("Failed to flash the keyboard backlight: {e}");
+                    // Reconnect next time in case UPower restarted
+                    backlight = None;
+                }
+
+                // Notifications that came in during the blink were already noticed
+                while receiver.try_recv().is_ok() {}
+            }
+        });
+
+        Self { sender }
+    }
+
+    /// Blink the LEDs in `config` once in the background
+    pub fn blink(&self, config: &Feedback) {
+        _ = self.sender.send((config.leds.clone(), config.duration));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggled_flips_between_off_and_max() {
+        assert_eq!(toggled(0, 3), 3);
+        assert_eq!(toggled(1, 3), 3);
+        assert_eq!(toggled(3, 3), 0);
+    }
+}
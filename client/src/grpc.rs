@@ -1,11 +1,58 @@
 use crate::Event;
 use crate::moxnotify::client::client_service_client::ClientServiceClient;
-use crate::moxnotify::client::{ClientNotifyRequest, notification_message};
+use crate::moxnotify::client::{ClientNotifyRequest, GetBlobRequest, notification_message};
+use crate::moxnotify::types::{Capability, Hello, NewNotification, image};
 use futures_lite::stream::StreamExt;
 use tokio::time;
 use tonic::Request;
 use tonic::transport::Channel;
 
+impl NewNotification {
+    /// Name of the app to show, `app_name` unless its desktop entry has a better one
+    pub fn display_name(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.app_name)
+    }
+}
+
+/// Fetch image bytes that were stored out-of-band and put them back inline
+async fn fetch_image_blob(
+    client: &mut ClientServiceClient<Channel>,
+    notification: &mut NewNotification,
+) {
+    let Some(hints) = notification.hints.as_mut() else {
+        return;
+    };
+
+    let Some(image::Image::Data(data)) = hints.image.as_mut().and_then(|i| i.image.as_mut()) else {
+        return;
+    };
+
+    let Some(key) = data.blob.take() else {
+        return;
+    };
+
+    match client.get_blob(Request::new(GetBlobRequest { key })).await {
+        Ok(response) => data.data = response.into_inner().data,
+        Err(e) => {
+            log::warn!(
+                "Failed to fetch image for notification id={}: {}",
+                notification.id,
+                e.message()
+            );
+            hints.image = None;
+        }
+    }
+}
+
+/// Identifies this client to the scheduler across reconnects, so it gets back the
+/// viewport and selection it had. One client runs per Wayland display of a machine
+fn client_id() -> String {
+    let machine = std::fs::read_to_string("/etc/machine-id").unwrap_or_default();
+    let display = std::env::var("WAYLAND_DISPLAY").unwrap_or_default();
+
+    format!("{}/{}", machine.trim(), display)
+}
+
 pub async fn serve(
     mut client: ClientServiceClient<Channel>,
     event_sender: calloop::channel::Sender<Event>,
@@ -13,7 +60,14 @@ pub async fn serve(
 ) -> anyhow::Result<()> {
     let mut disconnects = 0;
     loop {
-        let request = Request::new(ClientNotifyRequest { max_visible });
+        let request = Request::new(ClientNotifyRequest {
+            max_visible,
+            hello: Some(Hello {
+                protocol_version: config::protocol::VERSION,
+                capabilities: vec![Capability::ImageBlobs as i32],
+                client_id: client_id(),
+            }),
+        });
         if let Ok(mut stream) = client
             .notify(request)
             .await
@@ -27,18 +81,22 @@ pub async fn serve(
                 {
                     disconnects = 0;
                     match message {
-                        notification_message::Message::Notification(notification) => {
+                        notification_message::Message::Notification(mut notification) => {
                             log::info!(
-                                "Received notification: id={}, app_name='{}', summary='{}', body='{}', urgency='{}'",
+                                "Received notification: id={}, app_name='{}', summary={}, body={}, urgency='{}'",
                                 notification.id,
                                 notification.app_name,
-                                notification.summary,
-                                notification.body,
+                                logging::content(&notification.summary),
+                                logging::content(&notification.body),
                                 notification.hints.as_ref().unwrap().urgency
                             );
 
-                            if let Err(e) = event_sender.send(Event::Notify(Box::new(notification)))
-                            {
+                            fetch_image_blob(&mut client, &mut notification).await;
+
+                            if let Err(e) = event_sender.send(Event::Notify {
+                                data: Box::new(notification),
+                                restored: msg.restored,
+                            }) {
                                 log::error!("Error: {e}");
                             }
                         }
@@ -1,18 +1,43 @@
 use crate::CloseReason;
 use crate::Moxnotify;
+use crate::manager::palette::Command;
 use config::client::keymaps;
-use config::client::keymaps::{KeyAction, KeyWithModifiers, Keys, Modifiers};
-use calloop::RegistrationToken;
+use config::client::keymaps::{Key, KeyAction, KeyWithModifiers, Keys, Modifiers, SpecialKeyCode};
+use config::client::{General, KeyRepeat};
 use calloop::timer::{TimeoutAction, Timer};
+use calloop::{LoopHandle, RegistrationToken};
+use std::env;
+use std::ffi::OsString;
 use std::sync::atomic::Ordering;
 use std::time::Duration;
 use wayland_client::protocol::{wl_keyboard, wl_seat};
 use wayland_client::{Connection, Dispatch, QueueHandle, WEnum};
-use xkbcommon::xkb::{Context, Keymap, State};
+use xkbcommon::xkb::compose::{self, FeedResult, Status, Table};
+use xkbcommon::xkb::{self, Context, Keycode, Keymap, LayoutIndex, State};
 
 struct Xkb {
     context: Context,
     state: Option<State>,
+    /// Dead keys and compose sequences of the user's locale, `None` if it has no compose table
+    compose: Option<compose::State>,
+}
+
+/// What a pressed key amounts to once it went through the compose state
+enum Composed {
+    /// Not part of a sequence, the key is read as usual
+    Key,
+    /// Somewhere in a sequence, or one that was cancelled, the key does nothing by itself
+    Pending,
+    Character(char),
+}
+
+/// Locale the compose table is picked for, as libc would resolve LC_CTYPE
+fn locale() -> OsString {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .into_iter()
+        .filter_map(env::var_os)
+        .find(|locale| !locale.is_empty())
+        .unwrap_or_else(|| "C".into())
 }
 
 pub struct Keyboard {
@@ -21,33 +46,145 @@ pub struct Keyboard {
     xkb: Xkb,
     pub key_combination: Keys,
     modifiers: Modifiers,
+    /// Active xkb layout, `None` until the keymap is known
+    layout: Option<LayoutIndex>,
 }
 
 #[derive(Default)]
 pub struct RepeatInfo {
     pub key: Option<config::client::keymaps::Key>,
+    /// Repeats per second the compositor asked for, 0 turns repeating off
     rate: i32,
+    /// Milliseconds the compositor wants a key held before it repeats
     delay: i32,
     registration_token: Option<RegistrationToken>,
 }
 
+impl RepeatInfo {
+    /// How long a key is held before it repeats and the time between repeats, with
+    /// `overrides` applied. `None` when repeating is turned off
+    fn timing(&self, overrides: &KeyRepeat) -> Option<(Duration, Duration)> {
+        let rate = overrides
+            .rate
+            .unwrap_or(u32::try_from(self.rate).unwrap_or_default());
+        if rate == 0 {
+            return None;
+        }
+
+        let delay = overrides.delay.unwrap_or(Duration::from_millis(
+            u64::try_from(self.delay).unwrap_or_default(),
+        ));
+
+        Some((delay, Duration::from_secs(1) / rate))
+    }
+
+    /// Stop repeating the held key
+    pub fn cancel(&mut self, loop_handle: &LoopHandle<'static, Moxnotify>) {
+        self.key = None;
+        if let Some(token) = self.registration_token.take() {
+            loop_handle.remove(token);
+        }
+    }
+}
+
 impl Keyboard {
     pub fn new(qh: &QueueHandle<Moxnotify>, wl_seat: &wl_seat::WlSeat) -> Self {
         let wl_keyboard = wl_seat.get_keyboard(qh, ());
 
         let xkb_context = Context::new(0);
+        let compose =
+            match Table::new_from_locale(&xkb_context, &locale(), compose::COMPILE_NO_FLAGS) {
+                Ok(table) => Some(compose::State::new(&table, compose::STATE_NO_FLAGS)),
+                Err(()) => {
+                    log::warn!("No compose table for the locale, dead keys won't compose");
+                    None
+                }
+            };
 
         Self {
             key_combination: Keys(Vec::new()),
             xkb: Xkb {
                 context: xkb_context,
                 state: None,
+                compose,
             },
             _wl_keyboard: wl_keyboard,
             repeat: RepeatInfo::default(),
             modifiers: Modifiers::default(),
+            layout: None,
         }
     }
+
+    /// Feed a pressed key to the compose state
+    fn compose(&mut self, keycode: Keycode) -> Composed {
+        let (Some(xkb_state), Some(compose)) = (self.xkb.state.as_ref(), self.xkb.compose.as_mut())
+        else {
+            return Composed::Key;
+        };
+
+        if let FeedResult::Ignored = compose.feed(xkb_state.key_get_one_sym(keycode)) {
+            return Composed::Key;
+        }
+
+        match compose.status() {
+            Status::Nothing => Composed::Key,
+            Status::Composing => Composed::Pending,
+            Status::Composed => {
+                let text = compose.utf8().unwrap_or_default();
+                compose.reset();
+
+                let mut chars = text.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(character), None) => Composed::Character(character),
+                    _ => Composed::Pending,
+                }
+            }
+            Status::Cancelled => {
+                compose.reset();
+                Composed::Pending
+            }
+        }
+    }
+
+    /// Key pressed at `keycode`, with dead keys and compose sequences applied
+    fn pressed(&mut self, keycode: Keycode, general: &General) -> Option<Key> {
+        match self.compose(keycode) {
+            Composed::Key => self.key(keycode, general),
+            Composed::Pending => None,
+            Composed::Character(character) => Some(Key::Character(character)),
+        }
+    }
+
+    /// Key at `keycode` on the active layout. Characters outside ascii are read from
+    /// the first layout instead, so keymaps and hints stay reachable while typing in
+    /// e.g. cyrillic, unless the layout has hint characters of its own
+    fn key(&self, keycode: Keycode, general: &General) -> Option<Key> {
+        let xkb_state = self.xkb.state.as_ref()?;
+        let key = Key::from_keycode(xkb_state, keycode)?;
+        let Key::Character(character) = key else {
+            return Some(key);
+        };
+
+        let keymap = xkb_state.get_keymap();
+        let layout = xkb_state.key_get_layout(keycode);
+        if character.is_ascii()
+            || layout == 0
+            || general
+                .layout_hint_characters
+                .contains_key(keymap.layout_get_name(layout))
+        {
+            return Some(key);
+        }
+
+        let level = xkb_state.key_get_level(keycode, layout);
+        keymap
+            .key_get_syms_by_level(keycode, 0, level)
+            .first()
+            .and_then(|&sym| char::from_u32(xkb::keysym_to_utf32(sym)))
+            .filter(char::is_ascii)
+            .map(Key::Character)
+            .or(Some(key))
+    }
 }
 
 impl Dispatch<wl_keyboard::WlKeyboard, ()> for Moxnotify {
@@ -77,6 +214,8 @@ impl Dispatch<wl_keyboard::WlKeyboard, ()> for Moxnotify {
                     Some(keymap) => {
                         let xkb_state = State::new(&keymap);
                         state.seat.keyboard.xkb.state = Some(xkb_state);
+                        state.seat.keyboard.layout = None;
+                        state.update_layout();
                     }
                     None => {
                         log::error!("Keymap data was unexpectedly empty.");
@@ -118,9 +257,11 @@ impl Dispatch<wl_keyboard::WlKeyboard, ()> for Moxnotify {
 
                     xkb_state.update_mask(shift_bit, shift_bit, shift_bit, 0, 0, group);
                 }
+
+                state.update_layout();
             }
             wl_keyboard::Event::Key {
-                serial: _,
+                serial,
                 time: _,
                 key,
                 state: WEnum::Value(value),
@@ -128,12 +269,15 @@ impl Dispatch<wl_keyboard::WlKeyboard, ()> for Moxnotify {
                 // The wayland protocol gives us an input event code. To convert this to an xkb
                 // keycode we must add 8.
                 let keycode = key + 8;
+                state.seat.serial = serial;
 
                 match value {
                     wl_keyboard::KeyState::Released => {
                         state.seat.keyboard.repeat.key = None;
-                        if let Some(xkb_state) = state.seat.keyboard.xkb.state.as_ref()
-                            && let Some(key) = config::client::keymaps::Key::from_keycode(xkb_state, keycode.into())
+                        if let Some(key) = state
+                            .seat
+                            .keyboard
+                            .key(keycode.into(), &state.config.general)
                         {
                             let key_with_modifiers = KeyWithModifiers {
                                 key,
@@ -146,13 +290,14 @@ impl Dispatch<wl_keyboard::WlKeyboard, ()> for Moxnotify {
                             }
                         }
 
-                        if let Some(token) = state.seat.keyboard.repeat.registration_token.take() {
-                            state.loop_handle.remove(token);
-                        }
+                        state.seat.keyboard.repeat.cancel(&state.loop_handle);
                     }
                     wl_keyboard::KeyState::Pressed => {
+                        let key = state
+                            .seat
+                            .keyboard
+                            .pressed(keycode.into(), &state.config.general);
                         if let Some(xkb_state) = state.seat.keyboard.xkb.state.as_ref() {
-                            let key = config::client::keymaps::Key::from_keycode(xkb_state, keycode.into());
                             state.seat.keyboard.repeat.key = key;
                             if let Some(key) = key {
                                 let key_with_modifiers = KeyWithModifiers {
@@ -162,20 +307,29 @@ impl Dispatch<wl_keyboard::WlKeyboard, ()> for Moxnotify {
                                 state.seat.keyboard.key_combination.push(key_with_modifiers);
                             }
 
-                            if xkb_state.get_keymap().key_repeats(keycode.into()) {
-                                if let Some(token) =
-                                    state.seat.keyboard.repeat.registration_token.take()
-                                {
-                                    state.loop_handle.remove(token);
-                                }
-
-                                let timer = Timer::from_duration(Duration::from_millis(
-                                    state.seat.keyboard.repeat.delay as u64,
-                                ));
-                                let rate = (1000 / state.seat.keyboard.repeat.rate) as u64;
+                            if let Some(token) =
+                                state.seat.keyboard.repeat.registration_token.take()
+                            {
+                                state.loop_handle.remove(token);
+                            }
+
+                            if xkb_state.get_keymap().key_repeats(keycode.into())
+                                && let Some((delay, interval)) = state
+                                    .seat
+                                    .keyboard
+                                    .repeat
+                                    .timing(&state.config.general.key_repeat)
+                            {
+                                let timer = Timer::from_duration(delay);
                                 state.seat.keyboard.repeat.registration_token = state
                                     .loop_handle
                                     .insert_source(timer, move |_, (), moxnotify| {
+                                        // Nothing is left to act on once the last
+                                        // notification went away mid-repeat
+                                        if moxnotify.notifications.notifications().is_empty() {
+                                            moxnotify.seat.keyboard.repeat.key = None;
+                                        }
+
                                         if let Some(key) = moxnotify.seat.keyboard.repeat.key {
                                             let key_with_modifiers = config::client::keymaps::KeyWithModifiers {
                                                 key,
@@ -187,19 +341,19 @@ impl Dispatch<wl_keyboard::WlKeyboard, ()> for Moxnotify {
                                                 .key_combination
                                                 .push(key_with_modifiers);
                                         } else {
+                                            moxnotify.seat.keyboard.repeat.registration_token =
+                                                None;
                                             return TimeoutAction::Drop;
                                         }
 
                                         if moxnotify.handle_key().is_err() {
+                                            moxnotify.seat.keyboard.repeat.registration_token =
+                                                None;
                                             return TimeoutAction::Drop;
                                         }
-                                        TimeoutAction::ToDuration(Duration::from_millis(rate))
+                                        TimeoutAction::ToDuration(interval)
                                     })
                                     .ok();
-                            } else if let Some(token) =
-                                state.seat.keyboard.repeat.registration_token
-                            {
-                                state.loop_handle.remove(token);
                             }
                         }
 
@@ -212,13 +366,236 @@ impl Dispatch<wl_keyboard::WlKeyboard, ()> for Moxnotify {
                 state.seat.keyboard.repeat.delay = delay;
                 state.seat.keyboard.repeat.rate = rate;
             }
+            // Keys released while another surface has focus are never reported
+            wl_keyboard::Event::Leave { .. } => {
+                state.seat.keyboard.repeat.cancel(&state.loop_handle);
+                state.seat.keyboard.key_combination.0.clear();
+            }
             _ => {}
         }
     }
 }
 
 impl Moxnotify {
+    /// Follow switches of the keyboard layout, which can come with other hint characters
+    fn update_layout(&mut self) {
+        let Some(xkb_state) = self.seat.keyboard.xkb.state.as_ref() else {
+            return;
+        };
+        let layout = xkb_state.serialize_layout(xkb::STATE_LAYOUT_EFFECTIVE);
+        if self.seat.keyboard.layout == Some(layout) {
+            return;
+        }
+
+        let name: Box<str> = xkb_state.get_keymap().layout_get_name(layout).into();
+        log::debug!("Keyboard layout switched to {name}");
+        self.seat.keyboard.layout = Some(layout);
+        self.notifications.set_layout(Some(name));
+        self.request_render();
+    }
+
+    /// Action the selected notification bound to the pressed key through its hints
+    fn keybind_action(&self) -> Option<crate::Event> {
+        if self.notifications.ui_state.mode.load(Ordering::Relaxed) != keymaps::Mode::Normal {
+            return None;
+        }
+
+        let [
+            KeyWithModifiers {
+                key: Key::Character(key),
+                modifiers,
+            },
+        ] = self.seat.keyboard.key_combination.as_slice()
+        else {
+            return None;
+        };
+        if *modifiers != Modifiers::default() {
+            return None;
+        }
+
+        let id = self.notifications.selected_id()?;
+        let notification = self
+            .notifications
+            .notifications()
+            .iter()
+            .find(|notification| notification.id() == id)?;
+        let action = notification
+            .data()
+            .hints
+            .as_ref()?
+            .keybinds
+            .get(&key.to_string())?;
+
+        Some(crate::Event::InvokeAction {
+            id,
+            key: action.clone(),
+            uuid: notification.uuid(),
+        })
+    }
+
+    /// Carry out a keymap's `action`. Returns `false` if it already updated the surface
+    fn run(&mut self, action: KeyAction) -> bool {
+        match action {
+            KeyAction::Noop => {}
+            KeyAction::NextNotification => self.notifications.next(),
+            KeyAction::PreviousNotification => self.notifications.prev(),
+            KeyAction::FirstNotification => self.notifications.first(),
+            KeyAction::LastNotification => self.notifications.last(),
+            KeyAction::DismissNotification => {
+                if let Some(id) = self.notifications.selected_id() {
+                    self.dismiss_with_reason(id, Some(CloseReason::ReasonDismissedByUser));
+                    return false;
+                }
+            }
+            KeyAction::Unfocus => {
+                if let Some(surface) = self.surface.as_mut() {
+                    surface.unfocus();
+                    self.seat.keyboard.key_combination.clear();
+                    self.notifications.deselect();
+                    self.seat.keyboard.repeat.key = None;
+                }
+            }
+            KeyAction::HintMode => self
+                .notifications
+                .ui_state
+                .mode
+                .store(keymaps::Mode::Hint, Ordering::Relaxed),
+            KeyAction::Uninhibit => self.notifications.uninhibit(),
+            KeyAction::Ihibit => self.notifications.inhibit(),
+            KeyAction::ToggleInhibit => {
+                if self.notifications.inhibited() {
+                    self.notifications.uninhibit();
+                } else {
+                    self.notifications.inhibit();
+                }
+            }
+            KeyAction::ShowWaiting => self.show_waiting(),
+            KeyAction::PopWaiting => self.pop_waiting(None),
+            KeyAction::Mute => {
+                self.audio.mute();
+            }
+            KeyAction::Unmute => {
+                self.audio.unmute();
+            }
+            KeyAction::ToggleMute => {
+                if self.audio.muted() {
+                    self.audio.unmute();
+                } else {
+                    self.audio.mute();
+                }
+            }
+            KeyAction::ToggleInspector => self.notifications.toggle_inspector(),
+            KeyAction::MuteApp => self.mute_app(),
+            KeyAction::ToggleHistory => {
+                if self.notifications.history_visible() {
+                    self.notifications.toggle_history();
+                } else {
+                    _ = self.event_sender.send(crate::Event::ShowHistory);
+                }
+            }
+            KeyAction::CommandPalette => self.notifications.open_palette(),
+            KeyAction::NormalMode => {
+                self.notifications
+                    .ui_state
+                    .mode
+                    .store(keymaps::Mode::Normal, Ordering::Relaxed);
+            }
+        }
+
+        true
+    }
+
+    /// Edit the query of the command palette or pick from it with the pressed key
+    fn palette_key(&mut self) {
+        let Some(KeyWithModifiers { key, modifiers }) = self.seat.keyboard.key_combination.pop()
+        else {
+            return;
+        };
+        self.seat.keyboard.key_combination.clear();
+        let Some(palette) = self.notifications.palette_mut() else {
+            return;
+        };
+
+        match key {
+            Key::SpecialKey(SpecialKeyCode::Escape) => self.notifications.close_palette(),
+            Key::SpecialKey(SpecialKeyCode::Enter) => {
+                let command = palette.selected();
+                self.notifications.close_palette();
+                if let Some(command) = command {
+                    self.run_command(command);
+                }
+            }
+            Key::SpecialKey(SpecialKeyCode::Up) => palette.prev(),
+            Key::Character('p') if modifiers.control => palette.prev(),
+            Key::SpecialKey(SpecialKeyCode::Down | SpecialKeyCode::Tab) => palette.next(),
+            Key::Character('n') if modifiers.control => palette.next(),
+            Key::Character('v') if modifiers.control => {
+                let pasting = self
+                    .seat
+                    .clipboard
+                    .as_ref()
+                    .is_some_and(|clipboard| clipboard.paste(self.event_sender.clone()));
+                if !pasting {
+                    log::debug!("Nothing to paste into the palette");
+                }
+            }
+            Key::SpecialKey(SpecialKeyCode::Backspace) => palette.pop(),
+            Key::SpecialKey(SpecialKeyCode::Space) => palette.push(' '),
+            Key::Character(c) if modifiers == Modifiers::default() => palette.push(c),
+            _ => {}
+        }
+
+        self.notifications.update_palette();
+    }
+
+    fn run_command(&mut self, command: Command) {
+        log::debug!("Command picked from the palette: {:?}", command);
+        match command {
+            Command::Key(action) => _ = self.run(action),
+            Command::DismissAll => {
+                _ = self
+                    .event_sender
+                    .send(crate::Event::Dismiss { all: true, id: 0 });
+            }
+            Command::ToggleHighContrast => {
+                let enabled = !self.notifications.high_contrast();
+                _ = self
+                    .event_sender
+                    .send(crate::Event::SetHighContrast(enabled));
+            }
+            Command::Invoke { id, key, uuid } => {
+                _ = self
+                    .event_sender
+                    .send(crate::Event::InvokeAction { id, key, uuid });
+            }
+        }
+    }
+
     fn handle_key(&mut self) -> anyhow::Result<()> {
+        if self.notifications.ui_state.mode.load(Ordering::Relaxed) == keymaps::Mode::Palette {
+            if let Some(action) = self
+                .config
+                .keymaps
+                .iter()
+                .find(|keymap| {
+                    keymap.keys == self.seat.keyboard.key_combination
+                        && keymap.mode == keymaps::Mode::Palette
+                })
+                .map(|keymap| keymap.action)
+            {
+                self.seat.keyboard.key_combination.clear();
+                self.notifications.close_palette();
+                if !self.run(action) {
+                    return Ok(());
+                }
+            } else {
+                self.palette_key();
+            }
+            self.update_surface_size();
+            self.request_render();
+            return Ok(());
+        }
+
         if !self
             .config
             .keymaps
@@ -230,66 +607,24 @@ impl Moxnotify {
 
         log::debug!("key‑combo => {}", self.seat.keyboard.key_combination);
 
-        if let Some(key_combination) = self.config.keymaps.iter().find(|keymap| {
-            keymap.keys == self.seat.keyboard.key_combination
-                && keymap.mode == self.notifications.ui_state.mode.load(Ordering::Relaxed)
-        }) {
-            log::debug!("Action executed: {:?}", key_combination.action);
+        if let Some(action) = self
+            .config
+            .keymaps
+            .iter()
+            .find(|keymap| {
+                keymap.keys == self.seat.keyboard.key_combination
+                    && keymap.mode == self.notifications.ui_state.mode.load(Ordering::Relaxed)
+            })
+            .map(|keymap| keymap.action)
+        {
+            log::debug!("Action executed: {:?}", action);
             self.seat.keyboard.key_combination.clear();
-            match key_combination.action {
-                KeyAction::Noop => {}
-                KeyAction::NextNotification => self.notifications.next(),
-                KeyAction::PreviousNotification => self.notifications.prev(),
-                KeyAction::FirstNotification => self.notifications.first(),
-                KeyAction::LastNotification => self.notifications.last(),
-                KeyAction::DismissNotification => {
-                    if let Some(id) = self.notifications.selected_id() {
-                        self.dismiss_with_reason(id, Some(CloseReason::ReasonDismissedByUser));
-                        return Ok(());
-                    }
-                }
-                KeyAction::Unfocus => {
-                    if let Some(surface) = self.surface.as_mut() {
-                        surface.unfocus();
-                        self.seat.keyboard.key_combination.clear();
-                        self.notifications.deselect();
-                        self.seat.keyboard.repeat.key = None;
-                    }
-                }
-                KeyAction::HintMode => self
-                    .notifications
-                    .ui_state
-                    .mode
-                    .store(keymaps::Mode::Hint, Ordering::Relaxed),
-                KeyAction::Uninhibit => self.notifications.uninhibit(),
-                KeyAction::Ihibit => self.notifications.inhibit(),
-                KeyAction::ToggleInhibit => {
-                    if self.notifications.inhibited() {
-                        self.notifications.uninhibit();
-                    } else {
-                        self.notifications.inhibit();
-                    }
-                }
-                KeyAction::Mute => {
-                    self.audio.mute();
-                }
-                KeyAction::Unmute => {
-                    self.audio.unmute();
-                }
-                KeyAction::ToggleMute => {
-                    if self.audio.muted() {
-                        self.audio.unmute();
-                    } else {
-                        self.audio.mute();
-                    }
-                }
-                KeyAction::NormalMode => {
-                    self.notifications
-                        .ui_state
-                        .mode
-                        .store(keymaps::Mode::Normal, Ordering::Relaxed);
-                }
+            if !self.run(action) {
+                return Ok(());
             }
+        } else if let Some(event) = self.keybind_action() {
+            self.seat.keyboard.key_combination.clear();
+            _ = self.event_sender.send(event);
         } else {
             let combination = self.seat.keyboard.key_combination.to_string();
             if let Some(notification) = self.notifications.selected_notification_mut()
@@ -300,13 +635,7 @@ impl Moxnotify {
         }
 
         self.update_surface_size();
-        if let Some(surface) = self.surface.as_mut() {
-            _ = surface.render(
-                &self.wgpu_state.device,
-                &self.wgpu_state.queue,
-                &self.notifications,
-            );
-        }
+        self.request_render();
 
         Ok(())
     }
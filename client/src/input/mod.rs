@@ -1,9 +1,12 @@
 mod keyboard;
 mod pointer;
+mod text_input;
 
 use crate::Moxnotify;
+use crate::wayland::clipboard::Clipboard;
 use keyboard::Keyboard;
 use pointer::Pointer;
+use text_input::TextInput;
 use wayland_client::{
     Connection, Dispatch, QueueHandle, delegate_noop,
     globals::GlobalList,
@@ -17,6 +20,12 @@ pub struct Seat {
     pointer: Pointer,
     pub keyboard: Keyboard,
     pub xdg_activation: xdg_activation_v1::XdgActivationV1,
+    /// `None` if the compositor has no clipboard
+    pub clipboard: Option<Clipboard>,
+    /// `None` if the compositor has no input method support
+    pub text_input: Option<TextInput>,
+    /// Serial of the last key press or button click, the clipboard is set with it
+    pub serial: u32,
 }
 
 impl Seat {
@@ -24,9 +33,14 @@ impl Seat {
         let wl_seat = globals.bind::<wl_seat::WlSeat, _, _>(qh, 1..=4, ())?;
         let keyboard = Keyboard::new(qh, &wl_seat);
         let pointer = Pointer::new(qh, globals, &wl_seat)?;
+        let clipboard = Clipboard::new(qh, globals, &wl_seat);
+        let text_input = TextInput::new(qh, globals, &wl_seat);
 
         Ok(Self {
             xdg_activation: globals.bind(qh, 1..=1, ())?,
+            clipboard,
+            text_input,
+            serial: 0,
             name: None,
             wl_seat,
             pointer,
@@ -51,3 +65,13 @@ impl Dispatch<wl_seat::WlSeat, ()> for Moxnotify {
 }
 
 delegate_noop!(Moxnotify: ignore wl_shm::WlShm);
+
+impl Moxnotify {
+    /// Enable the input method while the command palette is open
+    pub fn sync_text_input(&mut self) {
+        let wanted = self.notifications.palette_open();
+        if let Some(text_input) = self.seat.text_input.as_mut() {
+            text_input.set_wanted(wanted);
+        }
+    }
+}
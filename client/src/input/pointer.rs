@@ -99,26 +99,14 @@ impl Dispatch<wl_pointer::WlPointer, ()> for Moxnotify {
 
                 let pointer = &state.seat.pointer;
                 if state.notifications.hover(pointer.x, pointer.y) {
-                    if state.seat.pointer.state != PointerState::Hover
-                        && let Some(surface) = state.surface.as_mut()
-                    {
-                        _ = surface.render(
-                            &state.wgpu_state.device,
-                            &state.wgpu_state.queue,
-                            &state.notifications,
-                        );
+                    if state.seat.pointer.state != PointerState::Hover {
+                        state.request_render();
                     }
 
                     state.seat.pointer.change_state(PointerState::Hover);
                 } else {
-                    if state.seat.pointer.state != PointerState::Default
-                        && let Some(surface) = state.surface.as_mut()
-                    {
-                        _ = surface.render(
-                            &state.wgpu_state.device,
-                            &state.wgpu_state.queue,
-                            &state.notifications,
-                        );
+                    if state.seat.pointer.state != PointerState::Default {
+                        state.request_render();
                     }
 
                     state.seat.pointer.change_state(PointerState::Default);
@@ -129,25 +117,13 @@ impl Dispatch<wl_pointer::WlPointer, ()> for Moxnotify {
                         state.update_surface_size();
                         state.notifications.select(new_id);
 
-                        if let Some(surface) = state.surface.as_mut() {
-                            _ = surface.render(
-                                &state.wgpu_state.device,
-                                &state.wgpu_state.queue,
-                                &state.notifications,
-                            );
-                        }
+                        state.request_render();
                     }
                     (Some(new_id), None) => {
                         state.update_surface_size();
                         state.notifications.select(new_id);
 
-                        if let Some(surface) = state.surface.as_mut() {
-                            _ = surface.render(
-                                &state.wgpu_state.device,
-                                &state.wgpu_state.queue,
-                                &state.notifications,
-                            );
-                        }
+                        state.request_render();
                     }
                     (None, Some(_)) => {
                         if let Some(surface) = state.surface.as_ref()
@@ -162,22 +138,19 @@ impl Dispatch<wl_pointer::WlPointer, ()> for Moxnotify {
                             .mode
                             .store(keymaps::Mode::Normal, Ordering::Relaxed);
 
-                        if let Some(surface) = state.surface.as_mut() {
-                            _ = surface.render(
-                                &state.wgpu_state.device,
-                                &state.wgpu_state.queue,
-                                &state.notifications,
-                            );
-                        }
+                        state.request_render();
                     }
                     _ => {}
                 }
             }
             wl_pointer::Event::Button {
+                serial,
                 button,
                 state: WEnum::Value(value),
                 ..
             } => {
+                state.seat.serial = serial;
+
                 if button != LEFT_MOUSE_CLICK {
                     return;
                 }
@@ -190,7 +163,9 @@ impl Dispatch<wl_pointer::WlPointer, ()> for Moxnotify {
                         state.seat.pointer.change_state(PointerState::Default);
 
                         let (x, y) = (state.seat.pointer.x, state.seat.pointer.y);
-                        state.notifications.click(x, y);
+                        if !state.notifications.click(x, y) {
+                            state.focus_sender(x, y);
+                        }
                     }
                     _ => unreachable!(),
                 }
@@ -202,11 +177,7 @@ impl Dispatch<wl_pointer::WlPointer, ()> for Moxnotify {
                     state.seat.pointer.change_state(PointerState::Default);
                     state.notifications.deselect();
                     surface.unfocus();
-                    _ = surface.render(
-                        &state.wgpu_state.device,
-                        &state.wgpu_state.queue,
-                        &state.notifications,
-                    );
+                    surface.request_render();
                 }
             }
             wl_pointer::Event::Enter {
@@ -240,23 +211,11 @@ impl Dispatch<wl_pointer::WlPointer, ()> for Moxnotify {
                         if state.seat.pointer.scroll_accumulator.is_sign_positive() {
                             state.notifications.next();
                             state.update_surface_size();
-                            if let Some(surface) = state.surface.as_mut() {
-                                _ = surface.render(
-                                    &state.wgpu_state.device,
-                                    &state.wgpu_state.queue,
-                                    &state.notifications,
-                                );
-                            }
+                            state.request_render();
                         } else {
                             state.notifications.prev();
                             state.update_surface_size();
-                            if let Some(surface) = state.surface.as_mut() {
-                                _ = surface.render(
-                                    &state.wgpu_state.device,
-                                    &state.wgpu_state.queue,
-                                    &state.notifications,
-                                );
-                            }
+                            state.request_render();
                         }
 
                         state.seat.pointer.scroll_accumulator = 0.0;
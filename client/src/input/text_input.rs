@@ -0,0 +1,116 @@
+use crate::Moxnotify;
+use wayland_client::globals::GlobalList;
+use wayland_client::protocol::wl_seat;
+use wayland_client::{Connection, Dispatch, QueueHandle, delegate_noop};
+use wayland_protocols::wp::text_input::zv3::client::{
+    zwp_text_input_manager_v3, zwp_text_input_v3,
+};
+
+/// Changes the input method sent since the last `done`
+#[derive(Default)]
+struct Pending {
+    commit: Option<String>,
+    preedit: Option<String>,
+}
+
+/// Input method support for the command palette, so e.g. CJK text can be typed
+/// into its query
+pub struct TextInput {
+    text_input: zwp_text_input_v3::ZwpTextInputV3,
+    /// Whether the input method is focused on our surface
+    entered: bool,
+    enabled: bool,
+    pending: Pending,
+}
+
+impl TextInput {
+    /// `None` if the compositor doesn't support zwp_text_input_manager_v3
+    pub fn new(
+        qh: &QueueHandle<Moxnotify>,
+        globals: &GlobalList,
+        wl_seat: &wl_seat::WlSeat,
+    ) -> Option<Self> {
+        let manager: zwp_text_input_manager_v3::ZwpTextInputManagerV3 =
+            globals.bind(qh, 1..=1, ()).ok()?;
+
+        Some(Self {
+            text_input: manager.get_text_input(wl_seat, qh, ()),
+            entered: false,
+            enabled: false,
+            pending: Pending::default(),
+        })
+    }
+
+    /// Enable the input method while something takes text, as long as it's focused
+    /// on our surface
+    pub fn set_wanted(&mut self, wanted: bool) {
+        let enabled = wanted && self.entered;
+        if enabled == self.enabled {
+            return;
+        }
+
+        if enabled {
+            self.text_input.enable();
+            self.text_input.set_content_type(
+                zwp_text_input_v3::ContentHint::None,
+                zwp_text_input_v3::ContentPurpose::Normal,
+            );
+        } else {
+            self.text_input.disable();
+        }
+        self.text_input.commit();
+        self.enabled = enabled;
+    }
+}
+
+impl Dispatch<zwp_text_input_v3::ZwpTextInputV3, ()> for Moxnotify {
+    fn event(
+        state: &mut Self,
+        _: &zwp_text_input_v3::ZwpTextInputV3,
+        event: <zwp_text_input_v3::ZwpTextInputV3 as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let Some(text_input) = state.seat.text_input.as_mut() else {
+            return;
+        };
+
+        match event {
+            zwp_text_input_v3::Event::Enter { .. } => text_input.entered = true,
+            zwp_text_input_v3::Event::Leave { .. } => {
+                text_input.entered = false;
+                text_input.enabled = false;
+                text_input.pending = Pending::default();
+                if let Some(palette) = state.notifications.palette_mut() {
+                    palette.set_preedit(String::new());
+                    state.notifications.update_palette();
+                }
+            }
+            zwp_text_input_v3::Event::PreeditString { text, .. } => {
+                text_input.pending.preedit = text;
+            }
+            zwp_text_input_v3::Event::CommitString { text } => {
+                text_input.pending.commit = text;
+            }
+            zwp_text_input_v3::Event::Done { .. } => {
+                let pending = std::mem::take(&mut text_input.pending);
+                let Some(palette) = state.notifications.palette_mut() else {
+                    return;
+                };
+
+                if let Some(commit) = pending.commit {
+                    commit.chars().for_each(|c| palette.push(c));
+                }
+                // Preedit text that isn't sent again is gone
+                palette.set_preedit(pending.preedit.unwrap_or_default());
+                state.notifications.update_palette();
+                state.update_surface_size();
+                state.request_render();
+            }
+            _ => {}
+        }
+    }
+}
+
+delegate_noop!(Moxnotify: zwp_text_input_manager_v3::ZwpTextInputManagerV3);
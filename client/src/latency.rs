@@ -0,0 +1,77 @@
+use crate::components::notification::NotificationId;
+use crate::moxnotify::types::Latency;
+
+/// Notifications slower than this end to end are logged as warnings
+const SLOW_MS: i64 = 1000;
+/// Averages are logged after this many notifications
+const REPORT_EVERY: u64 = 100;
+
+const HOPS: [&str; 3] = [
+    "collector -> control plane",
+    "control plane -> scheduler",
+    "scheduler -> client",
+];
+
+#[derive(Default, Clone, Copy)]
+struct Hop {
+    sum: i64,
+    max: i64,
+}
+
+/// Aggregates how long notifications spend between services, to tell which hop
+/// makes them show up late. Timestamps come from each service's clock, so hops
+/// between hosts include their clock skew
+#[derive(Default)]
+pub struct LatencyStats {
+    count: u64,
+    hops: [Hop; 3],
+}
+
+impl LatencyStats {
+    pub fn record(&mut self, id: NotificationId, latency: &Latency) {
+        let delays = [
+            latency.ingested_at - latency.collected_at,
+            latency.scheduled_at - latency.ingested_at,
+            latency.displayed_at - latency.scheduled_at,
+        ];
+        let total = latency.displayed_at - latency.collected_at;
+
+        let breakdown = HOPS
+            .iter()
+            .zip(delays)
+            .map(|(hop, delay)| format!("{hop}: {delay}ms"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if total > SLOW_MS {
+            log::warn!("Notification {id} took {total}ms to arrive, {breakdown}");
+        } else {
+            log::debug!("Notification {id} took {total}ms to arrive, {breakdown}");
+        }
+
+        self.count += 1;
+        self.hops.iter_mut().zip(delays).for_each(|(hop, delay)| {
+            hop.sum += delay;
+            hop.max = hop.max.max(delay);
+        });
+
+        if self.count == REPORT_EVERY {
+            let report = HOPS
+                .iter()
+                .zip(self.hops)
+                .map(|(name, hop)| {
+                    format!(
+                        "{name}: avg {}ms max {}ms",
+                        hop.sum / self.count as i64,
+                        hop.max
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            log::info!(
+                "Latency over the last {} notifications, {report}",
+                self.count
+            );
+            *self = Self::default();
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! Fetches what's shown under a notification body for the first link in it
+
+use config::client::LinkPreviews;
+use image::RgbaImage;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Favicons are scaled down to this many pixels on either side
+pub const FAVICON_SIZE: u32 = 16;
+
+pub struct Preview {
+    pub url: Arc<str>,
+    pub title: String,
+    pub favicon: Option<RgbaImage>,
+}
+
+/// Whether `ip` is reachable from anywhere, rather than on this machine or its network
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            // 100.64.0.0/10 is shared by carrier-grade NATs
+            let shared = first == 100 && second & 0xc0 == 64;
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || shared)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local())
+            }
+        },
+    }
+}
+
+/// Whether `url` is something a preview is fetched for, an http(s) link that
+/// doesn't point at an address on this machine or its network. Names are only
+/// checked once resolved, by [`PublicResolver`]
+#[must_use]
+pub fn fetches(url: &str) -> bool {
+    let Ok(url) = reqwest::Url::parse(url) else {
+        return false;
+    };
+    if !matches!(url.scheme(), "http" | "https") {
+        return false;
+    }
+
+    // The host is normalized, so e.g. `http://2130706433/` is caught as 127.0.0.1
+    match url.host_str() {
+        Some(host) => host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .ok()
+            .is_none_or(is_public),
+        None => false,
+    }
+}
+
+/// Links written out in `text` instead of being marked up
+pub fn plain_links(text: &str) -> impl Iterator<Item = &str> {
+    text.split_whitespace()
+        .map(|word| {
+            word.trim_start_matches(['(', '<', '"', '\''])
+                .trim_end_matches(['.', ',', ';', ':', '!', '?', ')', '>', '"', '\''])
+        })
+        .filter(|word| word.starts_with("https://") || word.starts_with("http://"))
+}
+
+/// Resolves names like the system does, leaving out the addresses [`fetches`]
+/// rejects, so a page can't have previews reach services that aren't public
+struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|addr| is_public(addr.ip()))
+                .collect::<Vec<SocketAddr>>();
+            if addrs.is_empty() {
+                return Err(format!("{host} has no public address").into());
+            }
+
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+/// Client previews are fetched with. It doesn't follow redirects, since they could
+/// lead anywhere [`fetches`] rejects
+pub fn client(timeout: Duration) -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .redirect(reqwest::redirect::Policy::none())
+        .dns_resolver(Arc::new(PublicResolver))
+        .build()
+}
+
+/// Read at most `max_size` bytes of the response to `url`
+async fn get(client: &reqwest::Client, url: &str, max_size: usize) -> anyhow::Result<Vec<u8>> {
+    anyhow::ensure!(fetches(url), "Previews aren't fetched for {url}");
+    let mut response = client.get(url).send().await?;
+    // Redirects aren't followed, so they end up here as well
+    anyhow::ensure!(
+        response.status().is_success(),
+        "{url} responded with {}",
+        response.status()
+    );
+
+    let mut body = Vec::new();
+    while body.len() < max_size
+        && let Some(chunk) = response.chunk().await?
+    {
+        body.extend_from_slice(&chunk);
+    }
+    body.truncate(max_size);
+
+    Ok(body)
+}
+
+fn decode_entities(text: &str) -> String {
+    [
+        ("&lt;", "<"),
+        ("&gt;", ">"),
+        ("&quot;", "\""),
+        ("&#39;", "'"),
+        ("&apos;", "'"),
+        ("&nbsp;", " "),
+        ("&amp;", "&"),
+    ]
+    .into_iter()
+    .fold(text.to_string(), |text, (entity, character)| {
+        text.replace(entity, character)
+    })
+}
+
+/// Contents of the page's `<title>`, with whitespace collapsed
+fn title(html: &str) -> Option<String> {
+    let lowercase = html.to_ascii_lowercase();
+    let start = lowercase.find("<title")?;
+    let start = start + lowercase[start..].find('>')? + 1;
+    let end = start + lowercase[start..].find("</title")?;
+
+    let title = decode_entities(&html[start..end])
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    (!title.is_empty()).then_some(title)
+}
+
+/// `/favicon.ico` of the site `url` is on
+fn favicon_url(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once("://")?;
+    let host = rest.split(['/', '?', '#']).next()?;
+
+    Some(format!("{scheme}://{host}/favicon.ico"))
+}
+
+async fn favicon(client: &reqwest::Client, url: &str, max_size: usize) -> Option<RgbaImage> {
+    let data = get(client, &favicon_url(url)?, max_size).await.ok()?;
+    let favicon = image::load_from_memory(&data).ok()?;
+
+    Some(
+        favicon
+            .resize(
+                FAVICON_SIZE,
+                FAVICON_SIZE,
+                image::imageops::FilterType::Triangle,
+            )
+            .into_rgba8(),
+    )
+}
+
+pub async fn fetch(
+    client: &reqwest::Client,
+    url: Arc<str>,
+    config: &LinkPreviews,
+) -> anyhow::Result<Preview> {
+    let page = get(client, &url, config.max_size).await?;
+    let title = title(&String::from_utf8_lossy(&page))
+        .ok_or_else(|| anyhow::anyhow!("Page has no title"))?;
+    let favicon = favicon(client, &url, config.max_size).await;
+
+    Ok(Preview {
+        url,
+        title,
+        favicon,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_title() {
+        assert_eq!(
+            title("<html><head><TITLE lang=\"en\">\n  Tom &amp; Jerry\n</TITLE></head>"),
+            Some("Tom & Jerry".to_string())
+        );
+        assert_eq!(title("<title> </title>"), None);
+        assert_eq!(title("<p>no title</p>"), None);
+        assert_eq!(
+            favicon_url("https://example.com/a/b?c#d"),
+            Some("https://example.com/favicon.ico".to_string())
+        );
+    }
+
+    #[test]
+    fn fetches_public_links_only() {
+        assert!(fetches("https://example.com/a"));
+        assert!(fetches("http://93.184.215.14/"));
+        assert!(!fetches("file:///etc/passwd"));
+        assert!(!fetches("http://127.0.0.1:8080/"));
+        assert!(!fetches("http://2130706433/"));
+        assert!(!fetches("http://192.168.1.1/"));
+        assert!(!fetches("http://169.254.169.254/latest/meta-data/"));
+        assert!(!fetches("http://[::1]/"));
+        assert!(!fetches("http://[::ffff:10.0.0.1]/"));
+        assert!(!fetches("http://[fd00::1]/"));
+    }
+
+    #[test]
+    fn finds_plain_links() {
+        assert_eq!(
+            plain_links("Build failed (https://ci.example.com/42). See http://x.org, or not")
+                .collect::<Vec<_>>(),
+            ["https://ci.example.com/42", "http://x.org"]
+        );
+    }
+}
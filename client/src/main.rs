@@ -13,41 +13,61 @@ mod audio;
 pub mod components;
 pub mod css;
 mod dbus;
+mod feedback;
 mod grpc;
 mod input;
+mod latency;
+mod link_preview;
 mod manager;
+mod preview;
 mod rendering;
+mod searcher;
 pub mod styles;
 pub mod utils;
 mod wayland;
+#[cfg(feature = "x11")]
+mod x11;
 
 use crate::utils::wait;
 use audio::Audio;
 use calloop::EventLoop;
+use calloop::timer::{TimeoutAction, Timer};
 use calloop_wayland_source::WaylandSource;
 use clap::Parser;
 use components::notification::NotificationId;
+use config::client::Calls;
 use config::client::ClientConfig as Config;
 use config::client::keymaps;
-use glyphon::FontSystem;
+use dbus::history_lock::HistoryLock;
+use dbus::sleep::SleepDigest;
 use input::Seat;
+use latency::LatencyStats;
 use manager::NotificationManager;
-use moxnotify::client::{ClientActionInvokedRequest, GetViewportRequest};
+use moxnotify::client::{
+    ClientActionInvokedRequest, ClientNotificationClosedRequest, GetViewportRequest,
+    MuteAppRequest, ViewportNavigationResponse,
+};
 use moxnotify::types::CloseReason;
-use moxnotify::types::{ActionInvoked, NewNotification, Urgency};
+use moxnotify::types::{ActionInvoked, NewNotification, NotificationClosed, Urgency};
+use rendering::RenderState;
+use rendering::glyphon::FontSystem;
 use rendering::surface::{FocusReason, Surface};
-use rendering::wgpu_state;
 use std::cell::RefCell;
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
 use tokio::sync::broadcast;
+use wayland::foreign_toplevel::ForeignToplevels;
+use wayland::shell::Shell;
 use wayland_client::globals::{GlobalList, registry_queue_init};
 use wayland_client::protocol::{wl_compositor, wl_output};
 use wayland_client::{Connection, Dispatch, Proxy, QueueHandle, delegate_noop};
-use wayland_protocols_wlr::layer_shell::v1::client::zwlr_layer_shell_v1;
+
+const SLEEP_DIGEST_DELAY: Duration = Duration::from_secs(10);
 
 #[derive(Debug)]
 pub struct Output {
@@ -69,11 +89,11 @@ impl Output {
 }
 
 pub struct Moxnotify {
-    layer_shell: zwlr_layer_shell_v1::ZwlrLayerShellV1,
+    shell: Shell,
     seat: Seat,
     surface: Option<Surface>,
     outputs: Vec<Output>,
-    wgpu_state: wgpu_state::WgpuState,
+    render_state: RenderState,
     notifications: NotificationManager,
     config: Arc<Config>,
     qh: QueueHandle<Self>,
@@ -82,8 +102,20 @@ pub struct Moxnotify {
     emit_sender: broadcast::Sender<EmitEvent>,
     compositor: wl_compositor::WlCompositor,
     audio: Audio,
+    blinker: feedback::Blinker,
     font_system: Rc<RefCell<FontSystem>>,
     output: Option<Arc<str>>,
+    toplevels: Option<ForeignToplevels>,
+    event_sender: calloop::channel::Sender<Event>,
+    history_lock: HistoryLock,
+    sleep_digest: Option<SleepDigest>,
+    latency: LatencyStats,
+    /// Calls whose sound is repeated until they go away
+    ringing: HashSet<NotificationId>,
+    /// Notifications are inhibited because of `dnd.auto_inhibit`, not by hand
+    auto_inhibited: bool,
+    /// Config file reloaded when it changes, `None` when running on the defaults
+    config_path: Option<PathBuf>,
 }
 
 impl Moxnotify {
@@ -94,13 +126,26 @@ impl Moxnotify {
         loop_handle: calloop::LoopHandle<'static, Self>,
         emit_sender: broadcast::Sender<EmitEvent>,
         event_sender: calloop::channel::Sender<Event>,
+        history_lock: HistoryLock,
         config: Arc<Config>,
     ) -> anyhow::Result<Self> {
-        let layer_shell = globals.bind(&qh, 1..=5, ())?;
+        let shell = Shell::new(&qh, &globals)?;
         let compositor = globals.bind::<wl_compositor::WlCompositor, _, _>(&qh, 1..=6, ())?;
         let seat = Seat::new(&qh, &globals)?;
+        let toplevels =
+            if config.general.focus_sender || !config.general.dnd.auto_inhibit.is_empty() {
+                ForeignToplevels::new(&qh, &globals)
+            } else {
+                None
+            };
 
-        let wgpu_state = wgpu_state::WgpuState::new(conn).await?;
+        #[cfg(all(feature = "gpu", not(feature = "peek")))]
+        let render_state = RenderState::new(conn).await?;
+        #[cfg(feature = "peek")]
+        let render_state = {
+            _ = conn;
+            RenderState::new(&globals, &qh)?
+        };
 
         let font_system = Rc::new(RefCell::new(FontSystem::new()));
 
@@ -108,6 +153,7 @@ impl Moxnotify {
             // TODO: figure out a better way to handle it, Box clone is expensive
             output: config.general.output.clone(),
             audio: Audio::try_new().unwrap(),
+            blinker: feedback::Blinker::spawn(),
             globals,
             qh,
             notifications: NotificationManager::new(
@@ -118,17 +164,187 @@ impl Moxnotify {
             .await,
             font_system,
             config,
-            wgpu_state,
-            layer_shell,
+            render_state,
+            shell,
             seat,
             surface: None,
             outputs: Vec::new(),
             loop_handle,
             emit_sender,
             compositor,
+            toplevels,
+            event_sender,
+            history_lock,
+            sleep_digest: None,
+            latency: LatencyStats::default(),
+            ringing: HashSet::new(),
+            auto_inhibited: false,
+            config_path: None,
         })
     }
 
+    /// Load the config file again and apply it to everything on screen. Timers and
+    /// watchers set up at startup, the log level and translations keep the old settings
+    fn reload_config(&mut self) -> anyhow::Result<()> {
+        let config = Arc::new(config::Config::load(self.config_path.as_deref())?.client);
+        log::info!("Reloaded config");
+
+        // An output picked with moxnotifyctl stays until the config picks another
+        if config.general.output != self.config.general.output {
+            self.output = config.general.output.clone();
+        }
+        self.notifications.set_config(Arc::clone(&config));
+        self.config = config;
+        // Anchor, layer and margins only apply to a new surface
+        self.recreate_surface();
+
+        Ok(())
+    }
+
+    /// Bring the notifications waiting while inhibited to the view
+    fn show_waiting(&mut self) {
+        log::info!(
+            "Showing {} waiting notifications",
+            self.notifications.waiting()
+        );
+        self.notifications.show_waiting();
+        _ = self
+            .emit_sender
+            .send(EmitEvent::WaitingChanged(self.notifications.waiting()));
+    }
+
+    /// Bring the waiting notification `id`, or the oldest one, to the view
+    fn pop_waiting(&mut self, id: Option<NotificationId>) {
+        match self.notifications.pop_waiting(id) {
+            Some(id) => log::info!("Showing waiting notification {id}"),
+            None => {
+                log::debug!("No waiting notification to show");
+                return;
+            }
+        }
+        _ = self
+            .emit_sender
+            .send(EmitEvent::WaitingChanged(self.notifications.waiting()));
+    }
+
+    /// Silence the selected notification's app for `general.mute_app_for`, new
+    /// notifications from it are dropped by the control plane until then
+    fn mute_app(&mut self) {
+        let duration = self.config.general.mute_app_for;
+        let Some(app_name) = self
+            .notifications
+            .selected_notification_mut()
+            .map(|notification| notification.data().app_name.clone())
+        else {
+            return;
+        };
+        if duration.is_zero() || app_name.is_empty() {
+            return;
+        }
+
+        log::info!("Muting '{app_name}' for {}s", duration.as_secs());
+        let mut grpc_client = self.notifications.grpc_client.clone();
+        let request = MuteAppRequest {
+            app_name: app_name.clone(),
+            seconds: duration.as_secs().max(1) as u32,
+        };
+        tokio::spawn(async move {
+            if let Err(e) = grpc_client.mute_app(tonic::Request::new(request)).await {
+                log::error!("Failed to mute app: {}", e.message());
+            }
+        });
+
+        self.notifications
+            .mute(app_name.into(), Instant::now() + duration);
+        if let Err(e) =
+            self.loop_handle
+                .insert_source(Timer::from_duration(duration), |_, (), moxnotify| {
+                    moxnotify.notifications.expire_mutes();
+                    moxnotify.update_surface_size();
+                    moxnotify.request_render();
+                    TimeoutAction::Drop
+                })
+        {
+            log::warn!("Failed to schedule the end of the mute: {e}");
+        }
+    }
+
+    /// Play `path` again every `calls.ring_interval` for as long as call `id` is around
+    fn ring(&mut self, id: NotificationId, path: Arc<Path>) {
+        let interval = self.config.general.calls.ring_interval;
+        if interval.is_zero() || !self.ringing.insert(id) {
+            return;
+        }
+
+        if let Err(e) = self.loop_handle.insert_source(
+            Timer::from_duration(interval),
+            move |_, (), moxnotify| {
+                let inhibited = moxnotify.notifications.inhibited();
+                let Some(notification) = moxnotify
+                    .notifications
+                    .notifications()
+                    .iter()
+                    .find(|notification| notification.id() == id)
+                else {
+                    moxnotify.ringing.remove(&id);
+                    return TimeoutAction::Drop;
+                };
+
+                // Calls from DND exceptions keep ringing while inhibited
+                if (!inhibited || moxnotify.notifications.breaks_through(notification.data()))
+                    && let Err(e) = moxnotify.audio.play(&path)
+                {
+                    log::warn!("Failed to play audio file: {}, {e}", path.display());
+                }
+
+                TimeoutAction::ToDuration(interval)
+            },
+        ) {
+            log::warn!("Failed to ring for call {id}: {e}");
+            self.ringing.remove(&id);
+        }
+    }
+
+    /// Send one notification listing everything held back for the digest
+    fn show_digest(&mut self) {
+        let digest = self.notifications.take_digest();
+        if digest.is_empty() {
+            return;
+        }
+
+        let summary = format!("{} notifications in digest", digest.len());
+        let body = digest
+            .iter()
+            .map(|data| format!("{}: {}", data.display_name(), data.summary))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        log::info!("Showing digest of {} notifications", digest.len());
+        let mut grpc_client = self.notifications.grpc_client.clone();
+        tokio::spawn(async move {
+            if let Err(e) = dbus::notifications::send(&summary, &body).await {
+                log::error!("Failed to send digest: {e}");
+                return;
+            }
+
+            // The scheduler still tracks what the digest held back, it's done with now
+            for data in digest {
+                if let Err(e) = grpc_client
+                    .notification_closed(tonic::Request::new(ClientNotificationClosedRequest {
+                        notification_closed: Some(NotificationClosed {
+                            id: data.id,
+                            reason: CloseReason::ReasonUnknown as i32,
+                            uuid: data.uuid,
+                        }),
+                    }))
+                    .await
+                {
+                    log::error!("Failed to close digested notification {}: {e}", data.id);
+                }
+            }
+        });
+    }
+
     fn handle_app_event(&mut self, event: Event) -> anyhow::Result<()> {
         match event {
             Event::Dismiss { all, id } => {
@@ -151,7 +367,9 @@ impl Moxnotify {
                 }
             }
             Event::InvokeAction { id, key, uuid } => {
-                if let Some(surface) = self.surface.as_ref() {
+                if id == preview::ID {
+                    log::info!("Action invoked on the preview: key: {}", key);
+                } else if let Some(surface) = self.surface.as_ref() {
                     let token = surface.token.as_ref().map(Arc::clone);
 
                     log::info!("Action invoked: id: {}, key: {}", id, key);
@@ -202,21 +420,31 @@ impl Moxnotify {
                     }
                 }
             }
-            Event::Notify(data) => {
+            Event::Notify { mut data, restored } => {
                 log::info!(
-                    "Receiving notification from {}: '{}'",
+                    "Receiving notification from {}: {}",
                     data.app_name,
-                    data.summary
+                    logging::content(&data.summary)
                 );
 
+                // Notifications resent when reconnecting weren't scheduled just now
+                if let Some(latency) = data.latency.as_mut()
+                    && latency.scheduled_at > 0
+                {
+                    latency.displayed_at = config::now_ms();
+                    self.latency.record(data.id, latency);
+                }
+
+                // Restored ones were counted when they first arrived
+                if !restored && let Some(sleep_digest) = self.sleep_digest.as_mut() {
+                    sleep_digest.record(&data);
+                }
+
                 let path = match (
                     data.hints.as_ref().unwrap().sound_file.clone(),
                     data.hints.as_ref().unwrap().sound_name.clone(),
                 ) {
-                    (None, Some(sound_name)) => freedesktop_sound::lookup(&sound_name)
-                        .with_cache()
-                        .find()
-                        .map(std::convert::Into::into),
+                    (None, Some(sound_name)) => audio::lookup(&sound_name),
                     (None, None) => {
                         match Urgency::try_from(data.hints.as_ref().unwrap().urgency).unwrap() {
                             Urgency::Low => self
@@ -248,9 +476,28 @@ impl Moxnotify {
                     }
                 };
 
-                let suppress_sound = data.hints.as_ref().unwrap().suppress_sound;
+                let suppress_sound = data.hints.as_ref().unwrap().suppress_sound
+                    || (restored && self.config.general.restore_silently)
+                    || self.notifications.replaces_quietly(&data);
+                let call = data
+                    .hints
+                    .as_ref()
+                    .is_some_and(|hints| Calls::matches(&hints.tags));
+                let id = data.id;
+                let urgency = data
+                    .hints
+                    .as_ref()
+                    .and_then(|hints| Urgency::try_from(hints.urgency).ok())
+                    .unwrap_or_default();
+                let inhibited =
+                    self.notifications.inhibited() && !self.notifications.breaks_through(&data);
 
                 self.notifications.add(*data);
+                if inhibited {
+                    _ = self
+                        .emit_sender
+                        .send(EmitEvent::WaitingChanged(self.notifications.waiting()));
+                }
 
                 let mut grpc_client = self.notifications.grpc_client.clone();
                 if let Ok(response) = wait(|| async move {
@@ -260,30 +507,136 @@ impl Moxnotify {
                         .unwrap()
                         .into_inner()
                 }) {
-                    self.notifications.notification_view.update(
+                    self.notifications.update_view(
                         response.focused_ids,
                         response.before_count,
                         response.after_count,
                     );
 
+                    // A restarted client selects what it had selected before
                     if let Some(selected_id) = response.selected_id
-                        && self.notifications.selected_id().is_some()
+                        && (self.notifications.selected_id().is_some()
+                            || (restored && response.selecting))
                     {
                         self.notifications.select(selected_id);
                     }
                 }
 
-                if self.notifications.inhibited() || suppress_sound {
+                if inhibited || suppress_sound {
                     log::debug!("Sound suppressed for notification");
-                } else if let Some(path) = path {
-                    log::debug!("Playing notification sound");
-                    if let Err(e) = self.audio.play(&path) {
-                        log::warn!("Failed to play audio file: {}, {e}", path.display());
+                } else {
+                    if self.audio.muted() && self.config.general.feedback.urgency.contains(&urgency)
+                    {
+                        log::debug!("Sounds are muted, blinking instead");
+                        self.blinker.blink(&self.config.general.feedback);
+                    }
+
+                    if let Some(path) = path {
+                        log::debug!("Playing notification sound");
+                        if let Err(e) = self.audio.play(&path) {
+                            log::warn!("Failed to play audio file: {}, {e}", path.display());
+                        }
+
+                        if call {
+                            self.ring(id, path);
+                        }
+                    }
+                }
+            }
+            Event::Preview(data) => {
+                log::info!("Previewing {}", logging::content(&data.summary));
+                self.notifications.preview(*data);
+            }
+            Event::Paste(text) => {
+                let Some(palette) = self.notifications.palette_mut() else {
+                    return Ok(());
+                };
+                // The query is a single line
+                text.chars()
+                    .map(|c| if c.is_whitespace() { ' ' } else { c })
+                    .filter(|c| !c.is_control())
+                    .for_each(|c| palette.push(c));
+                self.notifications.update_palette();
+            }
+            Event::CopyCode { id, code } => {
+                let Some(clipboard) = self.seat.clipboard.as_mut() else {
+                    log::warn!("The compositor has no clipboard to copy the code to");
+                    return Ok(());
+                };
+
+                log::info!("Copying the code of notification {id}");
+                clipboard.copy(&self.qh, code, self.seat.serial);
+                if self.config.general.codes.dismiss {
+                    self.dismiss_with_reason(id, Some(CloseReason::ReasonDismissedByUser));
+                }
+            }
+            Event::MediaControl { player, control } => {
+                tokio::spawn(async move {
+                    if let Err(e) = dbus::mpris::control(&player, control).await {
+                        log::warn!("Failed to {control:?} MPRIS player '{player}': {e}");
                     }
+                });
+            }
+            Event::LinkPreview(id, preview) => {
+                self.notifications.set_link_preview(id, *preview);
+            }
+            Event::Viewport(seq, response) => {
+                self.notifications.reconcile(seq, response);
+            }
+            Event::ReloadConfig => {
+                let error = self.reload_config().err().map(|e| {
+                    log::error!("Failed to reload config, keeping the previous one: {e}");
+                    Arc::from(e.to_string())
+                });
+                _ = self.emit_sender.send(EmitEvent::ConfigReloaded(error));
+            }
+            Event::StyleOverlay(css) => {
+                log::info!("Applying style overlay of {} bytes", css.len());
+                self.notifications.set_style_overlay(&css);
+            }
+            Event::SetHighContrast(enabled) => {
+                if self.notifications.high_contrast() == enabled {
+                    log::debug!(
+                        "High contrast already {}",
+                        if enabled { "on" } else { "off" }
+                    );
+                } else {
+                    log::info!(
+                        "Turning high contrast {}",
+                        if enabled { "on" } else { "off" }
+                    );
+                    self.notifications.set_high_contrast(enabled);
+                }
+            }
+            Event::GetHighContrast => {
+                _ = self
+                    .emit_sender
+                    .send(EmitEvent::HighContrast(self.notifications.high_contrast()));
+
+                return Ok(());
+            }
+            Event::ShowHistory => {
+                if self.history_lock.locked() {
+                    let history_lock = self.history_lock.clone();
+                    let event_sender = self.event_sender.clone();
+                    tokio::spawn(async move {
+                        match history_lock.unlock().await {
+                            Ok(true) => _ = event_sender.send(Event::ShowHistory),
+                            Ok(false) => log::info!("Not authorized, history stays locked"),
+                            Err(e) => log::error!("Failed to unlock history: {e}"),
+                        }
+                    });
+
+                    return Ok(());
+                }
+
+                if !self.notifications.history_visible() {
+                    self.notifications.toggle_history();
                 }
             }
             Event::CloseNotification(id) => {
                 log::info!("Closing notification with id={id}");
+                self.notifications.forget_digested(id);
                 self.dismiss_with_reason(id, None);
             }
             Event::FocusSurface => {
@@ -308,13 +661,25 @@ impl Moxnotify {
                     }
                 }
             }
-            Event::List => {
+            Event::List { full } => {
                 log::info!("Listing all active notifications");
                 let list = self
                     .notifications
                     .notifications()
                     .iter()
-                    .map(|notification| serde_json::to_string(&notification.data()).unwrap())
+                    .map(|notification| {
+                        let mut item = if full {
+                            serde_json::to_value(notification.data())
+                        } else {
+                            serde_json::to_value(notification.data_without_pixels())
+                        }
+                        .unwrap();
+                        if let Some(item) = item.as_object_mut() {
+                            item.insert("age".into(), notification.age().into());
+                            item.insert("remaining".into(), notification.remaining().into());
+                        }
+                        item.to_string()
+                    })
                     .collect::<Vec<_>>();
                 _ = self.emit_sender.send(EmitEvent::List(list));
 
@@ -345,6 +710,7 @@ impl Moxnotify {
                 return Ok(());
             }
             Event::Inhibit => {
+                self.auto_inhibited = false;
                 if self.notifications.inhibited() {
                     log::debug!("Notifications already inhibited");
                 } else {
@@ -356,6 +722,7 @@ impl Moxnotify {
                 }
             }
             Event::Uninhibit => {
+                self.auto_inhibited = false;
                 if self.notifications.inhibited() {
                     log::info!("Uninhibiting notifications");
 
@@ -366,6 +733,9 @@ impl Moxnotify {
                         self.notifications.inhibited(),
                     ));
                     self.notifications.uninhibit();
+                    _ = self
+                        .emit_sender
+                        .send(EmitEvent::WaitingChanged(self.notifications.waiting()));
                 } else {
                     log::debug!("Notifications already uninhibited");
                 }
@@ -395,6 +765,40 @@ impl Moxnotify {
             Event::SetOutput(output) => {
                 log::info!("Setting output to: {output:?}");
                 self.output = output;
+                // Placed anew below, on the new output
+                self.surface = None;
+            }
+            Event::ShowDigest => {
+                self.show_digest();
+                return Ok(());
+            }
+            Event::ShowWaiting => self.show_waiting(),
+            Event::PopWaiting(id) => self.pop_waiting(id),
+            Event::PrepareForSleep(true) => {
+                log::info!("Going to sleep");
+                let now = config::now_ms();
+                self.sleep_digest = Some(SleepDigest::new(now));
+
+                return Ok(());
+            }
+            Event::PrepareForSleep(false) => {
+                log::info!("Resumed from sleep");
+                // Give apps time to deliver what piled up while asleep
+                let timer = Timer::from_duration(SLEEP_DIGEST_DELAY);
+                self.loop_handle
+                    .insert_source(timer, |_, (), moxnotify| {
+                        if let Some(sleep_digest) = moxnotify.sleep_digest.take() {
+                            tokio::spawn(async move {
+                                if let Err(e) = sleep_digest.send().await {
+                                    log::error!("Failed to send sleep digest: {e}");
+                                }
+                            });
+                        }
+                        TimeoutAction::Drop
+                    })
+                    .map_err(|e| anyhow::anyhow!("Failed to insert source: {e}"))?;
+
+                return Ok(());
             }
             Event::ShowOutput => {
                 log::debug!("Getting current output");
@@ -404,17 +808,13 @@ impl Moxnotify {
                         .map(Arc::clone)
                         .unwrap_or("auto".into()),
                 ));
+
+                return Ok(());
             }
         }
 
         self.update_surface_size();
-        if let Some(surface) = self.surface.as_mut() {
-            surface.render(
-                &self.wgpu_state.device,
-                &self.wgpu_state.queue,
-                &self.notifications,
-            )?;
-        }
+        self.request_render();
         Ok(())
     }
 }
@@ -422,6 +822,8 @@ impl Moxnotify {
 #[derive(Clone)]
 pub enum EmitEvent {
     Waiting(usize),
+    /// Notifications were queued while inhibited or shown, with how many are left
+    WaitingChanged(usize),
     Open {
         uri: Arc<str>,
         token: Option<Arc<str>>,
@@ -431,7 +833,10 @@ pub enum EmitEvent {
     InhibitStateChanged(bool),
     Muted(bool),
     Inhibited(bool),
+    HighContrast(bool),
     ShowOutput(Arc<str>),
+    /// The config was reloaded, with why it failed if it did
+    ConfigReloaded(Option<Arc<str>>),
 }
 
 #[derive(Debug)]
@@ -447,9 +852,49 @@ pub enum Event {
         uuid: String,
     },
     InvokeAnchor(Arc<str>),
-    Notify(Box<NewNotification>),
+    /// Text read from the selection, typed into the command palette
+    Paste(String),
+    /// Copy a one-time code found in a notification to the clipboard
+    CopyCode {
+        id: NotificationId,
+        code: Arc<str>,
+    },
+    MediaControl {
+        player: Arc<str>,
+        control: dbus::mpris::Control,
+    },
+    Notify {
+        data: Box<NewNotification>,
+        /// Already active when the client connected, restored without a sound
+        restored: bool,
+    },
+    /// Synthetic notification shown without going through the scheduler
+    Preview(Box<NewNotification>),
+    /// Title and favicon fetched for the first link in a notification's body
+    LinkPreview(NotificationId, Box<link_preview::Preview>),
+    /// The scheduler's answer to a navigation, numbered in the order they were sent
+    Viewport(u64, ViewportNavigationResponse),
+    /// Css layered over the configured styles, replacing the previous overlay
+    StyleOverlay(String),
+    /// Load the config file again, answered with [`EmitEvent::ConfigReloaded`]
+    ReloadConfig,
+    /// Switch the high contrast styles on or off, over whatever the theme is
+    SetHighContrast(bool),
+    GetHighContrast,
+    /// Expand the history of the selected notification, gated by [`HistoryLock`]
+    ShowHistory,
+    ShowDigest,
+    /// Show the notifications waiting while inhibited, staying inhibited
+    ShowWaiting,
+    /// Show one waiting notification, the oldest one without an ID
+    PopWaiting(Option<NotificationId>),
+    /// logind is about to suspend the system (`true`) or has resumed it (`false`)
+    PrepareForSleep(bool),
     CloseNotification(u32),
-    List,
+    /// List the notifications on screen, with the pixels of inline images if `full`
+    List {
+        full: bool,
+    },
     FocusSurface,
     Mute,
     Unmute,
@@ -481,13 +926,32 @@ impl Dispatch<wl_output::WlOutput, ()> for Moxnotify {
         match event {
             wl_output::Event::Scale { factor } => output.scale = factor as f32,
             wl_output::Event::Name { name } => output.name = Some(name.into()),
+            wl_output::Event::Done => {
+                let (id, scale) = (output.id, output.scale);
+                let Some(surface) = state.surface.as_ref() else {
+                    return;
+                };
+
+                // The configured output came back, move notifications back to it
+                if output.name.is_some()
+                    && output.name == state.output
+                    && surface.output != Some(id)
+                {
+                    log::info!(
+                        "Output {} is available, moving notifications to it",
+                        output.name.as_deref().unwrap_or_default()
+                    );
+                    state.recreate_surface();
+                } else if surface.output == Some(id) {
+                    state.set_scale(scale);
+                }
+            }
             _ => {}
         }
     }
 }
 
 delegate_noop!(Moxnotify: wl_compositor::WlCompositor);
-delegate_noop!(Moxnotify: zwlr_layer_shell_v1::ZwlrLayerShellV1);
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -496,6 +960,9 @@ struct Cli {
     config: Option<Box<Path>>,
 }
 
+/// How often the config file is checked for changes
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
@@ -506,16 +973,37 @@ async fn main() -> anyhow::Result<()> {
             println!("Failed to load config, using default configuration: {err}");
             config::Config::default()
         });
-    env_logger::Builder::new()
-        .filter(Some("client"), config.client.log_level.into())
-        .init();
+    logging::init("client", config.client.log_level.into());
+    logging::set_log_content(config.log_content);
+    i18n::init(config.locale.as_deref(), &config.translations);
+    let config_path = config::Config::path(cli.config.as_deref()).unwrap_or_else(|err| {
+        log::warn!("Failed to find the config file, it won't be reloaded: {err}");
+        None
+    });
+
+    let x11 =
+        std::env::var_os("WAYLAND_DISPLAY").is_none() && std::env::var_os("DISPLAY").is_some();
+    #[cfg(feature = "x11")]
+    if x11 {
+        log::info!("WAYLAND_DISPLAY isn't set, showing notifications on X11");
+        return x11::run(config).await;
+    }
 
-    let conn = Connection::connect_to_env().expect("Failed to connect to Wayland");
+    let conn = match Connection::connect_to_env() {
+        Ok(conn) => conn,
+        Err(e) if x11 => {
+            anyhow::bail!(
+                "Failed to connect to Wayland: {e}. Build the client with the x11 feature to use it on X11, or view notifications with moxnotify-tui"
+            );
+        }
+        Err(e) => anyhow::bail!("Failed to connect to Wayland: {e}"),
+    };
     let (globals, event_queue) = registry_queue_init(&conn)?;
     let qh = event_queue.handle();
 
     let (emit_sender, _emit_receiver) = broadcast::channel(std::mem::size_of::<EmitEvent>());
     let (event_sender, event_receiver) = calloop::channel::channel();
+    let history_lock = HistoryLock::default();
     let mut event_loop = EventLoop::try_new()?;
     let mut moxnotify = Moxnotify::new(
         &conn,
@@ -524,6 +1012,7 @@ async fn main() -> anyhow::Result<()> {
         event_loop.handle(),
         emit_sender.clone(),
         event_sender.clone(),
+        history_lock.clone(),
         Arc::new(config.client),
     )
     .await?;
@@ -560,9 +1049,99 @@ async fn main() -> anyhow::Result<()> {
         })?;
     }
 
+    let lock_after = moxnotify.config.general.history.lock_after;
+    if !lock_after.is_zero() {
+        let history_lock = history_lock.clone();
+        scheduler.schedule(async move {
+            if let Err(e) = history_lock.watch(lock_after).await {
+                log::error!("Failed to watch the session, history won't be locked: {e}");
+            }
+        })?;
+    }
+
+    moxnotify.config_path = config_path;
+    if let Some(path) = moxnotify.config_path.clone() {
+        let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        let mut applied = modified(&path);
+        let event_sender = event_sender.clone();
+        event_loop
+            .handle()
+            .insert_source(
+                Timer::from_duration(CONFIG_POLL_INTERVAL),
+                move |_, (), _| {
+                    // Editors that save by renaming leave no file behind for a moment
+                    if let Some(modified) = modified(&path)
+                        && applied != Some(modified)
+                    {
+                        applied = Some(modified);
+                        _ = event_sender.send(Event::ReloadConfig);
+                    }
+                    TimeoutAction::ToDuration(CONFIG_POLL_INTERVAL)
+                },
+            )
+            .map_err(|e| anyhow::anyhow!("Failed to insert source: {e}"))?;
+    }
+
+    let digest = &moxnotify.config.general.digest;
+    if !digest.urgency.is_empty() && !digest.interval.is_zero() {
+        let interval = digest.interval;
+        event_loop
+            .handle()
+            .insert_source(Timer::from_duration(interval), move |_, (), moxnotify| {
+                moxnotify.show_digest();
+                TimeoutAction::ToDuration(interval)
+            })
+            .map_err(|e| anyhow::anyhow!("Failed to insert source: {e}"))?;
+    }
+
+    let timestamps = &moxnotify.config.general.timestamps;
+    if timestamps.enabled && !timestamps.update_interval.is_zero() {
+        let interval = timestamps.update_interval;
+        event_loop
+            .handle()
+            .insert_source(Timer::from_duration(interval), move |_, (), moxnotify| {
+                if !moxnotify.notifications.notifications().is_empty() {
+                    moxnotify.notifications.update_timestamps();
+                    moxnotify.request_render();
+                }
+                TimeoutAction::ToDuration(interval)
+            })
+            .map_err(|e| anyhow::anyhow!("Failed to insert source: {e}"))?;
+    }
+
+    if moxnotify.config.general.sleep_digest {
+        let event_sender = event_sender.clone();
+        scheduler.schedule(async move {
+            if let Err(e) = dbus::sleep::watch(event_sender).await {
+                log::error!("Failed to watch for sleep, no digest will be shown: {e}");
+            }
+        })?;
+    }
+
+    if moxnotify.config.general.media.enabled {
+        let players = Arc::clone(&moxnotify.notifications.ui_state.players);
+        scheduler.schedule(async move {
+            if let Err(e) = dbus::mpris::watch(players).await {
+                log::error!("Failed to watch for MPRIS players, media won't be controllable: {e}");
+            }
+        })?;
+    }
+
+    let reduced_motion = Arc::clone(&moxnotify.notifications.ui_state.reduced_motion);
+    scheduler.schedule(async move {
+        if let Err(e) = dbus::portal::settings::watch_reduced_motion(reduced_motion).await {
+            log::warn!("Failed to read the desktop's reduced motion preference: {e}");
+        }
+    })?;
+
     let emit_receiver = emit_sender.subscribe();
+    let searcher = searcher::Searcher::new(&config.searcher)
+        .inspect_err(|e| log::error!("Failed to set up history access over D-Bus: {e}"))
+        .ok();
     scheduler.schedule(async move {
-        if let Err(e) = dbus::moxnotify::serve(event_sender, emit_receiver).await {
+        if let Err(e) =
+            dbus::moxnotify::serve(event_sender, emit_receiver, history_lock, searcher).await
+        {
             log::error!("{e}");
         }
     })?;
@@ -590,7 +1169,7 @@ async fn main() -> anyhow::Result<()> {
         })
         .map_err(|e| anyhow::anyhow!("Failed to insert source: {e}"))?;
 
-    event_loop.run(None, &mut moxnotify, |_| {})?;
+    event_loop.run(None, &mut moxnotify, Moxnotify::flush_render)?;
 
     Ok(())
 }
@@ -1,39 +1,63 @@
+mod navigation;
+pub mod palette;
 mod view;
 
 use crate::components::notification;
 use crate::components::notification::{Notification, NotificationId};
 use crate::components::{Component, Data};
-use crate::css::parse_css;
-use crate::styles::Styles;
-use config::client::{ClientConfig as Config, keymaps};
+use crate::css::{parse_css, parse_css_with_overrides};
+use crate::dbus::notifications;
+use crate::link_preview::{self, Preview};
 use crate::moxnotify::client::client_service_client::ClientServiceClient;
 use crate::moxnotify::client::viewport_navigation_request::Direction;
 use crate::moxnotify::client::{
-    ClientNotificationClosedRequest, GetViewportRequest, RestartTimersRequest, StopTimersRequest,
-    ViewportNavigationRequest,
+    ClientNotificationClosedRequest, GetViewportRequest, ViewportNavigationResponse,
 };
-use crate::moxnotify::types::{NewNotification, NotificationClosed};
+use crate::moxnotify::types::{NewNotification, NotificationClosed, Urgency};
+use crate::preview;
+use crate::rendering::glyphon::{FontSystem, TextArea};
+use crate::rendering::moxui::{shape_renderer, texture_renderer};
+use crate::styles::Styles;
 use crate::utils::wait;
 use crate::{CloseReason, Moxnotify};
 use atomic_float::AtomicF32;
-use glyphon::{FontSystem, TextArea};
-use moxui::{shape_renderer, texture_renderer};
+use chrono::Timelike;
+use config::client::{ClientConfig as Config, keymaps};
+use config::keys::stream_position;
+use config::types::Compression;
+use navigation::Navigation;
+use palette::{Command, Entry, Palette};
 use std::cell::RefCell;
 use std::collections::VecDeque;
 use std::fmt;
 use std::ops::RangeBounds;
 use std::rc::Rc;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use tonic::codec::CompressionEncoding;
 use tonic::transport::Channel;
 use view::NotificationView;
 
+/// Bursts smaller than this are shaped on the main thread, spawning workers
+/// and cloning the font database costs more than it saves.
+const PARALLEL_SHAPING_THRESHOLD: usize = 8;
+
+/// Large fonts, maximum contrast colors and thick borders for low vision
+const HIGH_CONTRAST: &str = include_str!("../high_contrast.css");
+
 #[derive(Clone)]
 pub struct UiState {
     pub scale: Arc<AtomicF32>,
     pub mode: Arc<keymaps::AtomicMode>,
     pub selected: Arc<AtomicBool>,
     pub selected_id: Arc<AtomicU32>,
+    /// Name of the active keyboard layout, picks the hint characters
+    pub layout: Arc<RwLock<Option<Box<str>>>>,
+    /// MPRIS players on the session bus, media notifications control them
+    pub players: Arc<RwLock<Vec<Box<str>>>>,
+    /// The desktop asks for reduced motion through the settings portal
+    pub reduced_motion: Arc<AtomicBool>,
 }
 
 impl Default for UiState {
@@ -43,6 +67,9 @@ impl Default for UiState {
             scale: Arc::new(AtomicF32::new(1.0)),
             selected: Arc::new(AtomicBool::new(false)),
             selected_id: Arc::new(AtomicU32::new(0)),
+            layout: Arc::new(RwLock::new(None)),
+            players: Arc::new(RwLock::new(Vec::new())),
+            reduced_motion: Arc::new(AtomicBool::new(false)),
         }
     }
 }
@@ -50,12 +77,24 @@ impl Default for UiState {
 pub struct NotificationManager {
     notifications: VecDeque<Notification>,
     waiting: Vec<NewNotification>,
+    digest: Vec<NewNotification>,
+    /// Apps muted from this client and when their mute ends
+    muted: Vec<(Box<str>, Instant)>,
     config: Arc<Config>,
     styles: Arc<Styles>,
+    /// Css layered over the configured styles
+    style_overlay: String,
+    /// [`HIGH_CONTRAST`] is layered over everything else
+    high_contrast: bool,
     sender: calloop::channel::Sender<crate::Event>,
     inhibited: bool,
     font_system: Rc<RefCell<FontSystem>>,
+    /// Fetches link previews, `None` unless they're enabled
+    http: Option<reqwest::Client>,
     pub grpc_client: ClientServiceClient<Channel>,
+    navigation: Navigation,
+    /// Open while in [`keymaps::Mode::Palette`]
+    palette: Option<Palette>,
     pub notification_view: NotificationView,
     pub ui_state: UiState,
 }
@@ -70,16 +109,36 @@ impl NotificationManager {
 
         log::info!("Connecting to scheduler at: {}", scheduler_addr);
 
-        let client = ClientServiceClient::connect(scheduler_addr).await.unwrap();
+        let mut client = ClientServiceClient::connect(scheduler_addr)
+            .await
+            .unwrap()
+            .accept_compressed(CompressionEncoding::Zstd);
+        if config.compression == Compression::Zstd {
+            client = client.send_compressed(CompressionEncoding::Zstd);
+        }
 
         let ui_state = UiState::default();
         let styles = Arc::new(parse_css(&config.css));
+        let http = config
+            .general
+            .link_previews
+            .enabled
+            .then(|| {
+                link_preview::client(config.general.link_previews.timeout)
+                    .inspect_err(|e| log::error!("Failed to set up link previews: {e}"))
+                    .ok()
+            })
+            .flatten();
 
         Self {
+            navigation: Navigation::new(client.clone(), sender.clone()),
             grpc_client: client,
+            palette: None,
             sender,
             inhibited: false,
             waiting: Vec::new(),
+            digest: Vec::new(),
+            muted: Vec::new(),
             notification_view: NotificationView::new(
                 Arc::clone(&config),
                 Arc::clone(&styles),
@@ -87,9 +146,12 @@ impl NotificationManager {
                 Rc::clone(&font_system),
             ),
             font_system,
+            http,
             notifications: VecDeque::new(),
             config,
             styles,
+            style_overlay: String::new(),
+            high_contrast: false,
             ui_state,
         }
     }
@@ -97,6 +159,7 @@ impl NotificationManager {
     /// Inhibit notifications
     pub fn inhibit(&mut self) {
         self.inhibited = true;
+        self.update_waiting_badge();
     }
 
     /// Stop inhibiting notifications and bring any inhibited
@@ -105,6 +168,61 @@ impl NotificationManager {
         let drained: Vec<_> = self.waiting.drain(..).collect();
         self.add_many(drained);
         self.inhibited = false;
+        self.update_waiting_badge();
+    }
+
+    /// Bring the notifications waiting while inhibited to the view, new ones keep waiting
+    pub fn show_waiting(&mut self) {
+        let drained: Vec<_> = self.waiting.drain(..).collect();
+        self.add_many(drained);
+        self.update_waiting_badge();
+    }
+
+    /// Bring only the waiting notification `id` to the view, or the oldest one without
+    /// an `id`. Returns the ID of the shown notification, if there was one
+    pub fn pop_waiting(&mut self, id: Option<NotificationId>) -> Option<NotificationId> {
+        let index = match id {
+            Some(id) => self.waiting.iter().position(|data| data.id == id)?,
+            None if self.waiting.is_empty() => return None,
+            None => 0,
+        };
+
+        let data = self.waiting.remove(index);
+        let id = data.id;
+        self.add_many(vec![data]);
+        self.update_waiting_badge();
+
+        Some(id)
+    }
+
+    /// Count the waiting notifications in the badge, only shown while inhibited
+    fn update_waiting_badge(&mut self) {
+        let count = if self.inhibited && self.config.general.dnd.badge {
+            self.waiting.len() as u32
+        } else {
+            0
+        };
+
+        self.notification_view.set_waiting(count);
+    }
+
+    /// Show `app_name` as muted in the badge until `until`
+    pub fn mute(&mut self, app_name: Box<str>, until: Instant) {
+        self.muted.retain(|(muted, _)| *muted != app_name);
+        self.muted.push((app_name, until));
+        self.update_muted_badge();
+    }
+
+    /// Stop showing the apps whose mute ended
+    pub fn expire_mutes(&mut self) {
+        let now = Instant::now();
+        self.muted.retain(|(_, until)| *until > now);
+        self.update_muted_badge();
+    }
+
+    fn update_muted_badge(&mut self) {
+        let apps = self.muted.iter().map(|(app, _)| app.clone()).collect();
+        self.notification_view.set_muted(apps);
     }
 
     pub fn inhibited(&mut self) -> bool {
@@ -140,6 +258,16 @@ impl NotificationManager {
                 notification.get_render_bounds().x + notification.get_render_bounds().width
             })
             .max_by(|a, b| a.partial_cmp(b).unwrap())
+            .or_else(|| {
+                self.notification_view
+                    .waiting_bounds()
+                    .map(|bounds| bounds.width)
+            })
+            .or_else(|| {
+                self.notification_view
+                    .palette_bounds()
+                    .map(|bounds| bounds.width)
+            })
             .unwrap_or_default();
 
         if let Some((instance, text_area)) = self.notification_view.prev_data(total_width) {
@@ -152,6 +280,16 @@ impl NotificationManager {
             text_areas.push(text_area);
         }
 
+        if let Some((instance, text_area)) = self.notification_view.waiting_data(total_width) {
+            instances.push(instance);
+            text_areas.push(text_area);
+        }
+
+        if let Some((instance, text_area)) = self.notification_view.palette_data(total_width) {
+            instances.push(instance);
+            text_areas.push(text_area);
+        }
+
         (instances, text_areas, textures)
     }
 
@@ -166,6 +304,18 @@ impl NotificationManager {
     }
 
     pub fn click(&mut self, x: f64, y: f64) -> bool {
+        if let Some(bounds) = self.notification_view.waiting_bounds()
+            && x >= bounds.x as f64
+            && x <= (bounds.x + bounds.width) as f64
+            && y >= bounds.y as f64
+            && y <= (bounds.y + bounds.height) as f64
+        {
+            if let Err(e) = self.sender.send(crate::Event::ShowWaiting) {
+                log::error!("{e}");
+            }
+            return true;
+        }
+
         self.iter_viewed_mut().any(|notification| {
             notification
                 .buttons_mut()
@@ -200,7 +350,19 @@ impl NotificationManager {
             .map(|b| b.height)
             .unwrap_or(0.0);
 
-        prev_height + notification_height + next_height
+        let waiting_height = self
+            .notification_view
+            .waiting_bounds()
+            .map(|b| b.height)
+            .unwrap_or(0.0);
+
+        let palette_height = self
+            .notification_view
+            .palette_bounds()
+            .map(|b| b.height)
+            .unwrap_or(0.0);
+
+        prev_height + notification_height + next_height + waiting_height + palette_height
     }
 
     pub fn width(&self) -> f32 {
@@ -216,7 +378,13 @@ impl NotificationManager {
                 });
 
         if min_x == f32::MAX || max_x == f32::MIN {
-            0.0
+            // Nothing but the badge is left while notifications wait
+            self.notification_view
+                .waiting_bounds()
+                .into_iter()
+                .chain(self.notification_view.palette_bounds())
+                .map(|bounds| bounds.width)
+                .fold(0.0, f32::max)
         } else {
             max_x - min_x
         }
@@ -239,6 +407,136 @@ impl NotificationManager {
             .find(|notification| Some(notification.id()) == id)
     }
 
+    /// Toggle the debug inspector of the selected notification
+    pub fn toggle_inspector(&mut self) {
+        let font_system = Rc::clone(&self.font_system);
+        let Some(notification) = self.selected_notification_mut() else {
+            return;
+        };
+
+        notification.toggle_inspector(&mut font_system.borrow_mut());
+        self.update_size();
+    }
+
+    /// Open the command palette with what can be done right now
+    pub fn open_palette(&mut self) {
+        let mut entries = vec![
+            Entry::new(i18n::tr!("palette-dismiss-all"), Command::DismissAll),
+            Entry::new(
+                i18n::tr!("palette-toggle-dnd"),
+                Command::Key(keymaps::KeyAction::ToggleInhibit),
+            ),
+            Entry::new(
+                i18n::tr!("palette-toggle-sound"),
+                Command::Key(keymaps::KeyAction::ToggleMute),
+            ),
+            Entry::new(
+                i18n::tr!("palette-high-contrast"),
+                Command::ToggleHighContrast,
+            ),
+        ];
+        if !self.waiting.is_empty() {
+            entries.push(Entry::new(
+                i18n::tr!("palette-show-waiting"),
+                Command::Key(keymaps::KeyAction::ShowWaiting),
+            ));
+        }
+
+        let id = self.selected_id();
+        if let Some(notification) = self
+            .notifications
+            .iter()
+            .find(|notification| Some(notification.id()) == id)
+        {
+            let data = notification.data();
+            entries.extend(data.actions.iter().map(|action| {
+                Entry::new(
+                    i18n::tr!("palette-invoke", action = action.label.clone()),
+                    Command::Invoke {
+                        id: data.id,
+                        key: action.key.clone(),
+                        uuid: notification.uuid(),
+                    },
+                )
+            }));
+            entries.push(Entry::new(
+                i18n::tr!("palette-history"),
+                Command::Key(keymaps::KeyAction::ToggleHistory),
+            ));
+            entries.push(Entry::new(
+                i18n::tr!("palette-mute-app", app = data.display_name().to_string()),
+                Command::Key(keymaps::KeyAction::MuteApp),
+            ));
+            entries.push(Entry::new(
+                i18n::tr!("palette-inspector"),
+                Command::Key(keymaps::KeyAction::ToggleInspector),
+            ));
+        }
+
+        self.palette = Some(Palette::new(entries));
+        self.ui_state
+            .mode
+            .store(keymaps::Mode::Palette, Ordering::Relaxed);
+        self.update_palette();
+    }
+
+    pub fn close_palette(&mut self) {
+        self.palette = None;
+        self.ui_state
+            .mode
+            .store(keymaps::Mode::Normal, Ordering::Relaxed);
+        self.update_palette();
+    }
+
+    #[must_use]
+    pub fn palette_open(&self) -> bool {
+        self.palette.is_some()
+    }
+
+    pub fn palette_mut(&mut self) -> Option<&mut Palette> {
+        self.palette.as_mut()
+    }
+
+    /// Show the palette as it is now, after its query or selection changed
+    pub fn update_palette(&mut self) {
+        self.notification_view
+            .set_palette(self.palette.as_ref().map(Palette::text));
+        self.update_size();
+    }
+
+    /// Bring the relative times of all notifications up to date
+    pub fn update_timestamps(&mut self) {
+        let mut font_system = self.font_system.borrow_mut();
+        self.notifications
+            .iter_mut()
+            .for_each(|notification| notification.update_timestamp(&mut font_system));
+    }
+
+    /// Whether anything in view changes from frame to frame
+    #[must_use]
+    pub fn animating(&self) -> bool {
+        self.iter_viewed().any(Notification::animating)
+    }
+
+    #[must_use]
+    pub fn history_visible(&self) -> bool {
+        let id = self.selected_id();
+        self.notifications
+            .iter()
+            .find(|notification| Some(notification.id()) == id)
+            .is_some_and(Notification::history_visible)
+    }
+
+    pub fn toggle_history(&mut self) {
+        let font_system = Rc::clone(&self.font_system);
+        let Some(notification) = self.selected_notification_mut() else {
+            return;
+        };
+
+        notification.toggle_history(&mut font_system.borrow_mut());
+        self.update_size();
+    }
+
     pub fn select(&mut self, id: NotificationId) {
         let Some(new_index) = self.notifications.iter().position(|n| n.id() == id) else {
             return;
@@ -253,14 +551,7 @@ impl NotificationManager {
 
         self.ui_state.selected_id.store(id, Ordering::Relaxed);
         self.ui_state.selected.store(true, Ordering::Relaxed);
-
-        let mut grpc_client = self.grpc_client.clone();
-        _ = wait(|| async move {
-            grpc_client
-                .stop_timers(tonic::Request::new(StopTimersRequest {}))
-                .await
-                .unwrap();
-        });
+        self.navigation.stop_timers();
 
         self.update_size();
     }
@@ -281,110 +572,102 @@ impl NotificationManager {
     }
 
     pub fn start_timers_for_visible(&mut self) {
-        let mut grpc_client = self.grpc_client.clone();
-        _ = wait(|| async move {
-            grpc_client
-                .restart_timers(tonic::Request::new(RestartTimersRequest {}))
-                .await
-                .unwrap();
-        });
+        self.navigation.restart_timers();
     }
 
-    /// Select next notification
-    pub fn next(&mut self) {
-        let mut grpc_client = self.grpc_client.clone();
-        if let Ok(response) = wait(|| async move {
-            grpc_client
-                .navigate_viewport(tonic::Request::new(ViewportNavigationRequest {
-                    direction: Direction::Next as i32,
-                }))
-                .await
-                .unwrap()
-                .into_inner()
-        }) {
-            if let Some(selected_id) = response.selected_id {
-                self.select(selected_id);
-            }
+    /// Move the selection and viewport in `direction` right away, predicting where
+    /// the scheduler moves them. Its answer corrects the prediction in [`Self::reconcile`]
+    fn navigate(&mut self, direction: Direction) {
+        // The order the scheduler lists them in
+        let mut order: Vec<_> = self
+            .notifications
+            .iter()
+            .map(|notification| {
+                let data = notification.data();
+                (
+                    stream_position(&data.stream_id),
+                    data.timestamp,
+                    notification.id(),
+                )
+            })
+            .collect();
+        order.sort_by(|a, b| b.cmp(a));
+        let ids: Vec<_> = order.into_iter().map(|(_, _, id)| id).collect();
 
-            self.notification_view.update(
-                response.focused_ids,
-                response.before_count,
-                response.after_count,
-            );
+        self.navigation.navigate(direction);
+        if ids.is_empty() {
+            return;
         }
-    }
 
-    /// Select previous notification
-    pub fn prev(&mut self) {
-        let mut grpc_client = self.grpc_client.clone();
-
-        if let Ok(response) = wait(|| async move {
-            grpc_client
-                .navigate_viewport(tonic::Request::new(ViewportNavigationRequest {
-                    direction: Direction::Prev as i32,
-                }))
-                .await
-                .unwrap()
-                .into_inner()
-        }) {
-            if let Some(selected_id) = response.selected_id {
-                self.select(selected_id);
-            }
+        let selected_id = self.ui_state.selected_id.load(Ordering::Relaxed);
+        let selected = ids.iter().position(|&id| id == selected_id);
+        let width = self.config.general.max_visible.min(ids.len());
+        let start = ids
+            .iter()
+            .position(|id| self.notification_view.visible.contains(id))
+            .unwrap_or(ids.len() - width);
+        let (index, window) =
+            navigation::predict(direction, ids.len(), selected, start..start + width);
+
+        self.select(ids[index]);
+        self.update_view(
+            ids[window.clone()].to_vec(),
+            (ids.len() - window.end) as u32,
+            window.start as u32,
+        );
+        self.update_size();
+    }
 
-            self.notification_view.update(
-                response.focused_ids,
-                response.before_count,
-                response.after_count,
-            );
+    /// Show `visible` with `prev` and `next` hidden around it. A preview stays in view,
+    /// the scheduler leaves it out since it doesn't know about it
+    pub fn update_view(&mut self, mut visible: Vec<u32>, prev: u32, next: u32) {
+        if !visible.contains(&preview::ID)
+            && self.notifications.iter().any(|n| n.id() == preview::ID)
+        {
+            visible.push(preview::ID);
         }
+
+        self.notification_view.update(visible, prev, next);
     }
 
-    pub fn first(&mut self) {
-        let mut grpc_client = self.grpc_client.clone();
-
-        if let Ok(response) = wait(|| async move {
-            grpc_client
-                .navigate_viewport(tonic::Request::new(ViewportNavigationRequest {
-                    direction: Direction::First as i32,
-                }))
-                .await
-                .unwrap()
-                .into_inner()
-        }) {
-            if let Some(selected_id) = response.selected_id {
-                self.select(selected_id);
-            }
+    /// Apply the scheduler's answer to navigation `seq`, unless the user navigated
+    /// again since and another answer is on its way
+    pub fn reconcile(&mut self, seq: u64, response: ViewportNavigationResponse) {
+        if !self.navigation.is_latest(seq) {
+            return;
+        }
 
-            self.notification_view.update(
-                response.focused_ids,
-                response.before_count,
-                response.after_count,
-            );
+        if let Some(selected_id) = response.selected_id
+            && self.selected_id() != Some(selected_id)
+        {
+            log::debug!("Scheduler selected id={selected_id} instead");
+            self.select(selected_id);
         }
+
+        self.update_view(
+            response.focused_ids,
+            response.before_count,
+            response.after_count,
+        );
+        self.update_size();
     }
 
-    pub fn last(&mut self) {
-        let mut grpc_client = self.grpc_client.clone();
-
-        if let Ok(response) = wait(|| async move {
-            grpc_client
-                .navigate_viewport(tonic::Request::new(ViewportNavigationRequest {
-                    direction: Direction::Last as i32,
-                }))
-                .await
-                .unwrap()
-                .into_inner()
-        }) {
-            if let Some(selected_id) = response.selected_id {
-                self.select(selected_id);
-            }
+    /// Select next notification
+    pub fn next(&mut self) {
+        self.navigate(Direction::Next);
+    }
 
-            self.notification_view.update(
-                response.focused_ids,
-                response.before_count,
-                response.after_count,
-            );
-        }
+    /// Select previous notification
+    pub fn prev(&mut self) {
+        self.navigate(Direction::Prev);
+    }
+
+    pub fn first(&mut self) {
+        self.navigate(Direction::First);
+    }
+
+    pub fn last(&mut self) {
+        self.navigate(Direction::Last);
     }
 
     pub fn waiting(&self) -> usize {
@@ -392,7 +675,7 @@ impl NotificationManager {
     }
 
     pub fn add_many(&mut self, data: Vec<NewNotification>) {
-        let new_notifications: Vec<Notification> = {
+        let new_notifications: Vec<_> = if data.len() < PARALLEL_SHAPING_THRESHOLD {
             let mut font_system = self.font_system.borrow_mut();
             data.into_iter()
                 .map(|data| {
@@ -406,19 +689,233 @@ impl NotificationManager {
                     )
                 })
                 .collect()
+        } else {
+            self.shape_parallel(data)
         };
 
+        new_notifications.iter().for_each(|notification| {
+            if let Some(url) = notification.link_without_preview() {
+                self.fetch_link_preview(notification.id(), url);
+            }
+        });
         self.notifications.extend(new_notifications);
 
         self.update_size();
     }
 
+    /// Builds notifications across worker threads, each shaping text with its own
+    /// `FontSystem`. Workers share a clone of the main font database, so font ids
+    /// in the shaped buffers stay valid for the main `FontSystem` used to render them.
+    fn shape_parallel(&self, data: Vec<NewNotification>) -> Vec<Notification> {
+        let workers = std::thread::available_parallelism()
+            .map(std::num::NonZero::get)
+            .unwrap_or(1)
+            .min(data.len().div_ceil(PARALLEL_SHAPING_THRESHOLD));
+
+        let (locale, db) = {
+            let font_system = self.font_system.borrow();
+            (font_system.locale().to_string(), font_system.db().clone())
+        };
+
+        let chunk_size = data.len().div_ceil(workers);
+        let mut chunks = Vec::with_capacity(workers);
+        let mut data = data.into_iter();
+        loop {
+            let chunk: Vec<_> = data.by_ref().take(chunk_size).collect();
+            if chunk.is_empty() {
+                break;
+            }
+            chunks.push(chunk);
+        }
+
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = chunks
+                .into_iter()
+                .map(|chunk| {
+                    let locale = locale.clone();
+                    let db = db.clone();
+                    let config = Arc::clone(&self.config);
+                    let styles = Arc::clone(&self.styles);
+                    let ui_state = self.ui_state.clone();
+                    let sender = self.sender.clone();
+                    scope.spawn(move || {
+                        let mut font_system = FontSystem::new_with_locale_and_db(locale, db);
+                        chunk
+                            .into_iter()
+                            .map(|data| {
+                                Notification::new(
+                                    Arc::clone(&config),
+                                    Arc::clone(&styles),
+                                    &mut font_system,
+                                    data,
+                                    ui_state.clone(),
+                                    Some(sender.clone()),
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("Shaping thread panicked"))
+                .collect()
+        })
+    }
+
+    /// Whether `data` is held back for the digest instead of being shown
+    fn collects(&self, data: &NewNotification) -> bool {
+        let urgency = data
+            .hints
+            .as_ref()
+            .and_then(|hints| Urgency::try_from(hints.urgency).ok())
+            .unwrap_or_default();
+
+        data.app_name != notifications::APP_NAME
+            && self.config.general.digest.collects(&data.app_name, urgency)
+            && !self.notifications.iter().any(|n| n.id() == data.id)
+    }
+
+    /// Whether `data` replaces a notification on screen without being more urgent
+    /// than it, e.g. a progress update
+    pub fn replaces_quietly(&self, data: &NewNotification) -> bool {
+        let urgency = data
+            .hints
+            .as_ref()
+            .and_then(|hints| Urgency::try_from(hints.urgency).ok())
+            .unwrap_or_default();
+
+        !self
+            .config
+            .general
+            .sound_on_replace
+            .iter()
+            .any(|app| *app == data.app_name)
+            && self
+                .notifications
+                .iter()
+                .any(|n| n.id() == data.id && urgency <= n.urgency())
+    }
+
+    /// Whether `data` matches one of the DND exceptions and is shown while inhibited
+    pub fn breaks_through(&self, data: &NewNotification) -> bool {
+        let now = chrono::Local::now();
+        self.config.general.dnd.breaks_through(
+            &data.app_name,
+            &data.summary,
+            now.hour() * 60 + now.minute(),
+        )
+    }
+
+    /// Take the notifications held back for the digest
+    pub fn take_digest(&mut self) -> Vec<NewNotification> {
+        std::mem::take(&mut self.digest)
+    }
+
+    /// Drop `id` from the digest, once the scheduler closed it there's nothing to list
+    pub fn forget_digested(&mut self, id: NotificationId) {
+        self.digest.retain(|data| data.id != id);
+    }
+
     pub fn add(&mut self, data: NewNotification) {
-        if self.inhibited() {
+        if self.collects(&data) {
+            self.digest.push(data);
+            return;
+        }
+
+        if self.inhibited() && !self.breaks_through(&data) {
             self.waiting.push(data);
+            self.update_waiting_badge();
             return;
         }
 
+        self.insert(data);
+    }
+
+    /// Show `data` right away, bypassing the digest and inhibition. The scheduler
+    /// doesn't know about it, so it's kept in view until it's dismissed
+    pub fn preview(&mut self, data: NewNotification) {
+        if !self.notification_view.visible.contains(&data.id) {
+            self.notification_view.visible.push(data.id);
+        }
+
+        self.insert(data);
+    }
+
+    /// Restyle everything on screen with `overlay` layered over the configured css,
+    /// an empty overlay goes back to just the config
+    pub fn set_style_overlay(&mut self, overlay: &str) {
+        self.style_overlay = overlay.to_string();
+        self.restyle();
+    }
+
+    /// Restyle everything on screen for high contrast, regardless of the theme, or
+    /// go back to it
+    pub fn set_high_contrast(&mut self, enabled: bool) {
+        self.high_contrast = enabled;
+        self.restyle();
+    }
+
+    #[must_use]
+    pub fn high_contrast(&self) -> bool {
+        self.high_contrast
+    }
+
+    /// Switch to a reloaded `config`, rebuilding everything on screen with it.
+    /// The style overlay stays on top of the new css
+    pub fn set_config(&mut self, config: Arc<Config>) {
+        self.config = config;
+        self.restyle();
+        self.update_waiting_badge();
+    }
+
+    fn restyle(&mut self) {
+        let high_contrast = if self.high_contrast {
+            HIGH_CONTRAST
+        } else {
+            ""
+        };
+        self.styles = Arc::new(parse_css_with_overrides(
+            &format!("{}\n{}", self.config.css, self.style_overlay),
+            high_contrast,
+        ));
+        self.notification_view
+            .restyle(Arc::clone(&self.config), Arc::clone(&self.styles));
+
+        let mut font_system = self.font_system.borrow_mut();
+        for notification in &mut self.notifications {
+            notification.restyle(
+                &mut font_system,
+                Arc::clone(&self.styles),
+                Some(self.sender.clone()),
+            );
+        }
+        drop(font_system);
+
+        self.update_size();
+    }
+
+    /// Switch hint characters over to `layout`, relabeling the hints already shown
+    pub fn set_layout(&mut self, layout: Option<Box<str>>) {
+        let changes = self.config.general.hint_characters(layout.as_deref())
+            != self
+                .config
+                .general
+                .hint_characters(self.ui_state.layout.read().unwrap().as_deref());
+        *self.ui_state.layout.write().unwrap() = layout;
+
+        if changes {
+            let mut font_system = self.font_system.borrow_mut();
+            self.notifications
+                .iter_mut()
+                .filter_map(Notification::buttons_mut)
+                .for_each(|buttons| buttons.assign_hints(&mut font_system));
+        }
+    }
+
+    fn insert(&mut self, data: NewNotification) {
+        let id = data.id;
         if let Some(notification) = self.notifications.iter_mut().find(|n| n.id() == data.id) {
             notification.replace(
                 &mut self.font_system.borrow_mut(),
@@ -438,9 +935,48 @@ impl NotificationManager {
             self.notifications.push_back(notification);
         }
 
+        if let Some(url) = self
+            .notifications
+            .iter()
+            .find(|notification| notification.id() == id)
+            .and_then(Notification::link_without_preview)
+        {
+            self.fetch_link_preview(id, url);
+        }
+
         self.update_size();
     }
 
+    /// Fetch the preview of `url` in the background, it's shown under notification `id`
+    /// once it arrives
+    fn fetch_link_preview(&self, id: NotificationId, url: Arc<str>) {
+        let Some(http) = self.http.clone() else {
+            return;
+        };
+        let config = Arc::clone(&self.config);
+        let sender = self.sender.clone();
+
+        tokio::spawn(async move {
+            let link_previews = &config.general.link_previews;
+            let fetch = link_preview::fetch(&http, Arc::clone(&url), link_previews);
+            match tokio::time::timeout(link_previews.timeout, fetch).await {
+                Ok(Ok(preview)) => {
+                    _ = sender.send(crate::Event::LinkPreview(id, Box::new(preview)));
+                }
+                Ok(Err(e)) => log::debug!("No preview for {url}: {e}"),
+                Err(_) => log::debug!("Preview of {url} timed out"),
+            }
+        });
+    }
+
+    /// Show `preview` under notification `id`, if it's still around
+    pub fn set_link_preview(&mut self, id: NotificationId, preview: Preview) {
+        if let Some(notification) = self.notifications.iter_mut().find(|n| n.id() == id) {
+            notification.set_link_preview(&mut self.font_system.borrow_mut(), preview);
+            self.update_size();
+        }
+    }
+
     pub fn dismiss_by_id(&mut self, id: NotificationId) -> Option<Notification> {
         let index = self.notifications.iter().position(|n| n.id() == id)?;
 
@@ -491,6 +1027,22 @@ impl NotificationManager {
         });
 
         self.notification_view.set_next_position(0., start);
+
+        let start = start
+            + self
+                .notification_view
+                .next_bounds()
+                .map(|bounds| bounds.height)
+                .unwrap_or(0.0);
+        self.notification_view.set_waiting_position(0., start);
+
+        let start = start
+            + self
+                .notification_view
+                .waiting_bounds()
+                .map(|bounds| bounds.height)
+                .unwrap_or(0.0);
+        self.notification_view.set_palette_position(0., start);
     }
 }
 
@@ -561,13 +1113,17 @@ impl Moxnotify {
 
     pub fn dismiss_with_reason(&mut self, id: u32, reason: Option<CloseReason>) {
         if self.notifications.selected_id() == Some(id) {
-            self.notifications
-                .ui_state
-                .mode
-                .store(keymaps::Mode::Normal, Ordering::Relaxed);
+            self.notifications.close_palette();
         }
 
         if let Some(notification) = self.notifications.dismiss_by_id(id) {
+            // Nothing to tell the scheduler, it never knew about the preview
+            if id == preview::ID {
+                self.update_surface_size();
+                self.request_render();
+                return;
+            }
+
             let uuid = notification.uuid();
 
             let mut grpc_client = self.notifications.grpc_client.clone();
@@ -601,25 +1157,17 @@ impl Moxnotify {
                 self.notifications.select(*selected_id);
             }
 
-            self.notifications.notification_view.update(
+            self.notifications.update_view(
                 response.focused_ids,
                 response.before_count,
                 response.after_count,
             );
 
             self.update_surface_size();
-            if let Some(surface) = self.surface.as_mut()
-                && let Err(e) = surface.render(
-                    &self.wgpu_state.device,
-                    &self.wgpu_state.queue,
-                    &self.notifications,
-                )
-            {
-                log::error!("Render error: {e}");
-            }
+            self.request_render();
 
             if self.notifications.notifications().is_empty() {
-                self.seat.keyboard.repeat.key = None;
+                self.seat.keyboard.repeat.cancel(&self.loop_handle);
             }
 
             log::debug!("Successfully dismissed notification, id: {id}");
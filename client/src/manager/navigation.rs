@@ -0,0 +1,207 @@
+use crate::moxnotify::client::client_service_client::ClientServiceClient;
+use crate::moxnotify::client::viewport_navigation_request::Direction;
+use crate::moxnotify::client::{
+    RestartTimersRequest, StopTimersRequest, ViewportNavigationRequest,
+};
+use tokio::sync::mpsc;
+use tonic::transport::Channel;
+
+#[derive(Debug, PartialEq)]
+enum Request {
+    /// Numbered navigation, moving `count` steps
+    Navigate {
+        seq: u64,
+        direction: Direction,
+        count: u32,
+    },
+    StopTimers,
+    RestartTimers,
+}
+
+/// Merge consecutive navigation in the same direction into one request moving as
+/// many steps, so a held key costs one round trip per batch instead of per repeat
+fn coalesce(requests: Vec<Request>) -> Vec<Request> {
+    let mut coalesced: Vec<Request> = Vec::with_capacity(requests.len());
+    for request in requests {
+        if let (
+            Some(Request::Navigate {
+                seq,
+                direction,
+                count,
+            }),
+            Request::Navigate {
+                seq: next_seq,
+                direction: next_direction,
+                count: next_count,
+            },
+        ) = (coalesced.last_mut(), &request)
+            && direction == next_direction
+        {
+            *seq = *next_seq;
+            *count += next_count;
+            continue;
+        }
+
+        coalesced.push(request);
+    }
+
+    coalesced
+}
+
+/// Sends viewport and timer changes to the scheduler in the background, one batch at
+/// a time in the order they were made so the scheduler ends up where the client
+/// already moved to. Its answers to navigation come back as [`crate::Event::Viewport`]
+pub struct Navigation {
+    sender: mpsc::UnboundedSender<Request>,
+    latest: u64,
+}
+
+impl Navigation {
+    pub fn new(
+        mut grpc_client: ClientServiceClient<Channel>,
+        event_sender: calloop::channel::Sender<crate::Event>,
+    ) -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            while let Some(request) = receiver.recv().await {
+                // Everything queued up while the previous batch was in flight
+                let mut requests = vec![request];
+                while let Ok(request) = receiver.try_recv() {
+                    requests.push(request);
+                }
+
+                for request in coalesce(requests) {
+                    let result = match request {
+                        Request::Navigate {
+                            seq,
+                            direction,
+                            count,
+                        } => grpc_client
+                            .navigate_viewport(tonic::Request::new(ViewportNavigationRequest {
+                                direction: direction as i32,
+                                count,
+                            }))
+                            .await
+                            .map(|response| {
+                                _ = event_sender
+                                    .send(crate::Event::Viewport(seq, response.into_inner()));
+                            }),
+                        Request::StopTimers => grpc_client
+                            .stop_timers(tonic::Request::new(StopTimersRequest {}))
+                            .await
+                            .map(|_| ()),
+                        Request::RestartTimers => grpc_client
+                            .restart_timers(tonic::Request::new(RestartTimersRequest {}))
+                            .await
+                            .map(|_| ()),
+                    };
+
+                    if let Err(e) = result {
+                        log::warn!("Scheduler rejected viewport change: {}", e.message());
+                    }
+                }
+            }
+        });
+
+        Self { sender, latest: 0 }
+    }
+
+    /// Move the scheduler's viewport in `direction`
+    pub fn navigate(&mut self, direction: Direction) {
+        self.latest += 1;
+        _ = self.sender.send(Request::Navigate {
+            seq: self.latest,
+            direction,
+            count: 1,
+        });
+    }
+
+    pub fn stop_timers(&self) {
+        _ = self.sender.send(Request::StopTimers);
+    }
+
+    pub fn restart_timers(&self) {
+        _ = self.sender.send(Request::RestartTimers);
+    }
+
+    /// Whether `seq` answers the last navigation, answers to earlier ones are
+    /// already overtaken by local changes
+    pub fn is_latest(&self, seq: u64) -> bool {
+        seq == self.latest
+    }
+}
+
+/// Selected index and viewport after moving in `direction` among `len` notifications
+/// ordered newest first, the way the scheduler moves. `window` is the visible range,
+/// `selected` the index of the selected notification if it's still around
+pub fn predict(
+    direction: Direction,
+    len: usize,
+    selected: Option<usize>,
+    window: std::ops::Range<usize>,
+) -> (usize, std::ops::Range<usize>) {
+    let index = match (direction, selected) {
+        (Direction::Prev, Some(index)) if index + 1 < len => index + 1,
+        (Direction::Prev, _) | (Direction::Last, _) => 0,
+        (Direction::Next, Some(index)) if index > 0 => index - 1,
+        (Direction::Next, _) | (Direction::First, _) => len.saturating_sub(1),
+    };
+
+    let width = window.len();
+    let start = if index < window.start {
+        index
+    } else if index >= window.end {
+        index + 1 - width.max(1)
+    } else {
+        window.start
+    };
+
+    (index, start..(start + width).min(len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn predict_wraps_and_scrolls() {
+        // Newest selected, going further back selects the next older one
+        assert_eq!(predict(Direction::Prev, 5, Some(0), 0..3), (1, 0..3));
+        // Scrolls by one once the selection leaves the window
+        assert_eq!(predict(Direction::Prev, 5, Some(2), 0..3), (3, 1..4));
+        // Wraps around to the newest
+        assert_eq!(predict(Direction::Prev, 5, Some(4), 2..5), (0, 0..3));
+        assert_eq!(predict(Direction::Next, 5, Some(0), 0..3), (4, 2..5));
+        assert_eq!(predict(Direction::Next, 5, Some(3), 1..4), (2, 1..4));
+        assert_eq!(predict(Direction::First, 5, None, 0..3), (4, 2..5));
+        assert_eq!(predict(Direction::Last, 5, Some(4), 2..5), (0, 0..3));
+    }
+
+    #[test]
+    fn coalesce_merges_runs_in_one_direction() {
+        let navigate = |seq, direction, count| Request::Navigate {
+            seq,
+            direction,
+            count,
+        };
+
+        assert_eq!(
+            coalesce(vec![
+                navigate(1, Direction::Next, 1),
+                navigate(2, Direction::Next, 1),
+                navigate(3, Direction::Next, 1),
+                Request::StopTimers,
+                navigate(4, Direction::Prev, 1),
+                navigate(5, Direction::Next, 1),
+                navigate(6, Direction::Next, 1),
+            ]),
+            vec![
+                navigate(3, Direction::Next, 3),
+                Request::StopTimers,
+                navigate(4, Direction::Prev, 1),
+                navigate(6, Direction::Next, 2),
+            ]
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use crate::components::notification::NotificationId;
+use config::client::keymaps::KeyAction;
+
+/// Matching entries shown at once, the list scrolls to keep the selected one in view
+const MAX_SHOWN: usize = 8;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Key(KeyAction),
+    DismissAll,
+    ToggleHighContrast,
+    /// Invoke the action `key` of notification `id`
+    Invoke {
+        id: NotificationId,
+        key: String,
+        uuid: String,
+    },
+}
+
+pub struct Entry {
+    pub label: String,
+    pub command: Command,
+}
+
+impl Entry {
+    pub fn new(label: String, command: Command) -> Self {
+        Self { label, command }
+    }
+}
+
+/// Fuzzy searchable list of commands, picked from with the keyboard
+pub struct Palette {
+    query: String,
+    /// Text the input method is composing, shown after the query until it's committed
+    preedit: String,
+    entries: Vec<Entry>,
+    /// Index among the entries matching the query
+    selected: usize,
+}
+
+/// How well `query` matches `label`, `None` unless all of its characters appear in
+/// `label` in order, ignoring case. Runs of characters and ones starting a word
+/// score higher
+fn score(query: &str, label: &str) -> Option<u32> {
+    let label: Vec<char> = label.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+
+    for c in query.to_lowercase().chars() {
+        let index = position + label.get(position..)?.iter().position(|&l| l == c)?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == index) {
+            score += 2;
+        }
+        if index == 0 || label[index - 1].is_whitespace() {
+            score += 3;
+        }
+
+        previous = Some(index);
+        position = index + 1;
+    }
+
+    Some(score)
+}
+
+impl Palette {
+    pub fn new(entries: Vec<Entry>) -> Self {
+        Self {
+            query: String::new(),
+            preedit: String::new(),
+            entries,
+            selected: 0,
+        }
+    }
+
+    /// Entries matching the query, best first. Equally good ones keep their order
+    fn matching(&self) -> Vec<&Entry> {
+        let mut matching: Vec<_> = self
+            .entries
+            .iter()
+            .filter_map(|entry| score(&self.query, &entry.label).map(|score| (score, entry)))
+            .collect();
+        matching.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+        matching.into_iter().map(|(_, entry)| entry).collect()
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.selected = 0;
+    }
+
+    pub fn set_preedit(&mut self, preedit: String) {
+        self.preedit = preedit;
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    pub fn next(&mut self) {
+        let len = self.matching().len();
+        if len > 0 {
+            self.selected = (self.selected + 1) % len;
+        }
+    }
+
+    pub fn prev(&mut self) {
+        let len = self.matching().len();
+        if len > 0 {
+            self.selected = (self.selected + len - 1) % len;
+        }
+    }
+
+    #[must_use]
+    pub fn selected(&self) -> Option<Command> {
+        self.matching()
+            .get(self.selected)
+            .map(|entry| entry.command.clone())
+    }
+
+    /// The query followed by the matching entries, the selected one marked
+    #[must_use]
+    pub fn text(&self) -> String {
+        let matching = self.matching();
+        let start = self.selected.saturating_sub(MAX_SHOWN - 1);
+
+        std::iter::once(format!(": {}{}", self.query, self.preedit))
+            .chain(
+                matching
+                    .iter()
+                    .enumerate()
+                    .skip(start)
+                    .take(MAX_SHOWN)
+                    .map(|(i, entry)| {
+                        let marker = if i == self.selected { '›' } else { ' ' };
+                        format!("{marker} {}", entry.label)
+                    }),
+            )
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_prefers_runs_and_word_starts() {
+        assert_eq!(score("", "Dismiss all"), Some(0));
+        assert_eq!(score("xyz", "Dismiss all"), None);
+        assert_eq!(score("sd", "Dismiss all"), None);
+        assert!(score("da", "Dismiss all") > score("ds", "Dismiss all"));
+        assert!(score("dis", "Dismiss all") > score("dss", "Dismiss all"));
+    }
+
+    #[test]
+    fn selection_follows_the_query() {
+        let mut palette = Palette::new(vec![
+            Entry::new("Dismiss all".to_string(), Command::DismissAll),
+            Entry::new(
+                "Toggle do not disturb".to_string(),
+                Command::Key(KeyAction::ToggleInhibit),
+            ),
+        ]);
+
+        assert_eq!(palette.selected(), Some(Command::DismissAll));
+        palette.next();
+        assert_eq!(
+            palette.selected(),
+            Some(Command::Key(KeyAction::ToggleInhibit))
+        );
+        palette.next();
+        assert_eq!(palette.selected(), Some(Command::DismissAll));
+
+        palette.push('d');
+        palette.push('n');
+        palette.push('d');
+        assert_eq!(
+            palette.selected(),
+            Some(Command::Key(KeyAction::ToggleInhibit))
+        );
+        assert_eq!(palette.text(), ": dnd\n› Toggle do not disturb");
+
+        palette.set_preedit("にほ".to_string());
+        assert!(palette.text().starts_with(": dndにほ\n"));
+
+        palette.push('x');
+        assert_eq!(palette.selected(), None);
+    }
+}
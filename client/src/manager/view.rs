@@ -1,10 +1,10 @@
 use super::UiState;
 use crate::components::{Component, notification::Notification, text::Text};
 use crate::moxnotify::types::{NewNotification, NotificationHints};
+use crate::rendering::glyphon::{FontSystem, TextArea};
+use crate::rendering::moxui::shape_renderer;
 use crate::styles::Styles;
 use config::client::{ClientConfig as Config, Urgency};
-use glyphon::{FontSystem, TextArea};
-use moxui::shape_renderer;
 use std::{
     cell::RefCell,
     rc::Rc,
@@ -17,6 +17,13 @@ pub struct NotificationView {
     prev_count: u32,
     next: Notification,
     next_count: u32,
+    /// Badge counting the notifications waiting while inhibited and listing muted apps
+    waiting: Notification,
+    waiting_count: u32,
+    muted: Vec<Box<str>>,
+    /// Command palette, below everything else while it's open
+    palette: Notification,
+    palette_text: Option<String>,
     font_system: Rc<RefCell<FontSystem>>,
     styles: Arc<Styles>,
     ui_state: UiState,
@@ -54,6 +61,30 @@ impl NotificationView {
             ui_state.clone(),
         );
 
+        let waiting = Notification::counter(
+            Arc::clone(&config),
+            Arc::clone(&styles),
+            &mut font_system.borrow_mut(),
+            NewNotification {
+                summary: String::new(),
+                hints: Some(NotificationHints::default()),
+                ..Default::default()
+            },
+            ui_state.clone(),
+        );
+
+        let palette = Notification::counter(
+            Arc::clone(&config),
+            Arc::clone(&styles),
+            &mut font_system.borrow_mut(),
+            NewNotification {
+                summary: String::new(),
+                hints: Some(NotificationHints::default()),
+                ..Default::default()
+            },
+            ui_state.clone(),
+        );
+
         Self {
             visible: Vec::new(),
             styles: Arc::clone(&styles),
@@ -62,10 +93,34 @@ impl NotificationView {
             prev_count: 0,
             next,
             next_count: 0,
+            waiting,
+            waiting_count: 0,
+            muted: Vec::new(),
+            palette,
+            palette_text: None,
             ui_state,
         }
     }
 
+    /// Rebuild the counters with new styles, keeping what they show
+    pub fn restyle(&mut self, config: Arc<Config>, styles: Arc<Styles>) {
+        let visible = std::mem::take(&mut self.visible);
+        let (prev, next, waiting) = (self.prev_count, self.next_count, self.waiting_count);
+        let muted = std::mem::take(&mut self.muted);
+        let palette = self.palette_text.take();
+
+        *self = Self::new(
+            config,
+            styles,
+            self.ui_state.clone(),
+            Rc::clone(&self.font_system),
+        );
+        self.update(visible, prev, next);
+        self.waiting_count = waiting;
+        self.set_muted(muted);
+        self.set_palette(palette);
+    }
+
     pub fn update(&mut self, visible: Vec<u32>, prev: u32, next: u32) {
         self.set_visible(visible);
         self.set_prev(prev);
@@ -77,11 +132,7 @@ impl NotificationView {
     }
 
     fn set_prev(&mut self, count: u32) {
-        let summary = self
-            .styles
-            .next
-            .format
-            .replace("{}", &count.to_string());
+        let summary = i18n::tr!("notifications-more", count = count);
 
         let mut font_system = self.font_system.borrow_mut();
         self.prev
@@ -93,11 +144,7 @@ impl NotificationView {
     }
 
     fn set_next(&mut self, count: u32) {
-        let summary = self
-            .styles
-            .prev
-            .format
-            .replace("{}", &count.to_string());
+        let summary = i18n::tr!("notifications-more", count = count);
 
         let mut font_system = self.font_system.borrow_mut();
         self.next
@@ -108,6 +155,55 @@ impl NotificationView {
         self.next_count = count;
     }
 
+    /// Show `count` notifications as waiting
+    pub fn set_waiting(&mut self, count: u32) {
+        self.waiting_count = count;
+        self.update_badge();
+    }
+
+    /// List `apps` as muted
+    pub fn set_muted(&mut self, apps: Vec<Box<str>>) {
+        self.muted = apps;
+        self.update_badge();
+    }
+
+    /// Whether the badge has anything to show
+    fn badge_shown(&self) -> bool {
+        self.waiting_count > 0 || !self.muted.is_empty()
+    }
+
+    fn update_badge(&mut self) {
+        let waiting = (self.waiting_count > 0)
+            .then(|| i18n::tr!("notifications-waiting", count = self.waiting_count));
+        let muted =
+            (!self.muted.is_empty()).then(|| i18n::tr!("apps-muted", apps = self.muted.join(", ")));
+        let summary = waiting
+            .into_iter()
+            .chain(muted)
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut font_system = self.font_system.borrow_mut();
+        self.waiting
+            .summary
+            .as_mut()
+            .expect("Something went horribly wrong")
+            .set_text(&mut font_system, &summary);
+    }
+
+    /// Show the command palette with `text`, or hide it
+    pub fn set_palette(&mut self, text: Option<String>) {
+        if let Some(text) = text.as_ref() {
+            let mut font_system = self.font_system.borrow_mut();
+            self.palette
+                .summary
+                .as_mut()
+                .expect("Something went horribly wrong")
+                .set_text(&mut font_system, text);
+        }
+        self.palette_text = text;
+    }
+
     pub fn prev_data(
         &self,
         total_width: f32,
@@ -180,6 +276,76 @@ impl NotificationView {
         ))
     }
 
+    pub fn waiting_data(
+        &self,
+        total_width: f32,
+    ) -> Option<(shape_renderer::ShapeInstance, TextArea<'_>)> {
+        if !self.badge_shown() {
+            return None;
+        }
+
+        let extents = self.waiting.get_render_bounds();
+        let style = &self.styles.next;
+        const COUNTER_BORDER_SIZE: f32 = 1.0;
+        let instance = shape_renderer::ShapeInstance {
+            rect_pos: [extents.x, extents.y],
+            rect_size: [
+                total_width - COUNTER_BORDER_SIZE * 2.0,
+                extents.height - COUNTER_BORDER_SIZE * 2.0,
+            ],
+            rect_color: style.background.color(Urgency::Low),
+            border_radius: style.border.radius.into(),
+            border_size: [COUNTER_BORDER_SIZE; 4],
+            border_color: style.border.color.color(Urgency::Low),
+            scale: self.ui_state.scale.load(Ordering::Relaxed),
+            depth: 0.9,
+        };
+
+        Some((
+            instance,
+            self.waiting
+                .summary
+                .as_ref()
+                .expect("Something went horribly wrong")
+                .get_text_areas(Urgency::Low)
+                .swap_remove(0),
+        ))
+    }
+
+    pub fn palette_data(
+        &self,
+        total_width: f32,
+    ) -> Option<(shape_renderer::ShapeInstance, TextArea<'_>)> {
+        self.palette_text.as_ref()?;
+
+        let extents = self.palette.get_render_bounds();
+        let style = &self.styles.palette;
+        let border = style.border.size;
+        let instance = shape_renderer::ShapeInstance {
+            rect_pos: [extents.x, extents.y],
+            rect_size: [
+                total_width - border.left - border.right,
+                extents.height - border.top - border.bottom,
+            ],
+            rect_color: style.background.color(Urgency::Low),
+            border_radius: style.border.radius.into(),
+            border_size: border.into(),
+            border_color: style.border.color.color(Urgency::Low),
+            scale: self.ui_state.scale.load(Ordering::Relaxed),
+            depth: 0.9,
+        };
+
+        Some((
+            instance,
+            self.palette
+                .summary
+                .as_ref()
+                .expect("Something went horribly wrong")
+                .get_text_areas(Urgency::Low)
+                .swap_remove(0),
+        ))
+    }
+
     /// Get the bounds of the previous notification counter, if notifications exist
     pub fn prev_bounds(&self) -> Option<crate::components::Bounds> {
         if self.prev_count == 0 {
@@ -198,8 +364,34 @@ impl NotificationView {
         }
     }
 
+    /// Get the bounds of the waiting notification badge, if it's shown
+    pub fn waiting_bounds(&self) -> Option<crate::components::Bounds> {
+        if !self.badge_shown() {
+            None
+        } else {
+            Some(self.waiting.get_bounds())
+        }
+    }
+
+    /// Get the bounds of the command palette, if it's open
+    pub fn palette_bounds(&self) -> Option<crate::components::Bounds> {
+        self.palette_text
+            .as_ref()
+            .map(|_| self.palette.get_bounds())
+    }
+
     /// Set the position of the next notification counter
     pub fn set_next_position(&mut self, x: f32, y: f32) {
         self.next.set_position(x, y);
     }
+
+    /// Set the position of the waiting notification badge
+    pub fn set_waiting_position(&mut self, x: f32, y: f32) {
+        self.waiting.set_position(x, y);
+    }
+
+    /// Set the position of the command palette
+    pub fn set_palette_position(&mut self, x: f32, y: f32) {
+        self.palette.set_position(x, y);
+    }
 }
@@ -0,0 +1,69 @@
+//! Synthetic notifications shown without going through the pipeline,
+//! so theme authors can iterate on styles quickly
+
+use crate::moxnotify::types::{Action, Image, NewNotification, NotificationHints, Urgency, image};
+
+/// Every preview replaces the previous one, the ID is clear of the ones collectors hand
+/// out. The scheduler never sees it, so the client keeps it in view and closes it itself
+pub const ID: u32 = u32::MAX;
+
+/// Marks the notification as a preview for the scheduler, which doesn't know about it
+const UUID: &str = "preview";
+
+#[must_use]
+pub fn notification(
+    urgency: Urgency,
+    actions: bool,
+    image: bool,
+    progress: Option<i32>,
+) -> NewNotification {
+    let actions = if actions {
+        vec![
+            Action {
+                key: "default".to_string(),
+                label: "Open".to_string(),
+            },
+            Action {
+                key: "reply".to_string(),
+                label: "Reply".to_string(),
+            },
+            Action {
+                key: "mark-read".to_string(),
+                label: "Mark as read".to_string(),
+            },
+        ]
+    } else {
+        Vec::new()
+    };
+
+    let level = match urgency {
+        Urgency::Low => "Low",
+        Urgency::Normal => "Normal",
+        Urgency::Critical => "Critical",
+    };
+
+    let timestamp = config::now_ms();
+    NewNotification {
+        id: ID,
+        app_name: "moxnotify preview".to_string(),
+        app_icon: Some("preferences-desktop-theme".to_string()),
+        summary: format!("{level} urgency preview"),
+        body: "The <b>quick</b> brown fox jumps over the <i>lazy</i> dog. \
+            A second sentence, long enough to wrap onto another line."
+            .to_string(),
+        timeout: 0,
+        actions,
+        hints: Some(NotificationHints {
+            urgency: urgency as i32,
+            value: progress,
+            image: image.then(|| Image {
+                image: Some(image::Image::Name("image-x-generic".to_string())),
+            }),
+            ..Default::default()
+        }),
+        timestamp,
+        client_timestamp: timestamp,
+        uuid: UUID.to_string(),
+        latency: None,
+    }
+}
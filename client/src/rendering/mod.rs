@@ -1,4 +1,22 @@
 pub mod animation;
+#[cfg(feature = "peek")]
+mod peek;
+#[cfg(feature = "peek")]
+pub mod shm_state;
 pub mod surface;
 pub mod text;
+#[cfg(all(feature = "gpu", not(feature = "peek")))]
 pub mod wgpu_state;
+
+/// What components describe a frame with, the real crates or their software stand-ins
+#[cfg(all(feature = "gpu", not(feature = "peek")))]
+pub use ::glyphon;
+#[cfg(all(feature = "gpu", not(feature = "peek")))]
+pub use ::moxui;
+#[cfg(feature = "peek")]
+pub use peek::{glyphon, moxui};
+
+#[cfg(all(feature = "gpu", not(feature = "peek")))]
+pub type RenderState = wgpu_state::WgpuState;
+#[cfg(feature = "peek")]
+pub type RenderState = shm_state::ShmState;
@@ -0,0 +1,165 @@
+//! Stand-ins for the parts of glyphon and moxui the components describe a frame with,
+//! so the `peek` renderer doesn't pull in wgpu
+
+pub mod glyphon {
+    pub use cosmic_text::*;
+
+    /// Text drawn into the frame
+    pub struct TextArea<'a> {
+        pub buffer: &'a Buffer,
+        pub left: f32,
+        pub top: f32,
+        pub scale: f32,
+        pub bounds: TextBounds,
+        pub default_color: Color,
+        pub custom_glyphs: &'a [CustomGlyph],
+    }
+
+    /// Clip rectangle of a [`TextArea`]
+    #[derive(Clone, Copy, Debug)]
+    pub struct TextBounds {
+        pub left: i32,
+        pub top: i32,
+        pub right: i32,
+        pub bottom: i32,
+    }
+
+    /// Never drawn, there are no custom glyphs without a glyph atlas
+    pub struct CustomGlyph;
+}
+
+pub mod moxui {
+    pub mod shape_renderer {
+        /// Rounded rectangle with a border
+        #[derive(Clone, Copy, Debug)]
+        pub struct ShapeInstance {
+            pub rect_pos: [f32; 2],
+            pub rect_size: [f32; 2],
+            pub rect_color: [f32; 4],
+            pub border_radius: [f32; 4],
+            pub border_size: [f32; 4],
+            pub border_color: [f32; 4],
+            pub scale: f32,
+            pub depth: f32,
+        }
+    }
+
+    pub mod texture_renderer {
+        /// Straight alpha RGBA pixels of a texture
+        pub struct Buffer<'a> {
+            pub width: f32,
+            pub height: f32,
+            pub bytes: &'a [u8],
+        }
+
+        impl<'a> Buffer<'a> {
+            pub fn new(width: f32, height: f32) -> Self {
+                Self {
+                    width,
+                    height,
+                    bytes: &[],
+                }
+            }
+
+            pub fn set_bytes(&mut self, bytes: &'a [u8]) {
+                self.bytes = bytes;
+            }
+        }
+
+        /// Clip rectangle of a [`TextureArea`]
+        #[derive(Clone, Copy, Debug)]
+        pub struct TextureBounds {
+            pub left: u32,
+            pub top: u32,
+            pub right: u32,
+            pub bottom: u32,
+        }
+
+        /// Image drawn into the frame
+        pub struct TextureArea<'a> {
+            pub left: f32,
+            pub top: f32,
+            pub scale: f32,
+            pub rotation: f32,
+            pub bounds: TextureBounds,
+            pub skew: [f32; 2],
+            pub radius: [f32; 4],
+            pub buffer: Buffer<'a>,
+            pub depth: f32,
+        }
+
+        impl<'a> TextureArea<'a> {
+            /// Unrotated and unskewed texture, mirroring moxui's constructor
+            #[allow(clippy::too_many_arguments)]
+            pub fn simple(
+                bytes: &'a [u8],
+                left: f32,
+                top: f32,
+                width: f32,
+                height: f32,
+                bounds: TextureBounds,
+                radius: [f32; 4],
+                _: [f32; 4],
+                depth: f32,
+            ) -> Self {
+                Self {
+                    left,
+                    top,
+                    scale: 1.,
+                    rotation: 0.,
+                    bounds,
+                    skew: [0., 0.],
+                    radius,
+                    buffer: Buffer {
+                        width,
+                        height,
+                        bytes,
+                    },
+                    depth,
+                }
+            }
+        }
+    }
+
+    pub mod image {
+        use ::image::imageops::FilterType;
+        use ::image::{DynamicImage, RgbaImage};
+        use std::path::Path;
+        use std::sync::Arc;
+
+        /// Decoded RGBA image, cheap to clone
+        #[derive(Clone)]
+        pub struct Image(Arc<RgbaImage>);
+
+        impl Image {
+            pub fn from_raw(width: u32, height: u32, data: Vec<u8>) -> Option<Self> {
+                RgbaImage::from_raw(width, height, data).map(|image| Self(Arc::new(image)))
+            }
+
+            pub fn open(path: &Path) -> anyhow::Result<Self> {
+                Ok(Self(Arc::new(::image::open(path)?.into_rgba8())))
+            }
+
+            /// Scale to fit in `width` by `height`, keeping the aspect ratio
+            pub fn resize_to_fit(&self, width: u32, height: u32) -> anyhow::Result<Self> {
+                let image = DynamicImage::ImageRgba8((*self.0).clone())
+                    .resize(width, height, FilterType::Triangle)
+                    .into_rgba8();
+
+                Ok(Self(Arc::new(image)))
+            }
+
+            pub fn width(&self) -> u32 {
+                self.0.width()
+            }
+
+            pub fn height(&self) -> u32 {
+                self.0.height()
+            }
+
+            pub fn data(&self) -> &[u8] {
+                self.0.as_raw()
+            }
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use crate::Moxnotify;
+use wayland_client::globals::GlobalList;
+use wayland_client::protocol::{wl_shm, wl_shm_pool};
+use wayland_client::{QueueHandle, delegate_noop};
+
+/// Shared memory counterpart of `WgpuState` used by the `peek` build
+pub struct ShmState {
+    pub shm: wl_shm::WlShm,
+}
+
+impl ShmState {
+    pub fn new(globals: &GlobalList, qh: &QueueHandle<Moxnotify>) -> anyhow::Result<Self> {
+        Ok(Self {
+            shm: globals.bind(qh, 1..=1, ())?,
+        })
+    }
+}
+
+delegate_noop!(Moxnotify: ignore wl_shm::WlShm);
+delegate_noop!(Moxnotify: ignore wl_shm_pool::WlShmPool);
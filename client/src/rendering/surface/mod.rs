@@ -1,25 +1,38 @@
+#[cfg(feature = "peek")]
+pub mod shm_surface;
+#[cfg(all(feature = "gpu", not(feature = "peek")))]
 pub mod wgpu_surface;
 
 use crate::manager::NotificationManager;
-use crate::utils::buffers;
-use crate::wgpu_state;
+use crate::rendering::RenderState;
+use crate::rendering::glyphon::FontSystem;
+use crate::wayland::shell::{Role, Shell};
 use crate::{Moxnotify, Output};
 use config::client::{Anchor, ClientConfig as Config, Layer};
-use glyphon::FontSystem;
-use moxui::viewport;
 use std::{
     cell::RefCell,
     fmt,
     rc::Rc,
     sync::{Arc, atomic::Ordering},
 };
-use wayland_client::{Connection, Dispatch, QueueHandle, delegate_noop, protocol::wl_surface};
+use wayland_client::{
+    Connection, Dispatch, QueueHandle, delegate_noop,
+    protocol::{wl_callback, wl_surface},
+};
 use wayland_protocols::xdg::foreign::zv2::client::zxdg_exporter_v2;
 use wayland_protocols_wlr::layer_shell::v1::client::{
     zwlr_layer_shell_v1,
     zwlr_layer_surface_v1::{self, KeyboardInteractivity},
 };
-use wgpu::wgt::CommandEncoderDescriptor;
+
+// `peek` wins when both are enabled, so features stay additive
+#[cfg(not(any(feature = "gpu", feature = "peek")))]
+compile_error!("either the `gpu` or the `peek` feature has to be enabled");
+
+#[cfg(all(feature = "gpu", not(feature = "peek")))]
+type Backend = wgpu_surface::WgpuSurface;
+#[cfg(feature = "peek")]
+type Backend = shm_surface::ShmSurface;
 
 #[derive(PartialEq, Debug)]
 pub enum FocusReason {
@@ -38,27 +51,58 @@ impl fmt::Display for FocusReason {
 }
 
 pub struct Surface {
-    pub wgpu_surface: wgpu_surface::WgpuSurface,
+    backend: Backend,
     pub wl_surface: wl_surface::WlSurface,
-    pub layer_surface: zwlr_layer_surface_v1::ZwlrLayerSurfaceV1,
+    pub role: Role,
     pub scale: f32,
+    /// Global name of the output the surface was placed on, `None` if the compositor chose
+    pub output: Option<u32>,
     configured: bool,
+    /// Whether the notifications changed since the last presented frame
+    dirty: bool,
+    /// Whether a frame callback is outstanding, further renders wait for it
+    frame_pending: bool,
     pub token: Option<Arc<str>>,
     pub focus_reason: Option<FocusReason>,
     font_system: Rc<RefCell<FontSystem>>,
-    viewport: viewport::Viewport,
 }
 
 impl Surface {
     pub fn new(
-        wgpu_state: &wgpu_state::WgpuState,
+        render_state: &RenderState,
         wl_surface: wl_surface::WlSurface,
-        layer_shell: &zwlr_layer_shell_v1::ZwlrLayerShellV1,
+        shell: &Shell,
         qh: &QueueHandle<Moxnotify>,
         output: Option<&Output>,
         config: &Config,
         font_system: Rc<RefCell<FontSystem>>,
     ) -> anyhow::Result<Self> {
+        let scale = output.map_or(1.0, |o| o.scale);
+
+        let layer_shell = match shell {
+            Shell::Layer(layer_shell) => layer_shell,
+            Shell::Xdg(wm_base) => {
+                let role = Role::toplevel(wm_base, &wl_surface, qh);
+
+                log::debug!("New surface created");
+
+                return Ok(Self {
+                    focus_reason: None,
+                    token: None,
+                    configured: false,
+                    dirty: true,
+                    frame_pending: false,
+                    scale,
+                    // Placement is up to the compositor, known once the surface enters an output
+                    output: None,
+                    backend: Backend::new(render_state, &wl_surface, config)?,
+                    wl_surface,
+                    role,
+                    font_system,
+                });
+            }
+        };
+
         let layer_surface = layer_shell.get_layer_surface(
             &wl_surface,
             output.map(|o| &o.wl_output),
@@ -73,8 +117,6 @@ impl Surface {
             (),
         );
 
-        let scale = output.map_or(1.0, |o| o.scale);
-
         layer_surface.set_keyboard_interactivity(KeyboardInteractivity::None);
         layer_surface
             .set_anchor(zwlr_layer_surface_v1::Anchor::Right | zwlr_layer_surface_v1::Anchor::Top);
@@ -113,134 +155,75 @@ impl Surface {
 
         log::debug!("New surface created");
 
-        let viewport = viewport::Viewport::new(&wgpu_state.device);
-
         Ok(Self {
-            viewport,
             focus_reason: None,
             token: None,
             configured: false,
+            dirty: true,
+            frame_pending: false,
             scale,
-            wgpu_surface: wgpu_surface::WgpuSurface::new(wgpu_state, &wl_surface, config)?,
+            output: output.map(|o| o.id),
+            backend: Backend::new(render_state, &wl_surface, config)?,
             wl_surface,
-            layer_surface,
+            role: Role::Layer(layer_surface),
             font_system,
         })
     }
 
-    pub fn render(
+    /// Marks the surface as needing a redraw, the actual render happens once
+    /// the current event batch is processed and the compositor is ready for a new frame
+    pub fn request_render(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Renders a frame if anything changed and the previous frame was already
+    /// picked up by the compositor
+    pub fn render_if_needed(
         &mut self,
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
+        qh: &QueueHandle<Moxnotify>,
+        render_state: &RenderState,
         notifications: &NotificationManager,
     ) -> anyhow::Result<()> {
-        if !self.configured {
+        if !self.configured || !self.dirty || self.frame_pending {
             return Ok(());
         }
 
-        log::debug!("render()");
+        if self.backend.busy() {
+            // Retried once the backend releases its buffer
+            return Ok(());
+        }
 
-        let surface_texture = self
-            .wgpu_surface
-            .surface
-            .get_current_texture()
-            .expect("failed to acquire next swapchain texture");
-        let texture_view = surface_texture
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
-
-        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Render pass"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &texture_view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
-                    store: wgpu::StoreOp::Store,
-                },
-                depth_slice: None,
-            })],
-            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: self.wgpu_surface.depth_buffer.view(),
-                depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(1.0),
-                    store: wgpu::StoreOp::Store,
-                }),
-                stencil_ops: None,
-            }),
-            timestamp_writes: None,
-            occlusion_query_set: None,
-            multiview_mask: None,
-        });
+        // Requested before presenting so that the callback is attached to the commit made by the backend
+        self.wl_surface.frame(qh, ());
+        self.frame_pending = true;
+        self.dirty = false;
 
-        let (instances, text_data, textures) = notifications.data();
+        self.render(qh, render_state, notifications)
+            .inspect_err(|_| {
+                // Nothing was committed so the callback won't fire
+                self.frame_pending = false;
+            })
+    }
 
-        log::debug!(
-            "Rendering frame: {} instances, {} text areas, {} textures",
-            instances.len(),
-            text_data.len(),
-            textures.len()
-        );
+    fn render(
+        &mut self,
+        qh: &QueueHandle<Moxnotify>,
+        render_state: &RenderState,
+        notifications: &NotificationManager,
+    ) -> anyhow::Result<()> {
+        log::debug!("render()");
 
-        self.wgpu_surface
-            .shape_renderer
-            .prepare(device, queue, &instances);
-        self.wgpu_surface
-            .texture_renderer
-            .prepare(device, queue, &textures);
-        self.wgpu_surface.text_renderer.prepare(
-            device,
-            queue,
-            text_data,
+        self.backend.render(
+            qh,
+            render_state,
+            &self.wl_surface,
             &mut self.font_system.borrow_mut(),
-        )?;
-
-        self.wgpu_surface
-            .shape_renderer
-            .render(&mut render_pass, &self.viewport);
-        self.wgpu_surface.text_renderer.render(&mut render_pass)?;
-
-        drop(render_pass); // Drop renderpass and release mutable borrow on encoder
-
-        self.wgpu_surface
-            .texture_renderer
-            .render(&texture_view, &mut encoder, &self.viewport);
-
-        queue.submit(Some(encoder.finish()));
-        surface_texture.present();
-
-        Ok(())
+            notifications,
+        )
     }
 
-    pub fn resize(&mut self, queue: &wgpu::Queue, device: &wgpu::Device, width: u32, height: u32) {
-        if width == self.wgpu_surface.config.height
-            || height == self.wgpu_surface.config.width
-            || width == 0
-            || height == 0
-        {
-            return;
-        }
-        self.wgpu_surface.depth_buffer = buffers::DepthBuffer::new(device, width, height);
-        self.wgpu_surface.config.width = width;
-        self.wgpu_surface.config.height = height;
-        self.wgpu_surface
-            .surface
-            .configure(device, &self.wgpu_surface.config);
-        self.wgpu_surface
-            .text_renderer
-            .viewport
-            .update(queue, glyphon::Resolution { width, height });
-
-        self.viewport
-            .update(queue, viewport::Resolution { width, height });
-
-        self.wgpu_surface.texture_renderer.resize(
-            device,
-            self.wgpu_surface.config.format,
-            width as f32,
-            height as f32,
-        );
+    pub fn resize(&mut self, render_state: &RenderState, width: u32, height: u32) {
+        self.backend.resize(render_state, width, height);
     }
 
     pub fn focus(&mut self, focus_reason: FocusReason) {
@@ -250,10 +233,10 @@ impl Surface {
 
         match focus_reason {
             FocusReason::Ctl => self
-                .layer_surface
+                .role
                 .set_keyboard_interactivity(KeyboardInteractivity::Exclusive),
             FocusReason::MouseEnter => self
-                .layer_surface
+                .role
                 .set_keyboard_interactivity(KeyboardInteractivity::OnDemand),
         }
 
@@ -265,7 +248,7 @@ impl Surface {
     pub fn unfocus(&mut self) {
         log::debug!("Surface unfocused");
         if let Some(FocusReason::Ctl) = self.focus_reason {
-            self.layer_surface
+            self.role
                 .set_keyboard_interactivity(KeyboardInteractivity::OnDemand);
         }
         self.focus_reason = None;
@@ -274,7 +257,7 @@ impl Surface {
 
 impl Drop for Surface {
     fn drop(&mut self) {
-        self.layer_surface.destroy();
+        self.role.destroy();
         self.wl_surface.destroy();
         log::debug!("Surface destroyed");
     }
@@ -289,43 +272,140 @@ impl Dispatch<zwlr_layer_surface_v1::ZwlrLayerSurfaceV1, ()> for Moxnotify {
         _: &Connection,
         qh: &QueueHandle<Self>,
     ) {
+        if let zwlr_layer_surface_v1::Event::Closed = event {
+            // E.g. its output was unplugged
+            log::info!("Surface closed by the compositor, recreating it");
+            state.recreate_surface();
+            return;
+        }
+
         if let zwlr_layer_surface_v1::Event::Configure {
             serial,
             width,
             height,
         } = event
         {
-            if let Some(surface) = state.surface.as_ref() {
-                let token = state.seat.xdg_activation.get_activation_token(qh, ());
-                token.set_serial(serial, &state.seat.wl_seat);
-                token.set_surface(&surface.wl_surface);
-                token.commit();
-            }
+            state.configure_surface(qh, serial, width, height);
+        }
+    }
+}
 
-            if let Some(surface) = state.surface.as_mut() {
-                surface.resize(
-                    &state.wgpu_state.queue,
-                    &state.wgpu_state.device,
-                    width,
-                    height,
-                );
-                surface.layer_surface.ack_configure(serial);
-                surface.configured = true;
-                _ = surface.render(
-                    &state.wgpu_state.device,
-                    &state.wgpu_state.queue,
-                    &state.notifications,
-                );
-                log::debug!("Surface configured ({width}x{height}, serial={serial})");
+impl Dispatch<wl_callback::WlCallback, ()> for Moxnotify {
+    fn event(
+        state: &mut Self,
+        _: &wl_callback::WlCallback,
+        event: <wl_callback::WlCallback as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_callback::Event::Done { .. } = event
+            && let Some(surface) = state.surface.as_mut()
+        {
+            surface.frame_pending = false;
+            // Keep drawing frames until animations settle
+            if state.notifications.animating() {
+                surface.request_render();
             }
         }
     }
 }
 
 delegate_noop!(Moxnotify: zxdg_exporter_v2::ZxdgExporterV2);
-delegate_noop!(Moxnotify: ignore wl_surface::WlSurface);
+
+impl Dispatch<wl_surface::WlSurface, ()> for Moxnotify {
+    fn event(
+        state: &mut Self,
+        _: &wl_surface::WlSurface,
+        event: <wl_surface::WlSurface as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        // Only known up front when the surface was placed on a specific output
+        if let wl_surface::Event::Enter { output } = event
+            && let Some(output) = state.outputs.iter().find(|o| o.wl_output == output)
+            && let Some(surface) = state.surface.as_mut()
+            && surface.output.is_none()
+        {
+            surface.output = Some(output.id);
+            let scale = output.scale;
+            state.set_scale(scale);
+        }
+    }
+}
 
 impl Moxnotify {
+    pub fn request_render(&mut self) {
+        if let Some(surface) = self.surface.as_mut() {
+            surface.request_render();
+        }
+    }
+
+    /// Called once per event loop iteration so that every event dispatched
+    /// in it is coalesced into a single render pass
+    pub fn flush_render(&mut self) {
+        self.sync_text_input();
+        if let Some(surface) = self.surface.as_mut()
+            && let Err(e) =
+                surface.render_if_needed(&self.qh, &self.render_state, &self.notifications)
+        {
+            log::error!("Render error: {e}");
+        }
+    }
+
+    pub fn configure_surface(
+        &mut self,
+        qh: &QueueHandle<Self>,
+        serial: u32,
+        width: u32,
+        height: u32,
+    ) {
+        if let Some(surface) = self.surface.as_ref() {
+            let token = self.seat.xdg_activation.get_activation_token(qh, ());
+            token.set_serial(serial, &self.seat.wl_seat);
+            token.set_surface(&surface.wl_surface);
+            token.commit();
+        }
+
+        if let Some(surface) = self.surface.as_mut() {
+            surface.resize(&self.render_state, width, height);
+            surface.role.ack_configure(serial);
+            surface.configured = true;
+            surface.request_render();
+            log::debug!("Surface configured ({width}x{height}, serial={serial})");
+        }
+    }
+
+    /// Re-layout and redraw notifications at the scale of the output the surface is on
+    pub fn set_scale(&mut self, scale: f32) {
+        let Some(surface) = self.surface.as_mut() else {
+            return;
+        };
+
+        if surface.scale == scale {
+            return;
+        }
+
+        log::info!("Surface scale changed from {} to {scale}", surface.scale);
+        surface.scale = scale;
+        self.notifications
+            .ui_state
+            .scale
+            .store(scale, Ordering::Relaxed);
+
+        self.update_surface_size();
+        self.request_render();
+    }
+
+    /// Place the surface anew, on the configured output if it's available
+    /// and otherwise on the one chosen by the compositor
+    pub fn recreate_surface(&mut self) {
+        self.surface = None;
+        self.update_surface_size();
+        self.request_render();
+    }
+
     pub fn update_surface_size(&mut self) {
         self.notifications.update_size();
 
@@ -354,9 +434,9 @@ impl Moxnotify {
             }
 
             self.surface = Surface::new(
-                &self.wgpu_state,
+                &self.render_state,
                 wl_surface,
-                &self.layer_shell,
+                &self.shell,
                 &self.qh,
                 output,
                 &self.config,
@@ -380,10 +460,15 @@ impl Moxnotify {
             return;
         }
 
-        if let Some(surface) = self.surface.as_ref() {
-            surface
-                .layer_surface
-                .set_size(total_width as u32, total_height as u32);
+        if let Some(surface) = self.surface.as_mut() {
+            let (width, height) = (total_width as u32, total_height as u32);
+            surface.role.set_size(width, height);
+            // Unlike layer surfaces, toplevels aren't necessarily configured again after resizing
+            if let Role::Toplevel { .. } = surface.role
+                && surface.configured
+            {
+                surface.resize(&self.render_state, width, height);
+            }
             surface.wl_surface.commit();
         }
     }
@@ -0,0 +1,404 @@
+use crate::Moxnotify;
+use crate::manager::NotificationManager;
+use crate::rendering::glyphon::{self, FontSystem, SwashCache, TextArea};
+use crate::rendering::moxui::shape_renderer::ShapeInstance;
+use crate::rendering::moxui::texture_renderer::TextureArea;
+use crate::rendering::shm_state::ShmState;
+use anyhow::Context;
+use config::client::ClientConfig as Config;
+use memmap2::MmapMut;
+use rustix::fs::{MemfdFlags, memfd_create};
+use std::fs::File;
+use std::os::fd::AsFd;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tiny_skia::{
+    Color, FillRule, IntSize, Mask, Paint, PathBuilder, Pixmap, PixmapPaint, PremultipliedColorU8,
+    Rect, Transform,
+};
+use wayland_client::protocol::{wl_buffer, wl_shm, wl_surface};
+use wayland_client::{Connection, Dispatch, QueueHandle};
+
+/// Cubic bezier approximation of a quarter circle
+const KAPPA: f32 = 0.552_284_8;
+
+struct Buffer {
+    pool: wayland_client::protocol::wl_shm_pool::WlShmPool,
+    wl_buffer: wl_buffer::WlBuffer,
+    mmap: MmapMut,
+    width: u32,
+    height: u32,
+    /// Set while the compositor reads from the buffer
+    busy: Arc<AtomicBool>,
+}
+
+impl Buffer {
+    fn new(
+        shm_state: &ShmState,
+        qh: &QueueHandle<Moxnotify>,
+        width: u32,
+        height: u32,
+    ) -> anyhow::Result<Self> {
+        let stride = width * 4;
+        let size = stride * height;
+
+        let fd = memfd_create("moxnotify", MemfdFlags::CLOEXEC)?;
+        let file = File::from(fd);
+        file.set_len(u64::from(size))?;
+        let mmap = unsafe { MmapMut::map_mut(&file)? };
+
+        let pool = shm_state.shm.create_pool(file.as_fd(), size as i32, qh, ());
+        let busy = Arc::new(AtomicBool::new(false));
+        let wl_buffer = pool.create_buffer(
+            0,
+            width as i32,
+            height as i32,
+            stride as i32,
+            wl_shm::Format::Argb8888,
+            qh,
+            Arc::clone(&busy),
+        );
+
+        Ok(Self {
+            pool,
+            wl_buffer,
+            mmap,
+            width,
+            height,
+            busy,
+        })
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        self.wl_buffer.destroy();
+        self.pool.destroy();
+    }
+}
+
+/// Shapes and textures, painted back to front
+enum Layer<'a> {
+    Shape(&'a ShapeInstance),
+    Texture(&'a TextureArea<'a>),
+}
+
+impl Layer<'_> {
+    fn depth(&self) -> f32 {
+        match self {
+            Self::Shape(instance) => instance.depth,
+            Self::Texture(texture) => texture.depth,
+        }
+    }
+}
+
+/// CPU renderer drawing into a `wl_shm` buffer
+pub struct ShmSurface {
+    buffer: Option<Buffer>,
+    swash_cache: SwashCache,
+    width: u32,
+    height: u32,
+}
+
+impl ShmSurface {
+    pub fn new(_: &ShmState, _: &wl_surface::WlSurface, _: &Config) -> anyhow::Result<Self> {
+        Ok(Self {
+            buffer: None,
+            swash_cache: SwashCache::new(),
+            width: 0,
+            height: 0,
+        })
+    }
+
+    pub fn busy(&self) -> bool {
+        self.buffer
+            .as_ref()
+            .is_some_and(|buffer| buffer.busy.load(Ordering::Acquire))
+    }
+
+    pub fn render(
+        &mut self,
+        qh: &QueueHandle<Moxnotify>,
+        shm_state: &ShmState,
+        wl_surface: &wl_surface::WlSurface,
+        font_system: &mut FontSystem,
+        notifications: &NotificationManager,
+    ) -> anyhow::Result<()> {
+        if self.width == 0 || self.height == 0 {
+            return Ok(());
+        }
+
+        let buffer = match self.buffer.take() {
+            Some(buffer) if buffer.width == self.width && buffer.height == self.height => buffer,
+            _ => Buffer::new(shm_state, qh, self.width, self.height)?,
+        };
+        let buffer = self.buffer.insert(buffer);
+
+        let mut pixmap = Pixmap::new(self.width, self.height).context("Invalid surface size")?;
+
+        let (instances, text_areas, textures) = notifications.data();
+
+        log::debug!(
+            "Rendering frame: {} instances, {} text areas, {} textures",
+            instances.len(),
+            text_areas.len(),
+            textures.len()
+        );
+
+        // There's no depth buffer, so paint back to front instead
+        let mut layers: Vec<_> = instances
+            .iter()
+            .map(Layer::Shape)
+            .chain(textures.iter().map(Layer::Texture))
+            .collect();
+        layers.sort_by(|a, b| b.depth().total_cmp(&a.depth()));
+        layers.iter().for_each(|layer| match layer {
+            Layer::Shape(instance) => draw_shape(&mut pixmap, instance),
+            Layer::Texture(texture) => draw_texture(&mut pixmap, texture),
+        });
+        text_areas
+            .iter()
+            .for_each(|area| draw_text(&mut pixmap, font_system, &mut self.swash_cache, area));
+
+        // tiny-skia is RGBA while wl_shm's ARGB8888 is BGRA in memory
+        buffer
+            .mmap
+            .chunks_exact_mut(4)
+            .zip(pixmap.data().chunks_exact(4))
+            .for_each(|(dst, src)| dst.copy_from_slice(&[src[2], src[1], src[0], src[3]]));
+
+        buffer.busy.store(true, Ordering::Release);
+        wl_surface.attach(Some(&buffer.wl_buffer), 0, 0);
+        wl_surface.damage_buffer(0, 0, self.width as i32, self.height as i32);
+        wl_surface.commit();
+
+        Ok(())
+    }
+
+    pub fn resize(&mut self, _: &ShmState, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+    }
+}
+
+fn paint(color: [f32; 4]) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color(
+        Color::from_rgba(color[0], color[1], color[2], color[3]).unwrap_or(Color::TRANSPARENT),
+    );
+    paint.anti_alias = true;
+    paint
+}
+
+/// Append a rectangle with per corner radii in `[top_left, top_right, bottom_right, bottom_left]` order
+fn rounded_rect(pb: &mut PathBuilder, rect: Rect, radius: [f32; 4]) {
+    let (x, y, w, h) = (rect.x(), rect.y(), rect.width(), rect.height());
+    let [tl, tr, br, bl] = radius.map(|r| r.clamp(0., w.min(h) / 2.));
+
+    pb.move_to(x + tl, y);
+    pb.line_to(x + w - tr, y);
+    pb.cubic_to(
+        x + w - tr + tr * KAPPA,
+        y,
+        x + w,
+        y + tr - tr * KAPPA,
+        x + w,
+        y + tr,
+    );
+    pb.line_to(x + w, y + h - br);
+    pb.cubic_to(
+        x + w,
+        y + h - br + br * KAPPA,
+        x + w - br + br * KAPPA,
+        y + h,
+        x + w - br,
+        y + h,
+    );
+    pb.line_to(x + bl, y + h);
+    pb.cubic_to(
+        x + bl - bl * KAPPA,
+        y + h,
+        x,
+        y + h - bl + bl * KAPPA,
+        x,
+        y + h - bl,
+    );
+    pb.line_to(x, y + tl);
+    pb.cubic_to(x, y + tl - tl * KAPPA, x + tl - tl * KAPPA, y, x + tl, y);
+    pb.close();
+}
+
+fn draw_shape(pixmap: &mut Pixmap, instance: &ShapeInstance) {
+    let [x, y] = instance.rect_pos;
+    let [width, height] = instance.rect_size;
+    let [left, right, top, bottom] = instance.border_size;
+    let [bottom_right, top_right, bottom_left, top_left] = instance.border_radius;
+
+    let Some(outer) = Rect::from_xywh(x, y, width, height) else {
+        return;
+    };
+    let outer_radius = [top_left, top_right, bottom_right, bottom_left];
+    let inner = Rect::from_xywh(
+        x + left,
+        y + top,
+        width - left - right,
+        height - top - bottom,
+    );
+    let inner_radius = [
+        top_left - left.max(top),
+        top_right - right.max(top),
+        bottom_right - right.max(bottom),
+        bottom_left - left.max(bottom),
+    ];
+
+    if let Some(inner) = inner {
+        let mut pb = PathBuilder::new();
+        rounded_rect(&mut pb, inner, inner_radius);
+        if let Some(path) = pb.finish() {
+            pixmap.fill_path(
+                &path,
+                &paint(instance.rect_color),
+                FillRule::Winding,
+                Transform::identity(),
+                None,
+            );
+        }
+    }
+
+    // Outline only, so translucent backgrounds aren't tinted by the border color
+    let mut pb = PathBuilder::new();
+    rounded_rect(&mut pb, outer, outer_radius);
+    if let Some(inner) = inner {
+        rounded_rect(&mut pb, inner, inner_radius);
+    }
+    if let Some(path) = pb.finish() {
+        pixmap.fill_path(
+            &path,
+            &paint(instance.border_color),
+            FillRule::EvenOdd,
+            Transform::identity(),
+            None,
+        );
+    }
+}
+
+fn draw_texture(pixmap: &mut Pixmap, texture: &TextureArea) {
+    let buffer = &texture.buffer;
+    let Some(size) = IntSize::from_wh(buffer.width as u32, buffer.height as u32) else {
+        return;
+    };
+
+    // Textures are straight alpha while tiny-skia wants it premultiplied
+    let data = buffer
+        .bytes
+        .chunks_exact(4)
+        .flat_map(|pixel| {
+            let alpha = u32::from(pixel[3]);
+            let premultiply = |c: u8| (u32::from(c) * alpha / 255) as u8;
+            [
+                premultiply(pixel[0]),
+                premultiply(pixel[1]),
+                premultiply(pixel[2]),
+                pixel[3],
+            ]
+        })
+        .collect();
+    let Some(image) = Pixmap::from_vec(data, size) else {
+        return;
+    };
+
+    let bounds = texture.bounds;
+    let Some(clip) = Rect::from_ltrb(
+        bounds.left as f32,
+        bounds.top as f32,
+        bounds.right as f32,
+        bounds.bottom as f32,
+    ) else {
+        return;
+    };
+    let Some(mut mask) = Mask::new(pixmap.width(), pixmap.height()) else {
+        return;
+    };
+    let [bottom_right, top_right, bottom_left, top_left] = texture.radius;
+    let mut pb = PathBuilder::new();
+    rounded_rect(
+        &mut pb,
+        clip,
+        [top_left, top_right, bottom_right, bottom_left],
+    );
+    if let Some(path) = pb.finish() {
+        mask.fill_path(&path, FillRule::Winding, true, Transform::identity());
+    }
+
+    pixmap.draw_pixmap(
+        0,
+        0,
+        image.as_ref(),
+        &PixmapPaint::default(),
+        Transform::from_scale(texture.scale, texture.scale)
+            .post_translate(texture.left, texture.top),
+        Some(&mask),
+    );
+}
+
+fn draw_text(
+    pixmap: &mut Pixmap,
+    font_system: &mut FontSystem,
+    swash_cache: &mut SwashCache,
+    area: &TextArea,
+) {
+    let (width, height) = (pixmap.width() as i32, pixmap.height() as i32);
+    let bounds = area.bounds;
+    let pixels = pixmap.pixels_mut();
+
+    area.buffer.draw(
+        font_system,
+        swash_cache,
+        area.default_color,
+        |x, y, w, h, color| {
+            let (x, y) = (area.left as i32 + x, area.top as i32 + y);
+
+            for py in y.max(bounds.top).max(0)..(y + h as i32).min(bounds.bottom).min(height) {
+                for px in x.max(bounds.left).max(0)..(x + w as i32).min(bounds.right).min(width) {
+                    blend(&mut pixels[(py * width + px) as usize], color);
+                }
+            }
+        },
+    );
+}
+
+/// Source-over blend of a straight alpha glyph color onto a premultiplied pixel
+fn blend(dst: &mut PremultipliedColorU8, color: glyphon::Color) {
+    let alpha = u32::from(color.a());
+    if alpha == 0 {
+        return;
+    }
+
+    let inverse = 255 - alpha;
+    let over =
+        |src: u8, dst: u8| (u32::from(src) * alpha / 255 + u32::from(dst) * inverse / 255) as u8;
+
+    if let Some(color) = PremultipliedColorU8::from_rgba(
+        over(color.r(), dst.red()),
+        over(color.g(), dst.green()),
+        over(color.b(), dst.blue()),
+        (alpha + u32::from(dst.alpha()) * inverse / 255) as u8,
+    ) {
+        *dst = color;
+    }
+}
+
+impl Dispatch<wl_buffer::WlBuffer, Arc<AtomicBool>> for Moxnotify {
+    fn event(
+        _: &mut Self,
+        _: &wl_buffer::WlBuffer,
+        event: <wl_buffer::WlBuffer as wayland_client::Proxy>::Event,
+        busy: &Arc<AtomicBool>,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_buffer::Event::Release = event {
+            busy.store(false, Ordering::Release);
+        }
+    }
+}
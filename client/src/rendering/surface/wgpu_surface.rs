@@ -1,14 +1,18 @@
+use crate::Moxnotify;
+use crate::manager::NotificationManager;
 use crate::{
+    rendering::wgpu_state::WgpuState,
     utils::buffers::{self, DepthBuffer},
-    wgpu_state::WgpuState,
 };
 use anyhow::Context;
 use config::client::ClientConfig as Config;
-use moxui::{shape_renderer, text_renderer, texture_renderer};
+use glyphon::FontSystem;
+use moxui::{shape_renderer, text_renderer, texture_renderer, viewport};
 use raw_window_handle::{RawWindowHandle, WaylandWindowHandle};
 
 use std::ptr::NonNull;
-use wayland_client::{Proxy, protocol::wl_surface};
+use wayland_client::{Proxy, QueueHandle, protocol::wl_surface};
+use wgpu::wgt::CommandEncoderDescriptor;
 
 pub struct WgpuSurface {
     pub texture_renderer: texture_renderer::TextureRenderer,
@@ -17,6 +21,7 @@ pub struct WgpuSurface {
     pub surface: wgpu::Surface<'static>,
     pub config: wgpu::SurfaceConfiguration,
     pub depth_buffer: buffers::DepthBuffer,
+    viewport: viewport::Viewport,
 }
 
 impl WgpuSurface {
@@ -90,7 +95,10 @@ impl WgpuSurface {
 
         let depth_buffer = DepthBuffer::new(&wgpu_state.device, 1, 1);
 
+        let viewport = viewport::Viewport::new(&wgpu_state.device);
+
         Ok(Self {
+            viewport,
             shape_renderer,
             texture_renderer,
             text_renderer: text_ctx,
@@ -99,4 +107,101 @@ impl WgpuSurface {
             depth_buffer,
         })
     }
+
+    /// The swapchain is managed by wgpu, so a new frame can always be acquired
+    pub fn busy(&self) -> bool {
+        false
+    }
+
+    pub fn render(
+        &mut self,
+        _: &QueueHandle<Moxnotify>,
+        wgpu_state: &WgpuState,
+        _: &wl_surface::WlSurface,
+        font_system: &mut FontSystem,
+        notifications: &NotificationManager,
+    ) -> anyhow::Result<()> {
+        let (device, queue) = (&wgpu_state.device, &wgpu_state.queue);
+
+        let surface_texture = self
+            .surface
+            .get_current_texture()
+            .expect("failed to acquire next swapchain texture");
+        let texture_view = surface_texture
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &texture_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: self.depth_buffer.view(),
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+            multiview_mask: None,
+        });
+
+        let (instances, text_data, textures) = notifications.data();
+
+        log::debug!(
+            "Rendering frame: {} instances, {} text areas, {} textures",
+            instances.len(),
+            text_data.len(),
+            textures.len()
+        );
+
+        self.shape_renderer.prepare(device, queue, &instances);
+        self.texture_renderer.prepare(device, queue, &textures);
+        self.text_renderer
+            .prepare(device, queue, text_data, font_system)?;
+
+        self.shape_renderer.render(&mut render_pass, &self.viewport);
+        self.text_renderer.render(&mut render_pass)?;
+
+        drop(render_pass); // Drop renderpass and release mutable borrow on encoder
+
+        self.texture_renderer
+            .render(&texture_view, &mut encoder, &self.viewport);
+
+        queue.submit(Some(encoder.finish()));
+        surface_texture.present();
+
+        Ok(())
+    }
+
+    pub fn resize(&mut self, wgpu_state: &WgpuState, width: u32, height: u32) {
+        let (device, queue) = (&wgpu_state.device, &wgpu_state.queue);
+
+        if width == self.config.height || height == self.config.width || width == 0 || height == 0 {
+            return;
+        }
+        self.depth_buffer = buffers::DepthBuffer::new(device, width, height);
+        self.config.width = width;
+        self.config.height = height;
+        self.surface.configure(device, &self.config);
+        self.text_renderer
+            .viewport
+            .update(queue, glyphon::Resolution { width, height });
+
+        self.viewport
+            .update(queue, viewport::Resolution { width, height });
+
+        self.texture_renderer
+            .resize(device, self.config.format, width as f32, height as f32);
+    }
 }
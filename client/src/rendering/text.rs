@@ -1,10 +1,75 @@
 use crate::components::Bounds;
+use crate::rendering::glyphon::{self, Attrs, Buffer, FontSystem, Shaping, Weight};
 use crate::styles::Font;
-use glyphon::{Attrs, Buffer, FontSystem, Shaping, Weight};
+use config::client::length::DPI;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, LazyLock, Mutex};
+
+pub static BUFFER_CACHE: LazyLock<BufferCache> = LazyLock::new(BufferCache::default);
+
+/// Upper bound on cached buffers, the cache is flushed once it's exceeded
+const BUFFER_CACHE_CAPACITY: usize = 512;
+
+#[derive(PartialEq, Eq, Hash)]
+pub struct BufferKey {
+    family: Arc<str>,
+    size: u32,
+    weight: u16,
+    metadata: usize,
+    content: u64,
+}
+
+impl BufferKey {
+    pub fn new(
+        family: &Arc<str>,
+        size: f32,
+        weight: Weight,
+        metadata: usize,
+        content: &str,
+    ) -> Self {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+
+        Self {
+            family: Arc::clone(family),
+            size: size.to_bits(),
+            weight: weight.0,
+            metadata,
+            content: hasher.finish(),
+        }
+    }
+}
+
+/// Shaped buffers shared between notifications, so identical strings
+/// (dismiss buttons, repeated action labels, summaries) are only shaped once
+#[derive(Default)]
+pub struct BufferCache(Mutex<HashMap<BufferKey, Buffer>>);
+
+impl BufferCache {
+    pub fn get_or_shape<F>(&self, key: BufferKey, shape: F) -> Buffer
+    where
+        F: FnOnce() -> Buffer,
+    {
+        if let Some(buffer) = self.0.lock().unwrap().get(&key) {
+            return buffer.clone();
+        }
+
+        // Shape without holding the lock so parallel shaping isn't serialized
+        let buffer = shape();
+
+        let mut buffers = self.0.lock().unwrap();
+        if buffers.len() >= BUFFER_CACHE_CAPACITY {
+            buffers.clear();
+        }
+        buffers.insert(key, buffer.clone());
+
+        buffer
+    }
+}
 
 fn create_buffer(font: &Font, font_system: &mut FontSystem, max_width: Option<f32>) -> Buffer {
-    let dpi = 96.0;
-    let font_size = font.size as f32 * dpi / 72.0;
+    let font_size = font.size * DPI / 72.0;
     let mut buffer = Buffer::new(
         font_system,
         glyphon::Metrics::new(font_size, font_size * 1.2),
@@ -25,12 +90,22 @@ impl Text {
     where
         T: AsRef<str>,
     {
-        let attrs = Attrs::new()
-            .metadata(0.6_f32.to_bits() as usize)
-            .family(glyphon::Family::Name(&font.family))
-            .weight(Weight::BOLD);
-        let mut buffer = create_buffer(font, font_system, None);
-        buffer.set_text(font_system, body.as_ref(), &attrs, Shaping::Advanced, None);
+        let key = BufferKey::new(
+            &font.family,
+            font.size,
+            Weight::BOLD,
+            0.6_f32.to_bits() as usize,
+            body.as_ref(),
+        );
+        let buffer = BUFFER_CACHE.get_or_shape(key, || {
+            let attrs = Attrs::new()
+                .metadata(0.6_f32.to_bits() as usize)
+                .family(glyphon::Family::Name(&font.family))
+                .weight(Weight::BOLD);
+            let mut buffer = create_buffer(font, font_system, None);
+            buffer.set_text(font_system, body.as_ref(), &attrs, Shaping::Advanced, None);
+            buffer
+        });
 
         Self {
             buffer,
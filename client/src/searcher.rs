@@ -0,0 +1,59 @@
+use config::SearcherConfig;
+
+/// Reads and clears the notification history through the searcher's HTTP API
+pub struct Searcher {
+    http: reqwest::Client,
+    url: String,
+    /// Read for the token on every request, the searcher may only generate it after
+    /// the client started
+    config: SearcherConfig,
+}
+
+impl Searcher {
+    pub fn new(config: &SearcherConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            http: reqwest::Client::new(),
+            url: format!("http://{}", config.address),
+            config: config.clone(),
+        })
+    }
+
+    async fn post(&self, path: &str, body: serde_json::Value) -> anyhow::Result<String> {
+        let mut request = self
+            .http
+            .post(format!("{}{path}", self.url))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string());
+        if let Some(token) = self.config.token()? {
+            request = request.bearer_auth(token);
+        }
+
+        Ok(request.send().await?.error_for_status()?.text().await?)
+    }
+
+    /// `limit` entries of the history starting at `offset`, newest first, as JSON.
+    /// Only the ones of `app_name` if set
+    pub async fn history(
+        &self,
+        offset: u32,
+        limit: u32,
+        app_name: Option<&str>,
+    ) -> anyhow::Result<Vec<String>> {
+        let body = serde_json::json!({
+            "offset": offset,
+            "limit": limit,
+            "app_name": app_name,
+        });
+        let entries: Vec<serde_json::Value> =
+            serde_json::from_str(&self.post("/api/history", body).await?)?;
+
+        Ok(entries.iter().map(ToString::to_string).collect())
+    }
+
+    /// Delete the whole history, returns how many entries were deleted
+    pub async fn clear(&self) -> anyhow::Result<u64> {
+        Ok(serde_json::from_str(
+            &self.post("/api/clear", serde_json::Value::Null).await?,
+        )?)
+    }
+}
@@ -1,13 +1,17 @@
 //! Minimal local styling types with hardcoded defaults.
 //! This module exists as a bridge until CSS styling (simplecss) is implemented.
 
+use crate::moxnotify::types::NotificationHints;
 pub use config::client::color::Color;
 use config::client::Urgency;
+use config::client::color::parse_hex;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct Font {
-    pub size: u32,
+    /// In points
+    pub size: f32,
     pub family: Arc<str>,
     pub color: Color,
 }
@@ -15,7 +19,7 @@ pub struct Font {
 impl Default for Font {
     fn default() -> Self {
         Self {
-            size: 10,
+            size: 10.,
             family: "DejaVu Sans".into(),
             color: Color::rgba([255, 255, 255, 255]),
         }
@@ -245,6 +249,17 @@ impl Button {
         }
     }
 
+    /// Solid `background`, for the accept and decline buttons of calls
+    pub fn filled(background: [u8; 4]) -> Self {
+        Self {
+            default: ButtonState {
+                background: Color::rgba(background),
+                ..Default::default()
+            },
+            hover: ButtonState::default_hover(),
+        }
+    }
+
     pub fn default_dismiss_unfocused() -> Self {
         Self {
             default: ButtonState::transparent(),
@@ -257,6 +272,8 @@ impl Button {
 pub struct Buttons {
     pub dismiss: Button,
     pub action: Button,
+    pub accept: Button,
+    pub decline: Button,
 }
 
 impl Default for Buttons {
@@ -264,6 +281,8 @@ impl Default for Buttons {
         Self {
             dismiss: Button::default(),
             action: Button::default_action(),
+            accept: Button::filled([158, 206, 106, 255]),
+            decline: Button::filled([247, 118, 142, 255]),
         }
     }
 }
@@ -272,14 +291,15 @@ impl Buttons {
     pub fn unfocused() -> Self {
         Self {
             dismiss: Button::default_dismiss_unfocused(),
-            action: Button::default_action(),
+            ..Default::default()
         }
     }
 }
 
 #[derive(Clone)]
 pub struct TextStyle {
-    pub size: u32,
+    /// In points
+    pub size: f32,
     pub family: Arc<str>,
     pub color: Color,
     pub border: Border,
@@ -289,7 +309,7 @@ pub struct TextStyle {
 impl Default for TextStyle {
     fn default() -> Self {
         Self {
-            size: 10,
+            size: 10.,
             family: "DejaVu Sans".into(),
             color: Color::rgba([255, 255, 255, 255]),
             border: Border {
@@ -352,6 +372,7 @@ impl StyleState {
     }
 }
 
+#[derive(Clone)]
 pub struct UrgencyStyles {
     pub focused: StyleState,
     pub unfocused: StyleState,
@@ -368,7 +389,6 @@ impl Default for UrgencyStyles {
 
 #[derive(Clone)]
 pub struct NotificationCounter {
-    pub format: String,
     pub background: Color,
     pub border: Border,
     pub font: Font,
@@ -377,7 +397,6 @@ pub struct NotificationCounter {
 impl Default for NotificationCounter {
     fn default() -> Self {
         Self {
-            format: "{} more".to_string(),
             background: Color::rgba([30, 30, 46, 200]),
             border: Border::default(),
             font: Font::default(),
@@ -385,12 +404,17 @@ impl Default for NotificationCounter {
     }
 }
 
+#[derive(Clone)]
 pub struct Styles {
     pub urgency_low: UrgencyStyles,
     pub urgency_normal: UrgencyStyles,
     pub urgency_critical: UrgencyStyles,
     pub next: NotificationCounter,
     pub prev: NotificationCounter,
+    /// Box of the command palette, `.palette` in css
+    pub palette: NotificationCounter,
+    /// Overrides for notifications with a matching `category` hint
+    pub categories: HashMap<Box<str>, Arc<Styles>>,
 }
 
 impl Default for Styles {
@@ -401,11 +425,65 @@ impl Default for Styles {
             urgency_critical: UrgencyStyles::default(),
             next: NotificationCounter::default(),
             prev: NotificationCounter::default(),
+            palette: NotificationCounter::default(),
+            categories: HashMap::new(),
         }
     }
 }
 
 impl Styles {
+    /// Styles for notifications of `category`, the base styles if it has no overrides
+    pub fn for_category(self: &Arc<Self>, category: Option<&str>) -> Arc<Self> {
+        category
+            .and_then(|category| self.categories.get(category))
+            .map_or_else(|| Arc::clone(self), Arc::clone)
+    }
+
+    /// Styles with the colors a notification asked for through its hints merged in.
+    /// Only the unfocused state takes them, so focus stays recognizable
+    pub fn with_hints(self: &Arc<Self>, hints: &NotificationHints) -> Arc<Self> {
+        let color = |color: Option<&String>| {
+            color
+                .and_then(|color| parse_hex(color).ok())
+                .map(Color::rgba)
+        };
+        let accent = color(hints.accent.as_ref());
+        let background = color(hints.background.as_ref());
+        let foreground = color(hints.foreground.as_ref());
+        let highlight = color(hints.highlight.as_ref());
+
+        if accent.is_none() && background.is_none() && foreground.is_none() && highlight.is_none() {
+            return Arc::clone(self);
+        }
+
+        let mut styles = (**self).clone();
+        [
+            &mut styles.urgency_low,
+            &mut styles.urgency_normal,
+            &mut styles.urgency_critical,
+        ]
+        .into_iter()
+        .map(|urgency_styles| &mut urgency_styles.unfocused)
+        .for_each(|style| {
+            if let Some(accent) = accent {
+                style.border.color = accent;
+            }
+            if let Some(background) = background {
+                style.background = background;
+            }
+            if let Some(foreground) = foreground {
+                style.font.color = foreground;
+                style.summary.color = foreground;
+                style.body.color = foreground;
+            }
+            if let Some(highlight) = highlight {
+                style.progress.complete_color = highlight;
+            }
+        });
+
+        Arc::new(styles)
+    }
+
     pub fn find_style(&self, urgency: Urgency, focused: bool) -> &StyleState {
         let urgency_styles = match urgency {
             Urgency::Low => &self.urgency_low,
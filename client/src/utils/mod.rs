@@ -1,4 +1,6 @@
+#[cfg(all(feature = "gpu", not(feature = "peek")))]
 pub mod buffers;
+#[cfg(all(feature = "gpu", not(feature = "peek")))]
 pub mod math;
 
 use std::sync::mpsc;
@@ -0,0 +1,292 @@
+use crate::Moxnotify;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::fd::AsFd;
+use std::os::unix::net::UnixStream;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use wayland_client::globals::GlobalList;
+use wayland_client::protocol::{
+    wl_data_device, wl_data_device_manager, wl_data_offer, wl_data_source, wl_seat,
+};
+use wayland_client::{Connection, Dispatch, QueueHandle, delegate_noop, event_created_child};
+use wayland_protocols_wlr::data_control::v1::client::{
+    zwlr_data_control_device_v1, zwlr_data_control_manager_v1, zwlr_data_control_offer_v1,
+    zwlr_data_control_source_v1,
+};
+
+/// Mime types copied text is offered as
+const MIME_TYPES: [&str; 4] = [
+    "text/plain;charset=utf-8",
+    "text/plain",
+    "UTF8_STRING",
+    "STRING",
+];
+
+/// Most bytes read from a pasted selection
+const MAX_PASTE: u64 = 64 * 1024;
+/// How long a paste waits for the selection's owner to send it
+const PASTE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Mime types a data offer was announced with
+#[derive(Default)]
+pub struct MimeTypes(Mutex<Vec<String>>);
+
+/// Sets the selection without a serial, so copying doesn't depend on keyboard focus,
+/// which notifications only get while they're navigated with the keyboard
+struct DataControl {
+    manager: zwlr_data_control_manager_v1::ZwlrDataControlManagerV1,
+    device: zwlr_data_control_device_v1::ZwlrDataControlDeviceV1,
+    source: Option<zwlr_data_control_source_v1::ZwlrDataControlSourceV1>,
+}
+
+/// Selection set by moxnotify, e.g. to copy codes out of notifications, and the
+/// one set by others, pasted into the command palette
+pub struct Clipboard {
+    manager: wl_data_device_manager::WlDataDeviceManager,
+    device: wl_data_device::WlDataDevice,
+    source: Option<wl_data_source::WlDataSource>,
+    selection: Option<wl_data_offer::WlDataOffer>,
+    /// `None` if the compositor doesn't support wlr-data-control
+    data_control: Option<DataControl>,
+}
+
+impl Clipboard {
+    /// `None` if the compositor doesn't support wl_data_device_manager
+    pub fn new(
+        qh: &QueueHandle<Moxnotify>,
+        globals: &GlobalList,
+        wl_seat: &wl_seat::WlSeat,
+    ) -> Option<Self> {
+        let manager: wl_data_device_manager::WlDataDeviceManager =
+            globals.bind(qh, 1..=3, ()).ok()?;
+        let device = manager.get_data_device(wl_seat, qh, ());
+        let data_control = globals
+            .bind::<zwlr_data_control_manager_v1::ZwlrDataControlManagerV1, _, _>(qh, 1..=2, ())
+            .ok()
+            .map(|manager| DataControl {
+                device: manager.get_data_device(wl_seat, qh, ()),
+                manager,
+                source: None,
+            });
+
+        Some(Self {
+            manager,
+            device,
+            source: None,
+            selection: None,
+            data_control,
+        })
+    }
+
+    /// Make `text` the selection. Without wlr-data-control the compositor only takes it
+    /// from the surface with keyboard focus, with `serial` of the input event that asked
+    /// for it
+    pub fn copy(&mut self, qh: &QueueHandle<Moxnotify>, text: Arc<str>, serial: u32) {
+        if let Some(data_control) = self.data_control.as_mut() {
+            let source = data_control.manager.create_data_source(qh, text);
+            MIME_TYPES
+                .into_iter()
+                .for_each(|mime_type| source.offer(mime_type.to_string()));
+            data_control.device.set_selection(Some(&source));
+
+            if let Some(previous) = data_control.source.replace(source) {
+                previous.destroy();
+            }
+            return;
+        }
+
+        let source = self.manager.create_data_source(qh, text);
+        MIME_TYPES
+            .into_iter()
+            .for_each(|mime_type| source.offer(mime_type.to_string()));
+        self.device.set_selection(Some(&source), serial);
+
+        if let Some(previous) = self.source.replace(source) {
+            previous.destroy();
+        }
+    }
+
+    /// Read the selection as text in the background, it's sent as [`crate::Event::Paste`].
+    /// Returns `false` if there's no selection offered as text
+    pub fn paste(&self, event_sender: calloop::channel::Sender<crate::Event>) -> bool {
+        let Some(offer) = self.selection.as_ref() else {
+            return false;
+        };
+        let Some(mime_type) = offer.data::<MimeTypes>().and_then(|offered| {
+            let offered = offered.0.lock().unwrap();
+            MIME_TYPES
+                .into_iter()
+                .find(|mime_type| offered.iter().any(|offered| offered == mime_type))
+        }) else {
+            return false;
+        };
+
+        let (mut reader, writer) = match UnixStream::pair() {
+            Ok(pair) => pair,
+            Err(e) => {
+                log::warn!("Failed to paste the selection: {e}");
+                return false;
+            }
+        };
+        offer.receive(mime_type.to_string(), writer.as_fd());
+        drop(writer);
+
+        // The selection may be ours, which is only sent once the event loop runs again
+        std::thread::spawn(move || {
+            let mut text = String::new();
+            let read = reader
+                .set_read_timeout(Some(PASTE_TIMEOUT))
+                .and_then(|()| (&mut reader).take(MAX_PASTE).read_to_string(&mut text));
+            match read {
+                Ok(_) => _ = event_sender.send(crate::Event::Paste(text)),
+                Err(e) => log::warn!("Failed to read the pasted selection: {e}"),
+            }
+        });
+
+        true
+    }
+}
+
+impl Dispatch<wl_data_source::WlDataSource, Arc<str>> for Moxnotify {
+    fn event(
+        state: &mut Self,
+        source: &wl_data_source::WlDataSource,
+        event: <wl_data_source::WlDataSource as wayland_client::Proxy>::Event,
+        text: &Arc<str>,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_data_source::Event::Send { fd, .. } => {
+                if let Err(e) = File::from(fd).write_all(text.as_bytes()) {
+                    log::warn!("Failed to send the clipboard contents: {e}");
+                }
+            }
+            wl_data_source::Event::Cancelled => {
+                if let Some(clipboard) = state.seat.clipboard.as_mut()
+                    && clipboard.source.as_ref() == Some(source)
+                {
+                    clipboard.source = None;
+                }
+                source.destroy();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<wl_data_device::WlDataDevice, ()> for Moxnotify {
+    fn event(
+        state: &mut Self,
+        _: &wl_data_device::WlDataDevice,
+        event: <wl_data_device::WlDataDevice as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_data_device::Event::Selection { id } => {
+                let previous = match state.seat.clipboard.as_mut() {
+                    Some(clipboard) => std::mem::replace(&mut clipboard.selection, id),
+                    None => id,
+                };
+                if let Some(previous) = previous {
+                    previous.destroy();
+                }
+            }
+            // Nothing is ever dropped into notifications
+            wl_data_device::Event::Enter {
+                id: Some(offer), ..
+            } => offer.destroy(),
+            _ => {}
+        }
+    }
+
+    event_created_child!(Moxnotify, wl_data_device::WlDataDevice, [
+        wl_data_device::EVT_DATA_OFFER_OPCODE => (wl_data_offer::WlDataOffer, MimeTypes::default())
+    ]);
+}
+
+impl Dispatch<wl_data_offer::WlDataOffer, MimeTypes> for Moxnotify {
+    fn event(
+        _: &mut Self,
+        _: &wl_data_offer::WlDataOffer,
+        event: <wl_data_offer::WlDataOffer as wayland_client::Proxy>::Event,
+        mime_types: &MimeTypes,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_data_offer::Event::Offer { mime_type } = event {
+            mime_types.0.lock().unwrap().push(mime_type);
+        }
+    }
+}
+
+delegate_noop!(Moxnotify: wl_data_device_manager::WlDataDeviceManager);
+
+impl Dispatch<zwlr_data_control_source_v1::ZwlrDataControlSourceV1, Arc<str>> for Moxnotify {
+    fn event(
+        state: &mut Self,
+        source: &zwlr_data_control_source_v1::ZwlrDataControlSourceV1,
+        event: <zwlr_data_control_source_v1::ZwlrDataControlSourceV1 as wayland_client::Proxy>::Event,
+        text: &Arc<str>,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_data_control_source_v1::Event::Send { fd, .. } => {
+                if let Err(e) = File::from(fd).write_all(text.as_bytes()) {
+                    log::warn!("Failed to send the clipboard contents: {e}");
+                }
+            }
+            zwlr_data_control_source_v1::Event::Cancelled => {
+                if let Some(data_control) = state
+                    .seat
+                    .clipboard
+                    .as_mut()
+                    .and_then(|clipboard| clipboard.data_control.as_mut())
+                    && data_control.source.as_ref() == Some(source)
+                {
+                    data_control.source = None;
+                }
+                source.destroy();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<zwlr_data_control_device_v1::ZwlrDataControlDeviceV1, ()> for Moxnotify {
+    fn event(
+        state: &mut Self,
+        _: &zwlr_data_control_device_v1::ZwlrDataControlDeviceV1,
+        event: <zwlr_data_control_device_v1::ZwlrDataControlDeviceV1 as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            // Pasting goes through wl_data_device, the palette has keyboard focus then
+            zwlr_data_control_device_v1::Event::Selection { id: Some(offer) }
+            | zwlr_data_control_device_v1::Event::PrimarySelection { id: Some(offer) } => {
+                offer.destroy()
+            }
+            zwlr_data_control_device_v1::Event::Finished => {
+                if let Some(clipboard) = state.seat.clipboard.as_mut()
+                    && let Some(data_control) = clipboard.data_control.take()
+                {
+                    data_control.device.destroy();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    event_created_child!(Moxnotify, zwlr_data_control_device_v1::ZwlrDataControlDeviceV1, [
+        zwlr_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (zwlr_data_control_offer_v1::ZwlrDataControlOfferV1, ())
+    ]);
+}
+
+delegate_noop!(Moxnotify: zwlr_data_control_manager_v1::ZwlrDataControlManagerV1);
+delegate_noop!(Moxnotify: ignore zwlr_data_control_offer_v1::ZwlrDataControlOfferV1);
@@ -0,0 +1,226 @@
+use crate::moxnotify::types::{CloseReason, NewNotification};
+use crate::{EmitEvent, Moxnotify};
+use config::client::AutoInhibit;
+use wayland_client::globals::GlobalList;
+use wayland_client::protocol::wl_seat;
+use wayland_client::{Connection, Dispatch, Proxy, QueueHandle, event_created_child};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::{
+    zwlr_foreign_toplevel_handle_v1, zwlr_foreign_toplevel_manager_v1,
+};
+
+struct Toplevel {
+    handle: zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1,
+    app_id: Option<String>,
+    activated: bool,
+    fullscreen: bool,
+}
+
+/// Windows of other clients, used to focus the application that sent a notification
+/// and to inhibit notifications while some of them are focused
+pub struct ForeignToplevels {
+    _manager: zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1,
+    toplevels: Vec<Toplevel>,
+}
+
+impl ForeignToplevels {
+    /// `None` if the compositor doesn't support wlr-foreign-toplevel-management
+    pub fn new(qh: &QueueHandle<Moxnotify>, globals: &GlobalList) -> Option<Self> {
+        let manager = globals.bind(qh, 1..=3, ()).ok()?;
+
+        Some(Self {
+            _manager: manager,
+            toplevels: Vec::new(),
+        })
+    }
+
+    /// Activate the toplevel whose app_id matches the notification's desktop entry,
+    /// falling back to its app_name. Returns whether one was found
+    pub fn focus(&self, notification: &NewNotification, wl_seat: &wl_seat::WlSeat) -> bool {
+        let desktop_entry = notification
+            .hints
+            .as_ref()
+            .and_then(|hints| hints.desktop_entry.as_deref());
+
+        let Some(toplevel) = [desktop_entry, Some(notification.app_name.as_str())]
+            .into_iter()
+            .flatten()
+            .filter(|id| !id.is_empty())
+            .find_map(|id| {
+                self.toplevels.iter().find(|toplevel| {
+                    toplevel
+                        .app_id
+                        .as_deref()
+                        .is_some_and(|app_id| app_id.eq_ignore_ascii_case(id))
+                })
+            })
+        else {
+            log::debug!(
+                "No toplevel found for notification id={} from '{}'",
+                notification.id,
+                notification.app_name
+            );
+            return false;
+        };
+
+        toplevel.handle.activate(wl_seat);
+        true
+    }
+
+    /// Whether a window matches one of `rules`
+    pub fn inhibits(&self, rules: &[AutoInhibit]) -> bool {
+        self.toplevels.iter().any(|toplevel| {
+            toplevel.app_id.as_deref().is_some_and(|app_id| {
+                rules
+                    .iter()
+                    .any(|rule| rule.matches(app_id, toplevel.activated, toplevel.fullscreen))
+            })
+        })
+    }
+}
+
+impl Moxnotify {
+    /// Inhibit notifications while a window matching `dnd.auto_inhibit` is focused or
+    /// fullscreen, and stop once none is, unless they were inhibited by hand
+    fn auto_inhibit(&mut self) {
+        let Some(toplevels) = self.toplevels.as_ref() else {
+            return;
+        };
+
+        let inhibits = toplevels.inhibits(&self.config.general.dnd.auto_inhibit);
+        if inhibits && !self.notifications.inhibited() {
+            log::info!("Inhibiting notifications while a window is focused");
+            self.notifications.inhibit();
+            self.auto_inhibited = true;
+            _ = self.emit_sender.send(EmitEvent::InhibitStateChanged(true));
+        } else if !inhibits && self.auto_inhibited {
+            self.auto_inhibited = false;
+            if self.notifications.inhibited() {
+                log::info!("Uninhibiting notifications after the window lost focus");
+                self.notifications.uninhibit();
+                _ = self.emit_sender.send(EmitEvent::InhibitStateChanged(false));
+                _ = self
+                    .emit_sender
+                    .send(EmitEvent::WaitingChanged(self.notifications.waiting()));
+                self.update_surface_size();
+                self.request_render();
+            }
+        }
+    }
+
+    /// Focus the window of the application that sent the notification at `x`, `y`
+    /// when it has no actions to invoke instead
+    pub fn focus_sender(&mut self, x: f64, y: f64) {
+        // Also bound for `dnd.auto_inhibit`
+        let Some(toplevels) = self
+            .toplevels
+            .as_ref()
+            .filter(|_| self.config.general.focus_sender)
+        else {
+            return;
+        };
+
+        let Some(notification) = self
+            .notifications
+            .get_by_coordinates(x, y)
+            .map(|notification| notification.data())
+            .filter(|data| data.actions.is_empty())
+        else {
+            return;
+        };
+
+        if !toplevels.focus(notification, &self.seat.wl_seat) {
+            return;
+        }
+
+        let id = notification.id;
+        if !notification
+            .hints
+            .as_ref()
+            .is_some_and(|hints| hints.resident)
+        {
+            self.dismiss_with_reason(id, Some(CloseReason::ReasonDismissedByUser));
+        }
+    }
+}
+
+impl Dispatch<zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1, ()> for Moxnotify {
+    fn event(
+        state: &mut Self,
+        _: &zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1,
+        event: <zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } = event
+            && let Some(toplevels) = state.toplevels.as_mut()
+        {
+            toplevels.toplevels.push(Toplevel {
+                handle: toplevel,
+                app_id: None,
+                activated: false,
+                fullscreen: false,
+            });
+        }
+    }
+
+    event_created_child!(Moxnotify, zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1, ())
+    ]);
+}
+
+impl Dispatch<zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1, ()> for Moxnotify {
+    fn event(
+        state: &mut Self,
+        handle: &zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1,
+        event: <zwlr_foreign_toplevel_handle_v1::ZwlrForeignToplevelHandleV1 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let Some(toplevels) = state.toplevels.as_mut() else {
+            return;
+        };
+
+        match event {
+            zwlr_foreign_toplevel_handle_v1::Event::AppId { app_id } => {
+                if let Some(toplevel) = toplevels
+                    .toplevels
+                    .iter_mut()
+                    .find(|toplevel| toplevel.handle == *handle)
+                {
+                    toplevel.app_id = Some(app_id);
+                }
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::State {
+                state: toplevel_state,
+            } => {
+                if let Some(toplevel) = toplevels
+                    .toplevels
+                    .iter_mut()
+                    .find(|toplevel| toplevel.handle == *handle)
+                {
+                    let states: Vec<_> = toplevel_state
+                        .chunks_exact(4)
+                        .filter_map(|state| state.try_into().ok())
+                        .map(u32::from_ne_bytes)
+                        .collect();
+                    toplevel.activated = states
+                        .contains(&(zwlr_foreign_toplevel_handle_v1::State::Activated as u32));
+                    toplevel.fullscreen = states
+                        .contains(&(zwlr_foreign_toplevel_handle_v1::State::Fullscreen as u32));
+                }
+            }
+            // Sent after every batch of changes to the window
+            zwlr_foreign_toplevel_handle_v1::Event::Done => state.auto_inhibit(),
+            zwlr_foreign_toplevel_handle_v1::Event::Closed => {
+                toplevels
+                    .toplevels
+                    .retain(|toplevel| toplevel.handle != *handle);
+                handle.destroy();
+                state.auto_inhibit();
+            }
+            _ => {}
+        }
+    }
+}
@@ -1,2 +1,5 @@
 mod activation_token;
+pub mod clipboard;
+pub mod foreign_toplevel;
 mod registry;
+pub mod shell;
@@ -1,6 +1,6 @@
 use crate::{Moxnotify, Output};
 use wayland_client::{
-    Connection, Dispatch, QueueHandle,
+    Connection, Dispatch, Proxy, QueueHandle,
     globals::GlobalListContents,
     protocol::{wl_output, wl_registry},
 };
@@ -28,7 +28,26 @@ impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for Moxnotify {
                 }
             }
             wl_registry::Event::GlobalRemove { name } => {
-                state.outputs.retain(|output| output.id != name);
+                let Some(index) = state.outputs.iter().position(|output| output.id == name) else {
+                    return;
+                };
+
+                let output = state.outputs.remove(index);
+                if output.wl_output.version() >= 3 {
+                    output.wl_output.release();
+                }
+
+                if state
+                    .surface
+                    .as_ref()
+                    .is_some_and(|surface| surface.output == Some(name))
+                {
+                    log::info!(
+                        "Output {} was removed, moving notifications to another output",
+                        output.name.as_deref().unwrap_or("unknown")
+                    );
+                    state.recreate_surface();
+                }
             }
             _ => unreachable!(),
         }
@@ -0,0 +1,144 @@
+use crate::Moxnotify;
+use wayland_client::globals::GlobalList;
+use wayland_client::protocol::wl_surface;
+use wayland_client::{Connection, Dispatch, QueueHandle, delegate_noop};
+use wayland_protocols::xdg::shell::client::{xdg_surface, xdg_toplevel, xdg_wm_base};
+use wayland_protocols_wlr::layer_shell::v1::client::{
+    zwlr_layer_shell_v1,
+    zwlr_layer_surface_v1::{self, KeyboardInteractivity},
+};
+
+/// Shell the notification surface is presented with, picked from the advertised globals
+pub enum Shell {
+    Layer(zwlr_layer_shell_v1::ZwlrLayerShellV1),
+    /// Fallback for compositors without wlr-layer-shell, e.g. nested compositors or kiosk
+    /// shells. Notifications are shown in a toplevel, popups would need a parent surface
+    Xdg(xdg_wm_base::XdgWmBase),
+}
+
+impl Shell {
+    pub fn new(qh: &QueueHandle<Moxnotify>, globals: &GlobalList) -> anyhow::Result<Self> {
+        if let Ok(layer_shell) = globals.bind(qh, 1..=5, ()) {
+            return Ok(Self::Layer(layer_shell));
+        }
+
+        log::warn!("wlr-layer-shell is not available, notifications are shown in a window");
+
+        Ok(Self::Xdg(globals.bind(qh, 1..=6, ())?))
+    }
+}
+
+/// Role of the notification surface
+pub enum Role {
+    Layer(zwlr_layer_surface_v1::ZwlrLayerSurfaceV1),
+    Toplevel {
+        xdg_surface: xdg_surface::XdgSurface,
+        toplevel: xdg_toplevel::XdgToplevel,
+    },
+}
+
+impl Role {
+    pub fn toplevel(
+        wm_base: &xdg_wm_base::XdgWmBase,
+        wl_surface: &wl_surface::WlSurface,
+        qh: &QueueHandle<Moxnotify>,
+    ) -> Self {
+        let xdg_surface = wm_base.get_xdg_surface(wl_surface, qh, ());
+        let toplevel = xdg_surface.get_toplevel(qh, ());
+        toplevel.set_title("moxnotify".into());
+        toplevel.set_app_id("moxnotify".into());
+
+        Self::Toplevel {
+            xdg_surface,
+            toplevel,
+        }
+    }
+
+    pub fn set_size(&self, width: u32, height: u32) {
+        match self {
+            Self::Layer(layer_surface) => layer_surface.set_size(width, height),
+            Self::Toplevel { toplevel, .. } => {
+                // Keeps tiling compositors from stretching the window
+                toplevel.set_min_size(width as i32, height as i32);
+                toplevel.set_max_size(width as i32, height as i32);
+            }
+        }
+    }
+
+    /// Toplevels get keyboard focus the way any other window does
+    pub fn set_keyboard_interactivity(&self, keyboard_interactivity: KeyboardInteractivity) {
+        if let Self::Layer(layer_surface) = self {
+            layer_surface.set_keyboard_interactivity(keyboard_interactivity);
+        }
+    }
+
+    pub fn ack_configure(&self, serial: u32) {
+        match self {
+            Self::Layer(layer_surface) => layer_surface.ack_configure(serial),
+            Self::Toplevel { xdg_surface, .. } => xdg_surface.ack_configure(serial),
+        }
+    }
+
+    pub fn destroy(&self) {
+        match self {
+            Self::Layer(layer_surface) => layer_surface.destroy(),
+            Self::Toplevel {
+                xdg_surface,
+                toplevel,
+            } => {
+                toplevel.destroy();
+                xdg_surface.destroy();
+            }
+        }
+    }
+}
+
+impl Dispatch<xdg_wm_base::XdgWmBase, ()> for Moxnotify {
+    fn event(
+        _: &mut Self,
+        wm_base: &xdg_wm_base::XdgWmBase,
+        event: <xdg_wm_base::XdgWmBase as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let xdg_wm_base::Event::Ping { serial } = event {
+            wm_base.pong(serial);
+        }
+    }
+}
+
+impl Dispatch<xdg_surface::XdgSurface, ()> for Moxnotify {
+    fn event(
+        state: &mut Self,
+        _: &xdg_surface::XdgSurface,
+        event: <xdg_surface::XdgSurface as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        if let xdg_surface::Event::Configure { serial } = event {
+            // Sizes suggested by the toplevel are ignored, the window is as big as the notifications
+            let width = state.notifications.width() as u32;
+            let height = state.notifications.height() as u32;
+            state.configure_surface(qh, serial, width, height);
+        }
+    }
+}
+
+impl Dispatch<xdg_toplevel::XdgToplevel, ()> for Moxnotify {
+    fn event(
+        _: &mut Self,
+        _: &xdg_toplevel::XdgToplevel,
+        event: <xdg_toplevel::XdgToplevel as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let xdg_toplevel::Event::Close = event {
+            log::debug!("Ignoring close request, the window goes away with the last notification");
+        }
+    }
+}
+
+delegate_noop!(Moxnotify: zwlr_layer_shell_v1::ZwlrLayerShellV1);
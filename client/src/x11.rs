@@ -0,0 +1,452 @@
+//! Fallback for plain X11 sessions. The scheduler's viewport is shown in an
+//! override-redirect window drawn with the core X font, so markup, icons and
+//! characters outside Latin-1 aren't shown. Left clicks invoke the default action,
+//! right clicks dismiss
+
+use crate::moxnotify::client::client_service_client::ClientServiceClient;
+use crate::moxnotify::client::{
+    ClientActionInvokedRequest, ClientNotificationClosedRequest, ClientNotifyRequest,
+    GetViewportRequest, notification_message,
+};
+use crate::moxnotify::types::{
+    ActionInvoked, Capability, CloseReason, Hello, NewNotification, NotificationClosed,
+};
+use config::types::Compression;
+use futures_lite::stream::StreamExt;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tonic::Request;
+use tonic::codec::CompressionEncoding;
+use tonic::transport::Channel;
+use x11rb::connection::Connection;
+use x11rb::protocol::Event as XEvent;
+use x11rb::protocol::xproto::{
+    ConfigureWindowAux, ConnectionExt, CreateGCAux, CreateWindowAux, EventMask, Rectangle, Window,
+    WindowClass,
+};
+use x11rb::rust_connection::RustConnection;
+
+const WIDTH: u16 = 300;
+/// Distance from the top right corner of the screen
+const MARGIN: i16 = 10;
+const PADDING: i16 = 8;
+const BACKGROUND: u32 = 0x1e1e2e;
+const FOREGROUND: u32 = 0xcdd6f4;
+const SEPARATOR: u32 = 0x45475a;
+
+enum Event {
+    Notify(Box<NewNotification>),
+    Close(u32),
+    Disconnected,
+    X(XEvent),
+}
+
+/// `text` as Latin-1 for the core font, without markup and cut to `max` characters
+fn latin1(text: &str, max: usize) -> Vec<u8> {
+    let mut in_tag = false;
+    let mut bytes: Vec<u8> = text
+        .chars()
+        .filter(|&c| match c {
+            '<' => {
+                in_tag = true;
+                false
+            }
+            '>' if in_tag => {
+                in_tag = false;
+                false
+            }
+            _ => !in_tag,
+        })
+        .map(|c| match c {
+            '\n' | '\t' => b' ',
+            c => u8::try_from(u32::from(c)).unwrap_or(b'?'),
+        })
+        .collect();
+
+    if bytes.len() > max {
+        bytes.truncate(max.saturating_sub(3));
+        bytes.extend_from_slice(b"...");
+    }
+
+    bytes
+}
+
+/// Subscribe to the scheduler and forward notifications, reconnecting with backoff
+async fn subscribe(
+    mut client: ClientServiceClient<Channel>,
+    events: mpsc::UnboundedSender<Event>,
+    max_visible: u32,
+) {
+    let mut backoff = supervisor::Backoff::default();
+    loop {
+        let request = Request::new(ClientNotifyRequest {
+            max_visible,
+            // Images aren't shown, so blob keys save sending their data
+            hello: Some(Hello {
+                protocol_version: config::protocol::VERSION,
+                capabilities: vec![Capability::ImageBlobs as i32],
+                client_id: String::new(),
+            }),
+        });
+        if let Ok(mut stream) = client
+            .notify(request)
+            .await
+            .map(|response| response.into_inner())
+        {
+            log::info!("Connected to scheduler, subscribing to notifications...");
+
+            while let Some(message) = stream.next().await {
+                let Some(message) = message.ok().and_then(|message| message.message) else {
+                    continue;
+                };
+                backoff.reset();
+                let event = match message {
+                    notification_message::Message::Notification(notification) => {
+                        Event::Notify(Box::new(notification))
+                    }
+                    notification_message::Message::CloseNotification(close) => {
+                        Event::Close(close.id)
+                    }
+                };
+                if events.send(event).is_err() {
+                    return;
+                }
+            }
+
+            if events.send(Event::Disconnected).is_err() {
+                return;
+            }
+        }
+
+        let delay = backoff.jittered_delay();
+        log::error!("Disconnected from scheduler, reconnecting in {delay:?}...");
+        tokio::time::sleep(delay).await;
+    }
+}
+
+struct X11 {
+    conn: Arc<RustConnection>,
+    window: Window,
+    gc: u32,
+    separator_gc: u32,
+    line_height: i16,
+    ascent: i16,
+    /// Characters of the fixed width font that fit on a line
+    columns: usize,
+    mapped: bool,
+}
+
+impl X11 {
+    fn new(conn: Arc<RustConnection>, screen_num: usize) -> anyhow::Result<Self> {
+        let screen = &conn.setup().roots[screen_num];
+
+        let font = conn.generate_id()?;
+        conn.open_font(font, b"fixed")?;
+        let metrics = conn.query_font(font)?.reply()?;
+        let char_width = metrics.max_bounds.character_width.max(1) as u16;
+
+        let window = conn.generate_id()?;
+        conn.create_window(
+            x11rb::COPY_DEPTH_FROM_PARENT,
+            window,
+            screen.root,
+            screen.width_in_pixels as i16 - WIDTH as i16 - MARGIN,
+            MARGIN,
+            WIDTH,
+            1,
+            1,
+            WindowClass::INPUT_OUTPUT,
+            x11rb::COPY_FROM_PARENT,
+            &CreateWindowAux::new()
+                .background_pixel(BACKGROUND)
+                .border_pixel(SEPARATOR)
+                .override_redirect(1)
+                .event_mask(EventMask::EXPOSURE | EventMask::BUTTON_PRESS),
+        )?;
+
+        let gc = conn.generate_id()?;
+        conn.create_gc(
+            gc,
+            window,
+            &CreateGCAux::new()
+                .foreground(FOREGROUND)
+                .background(BACKGROUND)
+                .font(font),
+        )?;
+        let separator_gc = conn.generate_id()?;
+        conn.create_gc(
+            separator_gc,
+            window,
+            &CreateGCAux::new().foreground(SEPARATOR),
+        )?;
+        conn.close_font(font)?;
+        conn.flush()?;
+
+        Ok(Self {
+            conn,
+            window,
+            gc,
+            separator_gc,
+            line_height: metrics.font_ascent + metrics.font_descent,
+            ascent: metrics.font_ascent,
+            columns: usize::from((WIDTH - 2 * PADDING as u16) / char_width),
+            mapped: false,
+        })
+    }
+
+    /// Summary and the start of the body, separated from the next by a line
+    fn entry_height(&self) -> i16 {
+        2 * PADDING + 2 * self.line_height + 1
+    }
+
+    fn draw(&mut self, notifications: &[&NewNotification]) -> anyhow::Result<()> {
+        if notifications.is_empty() {
+            if self.mapped {
+                self.conn.unmap_window(self.window)?;
+                self.mapped = false;
+            }
+            self.conn.flush()?;
+            return Ok(());
+        }
+
+        let height = self.entry_height() as u32 * notifications.len() as u32 - 1;
+        self.conn
+            .configure_window(self.window, &ConfigureWindowAux::new().height(height))?;
+        if !self.mapped {
+            self.conn.map_window(self.window)?;
+            self.mapped = true;
+        }
+        self.conn.clear_area(false, self.window, 0, 0, 0, 0)?;
+
+        for (i, notification) in notifications.iter().enumerate() {
+            let top = i as i16 * self.entry_height();
+            if i > 0 {
+                self.conn.poly_fill_rectangle(
+                    self.window,
+                    self.separator_gc,
+                    &[Rectangle {
+                        x: 0,
+                        y: top - 1,
+                        width: WIDTH,
+                        height: 1,
+                    }],
+                )?;
+            }
+
+            let summary = latin1(&notification.summary, self.columns);
+            let body = latin1(&notification.body, self.columns);
+            let baseline = top + PADDING + self.ascent;
+            self.conn
+                .image_text8(self.window, self.gc, PADDING, baseline, &summary)?;
+            self.conn.image_text8(
+                self.window,
+                self.gc,
+                PADDING,
+                baseline + self.line_height,
+                &body,
+            )?;
+        }
+
+        self.conn.flush()?;
+        Ok(())
+    }
+}
+
+struct State {
+    client: ClientServiceClient<Channel>,
+    notifications: Vec<NewNotification>,
+    /// IDs in the scheduler's viewport, in display order
+    focused_ids: Vec<u32>,
+}
+
+impl State {
+    fn visible(&self) -> Vec<&NewNotification> {
+        self.focused_ids
+            .iter()
+            .filter_map(|id| self.notifications.iter().find(|n| n.id == *id))
+            .collect()
+    }
+
+    async fn refresh_viewport(&mut self) -> Result<(), tonic::Status> {
+        self.focused_ids = self
+            .client
+            .get_viewport(Request::new(GetViewportRequest {}))
+            .await?
+            .into_inner()
+            .focused_ids;
+
+        Ok(())
+    }
+
+    async fn close(
+        &mut self,
+        id: u32,
+        uuid: String,
+        reason: CloseReason,
+    ) -> Result<(), tonic::Status> {
+        self.client
+            .notification_closed(Request::new(ClientNotificationClosedRequest {
+                notification_closed: Some(NotificationClosed {
+                    id,
+                    reason: reason as i32,
+                    uuid,
+                }),
+            }))
+            .await?;
+        self.notifications.retain(|n| n.id != id);
+
+        Ok(())
+    }
+
+    /// Left click invokes the default action when there's one, right click and
+    /// clicks without one dismiss
+    async fn click(
+        &mut self,
+        notification: NewNotification,
+        button: u8,
+    ) -> Result<(), tonic::Status> {
+        let default = notification
+            .actions
+            .iter()
+            .any(|action| action.key == "default");
+        if button != 1 || !default {
+            return self
+                .close(
+                    notification.id,
+                    notification.uuid,
+                    CloseReason::ReasonDismissedByUser,
+                )
+                .await;
+        }
+
+        self.client
+            .action_invoked(Request::new(ClientActionInvokedRequest {
+                action_invoked: Some(ActionInvoked {
+                    id: notification.id,
+                    action_key: "default".to_string(),
+                    token: String::new(),
+                    uuid: notification.uuid.clone(),
+                }),
+            }))
+            .await?;
+
+        let resident = notification
+            .hints
+            .as_ref()
+            .is_some_and(|hints| hints.resident);
+        if !resident {
+            self.close(
+                notification.id,
+                notification.uuid,
+                CloseReason::ReasonCloseNotificationCall,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Show notifications on the X11 display in `DISPLAY` until it goes away
+pub async fn run(config: config::Config) -> anyhow::Result<()> {
+    let (conn, screen_num) = x11rb::connect(None)?;
+    let conn = Arc::new(conn);
+    let mut x11 = X11::new(Arc::clone(&conn), screen_num)?;
+
+    let address = format!("http://{}", config.scheduler.address);
+    log::info!("Connecting to scheduler at: {}", address);
+    let mut client = ClientServiceClient::connect(address)
+        .await?
+        .accept_compressed(CompressionEncoding::Zstd);
+    if config.client.compression == Compression::Zstd {
+        client = client.send_compressed(CompressionEncoding::Zstd);
+    }
+
+    let (events, mut receiver) = mpsc::unbounded_channel();
+    tokio::spawn(subscribe(
+        client.clone(),
+        events.clone(),
+        config.client.general.max_visible as u32,
+    ));
+    // Replies are blocking, so X events are waited for on a thread of their own
+    std::thread::spawn(move || {
+        loop {
+            match conn.wait_for_event() {
+                Ok(event) => {
+                    if events.send(Event::X(event)).is_err() {
+                        return;
+                    }
+                }
+                Err(e) => {
+                    log::error!("Lost the connection to the X server: {e}");
+                    return;
+                }
+            }
+        }
+    });
+
+    let mut state = State {
+        client,
+        notifications: Vec::new(),
+        focused_ids: Vec::new(),
+    };
+
+    while let Some(event) = receiver.recv().await {
+        let result = match event {
+            Event::Notify(notification) => {
+                match state
+                    .notifications
+                    .iter_mut()
+                    .find(|n| n.id == notification.id)
+                {
+                    Some(existing) => *existing = *notification,
+                    None => state.notifications.push(*notification),
+                }
+                state.refresh_viewport().await
+            }
+            Event::Close(id) => {
+                state.notifications.retain(|n| n.id != id);
+                state.refresh_viewport().await
+            }
+            Event::Disconnected => {
+                state.notifications.clear();
+                state.focused_ids.clear();
+                Ok(())
+            }
+            Event::X(XEvent::ButtonPress(press)) => {
+                let index = usize::try_from(press.event_y / x11.entry_height()).unwrap_or_default();
+                match state.visible().get(index).map(|n| (*n).clone()) {
+                    Some(notification) => match state.click(notification, press.detail).await {
+                        Ok(()) => state.refresh_viewport().await,
+                        Err(e) => Err(e),
+                    },
+                    None => Ok(()),
+                }
+            }
+            Event::X(XEvent::Expose(_)) => Ok(()),
+            Event::X(_) => continue,
+        };
+        if let Err(e) = result {
+            log::error!("Scheduler request failed: {}", e.message());
+        }
+
+        x11.draw(&state.visible())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latin1_strips_markup_and_truncates() {
+        assert_eq!(
+            latin1("<b>Build</b> failed\non main", 40),
+            b"Build failed on main"
+        );
+        assert_eq!(latin1("Zażółć", 40), b"Za?\xf3??");
+        assert_eq!(latin1("0123456789", 8), b"01234...");
+    }
+}
@@ -0,0 +1,32 @@
+use crate::moxnotify::types::NewNotification;
+use config::CategoryConfig;
+use std::collections::HashMap;
+
+impl NewNotification {
+    /// Fill in the icon and sound configured for the notification's category,
+    /// values provided by the sender take precedence
+    pub fn apply_category(&mut self, categories: &HashMap<String, CategoryConfig>) {
+        let Some(hints) = self.hints.as_mut() else {
+            return;
+        };
+        let Some(category) = hints
+            .category
+            .as_deref()
+            .and_then(|category| categories.get(category))
+        else {
+            return;
+        };
+
+        if self.app_icon.is_none() {
+            self.app_icon = category.icon.clone();
+        }
+
+        if hints.sound_file.is_none() && hints.sound_name.is_none() {
+            hints.sound_file = category
+                .sound_file
+                .as_ref()
+                .map(|path| path.to_string_lossy().into_owned());
+            hints.sound_name = category.sound_name.clone();
+        }
+    }
+}
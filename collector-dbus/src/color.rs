@@ -0,0 +1,45 @@
+use config::client::color::parse_hex;
+
+/// `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`, normalized to `#rrggbbaa`
+fn sanitize(color: &str) -> Option<String> {
+    let color = color.trim();
+    // parse_hex slices by byte, so anything but ascii would panic
+    if !color
+        .strip_prefix('#')
+        .is_some_and(|hex| hex.chars().all(|c| c.is_ascii_hexdigit()))
+    {
+        return None;
+    }
+
+    let [r, g, b, a] = parse_hex(color).ok()?;
+    Some(format!("#{r:02x}{g:02x}{b:02x}{a:02x}"))
+}
+
+/// Color hint `name` set to `value`, `None` if it isn't a color
+pub fn hint(name: &str, value: zbus::zvariant::Value<'_>) -> Option<String> {
+    let color = zbus::zvariant::Str::try_from(value)
+        .ok()
+        .and_then(|color| sanitize(&color));
+    if color.is_none() {
+        log::warn!("Invalid {name} hint, expected a hex color");
+    }
+
+    color
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitizes_colors() {
+        assert_eq!(sanitize("#1E1e2E"), Some("#1e1e2eff".to_string()));
+        assert_eq!(sanitize(" #f00 "), Some("#ff0000ff".to_string()));
+        assert_eq!(sanitize("#f008"), Some("#ff000088".to_string()));
+        assert_eq!(sanitize("#11223344"), Some("#11223344".to_string()));
+        assert_eq!(sanitize("red"), None);
+        assert_eq!(sanitize("#12345"), None);
+        assert_eq!(sanitize("#ggg"), None);
+        assert_eq!(sanitize("#aébcd"), None);
+    }
+}
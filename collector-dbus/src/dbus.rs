@@ -1,144 +1,27 @@
+use crate::color;
+use crate::desktop_entry::DesktopEntries;
 use crate::moxnotify::types::{
-    Action, CloseReason, Image, ImageData, NewNotification, NotificationHints, Urgency,
+    Action, CloseReason, Image, ImageData, Latency, NewNotification, NotificationHints, Urgency,
 };
 use crate::{EmitEvent, Event};
 use chrono::offset::Local;
 use std::collections::HashMap;
-use std::str::FromStr;
 use std::sync::Arc;
 use tokio::sync::broadcast;
+use tokio_stream::StreamExt;
 use zbus::{
-    fdo::RequestNameFlags,
+    fdo::{DBusProxy, RequestNameFlags},
+    names::BusName,
     object_server::SignalEmitter,
-    zvariant::{Signature, Str, Structure},
+    zvariant::Str,
 };
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-impl<'a> TryFrom<Structure<'a>> for ImageData {
-    type Error = zbus::Error;
-
-    fn try_from(value: Structure<'a>) -> zbus::Result<Self> {
-        if Ok(value.signature()) != Signature::from_str("(iiibiiay)").as_ref() {
-            return Err(zbus::Error::Failure(format!(
-                "Invalid ImageData: invalid signature {}",
-                value.signature().to_string()
-            )));
-        }
-
-        let mut fields = value.into_fields();
-
-        if fields.len() != 7 {
-            return Err(zbus::Error::Failure(
-                "Invalid ImageData: missing fields".to_string(),
-            ));
-        }
-
-        let data = Vec::<u8>::try_from(fields.remove(6))
-            .map_err(|e| zbus::Error::Failure(format!("data: {e}")))?;
-        let channels = i32::try_from(fields.remove(5))
-            .map_err(|e| zbus::Error::Failure(format!("channels: {e}")))?;
-        let bits_per_sample = i32::try_from(fields.remove(4))
-            .map_err(|e| zbus::Error::Failure(format!("bits_per_sample: {e}")))?;
-        let rowstride = i32::try_from(fields.remove(2))
-            .map_err(|e| zbus::Error::Failure(format!("rowstride: {e}")))?;
-        let height = i32::try_from(fields.remove(1))
-            .map_err(|e| zbus::Error::Failure(format!("height: {e}")))?;
-        let width = i32::try_from(fields.remove(0))
-            .map_err(|e| zbus::Error::Failure(format!("width: {e}")))?;
-
-        if width <= 0 {
-            return Err(zbus::Error::Failure(
-                "Invalid ImageData: width is not positive".to_string(),
-            ));
-        }
-
-        if height <= 0 {
-            return Err(zbus::Error::Failure(
-                "Invalid ImageData: height is not positive".to_string(),
-            ));
-        }
-
-        if bits_per_sample != 8 {
-            return Err(zbus::Error::Failure(
-                "Invalid ImageData: bits_per_sample is not 8".to_string(),
-            ));
-        }
-
-        // Validate input data length
-        let expected_input_len = (rowstride * height) as usize;
-        if data.len() != expected_input_len {
-            return Err(zbus::Error::Failure(
-                "Invalid ImageData: data length does not match rowstride * height".to_string(),
-            ));
-        }
-
-        // Convert to 4-channel RGBA format
-        let width = width as u32;
-        let height = height as u32;
-        let channels = channels as usize;
-        let mut rgba_data = Vec::with_capacity((width * height * 4) as usize);
-
-        for y in 0..height {
-            let row_start = (y * rowstride as u32) as usize;
-            for x in 0..width {
-                let pixel_start = row_start + (x as usize * channels);
-
-                if pixel_start + channels > data.len() {
-                    return Err(zbus::Error::Failure(
-                        "Invalid ImageData: pixel data out of bounds".to_string(),
-                    ));
-                }
-
-                match channels {
-                    1 => {
-                        // Grayscale -> RGBA
-                        let gray = data[pixel_start];
-                        rgba_data.extend_from_slice(&[gray, gray, gray, 255]);
-                    }
-                    2 => {
-                        // Grayscale + Alpha -> RGBA
-                        let gray = data[pixel_start];
-                        let alpha = data[pixel_start + 1];
-                        rgba_data.extend_from_slice(&[gray, gray, gray, alpha]);
-                    }
-                    3 => {
-                        // RGB -> RGBA
-                        rgba_data.extend_from_slice(&[
-                            data[pixel_start],
-                            data[pixel_start + 1],
-                            data[pixel_start + 2],
-                            255,
-                        ]);
-                    }
-                    4 => {
-                        // RGBA -> RGBA (just copy)
-                        rgba_data.extend_from_slice(&[
-                            data[pixel_start],
-                            data[pixel_start + 1],
-                            data[pixel_start + 2],
-                            data[pixel_start + 3],
-                        ]);
-                    }
-                    _ => {
-                        return Err(zbus::Error::Failure(format!(
-                            "Invalid ImageData: unsupported channel count {channels}"
-                        )));
-                    }
-                }
-            }
-        }
-
-        Ok(Self {
-            width,
-            height,
-            data: rgba_data,
-        })
-    }
-}
+const NOTIFICATIONS_NAME: &str = "org.freedesktop.Notifications";
 
 impl NotificationHints {
-    fn new(hints: HashMap<&str, zbus::zvariant::Value<'_>>) -> Self {
+    fn new(hints: HashMap<&str, zbus::zvariant::Value<'_>>, max_image_dimension: u32) -> Self {
         hints
             .into_iter()
             .fold(NotificationHints::default(), |mut nh, (k, v)| {
@@ -222,7 +105,7 @@ impl NotificationHints {
                     }
                     "image-data" | "image_data" | "icon_data" => {
                         if let zbus::zvariant::Value::Structure(v) = v {
-                            if let Ok(image) = ImageData::try_from(v) {
+                            if let Ok(image) = ImageData::from_structure(v, max_image_dimension) {
                                 nh.image = Some(Image {
                                     image: Some(crate::moxnotify::types::image::Image::Data(image)),
                                 });
@@ -231,6 +114,29 @@ impl NotificationHints {
                             }
                         }
                     }
+                    // Either a dict or "key=action,..." for senders that can only pass strings
+                    "x-moxnotify-keybinds" => match v {
+                        zbus::zvariant::Value::Dict(dict) => {
+                            match HashMap::<String, String>::try_from(dict) {
+                                Ok(keybinds) => nh.keybinds = keybinds,
+                                Err(e) => log::warn!("Invalid keybinds: {e}"),
+                            }
+                        }
+                        zbus::zvariant::Value::Str(s) => {
+                            nh.keybinds = s
+                                .split(',')
+                                .filter_map(|keybind| keybind.split_once('='))
+                                .map(|(key, action)| {
+                                    (key.trim().to_string(), action.trim().to_string())
+                                })
+                                .collect();
+                        }
+                        _ => log::warn!("Invalid keybinds"),
+                    },
+                    "x-moxnotify-accent" | "frcolor" => nh.accent = color::hint(k, v),
+                    "bgcolor" => nh.background = color::hint(k, v),
+                    "fgcolor" => nh.foreground = color::hint(k, v),
+                    "hlcolor" => nh.highlight = color::hint(k, v),
                     _ => log::warn!("Unknown hint: {k}"),
                 }
                 nh
@@ -243,6 +149,9 @@ struct NotificationsImpl {
     event_sender: tokio::sync::mpsc::Sender<Event>,
     uuid: String,
     config: Arc<config::Config>,
+    desktop_entries: DesktopEntries,
+    /// Desktop file IDs of D-Bus activatable applications by notification ID
+    activatable: HashMap<u32, Box<str>>,
 }
 
 #[zbus::interface(name = "org.freedesktop.Notifications")]
@@ -287,7 +196,7 @@ impl NotificationsImpl {
             Some(app_icon.to_string())
         };
 
-        let hints = NotificationHints::new(hints);
+        let hints = NotificationHints::new(hints, self.config.collector.limits.max_image_dimension);
         let timeout = if expire_timeout == -1 {
             match Urgency::try_from(hints.urgency).unwrap() {
                 Urgency::Low => self.config.collector.default_timeout.urgency_low * 1000,
@@ -298,26 +207,77 @@ impl NotificationsImpl {
             expire_timeout
         };
 
+        let timestamp = Local::now().timestamp_millis();
+        let mut notification = NewNotification {
+            id,
+            app_name: app_name.into(),
+            summary: summary.into(),
+            body: body.into(),
+            timeout,
+            actions: actions
+                .chunks_exact(2)
+                .map(|action| Action {
+                    key: action[0].to_string(),
+                    label: action[1].to_string(),
+                })
+                .collect(),
+            hints: Some(hints),
+            app_icon,
+            timestamp,
+            client_timestamp: timestamp,
+            uuid: self.uuid.clone(),
+            latency: Some(Latency {
+                collected_at: timestamp,
+                ..Default::default()
+            }),
+        };
+
+        let desktop_id = notification
+            .hints
+            .as_ref()
+            .and_then(|hints| hints.desktop_entry.as_deref())
+            .map(|id| id.strip_suffix(".desktop").unwrap_or(id).to_string());
+        let entry = desktop_id
+            .as_deref()
+            .and_then(|desktop_id| self.desktop_entries.get(desktop_id));
+        if let Some(entry) = entry.as_ref() {
+            notification.apply_desktop_entry(entry);
+        }
+        match (desktop_id, entry) {
+            (Some(desktop_id), Some(entry)) if entry.dbus_activatable => {
+                self.activatable.insert(id, desktop_id.into());
+            }
+            _ => {
+                self.activatable.remove(&id);
+            }
+        }
+
+        notification.enforce_limits(&self.config.collector.limits);
+        notification.apply_category(&self.config.categories);
+        notification.transcode_image(&self.config.collector.image);
+
+        // Calls stay up until they're answered or declined
+        if let Some(hints) = notification.hints.as_mut()
+            && hints
+                .category
+                .as_ref()
+                .is_some_and(|category| self.config.collector.call_categories.contains(category))
+        {
+            if !hints.tags.iter().any(|tag| tag == config::tags::CALL) {
+                hints.tags.push(config::tags::CALL.to_string());
+            }
+            notification.timeout = 0;
+        }
+
+        // Notifications that never expire by default are left alone
+        let reading_speed = &self.config.collector.reading_speed;
+        if expire_timeout == -1 && reading_speed.enabled && notification.timeout != 0 {
+            notification.timeout = notification.reading_timeout(reading_speed);
+        }
+
         if let Err(e) = self
             .event_sender
-            .send(Event::Notify(Box::new(NewNotification {
-                id,
-                app_name: app_name.into(),
-                summary: summary.into(),
-                body: body.into(),
-                timeout,
-                actions: actions
-                    .chunks_exact(2)
-                    .map(|action| Action {
-                        key: action[0].to_string(),
-                        label: action[1].to_string(),
-                    })
-                    .collect(),
-                hints: Some(hints),
-                app_icon,
-                timestamp: Local::now().timestamp_millis(),
-                uuid: self.uuid.clone(),
-            })))
+            .send(Event::Notify(Box::new(notification)))
             .await
         {
             log::error!("Error: {e}");
@@ -362,32 +322,116 @@ impl NotificationsImpl {
     ) -> zbus::Result<()>;
 }
 
+struct CollectorInterface;
+
+#[zbus::interface(name = "pl.mox.Notify.Collector")]
+impl CollectorInterface {
+    #[zbus(signal)]
+    async fn name_ownership_changed(
+        signal_emitter: &SignalEmitter<'_>,
+        owned: bool,
+    ) -> zbus::Result<()>;
+}
+
+/// Describe whoever currently owns org.freedesktop.Notifications,
+/// e.g. "mako (pid 1234, :1.42)"
+async fn describe_owner(conn: &zbus::Connection) -> Option<String> {
+    let dbus = DBusProxy::new(conn).await.ok()?;
+    let name = BusName::try_from(NOTIFICATIONS_NAME).ok()?;
+    let owner = dbus.get_name_owner(name.clone()).await.ok()?;
+    let pid = dbus.get_connection_unix_process_id(name).await.ok();
+    let process = pid
+        .and_then(|pid| std::fs::read_to_string(format!("/proc/{pid}/comm")).ok())
+        .map(|comm| comm.trim().to_string());
+
+    Some(match (process, pid) {
+        (Some(process), Some(pid)) => format!("{process} (pid {pid}, {owner})"),
+        (None, Some(pid)) => format!("pid {pid} ({owner})"),
+        _ => owner.to_string(),
+    })
+}
+
+/// Raise the existing window of a running application through org.freedesktop.Application,
+/// applications that aren't running are left alone rather than launched
+async fn activate(conn: &zbus::Connection, desktop_id: &str, token: &str) -> zbus::Result<()> {
+    let dbus = DBusProxy::new(conn).await?;
+    if !dbus.name_has_owner(BusName::try_from(desktop_id)?).await? {
+        log::debug!("{desktop_id} is not running, not activating it");
+        return Ok(());
+    }
+
+    let path = format!("/{}", desktop_id.replace('.', "/").replace('-', "_"));
+    let mut platform_data = HashMap::new();
+    if !token.is_empty() {
+        platform_data.insert("activation-token", zbus::zvariant::Value::from(token));
+        platform_data.insert("desktop-startup-id", zbus::zvariant::Value::from(token));
+    }
+
+    conn.call_method(
+        Some(desktop_id),
+        path.as_str(),
+        Some("org.freedesktop.Application"),
+        "Activate",
+        &(platform_data,),
+    )
+    .await?;
+
+    Ok(())
+}
+
 pub async fn serve(
     event_sender: tokio::sync::mpsc::Sender<Event>,
     mut emit_receiver: broadcast::Receiver<EmitEvent>,
     uuid: String,
     config: Arc<config::Config>,
 ) -> zbus::Result<()> {
+    let replace_existing = config.collector.replace_existing;
+    let allow_replacement = config.collector.allow_replacement;
     let server = NotificationsImpl {
         next_id: 1,
         event_sender,
         uuid: uuid.clone(),
         config,
+        desktop_entries: DesktopEntries::new(),
+        activatable: HashMap::new(),
     };
 
     let conn = zbus::connection::Builder::session()?
         .serve_at("/org/freedesktop/Notifications", server)?
+        .serve_at("/pl/mox/Notify/Collector", CollectorInterface)?
+        .serve_at(logging::PATH, logging::LogInterface)?
         .build()
         .await?;
 
+    // Subscribe before requesting the name so the initial NameAcquired isn't missed
+    let dbus = DBusProxy::new(&conn).await?;
+    let mut name_acquired = dbus.receive_name_acquired().await?;
+    let mut name_lost = dbus.receive_name_lost().await?;
+
+    let flags = [
+        Some(RequestNameFlags::DoNotQueue),
+        allow_replacement.then_some(RequestNameFlags::AllowReplacement),
+        replace_existing.then_some(RequestNameFlags::ReplaceExisting),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
     if let Err(e) = conn
-        .request_name_with_flags(
-            "org.freedesktop.Notifications",
-            RequestNameFlags::DoNotQueue.into(),
-        )
+        .request_name_with_flags(NOTIFICATIONS_NAME, flags)
         .await
     {
-        log::error!("{e}, is another daemon running?");
+        match describe_owner(&conn).await {
+            Some(owner) if replace_existing => log::error!(
+                "{NOTIFICATIONS_NAME} is owned by {owner}, which does not allow replacement. Stop it before starting moxnotify"
+            ),
+            Some(owner) => log::error!(
+                "{NOTIFICATIONS_NAME} is already owned by {owner}. Stop it or set collector.replace_existing = true to take over"
+            ),
+            None => log::error!(
+                "Failed to acquire {NOTIFICATIONS_NAME}: {e}, is another daemon running?"
+            ),
+        }
         std::process::exit(0);
     }
 
@@ -396,58 +440,105 @@ pub async fn serve(
         .interface::<_, NotificationsImpl>("/org/freedesktop/Notifications")
         .await?;
 
+    let collector_iface = conn
+        .object_server()
+        .interface::<_, CollectorInterface>("/pl/mox/Notify/Collector")
+        .await?;
+
     loop {
-        match emit_receiver.recv().await {
-            Ok(EmitEvent::ActionInvoked(action)) => {
-                log::info!(
-                    "{} action invoked for notification with ID: {}.",
-                    action.action_key,
-                    action.id
-                );
-
-                _ = NotificationsImpl::activation_token(
-                    iface.signal_emitter(),
-                    action.id,
-                    &action.token,
-                )
-                .await;
-
-                _ = NotificationsImpl::action_invoked(
-                    iface.signal_emitter(),
-                    action.id,
-                    &action.action_key,
-                )
-                .await;
+        tokio::select! {
+            Some(acquired) = name_acquired.next() => {
+                if acquired
+                    .args()
+                    .is_ok_and(|args| args.name().as_str() == NOTIFICATIONS_NAME)
+                {
+                    log::info!("Acquired {NOTIFICATIONS_NAME}");
+                    _ = CollectorInterface::name_ownership_changed(
+                        collector_iface.signal_emitter(),
+                        true,
+                    )
+                    .await;
+                }
             }
-            Ok(EmitEvent::NotificationClosed(closed)) => {
-                let reason = match closed.reason() {
-                    CloseReason::ReasonExpired => 1,
-                    CloseReason::ReasonDismissedByUser => 2,
-                    CloseReason::ReasonCloseNotificationCall => 3,
-                    CloseReason::ReasonUnknown => 4,
-                };
-
-                if closed.uuid == uuid {
+            Some(lost) = name_lost.next() => {
+                if lost
+                    .args()
+                    .is_ok_and(|args| args.name().as_str() == NOTIFICATIONS_NAME)
+                {
+                    match describe_owner(&conn).await {
+                        Some(owner) => log::warn!("{NOTIFICATIONS_NAME} was taken over by {owner}, exiting"),
+                        None => log::warn!("Lost ownership of {NOTIFICATIONS_NAME}, exiting"),
+                    }
+                    _ = CollectorInterface::name_ownership_changed(
+                        collector_iface.signal_emitter(),
+                        false,
+                    )
+                    .await;
+                    std::process::exit(0);
+                }
+            }
+            event = emit_receiver.recv() => match event {
+                Ok(EmitEvent::ActionInvoked(action)) => {
                     log::info!(
-                        "Notification with ID: {} was closed. Reason: {:?}",
-                        closed.id,
-                        closed.reason()
+                        "{} action invoked for notification with ID: {}.",
+                        action.action_key,
+                        action.id
                     );
 
-                    _ = NotificationsImpl::notification_closed(
+                    _ = NotificationsImpl::activation_token(
                         iface.signal_emitter(),
-                        closed.id,
-                        reason,
+                        action.id,
+                        &action.token,
                     )
                     .await;
-                } else {
-                    log::debug!(
-                        "Notification with ID: {} was closed but uuid doesn't match, ignoring.",
-                        closed.id,
-                    );
+
+                    _ = NotificationsImpl::action_invoked(
+                        iface.signal_emitter(),
+                        action.id,
+                        &action.action_key,
+                    )
+                    .await;
+
+                    if action.action_key == "default" && action.uuid == uuid {
+                        let desktop_id = iface.get().await.activatable.get(&action.id).cloned();
+                        if let Some(desktop_id) = desktop_id
+                            && let Err(e) = activate(&conn, &desktop_id, &action.token).await
+                        {
+                            log::warn!("Failed to activate {desktop_id}: {e}");
+                        }
+                    }
                 }
-            }
-            _ => {}
+                Ok(EmitEvent::NotificationClosed(closed)) => {
+                    let reason = match closed.reason() {
+                        CloseReason::ReasonExpired => 1,
+                        CloseReason::ReasonDismissedByUser => 2,
+                        CloseReason::ReasonCloseNotificationCall => 3,
+                        CloseReason::ReasonUnknown => 4,
+                    };
+
+                    if closed.uuid == uuid {
+                        iface.get_mut().await.activatable.remove(&closed.id);
+                        log::info!(
+                            "Notification with ID: {} was closed. Reason: {:?}",
+                            closed.id,
+                            closed.reason()
+                        );
+
+                        _ = NotificationsImpl::notification_closed(
+                            iface.signal_emitter(),
+                            closed.id,
+                            reason,
+                        )
+                        .await;
+                    } else {
+                        log::debug!(
+                            "Notification with ID: {} was closed but uuid doesn't match, ignoring.",
+                            closed.id,
+                        );
+                    }
+                }
+                _ => {}
+            },
         }
     }
 }
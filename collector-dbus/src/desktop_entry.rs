@@ -0,0 +1,201 @@
+use crate::moxnotify::types::NewNotification;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Fields of a .desktop file that are relevant to notifications
+#[derive(Debug, Default, PartialEq)]
+pub struct DesktopEntry {
+    /// `Name`, localized to the current locale where available
+    pub name: Option<String>,
+    pub icon: Option<String>,
+    /// Implements org.freedesktop.Application, so it can be activated over D-Bus
+    pub dbus_activatable: bool,
+}
+
+impl DesktopEntry {
+    fn parse(contents: &str, locales: &[String]) -> Self {
+        let mut entry = DesktopEntry::default();
+        // Index into `locales` of the best localized name found so far
+        let mut name_rank = usize::MAX;
+        let mut in_entry = false;
+
+        for line in contents.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_entry = line == "[Desktop Entry]";
+                continue;
+            }
+
+            if !in_entry || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+
+            match key.split_once('[') {
+                Some(("Name", locale)) => {
+                    let locale = locale.trim_end_matches(']');
+                    if let Some(rank) = locales.iter().position(|l| l == locale)
+                        && rank < name_rank
+                    {
+                        name_rank = rank;
+                        entry.name = Some(value.to_string());
+                    }
+                }
+                None if key == "Name" && entry.name.is_none() => {
+                    entry.name = Some(value.to_string());
+                }
+                None if key == "Icon" => entry.icon = Some(value.to_string()),
+                None if key == "DBusActivatable" => entry.dbus_activatable = value == "true",
+                _ => {}
+            }
+        }
+
+        entry
+    }
+}
+
+/// Locale keys in order of preference as described by the Desktop Entry
+/// Specification, e.g. `sr_YU.UTF-8@Latn` yields `sr_YU@Latn`, `sr_YU`, `sr@Latn`, `sr`
+fn locales(locale: &str) -> Vec<String> {
+    let (locale, modifier) = match locale.split_once('@') {
+        Some((locale, modifier)) => (locale, Some(modifier)),
+        None => (locale, None),
+    };
+    let locale = locale.split('.').next().unwrap_or_default();
+    let (lang, country) = match locale.split_once('_') {
+        Some((lang, country)) => (lang, Some(country)),
+        None => (locale, None),
+    };
+
+    let mut locales = Vec::new();
+    if let (Some(country), Some(modifier)) = (country, modifier) {
+        locales.push(format!("{lang}_{country}@{modifier}"));
+    }
+    if let Some(country) = country {
+        locales.push(format!("{lang}_{country}"));
+    }
+    if let Some(modifier) = modifier {
+        locales.push(format!("{lang}@{modifier}"));
+    }
+    if !lang.is_empty() && lang != "C" && lang != "POSIX" {
+        locales.push(lang.to_string());
+    }
+
+    locales
+}
+
+fn application_dirs() -> Vec<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")));
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+
+    data_home
+        .into_iter()
+        .chain(
+            data_dirs
+                .split(':')
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from),
+        )
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+/// Resolves desktop-entry hints to .desktop files, caching the result per desktop file ID
+pub struct DesktopEntries {
+    locales: Vec<String>,
+    cache: HashMap<Box<str>, Option<Arc<DesktopEntry>>>,
+}
+
+impl DesktopEntries {
+    pub fn new() -> Self {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .find_map(|var| std::env::var(var).ok().filter(|locale| !locale.is_empty()))
+            .unwrap_or_default();
+
+        Self {
+            locales: locales(&locale),
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Look up a desktop file ID, without the .desktop suffix
+    pub fn get(&mut self, id: &str) -> Option<Arc<DesktopEntry>> {
+        if let Some(entry) = self.cache.get(id) {
+            return entry.clone();
+        }
+
+        let entry = application_dirs()
+            .into_iter()
+            .find_map(|dir| std::fs::read_to_string(dir.join(format!("{id}.desktop"))).ok())
+            .map(|contents| Arc::new(DesktopEntry::parse(&contents, &self.locales)));
+
+        if entry.is_none() {
+            log::debug!("No desktop entry found for '{id}'");
+        }
+
+        self.cache.insert(id.into(), entry.clone());
+        entry
+    }
+}
+
+impl NewNotification {
+    /// Show the application's name and icon from its desktop entry, app_name is kept
+    /// as sent for matching. An icon provided by the sender still takes precedence
+    pub fn apply_desktop_entry(&mut self, entry: &DesktopEntry) {
+        if entry.name.is_some() {
+            self.display_name = entry.name.clone();
+        }
+
+        if self.app_icon.is_none() {
+            self.app_icon = entry.icon.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locales_in_order_of_preference() {
+        assert_eq!(
+            locales("sr_YU.UTF-8@Latn"),
+            ["sr_YU@Latn", "sr_YU", "sr@Latn", "sr"]
+        );
+        assert_eq!(locales("pl_PL.UTF-8"), ["pl_PL", "pl"]);
+        assert!(locales("C.UTF-8").is_empty());
+    }
+
+    #[test]
+    fn parse_prefers_localized_name() {
+        let contents = "\
+[Desktop Entry]
+Name=Files
+Name[pl]=Pliki
+Name[pl_PL]=Menedżer plików
+Icon=org.gnome.Nautilus
+DBusActivatable=true
+
+[Desktop Action new-window]
+Name=New Window
+";
+
+        let entry = DesktopEntry::parse(contents, &locales("pl_PL.UTF-8"));
+        assert_eq!(entry.name.as_deref(), Some("Menedżer plików"));
+        assert_eq!(entry.icon.as_deref(), Some("org.gnome.Nautilus"));
+        assert!(entry.dbus_activatable);
+
+        let entry = DesktopEntry::parse(contents, &locales("de_DE.UTF-8"));
+        assert_eq!(entry.name.as_deref(), Some("Files"));
+    }
+}
@@ -0,0 +1,291 @@
+use crate::moxnotify::types::{ImageData, ImageEncoding, NewNotification};
+use config::{ImageConfig, ImageFormat};
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{ExtendedColorType, ImageEncoder, RgbaImage};
+use std::str::FromStr;
+use zbus::zvariant::{Signature, Structure};
+
+impl ImageData {
+    /// Convert an `image-data` hint to RGBA, rejecting images wider or taller
+    /// than `max_dimension` before anything is allocated for them
+    pub fn from_structure(value: Structure<'_>, max_dimension: u32) -> zbus::Result<Self> {
+        if Ok(value.signature()) != Signature::from_str("(iiibiiay)").as_ref() {
+            return Err(zbus::Error::Failure(format!(
+                "Invalid ImageData: invalid signature {}",
+                value.signature().to_string()
+            )));
+        }
+
+        let mut fields = value.into_fields();
+
+        if fields.len() != 7 {
+            return Err(zbus::Error::Failure(
+                "Invalid ImageData: missing fields".to_string(),
+            ));
+        }
+
+        let data = Vec::<u8>::try_from(fields.remove(6))
+            .map_err(|e| zbus::Error::Failure(format!("data: {e}")))?;
+        let channels = i32::try_from(fields.remove(5))
+            .map_err(|e| zbus::Error::Failure(format!("channels: {e}")))?;
+        let bits_per_sample = i32::try_from(fields.remove(4))
+            .map_err(|e| zbus::Error::Failure(format!("bits_per_sample: {e}")))?;
+        let rowstride = i32::try_from(fields.remove(2))
+            .map_err(|e| zbus::Error::Failure(format!("rowstride: {e}")))?;
+        let height = i32::try_from(fields.remove(1))
+            .map_err(|e| zbus::Error::Failure(format!("height: {e}")))?;
+        let width = i32::try_from(fields.remove(0))
+            .map_err(|e| zbus::Error::Failure(format!("width: {e}")))?;
+
+        if width <= 0 {
+            return Err(zbus::Error::Failure(
+                "Invalid ImageData: width is not positive".to_string(),
+            ));
+        }
+
+        if height <= 0 {
+            return Err(zbus::Error::Failure(
+                "Invalid ImageData: height is not positive".to_string(),
+            ));
+        }
+
+        if width as u32 > max_dimension || height as u32 > max_dimension {
+            return Err(zbus::Error::Failure(format!(
+                "Invalid ImageData: {width}x{height} exceeds {max_dimension} pixels"
+            )));
+        }
+
+        if bits_per_sample != 8 {
+            return Err(zbus::Error::Failure(
+                "Invalid ImageData: bits_per_sample is not 8".to_string(),
+            ));
+        }
+
+        if !(1..=4).contains(&channels) {
+            return Err(zbus::Error::Failure(format!(
+                "Invalid ImageData: unsupported channel count {channels}"
+            )));
+        }
+
+        let width = width as usize;
+        let height = height as usize;
+        let channels = channels as usize;
+
+        // A row has to hold at least `width` pixels
+        let row_len = width
+            .checked_mul(channels)
+            .ok_or_else(|| zbus::Error::Failure("Invalid ImageData: row too long".to_string()))?;
+        let rowstride = usize::try_from(rowstride)
+            .ok()
+            .filter(|&rowstride| rowstride >= row_len)
+            .ok_or_else(|| {
+                zbus::Error::Failure(format!(
+                    "Invalid ImageData: rowstride {rowstride} is shorter than a row of {row_len} bytes"
+                ))
+            })?;
+
+        // Validate input data length
+        let expected_input_len = rowstride.checked_mul(height).ok_or_else(|| {
+            zbus::Error::Failure("Invalid ImageData: image too large".to_string())
+        })?;
+        if data.len() != expected_input_len {
+            return Err(zbus::Error::Failure(
+                "Invalid ImageData: data length does not match rowstride * height".to_string(),
+            ));
+        }
+
+        // Convert to 4-channel RGBA format
+        let rgba_len = width
+            .checked_mul(height)
+            .and_then(|pixels| pixels.checked_mul(4))
+            .ok_or_else(|| {
+                zbus::Error::Failure("Invalid ImageData: image too large".to_string())
+            })?;
+        let mut rgba_data = Vec::with_capacity(rgba_len);
+
+        for y in 0..height {
+            let row_start = y * rowstride;
+            for x in 0..width {
+                let pixel_start = row_start + x * channels;
+
+                if pixel_start + channels > data.len() {
+                    return Err(zbus::Error::Failure(
+                        "Invalid ImageData: pixel data out of bounds".to_string(),
+                    ));
+                }
+
+                match channels {
+                    1 => {
+                        // Grayscale -> RGBA
+                        let gray = data[pixel_start];
+                        rgba_data.extend_from_slice(&[gray, gray, gray, 255]);
+                    }
+                    2 => {
+                        // Grayscale + Alpha -> RGBA
+                        let gray = data[pixel_start];
+                        let alpha = data[pixel_start + 1];
+                        rgba_data.extend_from_slice(&[gray, gray, gray, alpha]);
+                    }
+                    3 => {
+                        // RGB -> RGBA
+                        rgba_data.extend_from_slice(&[
+                            data[pixel_start],
+                            data[pixel_start + 1],
+                            data[pixel_start + 2],
+                            255,
+                        ]);
+                    }
+                    4 => {
+                        // RGBA -> RGBA (just copy)
+                        rgba_data.extend_from_slice(&[
+                            data[pixel_start],
+                            data[pixel_start + 1],
+                            data[pixel_start + 2],
+                            data[pixel_start + 3],
+                        ]);
+                    }
+                    _ => {
+                        return Err(zbus::Error::Failure(format!(
+                            "Invalid ImageData: unsupported channel count {channels}"
+                        )));
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            width: width as u32,
+            height: height as u32,
+            data: rgba_data,
+            encoding: ImageEncoding::Raw as i32,
+        })
+    }
+}
+
+impl ImageData {
+    /// Downscale the image to fit within `max_dimension` and encode it in the
+    /// configured format, so huge raw buffers don't travel through gRPC and Redis
+    pub fn transcode(self, config: &ImageConfig) -> anyhow::Result<Self> {
+        let mut buffer = RgbaImage::from_raw(self.width, self.height, self.data)
+            .ok_or_else(|| anyhow::anyhow!("image data does not match its dimensions"))?;
+
+        let longest = buffer.width().max(buffer.height());
+        if longest > config.max_dimension {
+            let scale = config.max_dimension as f32 / longest as f32;
+            let width = ((buffer.width() as f32 * scale).round() as u32).max(1);
+            let height = ((buffer.height() as f32 * scale).round() as u32).max(1);
+            buffer = image::imageops::resize(&buffer, width, height, FilterType::Triangle);
+        }
+
+        let (width, height) = buffer.dimensions();
+        let (data, encoding) = match config.format {
+            ImageFormat::Raw => (buffer.into_raw(), ImageEncoding::Raw),
+            ImageFormat::Png => {
+                let mut data = Vec::new();
+                PngEncoder::new(&mut data).write_image(
+                    &buffer,
+                    width,
+                    height,
+                    ExtendedColorType::Rgba8,
+                )?;
+                (data, ImageEncoding::Png)
+            }
+            ImageFormat::Webp => {
+                let mut data = Vec::new();
+                WebPEncoder::new_lossless(&mut data).write_image(
+                    &buffer,
+                    width,
+                    height,
+                    ExtendedColorType::Rgba8,
+                )?;
+                (data, ImageEncoding::Webp)
+            }
+        };
+
+        Ok(Self {
+            width,
+            height,
+            data,
+            encoding: encoding as i32,
+        })
+    }
+}
+
+impl NewNotification {
+    pub fn transcode_image(&mut self, config: &ImageConfig) {
+        let Some(image) = self.hints.as_mut().and_then(|hints| hints.image.as_mut()) else {
+            return;
+        };
+
+        let Some(crate::moxnotify::types::image::Image::Data(data)) = image.image.take() else {
+            return;
+        };
+
+        let (width, height, size) = (data.width, data.height, data.data.len());
+        match data.transcode(config) {
+            Ok(data) => {
+                log::debug!(
+                    "Transcoded {}x{} image ({} bytes) of notification id={} to {}x{} ({} bytes)",
+                    width,
+                    height,
+                    size,
+                    self.id,
+                    data.width,
+                    data.height,
+                    data.data.len()
+                );
+                image.image = Some(crate::moxnotify::types::image::Image::Data(data));
+            }
+            Err(e) => log::warn!(
+                "Dropping image of notification id={} from '{}': {e}",
+                self.id,
+                self.app_name
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hint(
+        width: i32,
+        height: i32,
+        rowstride: i32,
+        channels: i32,
+        data: Vec<u8>,
+    ) -> Structure<'static> {
+        Structure::from((width, height, rowstride, channels == 4, 8, channels, data))
+    }
+
+    #[test]
+    fn converts_rgb_with_padded_rows() {
+        let data = vec![1, 2, 3, 4, 5, 6, 0, 0, 7, 8, 9, 10, 11, 12, 0, 0];
+        let image = ImageData::from_structure(hint(2, 2, 8, 3, data), 16).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(
+            image.data,
+            [1, 2, 3, 255, 4, 5, 6, 255, 7, 8, 9, 255, 10, 11, 12, 255]
+        );
+    }
+
+    #[test]
+    fn rejects_oversized_dimensions() {
+        let data = vec![0; 17 * 4];
+        assert!(ImageData::from_structure(hint(17, 1, 17 * 4, 4, data), 16).is_err());
+    }
+
+    #[test]
+    fn rejects_negative_or_short_rowstride() {
+        assert!(ImageData::from_structure(hint(2, 2, -4, 4, vec![]), 16).is_err());
+        assert!(ImageData::from_structure(hint(2, 2, 4, 4, vec![0; 8]), 16).is_err());
+    }
+
+    #[test]
+    fn rejects_unsupported_channels() {
+        assert!(ImageData::from_structure(hint(1, 1, 5, 5, vec![0; 5]), 16).is_err());
+    }
+}
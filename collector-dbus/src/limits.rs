@@ -0,0 +1,117 @@
+use crate::moxnotify::types::{NewNotification, image};
+use config::Limits;
+
+/// Strip control characters the renderer can't display, keeping newlines and tabs
+fn sanitize(s: &str) -> String {
+    s.chars()
+        .filter(|c| !c.is_control() || matches!(c, '\n' | '\t'))
+        .collect()
+}
+
+/// Truncate to at most `max_bytes` bytes without splitting a character
+fn truncate_bytes(s: &mut String, max_bytes: usize) -> bool {
+    if s.len() <= max_bytes {
+        return false;
+    }
+
+    let mut end = max_bytes;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    s.truncate(end);
+
+    true
+}
+
+fn truncate_chars(s: &mut String, max_chars: usize) -> bool {
+    match s.char_indices().nth(max_chars) {
+        Some((end, _)) => {
+            s.truncate(end);
+            true
+        }
+        None => false,
+    }
+}
+
+impl NewNotification {
+    pub fn enforce_limits(&mut self, limits: &Limits) {
+        self.app_name = sanitize(&self.app_name);
+        self.summary = sanitize(&self.summary);
+        self.body = sanitize(&self.body);
+
+        if truncate_chars(&mut self.summary, limits.max_summary_length) {
+            log::warn!(
+                "Summary of notification id={} from '{}' truncated to {} characters",
+                self.id,
+                self.app_name,
+                limits.max_summary_length
+            );
+        }
+
+        let body_len = self.body.len();
+        if truncate_bytes(&mut self.body, limits.max_body_bytes) {
+            log::warn!(
+                "Body of notification id={} from '{}' truncated from {} to {} bytes",
+                self.id,
+                self.app_name,
+                body_len,
+                self.body.len()
+            );
+        }
+
+        if self.actions.len() > limits.max_actions {
+            log::warn!(
+                "Notification id={} from '{}' has {} actions, keeping the first {}",
+                self.id,
+                self.app_name,
+                self.actions.len(),
+                limits.max_actions
+            );
+            self.actions.truncate(limits.max_actions);
+        }
+
+        if let Some(hints) = self.hints.as_mut()
+            && let Some(image::Image::Data(data)) =
+                hints.image.as_ref().and_then(|image| image.image.as_ref())
+            && (data.width > limits.max_image_dimension || data.height > limits.max_image_dimension)
+        {
+            log::warn!(
+                "Dropping {}x{} image of notification id={} from '{}', exceeds {}px",
+                data.width,
+                data.height,
+                self.id,
+                self.app_name,
+                limits.max_image_dimension
+            );
+            hints.image = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_bytes_respects_char_boundaries() {
+        let mut s = "zażółć".to_string();
+        assert!(truncate_bytes(&mut s, 4));
+        assert_eq!(s, "za");
+
+        let mut s = "short".to_string();
+        assert!(!truncate_bytes(&mut s, 16));
+        assert_eq!(s, "short");
+    }
+
+    #[test]
+    fn truncate_chars_counts_characters() {
+        let mut s = "zażółć".to_string();
+        assert!(truncate_chars(&mut s, 3));
+        assert_eq!(s, "zaż");
+    }
+
+    #[test]
+    fn sanitize_keeps_whitespace() {
+        assert_eq!(sanitize("a\u{0}b\u{1b}[0m\nc\td"), "ab[0m\nc\td");
+    }
+}
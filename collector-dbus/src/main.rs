@@ -1,40 +1,19 @@
-pub mod moxnotify {
-    pub mod types {
-        tonic::include_proto!("moxnotify.types");
-    }
-    pub mod collector {
-        tonic::include_proto!("moxnotify.collector");
-    }
-}
-
+mod category;
+mod color;
 mod dbus;
+mod desktop_entry;
+mod image_data;
+mod limits;
+mod reading_speed;
 
 use clap::Parser;
+use forwarder::{EmitEvent, Event, Target};
 use std::path::Path;
 use std::sync::Arc;
-
-use moxnotify::collector::CollectorMessage;
-use moxnotify::collector::collector_service_client::CollectorServiceClient;
-use moxnotify::collector::{collector_message, collector_response};
-use moxnotify::types::{ActionInvoked, CloseNotification, NewNotification, NotificationClosed};
 use tokio::sync::{broadcast, mpsc};
-use tokio_stream::StreamExt;
-use tokio_stream::wrappers::ReceiverStream;
 use uuid::Uuid;
 
-type NotificationId = u32;
-
-#[derive(Debug)]
-pub enum Event {
-    Notify(Box<NewNotification>),
-    CloseNotification(NotificationId),
-}
-
-#[derive(Clone)]
-pub enum EmitEvent {
-    ActionInvoked(ActionInvoked),
-    NotificationClosed(NotificationClosed),
-}
+pub use forwarder::moxnotify;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -54,11 +33,12 @@ async fn main() -> anyhow::Result<()> {
         }),
     );
 
-    env_logger::Builder::new()
-        .filter(Some("collector"), config.collector.log_level.into())
-        .init();
+    logging::init("collector", config.collector.log_level.into());
+    // The forwarding to the control plane is shared with the SNI collector
+    logging::set_level("forwarder", config.collector.log_level.into());
+    logging::set_log_content(config.log_content);
 
-    let (event_sender, mut event_receiver) = mpsc::channel(128);
+    let (event_sender, event_receiver) = mpsc::channel(128);
     let (emit_sender, emit_receiver) = broadcast::channel(128);
 
     {
@@ -71,109 +51,10 @@ async fn main() -> anyhow::Result<()> {
         });
     }
 
-    let mut client =
-        CollectorServiceClient::connect(config.collector.control_plane_address.clone()).await?;
-
-    log::info!(
-        "Connected to control plane at {}",
-        config.collector.control_plane_address
-    );
-
-    let (tx, rx) = mpsc::channel(128);
-    let message_stream = ReceiverStream::new(rx);
-
-    let mut response_stream = client.notifications(message_stream).await?.into_inner();
-
-    loop {
-        tokio::select! {
-            event = event_receiver.recv() => {
-                let Some(event) = event else {
-                    log::info!("Event receiver closed");
-                    break;
-                };
-
-                let msg = match event {
-                    Event::Notify(data) => {
-                        log::info!(
-                            "Collected notification: id={}, app_name='{}', summary='{}'",
-                            data.id,
-                            data.app_name,
-                            data.summary,
-                        );
-
-                        CollectorMessage {
-                            message: Some(collector_message::Message::NewNotification(*data)),
-                        }
-                    }
-                    Event::CloseNotification(id) => {
-                        log::info!("Collected close notification request: id={}", id);
-
-                        CollectorMessage {
-                            message: Some(collector_message::Message::CloseNotification(
-                                CloseNotification { id },
-                            )),
-                        }
-                    }
-                };
-
-                if let Err(e) = tx.send(msg).await {
-                    log::error!("Failed to send message to control plane: {e}");
-                    break;
-                }
-            }
-
-            response = response_stream.next() => {
-                match response {
-                    Some(Ok(response)) => {
-                        if let Some(msg) = response.message {
-                            match msg {
-                                collector_response::Message::ActionInvoked(action) => {
-                                    log::info!(
-                                        "Received action invoked: id={}, action_key='{}'",
-                                        action.id,
-                                        action.action_key
-                                    );
-
-                                    if let Err(e) =
-                                        emit_sender.send(EmitEvent::ActionInvoked(action))
-                                    {
-                                        log::warn!(
-                                            "Failed to forward action invoked to DBus emitter: {}",
-                                            e
-                                        );
-                                    }
-                                }
-                                collector_response::Message::NotificationClosed(closed) => {
-                                    log::info!(
-                                        "Received notification closed: id={}, reason={:?}",
-                                        closed.id,
-                                        closed.reason()
-                                    );
-
-                                    if let Err(e) =
-                                        emit_sender.send(EmitEvent::NotificationClosed(closed))
-                                    {
-                                        log::warn!(
-                                            "Failed to forward notification closed to DBus emitter: {}",
-                                            e
-                                        );
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    Some(Err(e)) => {
-                        log::error!("Error receiving response from control plane: {}", e);
-                        break;
-                    }
-                    None => {
-                        log::info!("Response stream ended");
-                        break;
-                    }
-                }
-            }
-        }
-    }
-
-    Ok(())
+    let target = Target {
+        address: config.collector.control_plane_address.clone(),
+        compression: config.collector.compression,
+        offline_buffer: config.collector.offline_buffer,
+    };
+    forwarder::run(target, event_receiver, emit_sender).await
 }
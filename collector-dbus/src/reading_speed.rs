@@ -0,0 +1,80 @@
+use crate::moxnotify::types::NewNotification;
+use config::ReadingSpeed;
+
+/// Count words of text that may contain markup, tags are not counted
+fn count_words(s: &str) -> usize {
+    let mut in_tag = false;
+    let text: String = s
+        .chars()
+        .map(|c| match c {
+            '<' => {
+                in_tag = true;
+                ' '
+            }
+            '>' if in_tag => {
+                in_tag = false;
+                ' '
+            }
+            _ if in_tag => ' ',
+            c => c,
+        })
+        .collect();
+
+    text.split_whitespace().count()
+}
+
+impl NewNotification {
+    /// Timeout in milliseconds it takes to read the summary and body at the configured pace
+    pub fn reading_timeout(&self, reading_speed: &ReadingSpeed) -> i32 {
+        let words = count_words(&self.summary) + count_words(&self.body);
+        let ms = (words as u64 * 60_000 / u64::from(reading_speed.words_per_minute.max(1)))
+            .min(i32::MAX as u64) as i32;
+
+        let min = reading_speed.min_timeout.saturating_mul(1000);
+        let max = reading_speed.max_timeout.saturating_mul(1000).max(min);
+
+        ms.clamp(min, max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(summary: &str, body: &str) -> NewNotification {
+        NewNotification {
+            summary: summary.into(),
+            body: body.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn count_words_skips_markup() {
+        assert_eq!(count_words("<b>bold</b> and <i>italic</i>"), 3);
+        assert_eq!(count_words(""), 0);
+    }
+
+    #[test]
+    fn reading_timeout_is_clamped() {
+        let reading_speed = ReadingSpeed {
+            enabled: true,
+            words_per_minute: 60,
+            min_timeout: 3,
+            max_timeout: 10,
+        };
+
+        assert_eq!(
+            notification("Volume", "").reading_timeout(&reading_speed),
+            3000
+        );
+        assert_eq!(
+            notification("one two three four", "five").reading_timeout(&reading_speed),
+            5000
+        );
+        assert_eq!(
+            notification("summary", &"word ".repeat(100)).reading_timeout(&reading_speed),
+            10000
+        );
+    }
+}
@@ -0,0 +1,55 @@
+mod sni;
+
+use clap::Parser;
+use forwarder::{EmitEvent, Event, Target};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc};
+use uuid::Uuid;
+
+pub use forwarder::moxnotify;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[arg(short, long, value_name = "FILE", help = "Path to the config file")]
+    config: Option<Box<Path>>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    let config = Arc::new(
+        config::Config::load(cli.config.as_ref().map(|p| p.as_ref())).unwrap_or_else(|err| {
+            log::warn!("{err}");
+            config::Config::default()
+        }),
+    );
+
+    logging::init("collector_sni", config.sni_collector.log_level.into());
+    // The forwarding to the control plane is shared with the D-Bus collector
+    logging::set_level("forwarder", config.sni_collector.log_level.into());
+    logging::set_log_content(config.log_content);
+
+    let (event_sender, event_receiver) = mpsc::channel(128);
+    let (emit_sender, _) = broadcast::channel(128);
+
+    {
+        let config = Arc::clone(&config);
+        let emit_sender = emit_sender.clone();
+        tokio::spawn(async move {
+            let uuid = Uuid::new_v4().to_string();
+            if let Err(e) = sni::watch(event_sender, emit_sender, uuid, config).await {
+                log::error!("Failed to watch StatusNotifierItems: {e}");
+            }
+        });
+    }
+
+    let target = Target {
+        address: config.sni_collector.control_plane_address.clone(),
+        compression: config.sni_collector.compression,
+        offline_buffer: config.sni_collector.offline_buffer,
+    };
+    forwarder::run(target, event_receiver, emit_sender).await
+}
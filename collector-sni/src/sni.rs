@@ -0,0 +1,328 @@
+use crate::moxnotify::types::{Action, Latency, NewNotification, NotificationHints};
+use crate::{EmitEvent, Event};
+use chrono::offset::Local;
+use config::{SniApp, SniCollectorConfig};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio_stream::StreamExt;
+
+/// IDs handed out to items start here, clear of the D-Bus collector's sequential IDs
+const FIRST_ID: u32 = 1 << 31;
+
+const NEEDS_ATTENTION: &str = "NeedsAttention";
+
+#[zbus::proxy(
+    interface = "org.kde.StatusNotifierWatcher",
+    default_service = "org.kde.StatusNotifierWatcher",
+    default_path = "/StatusNotifierWatcher"
+)]
+trait StatusNotifierWatcher {
+    #[zbus(property)]
+    fn registered_status_notifier_items(&self) -> zbus::Result<Vec<String>>;
+
+    #[zbus(signal)]
+    fn status_notifier_item_registered(&self, service: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn status_notifier_item_unregistered(&self, service: &str) -> zbus::Result<()>;
+}
+
+#[zbus::proxy(interface = "org.kde.StatusNotifierItem")]
+trait StatusNotifierItem {
+    fn activate(&self, x: i32, y: i32) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn id(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn title(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn status(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn icon_name(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn attention_icon_name(&self) -> zbus::Result<String>;
+
+    #[zbus(signal)]
+    fn new_status(&self, status: &str) -> zbus::Result<()>;
+}
+
+/// Items register either as a bare bus name or as `bus_name/object/path`
+fn parse_service(service: &str) -> (&str, &str) {
+    match service.find('/') {
+        Some(i) => (&service[..i], &service[i..]),
+        None => (service, "/StatusNotifierItem"),
+    }
+}
+
+fn app_config(config: &SniCollectorConfig, app_id: &str) -> Option<SniApp> {
+    match config.apps.get(app_id) {
+        Some(app) if app.enabled => Some(app.clone()),
+        Some(_) => None,
+        None if config.watch_all => Some(SniApp::default()),
+        None => None,
+    }
+}
+
+struct Item {
+    id: u32,
+    app: SniApp,
+    proxy: StatusNotifierItemProxy<'static>,
+    uuid: String,
+    event_sender: mpsc::Sender<Event>,
+}
+
+impl Item {
+    /// `None` if the item's app isn't watched
+    async fn connect(
+        conn: &zbus::Connection,
+        service: &str,
+        id: u32,
+        config: &SniCollectorConfig,
+        uuid: String,
+        event_sender: mpsc::Sender<Event>,
+    ) -> zbus::Result<Option<Self>> {
+        let (destination, path) = parse_service(service);
+        let proxy = StatusNotifierItemProxy::builder(conn)
+            .destination(destination.to_string())?
+            .path(path.to_string())?
+            .build()
+            .await?;
+
+        let app_id = proxy.id().await?;
+        let Some(app) = app_config(config, &app_id) else {
+            log::debug!("Ignoring item '{app_id}' ({service})");
+            return Ok(None);
+        };
+        log::info!("Watching item '{app_id}' ({service}) as id={id}");
+
+        Ok(Some(Self {
+            id,
+            app,
+            proxy,
+            uuid,
+            event_sender,
+        }))
+    }
+
+    async fn notification(&self) -> NewNotification {
+        let app_id = self.proxy.id().await.unwrap_or_default();
+        let title = self
+            .proxy
+            .title()
+            .await
+            .ok()
+            .filter(|title| !title.is_empty())
+            .unwrap_or(app_id);
+        let icon = match self.proxy.attention_icon_name().await {
+            Ok(icon) if !icon.is_empty() => Some(icon),
+            _ => self
+                .proxy
+                .icon_name()
+                .await
+                .ok()
+                .filter(|icon| !icon.is_empty()),
+        };
+
+        let timestamp = Local::now().timestamp_millis();
+        NewNotification {
+            id: self.id,
+            summary: format!("{title} needs attention"),
+            app_name: title,
+            app_icon: icon,
+            body: String::new(),
+            timeout: self.app.timeout * 1000,
+            actions: vec![Action {
+                key: "default".to_string(),
+                label: "Open".to_string(),
+            }],
+            hints: Some(NotificationHints {
+                urgency: self.app.urgency as i32,
+                ..Default::default()
+            }),
+            timestamp,
+            client_timestamp: timestamp,
+            uuid: self.uuid.clone(),
+            latency: Some(Latency {
+                collected_at: timestamp,
+                ..Default::default()
+            }),
+        }
+    }
+
+    async fn send(&self, event: Event) {
+        if let Err(e) = self.event_sender.send(event).await {
+            log::error!("Failed to send event: {e}");
+        }
+    }
+
+    /// Notify when the item starts requesting attention and withdraw the
+    /// notification once it stops, until `cancel` fires or is dropped
+    async fn watch(
+        self,
+        mut emit_receiver: broadcast::Receiver<EmitEvent>,
+        mut cancel: oneshot::Receiver<()>,
+    ) -> zbus::Result<()> {
+        let mut new_status = self.proxy.receive_new_status().await?;
+        let mut status = self.proxy.status().await?;
+        let mut needs_attention = false;
+        // Cleared when the user dismisses the notification, so it isn't withdrawn twice
+        let mut notified = false;
+
+        loop {
+            let attention = status == NEEDS_ATTENTION;
+            if attention && !needs_attention {
+                log::info!("Item id={} requests attention", self.id);
+                self.send(Event::Notify(Box::new(self.notification().await)))
+                    .await;
+                notified = true;
+            } else if !attention && notified {
+                self.send(Event::CloseNotification(self.id)).await;
+                notified = false;
+            }
+            needs_attention = attention;
+
+            tokio::select! {
+                Some(signal) = new_status.next() => {
+                    status = signal.args()?.status.to_string();
+                }
+                event = emit_receiver.recv() => match event {
+                    Ok(EmitEvent::ActionInvoked(action))
+                        if action.id == self.id && action.uuid == self.uuid =>
+                    {
+                        if let Err(e) = self.proxy.activate(0, 0).await {
+                            log::warn!("Failed to activate item id={}: {e}", self.id);
+                        }
+                    }
+                    Ok(EmitEvent::NotificationClosed(closed))
+                        if closed.id == self.id && closed.uuid == self.uuid =>
+                    {
+                        notified = false;
+                    }
+                    Err(RecvError::Closed) => return Ok(()),
+                    _ => {}
+                },
+                _ = &mut cancel => {
+                    if notified {
+                        self.send(Event::CloseNotification(self.id)).await;
+                    }
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+struct Watcher {
+    conn: zbus::Connection,
+    config: Arc<config::Config>,
+    uuid: String,
+    event_sender: mpsc::Sender<Event>,
+    emit_sender: broadcast::Sender<EmitEvent>,
+    /// Dropping an item's sender stops watching it
+    items: HashMap<String, oneshot::Sender<()>>,
+    next_id: u32,
+}
+
+impl Watcher {
+    fn add(&mut self, service: String) {
+        if self.items.contains_key(&service) {
+            return;
+        }
+
+        let id = self.next_id;
+        self.next_id = self.next_id.checked_add(1).unwrap_or(FIRST_ID);
+
+        let (cancel_sender, cancel) = oneshot::channel();
+        self.items.insert(service.clone(), cancel_sender);
+
+        let conn = self.conn.clone();
+        let config = Arc::clone(&self.config);
+        let uuid = self.uuid.clone();
+        let event_sender = self.event_sender.clone();
+        let emit_receiver = self.emit_sender.subscribe();
+
+        tokio::spawn(async move {
+            let result = match Item::connect(
+                &conn,
+                &service,
+                id,
+                &config.sni_collector,
+                uuid,
+                event_sender,
+            )
+            .await
+            {
+                Ok(Some(item)) => item.watch(emit_receiver, cancel).await,
+                Ok(None) => Ok(()),
+                Err(e) => Err(e),
+            };
+
+            if let Err(e) = result {
+                log::warn!("Stopped watching {service}: {e}");
+            }
+        });
+    }
+}
+
+pub async fn watch(
+    event_sender: mpsc::Sender<Event>,
+    emit_sender: broadcast::Sender<EmitEvent>,
+    uuid: String,
+    config: Arc<config::Config>,
+) -> zbus::Result<()> {
+    let conn = zbus::Connection::session().await?;
+    let proxy = StatusNotifierWatcherProxy::new(&conn).await?;
+    let mut registered = proxy.receive_status_notifier_item_registered().await?;
+    let mut unregistered = proxy.receive_status_notifier_item_unregistered().await?;
+
+    let mut watcher = Watcher {
+        conn,
+        config,
+        uuid,
+        event_sender,
+        emit_sender,
+        items: HashMap::new(),
+        next_id: FIRST_ID,
+    };
+
+    proxy
+        .registered_status_notifier_items()
+        .await?
+        .into_iter()
+        .for_each(|service| watcher.add(service));
+
+    loop {
+        tokio::select! {
+            Some(signal) = registered.next() => {
+                watcher.add(signal.args()?.service.to_string());
+            }
+            Some(signal) = unregistered.next() => {
+                watcher.items.remove(signal.args()?.service);
+            }
+            else => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_service_with_and_without_path() {
+        assert_eq!(
+            parse_service(":1.42/org/ayatana/NotificationItem/foo"),
+            (":1.42", "/org/ayatana/NotificationItem/foo")
+        );
+        assert_eq!(
+            parse_service("org.kde.StatusNotifierItem-1234-1"),
+            ("org.kde.StatusNotifierItem-1234-1", "/StatusNotifierItem")
+        );
+    }
+}
@@ -2,6 +2,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     tonic_prost_build::configure()
         .type_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]")
         .type_attribute(".", "#[serde(rename_all = \"snake_case\")]")
+        .enum_attribute(
+            ".moxnotify.types.Urgency",
+            "#[derive(schemars::JsonSchema)]",
+        )
+        .field_attribute(
+            ".moxnotify.types.NotificationHints.tags",
+            "#[serde(default)]",
+        )
+        .field_attribute(
+            ".moxnotify.types.NotificationHints.keybinds",
+            "#[serde(default)]",
+        )
         .compile_protos(&["../proto/types.proto"], &["../proto"])?;
 
     Ok(())
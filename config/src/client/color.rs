@@ -96,14 +96,14 @@ impl Color {
         ]
     }
 
-    pub fn into_glyphon(self, urgency: Urgency) -> glyphon::Color {
+    pub fn into_glyphon(self, urgency: Urgency) -> cosmic_text::Color {
         let value = match urgency {
             Urgency::Low => self.urgency_low,
             Urgency::Normal => self.urgency_normal,
             Urgency::Critical => self.urgency_critical,
         };
 
-        glyphon::Color::rgba(value[0], value[1], value[2], value[3])
+        cosmic_text::Color::rgba(value[0], value[1], value[2], value[3])
     }
 }
 
@@ -1,17 +1,30 @@
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
 use serde::{Deserialize, Deserializer, de};
+use std::borrow::Cow;
 use std::marker::PhantomData;
 use std::ops::DerefMut;
 use std::sync::atomic::{AtomicU8, Ordering};
 use std::{fmt, ops::Deref, str::FromStr};
 use xkbcommon::xkb::Keysym;
 
-#[derive(Debug)]
+#[derive(Debug, JsonSchema)]
 pub struct Keymaps(Vec<KeyCombination>);
 
 impl Keymaps {
     pub fn matches(&self, sequence: &[KeyWithModifiers]) -> bool {
         self.iter().any(|kc| kc.keys.starts_with(sequence))
     }
+
+    /// Whether pressing `key` without modifiers in normal mode starts one of the keymaps
+    pub fn binds(&self, key: char) -> bool {
+        let key = KeyWithModifiers {
+            key: Key::Character(key),
+            modifiers: Modifiers::default(),
+        };
+
+        self.iter()
+            .any(|kc| kc.mode == Mode::Normal && kc.keys.first() == Some(&key))
+    }
 }
 
 impl<'de> Deserialize<'de> for Keymaps {
@@ -141,6 +154,54 @@ impl Default for Keymaps {
                 action: KeyAction::ToggleInhibit,
                 mode: Mode::Normal,
             },
+            KeyCombination {
+                keys: Keys(vec![KeyWithModifiers {
+                    key: Key::Character('w'),
+                    modifiers: Modifiers::default(),
+                }]),
+                action: KeyAction::ShowWaiting,
+                mode: Mode::Normal,
+            },
+            KeyCombination {
+                keys: Keys(vec![KeyWithModifiers {
+                    key: Key::Character('W'),
+                    modifiers: Modifiers::default(),
+                }]),
+                action: KeyAction::PopWaiting,
+                mode: Mode::Normal,
+            },
+            KeyCombination {
+                keys: Keys(vec![KeyWithModifiers {
+                    key: Key::SpecialKey(SpecialKeyCode::F12),
+                    modifiers: Modifiers::default(),
+                }]),
+                action: KeyAction::ToggleInspector,
+                mode: Mode::Normal,
+            },
+            KeyCombination {
+                keys: Keys(vec![KeyWithModifiers {
+                    key: Key::Character('e'),
+                    modifiers: Modifiers::default(),
+                }]),
+                action: KeyAction::ToggleHistory,
+                mode: Mode::Normal,
+            },
+            KeyCombination {
+                keys: Keys(vec![KeyWithModifiers {
+                    key: Key::Character('M'),
+                    modifiers: Modifiers::default(),
+                }]),
+                action: KeyAction::MuteApp,
+                mode: Mode::Normal,
+            },
+            KeyCombination {
+                keys: Keys(vec![KeyWithModifiers {
+                    key: Key::Character(':'),
+                    modifiers: Modifiers::default(),
+                }]),
+                action: KeyAction::CommandPalette,
+                mode: Mode::Normal,
+            },
         ])
     }
 }
@@ -153,7 +214,7 @@ impl Deref for Keymaps {
     }
 }
 
-#[derive(Deserialize, PartialEq, Eq, Hash, Debug, Default, Clone, Copy)]
+#[derive(Deserialize, JsonSchema, PartialEq, Eq, Hash, Debug, Default, Clone, Copy)]
 #[repr(u8)]
 pub enum Mode {
     #[default]
@@ -161,6 +222,9 @@ pub enum Mode {
     Normal = 0,
     #[serde(rename = "h")]
     Hint = 1,
+    /// Typing goes to the command palette
+    #[serde(rename = "p")]
+    Palette = 2,
 }
 
 pub struct AtomicMode {
@@ -178,6 +242,7 @@ impl AtomicMode {
         match self.inner.load(ordering) {
             0 => Mode::Normal,
             1 => Mode::Hint,
+            2 => Mode::Palette,
             _ => unreachable!("Invalid Mode value"),
         }
     }
@@ -191,6 +256,7 @@ impl AtomicMode {
         match old {
             0 => Mode::Normal,
             1 => Mode::Hint,
+            2 => Mode::Palette,
             _ => unreachable!("Invalid Mode value"),
         }
     }
@@ -209,11 +275,13 @@ impl AtomicMode {
             Ok(old) => Ok(match old {
                 0 => Mode::Normal,
                 1 => Mode::Hint,
+                2 => Mode::Palette,
                 _ => unreachable!(),
             }),
             Err(old) => Err(match old {
                 0 => Mode::Normal,
                 1 => Mode::Hint,
+                2 => Mode::Palette,
                 _ => unreachable!(),
             }),
         }
@@ -233,6 +301,7 @@ impl FromStr for Mode {
         match s.to_lowercase().as_str() {
             "normal" => Ok(Mode::Normal),
             "hint" => Ok(Mode::Hint),
+            "palette" => Ok(Mode::Palette),
             _ => Err(format!("Invalid mode: {s}")),
         }
     }
@@ -275,13 +344,29 @@ impl DerefMut for Keys {
     }
 }
 
-#[derive(Deserialize, PartialEq, Debug)]
+#[derive(Deserialize, JsonSchema, PartialEq, Debug)]
 pub struct KeyCombination {
     pub mode: Mode,
     pub keys: Keys,
     pub action: KeyAction,
 }
 
+impl JsonSchema for Keys {
+    fn schema_name() -> Cow<'static, str> {
+        "Keys".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "A key sequence, or a list of keys",
+            "anyOf": [
+                { "type": "string" },
+                { "type": "array", "items": { "type": "string" } }
+            ]
+        })
+    }
+}
+
 impl<'de> Deserialize<'de> for Keys {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -609,7 +694,7 @@ pub enum SpecialKeyCode {
     F12,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, JsonSchema, Debug, PartialEq, Clone, Copy)]
 #[serde(rename_all = "snake_case")]
 pub enum KeyAction {
     NextNotification,
@@ -627,4 +712,14 @@ pub enum KeyAction {
     Ihibit,
     Uninhibit,
     ToggleInhibit,
+    /// Show the notifications waiting while inhibited
+    ShowWaiting,
+    /// Show the oldest notification waiting while inhibited
+    PopWaiting,
+    ToggleInspector,
+    ToggleHistory,
+    /// Silence the selected notification's app for `general.mute_app_for`
+    MuteApp,
+    /// Search the actions of the client and the selected notification
+    CommandPalette,
 }
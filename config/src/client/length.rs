@@ -0,0 +1,83 @@
+use std::str::FromStr;
+
+/// Logical pixels per inch, the output scale is applied on top of it when rendering
+pub const DPI: f32 = 96.;
+
+/// A CSS size, numbers without a unit are pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Length {
+    Px(f32),
+    Pt(f32),
+    /// Relative to the font size
+    Em(f32),
+    /// Relative to the font size
+    Percent(f32),
+}
+
+impl Length {
+    /// Logical pixels, `font_size` in points is what `em` and `%` are relative to
+    pub fn to_px(self, font_size: f32) -> f32 {
+        let pt_to_px = |pt: f32| pt * DPI / 72.;
+
+        match self {
+            Self::Px(px) => px,
+            Self::Pt(pt) => pt_to_px(pt),
+            Self::Em(em) => em * pt_to_px(font_size),
+            Self::Percent(percent) => percent / 100. * pt_to_px(font_size),
+        }
+    }
+
+    pub fn to_pt(self, font_size: f32) -> f32 {
+        self.to_px(font_size) * 72. / DPI
+    }
+}
+
+impl FromStr for Length {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (value, unit): (&str, fn(f32) -> Self) = if let Some(value) = s.strip_suffix("px") {
+            (value, Self::Px)
+        } else if let Some(value) = s.strip_suffix("pt") {
+            (value, Self::Pt)
+        } else if let Some(value) = s.strip_suffix("em") {
+            (value, Self::Em)
+        } else if let Some(value) = s.strip_suffix('%') {
+            (value, Self::Percent)
+        } else {
+            (s, Self::Px)
+        };
+
+        value
+            .trim()
+            .parse()
+            .map(unit)
+            .map_err(|e| format!("Invalid length '{s}': {e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units() {
+        assert_eq!(Length::from_str("12"), Ok(Length::Px(12.)));
+        assert_eq!(Length::from_str("12px"), Ok(Length::Px(12.)));
+        assert_eq!(Length::from_str(" 9 pt"), Ok(Length::Pt(9.)));
+        assert_eq!(Length::from_str("1.5em"), Ok(Length::Em(1.5)));
+        assert_eq!(Length::from_str("50%"), Ok(Length::Percent(50.)));
+        assert!(Length::from_str("px").is_err());
+        assert!(Length::from_str("12vw").is_err());
+    }
+
+    #[test]
+    fn to_px() {
+        assert_eq!(Length::Px(10.).to_px(12.), 10.);
+        assert_eq!(Length::Pt(12.).to_px(0.), 16.);
+        assert_eq!(Length::Em(2.).to_px(12.), 32.);
+        assert_eq!(Length::Percent(50.).to_px(12.), 8.);
+        assert_eq!(Length::Px(16.).to_pt(0.), 12.);
+    }
+}
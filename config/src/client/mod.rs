@@ -6,35 +6,454 @@ pub mod moxnotify {
 
 pub mod color;
 pub mod keymaps;
+pub mod length;
 
 pub use moxnotify::types::Urgency;
 
-use crate::types::LogLevel;
+use crate::types::{Compression, LogLevel};
 use keymaps::Keymaps;
+use regex::Regex;
+use schemars::JsonSchema;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
-#[derive(Deserialize, Default, Clone)]
+#[derive(Deserialize, JsonSchema, Default, Clone)]
 pub struct SoundFile {
     pub urgency_low: Option<Arc<Path>>,
     pub urgency_normal: Option<Arc<Path>>,
     pub urgency_critical: Option<Arc<Path>>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct History {
     pub size: i64,
+    /// Require polkit authentication before showing history once the session was locked,
+    /// or idle for this long, 0 disables the lock
+    #[serde(deserialize_with = "crate::deserialize_duration")]
+    #[schemars(with = "String")]
+    pub lock_after: Duration,
 }
 
 impl Default for History {
     fn default() -> Self {
-        Self { size: 100 }
+        Self {
+            size: 100,
+            lock_after: Duration::ZERO,
+        }
+    }
+}
+
+fn deserialize_patterns<'de, D>(deserializer: D) -> Result<Vec<Regex>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|pattern| Regex::new(pattern).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+/// One-time codes in a notification get a button that copies them to the clipboard
+#[derive(Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Codes {
+    /// Searched for in the summary, then the body. The code is the first capture group,
+    /// or the whole match without one. Empty disables code detection
+    #[serde(deserialize_with = "deserialize_patterns")]
+    #[schemars(with = "Vec<String>")]
+    pub patterns: Vec<Regex>,
+    /// Dismiss the notification once its code is copied
+    pub dismiss: bool,
+}
+
+impl Codes {
+    /// First code found in `text`
+    #[must_use]
+    pub fn find<'a>(&self, text: &'a str) -> Option<&'a str> {
+        self.patterns
+            .iter()
+            .find_map(|pattern| {
+                let captures = pattern.captures(text)?;
+                captures.get(1).or_else(|| captures.get(0))
+            })
+            .map(|code| code.as_str())
     }
 }
 
-#[derive(Deserialize)]
+impl Default for Codes {
+    fn default() -> Self {
+        Self {
+            patterns: [
+                r"(?i)\b(?:code|otp|pin|passcode|one-time password)\b\D{0,16}?\b(\d{4,8})\b",
+                r"(?i)\b(\d{4,8})\b is your\b",
+            ]
+            .into_iter()
+            .map(|pattern| Regex::new(pattern).unwrap())
+            .collect(),
+            dismiss: false,
+        }
+    }
+}
+
+/// Notifications in one of `categories` get larger artwork and buttons controlling
+/// the MPRIS player of their app
+#[derive(Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Media {
+    pub enabled: bool,
+    /// Categories marking a notification as media
+    pub categories: Vec<String>,
+    /// Treat every notification from an app with a running player as media, even
+    /// without one of `categories`
+    pub any_player: bool,
+    /// Size of the artwork, used in place of `icon_size`
+    pub artwork_size: u32,
+}
+
+impl Media {
+    #[must_use]
+    pub fn category(&self, category: Option<&str>) -> bool {
+        self.enabled
+            && category.is_some_and(|category| self.categories.iter().any(|c| c == category))
+    }
+}
+
+impl Default for Media {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            categories: vec!["x-gnome.music".to_string(), "x-moxnotify.media".to_string()],
+            any_player: false,
+            artwork_size: 96,
+        }
+    }
+}
+
+/// Notifications the collector tagged as calls get prominent accept and decline
+/// buttons and ring until they go away
+#[derive(Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Calls {
+    /// Keys of the actions drawn as the accept button
+    pub accept: Vec<String>,
+    /// Keys of the actions drawn as the decline button
+    pub decline: Vec<String>,
+    /// How often the sound is repeated while the call is shown, 0 plays it once
+    #[serde(deserialize_with = "crate::deserialize_duration")]
+    #[schemars(with = "String")]
+    pub ring_interval: Duration,
+}
+
+impl Calls {
+    /// Whether the collector tagged a notification with `tags` as a call
+    #[must_use]
+    pub fn matches(tags: &[String]) -> bool {
+        tags.iter().any(|tag| tag == crate::tags::CALL)
+    }
+}
+
+impl Default for Calls {
+    fn default() -> Self {
+        Self {
+            accept: vec!["accept".to_string(), "answer".to_string()],
+            decline: vec![
+                "decline".to_string(),
+                "reject".to_string(),
+                "hangup".to_string(),
+            ],
+            ring_interval: Duration::from_secs(3),
+        }
+    }
+}
+
+/// Time a notification arrived, shown under its text as e.g. "2 min ago"
+/// and as the absolute time while hovered
+#[derive(Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Timestamps {
+    pub enabled: bool,
+    /// strftime format of the absolute time, localized with the top level `locale`
+    pub format: String,
+    /// How often relative times are brought up to date
+    #[serde(deserialize_with = "crate::deserialize_duration")]
+    #[schemars(with = "String")]
+    pub update_interval: Duration,
+}
+
+impl Timestamps {
+    /// How long ago something happened `elapsed` ago, in the largest whole unit and
+    /// the language of the built-in strings
+    #[must_use]
+    pub fn relative(elapsed: Duration) -> String {
+        match elapsed.as_secs() {
+            secs if secs < 60 => i18n::tr!("time-just-now"),
+            secs if secs < 60 * 60 => i18n::tr!("time-minutes-ago", count = secs / 60),
+            secs if secs < 24 * 60 * 60 => i18n::tr!("time-hours-ago", count = secs / (60 * 60)),
+            secs => i18n::tr!("time-days-ago", count = secs / (24 * 60 * 60)),
+        }
+    }
+}
+
+impl Default for Timestamps {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            format: "%x %X".to_string(),
+            update_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Title and favicon of the first link in a body, shown under it. Fetching them tells
+/// the linked site that the notification was shown, so it's off unless enabled
+#[derive(Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct LinkPreviews {
+    pub enabled: bool,
+    /// Give up on a preview that takes longer than this to fetch
+    #[serde(deserialize_with = "crate::deserialize_duration")]
+    #[schemars(with = "String")]
+    pub timeout: Duration,
+    /// Most bytes read of a page or favicon
+    pub max_size: usize,
+}
+
+impl Default for LinkPreviews {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout: Duration::from_secs(3),
+            max_size: 512 * 1024,
+        }
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Digest {
+    /// Urgencies held back for the digest, empty disables it
+    pub urgency: Vec<Urgency>,
+    /// Apps held back for the digest, empty matches every app
+    pub apps: Vec<String>,
+    /// How often the digest is shown, 0 only shows it on demand
+    #[serde(deserialize_with = "crate::deserialize_duration")]
+    #[schemars(with = "String")]
+    pub interval: Duration,
+}
+
+impl Default for Digest {
+    fn default() -> Self {
+        Self {
+            urgency: Vec::new(),
+            apps: Vec::new(),
+            interval: Duration::from_secs(30 * 60),
+        }
+    }
+}
+
+impl Digest {
+    #[must_use]
+    pub fn collects(&self, app_name: &str, urgency: Urgency) -> bool {
+        self.urgency.contains(&urgency)
+            && (self.apps.is_empty() || self.apps.iter().any(|app| app == app_name))
+    }
+}
+
+/// Blink LEDs for notifications that would have played a sound while sounds are muted
+#[derive(Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Feedback {
+    /// Urgencies that blink, empty disables it
+    pub urgency: Vec<Urgency>,
+    /// LEDs under /sys/class/leds to blink, empty flashes the keyboard backlight
+    /// through UPower instead
+    pub leds: Vec<String>,
+    /// How long the LEDs stay toggled
+    #[serde(deserialize_with = "crate::deserialize_duration")]
+    #[schemars(with = "String")]
+    pub duration: Duration,
+}
+
+impl Default for Feedback {
+    fn default() -> Self {
+        Self {
+            urgency: Vec::new(),
+            leds: Vec::new(),
+            duration: Duration::from_millis(500),
+        }
+    }
+}
+
+fn deserialize_pattern<'de, D>(deserializer: D) -> Result<Option<Regex>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|pattern| Regex::new(&pattern).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// Local time of day between `from` and `to`, in minutes since midnight. Wraps past
+/// midnight when `to` is earlier than `from`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hours {
+    pub from: u32,
+    pub to: u32,
+}
+
+impl Hours {
+    #[must_use]
+    pub fn contains(&self, minute: u32) -> bool {
+        if self.from <= self.to {
+            (self.from..self.to).contains(&minute)
+        } else {
+            minute >= self.from || minute < self.to
+        }
+    }
+}
+
+impl std::str::FromStr for Hours {
+    type Err = String;
+
+    /// Parse hours written like `"22:00-07:30"`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let minute = |time: &str| {
+            let (hour, minute) = time.trim().split_once(':')?;
+            let (hour, minute) = (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?);
+            (hour < 24 && minute < 60).then_some(hour * 60 + minute)
+        };
+
+        s.split_once('-')
+            .and_then(|(from, to)| {
+                Some(Self {
+                    from: minute(from)?,
+                    to: minute(to)?,
+                })
+            })
+            .ok_or_else(|| format!("Invalid hours '{s}', expected e.g. \"22:00-07:00\""))
+    }
+}
+
+impl<'de> Deserialize<'de> for Hours {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Notifications shown while notifications are inhibited, e.g. messages from a
+/// particular person. Every set condition has to match
+#[derive(Deserialize, JsonSchema, Default)]
+#[serde(default)]
+pub struct DndException {
+    /// Apps breaking through, empty matches every app
+    pub apps: Vec<String>,
+    /// Matched against the summary, which is the sender for most messengers
+    #[serde(deserialize_with = "deserialize_pattern")]
+    #[schemars(with = "Option<String>")]
+    pub summary: Option<Regex>,
+    /// When the exception applies as `"HH:MM-HH:MM"` in local time, always if unset
+    #[schemars(with = "Option<String>")]
+    pub hours: Option<Hours>,
+}
+
+impl DndException {
+    #[must_use]
+    pub fn matches(&self, app_name: &str, summary: &str, minute: u32) -> bool {
+        (self.apps.is_empty() || self.apps.iter().any(|app| app == app_name))
+            && self
+                .summary
+                .as_ref()
+                .is_none_or(|pattern| pattern.is_match(summary))
+            && self.hours.is_none_or(|hours| hours.contains(minute))
+    }
+}
+
+/// State of a window that inhibits notifications
+#[derive(Deserialize, JsonSchema, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InhibitOn {
+    #[default]
+    Focused,
+    Fullscreen,
+}
+
+/// Inhibits notifications while a window of the app is focused or fullscreen,
+/// e.g. while screen sharing with OBS
+#[derive(Deserialize, JsonSchema)]
+pub struct AutoInhibit {
+    /// app_id of the window, compared ignoring case
+    pub app_id: String,
+    #[serde(default)]
+    pub on: InhibitOn,
+}
+
+impl AutoInhibit {
+    #[must_use]
+    pub fn matches(&self, app_id: &str, focused: bool, fullscreen: bool) -> bool {
+        self.app_id.eq_ignore_ascii_case(app_id)
+            && match self.on {
+                InhibitOn::Focused => focused,
+                InhibitOn::Fullscreen => fullscreen,
+            }
+    }
+}
+
+/// Do not disturb, entered by inhibiting notifications
+#[derive(Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Dnd {
+    /// Notifications matching any of these are shown instead of waiting for DND to end
+    pub exceptions: Vec<DndException>,
+    /// Show how many notifications are waiting in a badge, without their content
+    pub badge: bool,
+    /// Windows inhibiting notifications until they lose focus or leave fullscreen,
+    /// requires wlr-foreign-toplevel-management
+    pub auto_inhibit: Vec<AutoInhibit>,
+}
+
+impl Default for Dnd {
+    fn default() -> Self {
+        Self {
+            exceptions: Vec::new(),
+            badge: true,
+            auto_inhibit: Vec::new(),
+        }
+    }
+}
+
+impl Dnd {
+    /// Whether a notification from `app_name` arriving `minute` minutes after local
+    /// midnight is shown while inhibited
+    #[must_use]
+    pub fn breaks_through(&self, app_name: &str, summary: &str, minute: u32) -> bool {
+        self.exceptions
+            .iter()
+            .any(|exception| exception.matches(app_name, summary, minute))
+    }
+}
+
+/// Repeating held keys, following the compositor's repeat info unless overridden
+#[derive(Deserialize, JsonSchema, Default)]
+#[serde(default)]
+pub struct KeyRepeat {
+    /// Repeats per second, 0 turns repeating off
+    pub rate: Option<u32>,
+    /// How long a key is held before it starts repeating
+    #[serde(deserialize_with = "crate::deserialize_optional_duration")]
+    #[schemars(with = "Option<String>")]
+    pub delay: Option<Duration>,
+}
+
+#[derive(Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct General {
     pub history: History,
@@ -43,6 +462,9 @@ pub struct General {
     pub ignore_sound_file: bool,
     pub scroll_sensitivity: f64,
     pub hint_characters: Box<str>,
+    /// `hint_characters` for keyboard layouts named as xkb does, e.g. "English (Dvorak)".
+    /// Keys typed on other layouts that don't produce ascii are read as the first layout's
+    pub layout_hint_characters: HashMap<Box<str>, Box<str>>,
     pub max_visible: usize,
     pub icon_size: u32,
     pub app_icon_size: u32,
@@ -51,6 +473,43 @@ pub struct General {
     pub output: Option<Arc<str>>,
     pub ignore_timeout: bool,
     pub margin: Insets,
+    /// Clicking a notification without actions focuses the sender's window,
+    /// requires wlr-foreign-toplevel-management
+    pub focus_sender: bool,
+    /// After resuming from suspend, summarize notifications received since going to sleep
+    pub sleep_digest: bool,
+    pub digest: Digest,
+    pub link_previews: LinkPreviews,
+    pub codes: Codes,
+    pub media: Media,
+    pub calls: Calls,
+    pub timestamps: Timestamps,
+    pub dnd: Dnd,
+    pub key_repeat: KeyRepeat,
+    /// How long the `mute_app` action silences the selected notification's app
+    #[serde(deserialize_with = "crate::deserialize_duration")]
+    #[schemars(with = "String")]
+    pub mute_app_for: Duration,
+    /// Notifications that were already active when the client connected, e.g. on
+    /// startup, are added without playing their sounds
+    pub restore_silently: bool,
+    /// Apps whose notifications play their sound again when they're replaced, other
+    /// apps only sound on replacing when the notification gets more urgent
+    pub sound_on_replace: Vec<String>,
+    pub feedback: Feedback,
+    /// Don't animate anything, also when the desktop doesn't ask for reduced motion
+    /// through the settings portal
+    pub reduced_motion: bool,
+}
+
+impl General {
+    /// Hint characters to use while `layout` is active
+    #[must_use]
+    pub fn hint_characters(&self, layout: Option<&str>) -> &str {
+        layout
+            .and_then(|layout| self.layout_hint_characters.get(layout))
+            .unwrap_or(&self.hint_characters)
+    }
 }
 
 impl Default for General {
@@ -60,6 +519,7 @@ impl Default for General {
             ignore_sound_file: false,
             theme: None,
             hint_characters: "sadfjklewcmpgh".into(),
+            layout_hint_characters: HashMap::new(),
             scroll_sensitivity: 20.,
             max_visible: 5,
             icon_size: 64,
@@ -70,11 +530,26 @@ impl Default for General {
             ignore_timeout: false,
             history: History::default(),
             margin: Insets::default(),
+            focus_sender: false,
+            sleep_digest: false,
+            digest: Digest::default(),
+            link_previews: LinkPreviews::default(),
+            codes: Codes::default(),
+            media: Media::default(),
+            calls: Calls::default(),
+            timestamps: Timestamps::default(),
+            dnd: Dnd::default(),
+            key_repeat: KeyRepeat::default(),
+            mute_app_for: Duration::from_secs(30 * 60),
+            restore_silently: true,
+            sound_on_replace: Vec::new(),
+            feedback: Feedback::default(),
+            reduced_motion: false,
         }
     }
 }
 
-#[derive(Deserialize, Default)]
+#[derive(Deserialize, JsonSchema, Default)]
 #[serde(default)]
 pub struct ClientConfig {
     pub general: General,
@@ -82,13 +557,18 @@ pub struct ClientConfig {
     pub css: String,
     #[serde(default = "default_log_level")]
     pub log_level: LogLevel,
+    #[serde(default)]
+    pub compression: Compression,
+    /// The top level `locale`, filled in when the config is loaded
+    #[serde(skip)]
+    pub locale: Option<String>,
 }
 
 fn default_log_level() -> LogLevel {
     LogLevel::default()
 }
 
-#[derive(Default, Clone, Copy, Deserialize, Debug)]
+#[derive(Default, Clone, Copy, Deserialize, JsonSchema, Debug)]
 #[serde(default)]
 pub struct Insets {
     pub left: f32,
@@ -114,7 +594,7 @@ impl From<Insets> for [f32; 4] {
     }
 }
 
-#[derive(Deserialize, Default)]
+#[derive(Deserialize, JsonSchema, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum Layer {
     Background,
@@ -124,7 +604,7 @@ pub enum Layer {
     Overlay,
 }
 
-#[derive(Deserialize, Default)]
+#[derive(Deserialize, JsonSchema, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum Anchor {
     #[default]
@@ -193,3 +673,71 @@ impl ClientConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_codes() {
+        let codes = Codes::default();
+
+        assert_eq!(
+            codes.find("Your verification code is 482913"),
+            Some("482913")
+        );
+        assert_eq!(codes.find("PIN: 1234"), Some("1234"));
+        assert_eq!(codes.find("771204 is your login code"), Some("771204"));
+        assert_eq!(codes.find("Meeting moved to 1530"), None);
+    }
+
+    #[test]
+    fn relative_timestamps() {
+        assert_eq!(Timestamps::relative(Duration::from_secs(59)), "just now");
+        assert_eq!(Timestamps::relative(Duration::from_secs(150)), "2 min ago");
+        assert_eq!(
+            Timestamps::relative(Duration::from_secs(3 * 3600)),
+            "3 h ago"
+        );
+        assert_eq!(
+            Timestamps::relative(Duration::from_secs(50 * 3600)),
+            "2 d ago"
+        );
+    }
+
+    #[test]
+    fn dnd_exceptions() {
+        let dnd = Dnd {
+            exceptions: vec![DndException {
+                apps: vec!["Signal".to_string()],
+                summary: Some(Regex::new("^Mom$").unwrap()),
+                hours: Some("22:00-07:00".parse().unwrap()),
+            }],
+            ..Default::default()
+        };
+
+        assert!(dnd.breaks_through("Signal", "Mom", 23 * 60));
+        assert!(dnd.breaks_through("Signal", "Mom", 6 * 60 + 59));
+        assert!(!dnd.breaks_through("Signal", "Mom", 7 * 60));
+        assert!(!dnd.breaks_through("Signal", "Boss", 23 * 60));
+        assert!(!dnd.breaks_through("Telegram", "Mom", 23 * 60));
+        assert!("25:00-07:00".parse::<Hours>().is_err());
+    }
+
+    #[test]
+    fn auto_inhibit() {
+        let obs = AutoInhibit {
+            app_id: "com.obsproject.Studio".to_string(),
+            on: InhibitOn::Focused,
+        };
+        assert!(obs.matches("com.obsproject.studio", true, false));
+        assert!(!obs.matches("com.obsproject.Studio", false, true));
+
+        let mpv = AutoInhibit {
+            app_id: "mpv".to_string(),
+            on: InhibitOn::Fullscreen,
+        };
+        assert!(mpv.matches("mpv", false, true));
+        assert!(!mpv.matches("mpv", true, false));
+    }
+}
@@ -0,0 +1,63 @@
+use tonic::{Code, Status};
+use tonic_types::{ErrorDetails, StatusExt};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors of the services working off the Redis streams: the control plane, the
+/// scheduler and the indexer
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Redis error: {0}")]
+    Redis(#[from] redis::RedisError),
+    #[error("Malformed JSON payload: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Payload is not valid UTF-8: {0}")]
+    Utf8(#[from] std::str::Utf8Error),
+    #[error("Failed to compress payload: {0}")]
+    Compress(#[source] std::io::Error),
+    #[error("Failed to decompress payload: {0}")]
+    Decompress(#[source] std::io::Error),
+    #[error("Request is missing `{0}`")]
+    MissingField(&'static str),
+    #[error("Unknown viewport direction: {0}")]
+    UnknownDirection(i32),
+    #[error("Connection has no remote address")]
+    NoRemoteAddr,
+}
+
+impl Error {
+    /// Whether retrying the same message can never succeed, in which case
+    /// it should be acknowledged and dropped instead of left pending
+    pub fn is_malformed(&self) -> bool {
+        matches!(self, Self::Json(_) | Self::Utf8(_) | Self::Decompress(_))
+    }
+
+    /// Whether the connection to Redis is gone and has to be replaced
+    pub fn is_disconnected(&self) -> bool {
+        matches!(self, Self::Redis(e) if e.is_unrecoverable_error())
+    }
+}
+
+/// Invalid argument naming the request field to fix
+fn field_violation(err: &Error, field: &str, description: impl Into<String>) -> Status {
+    Status::with_error_details(
+        Code::InvalidArgument,
+        err.to_string(),
+        ErrorDetails::with_bad_request_violation(field, description),
+    )
+}
+
+impl From<Error> for Status {
+    fn from(err: Error) -> Self {
+        match &err {
+            Error::MissingField(field) => field_violation(&err, field, "Field is required"),
+            Error::UnknownDirection(direction) => field_violation(
+                &err,
+                "direction",
+                format!("{direction} isn't a known viewport direction"),
+            ),
+            Error::NoRemoteAddr => Status::invalid_argument(err.to_string()),
+            _ => Status::internal(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,126 @@
+/// Names of the Redis keys, streams and Pub/Sub channels shared between
+/// services, all prefixed with the configured namespace
+#[derive(Clone, Debug)]
+pub struct Keys {
+    namespace: Box<str>,
+    /// Stream of incoming notifications
+    pub notify: String,
+    /// Stream of CloseNotification calls from collectors
+    pub close_notification: String,
+    /// Stream of notifications closed by clients or the scheduler
+    pub notification_closed: String,
+    pub action_invoked: String,
+    /// Hash of notifications that are currently displayed, by ID
+    pub active: String,
+    /// Sorted set of expiration timers
+    pub timers: String,
+    pub audit: String,
+    /// Hash of trigger queries to the hook they run, empty to show a notification
+    pub triggers: String,
+    pub pubsub_notification: String,
+    pub pubsub_close_notification: String,
+    pub pubsub_notification_closed: String,
+    pub pubsub_action_invoked: String,
+    /// Requests to delete the whole history, each naming the key the indexer
+    /// replies on
+    pub pubsub_clear_history: String,
+}
+
+impl Keys {
+    pub fn new(namespace: &str) -> Self {
+        let key = |name: &str| format!("{namespace}:{name}");
+
+        Self {
+            notify: key("notify"),
+            close_notification: key("close_notification"),
+            notification_closed: key("notification_closed"),
+            action_invoked: key("action_invoked"),
+            active: key("active"),
+            timers: key("timers"),
+            audit: key("audit"),
+            triggers: key("triggers"),
+            pubsub_notification: key("pubsub:notification"),
+            pubsub_close_notification: key("pubsub:close_notification"),
+            pubsub_notification_closed: key("pubsub:notification_closed"),
+            pubsub_action_invoked: key("pubsub:action_invoked"),
+            pubsub_clear_history: key("pubsub:clear_history"),
+            namespace: namespace.into(),
+        }
+    }
+
+    /// Out-of-band blob, addressed by the hash of its contents
+    pub fn blob(&self, hash: &str) -> String {
+        format!("{}:blob:{hash}", self.namespace)
+    }
+
+    pub fn timer(&self, id: u32) -> String {
+        format!("{}:timer:{id}", self.namespace)
+    }
+
+    pub fn client_state(&self, client_id: &str) -> String {
+        format!("{}:client:{client_id}:state", self.namespace)
+    }
+
+    /// Set while notifications from `app_name` are muted, expires when the mute ends
+    pub fn mute(&self, app_name: &str) -> String {
+        format!("{}:mute:{app_name}", self.namespace)
+    }
+
+    /// List the indexer pushes the outcome of the clear request `request` to
+    pub fn clear_history_reply(&self, request: &str) -> String {
+        format!("{}:clear_history:{request}", self.namespace)
+    }
+
+    /// Sorted set of the frecency of each action of `app_name`, as the base 2
+    /// logarithm of the summed up weights of its invocations
+    pub fn action_frecency(&self, app_name: &str) -> String {
+        format!("{}:action_log_frecency:{app_name}", self.namespace)
+    }
+}
+
+/// Position of a stream entry from its ID, `<milliseconds>-<sequence>`, so entries
+/// compare in the order Redis added them. `None` if `id` isn't one
+#[must_use]
+pub fn stream_position(id: &str) -> Option<(u64, u64)> {
+    let (ms, seq) = id.split_once('-')?;
+    Some((ms.parse().ok()?, seq.parse().ok()?))
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        Self::new("moxnotify")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_names_are_unchanged() {
+        let keys = Keys::default();
+        assert_eq!(keys.notify, "moxnotify:notify");
+        assert_eq!(
+            keys.pubsub_action_invoked,
+            "moxnotify:pubsub:action_invoked"
+        );
+        assert_eq!(keys.timer(7), "moxnotify:timer:7");
+        assert_eq!(Keys::new("work").blob("ab"), "work:blob:ab");
+        assert_eq!(
+            keys.action_frecency("signal"),
+            "moxnotify:action_log_frecency:signal"
+        );
+    }
+
+    #[test]
+    fn stream_positions_order_numerically() {
+        assert_eq!(
+            stream_position("1700000000000-3"),
+            Some((1_700_000_000_000, 3))
+        );
+        assert!(stream_position("999-10") > stream_position("999-9"));
+        assert!(stream_position("1000-0") > stream_position("999-10"));
+        assert_eq!(stream_position(""), None);
+        assert_eq!(stream_position("12"), None);
+    }
+}
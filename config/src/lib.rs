@@ -1,19 +1,26 @@
 pub mod client;
+pub mod error;
+pub mod keys;
+pub mod stream;
 pub mod types;
 
-use client::ClientConfig;
+use client::{ClientConfig, Urgency};
+use schemars::JsonSchema;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::time::Duration;
 use tvix_serde::from_str;
-use types::{LogLevel, Timeout};
+use types::{Compression, LogLevel, Timeout};
 
-#[derive(Deserialize, Default)]
+#[derive(Deserialize, JsonSchema, Default)]
 #[serde(default)]
 pub struct Config {
     #[serde(default)]
     pub collector: CollectorConfig,
     #[serde(default)]
+    pub sni_collector: SniCollectorConfig,
+    #[serde(default)]
     pub control_plane: ControlPlaneConfig,
     #[serde(default)]
     pub indexer: IndexerConfig,
@@ -27,28 +34,149 @@ pub struct Config {
     pub client: ClientConfig,
     #[serde(default)]
     pub redis: Redis,
+    #[serde(default)]
+    pub audit: AuditConfig,
+    #[serde(default)]
+    pub frecency: FrecencyConfig,
+    /// Defaults applied to notifications by their `category` hint, e.g. `"email.arrived"`
+    #[serde(default)]
+    pub categories: HashMap<String, CategoryConfig>,
+    /// Locale dates and times are formatted in and built-in strings are translated to,
+    /// e.g. `"de_DE"`. Taken from the environment when unset
+    pub locale: Option<String>,
+    /// Fluent messages replacing the built-in translations by language, e.g.
+    /// `{ pl."notifications-more" = "+{ $count }"; }`. Languages without a built-in
+    /// translation can be added this way
+    pub translations: HashMap<String, HashMap<String, String>>,
+    /// Write notification summaries and bodies to the logs of every service as they are,
+    /// instead of their length. Only meant for debugging
+    pub log_content: bool,
+}
+
+/// Per-category defaults, only filled in where the sender didn't provide a value
+#[derive(Deserialize, JsonSchema, Clone)]
+#[serde(default)]
+pub struct CategoryConfig {
+    /// Icon used when the notification has no app icon
+    pub icon: Option<String>,
+    /// Sound played when the notification specifies neither a sound file nor a sound name
+    pub sound_file: Option<PathBuf>,
+    pub sound_name: Option<String>,
+    /// Whether notifications of this category are kept in the searchable history
+    pub history: bool,
+}
+
+impl Default for CategoryConfig {
+    fn default() -> Self {
+        Self {
+            icon: None,
+            sound_file: None,
+            sound_name: None,
+            history: true,
+        }
+    }
 }
 
 fn default_redis_address() -> Box<str> {
     "redis://127.0.0.1/".into()
 }
 
-#[derive(Deserialize)]
+fn default_redis_namespace() -> Box<str> {
+    "moxnotify".into()
+}
+
+fn default_blob_ttl() -> Duration {
+    Duration::from_secs(86400) // daily
+}
+
+#[derive(Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct Redis {
     #[serde(default = "default_redis_address")]
     pub address: Box<str>,
+    /// How long out-of-band blobs such as image data are kept around
+    #[serde(
+        default = "default_blob_ttl",
+        deserialize_with = "deserialize_duration"
+    )]
+    #[schemars(with = "String")]
+    pub blob_ttl: Duration,
+    /// Prefix of every key, stream and channel, so independent deployments can share one Redis
+    #[serde(default = "default_redis_namespace")]
+    pub namespace: Box<str>,
 }
 
 impl Default for Redis {
     fn default() -> Self {
         Self {
             address: default_redis_address(),
+            blob_ttl: default_blob_ttl(),
+            namespace: default_redis_namespace(),
+        }
+    }
+}
+
+impl Redis {
+    pub fn keys(&self) -> keys::Keys {
+        keys::Keys::new(&self.namespace)
+    }
+}
+
+/// Lifecycle transitions of every notification, recorded to the
+/// audit stream for debugging lost notifications
+#[derive(Deserialize, JsonSchema, Clone, Copy)]
+#[serde(default)]
+pub struct AuditConfig {
+    pub enabled: bool,
+    /// Approximate number of entries kept in the stream
+    pub max_len: usize,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_len: 100_000,
         }
     }
 }
 
-#[derive(Deserialize)]
+/// Invocations are weighed relative to this, 2025-01-01 in milliseconds
+const FRECENCY_EPOCH: i64 = 1_735_689_600_000;
+
+/// Orders the actions of a notification by how often and how recently the user
+/// invoked them on notifications from the same app
+#[derive(Deserialize, JsonSchema, Clone, Copy)]
+#[serde(default)]
+pub struct FrecencyConfig {
+    pub enabled: bool,
+    /// An invocation counts half as much after this long
+    #[serde(deserialize_with = "deserialize_duration")]
+    #[schemars(with = "String")]
+    pub half_life: Duration,
+}
+
+impl Default for FrecencyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            half_life: Duration::from_secs(14 * 86400),
+        }
+    }
+}
+
+impl FrecencyConfig {
+    /// Base 2 logarithm of the weight of an invocation at `timestamp` milliseconds.
+    /// Weights double every half life, so scores summed up over time compare without
+    /// being decayed. They're kept as logarithms since they'd overflow an `f64` after
+    /// about 1000 half lives
+    #[must_use]
+    pub fn log_weight(&self, timestamp: i64) -> f64 {
+        (timestamp - FRECENCY_EPOCH) as f64 / self.half_life.as_millis().max(1) as f64
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct CollectorConfig {
     #[serde(default)]
@@ -57,6 +185,29 @@ pub struct CollectorConfig {
     pub control_plane_address: String,
     #[serde(default = "default_log_level")]
     pub log_level: LogLevel,
+    /// Take over org.freedesktop.Notifications if another daemon already owns it
+    #[serde(default)]
+    pub replace_existing: bool,
+    /// Let another daemon take over org.freedesktop.Notifications, the collector
+    /// exits once it does
+    #[serde(default)]
+    pub allow_replacement: bool,
+    #[serde(default)]
+    pub limits: Limits,
+    #[serde(default)]
+    pub image: ImageConfig,
+    #[serde(default)]
+    pub compression: Compression,
+    #[serde(default)]
+    pub reading_speed: ReadingSpeed,
+    /// How many notifications are kept while the control plane is unreachable,
+    /// the oldest are dropped beyond that
+    #[serde(default = "default_offline_buffer")]
+    pub offline_buffer: usize,
+    /// Notifications of these categories are incoming calls, which never expire and
+    /// are tagged `call` for clients to present them as such
+    #[serde(default = "default_call_categories")]
+    pub call_categories: Vec<String>,
 }
 
 impl Default for CollectorConfig {
@@ -65,21 +216,165 @@ impl Default for CollectorConfig {
             default_timeout: Timeout::default(),
             control_plane_address: default_control_plane_address(),
             log_level: default_log_level(),
+            replace_existing: false,
+            allow_replacement: false,
+            limits: Limits::default(),
+            image: ImageConfig::default(),
+            compression: Compression::default(),
+            reading_speed: ReadingSpeed::default(),
+            offline_buffer: default_offline_buffer(),
+            call_categories: default_call_categories(),
+        }
+    }
+}
+
+/// Synthesizes notifications from StatusNotifierItems that request attention,
+/// for apps that only signal urgency through their tray item
+#[derive(Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct SniCollectorConfig {
+    pub control_plane_address: String,
+    pub log_level: LogLevel,
+    pub compression: Compression,
+    /// Whether items without an entry in `apps` are watched
+    pub watch_all: bool,
+    /// Per app settings, keyed by the item's Id property
+    pub apps: HashMap<String, SniApp>,
+    /// How many notifications are kept while the control plane is unreachable
+    pub offline_buffer: usize,
+}
+
+impl Default for SniCollectorConfig {
+    fn default() -> Self {
+        Self {
+            control_plane_address: default_control_plane_address(),
+            log_level: default_log_level(),
+            compression: Compression::default(),
+            watch_all: true,
+            apps: HashMap::new(),
+            offline_buffer: default_offline_buffer(),
+        }
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Clone)]
+#[serde(default)]
+pub struct SniApp {
+    pub enabled: bool,
+    pub urgency: Urgency,
+    /// Timeout in seconds, 0 keeps the notification until the item stops requesting attention
+    pub timeout: i32,
+}
+
+impl Default for SniApp {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            urgency: Urgency::Normal,
+            timeout: 0,
+        }
+    }
+}
+
+/// Derive the timeout of notifications that leave it up to the server from
+/// how long their content takes to read, instead of using `default_timeout`
+#[derive(Deserialize, JsonSchema, Clone, Copy)]
+#[serde(default)]
+pub struct ReadingSpeed {
+    pub enabled: bool,
+    pub words_per_minute: u32,
+    /// Lower bound in seconds
+    pub min_timeout: i32,
+    /// Upper bound in seconds
+    pub max_timeout: i32,
+}
+
+impl Default for ReadingSpeed {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            words_per_minute: 200,
+            min_timeout: 3,
+            max_timeout: 30,
+        }
+    }
+}
+
+/// Upper bounds enforced on incoming notifications before they are forwarded
+#[derive(Deserialize, JsonSchema, Clone, Copy)]
+#[serde(default)]
+pub struct Limits {
+    /// Maximum summary length in characters
+    pub max_summary_length: usize,
+    /// Maximum body size in bytes
+    pub max_body_bytes: usize,
+    pub max_actions: usize,
+    /// Images with a larger width or height in pixels are rejected outright
+    pub max_image_dimension: u32,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_summary_length: 256,
+            max_body_bytes: 64 * 1024,
+            max_actions: 16,
+            max_image_dimension: 4096,
+        }
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageFormat {
+    Raw,
+    #[default]
+    Png,
+    Webp,
+}
+
+/// How image-data hints are transcoded before leaving the collector
+#[derive(Deserialize, JsonSchema, Clone, Copy)]
+#[serde(default)]
+pub struct ImageConfig {
+    /// Images are downscaled to fit within this many pixels on either side
+    pub max_dimension: u32,
+    pub format: ImageFormat,
+}
+
+impl Default for ImageConfig {
+    fn default() -> Self {
+        Self {
+            max_dimension: 256,
+            format: ImageFormat::default(),
         }
     }
 }
 
+fn default_offline_buffer() -> usize {
+    256
+}
+
+fn default_call_categories() -> Vec<String> {
+    vec!["call.incoming".to_string()]
+}
+
 fn default_control_plane_address() -> String {
     "http://[::1]:64201".to_string()
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct SchedulerConfig {
     #[serde(default = "default_scheduler_addr")]
     pub address: String,
     #[serde(default = "default_log_level")]
     pub log_level: LogLevel,
+    #[serde(default)]
+    pub compression: Compression,
+    /// Apps whose notifications may have an action invoked on expiry, see `FilterSet::expire_action`
+    #[serde(default)]
+    pub expire_action_apps: Vec<String>,
 }
 
 impl Default for SchedulerConfig {
@@ -87,6 +382,8 @@ impl Default for SchedulerConfig {
         Self {
             address: default_scheduler_addr(),
             log_level: default_log_level(),
+            compression: Compression::default(),
+            expire_action_apps: Vec::new(),
         }
     }
 }
@@ -95,31 +392,173 @@ fn default_scheduler_addr() -> String {
     "[::1]:64202".to_string()
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct ControlPlaneConfig {
     #[serde(default = "default_control_plane_addr")]
     pub address: String,
+    /// Where the admin API is served, separately from the collector API. Anyone
+    /// reaching it can close notifications, so it has to be a loopback address
+    #[serde(default = "default_admin_addr")]
+    pub admin_address: String,
     #[serde(default = "default_log_level")]
     pub log_level: LogLevel,
+    /// Applies to both collector responses and moxnotify:notify stream entries
+    #[serde(default)]
+    pub compression: Compression,
+    /// Applied in order to every notification before it reaches any consumer
+    #[serde(default)]
+    pub filters: Vec<Filter>,
+    #[serde(default)]
+    pub active_limit: ActiveLimit,
+    /// Commands run through `sh -c` for notifications matching the query they're
+    /// keyed by, with the query syntax of triggers. Only read from here, so the admin
+    /// API can't be used to run commands
+    #[serde(default)]
+    pub hooks: HashMap<String, String>,
+    #[serde(default)]
+    pub compaction: Compaction,
+}
+
+/// Cap on the notifications active at once, so a runaway app can't exhaust Redis
+/// and the clients' memory
+#[derive(Deserialize, JsonSchema, Clone, Copy)]
+#[serde(default)]
+pub struct ActiveLimit {
+    /// 0 doesn't limit them
+    pub max: usize,
+    pub overflow: Overflow,
+}
+
+impl Default for ActiveLimit {
+    fn default() -> Self {
+        Self {
+            max: 200,
+            overflow: Overflow::default(),
+        }
+    }
+}
+
+/// What happens to a notification arriving while `active_limit.max` are active
+#[derive(Deserialize, JsonSchema, Clone, Copy, Default, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Overflow {
+    /// Close the oldest low urgency notification, or normal one if there's none.
+    /// Critical ones are never closed
+    #[default]
+    DropOldest,
+    /// Close the oldest notification of the same app, so it replaces its own
+    Coalesce,
+    /// Drop the new notification
+    Reject,
 }
 
 impl Default for ControlPlaneConfig {
     fn default() -> Self {
         Self {
             address: default_control_plane_addr(),
+            admin_address: default_admin_addr(),
             log_level: default_log_level(),
+            compression: Compression::default(),
+            filters: Vec::new(),
+            active_limit: ActiveLimit::default(),
+            hooks: HashMap::new(),
+            compaction: Compaction::default(),
         }
     }
 }
 
-#[derive(Deserialize)]
+/// Drops, rewrites or tags the notifications it matches, e.g.
+/// `{ match.app_name = "Signal"; tags = [ "redact-body" ]; }`
+#[derive(Deserialize, JsonSchema, Clone, Default)]
+#[serde(default)]
+pub struct Filter {
+    #[serde(rename = "match")]
+    pub matches: FilterMatch,
+    /// Discard the notification, later filters aren't applied
+    pub drop: bool,
+    pub set: FilterSet,
+    /// Added to the notification's tags, see `tags` for the ones consumers act on
+    pub tags: Vec<String>,
+    /// Don't apply later filters to notifications this one matched
+    pub last: bool,
+}
+
+/// Every field that is set has to match, an empty match applies to all notifications
+#[derive(Deserialize, JsonSchema, Clone, Default)]
+#[serde(default)]
+pub struct FilterMatch {
+    pub app_name: Option<String>,
+    pub category: Option<String>,
+    pub urgency: Option<Urgency>,
+    /// Substring of the summary
+    pub summary: Option<String>,
+    /// Substring of the body
+    pub body: Option<String>,
+}
+
+/// Fields overwritten on matching notifications
+#[derive(Deserialize, JsonSchema, Clone, Default)]
+#[serde(default)]
+pub struct FilterSet {
+    pub app_name: Option<String>,
+    pub app_icon: Option<String>,
+    /// Template, e.g. `{if body}{body|first_line}{else}{summary}{end}`
+    pub summary: Option<String>,
+    /// Template like `summary`
+    pub body: Option<String>,
+    pub category: Option<String>,
+    pub urgency: Option<Urgency>,
+    /// Timeout in seconds, 0 never expires
+    pub timeout: Option<i32>,
+    /// Key of the action invoked if the notification expires without interaction,
+    /// only for apps listed in `scheduler.expire_action_apps`
+    pub expire_action: Option<String>,
+}
+
+/// Tags consumers act on
+pub mod tags {
+    /// Not kept in the searchable history
+    pub const NO_HISTORY: &str = "no-history";
+    /// Kept in the searchable history without its body
+    pub const REDACT_BODY: &str = "redact-body";
+    /// Incoming call, see `collector.call_categories`
+    pub const CALL: &str = "call";
+}
+
+pub mod protocol {
+    /// Sent in the `Hello` of collectors and clients, bumped when a change needs
+    /// the other side to know which version it's talking to
+    pub const VERSION: u32 = 1;
+}
+
+#[derive(Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct IndexerConfig {
     #[serde(default = "default_control_plane_address")]
     pub control_plane_address: String,
     #[serde(default = "default_log_level")]
     pub log_level: LogLevel,
+    /// Consumer name within the indexer group, defaults to one derived from the hostname
+    pub consumer: Option<String>,
+    /// Concurrent consumers reading the notify stream, their documents are
+    /// committed by a single index writer
+    pub workers: usize,
+    /// Notifications identical to one from the same app received within this
+    /// window are merged into its history entry, counting repeats. 0 disables it
+    #[serde(
+        default = "default_dedup_window",
+        deserialize_with = "deserialize_duration"
+    )]
+    #[schemars(with = "String")]
+    pub dedup_window: Duration,
+    /// Directory of the history index, the searcher and janitor use it too unless
+    /// they set their own
+    pub index_path: Option<PathBuf>,
+}
+
+fn default_dedup_window() -> Duration {
+    Duration::from_secs(60)
 }
 
 impl Default for IndexerConfig {
@@ -127,17 +566,29 @@ impl Default for IndexerConfig {
         Self {
             control_plane_address: default_control_plane_address(),
             log_level: default_log_level(),
+            consumer: None,
+            workers: 1,
+            dedup_window: default_dedup_window(),
+            index_path: None,
         }
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema, Clone)]
 #[serde(default)]
 pub struct SearcherConfig {
     #[serde(default = "default_searcher_addr")]
     pub address: String,
     #[serde(default = "default_log_level")]
     pub log_level: LogLevel,
+    /// Bearer token requests have to carry in their `Authorization` header
+    pub token: Option<String>,
+    /// File containing the token, read instead of `token` so it stays out of the config
+    pub token_file: Option<PathBuf>,
+    /// Directory of the history index, the indexer's when unset
+    pub index_path: Option<PathBuf>,
+    /// Queries `/api/watch` can follow by name, e.g. `{ deploys = "app_name:ci AND failed"; }`
+    pub saved_searches: HashMap<String, String>,
 }
 
 impl Default for SearcherConfig {
@@ -145,25 +596,77 @@ impl Default for SearcherConfig {
         Self {
             address: default_searcher_addr(),
             log_level: default_log_level(),
+            token: None,
+            token_file: None,
+            index_path: None,
+            saved_searches: HashMap::new(),
         }
     }
 }
 
+fn read_token(path: &std::path::Path) -> anyhow::Result<String> {
+    let token = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", path.display()))?;
+    Ok(token.trim().to_string())
+}
+
+impl SearcherConfig {
+    /// Token the searcher requires, surrounding whitespace of `token_file` is ignored.
+    /// Without either it's the one the searcher generated, `None` until it did
+    pub fn token(&self) -> anyhow::Result<Option<String>> {
+        if self.has_token() {
+            return match self.token_file.as_ref() {
+                Some(path) => read_token(path).map(Some),
+                None => Ok(self.token.clone()),
+            };
+        }
+
+        match Self::generated_token_path() {
+            Some(path) if path.exists() => read_token(&path).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Whether `token` or `token_file` is set
+    #[must_use]
+    pub fn has_token(&self) -> bool {
+        self.token_file.is_some() || self.token.as_ref().is_some_and(|token| !token.is_empty())
+    }
+
+    /// Where the searcher writes the token it generates when none is configured, so
+    /// only the user's own processes can read it
+    #[must_use]
+    pub fn generated_token_path() -> Option<PathBuf> {
+        std::env::var_os("XDG_RUNTIME_DIR")
+            .filter(|runtime_dir| !runtime_dir.is_empty())
+            .map(|runtime_dir| PathBuf::from(runtime_dir).join("moxnotify/searcher-token"))
+    }
+}
+
 fn default_searcher_addr() -> String {
-    "0.0.0.0:64203".to_string()
+    "127.0.0.1:64203".to_string()
 }
 
 fn default_control_plane_addr() -> String {
     "[::1]:64201".to_string()
 }
 
-#[derive(Deserialize)]
+fn default_admin_addr() -> String {
+    "[::1]:64204".to_string()
+}
+
+#[derive(Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct JanitorConfig {
     #[serde(default = "default_log_level")]
     pub log_level: LogLevel,
     #[serde(default)]
     pub retention: Retention,
+    /// Directory of the history index, the indexer's when unset
+    pub index_path: Option<PathBuf>,
+    /// Clean up history inside the indexer with its writer instead, the janitor
+    /// service then exits right away
+    pub embedded: bool,
 }
 
 impl Default for JanitorConfig {
@@ -171,23 +674,76 @@ impl Default for JanitorConfig {
         Self {
             log_level: default_log_level(),
             retention: Retention::default(),
+            index_path: None,
+            embedded: false,
         }
     }
 }
 
-#[derive(Deserialize)]
+/// Trimming of Redis streams once every consumer group has acknowledged their entries
+#[derive(Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Compaction {
+    pub enabled: bool,
+    #[serde(
+        default = "default_compaction_schedule",
+        deserialize_with = "deserialize_duration"
+    )]
+    #[schemars(with = "String")]
+    pub schedule: Duration,
+}
+
+fn default_compaction_schedule() -> Duration {
+    Duration::from_secs(600) // 10 minutes
+}
+
+impl Default for Compaction {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            schedule: default_compaction_schedule(),
+        }
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct Retention {
     #[serde(
         default = "default_retention_period",
         deserialize_with = "deserialize_duration"
     )]
+    #[schemars(with = "String")]
     pub period: Duration,
     #[serde(
         default = "default_retention_schedule",
         deserialize_with = "deserialize_duration"
     )]
+    #[schemars(with = "String")]
     pub schedule: Duration,
+    /// Retention of specific apps' history, by app name
+    pub apps: HashMap<String, AppRetention>,
+}
+
+impl Retention {
+    /// Whether notifications from `app_name` are kept in history at all
+    #[must_use]
+    pub fn keeps(&self, app_name: &str) -> bool {
+        self.apps.get(app_name).is_none_or(|app| !app.exclude)
+    }
+}
+
+/// History retention of one app
+#[derive(Deserialize, JsonSchema, Default)]
+#[serde(default)]
+pub struct AppRetention {
+    /// Keep the app's notifications out of history entirely, e.g. for OSDs
+    pub exclude: bool,
+    /// How long the app's notifications are kept. Only shortens `retention.period`,
+    /// everything older than that is removed regardless
+    #[serde(deserialize_with = "deserialize_optional_duration")]
+    #[schemars(with = "Option<String>")]
+    pub period: Option<Duration>,
 }
 
 fn default_retention_period() -> Duration {
@@ -218,11 +774,19 @@ where
     Ok(duration)
 }
 
+fn deserialize_optional_duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    deserialize_duration(deserializer).map(Some)
+}
+
 impl Default for Retention {
     fn default() -> Self {
         Self {
             period: default_retention_period(),
             schedule: default_retention_schedule(),
+            apps: HashMap::new(),
         }
     }
 }
@@ -238,28 +802,80 @@ pub fn xdg_config_dir() -> anyhow::Result<PathBuf> {
         .map_err(Into::into)
 }
 
+/// Milliseconds since the Unix epoch, the unit of every timestamp moxnotify keeps
+#[must_use]
+pub fn now_ms() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|now| now.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// Environment variable overriding the `index_path` of every service
+pub const INDEX_PATH_VAR: &str = "MOXNOTIFY_INDEX_PATH";
+
 impl Config {
+    /// Directory of the history index for a service with its own `index_path`
+    /// set to `own`. [`INDEX_PATH_VAR`] takes precedence, then `own`, then the
+    /// indexer's, then the data directory
+    #[must_use]
+    pub fn index_path(&self, own: Option<&std::path::Path>) -> PathBuf {
+        std::env::var_os(INDEX_PATH_VAR)
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| own.map(std::path::Path::to_path_buf))
+            .or_else(|| self.indexer.index_path.clone())
+            .or_else(data_dir::path)
+            .unwrap_or_default()
+    }
+
+    /// The file [`Self::load`] reads, `path` or the first config file found in the
+    /// config directory
+    pub fn path(path: Option<&std::path::Path>) -> anyhow::Result<Option<PathBuf>> {
+        if let Some(p) = path {
+            return Ok(Some(p.to_path_buf()));
+        }
+
+        let xdg = xdg_config_dir()?;
+        Ok([
+            xdg.join("mox/moxnotify/default.nix"),
+            xdg.join("mox/moxnotify.nix"),
+        ]
+        .into_iter()
+        .find(|p| p.is_file()))
+    }
+
     pub fn load(path: Option<&std::path::Path>) -> anyhow::Result<Self> {
-        let nix_code = if let Some(p) = path {
-            std::fs::read_to_string(p)?
-        } else {
-            let xdg = xdg_config_dir()?;
-            let candidates = [
-                xdg.join("mox/moxnotify/default.nix"),
-                xdg.join("mox/moxnotify.nix"),
-            ];
-            match candidates
-                .iter()
-                .find_map(|p| std::fs::read_to_string(p).ok())
-            {
-                Some(content) => content,
-                None => {
-                    log::warn!("Config file not found");
-                    return Ok(Self::default());
-                }
-            }
+        let Some(path) = Self::path(path)? else {
+            log::warn!("Config file not found");
+            let mut config = Self::default();
+            config.client.locale = config.locale();
+            return Ok(config);
         };
+        let nix_code = std::fs::read_to_string(path)?;
+
+        let mut config: Self = from_str(&nix_code).map_err(|e| anyhow::anyhow!("{e}"))?;
+        config.client.locale = config.locale();
+
+        Ok(config)
+    }
+
+    /// [`Self::locale`] or the one of the environment, without encoding and modifier
+    #[must_use]
+    pub fn locale(&self) -> Option<String> {
+        let locale = self.locale.clone().or_else(|| {
+            ["LC_ALL", "LC_TIME", "LANG"]
+                .into_iter()
+                .filter_map(|var| std::env::var(var).ok())
+                .find(|locale| !locale.is_empty())
+        })?;
+        let locale = locale.split(['.', '@']).next().unwrap_or_default();
+
+        (!locale.is_empty() && locale != "C" && locale != "POSIX").then(|| locale.to_string())
+    }
 
-        from_str(&nix_code).map_err(|e| anyhow::anyhow!("{e}"))
+    /// JSON Schema of the config, derived from the same definitions it's deserialized with
+    pub fn schema() -> schemars::Schema {
+        schemars::schema_for!(Self)
     }
 }
@@ -0,0 +1,43 @@
+use crate::error::{self, Error};
+use std::collections::HashMap;
+
+/// Field of a notify stream entry holding the notification JSON
+pub const NOTIFICATION: &str = "notification";
+/// Field holding the notification JSON zstd-compressed, used instead of
+/// [`NOTIFICATION`] when the control plane has compression enabled
+pub const NOTIFICATION_ZSTD: &str = "notification_zstd";
+
+/// Extract the notification JSON from a notify stream entry, `None` if it has none
+pub fn notification_payload(map: &HashMap<String, redis::Value>) -> error::Result<Option<Vec<u8>>> {
+    match (map.get(NOTIFICATION), map.get(NOTIFICATION_ZSTD)) {
+        (Some(redis::Value::BulkString(json)), _) => Ok(Some(json.clone())),
+        (_, Some(redis::Value::BulkString(compressed))) => zstd::decode_all(compressed.as_slice())
+            .map(Some)
+            .map_err(Error::Decompress),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notification_payload() {
+        let json = br#"{"id":1}"#.to_vec();
+        let compressed = zstd::encode_all(json.as_slice(), 0).unwrap();
+
+        let plain = HashMap::from([(
+            NOTIFICATION.to_string(),
+            redis::Value::BulkString(json.clone()),
+        )]);
+        let zstd = HashMap::from([(
+            NOTIFICATION_ZSTD.to_string(),
+            redis::Value::BulkString(compressed),
+        )]);
+
+        assert_eq!(notification_payload(&plain).unwrap(), Some(json.clone()));
+        assert_eq!(notification_payload(&zstd).unwrap(), Some(json));
+        assert_eq!(notification_payload(&HashMap::new()).unwrap(), None);
+    }
+}
@@ -1,7 +1,9 @@
 use log::LevelFilter;
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
 use serde::{Deserialize, Deserializer};
+use std::borrow::Cow;
 
-#[derive(Deserialize, Clone, Copy)]
+#[derive(Deserialize, JsonSchema, Clone, Copy)]
 pub struct Timeout {
     #[serde(default = "default_urgency_low")]
     pub urgency_low: i32,
@@ -33,6 +35,15 @@ fn default_urgency_critical() -> i32 {
     0
 }
 
+/// Compression applied to notification payloads on the wire
+#[derive(Deserialize, JsonSchema, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    #[default]
+    None,
+    Zstd,
+}
+
 #[derive(Clone, Copy)]
 pub struct LogLevel(pub LevelFilter);
 
@@ -66,6 +77,19 @@ impl<'de> Deserialize<'de> for LogLevel {
     }
 }
 
+impl JsonSchema for LogLevel {
+    fn schema_name() -> Cow<'static, str> {
+        "LogLevel".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "enum": ["off", "error", "warn", "info", "debug", "trace"]
+        })
+    }
+}
+
 impl From<LogLevel> for LevelFilter {
     fn from(level: LogLevel) -> Self {
         level.0
@@ -1,9 +1,24 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR")?);
+
     tonic_prost_build::configure()
+        .file_descriptor_set_path(out_dir.join("moxnotify_descriptor.bin"))
         .type_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]")
         .type_attribute(".", "#[serde(rename_all = \"snake_case\")]")
+        .field_attribute(
+            ".moxnotify.types.NotificationHints.tags",
+            "#[serde(default)]",
+        )
+        .field_attribute(
+            ".moxnotify.types.NotificationHints.keybinds",
+            "#[serde(default)]",
+        )
         .compile_protos(
-            &["../proto/types.proto", "../proto/collector.proto"],
+            &[
+                "../proto/types.proto",
+                "../proto/collector.proto",
+                "../proto/admin.proto",
+            ],
             &["../proto"],
         )?;
 
@@ -0,0 +1,89 @@
+use crate::moxnotify::types::{
+    CloseNotification, CloseReason, NewNotification, NotificationClosed,
+};
+use config::error;
+use config::keys::{self, Keys};
+use redis::AsyncTypedCommands;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Every notification in the active hash, newest first, as parsed and as stored
+pub async fn list(
+    con: &mut redis::aio::MultiplexedConnection,
+    keys: &Keys,
+) -> error::Result<Vec<(NewNotification, String)>> {
+    let active: HashMap<String, String> = AsyncTypedCommands::hgetall(con, &keys.active).await?;
+
+    let mut notifications: Vec<_> = active
+        .into_values()
+        .filter_map(
+            |json| match serde_json::from_str::<NewNotification>(&json) {
+                Ok(notification) => Some((notification, json)),
+                Err(e) => {
+                    log::warn!("Skipping malformed active notification: {}", e);
+                    None
+                }
+            },
+        )
+        .collect();
+    notifications.sort_by_key(|(notification, _)| {
+        Reverse((
+            keys::stream_position(&notification.stream_id),
+            notification.timestamp,
+        ))
+    });
+
+    Ok(notifications)
+}
+
+/// The active notification `id`, `None` if it isn't active
+pub async fn get(
+    con: &mut redis::aio::MultiplexedConnection,
+    keys: &Keys,
+    id: u32,
+) -> error::Result<Option<NewNotification>> {
+    let Some(json) = AsyncTypedCommands::hget(con, &keys.active, id.to_string()).await? else {
+        return Ok(None);
+    };
+
+    Ok(Some(serde_json::from_str(&json)?))
+}
+
+/// Close `id` on every client and tell the app it was closed for `reason`. Returns
+/// the closed notification, `None` if it isn't active
+pub async fn close(
+    con: &mut redis::aio::MultiplexedConnection,
+    keys: &Keys,
+    id: u32,
+    reason: CloseReason,
+) -> error::Result<Option<NewNotification>> {
+    let Some(notification) = get(&mut *con, keys, id).await? else {
+        return Ok(None);
+    };
+    let id_str = id.to_string();
+
+    let close = serde_json::to_string(&CloseNotification { id })?;
+    AsyncTypedCommands::xadd(
+        &mut *con,
+        &keys.close_notification,
+        "*",
+        &[("close_notification", close.as_str())],
+    )
+    .await?;
+    AsyncTypedCommands::hdel(&mut *con, &keys.active, id_str.as_str()).await?;
+
+    let closed = serde_json::to_string(&NotificationClosed {
+        id,
+        reason: reason.into(),
+        uuid: notification.uuid.clone(),
+    })?;
+    AsyncTypedCommands::xadd(
+        &mut *con,
+        &keys.notification_closed,
+        "*",
+        &[("notification", closed.as_str())],
+    )
+    .await?;
+
+    Ok(Some(notification))
+}
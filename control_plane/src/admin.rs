@@ -0,0 +1,191 @@
+use crate::ControlPlaneService;
+use crate::active;
+use crate::moxnotify::admin::admin_service_server::AdminService;
+use crate::moxnotify::admin::{
+    AddTriggerRequest, AddTriggerResponse, ForceCloseRequest, ForceCloseResponse,
+    ListActiveRequest, ListActiveResponse, ListTriggersRequest, ListTriggersResponse,
+    RebroadcastRequest, RebroadcastResponse, RemoveTriggerRequest, RemoveTriggerResponse, Trigger,
+};
+use crate::moxnotify::types::{CloseReason, NewNotification};
+use crate::trigger;
+use config::error;
+use std::collections::HashMap;
+use tonic::{Request, Response, Status};
+
+impl ControlPlaneService {
+    /// Every notification in the active hash, newest first, as parsed and as stored
+    async fn active(&self) -> error::Result<Vec<(NewNotification, String)>> {
+        let mut con = self.con();
+        active::list(&mut con, &self.keys).await
+    }
+
+    /// Close `id` on every client and tell the app it was closed, like a
+    /// CloseNotification call would. Returns `false` if it isn't active
+    async fn close_active(&self, id: u32) -> error::Result<bool> {
+        let reason = CloseReason::ReasonCloseNotificationCall;
+        let mut con = self.con();
+        let Some(notification) = active::close(&mut con, &self.keys, id, reason).await? else {
+            return Ok(false);
+        };
+
+        self.audit
+            .record(
+                id,
+                &notification.uuid,
+                audit::Event::Closed {
+                    reason: reason.as_str_name().to_string(),
+                },
+            )
+            .await;
+
+        Ok(true)
+    }
+
+    /// Publish every active notification to the scheduler again, which sends
+    /// them to its clients. Returns how many were published
+    async fn publish_active(&self) -> error::Result<u32> {
+        let active = self.active().await?;
+
+        let mut con = self.con();
+        // Oldest first, so clients stack them like they arrived
+        for (_, json) in active.iter().rev() {
+            redis::AsyncCommands::publish::<&str, &str, usize>(
+                &mut con,
+                &self.keys.pubsub_notification,
+                json,
+            )
+            .await?;
+        }
+
+        Ok(active.len() as u32)
+    }
+
+    /// Every trigger's query and the hook it runs. Triggers added through the API
+    /// never run one, hooks only come from `control_plane.hooks` in the config
+    pub(crate) async fn triggers(&self) -> error::Result<Vec<(String, String)>> {
+        let mut con = self.con();
+        let added: HashMap<String, String> =
+            redis::AsyncCommands::hgetall(&mut con, &self.keys.triggers).await?;
+
+        Ok(added
+            .into_keys()
+            .map(|query| (query, String::new()))
+            .chain(
+                self.hooks
+                    .iter()
+                    .map(|(query, hook)| (query.clone(), hook.clone())),
+            )
+            .collect())
+    }
+}
+
+#[tonic::async_trait]
+impl AdminService for ControlPlaneService {
+    async fn list_active(
+        &self,
+        _: Request<ListActiveRequest>,
+    ) -> Result<Response<ListActiveResponse>, Status> {
+        let notifications = self
+            .active()
+            .await?
+            .into_iter()
+            .map(|(notification, _)| notification)
+            .collect();
+
+        Ok(Response::new(ListActiveResponse { notifications }))
+    }
+
+    async fn force_close(
+        &self,
+        request: Request<ForceCloseRequest>,
+    ) -> Result<Response<ForceCloseResponse>, Status> {
+        let id = request.into_inner().id;
+        let closed = self.close_active(id).await?;
+        if closed {
+            log::info!("Force-closed notification id={}", id);
+        } else {
+            log::info!("Not force-closing id={}, it isn't active", id);
+        }
+
+        Ok(Response::new(ForceCloseResponse { closed }))
+    }
+
+    async fn rebroadcast(
+        &self,
+        _: Request<RebroadcastRequest>,
+    ) -> Result<Response<RebroadcastResponse>, Status> {
+        let count = self.publish_active().await?;
+        log::info!("Rebroadcast {} active notifications", count);
+
+        Ok(Response::new(RebroadcastResponse { count }))
+    }
+
+    async fn list_triggers(
+        &self,
+        _: Request<ListTriggersRequest>,
+    ) -> Result<Response<ListTriggersResponse>, Status> {
+        let mut triggers: Vec<_> = self
+            .triggers()
+            .await?
+            .into_iter()
+            .map(|(query, hook)| Trigger { query, hook })
+            .collect();
+        triggers.sort_by(|a, b| a.query.cmp(&b.query));
+
+        Ok(Response::new(ListTriggersResponse { triggers }))
+    }
+
+    async fn add_trigger(
+        &self,
+        request: Request<AddTriggerRequest>,
+    ) -> Result<Response<AddTriggerResponse>, Status> {
+        let Some(trigger) = request.into_inner().trigger else {
+            return Err(Status::invalid_argument("Missing trigger"));
+        };
+        if trigger.query.trim().is_empty() {
+            return Err(Status::invalid_argument(
+                "The query of a trigger can't be empty",
+            ));
+        }
+        // Anyone who can reach the API could run commands otherwise
+        if !trigger.hook.is_empty() {
+            return Err(Status::permission_denied(
+                "Hooks can only be set in control_plane.hooks of the config",
+            ));
+        }
+        trigger::terms(trigger::resolve(&trigger.query, &self.saved_searches))
+            .map_err(Status::invalid_argument)?;
+
+        let mut con = self.con();
+        redis::AsyncCommands::hset::<&str, &str, &str, ()>(
+            &mut con,
+            &self.keys.triggers,
+            &trigger.query,
+            "",
+        )
+        .await
+        .map_err(error::Error::from)?;
+        log::info!("Added trigger '{}'", trigger.query);
+
+        Ok(Response::new(AddTriggerResponse {}))
+    }
+
+    async fn remove_trigger(
+        &self,
+        request: Request<RemoveTriggerRequest>,
+    ) -> Result<Response<RemoveTriggerResponse>, Status> {
+        let query = request.into_inner().query;
+
+        let mut con = self.con();
+        let removed: usize = redis::AsyncCommands::hdel(&mut con, &self.keys.triggers, &query)
+            .await
+            .map_err(error::Error::from)?;
+        if removed > 0 {
+            log::info!("Removed trigger '{}'", query);
+        }
+
+        Ok(Response::new(RemoveTriggerResponse {
+            removed: removed > 0,
+        }))
+    }
+}
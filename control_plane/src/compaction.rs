@@ -0,0 +1,110 @@
+use config::keys::Keys;
+use redis::AsyncTypedCommands;
+use redis::streams::{StreamPendingReply, StreamTrimOptions, StreamTrimmingMode};
+
+/// Stream entry IDs are `<milliseconds>-<sequence>`
+fn parse_id(id: &str) -> Option<(u64, u64)> {
+    let (ms, seq) = id.split_once('-')?;
+    Some((ms.parse().ok()?, seq.parse().ok()?))
+}
+
+/// Oldest entry a group still needs: its first pending entry, or the one after
+/// the last it was delivered if it has acknowledged everything
+fn first_needed(last_delivered_id: &str, first_pending_id: Option<&str>) -> Option<(u64, u64)> {
+    match first_pending_id {
+        Some(id) => parse_id(id),
+        None => {
+            let (ms, seq) = parse_id(last_delivered_id)?;
+            Some(match seq.checked_add(1) {
+                Some(seq) => (ms, seq),
+                None => (ms + 1, 0),
+            })
+        }
+    }
+}
+
+/// Delete the entries of `stream` acknowledged by every consumer group,
+/// returns the number of entries deleted
+async fn compact_stream(
+    con: &mut redis::aio::MultiplexedConnection,
+    stream: &str,
+) -> redis::RedisResult<usize> {
+    let groups = AsyncTypedCommands::xinfo_groups(&mut *con, stream)
+        .await?
+        .groups;
+    // Without a group nothing tells which entries were consumed
+    if groups.is_empty() {
+        return Ok(0);
+    }
+
+    let mut min_id = None;
+    for group in &groups {
+        let first_pending_id =
+            match AsyncTypedCommands::xpending(&mut *con, stream, &group.name).await? {
+                StreamPendingReply::Data(data) => Some(data.start_id),
+                StreamPendingReply::Empty => None,
+            };
+
+        let Some(id) = first_needed(&group.last_delivered_id, first_pending_id.as_deref()) else {
+            log::warn!(
+                "Unexpected entry ID in group {} of {}, skipping",
+                group.name,
+                stream
+            );
+            return Ok(0);
+        };
+        min_id = Some(min_id.map_or(id, |min_id: (u64, u64)| min_id.min(id)));
+    }
+
+    let Some((ms, seq)) = min_id else {
+        return Ok(0);
+    };
+
+    AsyncTypedCommands::xtrim_options(
+        &mut *con,
+        stream,
+        &StreamTrimOptions::minid(StreamTrimmingMode::Exact, format!("{ms}-{seq}")),
+    )
+    .await
+}
+
+/// Compact every stream consumed through a consumer group
+pub async fn compact_streams(
+    con: &mut redis::aio::MultiplexedConnection,
+    keys: &Keys,
+) -> redis::RedisResult<usize> {
+    let mut deleted = 0;
+    for stream in [
+        &keys.notify,
+        &keys.close_notification,
+        &keys.notification_closed,
+        &keys.action_invoked,
+    ] {
+        match compact_stream(con, stream).await {
+            Ok(count) => {
+                log::debug!("Deleted {} entries from {}", count, stream);
+                deleted += count;
+            }
+            // The stream doesn't exist until the control plane creates its groups
+            Err(e) if e.code() == Some("ERR") => {
+                log::debug!("Skipping {}: {}", stream, e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_needed_entry() {
+        assert_eq!(first_needed("1700-3", None), Some((1700, 4)));
+        assert_eq!(first_needed("1700-3", Some("1500-0")), Some((1500, 0)));
+        assert_eq!(first_needed("0-0", None), Some((0, 1)));
+        assert_eq!(first_needed("invalid", None), None);
+    }
+}
@@ -0,0 +1,41 @@
+use config::keys::Keys;
+use redis::AsyncTypedCommands;
+
+/// Create the consumer groups of every stream, they're gone after Redis
+/// restarts without persistence
+pub async fn create_groups(con: &mut redis::aio::MultiplexedConnection, keys: &Keys) {
+    // If any of these errors it's likely because group already exists
+    for (stream, group) in [
+        (&keys.notify, "indexer-group"),
+        (&keys.notify, "scheduler-group"),
+        (&keys.notification_closed, "control-plane-group"),
+        (&keys.action_invoked, "control-plane-group"),
+        (&keys.close_notification, "scheduler-group"),
+    ] {
+        _ = AsyncTypedCommands::xgroup_create_mkstream(&mut *con, stream, group, "$").await;
+    }
+}
+
+/// Connect to Redis again after the connection was lost, retrying until it's back
+pub async fn reconnect(client: &redis::Client, keys: &Keys) -> redis::aio::MultiplexedConnection {
+    let mut backoff = supervisor::Backoff::default();
+
+    loop {
+        match client.get_multiplexed_async_connection().await {
+            Ok(mut con) => {
+                create_groups(&mut con, keys).await;
+                log::info!("Reconnected to Redis");
+                return con;
+            }
+            Err(e) => {
+                let delay = backoff.delay();
+                log::warn!(
+                    "Failed to reconnect to Redis: {}, retrying in {:?}",
+                    e,
+                    delay
+                );
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+}
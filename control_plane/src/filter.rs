@@ -0,0 +1,178 @@
+use crate::moxnotify::types::NewNotification;
+use crate::template::Template;
+use config::{Filter, FilterMatch, FilterSet};
+
+fn matches(notification: &NewNotification, filter: &FilterMatch) -> bool {
+    let hints = notification.hints.as_ref();
+
+    filter
+        .app_name
+        .as_ref()
+        .is_none_or(|app_name| *app_name == notification.app_name)
+        && filter.category.as_ref().is_none_or(|category| {
+            hints.and_then(|hints| hints.category.as_ref()) == Some(category)
+        })
+        && filter
+            .urgency
+            .is_none_or(|urgency| hints.map_or(0, |hints| hints.urgency) == urgency as i32)
+        && filter
+            .summary
+            .as_ref()
+            .is_none_or(|summary| notification.summary.contains(summary.as_str()))
+        && filter
+            .body
+            .as_ref()
+            .is_none_or(|body| notification.body.contains(body.as_str()))
+}
+
+/// Invalid templates are used as plain text so filters written before templates keep working
+fn render(template: &str, notification: &NewNotification) -> String {
+    match Template::parse(template) {
+        Ok(template) => template.render(notification),
+        Err(e) => {
+            log::warn!("Invalid template '{template}': {e}");
+            template.to_string()
+        }
+    }
+}
+
+fn set(notification: &mut NewNotification, set: &FilterSet) {
+    if let Some(app_name) = set.app_name.as_ref() {
+        notification.app_name = app_name.clone();
+    }
+    if let Some(app_icon) = set.app_icon.as_ref() {
+        notification.app_icon = Some(app_icon.clone());
+    }
+    // Both are rendered against the notification as it came in
+    let summary = set
+        .summary
+        .as_deref()
+        .map(|summary| render(summary, notification));
+    let body = set.body.as_deref().map(|body| render(body, notification));
+    if let Some(summary) = summary {
+        notification.summary = summary;
+    }
+    if let Some(body) = body {
+        notification.body = body;
+    }
+    if let Some(timeout) = set.timeout {
+        notification.timeout = timeout * 1000;
+    }
+
+    let hints = notification.hints.get_or_insert_default();
+    if let Some(category) = set.category.as_ref() {
+        hints.category = Some(category.clone());
+    }
+    if let Some(urgency) = set.urgency {
+        hints.urgency = urgency as i32;
+    }
+    if let Some(expire_action) = set.expire_action.as_ref() {
+        hints.expire_action = Some(expire_action.clone());
+    }
+}
+
+/// Apply `filters` in order, returns `false` if the notification should be dropped
+pub fn apply(filters: &[Filter], notification: &mut NewNotification) -> bool {
+    for filter in filters {
+        if !matches(notification, &filter.matches) {
+            continue;
+        }
+
+        if filter.drop {
+            return false;
+        }
+
+        set(notification, &filter.set);
+
+        let hints = notification.hints.get_or_insert_default();
+        filter.tags.iter().for_each(|tag| {
+            if !hints.tags.contains(tag) {
+                hints.tags.push(tag.clone());
+            }
+        });
+
+        if filter.last {
+            break;
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moxnotify::types::NotificationHints;
+
+    fn notification(app_name: &str) -> NewNotification {
+        NewNotification {
+            app_name: app_name.to_string(),
+            summary: "New message".to_string(),
+            body: "secret".to_string(),
+            hints: Some(NotificationHints::default()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn drop_stops_processing() {
+        let filters = [Filter {
+            matches: FilterMatch {
+                app_name: Some("spam".to_string()),
+                ..Default::default()
+            },
+            drop: true,
+            ..Default::default()
+        }];
+
+        assert!(!apply(&filters, &mut notification("spam")));
+        assert!(apply(&filters, &mut notification("signal")));
+    }
+
+    #[test]
+    fn set_and_tag_matching_notifications() {
+        let filters = [
+            Filter {
+                matches: FilterMatch {
+                    app_name: Some("signal".to_string()),
+                    ..Default::default()
+                },
+                set: FilterSet {
+                    body: Some(String::new()),
+                    ..Default::default()
+                },
+                tags: vec![config::tags::REDACT_BODY.to_string()],
+                last: true,
+                ..Default::default()
+            },
+            Filter {
+                drop: true,
+                ..Default::default()
+            },
+        ];
+
+        let mut signal = notification("signal");
+        assert!(apply(&filters, &mut signal));
+        assert!(signal.body.is_empty());
+        assert_eq!(signal.hints.unwrap().tags, [config::tags::REDACT_BODY]);
+
+        assert!(!apply(&filters, &mut notification("other")));
+    }
+
+    #[test]
+    fn set_renders_templates() {
+        let filters = [Filter {
+            set: FilterSet {
+                summary: Some("{app_name}: {body|upper}".to_string()),
+                body: Some("{if !summary}empty{else}{summary}{end}".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        }];
+
+        let mut signal = notification("signal");
+        assert!(apply(&filters, &mut signal));
+        assert_eq!(signal.summary, "signal: SECRET");
+        assert_eq!(signal.body, "New message");
+    }
+}
@@ -5,77 +5,534 @@ pub mod moxnotify {
     pub mod collector {
         tonic::include_proto!("moxnotify.collector");
     }
+    pub mod admin {
+        tonic::include_proto!("moxnotify.admin");
+    }
+
+    /// Served through gRPC reflection so tools like grpcurl can discover the API
+    pub const FILE_DESCRIPTOR_SET: &[u8] =
+        tonic::include_file_descriptor_set!("moxnotify_descriptor");
 }
 
+mod active;
+mod admin;
+mod compaction;
+mod connection;
+mod filter;
+mod overflow;
+mod template;
+mod trigger;
+
 use crate::moxnotify::collector::{collector_message, collector_response};
-use crate::moxnotify::types::{ActionInvoked, NotificationClosed};
+use crate::moxnotify::types::{
+    ActionInvoked, CloseReason, NewNotification, NotificationClosed, Urgency, image,
+};
+use audit::AuditLog;
 use clap::Parser;
+use config::error::{self, Error};
+use config::keys::Keys;
+use config::types::Compression;
+use config::{ActiveLimit, Filter};
+use moxnotify::admin::admin_service_server::AdminServiceServer;
 use moxnotify::collector::collector_service_server::{CollectorService, CollectorServiceServer};
 use moxnotify::collector::{CollectorMessage, CollectorResponse};
 use redis::AsyncTypedCommands;
 use redis::streams::StreamReadOptions;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::path::Path;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use tokio::sync::{Mutex, mpsc};
 use tokio_stream::StreamExt;
-use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::codec::CompressionEncoding;
 use tonic::transport::Server;
 use tonic::{Request, Response, Status};
 
+/// Move inline image bytes into a content-addressed Redis key, so the stream
+/// entry and the active hash only carry a reference to them
+async fn offload_image(
+    con: &mut redis::aio::MultiplexedConnection,
+    keys: &Keys,
+    notification: &mut NewNotification,
+    ttl: Duration,
+) -> redis::RedisResult<()> {
+    let Some(image::Image::Data(data)) = notification
+        .hints
+        .as_mut()
+        .and_then(|hints| hints.image.as_mut())
+        .and_then(|image| image.image.as_mut())
+    else {
+        return Ok(());
+    };
+
+    if data.data.is_empty() {
+        return Ok(());
+    }
+
+    let key = format!("{:x}", Sha256::digest(&data.data));
+    redis::AsyncCommands::set_ex::<String, &[u8], ()>(
+        con,
+        keys.blob(&key),
+        data.data.as_slice(),
+        ttl.as_secs(),
+    )
+    .await?;
+
+    data.data.clear();
+    data.blob = Some(key);
+
+    Ok(())
+}
+
+/// Put the actions the user invoked most often and most recently on notifications
+/// from the same app first, so they also get the first hint characters
+async fn order_actions(
+    con: &mut redis::aio::MultiplexedConnection,
+    keys: &Keys,
+    notification: &mut NewNotification,
+) -> redis::RedisResult<()> {
+    if notification.actions.len() < 2 {
+        return Ok(());
+    }
+
+    let scores: HashMap<String, f64> = redis::AsyncCommands::zrange_withscores::<
+        String,
+        Vec<(String, f64)>,
+    >(
+        con, keys.action_frecency(&notification.app_name), 0, -1
+    )
+    .await?
+    .into_iter()
+    .collect();
+
+    // Stable, actions that were never invoked keep the order the app sent them in.
+    // Scores are logarithms, so they can be below 0
+    notification.actions.sort_by(|a, b| {
+        let score = |key: &str| scores.get(key).copied().unwrap_or(f64::NEG_INFINITY);
+        score(&b.key).total_cmp(&score(&a.key))
+    });
+
+    Ok(())
+}
+
+/// Whether `notification`'s app was muted from a client. Critical notifications
+/// aren't held back by mutes
+async fn muted(
+    con: &mut redis::aio::MultiplexedConnection,
+    keys: &Keys,
+    notification: &NewNotification,
+) -> redis::RedisResult<bool> {
+    let critical = notification
+        .hints
+        .as_ref()
+        .is_some_and(|hints| hints.urgency() == Urgency::Critical);
+    if critical {
+        return Ok(false);
+    }
+
+    redis::AsyncCommands::exists(con, keys.mute(&notification.app_name)).await
+}
+
+/// Keep the active notifications within `limit` for `incoming`, closing one if the
+/// overflow policy calls for it. Returns `false` if `incoming` has to be dropped
+async fn make_room(
+    con: &mut redis::aio::MultiplexedConnection,
+    keys: &Keys,
+    audit: &AuditLog,
+    limit: ActiveLimit,
+    incoming: &NewNotification,
+) -> bool {
+    // Counting is cheaper than reading them all, which is only needed when full
+    match AsyncTypedCommands::hlen(&mut *con, &keys.active).await {
+        Ok(count) if limit.max == 0 || count < limit.max => return true,
+        Ok(_) => {}
+        Err(e) => {
+            log::warn!("Failed to count active notifications, not limiting: {}", e);
+            return true;
+        }
+    }
+
+    let active = match active::list(con, keys).await {
+        Ok(active) => active,
+        Err(e) => {
+            log::warn!("Failed to read active notifications, not limiting: {}", e);
+            return true;
+        }
+    };
+    let active: Vec<_> = active
+        .into_iter()
+        .map(|(notification, _)| notification)
+        .collect();
+
+    match overflow::decide(limit, &active, incoming) {
+        overflow::Decision::Admit => true,
+        overflow::Decision::Close(id) => {
+            log::warn!(
+                "{} notifications active, closing id={} to make room for id={}",
+                active.len(),
+                id,
+                incoming.id
+            );
+            let reason = CloseReason::ReasonUnknown;
+            match active::close(con, keys, id, reason).await {
+                Ok(Some(closed)) => {
+                    audit
+                        .record(
+                            id,
+                            &closed.uuid,
+                            audit::Event::Closed {
+                                reason: reason.as_str_name().to_string(),
+                            },
+                        )
+                        .await;
+                }
+                Ok(None) => {}
+                Err(e) => log::warn!("Failed to close id={}: {}", id, e),
+            }
+            true
+        }
+        overflow::Decision::Reject => {
+            log::error!(
+                "{} notifications active, dropping id={} from '{}'",
+                active.len(),
+                incoming.id,
+                incoming.app_name
+            );
+            false
+        }
+    }
+}
+
+/// Append a notification to the notify stream, zstd-compressed
+/// under the `notification_zstd` field if compression is enabled.
+/// Returns the ID of the entry
+async fn add_to_stream(
+    con: &mut redis::aio::MultiplexedConnection,
+    keys: &Keys,
+    json: &str,
+    compression: Compression,
+) -> error::Result<Option<String>> {
+    let stream_id = match compression {
+        Compression::None => {
+            AsyncTypedCommands::xadd(
+                con,
+                &keys.notify,
+                "*",
+                &[(config::stream::NOTIFICATION, json)],
+            )
+            .await?
+        }
+        Compression::Zstd => {
+            let compressed = zstd::encode_all(json.as_bytes(), 0).map_err(Error::Compress)?;
+            AsyncTypedCommands::xadd(
+                con,
+                &keys.notify,
+                "*",
+                &[(config::stream::NOTIFICATION_ZSTD, compressed.as_slice())],
+            )
+            .await?
+        }
+    };
+
+    Ok(stream_id)
+}
+
+/// Forward notification_closed and action_invoked Pub/Sub messages to a
+/// collector's connection until the Pub/Sub connection is lost, returns
+/// `false` once the collector's forwarder is gone
+async fn forward_pubsub(
+    mut pubsub: redis::aio::PubSub,
+    keys: &Keys,
+    notification_closed_tx: &mpsc::Sender<NotificationClosed>,
+    action_invoked_tx: &mpsc::Sender<ActionInvoked>,
+) -> redis::RedisResult<bool> {
+    pubsub.subscribe(&keys.pubsub_notification_closed).await?;
+    pubsub.subscribe(&keys.pubsub_action_invoked).await?;
+
+    let mut pubsub_stream = pubsub.on_message();
+
+    while let Some(msg) = pubsub_stream.next().await {
+        let payload = match msg.get_payload::<String>() {
+            Ok(payload) => payload,
+            Err(e) => {
+                log::warn!("Received malformed Pub/Sub message: {}", e);
+                continue;
+            }
+        };
+
+        let sent = match msg.get_channel_name() {
+            channel if channel == keys.pubsub_notification_closed => {
+                match serde_json::from_str::<NotificationClosed>(&payload) {
+                    Ok(closed) => notification_closed_tx.send(closed).await.is_ok(),
+                    Err(_) => true,
+                }
+            }
+            channel if channel == keys.pubsub_action_invoked => {
+                match serde_json::from_str::<ActionInvoked>(&payload) {
+                    Ok(action) => action_invoked_tx.send(action).await.is_ok(),
+                    Err(_) => true,
+                }
+            }
+            _ => true,
+        };
+
+        if !sent {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Forward Pub/Sub messages to a collector's connection, resubscribing
+/// whenever the connection to Redis is lost, e.g. because it restarted
+async fn read_pubsub(
+    client: redis::Client,
+    keys: Arc<Keys>,
+    notification_closed_tx: mpsc::Sender<NotificationClosed>,
+    action_invoked_tx: mpsc::Sender<ActionInvoked>,
+) {
+    let mut backoff = supervisor::Backoff::default();
+    let mut lost = false;
+
+    while !notification_closed_tx.is_closed() {
+        let result = match client.get_async_pubsub().await {
+            Ok(pubsub) => {
+                if lost {
+                    log::info!("Redis Pub/Sub connection restored, resubscribed");
+                    lost = false;
+                }
+                backoff.reset();
+
+                forward_pubsub(pubsub, &keys, &notification_closed_tx, &action_invoked_tx).await
+            }
+            Err(e) => Err(e),
+        };
+
+        match result {
+            // The collector's forwarder is gone
+            Ok(false) => return,
+            Ok(true) => log::warn!("Redis Pub/Sub connection lost"),
+            Err(e) => log::error!("Failed to subscribe to Redis Pub/Sub: {}", e),
+        }
+        lost = true;
+
+        let delay = backoff.delay();
+        log::info!("Resubscribing to Redis Pub/Sub in {:?}", delay);
+        tokio::time::sleep(delay).await;
+    }
+}
+
 #[derive(Clone)]
 pub struct ControlPlaneService {
-    con: Arc<Mutex<redis::aio::MultiplexedConnection>>,
+    /// Cloned for every use, the lock is only held to clone or replace it
+    con: Arc<RwLock<redis::aio::MultiplexedConnection>>,
     redis_client: redis::Client,
+    keys: Arc<Keys>,
+    blob_ttl: Duration,
+    compression: Compression,
+    audit: AuditLog,
+    filters: Arc<[Filter]>,
+    frecency: bool,
+    active_limit: ActiveLimit,
+    /// Held from making room until the notification is active, so notifications of
+    /// concurrent collectors can't both take the last slot
+    admission: Arc<Mutex<()>>,
+    saved_searches: Arc<HashMap<String, String>>,
+    /// Commands run for matching notifications by query, see `control_plane.hooks`
+    hooks: Arc<HashMap<String, String>>,
 }
 
 impl ControlPlaneService {
+    #[allow(clippy::too_many_arguments)]
     async fn try_new(
         mut redis_con: redis::aio::MultiplexedConnection,
         redis_client: redis::Client,
+        keys: Arc<Keys>,
+        blob_ttl: Duration,
+        compression: Compression,
+        audit: AuditLog,
+        filters: Arc<[Filter]>,
+        frecency: bool,
+        active_limit: ActiveLimit,
+        saved_searches: Arc<HashMap<String, String>>,
+        hooks: Arc<HashMap<String, String>>,
     ) -> anyhow::Result<Self> {
-        // If any of these errors it's likely because group already exists
-        _ = AsyncTypedCommands::xgroup_create_mkstream(
-            &mut redis_con,
-            "moxnotify:notify",
-            "indexer-group",
-            "$",
-        )
-        .await;
-        _ = AsyncTypedCommands::xgroup_create_mkstream(
-            &mut redis_con,
-            "moxnotify:notify",
-            "scheduler-group",
-            "$",
-        )
-        .await;
-        _ = AsyncTypedCommands::xgroup_create_mkstream(
-            &mut redis_con,
-            "moxnotify:notification_closed",
-            "control-plane-group",
-            "$",
-        )
-        .await;
-        _ = AsyncTypedCommands::xgroup_create_mkstream(
-            &mut redis_con,
-            "moxnotify:action_invoked",
-            "control-plane-group",
-            "$",
-        )
-        .await;
-        _ = AsyncTypedCommands::xgroup_create_mkstream(
-            &mut redis_con,
-            "moxnotify:close_notification",
-            "scheduler-group",
-            "$",
-        )
-        .await;
+        connection::create_groups(&mut redis_con, &keys).await;
 
         Ok(Self {
-            con: Arc::new(Mutex::new(redis_con)),
+            con: Arc::new(RwLock::new(redis_con)),
             redis_client,
+            keys,
+            blob_ttl,
+            compression,
+            audit,
+            filters,
+            frecency,
+            active_limit,
+            admission: Arc::new(Mutex::new(())),
+            saved_searches,
+            hooks,
         })
     }
+
+    /// A handle on the shared connection. Requests on it are pipelined, so a slow
+    /// one doesn't hold up the others
+    fn con(&self) -> redis::aio::MultiplexedConnection {
+        self.con.read().unwrap().clone()
+    }
+
+    /// Replace the shared connection once Redis is reachable again
+    async fn reconnect(&self) {
+        let con = connection::reconnect(&self.redis_client, &self.keys).await;
+        *self.con.write().unwrap() = con;
+    }
+
+    /// Run the hooks of the triggers `notification` matches. Returns a critical copy
+    /// of it to show as well if a trigger without a hook matches
+    async fn fire_triggers(&self, notification: &NewNotification) -> Option<NewNotification> {
+        let triggers = match self.triggers().await {
+            Ok(triggers) => triggers,
+            Err(e) => {
+                log::warn!("Failed to read triggers: {}", e);
+                return None;
+            }
+        };
+
+        let mut derive = false;
+        for (query, hook) in &triggers {
+            let terms = match trigger::terms(trigger::resolve(query, &self.saved_searches)) {
+                Ok(terms) => terms,
+                Err(e) => {
+                    log::warn!("Skipping trigger '{}': {}", query, e);
+                    continue;
+                }
+            };
+            if !trigger::matches(&terms, notification) {
+                continue;
+            }
+
+            log::info!(
+                "Notification id={} matches trigger '{}'",
+                notification.id,
+                query
+            );
+            if hook.is_empty() {
+                derive = true;
+            } else {
+                trigger::run_hook(hook, query, notification);
+            }
+        }
+
+        derive.then(|| trigger::derive(notification))
+    }
+
+    /// Show `notification` on the clients, unless it's muted or there's no room for it
+    async fn admit(&self, mut notification: NewNotification) {
+        let mut con = self.con();
+        match muted(&mut con, &self.keys, &notification).await {
+            Ok(true) => {
+                log::info!(
+                    "Notification id={} dropped, '{}' is muted",
+                    notification.id,
+                    notification.app_name
+                );
+                self.audit
+                    .record(notification.id, &notification.uuid, audit::Event::Filtered)
+                    .await;
+                return;
+            }
+            Ok(false) => {}
+            Err(e) => log::warn!(
+                "Failed to check if '{}' is muted: {}",
+                notification.app_name,
+                e
+            ),
+        }
+
+        let _admission = self.admission.lock().await;
+        if !make_room(
+            &mut con,
+            &self.keys,
+            &self.audit,
+            self.active_limit,
+            &notification,
+        )
+        .await
+        {
+            self.audit
+                .record(notification.id, &notification.uuid, audit::Event::Filtered)
+                .await;
+            return;
+        }
+
+        if self.frecency
+            && let Err(e) = order_actions(&mut con, &self.keys, &mut notification).await
+        {
+            log::warn!("Failed to order actions by frecency: {}", e);
+        }
+
+        if let Err(e) = offload_image(&mut con, &self.keys, &mut notification, self.blob_ttl).await
+        {
+            log::warn!("Failed to store image blob, keeping it inline: {}", e);
+        }
+
+        let mut json = match serde_json::to_string(&notification) {
+            Ok(json) => json,
+            Err(e) => {
+                log::error!("Failed to serialize notification: {}", e);
+                return;
+            }
+        };
+        match add_to_stream(&mut con, &self.keys, &json, self.compression).await {
+            // Clients order notifications by the entry ID, which unlike
+            // timestamps is unique and only ever grows
+            Ok(Some(stream_id)) => {
+                notification.stream_id = stream_id;
+                match serde_json::to_string(&notification) {
+                    Ok(with_id) => json = with_id,
+                    Err(e) => {
+                        log::warn!("Failed to serialize notification with its stream ID: {}", e)
+                    }
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                log::error!("Failed to add notification to Redis stream: {}", e);
+                if e.is_disconnected() {
+                    self.reconnect().await;
+                }
+                return;
+            }
+        }
+
+        let id_str = notification.id.to_string();
+        if let Err(e) =
+            AsyncTypedCommands::hset(&mut con, &self.keys.active, id_str.as_str(), json.as_str())
+                .await
+        {
+            log::warn!("Failed to add notification to active HASH: {}", e);
+        }
+
+        // Publish to Redis Pub/Sub
+        if let Err(e) = redis::AsyncCommands::publish::<&str, &str, usize>(
+            &mut con,
+            &self.keys.pubsub_notification,
+            &json,
+        )
+        .await
+        {
+            log::error!("Failed to publish notification to Redis Pub/Sub: {}", e);
+        }
+
+        self.audit
+            .record(notification.id, &notification.uuid, audit::Event::Received)
+            .await;
+    }
 }
 
 #[tonic::async_trait]
@@ -92,104 +549,127 @@ impl CollectorService for ControlPlaneService {
         &self,
         request: Request<tonic::Streaming<CollectorMessage>>,
     ) -> Result<Response<Self::NotificationsStream>, Status> {
-        let remote_addr = request.remote_addr().unwrap();
+        let remote_addr = request.remote_addr();
         log::info!("New connection from: {:?}", remote_addr);
         let mut stream = request.into_inner();
 
-        let con = Arc::clone(&self.con);
+        let service = self.clone();
+        let keys = Arc::clone(&self.keys);
+        let audit = self.audit.clone();
+        let filters = Arc::clone(&self.filters);
 
-        let notification_closed_sub_client = self.redis_client.clone();
+        let sub_client = self.redis_client.clone();
+        let pubsub_keys = Arc::clone(&self.keys);
         let (tx, rx) = mpsc::channel(128);
+        let (notification_closed_tx, mut notification_closed_rx) = mpsc::channel(128);
+        let (action_invoked_tx, mut action_invoked_rx) = mpsc::channel(128);
 
-        tokio::spawn(async move {
-            let (notification_closed_tx, mut notification_closed_rx) = mpsc::channel(128);
-            let (action_invoked_tx, mut action_invoked_rx) = mpsc::channel(128);
-
-            let mut pubsub = notification_closed_sub_client
-                .get_async_pubsub()
-                .await
-                .unwrap();
-
-            let _ = pubsub
-                .subscribe("moxnotify:pubsub:notification_closed")
-                .await;
-            let _ = pubsub.subscribe("moxnotify:pubsub:action_invoked").await;
-
-            let mut pubsub_stream = pubsub.on_message();
+        let pubsub_reader =
+            supervisor::spawn(format!("Pub/Sub reader for {:?}", remote_addr), move || {
+                read_pubsub(
+                    sub_client.clone(),
+                    Arc::clone(&pubsub_keys),
+                    notification_closed_tx.clone(),
+                    action_invoked_tx.clone(),
+                )
+            });
 
+        let forwarder = tokio::spawn(async move {
             loop {
                 tokio::select! {
-                    Some(msg) = pubsub_stream.next() => {
-                        let payload = msg.get_payload::<String>().unwrap();
-                        let channel = msg.get_channel_name();
-
-                        match channel {
-                            "moxnotify:pubsub:notification_closed" => {
-                                if let Ok(closed) = serde_json::from_str::<NotificationClosed>(&payload) {
-                                    let _ = notification_closed_tx.send(closed).await;
-                                }
-                            }
-                            "moxnotify:pubsub:action_invoked" => {
-                                if let Ok(action) = serde_json::from_str::<ActionInvoked>(&payload) {
-                                    let _ = action_invoked_tx.send(action).await;
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
                     msg = stream.next() => {
                         match msg {
                             Some(Ok(msg)) => match msg.message {
-                                Some(collector_message::Message::NewNotification(notification)) => {
+                                Some(collector_message::Message::NewNotification(mut notification)) => {
+                                    let received_at = config::now_ms();
+                                    notification.latency.get_or_insert_default().ingested_at = received_at;
+                                    // Ordered by this clock, the collectors' may be skewed. Older
+                                    // collectors only set the timestamp
+                                    if notification.client_timestamp == 0 {
+                                        notification.client_timestamp = notification.timestamp;
+                                    }
+                                    notification.timestamp = received_at;
                                     log::info!(
-                                        "Received notification: id={}, app_name='{}', summary='{}', body='{}', urgency='{}'",
+                                        "Received notification: id={}, app_name='{}', summary={}, body={}, urgency='{}'",
                                         notification.id,
                                         notification.app_name,
-                                        notification.summary,
-                                        notification.body,
-                                        notification.hints.as_ref().unwrap().urgency
+                                        logging::content(&notification.summary),
+                                        logging::content(&notification.body),
+                                        notification.hints.as_ref().map(|hints| hints.urgency).unwrap_or_default()
                                     );
 
-                                    let mut con = con.lock().await;
-                                    let json = serde_json::to_string(&notification).unwrap();
-                                    if let Err(e) = AsyncTypedCommands::xadd(&mut *con, "moxnotify:notify", "*", &[("notification", json.as_str())]).await {
-                                        log::error!("Failed to add notification to Redis stream: {}", e);
-                                        drop(con);
+                                    if !filter::apply(&filters, &mut notification) {
+                                        log::info!("Notification id={} dropped by filter", notification.id);
+                                        audit.record(notification.id, &notification.uuid, audit::Event::Filtered).await;
                                         continue;
                                     }
 
-                                    let id_str = notification.id.to_string();
-                                    if let Err(e) =
-                                        AsyncTypedCommands::hset(&mut *con, "moxnotify:active", id_str.as_str(), json.as_str()).await
-                                    {
-                                        log::warn!("Failed to add notification to active HASH: {}", e);
-                                    }
-
-                                    // Publish to Redis Pub/Sub
-                                    if let Err(e) = redis::AsyncCommands::publish::<&str, &str, usize>(&mut *con, "moxnotify:pubsub:notification", &json).await {
-                                        log::error!("Failed to publish notification to Redis Pub/Sub: {}", e);
+                                    let derived = service.fire_triggers(&notification).await;
+                                    service.admit(notification).await;
+                                    if let Some(derived) = derived {
+                                        service.admit(derived).await;
                                     }
                                 }
                                 Some(collector_message::Message::CloseNotification(close)) => {
                                     log::info!("Received close notification request: id={}", close.id);
 
-                                    let mut con = con.lock().await;
-                                    let json = serde_json::to_string(&close).unwrap();
+                                    let json = match serde_json::to_string(&close) {
+                                        Ok(json) => json,
+                                        Err(e) => {
+                                            log::error!("Failed to serialize close notification: {}", e);
+                                            continue;
+                                        }
+                                    };
+                                    let mut con = service.con();
+                                    let closed = match active::get(&mut con, &keys, close.id).await {
+                                        Ok(closed) => closed,
+                                        Err(e) => {
+                                            log::warn!("Failed to read active notification id={}: {}", close.id, e);
+                                            None
+                                        }
+                                    };
                                     if let Err(e) = AsyncTypedCommands::xadd(
-                                        &mut *con,
-                                        "moxnotify:close_notification",
+                                        &mut con,
+                                        &keys.close_notification,
                                         "*",
                                         &[("close_notification", json.as_str())],
                                     ).await {
                                         log::error!("Failed to add close_notification to Redis stream: {}", e);
-                                        drop(con);
                                         continue;
                                     }
 
                                     let id_str = close.id.to_string();
-                                    if let Err(e) = AsyncTypedCommands::hdel(&mut *con, "moxnotify:active", id_str.as_str()).await {
+                                    if let Err(e) = AsyncTypedCommands::hdel(&mut con, &keys.active, id_str.as_str()).await {
                                         log::warn!("Failed to remove notification from active HASH: {}", e);
                                     }
+
+                                    // Nothing was closed if it wasn't active
+                                    if let Some(closed) = closed {
+                                        audit
+                                            .record(
+                                                close.id,
+                                                &closed.uuid,
+                                                audit::Event::Closed {
+                                                    reason: CloseReason::ReasonCloseNotificationCall.as_str_name().to_string(),
+                                                },
+                                            )
+                                            .await;
+                                    }
+                                }
+                                Some(collector_message::Message::Hello(hello)) => {
+                                    log::info!(
+                                        "Collector {:?} speaks protocol version {}",
+                                        remote_addr,
+                                        hello.protocol_version
+                                    );
+                                    if hello.protocol_version > config::protocol::VERSION {
+                                        log::warn!(
+                                            "Collector {:?} is newer than the control plane (protocol version {} > {})",
+                                            remote_addr,
+                                            hello.protocol_version,
+                                            config::protocol::VERSION
+                                        );
+                                    }
                                 }
                                 None => {
                                     log::warn!("Received empty CollectorMessage");
@@ -257,13 +737,79 @@ impl CollectorService for ControlPlaneService {
                     else => {}
                 }
             }
+
+            pubsub_reader.abort();
         });
+        supervisor::watch(format!("forwarder for {:?}", remote_addr), forwarder);
 
         let output_stream: Self::NotificationsStream = Box::pin(ReceiverStream::new(rx));
         Ok(Response::new(output_stream))
     }
 }
 
+async fn forward_action_invoked(
+    con: &mut redis::aio::MultiplexedConnection,
+    keys: &Keys,
+    map: &HashMap<String, redis::Value>,
+) -> error::Result<()> {
+    let Some(redis::Value::BulkString(json)) = map.get("action") else {
+        return Ok(());
+    };
+    let json = std::str::from_utf8(json)?;
+    let action = serde_json::from_str::<ActionInvoked>(json)?;
+
+    log::info!(
+        "Received action_invoked from Redis: id: {}, action_key: {}",
+        action.id,
+        action.action_key
+    );
+
+    log::info!(
+        "Publishing action_invoked to Redis Pub/Sub: id={}, action_key={}",
+        action.id,
+        action.action_key
+    );
+
+    redis::AsyncCommands::publish::<&str, &str, usize>(con, &keys.pubsub_action_invoked, json)
+        .await?;
+
+    log::info!("Finished publishing for id={}", action.id);
+
+    Ok(())
+}
+
+async fn forward_notification_closed(
+    con: &mut redis::aio::MultiplexedConnection,
+    keys: &Keys,
+    map: &HashMap<String, redis::Value>,
+) -> error::Result<()> {
+    let Some(redis::Value::BulkString(json)) = map.get("notification") else {
+        return Ok(());
+    };
+    let json = std::str::from_utf8(json)?;
+    let closed = serde_json::from_str::<NotificationClosed>(json)?;
+
+    log::info!(
+        "Received notification_closed from Redis: id: {}, reason: {:?}",
+        closed.id,
+        closed.reason()
+    );
+
+    log::info!(
+        "Publishing notification_closed to Redis Pub/Sub: id={}, reason={:?}",
+        closed.id,
+        closed.reason()
+    );
+
+    redis::AsyncCommands::publish::<&str, &str, usize>(con, &keys.pubsub_notification_closed, json)
+        .await?;
+
+    log::debug!("Published notification_closed to Redis Pub/Sub");
+    log::info!("Finished publishing for id={}", closed.id);
+
+    Ok(())
+}
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
@@ -284,25 +830,126 @@ async fn main() -> anyhow::Result<()> {
     env_logger::Builder::new()
         .filter(Some("control_plane"), config.control_plane.log_level.into())
         .init();
+    logging::set_log_content(config.log_content);
 
-    let client = redis::Client::open(&*config.redis.address).unwrap();
+    let keys = Arc::new(config.redis.keys());
+    let client = redis::Client::open(&*config.redis.address)?;
     let write_con = client.get_multiplexed_async_connection().await?;
     let read_con = client.get_multiplexed_async_connection().await?;
     let pub_con = client.get_multiplexed_async_connection().await?;
+    let audit = AuditLog::new(
+        client.get_multiplexed_async_connection().await?,
+        "control_plane",
+        keys.audit.clone(),
+        &config.audit,
+    );
+
+    let service = ControlPlaneService::try_new(
+        write_con,
+        client.clone(),
+        Arc::clone(&keys),
+        config.redis.blob_ttl,
+        config.control_plane.compression,
+        audit,
+        config.control_plane.filters.clone().into(),
+        config.frecency.enabled,
+        config.control_plane.active_limit,
+        config.searcher.saved_searches.clone().into(),
+        config.control_plane.hooks.clone().into(),
+    )
+    .await?;
+
+    if config.control_plane.compaction.enabled {
+        let schedule = config.control_plane.compaction.schedule;
+        let con = client.get_multiplexed_async_connection().await?;
+        let keys = Arc::clone(&keys);
+        supervisor::spawn("stream compaction", move || {
+            let mut con = con.clone();
+            let keys = Arc::clone(&keys);
+            async move {
+                let mut interval = tokio::time::interval(schedule);
+                loop {
+                    interval.tick().await;
+                    match compaction::compact_streams(&mut con, &keys).await {
+                        Ok(count) => {
+                            log::info!("Stream compaction completed: {} entries deleted", count)
+                        }
+                        Err(e) => log::error!("Stream compaction failed: {}", e),
+                    }
+                }
+            }
+        });
+    }
 
-    let service = ControlPlaneService::try_new(write_con, client.clone()).await?;
+    let admin_service = AdminServiceServer::new(service.clone());
+    let mut collector_service =
+        CollectorServiceServer::new(service).accept_compressed(CompressionEncoding::Zstd);
+    if config.control_plane.compression == Compression::Zstd {
+        collector_service = collector_service.send_compressed(CompressionEncoding::Zstd);
+    }
 
-    tokio::spawn(async move {
-        Server::builder()
-            .add_service(CollectorServiceServer::new(service.clone()))
-            .serve(config.control_plane.address.parse().unwrap())
-            .await
-            .unwrap();
+    let reflection_service = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(moxnotify::FILE_DESCRIPTOR_SET)
+        .build_v1()?;
 
-        log::info!(
-            "Control plane server listening on {}",
-            config.control_plane.address
-        );
+    let admin_address: SocketAddr = config.control_plane.admin_address.parse()?;
+    if !admin_address.ip().is_loopback() {
+        anyhow::bail!("control_plane.admin_address {admin_address} isn't a loopback address");
+    }
+    let admin_listener = std::net::TcpListener::bind(admin_address)?;
+    admin_listener.set_nonblocking(true)?;
+    let admin_reflection_service = reflection_service.clone();
+    supervisor::spawn("admin server", move || {
+        let admin_service = admin_service.clone();
+        let reflection_service = admin_reflection_service.clone();
+        let listener = admin_listener.try_clone();
+        async move {
+            let listener = match listener.and_then(tokio::net::TcpListener::from_std) {
+                Ok(listener) => listener,
+                Err(e) => {
+                    log::error!("Admin server failed to listen: {}", e);
+                    return;
+                }
+            };
+            if let Ok(addr) = listener.local_addr() {
+                log::info!("Admin server listening on {}", addr);
+            }
+            if let Err(e) = Server::builder()
+                .add_service(admin_service)
+                .add_service(reflection_service)
+                .serve_with_incoming(TcpListenerStream::new(listener))
+                .await
+            {
+                log::error!("Admin server failed: {}", e);
+            }
+        }
+    });
+
+    let listener = supervisor::listener(config.control_plane.address.parse()?)?;
+    supervisor::spawn("control plane server", move || {
+        let collector_service = collector_service.clone();
+        let reflection_service = reflection_service.clone();
+        let listener = listener.try_clone();
+        async move {
+            let listener = match listener.and_then(tokio::net::TcpListener::from_std) {
+                Ok(listener) => listener,
+                Err(e) => {
+                    log::error!("Control plane server failed to listen: {}", e);
+                    return;
+                }
+            };
+            if let Ok(addr) = listener.local_addr() {
+                log::info!("Control plane server listening on {}", addr);
+            }
+            if let Err(e) = Server::builder()
+                .add_service(collector_service)
+                .add_service(reflection_service)
+                .serve_with_incoming(TcpListenerStream::new(listener))
+                .await
+            {
+                log::error!("Control plane server failed: {}", e);
+            }
+        }
     });
 
     let mut read_con_mut = read_con;
@@ -315,9 +962,9 @@ async fn main() -> anyhow::Result<()> {
         let stream_ids = if read_pending { ["0", "0"] } else { [">", ">"] };
         read_pending = !read_pending;
 
-        if let Ok(Some(streams)) = AsyncTypedCommands::xread_options(
+        let streams = match AsyncTypedCommands::xread_options(
             &mut read_con_mut,
-            &["moxnotify:action_invoked", "moxnotify:notification_closed"],
+            &[&keys.action_invoked, &keys.notification_closed],
             &stream_ids,
             &StreamReadOptions::default()
                 .group("control-plane-group", "control-plane")
@@ -325,99 +972,65 @@ async fn main() -> anyhow::Result<()> {
         )
         .await
         {
-            for stream_key in streams.keys.iter() {
-                for stream_id in &stream_key.ids {
-                    match stream_key.key.as_str() {
-                        "moxnotify:action_invoked" => {
-                            if let Some(redis::Value::BulkString(json)) =
-                                stream_id.map.get("action")
-                            {
-                                let json = std::str::from_utf8(json).unwrap();
-                                let action = serde_json::from_str::<ActionInvoked>(json).unwrap();
-
-                                log::info!(
-                                    "Received action_invoked from Redis: id: {}, action_key: {}",
-                                    action.id,
-                                    action.action_key
-                                );
-
-                                log::info!(
-                                    "Publishing action_invoked to Redis Pub/Sub: id={}, action_key={}",
-                                    action.id,
-                                    action.action_key
-                                );
-
-                                if let Err(e) = redis::AsyncCommands::publish::<&str, &str, usize>(
-                                    &mut pub_con_mut,
-                                    "moxnotify:pubsub:action_invoked",
-                                    json,
-                                )
-                                .await
-                                {
-                                    log::error!(
-                                        "Failed to publish action_invoked to Redis Pub/Sub: {}",
-                                        e
-                                    );
-                                    // Don't ACK if publishing failed
-                                    continue;
-                                }
-
-                                log::info!("Finished publishing for id={}", action.id);
-                            }
-                        }
-                        "moxnotify:notification_closed" => {
-                            if let Some(redis::Value::BulkString(json)) =
-                                stream_id.map.get("notification")
-                            {
-                                let json = std::str::from_utf8(json).unwrap();
-                                let closed =
-                                    serde_json::from_str::<NotificationClosed>(json).unwrap();
-
-                                log::info!(
-                                    "Received notification_closed from Redis: id: {}, reason: {:?}",
-                                    closed.id,
-                                    closed.reason()
-                                );
-
-                                log::info!(
-                                    "Publishing notification_closed to Redis Pub/Sub: id={}, reason={:?}",
-                                    closed.id,
-                                    closed.reason()
-                                );
-
-                                if let Err(e) = redis::AsyncCommands::publish::<&str, &str, usize>(
-                                    &mut pub_con_mut,
-                                    "moxnotify:pubsub:notification_closed",
-                                    json,
-                                )
-                                .await
-                                {
-                                    log::error!(
-                                        "Failed to publish notification_closed to Redis Pub/Sub: {}",
-                                        e
-                                    );
-                                    // Don't ACK if publishing failed
-                                    continue;
-                                }
+            Ok(Some(streams)) => streams,
+            Ok(None) => continue,
+            Err(e) if e.is_unrecoverable_error() => {
+                log::error!("Lost connection to Redis while reading streams: {}", e);
+                read_con_mut = connection::reconnect(&client, &keys).await;
+                pub_con_mut = connection::reconnect(&client, &keys).await;
+                log::info!("Resumed reading Redis streams");
+                continue;
+            }
+            Err(e) => {
+                log::error!("Failed to read from Redis streams: {}", e);
+                // The groups are gone if Redis restarted without persistence
+                if e.code() == Some("NOGROUP") {
+                    connection::create_groups(&mut read_con_mut, &keys).await;
+                }
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
 
-                                log::debug!("Published notification_closed to Redis Pub/Sub");
-                                log::info!("Finished publishing for id={}", closed.id);
-                            }
-                        }
-                        _ => unreachable!(),
+        for stream_key in streams.keys.iter() {
+            for stream_id in &stream_key.ids {
+                let result = match stream_key.key.as_str() {
+                    key if key == keys.action_invoked => {
+                        forward_action_invoked(&mut pub_con_mut, &keys, &stream_id.map).await
+                    }
+                    key if key == keys.notification_closed => {
+                        forward_notification_closed(&mut pub_con_mut, &keys, &stream_id.map).await
                     }
+                    _ => unreachable!(),
+                };
 
-                    if let Err(ack_err) = AsyncTypedCommands::xack(
-                        &mut read_con_mut,
-                        stream_key.key.as_str(),
-                        "control-plane-group",
-                        &[stream_id.id.as_str()],
-                    )
-                    .await
-                    {
-                        log::error!("Failed to ACK message: {}", ack_err);
+                if let Err(e) = result {
+                    log::error!(
+                        "Failed to handle {} entry {}: {}",
+                        stream_key.key,
+                        stream_id.id,
+                        e
+                    );
+                    if e.is_disconnected() {
+                        pub_con_mut = connection::reconnect(&client, &keys).await;
+                    }
+                    // Don't ACK if publishing failed, but drop malformed entries
+                    // so they aren't redelivered forever
+                    if !e.is_malformed() {
+                        continue;
                     }
                 }
+
+                if let Err(ack_err) = AsyncTypedCommands::xack(
+                    &mut read_con_mut,
+                    stream_key.key.as_str(),
+                    "control-plane-group",
+                    &[stream_id.id.as_str()],
+                )
+                .await
+                {
+                    log::error!("Failed to ACK message: {}", ack_err);
+                }
             }
         }
     }
@@ -0,0 +1,139 @@
+use crate::moxnotify::types::{NewNotification, Urgency};
+use config::{ActiveLimit, Overflow};
+
+/// What to do with an incoming notification given the active ones
+#[derive(Debug, PartialEq)]
+pub enum Decision {
+    Admit,
+    /// Admit it once the active notification with this ID is closed to make room
+    Close(u32),
+    Reject,
+}
+
+fn urgency(notification: &NewNotification) -> Urgency {
+    notification
+        .hints
+        .as_ref()
+        .map(|hints| hints.urgency())
+        .unwrap_or_default()
+}
+
+/// How to keep `active`, ordered newest first, within `limit` when `incoming` arrives
+pub fn decide(
+    limit: ActiveLimit,
+    active: &[NewNotification],
+    incoming: &NewNotification,
+) -> Decision {
+    // Replacing a notification doesn't add one
+    if limit.max == 0
+        || active.len() < limit.max
+        || active
+            .iter()
+            .any(|notification| notification.id == incoming.id)
+    {
+        return Decision::Admit;
+    }
+
+    let oldest = |matches: &dyn Fn(&NewNotification) -> bool| {
+        active
+            .iter()
+            .rev()
+            .find(|notification| matches(notification))
+            .map(|notification| notification.id)
+    };
+
+    let id = match limit.overflow {
+        // Critical notifications are never dropped for others
+        Overflow::DropOldest => oldest(&|n| urgency(n) == Urgency::Low)
+            .or_else(|| oldest(&|n| urgency(n) == Urgency::Normal)),
+        Overflow::Coalesce => oldest(&|n| n.app_name == incoming.app_name),
+        Overflow::Reject => None,
+    };
+
+    id.map_or(Decision::Reject, Decision::Close)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moxnotify::types::NotificationHints;
+
+    fn notification(id: u32, app_name: &str, urgency: Urgency) -> NewNotification {
+        NewNotification {
+            id,
+            app_name: app_name.to_string(),
+            hints: Some(NotificationHints {
+                urgency: urgency as i32,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn limit(overflow: Overflow) -> ActiveLimit {
+        ActiveLimit { max: 3, overflow }
+    }
+
+    #[test]
+    fn overflow_policies_make_room() {
+        // Newest first
+        let active = [
+            notification(3, "spam", Urgency::Low),
+            notification(2, "mail", Urgency::Low),
+            notification(1, "spam", Urgency::Normal),
+        ];
+        let incoming = notification(4, "spam", Urgency::Normal);
+
+        assert_eq!(
+            decide(limit(Overflow::DropOldest), &active[..2], &incoming),
+            Decision::Admit
+        );
+        assert_eq!(
+            decide(limit(Overflow::DropOldest), &active, &incoming),
+            Decision::Close(2)
+        );
+        assert_eq!(
+            decide(limit(Overflow::Coalesce), &active, &incoming),
+            Decision::Close(1)
+        );
+        assert_eq!(
+            decide(limit(Overflow::Reject), &active, &incoming),
+            Decision::Reject
+        );
+        // Replacements take the place of the one they replace
+        assert_eq!(
+            decide(
+                limit(Overflow::Reject),
+                &active,
+                &notification(3, "spam", Urgency::Low)
+            ),
+            Decision::Admit
+        );
+    }
+
+    #[test]
+    fn critical_notifications_are_kept() {
+        let active = [
+            notification(3, "a", Urgency::Critical),
+            notification(2, "b", Urgency::Critical),
+            notification(1, "c", Urgency::Critical),
+        ];
+
+        assert_eq!(
+            decide(
+                limit(Overflow::DropOldest),
+                &active,
+                &notification(4, "d", Urgency::Low)
+            ),
+            Decision::Reject
+        );
+        assert_eq!(
+            decide(
+                limit(Overflow::Coalesce),
+                &active,
+                &notification(4, "d", Urgency::Low)
+            ),
+            Decision::Reject
+        );
+    }
+}
@@ -0,0 +1,322 @@
+//! Templates for the summary and body filters set, e.g.
+//! `{app_name|upper}: {if body}{body|first_line|truncate:40}{else}no text{end}`
+
+use crate::moxnotify::types::{NewNotification, Urgency};
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum Error {
+    #[error("Unclosed '{{'")]
+    Unclosed,
+    #[error("Unknown field '{0}'")]
+    UnknownField(String),
+    #[error("Unknown filter '{0}'")]
+    UnknownFilter(String),
+    #[error("'{0}' without a matching '{{if}}'")]
+    Unmatched(&'static str),
+    #[error("'{{if}}' without a matching '{{end}}'")]
+    MissingEnd,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    AppName,
+    Summary,
+    Body,
+    Category,
+    Urgency,
+}
+
+impl Field {
+    fn parse(name: &str) -> Result<Self, Error> {
+        match name {
+            "app_name" => Ok(Self::AppName),
+            "summary" => Ok(Self::Summary),
+            "body" => Ok(Self::Body),
+            "category" => Ok(Self::Category),
+            "urgency" => Ok(Self::Urgency),
+            _ => Err(Error::UnknownField(name.to_string())),
+        }
+    }
+
+    fn value(self, notification: &NewNotification) -> &str {
+        let hints = notification.hints.as_ref();
+
+        match self {
+            Self::AppName => &notification.app_name,
+            Self::Summary => &notification.summary,
+            Self::Body => &notification.body,
+            Self::Category => hints
+                .and_then(|hints| hints.category.as_deref())
+                .unwrap_or_default(),
+            Self::Urgency => match hints.map(|hints| hints.urgency()) {
+                Some(Urgency::Low) => "low",
+                Some(Urgency::Critical) => "critical",
+                Some(Urgency::Normal) | None => "normal",
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Filter {
+    Upper,
+    Lower,
+    Trim,
+    FirstLine,
+    /// At most this many characters, an ellipsis marks the cut
+    Truncate(usize),
+}
+
+impl Filter {
+    fn parse(filter: &str) -> Result<Self, Error> {
+        match filter.trim().split_once(':') {
+            None if filter.trim() == "upper" => Ok(Self::Upper),
+            None if filter.trim() == "lower" => Ok(Self::Lower),
+            None if filter.trim() == "trim" => Ok(Self::Trim),
+            None if filter.trim() == "first_line" => Ok(Self::FirstLine),
+            Some(("truncate", len)) => len
+                .trim()
+                .parse()
+                .map(Self::Truncate)
+                .map_err(|_| Error::UnknownFilter(filter.to_string())),
+            _ => Err(Error::UnknownFilter(filter.to_string())),
+        }
+    }
+
+    fn apply(&self, value: String) -> String {
+        match self {
+            Self::Upper => value.to_uppercase(),
+            Self::Lower => value.to_lowercase(),
+            Self::Trim => value.trim().to_string(),
+            Self::FirstLine => value.lines().next().unwrap_or_default().to_string(),
+            Self::Truncate(len) => match value.char_indices().nth(*len) {
+                Some((end, _)) => format!("{}…", &value[..end]),
+                None => value,
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Node {
+    Text(String),
+    Value {
+        field: Field,
+        filters: Vec<Filter>,
+    },
+    /// Renders `then` if the field isn't empty, or is with `negated`
+    If {
+        field: Field,
+        negated: bool,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+enum Tag<'a> {
+    If { field: Field, negated: bool },
+    Else,
+    End,
+    Value(&'a str),
+}
+
+/// Split `template` into text and the contents of `{...}` tags, `{{` and `}}` are literal braces
+fn tokenize(template: &str) -> Result<Vec<Result<String, Tag<'_>>>, Error> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut rest = template;
+
+    while let Some(i) = rest.find(['{', '}']) {
+        text.push_str(&rest[..i]);
+        let brace = &rest[i..i + 1];
+        rest = &rest[i + 1..];
+
+        if let Some(after) = rest.strip_prefix(brace) {
+            text.push_str(brace);
+            rest = after;
+            continue;
+        }
+        if brace == "}" {
+            text.push('}');
+            continue;
+        }
+
+        let end = rest.find('}').ok_or(Error::Unclosed)?;
+        let tag = rest[..end].trim();
+        rest = &rest[end + 1..];
+
+        if !text.is_empty() {
+            tokens.push(Ok(std::mem::take(&mut text)));
+        }
+        tokens.push(Err(match tag.split_once(char::is_whitespace) {
+            Some(("if", field)) => {
+                let field = field.trim();
+                let (field, negated) = match field.strip_prefix('!') {
+                    Some(field) => (field.trim(), true),
+                    None => (field, false),
+                };
+                Tag::If {
+                    field: Field::parse(field)?,
+                    negated,
+                }
+            }
+            _ if tag == "else" => Tag::Else,
+            _ if tag == "end" => Tag::End,
+            _ => Tag::Value(tag),
+        }));
+    }
+
+    text.push_str(rest);
+    if !text.is_empty() {
+        tokens.push(Ok(text));
+    }
+
+    Ok(tokens)
+}
+
+pub struct Template(Vec<Node>);
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self, Error> {
+        // Innermost open block last, with the branch being filled
+        let mut blocks: Vec<(Field, bool, Vec<Node>, Option<Vec<Node>>)> = Vec::new();
+        let mut nodes = Vec::new();
+
+        for token in tokenize(template)? {
+            let node = match token {
+                Ok(text) => Node::Text(text),
+                Err(Tag::Value(value)) => {
+                    let mut parts = value.split('|');
+                    Node::Value {
+                        field: Field::parse(parts.next().unwrap_or_default().trim())?,
+                        filters: parts.map(Filter::parse).collect::<Result<_, _>>()?,
+                    }
+                }
+                Err(Tag::If { field, negated }) => {
+                    blocks.push((field, negated, std::mem::take(&mut nodes), None));
+                    continue;
+                }
+                Err(Tag::Else) => {
+                    let block = blocks.last_mut().ok_or(Error::Unmatched("{else}"))?;
+                    if block.3.is_some() {
+                        return Err(Error::Unmatched("{else}"));
+                    }
+                    block.3 = Some(std::mem::take(&mut nodes));
+                    continue;
+                }
+                Err(Tag::End) => {
+                    let (field, negated, outer, then) =
+                        blocks.pop().ok_or(Error::Unmatched("{end}"))?;
+                    let branch = std::mem::replace(&mut nodes, outer);
+                    let (then, otherwise) = match then {
+                        Some(then) => (then, branch),
+                        None => (branch, Vec::new()),
+                    };
+                    Node::If {
+                        field,
+                        negated,
+                        then,
+                        otherwise,
+                    }
+                }
+            };
+            nodes.push(node);
+        }
+
+        if !blocks.is_empty() {
+            return Err(Error::MissingEnd);
+        }
+
+        Ok(Self(nodes))
+    }
+
+    #[must_use]
+    pub fn render(&self, notification: &NewNotification) -> String {
+        let mut output = String::new();
+        render(&self.0, notification, &mut output);
+        output
+    }
+}
+
+fn render(nodes: &[Node], notification: &NewNotification, output: &mut String) {
+    nodes.iter().for_each(|node| match node {
+        Node::Text(text) => output.push_str(text),
+        Node::Value { field, filters } => {
+            let value = filters
+                .iter()
+                .fold(field.value(notification).to_string(), |value, filter| {
+                    filter.apply(value)
+                });
+            output.push_str(&value);
+        }
+        Node::If {
+            field,
+            negated,
+            then,
+            otherwise,
+        } => {
+            let branch = if field.value(notification).is_empty() == *negated {
+                then
+            } else {
+                otherwise
+            };
+            render(branch, notification, output);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moxnotify::types::NotificationHints;
+
+    fn render(template: &str, summary: &str, body: &str) -> String {
+        let notification = NewNotification {
+            app_name: "Signal".to_string(),
+            summary: summary.to_string(),
+            body: body.to_string(),
+            hints: Some(NotificationHints::default()),
+            ..Default::default()
+        };
+
+        Template::parse(template).unwrap().render(&notification)
+    }
+
+    #[test]
+    fn renders_fields_and_filters() {
+        assert_eq!(
+            render("{app_name|upper}: {summary}", "Alice", ""),
+            "SIGNAL: Alice"
+        );
+        assert_eq!(
+            render("{body|first_line|truncate:5}", "", "Hello there\nbye"),
+            "Hello…"
+        );
+        assert_eq!(render("{{literal}}", "", ""), "{literal}");
+    }
+
+    #[test]
+    fn renders_conditionals() {
+        let template = "{if body}{summary}: {body}{else}{summary}{end}{if !body} (no text){end}";
+        assert_eq!(render(template, "Alice", "hi"), "Alice: hi");
+        assert_eq!(render(template, "Alice", ""), "Alice (no text)");
+        assert_eq!(
+            render("{if summary}{if body}both{end}{end}", "a", "b"),
+            "both"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        assert_eq!(Template::parse("{body").err(), Some(Error::Unclosed));
+        assert_eq!(
+            Template::parse("{sender}").err(),
+            Some(Error::UnknownField("sender".to_string()))
+        );
+        assert_eq!(Template::parse("{if body}x").err(), Some(Error::MissingEnd));
+        assert_eq!(
+            Template::parse("x{end}").err(),
+            Some(Error::Unmatched("{end}"))
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use crate::moxnotify::types::{NewNotification, NotificationHints, Urgency};
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// IDs handed out to derived notifications start here, clear of the D-Bus
+/// collector's sequential IDs and the StatusNotifierItem collector's from 1 << 31
+pub const FIRST_ID: u32 = 3 << 30;
+
+/// No collector has it, so they ignore actions and closes of derived notifications
+pub const UUID: &str = "trigger";
+
+const FIELDS: [&str; 3] = ["app_name", "summary", "body"];
+
+/// Words of the searcher's query syntax that [`matches`] has no equivalent for
+const OPERATORS: [&str; 2] = ["OR", "NOT"];
+
+/// Words and "quoted phrases" of `query`. `AND` is skipped, so saved searches
+/// written for the searcher read the same. Fails on the searcher's other
+/// operators, which would otherwise be looked for as words and never match
+pub fn terms(query: &str) -> Result<Vec<String>, String> {
+    let mut terms = Vec::new();
+    let mut term = String::new();
+    let mut quoted = false;
+    let mut push = |term: &mut String, quoted_term: bool| -> Result<(), String> {
+        let term = std::mem::take(term);
+        if term.is_empty() || term == "AND" {
+            return Ok(());
+        }
+        if !quoted_term
+            && (OPERATORS.contains(&term.as_str())
+                || term.starts_with(['-', '+'])
+                || term.contains(['(', ')']))
+        {
+            return Err(format!("'{term}' isn't supported in trigger queries"));
+        }
+        terms.push(term);
+        Ok(())
+    };
+    let mut was_quoted = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                was_quoted = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                push(&mut term, was_quoted)?;
+                was_quoted = false;
+            }
+            c => term.push(c),
+        }
+    }
+    push(&mut term, was_quoted)?;
+
+    Ok(terms)
+}
+
+/// Whether every one of `terms` is in the app name, summary or body of
+/// `notification`, ignoring case. Terms like `summary:word` only look at that field
+pub fn matches(terms: &[String], notification: &NewNotification) -> bool {
+    let values = [
+        notification.app_name.to_lowercase(),
+        notification.summary.to_lowercase(),
+        notification.body.to_lowercase(),
+    ];
+
+    !terms.is_empty()
+        && terms.iter().all(|term| {
+            let (field, text) = match term.split_once(':') {
+                Some((field, text)) if FIELDS.contains(&field) => (Some(field), text),
+                _ => (None, term.as_str()),
+            };
+            let text = text.to_lowercase();
+
+            FIELDS
+                .iter()
+                .zip(&values)
+                .filter(|(name, _)| field.is_none_or(|field| field == **name))
+                .any(|(_, value)| value.contains(&text))
+        })
+}
+
+/// The query `query` stands for, the saved search's if it names one
+pub fn resolve<'a>(query: &'a str, saved_searches: &'a HashMap<String, String>) -> &'a str {
+    saved_searches.get(query).map_or(query, String::as_str)
+}
+
+/// ID of the copy derived from `notification`. The same for every update of it,
+/// so the copy is replaced instead of piling up, e.g. with progress updates
+pub fn derived_id(notification: &NewNotification) -> u32 {
+    let mut hasher = DefaultHasher::new();
+    (&notification.uuid, notification.id).hash(&mut hasher);
+
+    FIRST_ID + (hasher.finish() % u64::from(u32::MAX - FIRST_ID)) as u32
+}
+
+/// A critical copy of `notification` that stays until it's dismissed
+pub fn derive(notification: &NewNotification) -> NewNotification {
+    NewNotification {
+        id: derived_id(notification),
+        app_name: notification.app_name.clone(),
+        app_icon: notification.app_icon.clone(),
+        summary: notification.summary.clone(),
+        body: notification.body.clone(),
+        timeout: 0,
+        hints: Some(NotificationHints {
+            urgency: Urgency::Critical as i32,
+            category: notification
+                .hints
+                .as_ref()
+                .and_then(|hints| hints.category.clone()),
+            ..Default::default()
+        }),
+        timestamp: notification.timestamp,
+        uuid: UUID.to_string(),
+        client_timestamp: notification.client_timestamp,
+        ..Default::default()
+    }
+}
+
+/// Run `hook` through the shell in the background with `notification` in the
+/// environment
+pub fn run_hook(hook: &str, query: &str, notification: &NewNotification) {
+    let child = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(hook)
+        .env("MOXNOTIFY_TRIGGER", query)
+        .env("MOXNOTIFY_ID", notification.id.to_string())
+        .env("MOXNOTIFY_APP_NAME", &notification.app_name)
+        .env("MOXNOTIFY_SUMMARY", &notification.summary)
+        .env("MOXNOTIFY_BODY", &notification.body)
+        .spawn();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            log::error!("Failed to run hook of trigger '{}': {}", query, e);
+            return;
+        }
+    };
+
+    let query = query.to_string();
+    tokio::spawn(async move {
+        match child.wait().await {
+            Ok(status) if status.success() => {}
+            Ok(status) => log::warn!("Hook of trigger '{}' exited with {}", query, status),
+            Err(e) => log::error!("Failed to wait for hook of trigger '{}': {}", query, e),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(summary: &str, body: &str) -> NewNotification {
+        NewNotification {
+            app_name: "CI".to_string(),
+            summary: summary.to_string(),
+            body: body.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn matches(query: &str, notification: &NewNotification) -> bool {
+        super::matches(&terms(query).unwrap(), notification)
+    }
+
+    #[test]
+    fn every_term_has_to_match() {
+        let failed = notification("Deploy of api", "Build FAILED after 3m");
+
+        assert!(matches("deploy failed", &failed));
+        assert!(matches("ci \"failed after\"", &failed));
+        assert!(!matches("deploy succeeded", &failed));
+        assert!(!matches("\"deploy failed\"", &failed));
+        assert!(!matches("", &failed));
+    }
+
+    #[test]
+    fn fields_limit_terms() {
+        let failed = notification("Deploy of api", "Build failed");
+
+        assert!(matches("summary:deploy body:failed", &failed));
+        assert!(!matches("summary:failed", &failed));
+        assert!(matches("app_name:ci AND failed", &failed));
+    }
+
+    #[test]
+    fn other_operators_are_rejected() {
+        assert!(terms("deploy OR release").is_err());
+        assert!(terms("NOT deploy").is_err());
+        assert!(terms("deploy -staging").is_err());
+        assert!(terms("(deploy failed)").is_err());
+        assert_eq!(
+            terms("\"or not\" \"-1\"").unwrap(),
+            vec!["or not".to_string(), "-1".to_string()]
+        );
+    }
+
+    #[test]
+    fn updates_replace_the_derived_copy() {
+        let first = notification("Upload", "10%");
+        let update = notification("Upload", "20%");
+        let other = NewNotification {
+            id: 1,
+            ..notification("Upload", "10%")
+        };
+
+        assert_eq!(derive(&first).id, derive(&update).id);
+        assert_ne!(derive(&first).id, derive(&other).id);
+        assert!(derive(&first).id >= FIRST_ID);
+    }
+}
@@ -0,0 +1,12 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tonic_prost_build::configure()
+        .build_server(false)
+        .type_attribute(".", "#[derive(serde::Serialize)]")
+        .type_attribute(".", "#[serde(rename_all = \"snake_case\")]")
+        .compile_protos(
+            &["../proto/types.proto", "../proto/admin.proto"],
+            &["../proto"],
+        )?;
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use crate::moxnotify::admin::admin_service_client::AdminServiceClient;
+use crate::moxnotify::admin::{
+    AddTriggerRequest, ForceCloseRequest, ListActiveRequest, ListTriggersRequest,
+    RebroadcastRequest, RemoveTriggerRequest, Trigger,
+};
+use crate::notify::{self, ListItem};
+
+pub enum Action {
+    List,
+    Close(u32),
+    Rebroadcast,
+    Triggers,
+    AddTrigger(String),
+    RemoveTrigger(String),
+}
+
+/// Run `action` against the admin API of the control plane at `address`, or the
+/// configured `control_plane.admin_address`
+pub async fn run(
+    config: &config::Config,
+    address: Option<&str>,
+    action: Action,
+    json: bool,
+) -> anyhow::Result<()> {
+    let address = address.map_or_else(
+        || format!("http://{}", config.control_plane.admin_address),
+        str::to_string,
+    );
+    let mut admin = AdminServiceClient::connect(address).await?;
+
+    match action {
+        Action::List => {
+            let now = config::now_ms();
+
+            // Same shape as the client's list, so both print the same table
+            let list = admin
+                .list_active(ListActiveRequest {})
+                .await?
+                .into_inner()
+                .notifications
+                .into_iter()
+                .map(|notification| {
+                    let age = (now - notification.timestamp).max(0);
+                    let remaining = (notification.timeout > 0)
+                        .then(|| (i64::from(notification.timeout) - age).max(0));
+                    let mut item = serde_json::to_value(notification)?;
+                    if let Some(item) = item.as_object_mut() {
+                        item.insert("age".into(), age.into());
+                        item.insert("remaining".into(), remaining.into());
+                    }
+                    Ok(ListItem::new(item, false))
+                })
+                .collect::<serde_json::Result<Vec<_>>>()?;
+
+            if json {
+                println!("{}", serde_json::to_string(&list)?);
+            } else {
+                print!("{}", notify::table(&list));
+            }
+        }
+        Action::Close(id) => {
+            let closed = admin
+                .force_close(ForceCloseRequest { id })
+                .await?
+                .into_inner()
+                .closed;
+
+            if json {
+                println!("{}", serde_json::json!({ "id": id, "closed": closed }));
+            } else if closed {
+                println!("{}", i18n::tr!("ctl-admin-closed", id = id));
+            } else {
+                anyhow::bail!(i18n::tr!("ctl-admin-not-active", id = id));
+            }
+        }
+        Action::Rebroadcast => {
+            let count = admin
+                .rebroadcast(RebroadcastRequest {})
+                .await?
+                .into_inner()
+                .count;
+
+            if json {
+                println!("{}", serde_json::json!({ "count": count }));
+            } else {
+                println!("{}", i18n::tr!("ctl-admin-rebroadcast", count = count));
+            }
+        }
+        Action::Triggers => {
+            let triggers = admin
+                .list_triggers(ListTriggersRequest {})
+                .await?
+                .into_inner()
+                .triggers;
+
+            if json {
+                println!("{}", serde_json::to_string(&triggers)?);
+            } else {
+                for trigger in triggers {
+                    if trigger.hook.is_empty() {
+                        println!("{}", trigger.query);
+                    } else {
+                        println!("{}\t{}", trigger.query, trigger.hook);
+                    }
+                }
+            }
+        }
+        Action::AddTrigger(query) => {
+            admin
+                .add_trigger(AddTriggerRequest {
+                    trigger: Some(Trigger {
+                        query: query.clone(),
+                        hook: String::new(),
+                    }),
+                })
+                .await?;
+
+            if json {
+                println!("{}", serde_json::json!({ "query": query }));
+            } else {
+                println!("{}", i18n::tr!("ctl-trigger-added", query = query.as_str()));
+            }
+        }
+        Action::RemoveTrigger(query) => {
+            let removed = admin
+                .remove_trigger(RemoveTriggerRequest {
+                    query: query.clone(),
+                })
+                .await?
+                .into_inner()
+                .removed;
+
+            if json {
+                println!(
+                    "{}",
+                    serde_json::json!({ "query": query, "removed": removed })
+                );
+            } else if removed {
+                println!(
+                    "{}",
+                    i18n::tr!("ctl-trigger-removed", query = query.as_str())
+                );
+            } else {
+                anyhow::bail!(i18n::tr!("ctl-trigger-not-found", query = query.as_str()));
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use chrono::{DateTime, Local};
+
+/// Print the recorded lifecycle of notification `id` with `uuid`, as an array of
+/// entries with `json`
+pub async fn show(
+    config: &config::Config,
+    id: u32,
+    uuid: Option<&str>,
+    json: bool,
+) -> anyhow::Result<()> {
+    if !config.client.general.history.lock_after.is_zero()
+        && !crate::notify::unlock_history().await?
+    {
+        anyhow::bail!(i18n::tr!("ctl-history-locked"));
+    }
+
+    let client = redis::Client::open(&*config.redis.address)?;
+    let mut con = client.get_multiplexed_async_connection().await?;
+    let entries = audit::history(&mut con, &config.redis.keys().audit, id, uuid).await?;
+
+    if json {
+        println!("{}", serde_json::to_string(&entries)?);
+        return Ok(());
+    }
+
+    if entries.is_empty() {
+        if config.audit.enabled {
+            println!("{}", i18n::tr!("ctl-audit-empty", id = id));
+        } else {
+            println!("{}", i18n::tr!("ctl-audit-disabled", id = id));
+        }
+        return Ok(());
+    }
+
+    entries.iter().for_each(|entry| {
+        let timestamp = DateTime::from_timestamp_millis(entry.timestamp)
+            .map(|timestamp| {
+                timestamp
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S%.3f")
+                    .to_string()
+            })
+            .unwrap_or_else(|| entry.timestamp.to_string());
+
+        println!(
+            "{timestamp}  {:<13}  {}  {}",
+            entry.service, entry.event, entry.uuid
+        );
+    });
+
+    Ok(())
+}
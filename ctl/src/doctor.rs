@@ -0,0 +1,245 @@
+use crate::notify::{NotificationsProxy, NotifyProxy};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use zbus::fdo::DBusProxy;
+use zbus::names::BusName;
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+/// How long the test notification gets to go through the pipeline
+const PIPELINE_TIMEOUT: Duration = Duration::from_secs(5);
+
+enum Status {
+    Ok(String),
+    Warn(String),
+    Fail(String),
+}
+
+#[derive(Default)]
+struct Report {
+    failed: usize,
+    /// Checks are collected and printed at once as JSON instead of as they finish
+    json: Option<Vec<serde_json::Value>>,
+}
+
+impl Report {
+    fn check(&mut self, name: &str, status: Status) {
+        let (label, detail) = match status {
+            Status::Ok(detail) => ("ok", detail),
+            Status::Warn(detail) => ("warn", detail),
+            Status::Fail(detail) => {
+                self.failed += 1;
+                ("FAIL", detail)
+            }
+        };
+
+        match self.json.as_mut() {
+            Some(checks) => checks.push(serde_json::json!({
+                "check": name,
+                "status": label.to_lowercase(),
+                "detail": detail,
+            })),
+            None => println!("[{label:^4}] {name:<24} {detail}"),
+        }
+    }
+}
+
+/// Services listen on an unspecified address, connect to them over loopback instead
+fn loopback(address: &str) -> String {
+    let address = address
+        .trim_start_matches("http://")
+        .trim_start_matches("https://");
+
+    address
+        .replacen("0.0.0.0:", "127.0.0.1:", 1)
+        .replacen("[::]:", "[::1]:", 1)
+}
+
+async fn reachable(address: &str) -> Status {
+    let address = loopback(address);
+    match tokio::time::timeout(TIMEOUT, TcpStream::connect(&address)).await {
+        Ok(Ok(_)) => Status::Ok(address),
+        Ok(Err(e)) => Status::Fail(format!("{address}: {e}")),
+        Err(_) => Status::Fail(format!("{address}: timed out")),
+    }
+}
+
+async fn name_owner(dbus: &DBusProxy<'_>, name: &'static str) -> Status {
+    let Ok(name) = BusName::try_from(name) else {
+        return Status::Fail(format!("{name} isn't a valid bus name"));
+    };
+
+    match dbus.get_name_owner(name.clone()).await {
+        Ok(owner) => Status::Ok(format!("{name} owned by {owner}")),
+        Err(e) => Status::Fail(format!("{name}: {e}")),
+    }
+}
+
+/// The index the searcher reads
+fn index(config: &config::Config) -> Status {
+    let path = config.index_path(config.searcher.index_path.as_deref());
+
+    if !path.join("meta.json").exists() {
+        return Status::Warn(format!(
+            "no index at {}, history search isn't set up",
+            path.display()
+        ));
+    }
+
+    match std::fs::read_to_string(path.join("moxnotify-version")) {
+        Ok(version) => Status::Ok(format!("{}, version {}", path.display(), version.trim())),
+        Err(e) => Status::Warn(format!("{}, unknown version: {e}", path.display())),
+    }
+}
+
+/// Send a notification and follow it through the services, reporting the last one it reached
+async fn pipeline(
+    config: &config::Config,
+    notifications: &NotificationsProxy<'_>,
+    con: Option<&mut redis::aio::MultiplexedConnection>,
+) -> Status {
+    let sent_at = config::now_ms();
+    let id = match notifications
+        .notify(
+            "moxnotifyctl",
+            0,
+            "",
+            "moxnotifyctl doctor",
+            "Checking the notification pipeline",
+            &[],
+            HashMap::new(),
+            PIPELINE_TIMEOUT.as_millis() as i32,
+        )
+        .await
+    {
+        Ok(id) => id,
+        Err(e) => return Status::Fail(format!("collector rejected the notification: {e}")),
+    };
+
+    let Some(con) = con else {
+        return Status::Warn(format!(
+            "collector accepted notification {id}, Redis is needed to follow it further"
+        ));
+    };
+
+    let keys = config.redis.keys();
+    let deadline = tokio::time::Instant::now() + PIPELINE_TIMEOUT;
+    let mut stage = "collector";
+    while tokio::time::Instant::now() < deadline {
+        if config.audit.enabled {
+            let entries = audit::history(con, &keys.audit, id, None)
+                .await
+                .unwrap_or_default();
+            let reached = |event: fn(&audit::Event) -> bool| {
+                entries
+                    .iter()
+                    .any(|entry| entry.timestamp >= sent_at && event(&entry.event))
+            };
+
+            if reached(|event| matches!(event, audit::Event::Displayed)) {
+                return Status::Ok(format!(
+                    "notification {id} displayed in {}ms",
+                    config::now_ms() - sent_at
+                ));
+            }
+            if reached(|event| matches!(event, audit::Event::Received)) {
+                stage = "control plane";
+            }
+        } else if redis::AsyncTypedCommands::hexists(&mut *con, &keys.active, id.to_string())
+            .await
+            .unwrap_or_default()
+        {
+            return Status::Warn(format!(
+                "notification {id} reached the control plane, enable audit.enabled to follow it to the client"
+            ));
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    Status::Fail(format!("notification {id} stalled after the {stage}"))
+}
+
+/// Check every part of the pipeline and report the ones that don't work
+pub async fn run(config: &config::Config, json: bool) -> anyhow::Result<()> {
+    let mut report = Report {
+        json: json.then(Vec::new),
+        ..Default::default()
+    };
+
+    let conn = zbus::Connection::session().await?;
+    let dbus = DBusProxy::new(&conn).await?;
+    report.check(
+        "notification server",
+        name_owner(&dbus, "org.freedesktop.Notifications").await,
+    );
+    report.check("client", name_owner(&dbus, "pl.mox.Notify").await);
+
+    let notifications = NotificationsProxy::new(&conn).await?;
+    let server = match notifications.get_server_information().await {
+        Ok((name, vendor, version, _)) if &*name == "moxnotify" || &*vendor == "mox" => {
+            Status::Ok(format!("{name} {version}"))
+        }
+        Ok((name, vendor, ..)) => Status::Fail(format!("{name} by {vendor} isn't moxnotify")),
+        Err(e) => Status::Fail(e.to_string()),
+    };
+    report.check("server information", server);
+
+    if let Ok(notify) = NotifyProxy::new(&conn).await {
+        match notify.waiting().await {
+            Ok(waiting) => {
+                report.check("client responds", Status::Ok(format!("{waiting} waiting")))
+            }
+            Err(e) => report.check("client responds", Status::Fail(e.to_string())),
+        }
+    }
+
+    report.check(
+        "control plane",
+        reachable(&config.control_plane.address).await,
+    );
+    report.check("scheduler", reachable(&config.scheduler.address).await);
+    report.check("searcher", reachable(&config.searcher.address).await);
+
+    let mut con = match redis::Client::open(&*config.redis.address) {
+        Ok(client) => {
+            match tokio::time::timeout(TIMEOUT, client.get_multiplexed_async_connection()).await {
+                Ok(Ok(con)) => Some(con),
+                Ok(Err(e)) => {
+                    report.check("redis", Status::Fail(e.to_string()));
+                    None
+                }
+                Err(_) => {
+                    report.check("redis", Status::Fail("timed out".to_string()));
+                    None
+                }
+            }
+        }
+        Err(e) => {
+            report.check("redis", Status::Fail(e.to_string()));
+            None
+        }
+    };
+    if let Some(con) = con.as_mut() {
+        match redis::cmd("PING").query_async::<String>(con).await {
+            Ok(_) => report.check("redis", Status::Ok(config.redis.address.to_string())),
+            Err(e) => report.check("redis", Status::Fail(e.to_string())),
+        }
+    }
+
+    report.check("history index", index(config));
+    report.check(
+        "pipeline",
+        pipeline(config, &notifications, con.as_mut()).await,
+    );
+
+    if let Some(checks) = report.json.as_ref() {
+        println!("{}", serde_json::to_string(checks)?);
+    }
+
+    if report.failed > 0 {
+        anyhow::bail!(i18n::tr!("ctl-doctor-failed", count = report.failed));
+    }
+
+    Ok(())
+}
@@ -1,14 +1,41 @@
+pub mod moxnotify {
+    pub mod types {
+        tonic::include_proto!("moxnotify.types");
+    }
+    pub mod admin {
+        tonic::include_proto!("moxnotify.admin");
+    }
+}
+
+mod admin;
+mod audit;
+mod doctor;
 mod notify;
-use clap::{Parser, Subcommand};
-use std::path::Path;
+
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Parser)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, arg_required_else_help = true)]
 struct Cli {
     #[arg(short, long, value_name = "FILE", help = "Path to the config file")]
     config: Option<Box<Path>>,
+    #[arg(
+        long,
+        exclusive = true,
+        help = "Print the JSON Schema of the config for editors to validate it against"
+    )]
+    dump_config_schema: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "Print output as JSON for scripts, one document per line when streaming"
+    )]
+    json: bool,
     #[command(subcommand)]
-    command: NotifyCommand,
+    command: Option<NotifyCommand>,
 }
 
 #[derive(Subcommand)]
@@ -44,10 +71,25 @@ enum NotifyCommand {
     },
 
     #[command(about = "List active notifications")]
-    List,
+    List {
+        #[arg(
+            long,
+            help = "Print every notification as JSON, including the pixels of inline images"
+        )]
+        full: bool,
+    },
 
-    #[command(about = "List active notifications")]
-    Waiting,
+    #[command(about = "Print how many notifications wait for inhibiting to end")]
+    Waiting {
+        #[command(subcommand)]
+        action: Option<WaitingAction>,
+    },
+
+    #[command(about = "Show the notifications held back for the digest")]
+    Digest,
+
+    #[command(about = "Load the client's config file again")]
+    Reload,
 
     #[command(about = "Mute notifications")]
     Mute {
@@ -60,6 +102,253 @@ enum NotifyCommand {
         #[command(subcommand)]
         action: SwitchAction,
     },
+
+    #[command(about = "Style notifications for low vision, regardless of the theme")]
+    HighContrast {
+        #[command(subcommand)]
+        action: SwitchAction,
+    },
+
+    #[command(about = "Read or clear the notification history")]
+    History {
+        #[command(subcommand)]
+        action: HistoryAction,
+    },
+
+    #[command(about = "Show the recorded lifecycle of a notification")]
+    Audit {
+        #[arg(help = "ID of the notification")]
+        id: u32,
+        #[arg(
+            long,
+            help = "UUID of the notification, the latest one with the ID when unset"
+        )]
+        uuid: Option<String>,
+    },
+
+    #[command(about = "Check every part of the notification pipeline")]
+    Doctor,
+
+    #[command(about = "Manage the notifications the control plane tracks as active")]
+    Admin {
+        #[arg(
+            long,
+            help = "Address of the control plane's admin API, control_plane.admin_address by default"
+        )]
+        address: Option<String>,
+        #[command(subcommand)]
+        action: AdminAction,
+    },
+
+    #[command(about = "Act on incoming notifications that match a query")]
+    Trigger {
+        #[command(subcommand)]
+        action: TriggerAction,
+    },
+
+    #[command(about = "Show a synthetic notification to try out styles, bypassing the pipeline")]
+    Preview {
+        #[arg(short, long, value_enum, default_value_t = Urgency::Normal, help = "Urgency level")]
+        urgency: Urgency,
+
+        #[arg(long, help = "Add actions")]
+        with_actions: bool,
+
+        #[arg(long, help = "Add an image")]
+        with_image: bool,
+
+        #[arg(long, value_name = "PERCENT", help = "Add a progress bar")]
+        with_progress: Option<u8>,
+
+        #[arg(
+            long,
+            help = "Cycle through every urgency, with and without actions, image and progress"
+        )]
+        cycle: bool,
+
+        #[arg(
+            long,
+            value_name = "SECONDS",
+            default_value_t = 2,
+            requires = "cycle",
+            help = "How long every state of the cycle is shown"
+        )]
+        interval: u64,
+    },
+
+    #[command(about = "Restyle notifications on screen with css layered over the config")]
+    Style {
+        #[arg(
+            required_unless_present = "reset",
+            help = "Css file applied on top of the configured styles"
+        )]
+        file: Option<PathBuf>,
+
+        #[arg(
+            long,
+            requires = "file",
+            help = "Apply the file again whenever it changes"
+        )]
+        watch: bool,
+
+        #[arg(
+            long,
+            conflicts_with = "file",
+            help = "Go back to the configured styles"
+        )]
+        reset: bool,
+    },
+
+    #[command(about = "Change the log level of a module while the service is running")]
+    LogLevel {
+        #[arg(short, long, value_enum, default_value_t = Service::Client, help = "Service to change")]
+        service: Service,
+
+        #[arg(
+            requires = "level",
+            help = "Module to change, e.g. client::wayland, prints every level if omitted"
+        )]
+        module: Option<String>,
+
+        #[arg(help = "One of off, error, warn, info, debug or trace")]
+        level: Option<String>,
+    },
+
+    #[command(about = "Print shell completions")]
+    Completions {
+        #[arg(help = "Shell to complete in")]
+        shell: clap_complete::Shell,
+    },
+
+    #[command(about = "Send a notification")]
+    Send {
+        #[arg(help = "Summary of the notification")]
+        summary: String,
+
+        #[arg(help = "Body of the notification")]
+        body: Option<String>,
+
+        #[arg(
+            short,
+            long,
+            default_value = "",
+            help = "Name of the sending application"
+        )]
+        app_name: String,
+
+        #[arg(short, long, value_enum, default_value_t = Urgency::Normal, help = "Urgency level")]
+        urgency: Urgency,
+
+        #[arg(
+            short = 't',
+            long,
+            default_value_t = -1,
+            allow_negative_numbers = true,
+            help = "Timeout in milliseconds, 0 never expires, -1 uses the server default"
+        )]
+        expire_time: i32,
+
+        #[arg(short, long, default_value = "", help = "Icon name or path")]
+        icon: String,
+
+        #[arg(
+            short,
+            long,
+            default_value_t = 0,
+            help = "ID of the notification to replace"
+        )]
+        replace_id: u32,
+
+        #[arg(
+            short = 'A',
+            long = "action",
+            value_name = "[NAME=]TEXT",
+            help = "Add an action and print the name of the invoked one to stdout"
+        )]
+        actions: Vec<String>,
+
+        #[arg(short, long, help = "Print the notification ID")]
+        print_id: bool,
+
+        #[arg(
+            short,
+            long,
+            help = "Wait until the notification is closed and print the reason"
+        )]
+        wait: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Urgency {
+    Low,
+    Normal,
+    Critical,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Service {
+    Client,
+    Collector,
+}
+
+#[derive(Subcommand)]
+enum WaitingAction {
+    #[command(about = "Show every waiting notification, new ones keep waiting")]
+    Show,
+    #[command(about = "Show only the oldest waiting notification")]
+    Pop {
+        #[arg(help = "ID of the waiting notification to show instead of the oldest")]
+        id: Option<u32>,
+    },
+}
+
+#[derive(Subcommand)]
+enum HistoryAction {
+    #[command(about = "List history entries, newest first")]
+    List {
+        #[arg(
+            long,
+            default_value_t = 0,
+            help = "How many of the newest entries to skip"
+        )]
+        offset: u32,
+        #[arg(long, default_value_t = 20, help = "How many entries to list")]
+        limit: u32,
+        #[arg(long, help = "Only list the entries of this app")]
+        app: Option<String>,
+    },
+    #[command(about = "Delete the whole history")]
+    Clear,
+}
+
+#[derive(Subcommand)]
+enum AdminAction {
+    #[command(about = "List the active notifications")]
+    List,
+    #[command(about = "Close a notification on every client")]
+    Close {
+        #[arg(help = "ID of the notification")]
+        id: u32,
+    },
+    #[command(about = "Send every active notification to the clients again")]
+    Rebroadcast,
+}
+
+#[derive(Subcommand)]
+enum TriggerAction {
+    #[command(about = "List the triggers")]
+    List,
+    #[command(about = "Add a trigger showing a critical copy of matching notifications")]
+    Add {
+        #[arg(help = "Words that all have to be in the notification, or a saved search")]
+        query: String,
+    },
+    #[command(about = "Remove a trigger")]
+    Remove {
+        #[arg(help = "Query of the trigger")]
+        query: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -74,10 +363,111 @@ enum SwitchAction {
 async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
-    let event = match cli.command {
-        NotifyCommand::Waiting => notify::Event::Waiting,
+    if cli.dump_config_schema {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&config::Config::schema())?
+        );
+        return Ok(());
+    }
+
+    let Some(command) = cli.command else {
+        Cli::command()
+            .error(ErrorKind::MissingSubcommand, "A subcommand is required")
+            .exit();
+    };
+
+    let config = config::Config::load(cli.config.as_deref()).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        config::Config::default()
+    });
+    i18n::init(config.locale.as_deref(), &config.translations);
+
+    let event = match command {
+        NotifyCommand::Completions { shell } => {
+            clap_complete::generate(
+                shell,
+                &mut Cli::command(),
+                "moxnotifyctl",
+                &mut std::io::stdout(),
+            );
+            return Ok(());
+        }
+        NotifyCommand::Audit { id, uuid } => {
+            return audit::show(&config, id, uuid.as_deref(), cli.json).await;
+        }
+        NotifyCommand::Doctor => return doctor::run(&config, cli.json).await,
+        NotifyCommand::Admin { address, action } => {
+            let action = match action {
+                AdminAction::List => admin::Action::List,
+                AdminAction::Close { id } => admin::Action::Close(id),
+                AdminAction::Rebroadcast => admin::Action::Rebroadcast,
+            };
+            return admin::run(&config, address.as_deref(), action, cli.json).await;
+        }
+        NotifyCommand::Trigger { action } => {
+            let action = match action {
+                TriggerAction::List => admin::Action::Triggers,
+                TriggerAction::Add { query } => admin::Action::AddTrigger(query),
+                TriggerAction::Remove { query } => admin::Action::RemoveTrigger(query),
+            };
+            return admin::run(&config, None, action, cli.json).await;
+        }
+        NotifyCommand::Preview {
+            urgency,
+            with_actions,
+            with_image,
+            with_progress,
+            cycle,
+            interval,
+        } => {
+            let preview = if cycle {
+                notify::Preview::Cycle(Duration::from_secs(interval))
+            } else {
+                notify::Preview::One {
+                    urgency: urgency as u8,
+                    actions: with_actions,
+                    image: with_image,
+                    progress: with_progress,
+                }
+            };
+            return notify::preview(preview, cli.json).await.map_err(Into::into);
+        }
+        NotifyCommand::Style { file, watch, .. } => {
+            return notify::style(file.as_deref(), watch, cli.json)
+                .await
+                .map_err(Into::into);
+        }
+        NotifyCommand::LogLevel {
+            service,
+            module,
+            level,
+        } => {
+            let destination = match service {
+                Service::Client => "pl.mox.Notify",
+                Service::Collector => "org.freedesktop.Notifications",
+            };
+            return notify::log_level(destination, module.zip(level), cli.json)
+                .await
+                .map_err(Into::into);
+        }
+        NotifyCommand::Waiting { action } => match action {
+            None => notify::Event::Waiting,
+            Some(WaitingAction::Show) => notify::Event::ShowWaiting,
+            Some(WaitingAction::Pop { id }) => notify::Event::PopWaiting(id),
+        },
         NotifyCommand::Focus => notify::Event::Focus,
-        NotifyCommand::List => notify::Event::List,
+        NotifyCommand::Digest => notify::Event::Digest,
+        NotifyCommand::Reload => notify::Event::Reload,
+        NotifyCommand::History { action } => match action {
+            HistoryAction::List { offset, limit, app } => notify::Event::History {
+                offset,
+                limit,
+                app_name: app,
+            },
+            HistoryAction::Clear => notify::Event::ClearHistory,
+        },
+        NotifyCommand::List { full } => notify::Event::List { full },
         NotifyCommand::Dismiss { all, notification } => {
             if all {
                 notify::Event::DismissAll
@@ -98,6 +488,42 @@ async fn main() -> anyhow::Result<()> {
             SwitchAction::Toggle => notify::Event::ToggleInhibit,
             SwitchAction::State => notify::Event::InhibitState,
         },
+        NotifyCommand::HighContrast { action } => match action {
+            SwitchAction::On => notify::Event::HighContrast(true),
+            SwitchAction::Off => notify::Event::HighContrast(false),
+            SwitchAction::Toggle => notify::Event::ToggleHighContrast,
+            SwitchAction::State => notify::Event::HighContrastState,
+        },
+        NotifyCommand::Send {
+            summary,
+            body,
+            app_name,
+            urgency,
+            expire_time,
+            icon,
+            replace_id,
+            actions,
+            print_id,
+            wait,
+        } => notify::Event::Send(Box::new(notify::Notification {
+            app_name,
+            replaces_id: replace_id,
+            app_icon: icon,
+            summary,
+            body: body.unwrap_or_default(),
+            actions: actions
+                .iter()
+                .enumerate()
+                .map(|(i, action)| match action.split_once('=') {
+                    Some((key, label)) => (key.to_string(), label.to_string()),
+                    None => (i.to_string(), action.to_string()),
+                })
+                .collect(),
+            urgency: urgency as u8,
+            expire_timeout: expire_time,
+            print_id,
+            wait,
+        })),
         NotifyCommand::Output { set, unset } => {
             if let Some(output) = set {
                 notify::Event::SetOutput(Some(output))
@@ -109,5 +535,5 @@ async fn main() -> anyhow::Result<()> {
         }
     };
 
-    notify::emit(event).await.map_err(Into::into)
+    notify::emit(event, cli.json).await.map_err(Into::into)
 }
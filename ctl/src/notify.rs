@@ -1,10 +1,20 @@
+use futures_lite::stream::StreamExt;
+use serde::Serialize;
+use std::collections::HashMap;
 use std::io;
 use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
 
 pub enum Event {
     Waiting,
+    ShowWaiting,
+    PopWaiting(Option<u32>),
     Focus,
-    List,
+    /// List the notifications on screen, with the pixels of inline images if `full`
+    List {
+        full: bool,
+    },
     DismissAll,
     DismissOne(u32),
     Mute,
@@ -16,6 +26,33 @@ pub enum Event {
     ToggleMute,
     MuteState,
     SetOutput(Option<String>),
+    Digest,
+    /// Load the client's config file again
+    Reload,
+    /// List `limit` history entries from `offset`, newest first, of `app_name` if set
+    History {
+        offset: u32,
+        limit: u32,
+        app_name: Option<String>,
+    },
+    ClearHistory,
+    HighContrast(bool),
+    ToggleHighContrast,
+    HighContrastState,
+    Send(Box<Notification>),
+}
+
+pub struct Notification {
+    pub app_name: String,
+    pub replaces_id: u32,
+    pub app_icon: String,
+    pub summary: String,
+    pub body: String,
+    pub actions: Vec<(String, String)>,
+    pub urgency: u8,
+    pub expire_timeout: i32,
+    pub print_id: bool,
+    pub wait: bool,
 }
 
 #[zbus::proxy(
@@ -23,11 +60,30 @@ pub enum Event {
     default_service = "org.freedesktop.Notifications",
     default_path = "/org/freedesktop/Notifications"
 )]
-trait Notifications {
+pub trait Notifications {
     #[allow(clippy::type_complexity)]
     async fn get_server_information(
         &self,
     ) -> zbus::fdo::Result<(Box<str>, Box<str>, Box<str>, Box<str>)>;
+
+    #[allow(clippy::too_many_arguments)]
+    async fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, zbus::zvariant::Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    #[zbus(signal)]
+    async fn action_invoked(&self, id: u32, action_key: String) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn notification_closed(&self, id: u32, reason: u32) -> zbus::Result<()>;
 }
 
 #[zbus::proxy(
@@ -35,13 +91,31 @@ trait Notifications {
     default_service = "pl.mox.Notify",
     default_path = "/pl/mox/Notify"
 )]
-trait Notify {
+pub trait Notify {
     async fn focus(&self) -> zbus::Result<()>;
 
-    async fn list(&self) -> zbus::Result<Vec<String>>;
+    async fn list(&self, full: bool) -> zbus::Result<Vec<String>>;
 
     async fn dismiss(&self, all: bool, id: u32) -> zbus::Result<()>;
 
+    async fn digest(&self) -> zbus::Result<()>;
+
+    async fn preview(
+        &self,
+        urgency: u8,
+        actions: bool,
+        image: bool,
+        progress: i32,
+    ) -> zbus::Result<()>;
+
+    async fn set_style_overlay(&self, css: &str) -> zbus::Result<()>;
+
+    async fn reload(&self) -> zbus::Result<()>;
+
+    async fn set_high_contrast(&self, enabled: bool) -> zbus::Result<()>;
+
+    async fn high_contrast(&self) -> zbus::Result<bool>;
+
     async fn mute(&self) -> zbus::Result<()>;
 
     async fn unmute(&self) -> zbus::Result<()>;
@@ -56,13 +130,199 @@ trait Notify {
 
     async fn waiting(&self) -> zbus::Result<u32>;
 
+    async fn show_waiting(&self) -> zbus::Result<()>;
+
+    async fn pop_waiting(&self, id: u32) -> zbus::Result<()>;
+
     async fn output(&self, all: bool, output: String) -> zbus::Result<()>;
+
+    async fn unlock_history(&self) -> zbus::Result<bool>;
+
+    async fn get_history(
+        &self,
+        offset: u32,
+        limit: u32,
+        app_name: &str,
+    ) -> zbus::Result<Vec<String>>;
+
+    async fn clear_history(&self) -> zbus::Result<u64>;
+}
+
+#[zbus::proxy(interface = "pl.mox.Notify.Log", default_path = "/pl/mox/Notify/Log")]
+pub trait Log {
+    async fn set_level(&self, module: &str, level: &str) -> zbus::Result<()>;
+
+    async fn levels(&self) -> zbus::Result<Vec<String>>;
 }
 
-pub async fn emit(event: Event) -> zbus::Result<()> {
+/// Set the level of a module in the service owning `destination`, or print every level
+pub async fn log_level(
+    destination: &str,
+    change: Option<(String, String)>,
+    json: bool,
+) -> zbus::Result<()> {
+    let conn = zbus::Connection::session().await?;
+    let log = LogProxy::builder(&conn)
+        .destination(destination.to_string())?
+        .build()
+        .await?;
+
+    match change {
+        Some((module, level)) => log.set_level(&module, &level).await?,
+        None if json => {
+            let levels: serde_json::Map<_, _> = log
+                .levels()
+                .await?
+                .iter()
+                .filter_map(|level| level.split_once('='))
+                .map(|(module, level)| (module.to_string(), level.into()))
+                .collect();
+            println!("{}", serde_json::Value::Object(levels));
+        }
+        None => {
+            let mut out = io::stdout().lock();
+            for level in log.levels().await? {
+                writeln!(out, "{level}")?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub enum Preview {
+    One {
+        urgency: u8,
+        actions: bool,
+        image: bool,
+        progress: Option<u8>,
+    },
+    /// Show every urgency in every state, each for this long
+    Cycle(Duration),
+}
+
+/// States shown for every urgency when cycling, as (actions, image, progress)
+const PREVIEW_STATES: [(bool, bool, Option<u8>); 5] = [
+    (false, false, None),
+    (true, false, None),
+    (false, true, None),
+    (false, false, Some(60)),
+    (true, true, Some(60)),
+];
+
+/// Have the client show a synthetic notification without going through the pipeline
+pub async fn preview(preview: Preview, json: bool) -> zbus::Result<()> {
+    let conn = zbus::Connection::session().await?;
+    let notify = NotifyProxy::new(&conn).await?;
+
+    match preview {
+        Preview::One {
+            urgency,
+            actions,
+            image,
+            progress,
+        } => {
+            notify
+                .preview(urgency, actions, image, progress.map_or(-1, i32::from))
+                .await
+        }
+        Preview::Cycle(interval) => {
+            let mut out = io::stdout().lock();
+            for (urgency, name) in ["low", "normal", "critical"].into_iter().enumerate() {
+                for (actions, image, progress) in PREVIEW_STATES {
+                    if json {
+                        let state = serde_json::json!({
+                            "urgency": name,
+                            "actions": actions,
+                            "image": image,
+                            "progress": progress,
+                        });
+                        writeln!(out, "{state}")?;
+                    } else {
+                        writeln!(
+                            out,
+                            "urgency: {name}, actions: {actions}, image: {image}, progress: {progress:?}"
+                        )?;
+                    }
+                    out.flush()?;
+
+                    notify
+                        .preview(
+                            urgency as u8,
+                            actions,
+                            image,
+                            progress.map_or(-1, i32::from),
+                        )
+                        .await?;
+                    tokio::time::sleep(interval).await;
+                }
+            }
+
+            Ok(())
+        }
+    }
+}
+
+/// How often a watched style file is checked for changes
+const STYLE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Layer the css in `file` over the client's configured styles, or drop the overlay
+/// without a file. When watching, the file is applied again every time it's modified
+pub async fn style(file: Option<&Path>, watch: bool, json: bool) -> zbus::Result<()> {
+    let conn = zbus::Connection::session().await?;
+    let notify = NotifyProxy::new(&conn).await?;
+
+    let Some(file) = file else {
+        return notify.set_style_overlay("").await;
+    };
+
+    let mut applied = None;
+    loop {
+        match std::fs::metadata(file).and_then(|metadata| metadata.modified()) {
+            Ok(modified) if applied == Some(modified) => {}
+            Ok(modified) => {
+                let css = std::fs::read_to_string(file)?;
+                notify.set_style_overlay(&css).await?;
+                applied = Some(modified);
+
+                if !watch {
+                    return Ok(());
+                }
+                if json {
+                    println!("{}", serde_json::json!({ "applied": file }));
+                } else {
+                    println!(
+                        "{}",
+                        i18n::tr!("ctl-style-applied", file = file.display().to_string())
+                    );
+                }
+            }
+            // Editors that save by renaming leave no file behind for a moment
+            Err(_) if applied.is_some() => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        tokio::time::sleep(STYLE_POLL_INTERVAL).await;
+    }
+}
+
+/// Ask the client to authenticate the user if history is locked
+pub async fn unlock_history() -> zbus::Result<bool> {
+    let conn = zbus::Connection::session().await?;
+    NotifyProxy::new(&conn).await?.unlock_history().await
+}
+
+/// Carry out `event`, printing what it queried as JSON with `json`
+pub async fn emit(event: Event, json: bool) -> zbus::Result<()> {
     let conn = zbus::Connection::session().await?;
 
     let notifications = NotificationsProxy::new(&conn).await?;
+
+    // Sending only relies on the freedesktop interface, so it works with any server
+    if let Event::Send(notification) = event {
+        return send(&notifications, *notification, json).await;
+    }
+
     let server_information = notifications.get_server_information().await?;
     assert!(
         !(*server_information.0 != *"moxnotify" && *server_information.1 != *"mox"),
@@ -70,6 +330,11 @@ pub async fn emit(event: Event) -> zbus::Result<()> {
     );
 
     let notify = NotifyProxy::new(&conn).await?;
+    if matches!(event, Event::History { .. } | Event::ClearHistory)
+        && !notify.unlock_history().await?
+    {
+        return Err(zbus::Error::Failure(i18n::tr!("ctl-history-locked")));
+    }
     let mut out = io::stdout().lock();
 
     match event {
@@ -79,17 +344,85 @@ pub async fn emit(event: Event) -> zbus::Result<()> {
                 .await?
         }
         Event::Focus => notify.focus().await?,
+        Event::Digest => notify.digest().await?,
+        Event::Reload => notify.reload().await?,
+        Event::Waiting if json => {
+            let waiting = notify.waiting().await?;
+            writeln!(out, "{}", serde_json::json!({ "waiting": waiting }))?;
+        }
         Event::Waiting => {
             writeln!(out, "{}", notify.waiting().await?)?;
         }
-        Event::List => {
-            let list = notify.list().await?;
-            for item in list {
+        Event::ShowWaiting => notify.show_waiting().await?,
+        Event::PopWaiting(id) => notify.pop_waiting(id.unwrap_or_default()).await?,
+        Event::List { full: true } if !json => {
+            for item in notify.list(true).await? {
                 writeln!(out, "{item}")?;
             }
         }
+        Event::List { full } => {
+            // Every item is a notification serialized by the client
+            let list: Vec<_> = notify
+                .list(full)
+                .await?
+                .iter()
+                .filter_map(|item| serde_json::from_str(item).ok())
+                .map(|item| ListItem::new(item, full))
+                .collect();
+            if json {
+                writeln!(out, "{}", serde_json::to_string(&list)?)?;
+            } else {
+                write!(out, "{}", table(&list))?;
+            }
+        }
         Event::DismissAll => notify.dismiss(true, 0).await?,
         Event::DismissOne(index) => notify.dismiss(false, index).await?,
+        Event::History {
+            offset,
+            limit,
+            app_name,
+        } => {
+            let entries = notify
+                .get_history(offset, limit, app_name.as_deref().unwrap_or_default())
+                .await?;
+            if json {
+                let entries: Vec<serde_json::Value> = entries
+                    .iter()
+                    .filter_map(|entry| serde_json::from_str(entry).ok())
+                    .collect();
+                writeln!(out, "{}", serde_json::Value::Array(entries))?;
+            } else {
+                for entry in entries {
+                    if let Ok(entry) = serde_json::from_str(&entry) {
+                        writeln!(out, "{}", history_entry(&entry))?;
+                    }
+                }
+            }
+        }
+        Event::ClearHistory if json => {
+            let count = notify.clear_history().await?;
+            writeln!(out, "{}", serde_json::json!({ "count": count }))?;
+        }
+        Event::ClearHistory => {
+            let count = notify.clear_history().await?;
+            writeln!(out, "{}", i18n::tr!("ctl-history-cleared", count = count))?;
+        }
+        Event::HighContrast(enabled) => notify.set_high_contrast(enabled).await?,
+        Event::ToggleHighContrast => {
+            let enabled = notify.high_contrast().await?;
+            notify.set_high_contrast(!enabled).await?;
+        }
+        Event::HighContrastState if json => {
+            let enabled = notify.high_contrast().await?;
+            writeln!(out, "{}", serde_json::json!({ "high_contrast": enabled }))?;
+        }
+        Event::HighContrastState => {
+            if notify.high_contrast().await? {
+                writeln!(out, "on")?;
+            } else {
+                writeln!(out, "off")?;
+            }
+        }
         Event::Unmute => notify.unmute().await?,
         Event::Mute => notify.mute().await?,
         Event::ToggleMute => {
@@ -99,6 +432,10 @@ pub async fn emit(event: Event) -> zbus::Result<()> {
                 notify.mute().await?;
             }
         }
+        Event::MuteState if json => {
+            let muted = notify.muted().await?;
+            writeln!(out, "{}", serde_json::json!({ "muted": muted }))?;
+        }
         Event::MuteState => {
             if notify.muted().await? {
                 writeln!(out, "muted")?;
@@ -115,6 +452,10 @@ pub async fn emit(event: Event) -> zbus::Result<()> {
                 notify.inhibit().await?;
             }
         }
+        Event::InhibitState if json => {
+            let inhibited = notify.inhibited().await?;
+            writeln!(out, "{}", serde_json::json!({ "inhibited": inhibited }))?;
+        }
         Event::InhibitState => {
             if notify.inhibited().await? {
                 writeln!(out, "inhibited")?;
@@ -122,6 +463,251 @@ pub async fn emit(event: Event) -> zbus::Result<()> {
                 writeln!(out, "uninhibited")?;
             }
         }
+        Event::Send(_) => unreachable!(),
+    }
+
+    Ok(())
+}
+
+/// One line of history: when, from which app and the summary. Fields of stored
+/// entries are arrays of values
+fn history_entry(entry: &serde_json::Value) -> String {
+    let field = |name: &str| entry[name][0].as_str().unwrap_or_default().to_string();
+    let timestamp = chrono::DateTime::parse_from_rfc3339(&field("timestamp"))
+        .map(|timestamp| {
+            timestamp
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_default();
+
+    format!("{timestamp}  {}  {}", field("app_name"), field("summary"))
+}
+
+/// `millis` rounded down to the largest unit, like `3m`
+fn short_duration(millis: i64) -> String {
+    let secs = millis / 1000;
+    match secs {
+        ..60 => format!("{secs}s"),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+/// A notification as `list --json` prints it. Fields are only ever added, while the
+/// notifications the client and control plane hand out follow the protocol
+#[derive(Serialize)]
+pub struct ListItem {
+    pub id: u32,
+    pub uuid: String,
+    pub app_name: String,
+    pub summary: String,
+    pub body: String,
+    pub category: Option<String>,
+    /// `low`, `normal` or `critical`
+    pub urgency: &'static str,
+    pub actions: Vec<ListAction>,
+    /// Milliseconds, 0 never expires and -1 leaves it to the client
+    pub timeout: i64,
+    /// Milliseconds since it was received
+    pub age: i64,
+    /// Milliseconds until it expires, `None` unless it has a timeout of its own
+    pub remaining: Option<i64>,
+    /// Everything the client has of it, following the protocol. Only with `--full`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notification: Option<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+pub struct ListAction {
+    pub key: String,
+    pub label: String,
+}
+
+impl ListItem {
+    /// `notification` as serialized from the protocol, with `age` and `remaining`
+    /// added. It's kept whole if `full`
+    pub fn new(notification: serde_json::Value, full: bool) -> Self {
+        let string = |value: &serde_json::Value| value.as_str().unwrap_or_default().to_string();
+        let hints = &notification["hints"];
+
+        Self {
+            id: notification["id"].as_u64().unwrap_or_default() as u32,
+            uuid: string(&notification["uuid"]),
+            app_name: string(&notification["app_name"]),
+            summary: string(&notification["summary"]),
+            body: string(&notification["body"]),
+            category: hints["category"].as_str().map(str::to_string),
+            urgency: match hints["urgency"].as_i64() {
+                Some(0) => "low",
+                Some(2) => "critical",
+                _ => "normal",
+            },
+            actions: notification["actions"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|action| ListAction {
+                    key: string(&action["key"]),
+                    label: string(&action["label"]),
+                })
+                .collect(),
+            timeout: notification["timeout"].as_i64().unwrap_or(-1),
+            age: notification["age"].as_i64().unwrap_or_default(),
+            remaining: notification["remaining"].as_i64(),
+            notification: full.then_some(notification),
+        }
+    }
+}
+
+/// One row per notification in `list`, with notifications from the same app with the
+/// same summary collapsed into the first one and counted
+pub fn table(list: &[ListItem]) -> String {
+    const SUMMARY_WIDTH: usize = 40;
+
+    let mut rows: Vec<(&ListItem, usize)> = Vec::new();
+    for item in list {
+        match rows
+            .iter_mut()
+            .find(|(row, _)| row.app_name == item.app_name && row.summary == item.summary)
+        {
+            Some((_, count)) => *count += 1,
+            None => rows.push((item, 1)),
+        }
+    }
+
+    let mut table = format!(
+        "{:>6}  {:<16}  {:<16}  {:<SUMMARY_WIDTH$}  {:>5}  {:>9}\n",
+        "ID", "APP", "CATEGORY", "SUMMARY", "AGE", "REMAINING"
+    );
+    for (item, count) in rows {
+        let mut summary = item.summary.replace('\n', " ");
+        let suffix = if count > 1 {
+            format!(" (×{count})")
+        } else {
+            String::new()
+        };
+        let width = SUMMARY_WIDTH - suffix.chars().count();
+        if summary.chars().count() > width {
+            summary = summary.chars().take(width - 1).collect();
+            summary.push('…');
+        }
+        summary.push_str(&suffix);
+
+        let age = short_duration(item.age);
+        let remaining = match (item.remaining, item.timeout) {
+            (Some(remaining), _) => short_duration(remaining),
+            (None, 0) => "never".to_string(),
+            (None, _) => "default".to_string(),
+        };
+        table.push_str(&format!(
+            "{:>6}  {:<16.16}  {:<16.16}  {:<SUMMARY_WIDTH$}  {:>5}  {:>9}\n",
+            item.id,
+            item.app_name,
+            item.category.as_deref().unwrap_or("-"),
+            summary,
+            age,
+            remaining,
+        ));
+    }
+
+    table
+}
+
+/// Send `notification`, with `json` its ID, invoked action and close reason are
+/// printed as one object per line
+async fn send(
+    notifications: &NotificationsProxy<'_>,
+    notification: Notification,
+    json: bool,
+) -> zbus::Result<()> {
+    let mut out = io::stdout().lock();
+
+    // Subscribe before sending so signals for short-lived notifications aren't missed
+    let mut action_invoked = notifications.receive_action_invoked().await?;
+    let mut notification_closed = notifications.receive_notification_closed().await?;
+
+    let actions = notification
+        .actions
+        .iter()
+        .flat_map(|(key, label)| [key.as_str(), label.as_str()])
+        .collect::<Vec<_>>();
+
+    let mut hints = HashMap::new();
+    hints.insert("urgency", zbus::zvariant::Value::U8(notification.urgency));
+
+    let id = notifications
+        .notify(
+            &notification.app_name,
+            notification.replaces_id,
+            &notification.app_icon,
+            &notification.summary,
+            &notification.body,
+            &actions,
+            hints,
+            notification.expire_timeout,
+        )
+        .await?;
+
+    if notification.print_id {
+        if json {
+            writeln!(out, "{}", serde_json::json!({ "id": id }))?;
+        } else {
+            writeln!(out, "{id}")?;
+        }
+        out.flush()?;
+    }
+
+    // Like notify-send, block while actions are pending even without --wait
+    if !notification.wait && notification.actions.is_empty() {
+        return Ok(());
+    }
+
+    loop {
+        tokio::select! {
+            Some(signal) = action_invoked.next() => {
+                let args = signal.args()?;
+                if *args.id() != id {
+                    continue;
+                }
+
+                if json {
+                    writeln!(out, "{}", serde_json::json!({ "id": id, "action": args.action_key() }))?;
+                } else {
+                    writeln!(out, "{}", args.action_key())?;
+                }
+                out.flush()?;
+
+                if !notification.wait {
+                    break;
+                }
+            }
+            Some(signal) = notification_closed.next() => {
+                let args = signal.args()?;
+                if *args.id() != id {
+                    continue;
+                }
+
+                if notification.wait {
+                    let reason = match *args.reason() {
+                        1 => "expired",
+                        2 => "dismissed",
+                        3 => "closed",
+                        _ => "undefined",
+                    };
+                    if json {
+                        writeln!(out, "{}", serde_json::json!({ "id": id, "closed": reason }))?;
+                    } else {
+                        writeln!(out, "{reason}")?;
+                    }
+                }
+
+                break;
+            }
+            else => break,
+        }
     }
 
     Ok(())
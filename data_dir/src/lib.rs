@@ -0,0 +1,102 @@
+//! Where moxnotify keeps its data, and the lock services take before writing to
+//! the history index in it
+
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// `moxnotify` in `$XDG_DATA_HOME`, or in `~/.local/share` when that's unset
+pub fn path() -> Option<PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .filter(|data_home| !data_home.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .map(|data_home| data_home.join("moxnotify"))
+}
+
+/// Exclusive advisory lock on a directory, released when dropped or when the
+/// process holding it exits
+pub struct Lock {
+    _file: File,
+}
+
+/// `<dir>.lock` next to `dir` rather than in it, so the lock stays put while the
+/// directory is moved aside and replaced, like a migration of the index does
+fn lock_path(dir: &Path) -> PathBuf {
+    let mut path = OsString::from(dir.as_os_str());
+    path.push(".lock");
+    PathBuf::from(path)
+}
+
+fn open(dir: &Path) -> io::Result<File> {
+    let path = lock_path(dir);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+}
+
+/// Lock `dir` so no other process writes to it, failing right away with
+/// [`io::ErrorKind::WouldBlock`] while another one holds the lock
+pub fn lock(dir: &Path) -> io::Result<Lock> {
+    let file = open(dir)?;
+    file.try_lock()?;
+
+    Ok(Lock { _file: file })
+}
+
+/// Like [`lock`], but waits for another process holding the lock to release it
+pub fn lock_blocking(dir: &Path) -> io::Result<Lock> {
+    let file = open(dir)?;
+    file.lock()?;
+
+    Ok(Lock { _file: file })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_is_exclusive_until_dropped() {
+        let dir = std::env::temp_dir().join(format!("moxnotify-lock-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let held = lock(&dir).unwrap();
+        assert_eq!(
+            lock(&dir).err().map(|e| e.kind()),
+            Some(io::ErrorKind::WouldBlock)
+        );
+
+        drop(held);
+        assert!(lock(&dir).is_ok());
+
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::remove_file(lock_path(&dir)).ok();
+    }
+
+    #[test]
+    fn lock_outlives_replacing_the_directory() {
+        let dir = std::env::temp_dir().join(format!("moxnotify-swap-{}", std::process::id()));
+        let old = dir.with_extension("old");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let _held = lock(&dir).unwrap();
+        std::fs::rename(&dir, &old).unwrap();
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::remove_dir_all(&old).unwrap();
+        assert_eq!(
+            lock(&dir).err().map(|e| e.kind()),
+            Some(io::ErrorKind::WouldBlock)
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::remove_file(lock_path(&dir)).ok();
+    }
+}
@@ -0,0 +1,215 @@
+pub mod moxnotify {
+    pub mod types {
+        tonic::include_proto!("moxnotify.types");
+    }
+    pub mod collector {
+        tonic::include_proto!("moxnotify.collector");
+    }
+}
+
+mod queue;
+
+use config::types::Compression;
+use moxnotify::collector::CollectorMessage;
+use moxnotify::collector::collector_service_client::CollectorServiceClient;
+use moxnotify::collector::{collector_message, collector_response};
+use moxnotify::types::{
+    ActionInvoked, CloseNotification, Hello, NewNotification, NotificationClosed,
+};
+use queue::Queue;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::StreamExt;
+use tonic::codec::CompressionEncoding;
+
+type NotificationId = u32;
+
+/// Something a collector picked up for the control plane
+#[derive(Debug)]
+pub enum Event {
+    Notify(Box<NewNotification>),
+    CloseNotification(NotificationId),
+}
+
+/// Something the control plane sends back to the collector
+#[derive(Clone)]
+pub enum EmitEvent {
+    ActionInvoked(ActionInvoked),
+    NotificationClosed(NotificationClosed),
+}
+
+/// How a collector reaches the control plane
+pub struct Target {
+    pub address: String,
+    pub compression: Compression,
+    /// How many messages are kept while the control plane is unreachable
+    pub offline_buffer: usize,
+}
+
+fn message(event: Event) -> CollectorMessage {
+    match event {
+        Event::Notify(data) => {
+            log::info!(
+                "Collected notification: id={}, app_name='{}', summary={}",
+                data.id,
+                data.app_name,
+                logging::content(&data.summary),
+            );
+
+            CollectorMessage {
+                message: Some(collector_message::Message::NewNotification(*data)),
+            }
+        }
+        Event::CloseNotification(id) => {
+            log::info!("Collected close notification request: id={}", id);
+
+            CollectorMessage {
+                message: Some(collector_message::Message::CloseNotification(
+                    CloseNotification { id },
+                )),
+            }
+        }
+    }
+}
+
+/// Send collected messages to the control plane, starting with the queued ones, and
+/// forward its responses until the connection breaks. `Ok` once nothing is collected
+/// anymore
+async fn forward(
+    target: &Target,
+    event_receiver: &mut mpsc::Receiver<Event>,
+    emit_sender: &broadcast::Sender<EmitEvent>,
+    queue: &Arc<Queue>,
+    backoff: &mut supervisor::Backoff,
+) -> anyhow::Result<()> {
+    let mut client = CollectorServiceClient::connect(target.address.clone())
+        .await?
+        .accept_compressed(CompressionEncoding::Zstd);
+    if target.compression == Compression::Zstd {
+        client = client.send_compressed(CompressionEncoding::Zstd);
+    }
+
+    log::info!("Connected to control plane at {}", target.address);
+
+    let hello = CollectorMessage {
+        message: Some(collector_message::Message::Hello(Hello {
+            protocol_version: config::protocol::VERSION,
+            capabilities: Vec::new(),
+            client_id: String::new(),
+        })),
+    };
+
+    let pending = queue.pending();
+    if pending > 0 {
+        log::info!("Sending {pending} messages collected while disconnected");
+    }
+    let message_stream = tokio_stream::once(hello).chain(queue.connect());
+
+    let mut response_stream = client.notifications(message_stream).await?.into_inner();
+    backoff.reset();
+
+    loop {
+        tokio::select! {
+            event = event_receiver.recv() => {
+                let Some(event) = event else {
+                    log::info!("Event receiver closed");
+                    return Ok(());
+                };
+
+                queue.push(message(event));
+            }
+
+            response = response_stream.next() => {
+                match response {
+                    Some(Ok(response)) => {
+                        if let Some(msg) = response.message {
+                            match msg {
+                                collector_response::Message::ActionInvoked(action) => {
+                                    log::info!(
+                                        "Received action invoked: id={}, action_key='{}'",
+                                        action.id,
+                                        action.action_key
+                                    );
+
+                                    if let Err(e) =
+                                        emit_sender.send(EmitEvent::ActionInvoked(action))
+                                    {
+                                        log::debug!(
+                                            "Failed to forward action invoked, nothing is listening: {}",
+                                            e
+                                        );
+                                    }
+                                }
+                                collector_response::Message::NotificationClosed(closed) => {
+                                    log::info!(
+                                        "Received notification closed: id={}, reason={:?}",
+                                        closed.id,
+                                        closed.reason()
+                                    );
+
+                                    if let Err(e) =
+                                        emit_sender.send(EmitEvent::NotificationClosed(closed))
+                                    {
+                                        log::debug!(
+                                            "Failed to forward notification closed, nothing is listening: {}",
+                                            e
+                                        );
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    Some(Err(e)) => {
+                        anyhow::bail!("Error receiving response from control plane: {e}");
+                    }
+                    None => anyhow::bail!("Response stream ended"),
+                }
+            }
+        }
+    }
+}
+
+/// Forward everything collected through `event_receiver` to the control plane and its
+/// responses to `emit_sender`, reconnecting with an exponential backoff whenever the
+/// connection breaks. Returns once nothing is collected anymore
+pub async fn run(
+    target: Target,
+    mut event_receiver: mpsc::Receiver<Event>,
+    emit_sender: broadcast::Sender<EmitEvent>,
+) -> anyhow::Result<()> {
+    let queue = Queue::new(target.offline_buffer);
+    let mut backoff = supervisor::Backoff::default();
+    loop {
+        match forward(
+            &target,
+            &mut event_receiver,
+            &emit_sender,
+            &queue,
+            &mut backoff,
+        )
+        .await
+        {
+            Ok(()) => return Ok(()),
+            Err(e) => log::error!("Disconnected from control plane: {e}"),
+        }
+        queue.disconnect();
+
+        let delay = backoff.jittered_delay();
+        log::info!("Reconnecting to control plane in {:?}", delay);
+        // Keep collecting meanwhile, the queue is sent once reconnected
+        let reconnect = tokio::time::sleep(delay);
+        tokio::pin!(reconnect);
+        loop {
+            tokio::select! {
+                _ = &mut reconnect => break,
+                event = event_receiver.recv() => {
+                    let Some(event) = event else {
+                        log::info!("Event receiver closed");
+                        return Ok(());
+                    };
+                    queue.push(message(event));
+                }
+            }
+        }
+    }
+}
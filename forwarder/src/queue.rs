@@ -0,0 +1,174 @@
+use crate::moxnotify::collector::CollectorMessage;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use tokio_stream::Stream;
+
+/// How many messages may wait for the control plane to take them while connected
+const CONNECTED_CAPACITY: usize = 128;
+
+/// Messages waiting for the control plane. The stream of a connection only takes them
+/// out once sending them, so nothing it didn't take is lost when the connection breaks
+pub struct Queue {
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    messages: VecDeque<CollectorMessage>,
+    /// How many are kept while disconnected, 0 keeps none
+    offline_capacity: usize,
+    connected: bool,
+    /// Bumped on every connect and disconnect, so streams of old connections end
+    generation: u64,
+    waker: Option<Waker>,
+}
+
+impl Queue {
+    pub fn new(offline_capacity: usize) -> Arc<Self> {
+        Arc::new(Self {
+            inner: Mutex::new(Inner {
+                messages: VecDeque::new(),
+                offline_capacity,
+                connected: false,
+                generation: 0,
+                waker: None,
+            }),
+        })
+    }
+
+    /// How many messages are waiting
+    pub fn pending(&self) -> usize {
+        self.inner.lock().unwrap().messages.len()
+    }
+
+    /// Queue `message`, dropping the oldest beyond the capacity
+    pub fn push(&self, message: CollectorMessage) {
+        let mut inner = self.inner.lock().unwrap();
+        let capacity = if inner.connected {
+            CONNECTED_CAPACITY.max(inner.offline_capacity)
+        } else {
+            inner.offline_capacity
+        };
+
+        if capacity == 0 {
+            return;
+        }
+
+        if inner.messages.len() >= capacity {
+            log::warn!(
+                "{} messages waiting for the control plane, dropping the oldest",
+                inner.messages.len()
+            );
+            inner.messages.pop_front();
+        }
+        inner.messages.push_back(message);
+
+        if let Some(waker) = inner.waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Stream of the messages for a new connection, starting with the queued ones
+    pub fn connect(self: &Arc<Self>) -> Outgoing {
+        let mut inner = self.inner.lock().unwrap();
+        inner.connected = true;
+        inner.generation += 1;
+
+        Outgoing {
+            queue: Arc::clone(self),
+            generation: inner.generation,
+        }
+    }
+
+    /// Keep what the broken connection didn't take, as far as the offline capacity allows
+    pub fn disconnect(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.connected = false;
+        inner.generation += 1;
+
+        let excess = inner.messages.len().saturating_sub(inner.offline_capacity);
+        if excess > 0 {
+            log::warn!("Dropping {excess} messages the control plane didn't receive");
+            inner.messages.drain(..excess);
+        }
+
+        if let Some(waker) = inner.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Messages of one connection, ends once it's disconnected
+pub struct Outgoing {
+    queue: Arc<Queue>,
+    generation: u64,
+}
+
+impl Stream for Outgoing {
+    type Item = CollectorMessage;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut inner = self.queue.inner.lock().unwrap();
+        if inner.generation != self.generation {
+            return Poll::Ready(None);
+        }
+
+        match inner.messages.pop_front() {
+            Some(message) => Poll::Ready(Some(message)),
+            None => {
+                inner.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moxnotify::collector::collector_message;
+    use crate::moxnotify::types::CloseNotification;
+    use tokio_stream::StreamExt;
+
+    fn close(id: u32) -> CollectorMessage {
+        CollectorMessage {
+            message: Some(collector_message::Message::CloseNotification(
+                CloseNotification { id },
+            )),
+        }
+    }
+
+    fn id(message: Option<CollectorMessage>) -> Option<u32> {
+        match message?.message? {
+            collector_message::Message::CloseNotification(close) => Some(close.id),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_offline_capacity() {
+        let queue = Queue::new(2);
+        (1..=3).for_each(|i| queue.push(close(i)));
+        assert_eq!(queue.pending(), 2);
+
+        let queue = Queue::new(0);
+        queue.push(close(1));
+        assert_eq!(queue.pending(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_untaken_messages_survive_disconnect() {
+        let queue = Queue::new(4);
+        let mut outgoing = queue.connect();
+        (1..=3).for_each(|i| queue.push(close(i)));
+
+        assert_eq!(id(outgoing.next().await), Some(1));
+        queue.disconnect();
+        assert_eq!(id(outgoing.next().await), None);
+
+        let mut outgoing = queue.connect();
+        assert_eq!(id(outgoing.next().await), Some(2));
+        assert_eq!(id(outgoing.next().await), Some(3));
+    }
+}
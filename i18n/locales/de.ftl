@@ -0,0 +1,57 @@
+## moxnotify
+
+notifications-more = { $count } weitere
+notifications-waiting = { $count } ausstehend
+apps-muted = Stummgeschaltet: { $apps }
+palette-dismiss-all = Alle verwerfen
+palette-toggle-dnd = Nicht stören umschalten
+palette-toggle-sound = Ton umschalten
+palette-high-contrast = Hohen Kontrast umschalten
+palette-show-waiting = Ausstehende Benachrichtigungen anzeigen
+palette-invoke = { $action } ausführen
+palette-history = Verlauf öffnen
+palette-mute-app = { $app } stummschalten
+palette-inspector = Inspektor umschalten
+copy-code = { $code } kopieren
+time-just-now = gerade eben
+time-minutes-ago = vor { $count } Min.
+time-hours-ago = vor { $count } Std.
+time-days-ago = vor { $count ->
+    [one] { $count } Tag
+   *[other] { $count } Tagen
+}
+
+## moxnotify-tui
+
+tui-help = j/k bewegen  gg/G erste/letzte  enter/1-9 Aktion  x schließen  X alle schließen  esc Fokus lösen  q beenden
+tui-connecting = Verbinde mit dem Scheduler...
+tui-status = moxnotify: { $count ->
+    [one] { $count } Benachrichtigung
+   *[other] { $count } Benachrichtigungen
+}
+tui-more-before = ↑ { $count } weitere
+tui-more-after = ↓ { $count } weitere
+
+## moxnotifyctl
+
+ctl-audit-empty = Keine Audit-Einträge für Benachrichtigung { $id }
+ctl-audit-disabled = Keine Audit-Einträge für Benachrichtigung { $id }, das Audit-Log ist deaktiviert
+ctl-history-locked = Der Verlauf ist gesperrt, die Authentifizierung ist fehlgeschlagen
+ctl-history-cleared = { $count ->
+    [one] { $count } Verlaufseintrag gelöscht
+   *[other] { $count } Verlaufseinträge gelöscht
+}
+ctl-style-applied = { $file } angewendet
+ctl-doctor-failed = { $count ->
+    [one] { $count } Prüfung fehlgeschlagen
+   *[other] { $count } Prüfungen fehlgeschlagen
+}
+ctl-admin-closed = Benachrichtigung { $id } geschlossen
+ctl-admin-not-active = Benachrichtigung { $id } ist nicht aktiv
+ctl-admin-rebroadcast = { $count ->
+    [one] { $count } Benachrichtigung erneut an die Clients gesendet
+   *[other] { $count } Benachrichtigungen erneut an die Clients gesendet
+}
+ctl-trigger-added = Auslöser für "{ $query }" hinzugefügt
+ctl-trigger-removed = Auslöser für "{ $query }" entfernt
+ctl-trigger-not-found = Kein Auslöser für "{ $query }"
@@ -0,0 +1,58 @@
+## moxnotify
+
+# Counters above and below the visible notifications
+notifications-more = { $count } more
+# Badge counting the notifications waiting while inhibited
+notifications-waiting = { $count } waiting
+# Badge listing the apps muted from the client
+apps-muted = Muted: { $apps }
+# Entries of the command palette
+palette-dismiss-all = Dismiss all
+palette-toggle-dnd = Toggle do not disturb
+palette-toggle-sound = Toggle sound
+palette-high-contrast = Toggle high contrast
+palette-show-waiting = Show waiting notifications
+palette-invoke = Invoke { $action }
+palette-history = Open history
+palette-mute-app = Mute { $app }
+palette-inspector = Toggle inspector
+copy-code = Copy { $code }
+time-just-now = just now
+time-minutes-ago = { $count } min ago
+time-hours-ago = { $count } h ago
+time-days-ago = { $count } d ago
+
+## moxnotify-tui
+
+tui-help = j/k move  gg/G first/last  enter/1-9 action  x dismiss  X dismiss all  esc unfocus  q quit
+tui-connecting = Connecting to scheduler...
+tui-status = moxnotify: { $count ->
+    [one] { $count } notification
+   *[other] { $count } notifications
+}
+tui-more-before = ↑ { $count } more
+tui-more-after = ↓ { $count } more
+
+## moxnotifyctl
+
+ctl-audit-empty = No audit entries for notification { $id }
+ctl-audit-disabled = No audit entries for notification { $id }, audit log is disabled
+ctl-history-locked = History is locked, authentication failed
+ctl-history-cleared = { $count ->
+    [one] Deleted { $count } history entry
+   *[other] Deleted { $count } history entries
+}
+ctl-style-applied = Applied { $file }
+ctl-doctor-failed = { $count ->
+    [one] { $count } check failed
+   *[other] { $count } checks failed
+}
+ctl-admin-closed = Closed notification { $id }
+ctl-admin-not-active = Notification { $id } isn't active
+ctl-admin-rebroadcast = { $count ->
+    [one] Sent { $count } notification to clients again
+   *[other] Sent { $count } notifications to clients again
+}
+ctl-trigger-added = Added trigger for "{ $query }"
+ctl-trigger-removed = Removed trigger for "{ $query }"
+ctl-trigger-not-found = No trigger for "{ $query }"
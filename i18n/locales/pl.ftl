@@ -0,0 +1,61 @@
+## moxnotify
+
+notifications-more = jeszcze { $count }
+notifications-waiting = oczekujące: { $count }
+apps-muted = Wyciszone: { $apps }
+palette-dismiss-all = Odrzuć wszystkie
+palette-toggle-dnd = Przełącz tryb nie przeszkadzać
+palette-toggle-sound = Przełącz dźwięk
+palette-high-contrast = Przełącz wysoki kontrast
+palette-show-waiting = Pokaż oczekujące powiadomienia
+palette-invoke = Wywołaj { $action }
+palette-history = Otwórz historię
+palette-mute-app = Wycisz { $app }
+palette-inspector = Przełącz inspektor
+copy-code = Kopiuj { $code }
+time-just-now = przed chwilą
+time-minutes-ago = { $count } min temu
+time-hours-ago = { $count } godz. temu
+time-days-ago = { $count ->
+    [one] { $count } dzień temu
+   *[other] { $count } dni temu
+}
+
+## moxnotify-tui
+
+tui-help = j/k ruch  gg/G pierwsze/ostatnie  enter/1-9 akcja  x odrzuć  X odrzuć wszystkie  esc odznacz  q wyjdź
+tui-connecting = Łączenie z harmonogramem...
+tui-status = moxnotify: { $count ->
+    [one] { $count } powiadomienie
+    [few] { $count } powiadomienia
+   *[many] { $count } powiadomień
+}
+tui-more-before = ↑ jeszcze { $count }
+tui-more-after = ↓ jeszcze { $count }
+
+## moxnotifyctl
+
+ctl-audit-empty = Brak wpisów audytu dla powiadomienia { $id }
+ctl-audit-disabled = Brak wpisów audytu dla powiadomienia { $id }, dziennik audytu jest wyłączony
+ctl-history-locked = Historia jest zablokowana, uwierzytelnianie nie powiodło się
+ctl-history-cleared = { $count ->
+    [one] Usunięto { $count } wpis historii
+    [few] Usunięto { $count } wpisy historii
+   *[many] Usunięto { $count } wpisów historii
+}
+ctl-style-applied = Zastosowano { $file }
+ctl-doctor-failed = { $count ->
+    [one] { $count } test nie powiódł się
+    [few] { $count } testy nie powiodły się
+   *[many] { $count } testów nie powiodło się
+}
+ctl-admin-closed = Zamknięto powiadomienie { $id }
+ctl-admin-not-active = Powiadomienie { $id } nie jest aktywne
+ctl-admin-rebroadcast = { $count ->
+    [one] Ponownie wysłano { $count } powiadomienie do klientów
+    [few] Ponownie wysłano { $count } powiadomienia do klientów
+   *[many] Ponownie wysłano { $count } powiadomień do klientów
+}
+ctl-trigger-added = Dodano wyzwalacz dla "{ $query }"
+ctl-trigger-removed = Usunięto wyzwalacz dla "{ $query }"
+ctl-trigger-not-found = Brak wyzwalacza dla "{ $query }"
@@ -0,0 +1,232 @@
+//! Built-in UI strings in the user's language, translated with fluent. Shipped
+//! translations can be reworded and missing languages added from the config
+
+use fluent_bundle::FluentResource;
+use fluent_bundle::concurrent::FluentBundle;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use unic_langid::LanguageIdentifier;
+
+pub use fluent_bundle::FluentArgs;
+
+/// Translations shipped with moxnotify, by language
+const BUNDLED: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+    ("pl", include_str!("../locales/pl.ftl")),
+];
+
+/// Language every message is translated to
+const FALLBACK: &str = "en";
+
+static LOCALIZER: OnceLock<Localizer> = OnceLock::new();
+
+/// Language of a locale like `pl_PL.UTF-8`, `None` for the C locale
+fn language(locale: &str) -> Option<LanguageIdentifier> {
+    let locale = locale.split(['.', '@']).next()?;
+    if locale.is_empty() || locale == "C" || locale == "POSIX" {
+        return None;
+    }
+
+    locale.replace('_', "-").parse().ok()
+}
+
+/// Parse `source`, keeping the messages that are valid
+fn resource(source: String, origin: &str) -> FluentResource {
+    FluentResource::try_new(source).unwrap_or_else(|(resource, errors)| {
+        errors
+            .iter()
+            .for_each(|e| log::warn!("Invalid translation in {origin}: {e:?}"));
+        resource
+    })
+}
+
+fn bundle(
+    language: LanguageIdentifier,
+    overrides: &HashMap<String, HashMap<String, String>>,
+) -> FluentBundle<FluentResource> {
+    let code = language.language.as_str().to_string();
+    let mut bundle = FluentBundle::new_concurrent(vec![language]);
+    // Directional isolation marks render as boxes in fonts without them
+    bundle.set_use_isolating(false);
+
+    if let Some((_, source)) = BUNDLED.iter().find(|(bundled, _)| *bundled == code) {
+        bundle.add_resource_overriding(resource(source.to_string(), &code));
+    }
+
+    overrides
+        .get(&code)
+        .into_iter()
+        .flatten()
+        .for_each(|(id, pattern)| {
+            // Continuation lines of a pattern have to be indented
+            let source = format!("{id} = {}\n", pattern.replace('\n', "\n    "));
+            bundle.add_resource_overriding(resource(source, &format!("translations.{code}")));
+        });
+
+    bundle
+}
+
+/// Messages of one language, falling back to English ones it has no translation of
+pub struct Localizer {
+    bundles: Vec<FluentBundle<FluentResource>>,
+}
+
+impl Localizer {
+    /// Messages in the language of `locale`, with the ones in `overrides` under the
+    /// language's code replacing the shipped translations
+    #[must_use]
+    pub fn new(locale: Option<&str>, overrides: &HashMap<String, HashMap<String, String>>) -> Self {
+        let mut bundles = Vec::new();
+        if let Some(language) = locale
+            .and_then(language)
+            .filter(|language| language.language.as_str() != FALLBACK)
+        {
+            bundles.push(bundle(language, overrides));
+        }
+        if let Ok(fallback) = FALLBACK.parse() {
+            bundles.push(bundle(fallback, overrides));
+        }
+
+        Self { bundles }
+    }
+
+    /// Message `id` with `args` filled in, or `id` itself if no language has it
+    #[must_use]
+    pub fn format(&self, id: &str, args: Option<&FluentArgs>) -> String {
+        self.bundles
+            .iter()
+            .find_map(|bundle| {
+                let pattern = bundle.get_message(id)?.value()?;
+                let mut errors = Vec::new();
+                let text = bundle.format_pattern(pattern, args, &mut errors);
+                errors
+                    .iter()
+                    .for_each(|e| log::warn!("Failed to format message '{id}': {e}"));
+
+                Some(text.into_owned())
+            })
+            .unwrap_or_else(|| {
+                log::warn!("No translation of message '{id}'");
+                id.to_string()
+            })
+    }
+}
+
+/// Translate messages to `locale`, or the one of `LC_ALL`, `LC_MESSAGES` or `LANG`
+/// when unset. Messages formatted before this is called are English
+pub fn init(locale: Option<&str>, overrides: &HashMap<String, HashMap<String, String>>) {
+    let locale = locale.map(str::to_string).or_else(|| {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|locale| !locale.is_empty())
+    });
+
+    if LOCALIZER
+        .set(Localizer::new(locale.as_deref(), overrides))
+        .is_err()
+    {
+        log::warn!("Translations were already set up, keeping the previous language");
+    }
+}
+
+/// Message `id` in the language picked by [`init`], see [`tr!`]
+#[must_use]
+pub fn translate(id: &str, args: Option<&FluentArgs>) -> String {
+    LOCALIZER
+        .get_or_init(|| Localizer::new(None, &HashMap::new()))
+        .format(id, args)
+}
+
+/// Translate a message, e.g. `tr!("notifications-more", count = 3)`
+#[macro_export]
+macro_rules! tr {
+    ($id:expr) => {
+        $crate::translate($id, None)
+    };
+    ($id:expr, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = $crate::FluentArgs::new();
+        $(args.set(stringify!($name), $value);)+
+        $crate::translate($id, Some(&args))
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(count: u32) -> FluentArgs<'static> {
+        let mut args = FluentArgs::new();
+        args.set("count", count);
+        args
+    }
+
+    #[test]
+    fn bundled_translations_are_complete() {
+        let ids: Vec<_> = BUNDLED[0]
+            .1
+            .lines()
+            .filter(|line| line.starts_with(char::is_alphabetic))
+            .filter_map(|line| line.split_once(" ="))
+            .map(|(id, _)| id)
+            .collect();
+
+        BUNDLED.iter().for_each(|(code, source)| {
+            assert!(
+                FluentResource::try_new(source.to_string()).is_ok(),
+                "{code} has invalid messages"
+            );
+            let localizer = Localizer::new(Some(code), &HashMap::new());
+            ids.iter().for_each(|id| {
+                assert!(localizer.bundles[0].has_message(id), "{code} misses {id}");
+            });
+        });
+    }
+
+    #[test]
+    fn plurals_follow_the_language() {
+        let localizer = Localizer::new(Some("pl_PL.UTF-8"), &HashMap::new());
+        assert_eq!(
+            localizer.format("tui-status", Some(&count(3))),
+            "moxnotify: 3 powiadomienia"
+        );
+        assert_eq!(
+            localizer.format("tui-status", Some(&count(5))),
+            "moxnotify: 5 powiadomień"
+        );
+
+        let localizer = Localizer::new(Some("C"), &HashMap::new());
+        assert_eq!(
+            localizer.format("tui-status", Some(&count(1))),
+            "moxnotify: 1 notification"
+        );
+    }
+
+    #[test]
+    fn overrides_replace_and_add_translations() {
+        let overrides = HashMap::from([
+            (
+                "en".to_string(),
+                HashMap::from([("notifications-more".to_string(), "+{ $count }".to_string())]),
+            ),
+            (
+                "fr".to_string(),
+                HashMap::from([("time-just-now".to_string(), "à l'instant".to_string())]),
+            ),
+        ]);
+
+        let localizer = Localizer::new(Some("en_US"), &overrides);
+        assert_eq!(
+            localizer.format("notifications-more", Some(&count(3))),
+            "+3"
+        );
+
+        let localizer = Localizer::new(Some("fr_FR"), &overrides);
+        assert_eq!(localizer.format("time-just-now", None), "à l'instant");
+        assert_eq!(
+            localizer.format("time-minutes-ago", Some(&count(2))),
+            "2 min ago"
+        );
+    }
+}
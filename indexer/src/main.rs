@@ -4,52 +4,30 @@ pub mod moxnotify {
     }
 }
 
+mod migrations;
+
 use clap::Parser;
-use moxnotify::types::NewNotification;
+use config::error;
+use config::keys::Keys;
+use moxnotify::types::{NewNotification, image};
 use redis::AsyncTypedCommands;
-use redis::streams::StreamReadOptions;
-use std::path::{Path, PathBuf};
+use redis::streams::{StreamAutoClaimOptions, StreamReadOptions};
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
-use tantivy::directory::MmapDirectory;
-use tantivy::{DateTime, Index, IndexWriter, schema::*};
-
-fn path() -> PathBuf {
-    let path = std::env::var("XDG_DATA_HOME")
-        .map(|data_home| PathBuf::from(data_home).join("moxnotify"))
-        .or_else(|_| {
-            std::env::var("HOME").map(|home| PathBuf::from(home).join(".local/share/moxnotify"))
-        })
-        .unwrap_or_else(|_| PathBuf::from(""));
-
-    if let Some(dir) = path.parent() {
-        std::fs::create_dir_all(dir).ok();
-    }
-
-    path
-}
-
-#[derive(Parser)]
-#[command(author, version, about, long_about = None)]
-struct Cli {
-    #[arg(short, long, value_name = "FILE", help = "Path to the config file")]
-    config: Option<Box<Path>>,
-}
-
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+use std::time::Duration;
+use tantivy::{DateTime, IndexReader, IndexWriter, ReloadPolicy, Term, schema::*};
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::StreamExt;
 
-    let config = Arc::new(
-        config::Config::load(cli.config.as_ref().map(|p| p.as_ref())).unwrap_or_else(|err| {
-            log::warn!("{err}");
-            config::Config::default()
-        }),
-    );
+const GROUP: &str = "indexer-group";
 
-    env_logger::Builder::new()
-        .filter(Some("indexer"), config.indexer.log_level.into())
-        .init();
+/// Entries left pending by a consumer for this long are claimed by another,
+/// so a crashed instance's entries still get indexed
+const CLAIM_IDLE_TIME: Duration = Duration::from_secs(60);
 
+/// Fields must only ever be appended, see `migrations`
+fn schema() -> Schema {
     let mut schema_builder = Schema::builder();
 
     schema_builder.add_u64_field("id", INDEXED | STORED | FAST);
@@ -69,24 +47,357 @@ async fn main() -> anyhow::Result<()> {
     schema_builder.add_text_field("app_icon", STORED);
 
     schema_builder.add_json_field("hints", STORED);
-    let schema = schema_builder.build();
+    schema_builder.add_u64_field("count", STORED | FAST);
+    schema_builder.add_text_field("key", STRING | STORED);
+    schema_builder.build()
+}
 
-    let index =
-        Index::open_or_create(MmapDirectory::open(path()).unwrap(), schema.clone()).unwrap();
-    let mut index_writer: IndexWriter = index.writer(50_000_000)?;
+/// Identifies a history entry, unlike its ID which collectors reuse across restarts,
+/// with each other and for replacements
+fn entry_key(uuid: &str, id: u64, timestamp: i64) -> String {
+    format!("{uuid}:{id}:{timestamp}")
+}
 
-    let id = schema.get_field("id").unwrap();
-    let summary = schema.get_field("summary").unwrap();
-    let timestamp = schema.get_field("timestamp").unwrap();
-    let body = schema.get_field("body").unwrap();
-    let app_name = schema.get_field("app_name").unwrap();
-    let app_icon = schema.get_field("app_icon").unwrap();
-    let timeout = schema.get_field("timeout").unwrap();
+struct Fields {
+    id: Field,
+    summary: Field,
+    timestamp: Field,
+    body: Field,
+    app_name: Field,
+    app_icon: Field,
+    timeout: Field,
+    hints: Field,
+    count: Field,
+    key: Field,
+}
 
-    let hints = schema.get_field("hints").unwrap();
+impl Fields {
+    fn new(schema: &Schema) -> tantivy::Result<Self> {
+        Ok(Self {
+            id: schema.get_field("id")?,
+            summary: schema.get_field("summary")?,
+            timestamp: schema.get_field("timestamp")?,
+            body: schema.get_field("body")?,
+            app_name: schema.get_field("app_name")?,
+            app_icon: schema.get_field("app_icon")?,
+            timeout: schema.get_field("timeout")?,
+            hints: schema.get_field("hints")?,
+            count: schema.get_field("count")?,
+            key: schema.get_field("key")?,
+        })
+    }
+}
 
-    let client = redis::Client::open(&*config.redis.address)?;
-    let mut con = client.get_multiplexed_async_connection().await?;
+/// Notifications with the same key are duplicates of each other
+#[derive(Hash, PartialEq, Eq)]
+struct DedupKey {
+    app_name: String,
+    summary: String,
+    body: String,
+}
+
+struct Entry {
+    document: TantivyDocument,
+    key: DedupKey,
+    id: u64,
+    /// See [`entry_key`]
+    unique: String,
+    /// Milliseconds since the epoch
+    timestamp: i64,
+}
+
+/// The notification of a notify stream entry, with the image data the control plane
+/// stored as a blob put back inline, since the blob expires long before the history
+async fn notification(
+    con: &mut redis::aio::MultiplexedConnection,
+    keys: &Keys,
+    map: &HashMap<String, redis::Value>,
+) -> error::Result<Option<NewNotification>> {
+    let Some(json) = config::stream::notification_payload(map)? else {
+        return Ok(None);
+    };
+    let mut notification = serde_json::from_slice::<NewNotification>(&json)?;
+
+    let Some(hints) = notification.hints.as_mut() else {
+        return Ok(Some(notification));
+    };
+    if let Some(image::Image::Data(data)) = hints.image.as_mut().and_then(|i| i.image.as_mut())
+        && let Some(key) = data.blob.take()
+    {
+        match redis::AsyncCommands::get::<String, Option<Vec<u8>>>(con, keys.blob(&key)).await? {
+            Some(blob) => data.data = blob,
+            None => {
+                log::warn!("Blob {key} expired before it was indexed, dropping the image");
+                hints.image = None;
+            }
+        }
+    }
+
+    Ok(Some(notification))
+}
+
+/// `None` if the entry shouldn't be indexed
+fn document(
+    fields: &Fields,
+    notification: NewNotification,
+    categories: &HashMap<String, config::CategoryConfig>,
+    retention: &config::Retention,
+) -> error::Result<Option<Entry>> {
+    if !retention.keeps(&notification.app_name) {
+        log::debug!(
+            "Not indexing notification id={} of '{}', the app is excluded from history",
+            notification.id,
+            notification.app_name
+        );
+        return Ok(None);
+    }
+
+    if let Some(category) = notification
+        .hints
+        .as_ref()
+        .and_then(|hints| hints.category.as_deref())
+        .filter(|category| categories.get(*category).is_some_and(|c| !c.history))
+    {
+        log::debug!(
+            "Not indexing notification id={} of category '{}', history is disabled for it",
+            notification.id,
+            category
+        );
+        return Ok(None);
+    }
+
+    let tags = notification
+        .hints
+        .as_ref()
+        .map(|hints| hints.tags.as_slice())
+        .unwrap_or_default();
+    if tags.iter().any(|tag| tag == config::tags::NO_HISTORY) {
+        log::debug!(
+            "Not indexing notification id={}, it's tagged {}",
+            notification.id,
+            config::tags::NO_HISTORY
+        );
+        return Ok(None);
+    }
+    let redact_body = tags.iter().any(|tag| tag == config::tags::REDACT_BODY);
+
+    let body = logging::content(&notification.body);
+    log::info!(
+        "Indexing notification: id={}, app_name='{}', summary={}, body={}, urgency='{}'",
+        notification.id,
+        notification.app_name,
+        logging::content(&notification.summary),
+        if redact_body {
+            &"<redacted>" as &dyn std::fmt::Display
+        } else {
+            &body
+        },
+        notification
+            .hints
+            .as_ref()
+            .map(|hints| hints.urgency)
+            .unwrap_or_default()
+    );
+
+    let key = DedupKey {
+        app_name: notification.app_name.clone(),
+        summary: notification.summary.clone(),
+        body: notification.body.clone(),
+    };
+
+    let unique = entry_key(
+        &notification.uuid,
+        u64::from(notification.id),
+        notification.timestamp,
+    );
+    let mut doc = TantivyDocument::default();
+
+    doc.add_u64(fields.id, notification.id as u64);
+    doc.add_text(fields.key, &unique);
+    doc.add_date(
+        fields.timestamp,
+        DateTime::from_timestamp_millis(notification.timestamp),
+    );
+    doc.add_text(fields.summary, notification.summary);
+    if !redact_body {
+        doc.add_text(fields.body, notification.body);
+    }
+    doc.add_text(fields.app_name, notification.app_name);
+    doc.add_i64(fields.timeout, notification.timeout as i64);
+
+    if let Some(icon) = notification.app_icon {
+        doc.add_text(fields.app_icon, icon);
+    }
+
+    if let Some(h) = notification.hints {
+        doc.add_text(fields.hints, serde_json::to_string(&h)?);
+    }
+
+    Ok(Some(Entry {
+        document: doc,
+        key,
+        id: notification.id as u64,
+        unique,
+        timestamp: notification.timestamp,
+    }))
+}
+
+/// Documents read by a worker, committed together by the writer
+struct Batch {
+    entries: Vec<Entry>,
+    committed: oneshot::Sender<tantivy::Result<()>>,
+}
+
+/// Work for the writer
+enum Job {
+    Index(Batch),
+    /// Clean up history like the janitor does, see `janitor.embedded`
+    Cleanup,
+    /// Delete the whole history, sending back how many entries were deleted
+    Clear(oneshot::Sender<tantivy::Result<u64>>),
+}
+
+/// Latest history entry of a key
+struct Indexed {
+    id: u64,
+    /// See [`entry_key`]
+    unique: String,
+    timestamp: i64,
+    count: u64,
+}
+
+/// Apply the janitor's retention with the indexer's writer
+fn cleanup(index_writer: &mut IndexWriter, retention: &config::Retention) -> anyhow::Result<u64> {
+    let reader: IndexReader = index_writer
+        .index()
+        .reader_builder()
+        .reload_policy(ReloadPolicy::Manual)
+        .try_into()?;
+
+    janitor::cleanup(&reader, index_writer, retention)
+}
+
+/// Delete every document, returns how many there were
+fn clear(index_writer: &mut IndexWriter) -> tantivy::Result<u64> {
+    let reader: IndexReader = index_writer
+        .index()
+        .reader_builder()
+        .reload_policy(ReloadPolicy::Manual)
+        .try_into()?;
+    let count = reader.searcher().num_docs();
+
+    index_writer.delete_all_documents()?;
+    index_writer.commit()?;
+
+    Ok(count)
+}
+
+/// Owns the only IndexWriter, tantivy allows a single one per index.
+/// Being the only writer it also remembers recent entries to merge duplicates into
+fn write(
+    mut index_writer: IndexWriter,
+    fields: Arc<Fields>,
+    config: Arc<config::Config>,
+    mut jobs: mpsc::Receiver<Job>,
+) {
+    let window = config.indexer.dedup_window.as_millis() as i64;
+    let mut recent: HashMap<DedupKey, Indexed> = HashMap::new();
+
+    while let Some(job) = jobs.blocking_recv() {
+        let batch = match job {
+            Job::Index(batch) => batch,
+            Job::Cleanup => {
+                match cleanup(&mut index_writer, &config.janitor.retention) {
+                    Ok(count) => {
+                        log::info!("History cleanup completed: {} documents deleted", count)
+                    }
+                    Err(e) => {
+                        log::error!("History cleanup failed: {}", e);
+                        _ = index_writer.rollback();
+                    }
+                }
+                continue;
+            }
+            Job::Clear(cleared) => {
+                let result = clear(&mut index_writer);
+                if result.is_err() {
+                    _ = index_writer.rollback();
+                }
+                // The entries to merge repeats into are gone
+                recent.clear();
+                _ = cleared.send(result);
+                continue;
+            }
+        };
+
+        let result = batch
+            .entries
+            .into_iter()
+            .try_for_each(|mut entry| {
+                let count = match recent.get(&entry.key) {
+                    Some(previous)
+                        if window > 0 && entry.timestamp - previous.timestamp <= window =>
+                    {
+                        log::debug!(
+                            "Merging notification id={} into duplicate id={}",
+                            entry.id,
+                            previous.id
+                        );
+                        index_writer
+                            .delete_term(Term::from_field_text(fields.key, &previous.unique));
+                        previous.count + 1
+                    }
+                    _ => 1,
+                };
+
+                entry.document.add_u64(fields.count, count);
+                index_writer.add_document(entry.document)?;
+                recent.insert(
+                    entry.key,
+                    Indexed {
+                        id: entry.id,
+                        unique: entry.unique,
+                        timestamp: entry.timestamp,
+                        count,
+                    },
+                );
+
+                Ok(())
+            })
+            .and_then(|_| index_writer.commit().map(|_| ()));
+
+        if result.is_err() {
+            _ = index_writer.rollback();
+            // Entries of the batch are gone, merging into them would drop the repeats
+            recent.clear();
+        }
+        _ = batch.committed.send(result);
+
+        // Entries outside the window can't be merged into anymore
+        if let Some(newest) = recent.values().map(|indexed| indexed.timestamp).max() {
+            recent.retain(|_, indexed| newest - indexed.timestamp <= window);
+        }
+    }
+}
+
+/// Index a batch of entries, `None` if the writer is gone
+async fn commit(jobs: &mpsc::Sender<Job>, entries: Vec<Entry>) -> Option<tantivy::Result<()>> {
+    let (committed, result) = oneshot::channel();
+    jobs.send(Job::Index(Batch { entries, committed }))
+        .await
+        .ok()?;
+    result.await.ok()
+}
+
+/// Consume the notify stream as `consumer` of the indexer group
+async fn work(
+    mut con: redis::aio::MultiplexedConnection,
+    consumer: String,
+    keys: Arc<Keys>,
+    fields: Arc<Fields>,
+    config: Arc<config::Config>,
+    jobs: mpsc::Sender<Job>,
+) {
     let mut read_pending = false;
 
     loop {
@@ -95,73 +406,270 @@ async fn main() -> anyhow::Result<()> {
         let stream_id = if read_pending { "0" } else { ">" };
         read_pending = !read_pending;
 
-        if let Ok(Some(streams)) = AsyncTypedCommands::xread_options(
+        if stream_id == "0"
+            && let Err(e) = AsyncTypedCommands::xautoclaim_options(
+                &mut con,
+                &keys.notify,
+                GROUP,
+                &consumer,
+                CLAIM_IDLE_TIME.as_millis() as usize,
+                "0",
+                StreamAutoClaimOptions::default().with_justid(),
+            )
+            .await
+        {
+            log::warn!("Failed to claim idle entries for {}: {}", consumer, e);
+        }
+
+        let streams = match AsyncTypedCommands::xread_options(
             &mut con,
-            &["moxnotify:notify"],
+            &[&keys.notify],
             &[stream_id],
             &StreamReadOptions::default()
-                .group("indexer-group", "indexer-1")
+                .group(GROUP, &consumer)
                 .block(if stream_id == ">" { 100 } else { 0 }), // Block only when reading new messages
         )
         .await
         {
-            if let Some(stream_key) = streams.keys.iter().find(|sk| sk.key == "moxnotify:notify") {
-                for stream_id in stream_key.ids.iter() {
-                    if let Some(redis::Value::BulkString(json)) = stream_id.map.get("notification")
-                    {
-                        let notification =
-                            serde_json::from_str::<NewNotification>(str::from_utf8(json).unwrap())
-                                .unwrap();
-
-                        log::info!(
-                            "Indexing notification: id={}, app_name='{}', summary='{}', body='{}', urgency='{}'",
-                            notification.id,
-                            notification.app_name,
-                            notification.summary,
-                            notification.body,
-                            notification.hints.as_ref().unwrap().urgency
-                        );
+            Ok(Some(streams)) => streams,
+            Ok(None) if stream_id == ">" => {
+                // No new messages available, yield to avoid busy-waiting
+                tokio::task::yield_now().await;
+                continue;
+            }
+            Ok(None) => continue,
+            Err(e) => {
+                log::error!("Failed to read from Redis stream: {}", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
 
-                        let mut doc = TantivyDocument::default();
+        let Some(stream_key) = streams.keys.iter().find(|sk| sk.key == keys.notify) else {
+            continue;
+        };
 
-                        doc.add_u64(id, notification.id as u64);
-                        doc.add_date(
-                            timestamp,
-                            DateTime::from_timestamp_millis(notification.timestamp),
-                        );
-                        doc.add_text(summary, notification.summary);
-                        doc.add_text(body, notification.body);
-                        doc.add_text(app_name, notification.app_name);
-                        doc.add_i64(timeout, notification.timeout as i64);
+        let mut entries = Vec::new();
+        let mut ids = Vec::new();
+        for stream_id in stream_key.ids.iter() {
+            let entry = match notification(&mut con, &keys, &stream_id.map).await {
+                Ok(Some(notification)) => document(
+                    &fields,
+                    notification,
+                    &config.categories,
+                    &config.janitor.retention,
+                ),
+                Ok(None) => Ok(None),
+                Err(e) => Err(e),
+            };
+            match entry {
+                Ok(entry) => {
+                    entries.extend(entry);
+                    ids.push(stream_id.id.as_str());
+                }
+                Err(e) => {
+                    log::error!("Failed to index entry {}: {}", stream_id.id, e);
+                    // Leave it pending so it's retried, unless it can never be indexed
+                    if e.is_malformed() {
+                        ids.push(stream_id.id.as_str());
+                    }
+                }
+            }
+        }
+
+        if !entries.is_empty() {
+            match commit(&jobs, entries).await {
+                Some(Ok(())) => {}
+                Some(Err(e)) => {
+                    // Leave the whole batch pending so it's retried
+                    log::error!("Failed to commit {} entries: {}", ids.len(), e);
+                    continue;
+                }
+                None => {
+                    log::error!("Index writer stopped, stopping {}", consumer);
+                    return;
+                }
+            }
+        }
+
+        if !ids.is_empty()
+            && let Err(e) = AsyncTypedCommands::xack(&mut con, &keys.notify, GROUP, &ids).await
+        {
+            log::error!("Failed to ACK message: {}", e);
+        }
+    }
+}
 
-                        if let Some(icon) = notification.app_icon {
-                            doc.add_text(app_icon, icon);
-                        }
+/// Clear the history with the writer of `jobs`, then push the outcome to `reply`
+async fn clear_history(
+    con: &mut redis::aio::MultiplexedConnection,
+    jobs: &mpsc::Sender<Job>,
+    reply: &str,
+) -> redis::RedisResult<()> {
+    let (cleared, result) = oneshot::channel();
+    let outcome = match jobs.send(Job::Clear(cleared)).await {
+        Ok(()) => result
+            .await
+            .map_err(|_| "the index writer stopped".to_string())
+            .and_then(|result| result.map_err(|e| e.to_string())),
+        Err(_) => Err("the index writer stopped".to_string()),
+    };
+    match &outcome {
+        Ok(count) => log::info!("Cleared history of {} entries", count),
+        Err(e) => log::error!("Failed to clear history: {}", e),
+    }
 
-                        if let Some(h) = notification.hints {
-                            doc.add_text(hints, serde_json::to_string(&h).unwrap());
-                        }
+    let outcome = serde_json::to_string(&outcome).unwrap_or_default();
+    AsyncTypedCommands::lpush(&mut *con, reply, outcome).await?;
+    // Nobody reads it once the searcher gave up waiting
+    AsyncTypedCommands::expire(&mut *con, reply, 60).await?;
 
-                        index_writer.add_document(doc).unwrap();
+    Ok(())
+}
 
-                        index_writer.commit().unwrap();
-                    }
+/// Serve the searcher's requests to clear the history, which it can't do itself
+/// while the indexer holds the only writer
+async fn serve_clears(client: redis::Client, keys: Arc<Keys>, jobs: mpsc::WeakSender<Job>) {
+    loop {
+        let result = async {
+            let mut con = client.get_multiplexed_async_connection().await?;
+            let mut pubsub = client.get_async_pubsub().await?;
+            pubsub.subscribe(&keys.pubsub_clear_history).await?;
+            let mut requests = pubsub.on_message();
 
-                    if let Err(e) = AsyncTypedCommands::xack(
-                        &mut con,
-                        "moxnotify:notify",
-                        "indexer-group",
-                        &[stream_id.id.as_str()],
-                    )
-                    .await
-                    {
-                        log::error!("Failed to ACK message: {}", e);
-                    }
-                }
+            while let Some(msg) = requests.next().await {
+                let Ok(reply) = msg.get_payload::<String>() else {
+                    continue;
+                };
+                let Some(jobs) = jobs.upgrade() else {
+                    return Ok(());
+                };
+                clear_history(&mut con, &jobs, &reply).await?;
             }
-        } else if stream_id == ">" {
-            // No new messages available, yield to avoid busy-waiting
-            tokio::task::yield_now().await;
+
+            redis::RedisResult::Ok(())
         }
+        .await;
+
+        if jobs.upgrade().is_none() {
+            return;
+        }
+        if let Err(e) = result {
+            log::error!("Failed to serve history clear requests: {}", e);
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
     }
 }
+
+/// Name of this instance within the indexer group, distinct instances
+/// must use distinct names
+fn consumer_name(config: &config::IndexerConfig) -> String {
+    config
+        .consumer
+        .clone()
+        .or_else(|| {
+            std::fs::read_to_string("/proc/sys/kernel/hostname")
+                .ok()
+                .or_else(|| std::env::var("HOSTNAME").ok())
+                .map(|hostname| hostname.trim().to_string())
+                .filter(|hostname| !hostname.is_empty())
+                .map(|hostname| format!("indexer-{hostname}"))
+        })
+        .unwrap_or_else(|| "indexer".to_string())
+}
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[arg(short, long, value_name = "FILE", help = "Path to the config file")]
+    config: Option<Box<Path>>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    let config = Arc::new(
+        config::Config::load(cli.config.as_ref().map(|p| p.as_ref())).unwrap_or_else(|err| {
+            log::warn!("{err}");
+            config::Config::default()
+        }),
+    );
+
+    env_logger::Builder::new()
+        .filter(Some("indexer"), config.indexer.log_level.into())
+        .init();
+    logging::set_log_content(config.log_content);
+
+    let schema = schema();
+
+    let index_path = config.index_path(None);
+    log::info!("Using index path: {}", index_path.display());
+    // Held until the indexer exits, the janitor and searcher don't write meanwhile
+    let _lock = match data_dir::lock(&index_path) {
+        Ok(lock) => lock,
+        Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+            log::info!("Waiting for another process writing to the history index to finish");
+            data_dir::lock_blocking(&index_path)?
+        }
+        Err(e) => return Err(e.into()),
+    };
+    migrations::recover(&index_path)?;
+    std::fs::create_dir_all(&index_path)?;
+    let index = migrations::open(&index_path, schema.clone())?;
+    let index_writer: IndexWriter = index.writer(50_000_000)?;
+    let fields = Arc::new(Fields::new(&schema)?);
+
+    let keys = Arc::new(config.redis.keys());
+    let client = redis::Client::open(&*config.redis.address)?;
+    let consumer = consumer_name(&config.indexer);
+    let (jobs, job_receiver) = mpsc::channel(config.indexer.workers.max(1));
+
+    let mut workers = tokio::task::JoinSet::new();
+    for i in 0..config.indexer.workers.max(1) {
+        let consumer = format!("{consumer}-{}", i + 1);
+        log::info!("Starting worker {}", consumer);
+
+        workers.spawn(work(
+            client.get_multiplexed_async_connection().await?,
+            consumer,
+            Arc::clone(&keys),
+            Arc::clone(&fields),
+            Arc::clone(&config),
+            jobs.clone(),
+        ));
+    }
+
+    tokio::spawn(serve_clears(
+        client.clone(),
+        Arc::clone(&keys),
+        jobs.downgrade(),
+    ));
+
+    if config.janitor.embedded {
+        log::info!(
+            "Cleaning up history every {} seconds",
+            config.janitor.retention.schedule.as_secs()
+        );
+        // Doesn't keep the writer running once the workers are gone
+        let cleanup_jobs = jobs.downgrade();
+        let mut interval = tokio::time::interval(config.janitor.retention.schedule);
+        tokio::spawn(async move {
+            loop {
+                interval.tick().await;
+                let Some(jobs) = cleanup_jobs.upgrade() else {
+                    break;
+                };
+                if jobs.send(Job::Cleanup).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    drop(jobs);
+
+    tokio::task::spawn_blocking(move || write(index_writer, fields, config, job_receiver)).await?;
+    workers.join_all().await;
+
+    Ok(())
+}
@@ -0,0 +1,273 @@
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+use tantivy::directory::MmapDirectory;
+use tantivy::{Index, IndexSettings, IndexWriter, schema::*};
+
+/// Rewrites a document of the previous version for the current schema
+type Migration = fn(&Schema, &mut TantivyDocument) -> tantivy::Result<()>;
+
+/// Version `i + 1` of the index is reached by applying `MIGRATIONS[i]` to every
+/// document. Fields are only ever appended to the schema so their ids carry over
+const MIGRATIONS: &[Migration] = &[
+    // Repeats merged into an entry are counted
+    |schema, doc| {
+        doc.add_u64(schema.get_field("count")?, 1);
+        Ok(())
+    },
+    // Entries are told apart by a key of their own, collectors reuse IDs. The UUID
+    // of the collector wasn't kept before
+    |schema, doc| {
+        let id = doc
+            .get_first(schema.get_field("id")?)
+            .and_then(|id| id.as_u64())
+            .unwrap_or_default();
+        let timestamp = doc
+            .get_first(schema.get_field("timestamp")?)
+            .and_then(|timestamp| timestamp.as_datetime())
+            .map(|timestamp| timestamp.into_timestamp_millis())
+            .unwrap_or_default();
+        doc.add_text(
+            schema.get_field("key")?,
+            crate::entry_key("", id, timestamp),
+        );
+        Ok(())
+    },
+];
+
+pub const VERSION: usize = MIGRATIONS.len();
+
+/// Kept next to tantivy's files, which leaves files it doesn't manage alone
+const VERSION_FILE: &str = "moxnotify-version";
+
+fn version(path: &Path, index: &Index) -> anyhow::Result<usize> {
+    match std::fs::read_to_string(path.join(VERSION_FILE)) {
+        Ok(version) => Ok(version.trim().parse()?),
+        // Predates the version file, which came after the first migration
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Ok(usize::from(index.schema().get_field("count").is_ok()))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write_version(path: &Path) -> std::io::Result<()> {
+    std::fs::write(path.join(VERSION_FILE), VERSION.to_string())
+}
+
+fn migrated_path(path: &Path) -> PathBuf {
+    path.with_extension("migrating")
+}
+
+fn old_path(path: &Path) -> PathBuf {
+    path.with_extension("old")
+}
+
+/// Finish a migration of the index at `path` interrupted while swapping in the
+/// migrated index. Has to run with the index locked, before anything creates `path` again
+pub fn recover(path: &Path) -> std::io::Result<()> {
+    let old_path = old_path(path);
+    if !old_path.exists() {
+        return Ok(());
+    }
+
+    if !path.exists() {
+        // The migrated index is complete once the old one was moved aside, unless
+        // the migration didn't get to write its version
+        let migrated_path = migrated_path(path);
+        let source = if migrated_path.join(VERSION_FILE).exists() {
+            migrated_path
+        } else {
+            old_path.clone()
+        };
+        log::warn!(
+            "Migration of the history index at {:?} was interrupted, recovering it from {:?}",
+            path,
+            source
+        );
+        std::fs::rename(&source, path)?;
+    }
+
+    if old_path.exists() {
+        std::fs::remove_dir_all(&old_path)?;
+    }
+
+    Ok(())
+}
+
+/// Open the history index at `path`, creating it with `schema` or upgrading it to it
+pub fn open(path: &Path, schema: Schema) -> anyhow::Result<Index> {
+    let directory = MmapDirectory::open(path)?;
+    if !Index::exists(&directory)? {
+        let index = Index::create(directory, schema, IndexSettings::default())?;
+        write_version(path)?;
+        return Ok(index);
+    }
+
+    let index = Index::open(directory)?;
+    let version = version(path, &index)?;
+    match version.cmp(&VERSION) {
+        Ordering::Equal => {
+            write_version(path)?;
+            Ok(index)
+        }
+        Ordering::Less => {
+            drop(index);
+            migrate(path, schema, version)
+        }
+        Ordering::Greater => anyhow::bail!(
+            "History index is version {version}, this indexer only supports up to {VERSION}"
+        ),
+    }
+}
+
+/// Rebuild the index at `path` with `schema`, applying the migrations after `from`
+/// to its documents. The old index is only moved aside once the new one is complete,
+/// and only removed once the new one took its place, see [`recover`]
+fn migrate(path: &Path, schema: Schema, from: usize) -> anyhow::Result<Index> {
+    log::info!(
+        "Migrating the history index at {:?} from version {} to {}",
+        path,
+        from,
+        VERSION
+    );
+
+    let old = Index::open_in_dir(path)?;
+    let searcher = old.reader()?.searcher();
+
+    let migrated_path = migrated_path(path);
+    _ = std::fs::remove_dir_all(&migrated_path);
+    std::fs::create_dir_all(&migrated_path)?;
+
+    let index = Index::create_in_dir(&migrated_path, schema.clone())?;
+    let mut index_writer: IndexWriter = index.writer(50_000_000)?;
+
+    let mut migrated = 0;
+    for segment_reader in searcher.segment_readers() {
+        let store = segment_reader.get_store_reader(0)?;
+        for doc in store.iter::<TantivyDocument>(segment_reader.alive_bitset()) {
+            let mut doc = doc?;
+            for migration in &MIGRATIONS[from..] {
+                migration(&schema, &mut doc)?;
+            }
+            index_writer.add_document(doc)?;
+            migrated += 1;
+        }
+    }
+
+    index_writer.commit()?;
+    index_writer.wait_merging_threads()?;
+    write_version(&migrated_path)?;
+    drop(index);
+    drop(searcher);
+    drop(old);
+
+    let old_path = old_path(path);
+    std::fs::rename(path, &old_path)?;
+    std::fs::rename(&migrated_path, path)?;
+    std::fs::remove_dir_all(&old_path)?;
+    log::info!("Migrated {} documents", migrated);
+
+    Ok(Index::open_in_dir(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tantivy::collector::TopDocs;
+    use tantivy::query::AllQuery;
+
+    fn dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("moxnotify-{name}-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&path);
+        _ = std::fs::remove_dir_all(migrated_path(&path));
+        _ = std::fs::remove_dir_all(old_path(&path));
+        std::fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    /// Index as created before the count field and the version file existed
+    fn fixture_v0(path: &Path) {
+        let mut schema_builder = Schema::builder();
+        crate::schema()
+            .fields()
+            .filter(|(_, entry)| !["count", "key"].contains(&entry.name()))
+            .for_each(|(_, entry)| {
+                schema_builder.add_field(entry.clone());
+            });
+        let schema = schema_builder.build();
+
+        let index = Index::create_in_dir(path, schema.clone()).unwrap();
+        let mut index_writer: IndexWriter = index.writer_with_num_threads(1, 15_000_000).unwrap();
+        let mut doc = TantivyDocument::default();
+        doc.add_u64(schema.get_field("id").unwrap(), 7);
+        doc.add_text(schema.get_field("summary").unwrap(), "hello");
+        index_writer.add_document(doc).unwrap();
+        index_writer.commit().unwrap();
+    }
+
+    #[test]
+    fn upgrades_v0() {
+        let path = dir("upgrades-v0");
+        fixture_v0(&path);
+
+        let schema = crate::schema();
+        let index = open(&path, schema.clone()).unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        let docs = searcher
+            .search(&AllQuery, &TopDocs::with_limit(10))
+            .unwrap();
+        assert_eq!(docs.len(), 1);
+
+        let doc = searcher.doc::<TantivyDocument>(docs[0].1).unwrap();
+        let field = |name| doc.get_first(schema.get_field(name).unwrap());
+        assert_eq!(field("id").and_then(|id| id.as_u64()), Some(7));
+        assert_eq!(field("summary").and_then(|s| s.as_str()), Some("hello"));
+        assert_eq!(field("count").and_then(|count| count.as_u64()), Some(1));
+        assert_eq!(field("key").and_then(|key| key.as_str()), Some(":7:0"));
+        assert_eq!(version(&path, &index).unwrap(), VERSION);
+    }
+
+    #[test]
+    fn recovers_interrupted_swap() {
+        let path = dir("recovers-swap");
+        fixture_v0(&path);
+        std::fs::rename(&path, old_path(&path)).unwrap();
+        std::fs::create_dir_all(migrated_path(&path)).unwrap();
+        drop(open(&migrated_path(&path), crate::schema()).unwrap());
+
+        recover(&path).unwrap();
+        assert!(!old_path(&path).exists());
+        assert!(!migrated_path(&path).exists());
+        let index = Index::open_in_dir(&path).unwrap();
+        assert_eq!(version(&path, &index).unwrap(), VERSION);
+    }
+
+    #[test]
+    fn recovers_old_index_without_migrated_one() {
+        let path = dir("recovers-old");
+        fixture_v0(&path);
+        std::fs::rename(&path, old_path(&path)).unwrap();
+
+        recover(&path).unwrap();
+        assert!(!old_path(&path).exists());
+        let index = Index::open_in_dir(&path).unwrap();
+        assert_eq!(version(&path, &index).unwrap(), 0);
+    }
+
+    #[test]
+    fn creates_current_version() {
+        let path = dir("creates-current");
+
+        let index = open(&path, crate::schema()).unwrap();
+        assert_eq!(version(&path, &index).unwrap(), VERSION);
+    }
+
+    #[test]
+    fn rejects_newer_version() {
+        let path = dir("rejects-newer");
+        drop(open(&path, crate::schema()).unwrap());
+        std::fs::write(path.join(VERSION_FILE), (VERSION + 1).to_string()).unwrap();
+
+        assert!(open(&path, crate::schema()).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+//! Removes history past its retention. Runs as its own service, or inside the
+//! indexer with `janitor.embedded`, which then shares its writer
+
+use config::now_ms;
+use std::ops::Bound as StdBound;
+use std::time::Duration;
+use tantivy::collector::Count;
+use tantivy::query::{BooleanQuery, Query, QueryClone, RangeQuery, TermQuery};
+use tantivy::{DateTime, IndexReader, IndexWriter, Term, schema::*};
+
+/// Delete every document matching `query`, returns how many were deleted
+fn delete_matching(
+    reader: &IndexReader,
+    index_writer: &mut IndexWriter,
+    query: &dyn Query,
+) -> anyhow::Result<u64> {
+    reader.reload()?;
+    let count = reader.searcher().search(query, &Count)? as u64;
+    log::info!("Found {} documents to delete", count);
+
+    if count == 0 {
+        return Ok(0);
+    }
+
+    // By the query itself, IDs are reused by other apps' documents
+    index_writer.delete_query(query.box_clone())?;
+    index_writer.commit()?;
+    log::info!("Deleted {} documents", count);
+
+    Ok(count)
+}
+
+/// Documents older than `retention_ms`
+fn older_than(timestamp_field: Field, retention_ms: i64) -> RangeQuery {
+    let cutoff_datetime = DateTime::from_timestamp_millis(now_ms() - retention_ms);
+
+    RangeQuery::new(
+        StdBound::Unbounded,
+        StdBound::Included(Term::from_field_date(timestamp_field, cutoff_datetime)),
+    )
+}
+
+/// Whole days of `period`, rounded up
+pub fn retention_days(period: Duration) -> u64 {
+    period.as_secs().div_ceil(86400)
+}
+
+fn cleanup_old_documents(
+    reader: &IndexReader,
+    index_writer: &mut IndexWriter,
+    retention_days: u64,
+) -> anyhow::Result<u64> {
+    let schema = index_writer.index().schema();
+    let timestamp_field = schema.get_field("timestamp").unwrap();
+
+    let retention_ms = (retention_days as i64) * 24 * 60 * 60 * 1000;
+
+    log::info!(
+        "Cleaning up documents older than {} days (cutoff: {} ms, now: {} ms)",
+        retention_days,
+        now_ms() - retention_ms,
+        now_ms()
+    );
+
+    delete_matching(
+        reader,
+        index_writer,
+        &older_than(timestamp_field, retention_ms),
+    )
+}
+
+/// Delete the documents of apps excluded from history, or older than their app's
+/// own retention period
+fn cleanup_app_documents(
+    reader: &IndexReader,
+    index_writer: &mut IndexWriter,
+    retention: &config::Retention,
+) -> anyhow::Result<u64> {
+    let schema = index_writer.index().schema();
+    let timestamp_field = schema.get_field("timestamp").unwrap();
+    let app_name_field = schema.get_field("app_name").unwrap();
+
+    let mut deleted_count = 0;
+    for (app_name, app) in &retention.apps {
+        let app_query: Box<dyn Query> = Box::new(TermQuery::new(
+            Term::from_field_text(app_name_field, app_name),
+            IndexRecordOption::Basic,
+        ));
+
+        let query: Box<dyn Query> = match (app.exclude, app.period) {
+            (true, _) => {
+                log::info!("Cleaning up documents of '{}', it's excluded", app_name);
+                app_query
+            }
+            (false, Some(period)) => {
+                log::info!(
+                    "Cleaning up documents of '{}' older than {} seconds",
+                    app_name,
+                    period.as_secs()
+                );
+                Box::new(BooleanQuery::intersection(vec![
+                    app_query,
+                    Box::new(older_than(timestamp_field, period.as_millis() as i64)),
+                ]))
+            }
+            (false, None) => continue,
+        };
+
+        deleted_count += delete_matching(reader, index_writer, query.as_ref())?;
+    }
+
+    Ok(deleted_count)
+}
+
+/// Apply the retention period, then the app-specific retention, committing
+/// through `index_writer`. Returns how many documents were deleted
+pub fn cleanup(
+    reader: &IndexReader,
+    index_writer: &mut IndexWriter,
+    retention: &config::Retention,
+) -> anyhow::Result<u64> {
+    let deleted = cleanup_old_documents(reader, index_writer, retention_days(retention.period))?;
+    Ok(deleted + cleanup_app_documents(reader, index_writer, retention)?)
+}
@@ -0,0 +1,173 @@
+//! Logger whose per-module levels can be changed while the service is running,
+//! so debug logs can be captured without restarting and losing the repro
+
+use log::{LevelFilter, Log, Metadata, Record};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Object path the `pl.mox.Notify.Log` interface is served at
+pub const PATH: &str = "/pl/mox/Notify/Log";
+
+/// Level of every module, the longest one a log target starts with applies
+static LEVELS: RwLock<Vec<(String, LevelFilter)>> = RwLock::new(Vec::new());
+
+/// Whether notification content is written to logs as-is, see `content`
+static LOG_CONTENT: AtomicBool = AtomicBool::new(false);
+
+/// Whether `target` is `module` or one of its submodules
+fn within(target: &str, module: &str) -> bool {
+    target
+        .strip_prefix(module)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+fn level(target: &str) -> LevelFilter {
+    let levels = LEVELS.read().unwrap_or_else(|e| e.into_inner());
+    levels
+        .iter()
+        .filter(|(module, _)| within(target, module))
+        .max_by_key(|(module, _)| module.len())
+        .map_or(LevelFilter::Off, |(_, level)| *level)
+}
+
+struct Logger {
+    /// Only formats and writes records, filtering happens against `LEVELS`
+    writer: env_logger::Logger,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.writer.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.writer.flush();
+    }
+}
+
+/// Log `module` at `level` and leave every other module off, like
+/// `env_logger::Builder::new().filter(Some(module), level).init()`
+pub fn init(module: &str, level: LevelFilter) {
+    let writer = env_logger::Builder::new()
+        .filter_level(LevelFilter::Trace)
+        .build();
+
+    if let Err(e) = log::set_boxed_logger(Box::new(Logger { writer })) {
+        eprintln!("Failed to set logger: {e}");
+        return;
+    }
+    set_level(module, level);
+}
+
+/// Change the level of `module` and its submodules, more specific modules keep their own
+pub fn set_level(module: &str, level: LevelFilter) {
+    let mut levels = LEVELS.write().unwrap_or_else(|e| e.into_inner());
+    match levels.iter_mut().find(|(existing, _)| existing == module) {
+        Some((_, existing)) => *existing = level,
+        None => levels.push((module.to_string(), level)),
+    }
+
+    let max = levels
+        .iter()
+        .map(|(_, level)| *level)
+        .max()
+        .unwrap_or(LevelFilter::Off);
+    log::set_max_level(max);
+}
+
+/// Every module with a level, as `module=level`
+pub fn levels() -> Vec<String> {
+    let levels = LEVELS.read().unwrap_or_else(|e| e.into_inner());
+    levels
+        .iter()
+        .map(|(module, level)| format!("{module}={}", level.as_str().to_lowercase()))
+        .collect()
+}
+
+/// Write summaries and bodies wrapped in `content` to logs as they are, for debugging
+pub fn set_log_content(enabled: bool) {
+    LOG_CONTENT.store(enabled, Ordering::Relaxed);
+}
+
+/// Notification text that's only logged as-is after `set_log_content(true)`
+pub struct Content<'a> {
+    text: &'a str,
+    visible: bool,
+}
+
+/// Wrap a summary or body for logging. Unless content logging is on only its length
+/// is written, so it doesn't end up in the journal. Even a hash would give away
+/// short text such as a one-time code in a known template
+pub fn content(text: &str) -> Content<'_> {
+    Content {
+        text,
+        visible: LOG_CONTENT.load(Ordering::Relaxed),
+    }
+}
+
+impl fmt::Display for Content<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.visible {
+            write!(f, "'{}'", self.text)
+        } else {
+            write!(f, "<{} chars>", self.text.chars().count())
+        }
+    }
+}
+
+/// Lets `moxnotifyctl log-level` change levels over D-Bus, serve it at `PATH`
+pub struct LogInterface;
+
+#[zbus::interface(name = "pl.mox.Notify.Log")]
+impl LogInterface {
+    async fn set_level(&self, module: &str, level: &str) -> zbus::fdo::Result<()> {
+        let level = LevelFilter::from_str(level)
+            .map_err(|_| zbus::fdo::Error::InvalidArgs(format!("Invalid log level: {level}")))?;
+        set_level(module, level);
+
+        Ok(())
+    }
+
+    async fn levels(&self) -> Vec<String> {
+        levels()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn within_matches_whole_path_segments() {
+        assert!(within("client", "client"));
+        assert!(within("client::wayland", "client"));
+        assert!(!within("clientele", "client"));
+        assert!(!within("client", "client::wayland"));
+    }
+
+    #[test]
+    fn content_is_hidden_unless_enabled() {
+        let text = "Your code is 123456";
+        let hidden = Content {
+            text,
+            visible: false,
+        }
+        .to_string();
+        assert_eq!(hidden, "<19 chars>");
+
+        let visible = Content {
+            text,
+            visible: true,
+        }
+        .to_string();
+        assert_eq!(visible, "'Your code is 123456'");
+    }
+}
@@ -7,6 +7,17 @@
 let
   cfg = config.services.moxnotify;
   inherit (lib) types;
+  settingsTypes = import ./options.nix { inherit lib; };
+
+  # Unset options are null, leave them out so the config's defaults apply
+  withoutNulls =
+    value:
+    if builtins.isAttrs value then
+      lib.mapAttrs (_: withoutNulls) (lib.filterAttrs (_: value: value != null) value)
+    else if builtins.isList value then
+      map withoutNulls value
+    else
+      value;
 in
 {
   imports = [ ./stylix.nix ];
@@ -20,7 +31,7 @@ in
       package = lib.mkPackageOption pkgs "moxnotify-webui" { };
       searcherAddress = lib.mkOption {
         type = types.str;
-        default = "http://localhost:64203";
+        default = "http://127.0.0.1:64203";
       };
       port = lib.mkOption {
         type = types.int;
@@ -40,7 +51,7 @@ in
         default = cfg.enable;
       };
       settings = lib.mkOption {
-        type = lib.types.attrs;
+        type = settingsTypes.client;
         default = { };
         description = "Configuration for client";
       };
@@ -51,7 +62,7 @@ in
         default = cfg.enable;
       };
       settings = lib.mkOption {
-        type = lib.types.attrs;
+        type = settingsTypes.scheduler;
         default = { };
         description = "Configuration for scheduler";
       };
@@ -62,7 +73,7 @@ in
         default = cfg.enable;
       };
       settings = lib.mkOption {
-        type = lib.types.attrs;
+        type = settingsTypes.control_plane;
         default = { };
         description = "Configuration for control plane";
       };
@@ -73,7 +84,7 @@ in
         default = cfg.enable;
       };
       settings = lib.mkOption {
-        type = lib.types.attrs;
+        type = settingsTypes.indexer;
         default = { };
         description = "Configuration for indexer";
       };
@@ -84,7 +95,7 @@ in
         default = cfg.enable;
       };
       settings = lib.mkOption {
-        type = lib.types.attrs;
+        type = settingsTypes.searcher;
         default = { };
         description = "Configuration for searcher";
       };
@@ -95,7 +106,7 @@ in
         default = cfg.enable;
       };
       settings = lib.mkOption {
-        type = lib.types.attrs;
+        type = settingsTypes.janitor;
         default = { };
         description = "Configuration for janitor";
       };
@@ -106,21 +117,52 @@ in
         default = cfg.enable;
       };
       settings = lib.mkOption {
-        type = lib.types.attrs;
+        type = settingsTypes.collector;
         default = { };
         description = "Configuration for collector";
       };
     };
 
+    sniCollector = {
+      enable = lib.mkOption {
+        type = types.bool;
+        default = false;
+        description = "Synthesize notifications from tray items requesting attention";
+      };
+      settings = lib.mkOption {
+        type = settingsTypes.sni_collector;
+        default = { };
+        description = "Configuration for StatusNotifierItem collector";
+      };
+    };
+
     valkey.enable = lib.mkOption {
       type = types.bool;
       default = cfg.enable;
     };
 
+    locale = lib.mkOption {
+      type = types.nullOr types.str;
+      default = null;
+      description = "Locale dates and times are formatted in and built-in strings are translated to, taken from the environment when unset";
+    };
+
+    translations = lib.mkOption {
+      type = types.attrsOf (types.attrsOf types.str);
+      default = { };
+      description = "Fluent messages replacing the built-in translations, by language code";
+    };
+
+    logContent = lib.mkOption {
+      type = types.bool;
+      default = false;
+      description = "Log notification summaries and bodies as they are instead of their length, for debugging";
+    };
+
     redis.settings = lib.mkOption {
-      type = lib.types.attrs;
+      type = settingsTypes.redis;
       default = { };
-      description = "Configuration for collector";
+      description = "Configuration for Redis";
     };
 
     settings = lib.mkOption {
@@ -148,25 +190,54 @@ in
 
   config = lib.mkIf cfg.enable {
     xdg.configFile = {
-      "mox/moxnotify/default.nix".text = lib.generators.toPretty { } {
+      "mox/moxnotify/default.nix".text = lib.generators.toPretty { } (withoutNulls {
         redis = cfg.redis.settings;
         collector = cfg.collector.settings;
-        controlPlane = cfg.controlPlane.settings;
+        sni_collector = cfg.sniCollector.settings;
+        control_plane = cfg.controlPlane.settings;
         indexer = cfg.indexer.settings;
         searcher = cfg.searcher.settings;
         scheduler = cfg.scheduler.settings;
         janitor = cfg.janitor.settings;
         client = cfg.client.settings;
-      };
+        locale = cfg.locale;
+        translations = cfg.translations;
+        log_content = cfg.logContent;
+      });
     };
 
-    xdg.dataFile = lib.mkIf cfg.collector.enable {
-      "dbus-1/services/pl.mox.notify.service".text = ''
-        [D-BUS Service]
-        Name=org.freedesktop.Notifications
-        Exec=${cfg.package}/bin/moxnotify-collector
-        SystemdService=moxnotify-collector.service
-      '';
+    xdg.dataFile = lib.mkMerge [
+      (lib.mkIf cfg.collector.enable {
+        "dbus-1/services/pl.mox.notify.service".text = ''
+          [D-BUS Service]
+          Name=org.freedesktop.Notifications
+          Exec=${cfg.package}/bin/moxnotify-collector
+          SystemdService=moxnotify-collector.service
+        '';
+      })
+      (lib.mkIf cfg.client.enable {
+        "dbus-1/services/pl.mox.notify.client.service".text = ''
+          [D-BUS Service]
+          Name=pl.mox.Notify
+          Exec=${cfg.package}/bin/moxnotify-client
+          SystemdService=moxnotify-client.service
+        '';
+      })
+    ];
+
+    # The control plane and scheduler are started on the first connection to their address
+    systemd.user.sockets = {
+      moxnotify-control-plane = lib.mkIf cfg.controlPlane.enable {
+        Unit.Description = "Moxnotify Control Plane socket";
+        Socket.ListenStream = lib.defaultTo "[::1]:64201" cfg.controlPlane.settings.address;
+        Install.WantedBy = [ "sockets.target" ];
+      };
+
+      moxnotify-scheduler = lib.mkIf cfg.scheduler.enable {
+        Unit.Description = "Moxnotify Scheduler socket";
+        Socket.ListenStream = lib.defaultTo "[::1]:64202" cfg.scheduler.settings.address;
+        Install.WantedBy = [ "sockets.target" ];
+      };
     };
 
     systemd.user.services = {
@@ -195,14 +266,14 @@ in
         Unit = {
           Description = "Moxnotify Collector";
           After = lib.optionals cfg.controlPlane.enable [
-            "moxnotify-control-plane.service"
+            "moxnotify-control-plane.socket"
           ];
           Requires = lib.optionals cfg.controlPlane.enable [
-            "moxnotify-control-plane.service"
+            "moxnotify-control-plane.socket"
           ];
           Wants =
             lib.optionals cfg.scheduler.enable [
-              "moxnotify-scheduler.service"
+              "moxnotify-scheduler.socket"
             ]
             ++ lib.optionals cfg.indexer.enable [
               "moxnotify-indexer.service"
@@ -220,6 +291,31 @@ in
         };
       };
 
+      moxnotify-sni-collector = lib.mkIf cfg.sniCollector.enable {
+        Unit = {
+          Description = "Moxnotify StatusNotifierItem Collector";
+          After = [
+            "graphical-session.target"
+          ]
+          ++ lib.optionals cfg.controlPlane.enable [
+            "moxnotify-control-plane.socket"
+          ];
+          Requires = lib.optionals cfg.controlPlane.enable [
+            "moxnotify-control-plane.socket"
+          ];
+        };
+
+        Service = {
+          Type = "simple";
+          ExecStart = "${cfg.package}/bin/moxnotify-sni-collector";
+          Restart = "on-failure";
+        };
+
+        Install = {
+          WantedBy = [ "graphical-session.target" ];
+        };
+      };
+
       moxnotify-scheduler = lib.mkIf cfg.scheduler.enable {
         Unit = {
           Description = "Moxnotify Scheduler - Notification scheduling service";
@@ -250,8 +346,8 @@ in
             lib.optionals cfg.collector.enable [
               "moxnotify-collector.service"
             ]
-            ++ [ "moxnotify-scheduler.service" ];
-          Requires = [ "moxnotify-scheduler.service" ];
+            ++ [ "moxnotify-scheduler.socket" ];
+          Requires = [ "moxnotify-scheduler.socket" ];
           RefuseManualStart = false;
         };
 
@@ -0,0 +1,323 @@
+# Generated by `cargo xtask nix-options` from the config structs, don't edit by hand
+{ lib }:
+let
+  inherit (lib) types;
+  # Options left unset are dropped from the config, so the defaults of the config structs apply
+  option =
+    type:
+    lib.mkOption {
+      type = types.nullOr type;
+      default = null;
+    };
+in
+{
+  collector = types.submodule {
+    options = {
+      default_timeout = option (types.submodule {
+        options = {
+          urgency_low = option types.int;
+          urgency_normal = option types.int;
+          urgency_critical = option types.int;
+        };
+      });
+      control_plane_address = option types.str;
+      log_level = option types.str;
+      replace_existing = option types.bool;
+      allow_replacement = option types.bool;
+      limits = option (types.submodule {
+        options = {
+          max_summary_length = option types.int;
+          max_body_bytes = option types.int;
+          max_actions = option types.int;
+          max_image_dimension = option types.int;
+        };
+      });
+      image = option (types.submodule {
+        options = {
+          max_dimension = option types.int;
+          format = option (types.enum [ "raw" "png" "webp" ]);
+        };
+      });
+      compression = option (types.enum [ "none" "zstd" ]);
+      reading_speed = option (types.submodule {
+        options = {
+          enabled = option types.bool;
+          words_per_minute = option types.int;
+          min_timeout = option types.int;
+          max_timeout = option types.int;
+        };
+      });
+      offline_buffer = option types.int;
+      call_categories = option (types.listOf types.str);
+    };
+  };
+  sni_collector = types.submodule {
+    options = {
+      control_plane_address = option types.str;
+      log_level = option types.str;
+      compression = option (types.enum [ "none" "zstd" ]);
+      watch_all = option types.bool;
+      apps = option (types.attrsOf (types.submodule {
+        options = {
+          enabled = option types.bool;
+          urgency = option (types.enum [ "low" "normal" "critical" ]);
+          timeout = option types.int;
+        };
+      }));
+      offline_buffer = option types.int;
+    };
+  };
+  control_plane = types.submodule {
+    options = {
+      address = option types.str;
+      admin_address = option types.str;
+      log_level = option types.str;
+      compression = option (types.enum [ "none" "zstd" ]);
+      filters = option (types.listOf (types.submodule {
+        options = {
+          match = option (types.submodule {
+            options = {
+              app_name = option types.str;
+              category = option types.str;
+              urgency = option (types.enum [ "low" "normal" "critical" ]);
+              summary = option types.str;
+              body = option types.str;
+            };
+          });
+          drop = option types.bool;
+          set = option (types.submodule {
+            options = {
+              app_name = option types.str;
+              app_icon = option types.str;
+              summary = option types.str;
+              body = option types.str;
+              category = option types.str;
+              urgency = option (types.enum [ "low" "normal" "critical" ]);
+              timeout = option types.int;
+              expire_action = option types.str;
+            };
+          });
+          tags = option (types.listOf types.str);
+          last = option types.bool;
+        };
+      }));
+      active_limit = option (types.submodule {
+        options = {
+          max = option types.int;
+          overflow = option (types.enum [ "drop_oldest" "coalesce" "reject" ]);
+        };
+      });
+      hooks = option (types.attrsOf types.str);
+      compaction = option (types.submodule {
+        options = {
+          enabled = option types.bool;
+          schedule = option types.str;
+        };
+      });
+    };
+  };
+  indexer = types.submodule {
+    options = {
+      control_plane_address = option types.str;
+      log_level = option types.str;
+      consumer = option types.str;
+      workers = option types.int;
+      dedup_window = option types.str;
+      index_path = option types.str;
+    };
+  };
+  scheduler = types.submodule {
+    options = {
+      address = option types.str;
+      log_level = option types.str;
+      compression = option (types.enum [ "none" "zstd" ]);
+      expire_action_apps = option (types.listOf types.str);
+    };
+  };
+  searcher = types.submodule {
+    options = {
+      address = option types.str;
+      log_level = option types.str;
+      token = option types.str;
+      token_file = option types.str;
+      index_path = option types.str;
+      saved_searches = option (types.attrsOf types.str);
+    };
+  };
+  janitor = types.submodule {
+    options = {
+      log_level = option types.str;
+      retention = option (types.submodule {
+        options = {
+          period = option types.str;
+          schedule = option types.str;
+          apps = option (types.attrsOf (types.submodule {
+            options = {
+              exclude = option types.bool;
+              period = option types.str;
+            };
+          }));
+        };
+      });
+      index_path = option types.str;
+      embedded = option types.bool;
+    };
+  };
+  client = types.submodule {
+    options = {
+      general = option (types.submodule {
+        options = {
+          history = option (types.submodule {
+            options = {
+              size = option types.int;
+              lock_after = option types.str;
+            };
+          });
+          theme = option types.str;
+          default_sound_file = option (types.submodule {
+            options = {
+              urgency_low = option types.str;
+              urgency_normal = option types.str;
+              urgency_critical = option types.str;
+            };
+          });
+          ignore_sound_file = option types.bool;
+          scroll_sensitivity = option types.number;
+          hint_characters = option types.str;
+          layout_hint_characters = option (types.attrsOf types.str);
+          max_visible = option types.int;
+          icon_size = option types.int;
+          app_icon_size = option types.int;
+          anchor = option (types.enum [ "top_right" "top_center" "top_left" "bottom_right" "bottom_center" "bottom_left" "center_right" "center_left" "center" ]);
+          layer = option (types.enum [ "background" "bottom" "top" "overlay" ]);
+          output = option types.str;
+          ignore_timeout = option types.bool;
+          margin = option (types.submodule {
+            options = {
+              left = option types.number;
+              right = option types.number;
+              top = option types.number;
+              bottom = option types.number;
+            };
+          });
+          focus_sender = option types.bool;
+          sleep_digest = option types.bool;
+          digest = option (types.submodule {
+            options = {
+              urgency = option (types.listOf (types.enum [ "low" "normal" "critical" ]));
+              apps = option (types.listOf types.str);
+              interval = option types.str;
+            };
+          });
+          link_previews = option (types.submodule {
+            options = {
+              enabled = option types.bool;
+              timeout = option types.str;
+              max_size = option types.int;
+            };
+          });
+          codes = option (types.submodule {
+            options = {
+              patterns = option (types.listOf types.str);
+              dismiss = option types.bool;
+            };
+          });
+          media = option (types.submodule {
+            options = {
+              enabled = option types.bool;
+              categories = option (types.listOf types.str);
+              any_player = option types.bool;
+              artwork_size = option types.int;
+            };
+          });
+          calls = option (types.submodule {
+            options = {
+              accept = option (types.listOf types.str);
+              decline = option (types.listOf types.str);
+              ring_interval = option types.str;
+            };
+          });
+          timestamps = option (types.submodule {
+            options = {
+              enabled = option types.bool;
+              format = option types.str;
+              update_interval = option types.str;
+            };
+          });
+          dnd = option (types.submodule {
+            options = {
+              exceptions = option (types.listOf (types.submodule {
+                options = {
+                  apps = option (types.listOf types.str);
+                  summary = option types.str;
+                  hours = option types.str;
+                };
+              }));
+              badge = option types.bool;
+              auto_inhibit = option (types.listOf (types.submodule {
+                options = {
+                  app_id = option types.str;
+                  on = option (types.enum [ "focused" "fullscreen" ]);
+                };
+              }));
+            };
+          });
+          key_repeat = option (types.submodule {
+            options = {
+              rate = option types.int;
+              delay = option types.str;
+            };
+          });
+          mute_app_for = option types.str;
+          restore_silently = option types.bool;
+          sound_on_replace = option (types.listOf types.str);
+          feedback = option (types.submodule {
+            options = {
+              urgency = option (types.listOf (types.enum [ "low" "normal" "critical" ]));
+              leds = option (types.listOf types.str);
+              duration = option types.str;
+            };
+          });
+          reduced_motion = option types.bool;
+        };
+      });
+      keymaps = option (types.listOf (types.submodule {
+        options = {
+          mode = option (types.enum [ "n" "h" "p" ]);
+          keys = option types.anything;
+          action = option (types.enum [ "next_notification" "previous_notification" "dismiss_notification" "first_notification" "last_notification" "unfocus" "noop" "hint_mode" "normal_mode" "mute" "unmute" "toggle_mute" "ihibit" "uninhibit" "toggle_inhibit" "show_waiting" "pop_waiting" "toggle_inspector" "toggle_history" "mute_app" "command_palette" ]);
+        };
+      }));
+      css = option types.str;
+      log_level = option types.str;
+      compression = option (types.enum [ "none" "zstd" ]);
+    };
+  };
+  redis = types.submodule {
+    options = {
+      address = option types.str;
+      blob_ttl = option types.str;
+      namespace = option types.str;
+    };
+  };
+  audit = types.submodule {
+    options = {
+      enabled = option types.bool;
+      max_len = option types.int;
+    };
+  };
+  frecency = types.submodule {
+    options = {
+      enabled = option types.bool;
+      half_life = option types.str;
+    };
+  };
+  categories = types.attrsOf (types.submodule {
+    options = {
+      icon = option types.str;
+      sound_file = option types.str;
+      sound_name = option types.str;
+      history = option types.bool;
+    };
+  });
+}
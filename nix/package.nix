@@ -10,9 +10,25 @@
   libGL,
   egl-wayland,
   protobuf,
+  # Notification sounds, pulls in pipewire
+  withAudio ? true,
+  # Full text search and history retention, pulls in tantivy
+  withSearch ? true,
 }:
 let
   cargoToml = fromTOML (builtins.readFile ../Cargo.toml);
+  searchCrates = [
+    "indexer"
+    "janitor"
+    "searcher"
+  ];
+  cargoFlags = lib.concatStringsSep " " (
+    lib.optionals (!withSearch) (map (crate: "--exclude ${crate}") searchCrates)
+    ++ lib.optionals (!withAudio) [
+      "--no-default-features"
+      "--features client/gpu"
+    ]
+  );
 in
 rustPlatform.buildRustPackage {
   pname = "moxnotify";
@@ -37,6 +53,8 @@ rustPlatform.buildRustPackage {
         "client"
         "ctl"
         "collector-dbus"
+        "collector-sni"
+        "forwarder"
         "control_plane"
         "indexer"
         "janitor"
@@ -44,7 +62,17 @@ rustPlatform.buildRustPackage {
         "scheduler"
         "searcher"
         "config"
+        "supervisor"
+        "audit"
+        "tui"
+        "bench"
+        "xtask"
+        "logging"
+        "i18n"
+        "data_dir"
         "pl.mox.notify.service.in"
+        "pl.mox.notify.client.service.in"
+        "pl.mox.notify.policy"
         "Cargo.toml"
         "Cargo.lock"
       ];
@@ -60,25 +88,33 @@ rustPlatform.buildRustPackage {
   buildInputs = [
     libxkbcommon
     wayland
-    pipewire
     egl-wayland
-  ];
+  ]
+  ++ lib.optional withAudio pipewire;
 
   doCheck = false;
 
   buildPhase = ''
-    cargo build --release --workspace
+    cargo build --release --workspace ${cargoFlags}
   '';
 
   installPhase = ''
     install -Dm755 target/release/client $out/bin/moxnotify-client
     install -Dm755 target/release/collector-dbus $out/bin/moxnotify-collector
+    install -Dm755 target/release/collector-sni $out/bin/moxnotify-sni-collector
     install -Dm755 target/release/control_plane $out/bin/moxnotify-control-plane
+    install -Dm755 target/release/scheduler $out/bin/moxnotify-scheduler
+  ''
+  + lib.optionalString withSearch ''
     install -Dm755 target/release/indexer $out/bin/moxnotify-indexer
     install -Dm755 target/release/janitor $out/bin/moxnotify-janitor
-    install -Dm755 target/release/scheduler $out/bin/moxnotify-scheduler
     install -Dm755 target/release/searcher $out/bin/moxnotify-searcher
+  ''
+  + ''
     install -Dm755 target/release/ctl $out/bin/moxnotifyctl
+    install -Dm755 target/release/tui $out/bin/moxnotify-tui
+    install -Dm755 target/release/bench $out/bin/moxnotify-bench
+    install -Dm644 pl.mox.notify.policy $out/share/polkit-1/actions/pl.mox.notify.policy
   '';
 
   postFixup = ''
@@ -1,3 +1,4 @@
+use config::keys::Keys;
 use redis::AsyncTypedCommands;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -25,23 +26,21 @@ impl Default for ClientState {
 }
 
 pub struct ClientStateManager {
-    redis_con: Arc<Mutex<redis::aio::MultiplexedConnection>>,
+    redis_con: Arc<Mutex<redis::aio::ConnectionManager>>,
+    keys: Arc<Keys>,
 }
 
 impl ClientStateManager {
-    pub fn new(redis_con: redis::aio::MultiplexedConnection) -> Self {
+    pub fn new(redis_con: redis::aio::ConnectionManager, keys: Arc<Keys>) -> Self {
         Self {
             redis_con: Arc::new(Mutex::new(redis_con)),
+            keys,
         }
     }
 
-    fn client_key(client_id: &str) -> String {
-        format!("moxnotify:client:{}:state", client_id)
-    }
-
     pub async fn load_state(&self, client_id: &str) -> ClientState {
         let mut con = self.redis_con.lock().await;
-        let key = Self::client_key(client_id);
+        let key = self.keys.client_state(client_id);
 
         match con.hgetall::<&str>(&key).await {
             Ok(hash_data) => {
@@ -107,7 +106,7 @@ impl ClientStateManager {
 
     pub async fn save_state(&self, client_id: &str, state: &ClientState) {
         let mut con = self.redis_con.lock().await;
-        let key = Self::client_key(client_id);
+        let key = self.keys.client_state(client_id);
 
         let mut success = true;
 
@@ -165,9 +164,37 @@ impl ClientStateManager {
         }
     }
 
+    /// Remember whether the client has a notification selected, so a restarted
+    /// client selects it again. Kept apart from [`ClientState`], which the forwarder
+    /// saves from its own copy
+    pub async fn set_selecting(&self, client_id: &str, selecting: bool) {
+        let mut con = self.redis_con.lock().await;
+        let key = self.keys.client_state(client_id);
+
+        if let Err(e) = con
+            .hset::<&str, &str, &str>(&key, "selecting", if selecting { "1" } else { "0" })
+            .await
+        {
+            log::warn!("Failed to save selecting for client {}: {}", client_id, e);
+        }
+    }
+
+    pub async fn selecting(&self, client_id: &str) -> bool {
+        let mut con = self.redis_con.lock().await;
+        let key = self.keys.client_state(client_id);
+
+        match con.hget::<&str, &str>(&key, "selecting").await {
+            Ok(selecting) => selecting.as_deref() == Some("1"),
+            Err(e) => {
+                log::warn!("Failed to load selecting for client {}: {}", client_id, e);
+                false
+            }
+        }
+    }
+
     pub async fn delete_state(&self, client_id: &str) {
         let mut con = self.redis_con.lock().await;
-        let key = Self::client_key(client_id);
+        let key = self.keys.client_state(client_id);
 
         match con.del::<&str>(&key).await {
             Ok(_) => {
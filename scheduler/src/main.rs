@@ -5,6 +5,10 @@ pub mod moxnotify {
     pub mod client {
         tonic::include_proto!("moxnotify.client");
     }
+
+    /// Served through gRPC reflection so tools like grpcurl can discover the API
+    pub const FILE_DESCRIPTOR_SET: &[u8] =
+        tonic::include_file_descriptor_set!("moxnotify_descriptor");
 }
 
 mod client_state;
@@ -14,65 +18,165 @@ mod view_range;
 use crate::client_state::{ClientState, ClientStateManager};
 use crate::moxnotify::client::notification_message;
 use crate::timeout_scheduler::TimeoutScheduler;
+use audit::AuditLog;
 use clap::Parser;
+use config::FrecencyConfig;
+use config::error::{self, Error};
+use config::keys::{Keys, stream_position};
+use config::types::Compression;
 use moxnotify::client::client_service_server::{ClientService, ClientServiceServer};
 use moxnotify::client::viewport_navigation_request::Direction;
 use moxnotify::client::{
     ClientActionInvokedRequest, ClientActionInvokedResponse, ClientNotificationClosedRequest,
-    ClientNotificationClosedResponse, ClientNotifyRequest, GetViewportRequest, NotificationMessage,
-    RestartTimersRequest, RestartTimersResponse, StopTimersRequest, StopTimersResponse,
-    ViewportNavigationRequest, ViewportNavigationResponse,
+    ClientNotificationClosedResponse, ClientNotifyRequest, GetBlobRequest, GetBlobResponse,
+    GetViewportRequest, MuteAppRequest, MuteAppResponse, NotificationMessage, RestartTimersRequest,
+    RestartTimersResponse, StopTimersRequest, StopTimersResponse, ViewportNavigationRequest,
+    ViewportNavigationResponse,
+};
+use moxnotify::types::{
+    ActionInvoked, Capability, CloseNotification, CloseReason, NewNotification, NotificationClosed,
+    image,
 };
-use moxnotify::types::{CloseNotification, CloseReason, NewNotification, NotificationClosed};
 use redis::AsyncTypedCommands;
+use redis::aio::ConnectionManagerConfig;
 use redis::streams::StreamReadOptions;
+use serde::de::DeserializeOwned;
 use std::collections::HashMap;
 use std::path::Path;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{Mutex, mpsc};
-use tokio_stream::wrappers::ReceiverStream;
-use tonic::{Request, Response, Status, transport::Server};
+use tokio_stream::StreamExt;
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::codec::CompressionEncoding;
+use tonic::{Code, Request, Response, Status, transport::Server};
+use tonic_types::{ErrorDetails, StatusExt};
 use view_range::ViewRange;
 
+/// Add the weight in ARGV[2] to the frecency of the action ARGV[1], both base 2
+/// logarithms: log2(2^a + 2^b) = max + log2(1 + 2^(min - max))
+const ADD_FRECENCY_SCRIPT: &str = r#"
+    local weight = tonumber(ARGV[2])
+    local score = redis.call('ZSCORE', KEYS[1], ARGV[1])
+    if score then
+        local high = math.max(tonumber(score), weight)
+        local low = math.min(tonumber(score), weight)
+        weight = high + math.log(1 + 2 ^ (low - high)) / math.log(2)
+    end
+    redis.call('ZADD', KEYS[1], weight, ARGV[1])
+"#;
+
+/// Record `invoked` and write it to the action_invoked stream for the collector
+/// to pass on to the app
+async fn write_action(
+    con: &mut redis::aio::ConnectionManager,
+    keys: &Keys,
+    audit: &AuditLog,
+    invoked: &ActionInvoked,
+) -> error::Result<()> {
+    audit
+        .record(
+            invoked.id,
+            &invoked.uuid,
+            audit::Event::Action {
+                key: invoked.action_key.clone(),
+            },
+        )
+        .await;
+
+    let json = serde_json::to_string(invoked)?;
+    if let Err(e) =
+        AsyncTypedCommands::xadd(con, &keys.action_invoked, "*", &[("action", json.as_str())]).await
+    {
+        log::error!("Failed to write action_invoked to Redis: {}", e);
+    }
+
+    Ok(())
+}
+
 #[derive(Clone)]
 struct Scheduler {
     timeouts: Arc<TimeoutScheduler>,
-    redis_con: Arc<Mutex<redis::aio::MultiplexedConnection>>,
+    redis_con: Arc<Mutex<redis::aio::ConnectionManager>>,
     redis_client: redis::Client,
     state_manager: Arc<ClientStateManager>,
+    keys: Arc<Keys>,
+    audit: AuditLog,
+    frecency: FrecencyConfig,
+    /// IDs that clients sent in their `Hello`, by the address of their connection
+    sessions: Arc<Mutex<HashMap<String, String>>>,
 }
 
 impl Scheduler {
     async fn new(
-        redis_con: redis::aio::MultiplexedConnection,
+        redis_con: redis::aio::ConnectionManager,
         redis_client: redis::Client,
-    ) -> Self {
-        let timeout_redis_con = redis_client
-            .get_multiplexed_async_connection()
-            .await
-            .expect("Failed to get Redis connection for timeout scheduler");
-
-        let state_redis_con = redis_client
-            .get_multiplexed_async_connection()
-            .await
-            .expect("Failed to get Redis connection for state manager");
-
-        Self {
-            timeouts: Arc::new(TimeoutScheduler::new(timeout_redis_con)),
+        keys: Arc<Keys>,
+        audit: AuditLog,
+        expire_action_apps: Arc<[String]>,
+        frecency: FrecencyConfig,
+    ) -> error::Result<Self> {
+        let timeout_redis_con = redis_client.get_connection_manager().await?;
+        let state_redis_con = redis_client.get_connection_manager().await?;
+
+        Ok(Self {
+            timeouts: Arc::new(TimeoutScheduler::new(
+                timeout_redis_con,
+                Arc::clone(&keys),
+                audit.clone(),
+                expire_action_apps,
+            )),
             redis_con: Arc::new(Mutex::new(redis_con)),
             redis_client,
-            state_manager: Arc::new(ClientStateManager::new(state_redis_con)),
-        }
+            state_manager: Arc::new(ClientStateManager::new(state_redis_con, Arc::clone(&keys))),
+            keys,
+            audit,
+            frecency,
+            sessions: Arc::default(),
+        })
+    }
+
+    /// Clients are identified by the ID they sent in their `Hello`, or by the
+    /// address of their connection when they didn't send one
+    async fn client_id<T>(&self, request: &Request<T>) -> error::Result<String> {
+        let address = connection_address(request)?;
+        let sessions = self.sessions.lock().await;
+
+        Ok(sessions.get(&address).cloned().unwrap_or(address))
+    }
+
+    async fn invoke_action(&self, invoked: &ActionInvoked) -> error::Result<()> {
+        let mut con = self.redis_con.lock().await;
+        write_action(&mut con, &self.keys, &self.audit, invoked).await
+    }
+
+    /// Count an action the user invoked towards the frecency of its app's actions
+    async fn record_frecency(&self, invoked: &ActionInvoked) -> error::Result<()> {
+        let mut con = self.redis_con.lock().await;
+
+        let Some(json) =
+            AsyncTypedCommands::hget(&mut *con, &self.keys.active, invoked.id.to_string()).await?
+        else {
+            return Ok(());
+        };
+        let notification = serde_json::from_str::<NewNotification>(&json)?;
+
+        redis::Script::new(ADD_FRECENCY_SCRIPT)
+            .key(self.keys.action_frecency(&notification.app_name))
+            .arg(&invoked.action_key)
+            .arg(self.frecency.log_weight(config::now_ms()))
+            .invoke_async::<()>(&mut *con)
+            .await?;
+
+        Ok(())
     }
 
-    async fn get_active_notifications(&self) -> HashMap<u32, NewNotification> {
+    async fn get_active_notifications(&self) -> error::Result<HashMap<u32, NewNotification>> {
         let mut con = self.redis_con.lock().await;
 
         let hash_data: HashMap<String, String> =
-            AsyncTypedCommands::hgetall(&mut *con, "moxnotify:active")
-                .await
-                .unwrap();
+            AsyncTypedCommands::hgetall(&mut *con, &self.keys.active).await?;
 
         let mut active_notifications = HashMap::new();
         for (id_str, json) in hash_data {
@@ -91,7 +195,43 @@ impl Scheduler {
             }
         }
 
-        active_notifications
+        Ok(active_notifications)
+    }
+
+    /// Put image data the control plane stored as a blob back inline, for
+    /// clients that can't fetch blobs themselves
+    async fn inline_image(&self, notification: &mut NewNotification) {
+        let Some(hints) = notification.hints.as_mut() else {
+            return;
+        };
+        let Some(image::Image::Data(data)) = hints.image.as_mut().and_then(|i| i.image.as_mut())
+        else {
+            return;
+        };
+        let Some(key) = data.blob.take() else {
+            return;
+        };
+
+        let mut con = self.redis_con.lock().await;
+        match redis::AsyncCommands::get::<String, Option<Vec<u8>>>(&mut *con, self.keys.blob(&key))
+            .await
+        {
+            Ok(Some(blob)) => data.data = blob,
+            Ok(None) => hints.image = None,
+            Err(e) => {
+                log::warn!("Failed to read blob {key}: {e}");
+                hints.image = None;
+            }
+        }
+    }
+
+    /// Same as [`Self::get_active_notifications`], but for background tasks that
+    /// have nowhere to propagate the error to, so it's logged instead
+    async fn active_notifications_or_empty(&self) -> HashMap<u32, NewNotification> {
+        self.get_active_notifications().await.unwrap_or_else(|e| {
+            log::error!("Failed to load active notifications: {}", e);
+            HashMap::new()
+        })
     }
 
     async fn start_timers_for_newly_visible(
@@ -116,6 +256,10 @@ impl Scheduler {
                 continue;
             }
 
+            self.audit
+                .record(notification.id, &notification.uuid, audit::Event::Displayed)
+                .await;
+
             let timeout_ms = notification.timeout;
             // Timeout == 0 means that notification never expires
             // Timeout == -1 means that timeout should be chosen by notifications server
@@ -137,6 +281,89 @@ impl Scheduler {
     }
 }
 
+/// Forward messages published on `channel` to `tx` until the Pub/Sub connection
+/// is lost, returns `false` once `tx` is closed
+async fn forward_messages<T: DeserializeOwned + Send>(
+    mut pubsub: redis::aio::PubSub,
+    channel: &str,
+    tx: &mpsc::Sender<T>,
+) -> redis::RedisResult<bool> {
+    pubsub.subscribe(channel).await?;
+    let mut messages = pubsub.on_message();
+
+    while let Some(msg) = messages.next().await {
+        if let Ok(payload) = msg.get_payload::<String>()
+            && let Ok(value) = serde_json::from_str::<T>(&payload)
+            && tx.send(value).await.is_err()
+        {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Forward messages published on `channel` to `tx` until it's closed,
+/// resubscribing whenever the connection to Redis is lost
+async fn forward_pubsub<T: DeserializeOwned + Send>(
+    client: redis::Client,
+    channel: String,
+    tx: mpsc::Sender<T>,
+) {
+    let mut backoff = supervisor::Backoff::default();
+    let mut lost = false;
+
+    while !tx.is_closed() {
+        let result = match client.get_async_pubsub().await {
+            Ok(pubsub) => {
+                if lost {
+                    log::info!(
+                        "Redis Pub/Sub connection restored, resubscribed to {}",
+                        channel
+                    );
+                    lost = false;
+                }
+                backoff.reset();
+
+                forward_messages(pubsub, &channel, &tx).await
+            }
+            Err(e) => Err(e),
+        };
+
+        let error = match result {
+            Ok(false) => return,
+            Ok(true) => "connection closed".to_string(),
+            Err(e) => e.to_string(),
+        };
+        lost = true;
+
+        let delay = backoff.delay();
+        log::warn!(
+            "Lost Redis Pub/Sub subscription to {}: {}, resubscribing in {:?}",
+            channel,
+            error,
+            delay
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Address of the connection `request` came in on
+fn connection_address<T>(request: &Request<T>) -> error::Result<String> {
+    request
+        .remote_addr()
+        .map(|remote_addr| format!("{:?}", remote_addr))
+        .ok_or(Error::NoRemoteAddr)
+}
+
+/// Order in which clients list notifications, the order they were added to the
+/// notify stream in. Ties of ones without a stream ID are broken by ID so every
+/// connection sees the same order
+fn newest_first(a: &NewNotification, b: &NewNotification) -> std::cmp::Ordering {
+    let position = |n: &NewNotification| (stream_position(&n.stream_id), n.timestamp, n.id);
+    position(b).cmp(&position(a))
+}
+
 #[tonic::async_trait]
 impl ClientService for Scheduler {
     type NotifyStream = Pin<
@@ -151,18 +378,43 @@ impl ClientService for Scheduler {
         &self,
         request: Request<ClientNotifyRequest>,
     ) -> Result<Response<Self::NotifyStream>, Status> {
-        let remote_addr = request.remote_addr().unwrap();
+        let address = connection_address(&request)?;
         let req = request.into_inner();
 
-        let client_id = format!("{:?}", remote_addr);
+        // Clients from before the handshake don't send one and get the base protocol
+        let hello = req.hello.unwrap_or_default();
+        // Clients with an ID of their own get their viewport back when they reconnect,
+        // e.g. after the scheduler restarted
+        let resumable = !hello.client_id.is_empty();
+        let client_id = if resumable {
+            self.sessions
+                .lock()
+                .await
+                .insert(address.clone(), hello.client_id.clone());
+            hello.client_id.clone()
+        } else {
+            address.clone()
+        };
         log::info!(
-            "New client connection from: {} (client_id: {})",
-            remote_addr,
-            client_id
+            "New client connection: {} (protocol version {})",
+            client_id,
+            hello.protocol_version
         );
+        if hello.protocol_version > config::protocol::VERSION {
+            log::warn!(
+                "Client {} is newer than the scheduler (protocol version {} > {})",
+                client_id,
+                hello.protocol_version,
+                config::protocol::VERSION
+            );
+        }
+        let image_blobs = hello
+            .capabilities
+            .contains(&(Capability::ImageBlobs as i32));
 
         let state_manager = Arc::clone(&self.state_manager);
         let mut client_state = state_manager.load_state(&client_id).await;
+        let restored = client_state.range_end > 0;
 
         client_state.max_visible = req.max_visible as usize;
 
@@ -174,75 +426,83 @@ impl ClientService for Scheduler {
 
         let notification_sub_client = self.redis_client.clone();
         let close_notification_sub_client = self.redis_client.clone();
+        let notification_channel = self.keys.pubsub_notification.clone();
+        let close_notification_channel = self.keys.pubsub_close_notification.clone();
         let (notification_tx, mut notification_rx) = mpsc::channel(128);
         let (close_notification_tx, mut close_notification_rx) = mpsc::channel(128);
 
-        tokio::spawn(async move {
-            let notification_tx = notification_tx;
-            tokio::task::spawn_blocking(move || {
-                if let Ok(mut con) = notification_sub_client.get_connection() {
-                    let mut pubsub = con.as_pubsub();
-                    if pubsub.subscribe("moxnotify:pubsub:notification").is_ok() {
-                        while let Ok(msg) = pubsub.get_message() {
-                            if let Ok(payload) = msg.get_payload::<String>()
-                                && let Ok(notification) =
-                                    serde_json::from_str::<NewNotification>(&payload)
-                                && notification_tx.blocking_send(notification).is_err()
-                            {
-                                break;
-                            }
-                        }
-                    }
-                }
-            })
-            .await
-            .ok();
-        });
-
-        tokio::spawn(async move {
-            let close_notification_tx = close_notification_tx;
-            tokio::task::spawn_blocking(move || {
-                if let Ok(mut con) = close_notification_sub_client.get_connection() {
-                    let mut pubsub = con.as_pubsub();
-                    if pubsub
-                        .subscribe("moxnotify:pubsub:close_notification")
-                        .is_ok()
-                    {
-                        while let Ok(msg) = pubsub.get_message() {
-                            if let Ok(payload) = msg.get_payload::<String>()
-                                && let Ok(close_notification) =
-                                    serde_json::from_str::<CloseNotification>(&payload)
-                                && close_notification_tx
-                                    .blocking_send(close_notification)
-                                    .is_err()
-                            {
-                                break;
-                            }
-                        }
-                    }
-                }
-            })
-            .await
-            .ok();
-        });
+        let notification_reader = supervisor::spawn(
+            format!("notification reader for {}", client_id),
+            move || {
+                forward_pubsub::<NewNotification>(
+                    notification_sub_client.clone(),
+                    notification_channel.clone(),
+                    notification_tx.clone(),
+                )
+            },
+        );
+
+        let close_notification_reader = supervisor::spawn(
+            format!("close notification reader for {}", client_id),
+            move || {
+                forward_pubsub::<CloseNotification>(
+                    close_notification_sub_client.clone(),
+                    close_notification_channel.clone(),
+                    close_notification_tx.clone(),
+                )
+            },
+        );
 
         let (tx, stream_rx) = mpsc::channel(128);
 
-        let active_notifications = self.get_active_notifications().await;
+        let active_notifications = self.get_active_notifications().await?;
 
         let notifications = {
             let mut notifications: Vec<NewNotification> =
                 active_notifications.into_values().collect::<Vec<_>>();
-            notifications.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+            notifications.sort_by(|a, b| newest_first(a, b));
 
             Arc::new(notifications)
         };
 
+        let mut initial_view_range = ViewRange {
+            max_visible: client_state.max_visible,
+            start: client_state.range_start,
+            end: client_state.range_end,
+        };
+        if restored {
+            initial_view_range.resume(notifications.len());
+        } else {
+            initial_view_range.show_tail(notifications.len());
+        }
+
+        // Notifications shown right away keep what was left of their timers instead
+        // of starting them over when the next one arrives
+        let initially_visible: Vec<&NewNotification> = notifications
+            .iter()
+            .skip(initial_view_range.start())
+            .take(initial_view_range.width())
+            .collect();
+        for notification in &initially_visible {
+            if notification.timeout > 0 {
+                self.timeouts
+                    .resume_timer(
+                        notification.id,
+                        notification.uuid.clone(),
+                        std::time::Duration::from_millis(notification.timeout as u64),
+                    )
+                    .await;
+            }
+        }
+        client_state.prev_visible_ids = initially_visible.iter().map(|n| n.id).collect();
+
         {
             let tx = tx.clone();
             let scheduler = self.clone();
             let state_manager = Arc::clone(&self.state_manager);
             let client_id_clone = client_id.clone();
+            let sessions = Arc::clone(&self.sessions);
+            let address = address.clone();
 
             let redis_con = Arc::clone(&self.redis_con);
             let timeouts = Arc::clone(&self.timeouts);
@@ -255,25 +515,30 @@ impl ClientService for Scheduler {
             let mut local_selected_id = client_state.selected_id;
             let mut local_prev_visible_ids = client_state.prev_visible_ids.clone();
 
-            tokio::spawn(async move {
+            let forwarder = tokio::spawn(async move {
                 let mut receiver = timeouts.receiver();
                 let redis_con = redis_con;
 
                 loop {
                     tokio::select! {
-                        Some(notification) = notification_rx.recv() => {
+                        Some(mut notification) = notification_rx.recv() => {
+                            notification.latency.get_or_insert_default().scheduled_at = config::now_ms();
+                            if !image_blobs {
+                                scheduler.inline_image(&mut notification).await;
+                            }
+
                             let active_count = {
                                 let mut redis_con = redis_con.lock().await;
-                                let hash_data: HashMap<String, String> = redis_con.hgetall("moxnotify:active").await.unwrap_or_default();
+                                let hash_data: HashMap<String, String> = redis_con.hgetall(&scheduler.keys.active).await.unwrap_or_default();
                                 hash_data.len()
                             };
 
                             view_range.show_tail(active_count);
                             log::debug!("notify, range: {}", view_range);
 
-                            let active_notifications = scheduler.get_active_notifications().await;
+                            let active_notifications = scheduler.active_notifications_or_empty().await;
                             let mut notifications_vec: Vec<&NewNotification> = active_notifications.values().collect();
-                            notifications_vec.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+                            notifications_vec.sort_by(|a, b| newest_first(a, b));
 
                             let focused_ids: Vec<u32> = notifications_vec
                                 .iter()
@@ -298,34 +563,36 @@ impl ClientService for Scheduler {
                             state_manager.save_state(&client_id_clone, &state).await;
 
                             let message = NotificationMessage {
-                                message: Some(notification_message::Message::Notification(notification))
+                                message: Some(notification_message::Message::Notification(notification)),
+                                restored: false,
                             };
 
                             if tx.send(Ok(message)).await.is_err() {
-                                log::info!("Client disconnected: {:?}", remote_addr);
+                                log::info!("Client disconnected: {}", client_id_clone);
                                 break;
                             }
                         }
                         Some(close_notification) = close_notification_rx.recv() => {
                             let message = NotificationMessage {
-                                message: Some(notification_message::Message::CloseNotification(close_notification))
+                                message: Some(notification_message::Message::CloseNotification(close_notification)),
+                                restored: false,
                             };
 
                             if tx.send(Ok(message)).await.is_err() {
-                                log::info!("Client disconnected: {:?}", remote_addr);
+                                log::info!("Client disconnected: {}", client_id_clone);
                                 break;
                             }
 
                             let mut redis_con = redis_con.lock().await;
-                            let hash_data: HashMap<String, String> = redis_con.hgetall("moxnotify:active").await.unwrap_or_default();
+                            let hash_data: HashMap<String, String> = redis_con.hgetall(&scheduler.keys.active).await.unwrap_or_default();
                             let remaining_count = hash_data.len();
                             drop(redis_con);
 
                             view_range.show_tail(remaining_count);
 
-                            let active_notifications = scheduler.get_active_notifications().await;
+                            let active_notifications = scheduler.active_notifications_or_empty().await;
                             let mut notifications_vec: Vec<&NewNotification> = active_notifications.values().collect();
-                            notifications_vec.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+                            notifications_vec.sort_by(|a, b| newest_first(a, b));
 
                             let focused_ids: Vec<u32> = notifications_vec
                                 .iter()
@@ -351,17 +618,18 @@ impl ClientService for Scheduler {
                         }
                         Ok((id, uuid)) = receiver.recv() => {
                             let message = NotificationMessage {
-                                message: Some(notification_message::Message::CloseNotification(CloseNotification { id }))
+                                message: Some(notification_message::Message::CloseNotification(CloseNotification { id })),
+                                restored: false,
                             };
 
                             if tx.send(Ok(message)).await.is_err() {
-                                log::info!("Client disconnected: {:?}", remote_addr);
+                                log::info!("Client disconnected: {}", client_id_clone);
                                 break;
                             }
 
-                            let active_notifications = scheduler.get_active_notifications().await;
+                            let active_notifications = scheduler.active_notifications_or_empty().await;
                             let mut notifications_vec: Vec<&NewNotification> = active_notifications.values().collect();
-                            notifications_vec.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+                            notifications_vec.sort_by(|a, b| newest_first(a, b));
 
                             if let Some(selected) = local_selected_id
                                 && selected == id
@@ -373,6 +641,8 @@ impl ClientService for Scheduler {
                                     .and_then(|idx| notifications_vec.get(idx).map(|n| n.id));
                             }
 
+                            scheduler.audit.record(id, &uuid, audit::Event::Expired).await;
+
                             let mut redis_con = redis_con.lock().await;
 
                             let closed = NotificationClosed {
@@ -381,32 +651,36 @@ impl ClientService for Scheduler {
                                 uuid,
                             };
 
-                            let json = serde_json::to_string(&closed).unwrap();
-                            if let Err(e) = AsyncTypedCommands::xadd(
-                                &mut *redis_con,
-                                "moxnotify:notification_closed",
-                                "*",
-                                &[("notification", json.as_str())],
-                            )
-                            .await
-                            {
-                                log::error!("Failed to write notification_closed to Redis: {}", e);
+                            match serde_json::to_string(&closed) {
+                                Ok(json) => {
+                                    if let Err(e) = AsyncTypedCommands::xadd(
+                                        &mut *redis_con,
+                                        &scheduler.keys.notification_closed,
+                                        "*",
+                                        &[("notification", json.as_str())],
+                                    )
+                                    .await
+                                    {
+                                        log::error!("Failed to write notification_closed to Redis: {}", e);
+                                    }
+                                }
+                                Err(e) => log::error!("Failed to serialize notification_closed: {}", e),
                             }
 
-                            if let Err(e) = AsyncTypedCommands::hdel(&mut *redis_con, "moxnotify:active", id.to_string().as_str()).await {
+                            if let Err(e) = AsyncTypedCommands::hdel(&mut *redis_con, &scheduler.keys.active, id.to_string().as_str()).await {
                                 log::warn!("Failed to remove notification from active HASH: {}", e);
                             }
 
-                            let hash_data: HashMap<String, String> = AsyncTypedCommands::hgetall(&mut *redis_con, "moxnotify:active").await.unwrap_or_default();
+                            let hash_data: HashMap<String, String> = AsyncTypedCommands::hgetall(&mut *redis_con, &scheduler.keys.active).await.unwrap_or_default();
                             let remaining_count = hash_data.len();
                             drop(redis_con);
 
                             view_range.show_tail(remaining_count);
                             log::debug!("Notification {id} expired, range: {}", view_range);
 
-                            let active_notifications = scheduler.get_active_notifications().await;
+                            let active_notifications = scheduler.active_notifications_or_empty().await;
                             let mut notifications_vec: Vec<&NewNotification> = active_notifications.values().collect();
-                            notifications_vec.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+                            notifications_vec.sort_by(|a, b| newest_first(a, b));
 
                             let focused_ids: Vec<u32> = notifications_vec
                                 .iter()
@@ -434,16 +708,17 @@ impl ClientService for Scheduler {
                     }
                 }
 
-                state_manager.delete_state(&client_id_clone).await;
+                if resumable {
+                    sessions.lock().await.remove(&address);
+                } else {
+                    state_manager.delete_state(&client_id_clone).await;
+                }
+                notification_reader.abort();
+                close_notification_reader.abort();
             });
+            supervisor::watch(format!("forwarder for {}", client_id), forwarder);
         }
 
-        let mut initial_view_range = ViewRange {
-            max_visible: client_state.max_visible,
-            start: client_state.range_start,
-            end: client_state.range_end,
-        };
-        initial_view_range.show_tail(notifications.len());
         let state = ClientState {
             selected_id: client_state.selected_id,
             range_start: initial_view_range.start(),
@@ -454,14 +729,18 @@ impl ClientService for Scheduler {
         self.state_manager.save_state(&client_id, &state).await;
 
         for notification in notifications.iter().rev() {
+            let mut notification = notification.to_owned();
+            if !image_blobs {
+                self.inline_image(&mut notification).await;
+            }
+
             let message = NotificationMessage {
-                message: Some(notification_message::Message::Notification(
-                    notification.to_owned(),
-                )),
+                message: Some(notification_message::Message::Notification(notification)),
+                restored: true,
             };
 
             if tx.send(Ok(message)).await.is_err() {
-                log::info!("Client disconnected during initial sync: {:?}", remote_addr);
+                log::info!("Client disconnected during initial sync: {}", client_id);
                 break;
             }
         }
@@ -474,9 +753,11 @@ impl ClientService for Scheduler {
         &self,
         request: Request<ClientNotificationClosedRequest>,
     ) -> Result<Response<ClientNotificationClosedResponse>, Status> {
-        let remote_addr = request.remote_addr().unwrap();
-        let client_id = format!("{:?}", remote_addr);
-        let closed = request.into_inner().notification_closed.unwrap();
+        let client_id = self.client_id(&request).await?;
+        let closed = request
+            .into_inner()
+            .notification_closed
+            .ok_or(Error::MissingField("notification_closed"))?;
         log::info!(
             "Received notification_closed request: id: {}, reason: {:?}, client: {}",
             closed.id,
@@ -485,11 +766,20 @@ impl ClientService for Scheduler {
         );
 
         self.timeouts.stop(closed.id).await;
+        self.audit
+            .record(
+                closed.id,
+                &closed.uuid,
+                audit::Event::Closed {
+                    reason: closed.reason().as_str_name().to_string(),
+                },
+            )
+            .await;
 
-        let active_notifications = self.get_active_notifications().await;
+        let active_notifications = self.get_active_notifications().await?;
 
         let mut notifications: Vec<&NewNotification> = active_notifications.values().collect();
-        notifications.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        notifications.sort_by(|a, b| newest_first(a, b));
 
         let mut client_state = self.state_manager.load_state(&client_id).await;
 
@@ -503,11 +793,11 @@ impl ClientService for Scheduler {
                 .and_then(|idx| notifications.get(idx).map(|n| n.id));
         }
 
+        let json = serde_json::to_string(&closed).map_err(Error::from)?;
         let mut con = self.redis_con.lock().await;
-        let json = serde_json::to_string(&closed).unwrap();
         if let Err(e) = AsyncTypedCommands::xadd(
             &mut *con,
-            "moxnotify:notification_closed",
+            &self.keys.notification_closed,
             "*",
             &[("notification", json.as_str())],
         )
@@ -518,7 +808,7 @@ impl ClientService for Scheduler {
 
         let id_str = closed.id.to_string();
         if let Err(e) =
-            AsyncTypedCommands::hdel(&mut *con, "moxnotify:active", id_str.as_str()).await
+            AsyncTypedCommands::hdel(&mut *con, &self.keys.active, id_str.as_str()).await
         {
             log::warn!("Failed to remove notification from active HASH: {}", e);
         }
@@ -560,24 +850,22 @@ impl ClientService for Scheduler {
         &self,
         request: Request<ClientActionInvokedRequest>,
     ) -> Result<Response<ClientActionInvokedResponse>, Status> {
-        let invoked = request.into_inner().action_invoked.unwrap();
+        let invoked = request
+            .into_inner()
+            .action_invoked
+            .ok_or(Error::MissingField("action_invoked"))?;
         log::info!(
             "Received action_invoked request: id: {}, key: {}",
             invoked.id,
             invoked.action_key
         );
 
-        let mut con = self.redis_con.lock().await;
-        let json = serde_json::to_string(&invoked).unwrap();
-        if let Err(e) = AsyncTypedCommands::xadd(
-            &mut *con,
-            "moxnotify:action_invoked",
-            "*",
-            &[("action", json.as_str())],
-        )
-        .await
+        self.invoke_action(&invoked).await?;
+
+        if self.frecency.enabled
+            && let Err(e) = self.record_frecency(&invoked).await
         {
-            log::error!("Failed to write action_invoked to Redis: {}", e);
+            log::warn!("Failed to record action frecency: {}", e);
         }
 
         Ok(Response::new(ClientActionInvokedResponse {}))
@@ -587,13 +875,14 @@ impl ClientService for Scheduler {
         &self,
         request: Request<ViewportNavigationRequest>,
     ) -> Result<Response<ViewportNavigationResponse>, Status> {
-        let remote_addr = request.remote_addr().unwrap();
-        let client_id = format!("{:?}", remote_addr);
+        let client_id = self.client_id(&request).await?;
         let req = request.into_inner();
-        let active_notifications = self.get_active_notifications().await;
+        let direction = Direction::try_from(req.direction)
+            .map_err(|_| Error::UnknownDirection(req.direction))?;
+        let active_notifications = self.get_active_notifications().await?;
 
         let mut notifications: Vec<&NewNotification> = active_notifications.values().collect();
-        notifications.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        notifications.sort_by(|a, b| newest_first(a, b));
 
         let mut client_state = self.state_manager.load_state(&client_id).await;
         let mut view_range = ViewRange {
@@ -602,51 +891,56 @@ impl ClientService for Scheduler {
             end: client_state.range_end,
         };
         let mut selected_id = client_state.selected_id;
-        match Direction::try_from(req.direction).unwrap() {
-            Direction::Prev => {
-                if let Some(selected) = selected_id
-                    && let Some(pos) = notifications.iter().position(|n| n.id == selected)
-                {
-                    let idx = pos
-                        .checked_add(1)
-                        .filter(|&i| i < notifications.len())
-                        .unwrap_or(0);
+        // Held keys are batched into one request moving several steps, every
+        // notification's worth of steps wraps back to where it started
+        let steps = (req.count.max(1) as usize - 1) % notifications.len().max(1) + 1;
+        for _ in 0..steps {
+            match direction {
+                Direction::Prev => {
+                    if let Some(selected) = selected_id
+                        && let Some(pos) = notifications.iter().position(|n| n.id == selected)
+                    {
+                        let idx = pos
+                            .checked_add(1)
+                            .filter(|&i| i < notifications.len())
+                            .unwrap_or(0);
 
-                    selected_id = notifications.get(idx).map(|n| n.id);
+                        selected_id = notifications.get(idx).map(|n| n.id);
 
-                    view_range.ensure_visible_down(idx);
-                } else if let Some(first) = notifications.first() {
-                    selected_id = Some(first.id);
+                        view_range.ensure_visible_down(idx);
+                    } else if let Some(first) = notifications.first() {
+                        selected_id = Some(first.id);
 
-                    view_range.show_tail(notifications.len());
+                        view_range.show_tail(notifications.len());
+                    }
+                    log::debug!("Direction::Prev, range: {}", view_range);
                 }
-                log::debug!("Direction::Prev, range: {}", view_range);
-            }
-            Direction::Next => {
-                if let Some(selected) = selected_id
-                    && let Some(pos) = notifications.iter().position(|n| n.id == selected)
-                {
-                    let idx = pos.checked_sub(1).unwrap_or(notifications.len() - 1);
+                Direction::Next => {
+                    if let Some(selected) = selected_id
+                        && let Some(pos) = notifications.iter().position(|n| n.id == selected)
+                    {
+                        let idx = pos.checked_sub(1).unwrap_or(notifications.len() - 1);
 
-                    selected_id = notifications.get(idx).map(|n| n.id);
+                        selected_id = notifications.get(idx).map(|n| n.id);
 
-                    view_range.ensure_visible_up(idx, notifications.len());
-                } else if let Some(last) = notifications.last() {
-                    selected_id = Some(last.id);
+                        view_range.ensure_visible_up(idx, notifications.len());
+                    } else if let Some(last) = notifications.last() {
+                        selected_id = Some(last.id);
 
+                        view_range.show_head();
+                    }
+                    log::debug!("Direction::Next, range: {}", view_range);
+                }
+                Direction::First => {
+                    selected_id = notifications.last().map(|n| n.id);
+                    view_range.show_tail(notifications.len());
+                    log::debug!("Direction::First, range: {}", view_range);
+                }
+                Direction::Last => {
+                    selected_id = notifications.first().map(|n| n.id);
                     view_range.show_head();
+                    log::debug!("Direction::Last, range: {}", view_range);
                 }
-                log::debug!("Direction::Next, range: {}", view_range);
-            }
-            Direction::First => {
-                selected_id = notifications.last().map(|n| n.id);
-                view_range.show_tail(notifications.len());
-                log::debug!("Direction::First, range: {}", view_range);
-            }
-            Direction::Last => {
-                selected_id = notifications.first().map(|n| n.id);
-                view_range.show_head();
-                log::debug!("Direction::Last, range: {}", view_range);
             }
         }
 
@@ -658,6 +952,14 @@ impl ClientService for Scheduler {
             .collect();
 
         let selected_id_val = selected_id;
+        if let Some(selected) = selected_id_val
+            && client_state.selected_id != Some(selected)
+            && let Some(notification) = notifications.iter().find(|n| n.id == selected)
+        {
+            self.audit
+                .record(selected, &notification.uuid, audit::Event::Selected)
+                .await;
+        }
         self.start_timers_for_newly_visible(
             &notifications,
             &focused_ids,
@@ -677,6 +979,7 @@ impl ClientService for Scheduler {
             before_count: notifications.len().saturating_sub(view_range.end()) as u32,
             after_count: view_range.start() as u32,
             selected_id: selected_id_val,
+            selecting: true,
         }))
     }
 
@@ -684,12 +987,11 @@ impl ClientService for Scheduler {
         &self,
         request: Request<GetViewportRequest>,
     ) -> Result<Response<ViewportNavigationResponse>, Status> {
-        let remote_addr = request.remote_addr().unwrap();
-        let client_id = format!("{:?}", remote_addr);
-        let active_notifications = self.get_active_notifications().await;
+        let client_id = self.client_id(&request).await?;
+        let active_notifications = self.get_active_notifications().await?;
 
         let mut notifications: Vec<&NewNotification> = active_notifications.values().collect();
-        notifications.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        notifications.sort_by(|a, b| newest_first(a, b));
 
         let client_state = self.state_manager.load_state(&client_id).await;
         let view_range = ViewRange {
@@ -710,6 +1012,7 @@ impl ClientService for Scheduler {
             before_count: notifications.len().saturating_sub(view_range.end()) as u32,
             after_count: view_range.start() as u32,
             selected_id: client_state.selected_id,
+            selecting: self.state_manager.selecting(&client_id).await,
         }))
     }
 
@@ -717,12 +1020,11 @@ impl ClientService for Scheduler {
         &self,
         request: Request<RestartTimersRequest>,
     ) -> Result<Response<RestartTimersResponse>, Status> {
-        let remote_addr = request.remote_addr().unwrap();
-        let client_id = format!("{:?}", remote_addr);
-        let active_notifications = self.get_active_notifications().await;
+        let client_id = self.client_id(&request).await?;
+        let active_notifications = self.get_active_notifications().await?;
 
         let mut notifications: Vec<&NewNotification> = active_notifications.values().collect();
-        notifications.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        notifications.sort_by(|a, b| newest_first(a, b));
 
         let client_state = self.state_manager.load_state(&client_id).await;
         let view_range = ViewRange {
@@ -757,6 +1059,8 @@ impl ClientService for Scheduler {
             }
         }
 
+        self.state_manager.set_selecting(&client_id, false).await;
+
         Ok(Response::new(RestartTimersResponse {}))
     }
 
@@ -764,12 +1068,11 @@ impl ClientService for Scheduler {
         &self,
         request: Request<StopTimersRequest>,
     ) -> Result<Response<StopTimersResponse>, Status> {
-        let remote_addr = request.remote_addr().unwrap();
-        let client_id = format!("{:?}", remote_addr);
-        let active_notifications = self.get_active_notifications().await;
+        let client_id = self.client_id(&request).await?;
+        let active_notifications = self.get_active_notifications().await?;
 
         let mut notifications: Vec<&NewNotification> = active_notifications.values().collect();
-        notifications.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        notifications.sort_by(|a, b| newest_first(a, b));
 
         let client_state = self.state_manager.load_state(&client_id).await;
         let view_range = ViewRange {
@@ -798,8 +1101,126 @@ impl ClientService for Scheduler {
             }
         }
 
+        self.state_manager.set_selecting(&client_id, true).await;
+
         Ok(Response::new(StopTimersResponse {}))
     }
+
+    async fn get_blob(
+        &self,
+        request: Request<GetBlobRequest>,
+    ) -> Result<Response<GetBlobResponse>, Status> {
+        let key = request.into_inner().key;
+        log::debug!("Received get_blob request: key: {}", key);
+
+        let mut con = self.redis_con.lock().await;
+        let data =
+            redis::AsyncCommands::get::<String, Option<Vec<u8>>>(&mut *con, self.keys.blob(&key))
+                .await
+                .map_err(|e| Status::internal(format!("Failed to read blob {key}: {e}")))?;
+
+        match data {
+            Some(data) => Ok(Response::new(GetBlobResponse { data })),
+            None => Err(Status::with_error_details(
+                Code::NotFound,
+                format!("Blob {key} not found"),
+                ErrorDetails::with_resource_info("blob", key, "", "Expired or never stored"),
+            )),
+        }
+    }
+
+    async fn mute_app(
+        &self,
+        request: Request<MuteAppRequest>,
+    ) -> Result<Response<MuteAppResponse>, Status> {
+        let MuteAppRequest { app_name, seconds } = request.into_inner();
+        if app_name.is_empty() || seconds == 0 {
+            return Err(Status::invalid_argument(
+                "Muting needs an app name and a duration",
+            ));
+        }
+        log::info!("Muting app '{}' for {}s", app_name, seconds);
+
+        // The control plane drops the app's notifications for as long as the key exists
+        let mut con = self.redis_con.lock().await;
+        redis::AsyncCommands::set_ex::<String, &str, ()>(
+            &mut *con,
+            self.keys.mute(&app_name),
+            "1",
+            seconds.into(),
+        )
+        .await
+        .map_err(|e| Status::internal(format!("Failed to mute {app_name}: {e}")))?;
+
+        Ok(Response::new(MuteAppResponse {}))
+    }
+}
+
+async fn handle_notify(
+    con: &mut redis::aio::ConnectionManager,
+    keys: &Keys,
+    retention: &config::Retention,
+    stream_id: &str,
+    map: &HashMap<String, redis::Value>,
+) -> error::Result<()> {
+    let Some(json) = config::stream::notification_payload(map)? else {
+        return Ok(());
+    };
+    let mut notification: NewNotification = serde_json::from_slice(&json)?;
+    notification.stream_id = stream_id.to_string();
+
+    log::info!(
+        "Scheduling notification: id={}, app_name='{}', summary={}",
+        notification.id,
+        notification.app_name,
+        logging::content(&notification.summary)
+    );
+
+    let json = serde_json::to_string(&notification)?;
+    redis::AsyncCommands::publish::<&str, &str, usize>(&mut *con, &keys.pubsub_notification, &json)
+        .await?;
+
+    // The indexer skips apps excluded from history, so their content isn't kept in
+    // the stream once shown. An entry deleted before the indexer read it is skipped
+    if !retention.keeps(&notification.app_name) {
+        AsyncTypedCommands::xdel(&mut *con, &keys.notify, &[stream_id]).await?;
+    }
+
+    Ok(())
+}
+
+async fn handle_close_notification(
+    con: &mut redis::aio::ConnectionManager,
+    keys: &Keys,
+    timeouts: &Arc<TimeoutScheduler>,
+    map: &HashMap<String, redis::Value>,
+) -> error::Result<()> {
+    let Some(redis::Value::BulkString(json)) = map.get("close_notification") else {
+        return Ok(());
+    };
+    let close_notification: CloseNotification = serde_json::from_str(std::str::from_utf8(json)?)?;
+
+    log::info!(
+        "Broadcasting close_notification to clients: id={}",
+        close_notification.id
+    );
+
+    let timeouts = Arc::clone(timeouts);
+    let id = close_notification.id;
+    tokio::spawn(async move {
+        timeouts.stop(id).await;
+    });
+
+    let id_str = close_notification.id.to_string();
+    if let Err(e) = con.hdel(&keys.active, id_str.as_str()).await {
+        log::warn!("Failed to remove notification from active HASH: {}", e);
+    }
+
+    let json = serde_json::to_string(&close_notification)?;
+    con.publish::<&str, &str>(&keys.pubsub_close_notification, &json)
+        .await?;
+
+    Ok(())
 }
 
 #[derive(Parser)]
@@ -823,32 +1244,81 @@ async fn main() -> anyhow::Result<()> {
     env_logger::Builder::new()
         .filter(Some("scheduler"), config.indexer.log_level.into())
         .init();
+    logging::set_log_content(config.log_content);
 
     log::info!("Connecting to Redis and subscribing to notifications...");
 
+    let keys = Arc::new(config.redis.keys());
     let client = redis::Client::open(&*config.redis.address)?;
-    let write_con = client.get_multiplexed_async_connection().await?;
-    let read_con = client.get_multiplexed_async_connection().await?;
-    let scheduler = Scheduler::new(write_con, client.clone()).await;
+    // Reconnect on their own once the connection is lost. Reads block until something
+    // arrives, so that connection mustn't time out waiting for the response
+    let write_con = client.get_connection_manager().await?;
+    let read_con = client
+        .get_connection_manager_with_config(
+            ConnectionManagerConfig::new().set_response_timeout(None),
+        )
+        .await?;
+    let audit = AuditLog::new(
+        client.get_multiplexed_async_connection().await?,
+        "scheduler",
+        keys.audit.clone(),
+        &config.audit,
+    );
+    let scheduler = Scheduler::new(
+        write_con,
+        client.clone(),
+        Arc::clone(&keys),
+        audit,
+        config.scheduler.expire_action_apps.clone().into(),
+        config.frecency,
+    )
+    .await?;
     let timeouts = Arc::clone(&scheduler.timeouts);
 
-    let server_addr = config.scheduler.address.parse()?;
-    tokio::spawn(async move {
-        log::info!("Scheduler server listening on {}", server_addr);
-        Server::builder()
-            .add_service(ClientServiceServer::new(scheduler))
-            .serve(server_addr)
-            .await
-            .expect("Server failed to start");
+    let mut client_service =
+        ClientServiceServer::new(scheduler).accept_compressed(CompressionEncoding::Zstd);
+    if config.scheduler.compression == Compression::Zstd {
+        client_service = client_service.send_compressed(CompressionEncoding::Zstd);
+    }
+
+    let reflection_service = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(moxnotify::FILE_DESCRIPTOR_SET)
+        .build_v1()?;
+
+    let listener = supervisor::listener(config.scheduler.address.parse()?)?;
+    supervisor::spawn("scheduler server", move || {
+        let client_service = client_service.clone();
+        let reflection_service = reflection_service.clone();
+        let listener = listener.try_clone();
+        async move {
+            let listener = match listener.and_then(tokio::net::TcpListener::from_std) {
+                Ok(listener) => listener,
+                Err(e) => {
+                    log::error!("Scheduler server failed to listen: {}", e);
+                    return;
+                }
+            };
+            if let Ok(addr) = listener.local_addr() {
+                log::info!("Scheduler server listening on {}", addr);
+            }
+            if let Err(e) = Server::builder()
+                .add_service(client_service)
+                .add_service(reflection_service)
+                .serve_with_incoming(TcpListenerStream::new(listener))
+                .await
+            {
+                log::error!("Scheduler server failed: {}", e);
+            }
+        }
     });
 
     log::info!("Subscribed to notifications from Redis stream");
 
     let mut con = read_con;
     loop {
-        if let Ok(Some(streams)) = AsyncTypedCommands::xread_options(
+        let streams = match AsyncTypedCommands::xread_options(
             &mut con,
-            &["moxnotify:notify", "moxnotify:close_notification"],
+            &[&keys.notify, &keys.close_notification],
             &[">", ">"],
             &StreamReadOptions::default()
                 .group("scheduler-group", "scheduler-1")
@@ -856,98 +1326,56 @@ async fn main() -> anyhow::Result<()> {
         )
         .await
         {
-            for stream_key in &streams.keys {
-                for stream_id in &stream_key.ids {
-                    match stream_key.key.as_str() {
-                        "moxnotify:notify" => {
-                            if let Some(redis::Value::BulkString(json)) =
-                                stream_id.map.get("notification")
-                            {
-                                let json = std::str::from_utf8(json).unwrap();
-                                let notification: NewNotification =
-                                    serde_json::from_str(json).unwrap();
-
-                                log::info!(
-                                    "Scheduling notification: id={}, app_name='{}', summary='{}'",
-                                    notification.id,
-                                    notification.app_name,
-                                    notification.summary
-                                );
-
-                                let json = serde_json::to_string(&notification).unwrap();
-
-                                if let Err(e) = redis::AsyncCommands::publish::<&str, &str, usize>(
-                                    &mut con,
-                                    "moxnotify:pubsub:notification",
-                                    &json,
-                                )
-                                .await
-                                {
-                                    log::error!(
-                                        "Failed to publish notification to Redis Pub/Sub: {}",
-                                        e
-                                    );
-                                    continue;
-                                }
-                            }
-                        }
-                        "moxnotify:close_notification" => {
-                            if let Some(redis::Value::BulkString(json)) =
-                                stream_id.map.get("close_notification")
-                            {
-                                let json = std::str::from_utf8(json).unwrap();
-                                let close_notification: CloseNotification =
-                                    serde_json::from_str(json).unwrap();
-
-                                log::info!(
-                                    "Broadcasting close_notification to clients: id={}",
-                                    close_notification.id
-                                );
-
-                                let timeouts = Arc::clone(&timeouts);
-                                let id = close_notification.id;
-                                tokio::spawn(async move {
-                                    timeouts.stop(id).await;
-                                });
-
-                                let id_str = close_notification.id.to_string();
-                                if let Err(e) = con.hdel("moxnotify:active", id_str.as_str()).await
-                                {
-                                    log::warn!(
-                                        "Failed to remove notification from active HASH: {}",
-                                        e
-                                    );
-                                }
+            Ok(Some(streams)) => streams,
+            Ok(None) => continue,
+            Err(e) => {
+                log::error!("Failed to read from Redis streams: {}", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
 
-                                let json = serde_json::to_string(&close_notification).unwrap();
-                                if let Err(e) = con
-                                    .publish::<&str, &str>(
-                                        "moxnotify:pubsub:close_notification",
-                                        &json,
-                                    )
-                                    .await
-                                {
-                                    log::error!(
-                                        "Failed to publish close_notification to Redis Pub/Sub: {}",
-                                        e
-                                    );
-                                    continue;
-                                }
-                            }
-                        }
-                        _ => unreachable!(),
+        for stream_key in &streams.keys {
+            for stream_id in &stream_key.ids {
+                let result = match stream_key.key.as_str() {
+                    key if key == keys.notify => {
+                        handle_notify(
+                            &mut con,
+                            &keys,
+                            &config.janitor.retention,
+                            &stream_id.id,
+                            &stream_id.map,
+                        )
+                        .await
                     }
-
-                    if let Err(e) = AsyncTypedCommands::xack(
-                        &mut con,
-                        stream_key.key.as_str(),
-                        "scheduler-group",
-                        &[stream_id.id.as_str()],
-                    )
-                    .await
-                    {
-                        log::error!("Failed to ACK message: {}", e);
+                    key if key == keys.close_notification => {
+                        handle_close_notification(&mut con, &keys, &timeouts, &stream_id.map).await
                     }
+                    _ => unreachable!(),
+                };
+
+                if let Err(e) = result {
+                    log::error!(
+                        "Failed to handle {} entry {}: {}",
+                        stream_key.key,
+                        stream_id.id,
+                        e
+                    );
+                    // Malformed entries are acknowledged so they don't stay pending forever
+                    if !e.is_malformed() {
+                        continue;
+                    }
+                }
+
+                if let Err(e) = AsyncTypedCommands::xack(
+                    &mut con,
+                    stream_key.key.as_str(),
+                    "scheduler-group",
+                    &[stream_id.id.as_str()],
+                )
+                .await
+                {
+                    log::error!("Failed to ACK message: {}", e);
                 }
             }
         }
@@ -1,8 +1,13 @@
+use crate::moxnotify::types::{ActionInvoked, NewNotification};
+use audit::AuditLog;
+use config::error;
+use config::keys::Keys;
 use redis::AsyncTypedCommands;
 use std::sync::Arc;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::Duration;
 use tokio::{
     sync::{Mutex, broadcast, watch},
+    task::JoinHandle,
     time,
 };
 
@@ -15,46 +20,143 @@ const POP_EXPIRED_TIMERS_SCRIPT: &str = r#"
     return timers
 "#;
 
+/// The action a filter set to run when `notification` expires, as long as its app
+/// is allowed to and the action exists
+fn expire_action(
+    notification: &NewNotification,
+    expire_action_apps: &[String],
+) -> Option<ActionInvoked> {
+    let action_key = notification.hints.as_ref()?.expire_action.as_ref()?;
+
+    if !expire_action_apps.contains(&notification.app_name) {
+        log::warn!(
+            "Not invoking '{}' on expiry of notification {}, '{}' isn't in expire_action_apps",
+            action_key,
+            notification.id,
+            notification.app_name
+        );
+        return None;
+    }
+
+    if !notification
+        .actions
+        .iter()
+        .any(|action| action.key == *action_key)
+    {
+        log::warn!(
+            "Notification {} has no action '{}' to invoke on expiry",
+            notification.id,
+            action_key
+        );
+        return None;
+    }
+
+    log::info!(
+        "Invoking '{}' on expiry of notification {}",
+        action_key,
+        notification.id
+    );
+
+    Some(ActionInvoked {
+        id: notification.id,
+        action_key: action_key.clone(),
+        token: String::new(),
+        uuid: notification.uuid.clone(),
+    })
+}
+
+/// Popping timers is atomic, so this runs once per expiry however many clients
+/// are connected
+struct Expiry {
+    keys: Arc<Keys>,
+    audit: AuditLog,
+    expire_action_apps: Arc<[String]>,
+}
+
+impl Expiry {
+    async fn invoke_action(
+        &self,
+        con: &mut redis::aio::ConnectionManager,
+        id: u32,
+        uuid: &str,
+    ) -> error::Result<()> {
+        let Some(json) =
+            AsyncTypedCommands::hget(&mut *con, &self.keys.active, id.to_string()).await?
+        else {
+            return Ok(());
+        };
+        let notification = serde_json::from_str::<NewNotification>(&json)?;
+        // The ID may have been reused since the timer started
+        if notification.uuid != uuid {
+            return Ok(());
+        }
+
+        match expire_action(&notification, &self.expire_action_apps) {
+            Some(invoked) => crate::write_action(con, &self.keys, &self.audit, &invoked).await,
+            None => Ok(()),
+        }
+    }
+}
+
 pub struct TimeoutScheduler {
     sender: broadcast::Sender<(u32, String)>,
-    redis_con: Arc<Mutex<redis::aio::MultiplexedConnection>>,
-    shutdown_tx: Option<tokio::sync::oneshot::Sender<()>>,
+    redis_con: Arc<Mutex<redis::aio::ConnectionManager>>,
+    keys: Arc<Keys>,
+    timer_task: JoinHandle<()>,
 }
 
 impl TimeoutScheduler {
-    pub fn new(redis_con: redis::aio::MultiplexedConnection) -> Self {
+    pub fn new(
+        redis_con: redis::aio::ConnectionManager,
+        keys: Arc<Keys>,
+        audit: AuditLog,
+        expire_action_apps: Arc<[String]>,
+    ) -> Self {
         let (sender, _) = broadcast::channel(32);
         let (global_pause, _) = watch::channel(false);
         let redis_con = Arc::new(Mutex::new(redis_con));
-        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
         let pop_script = redis::Script::new(POP_EXPIRED_TIMERS_SCRIPT);
 
         let timer_redis_con = Arc::clone(&redis_con);
         let timer_sender = sender.clone();
-        let mut timer_pause = global_pause.subscribe();
-        let timer_pop_script = pop_script.clone();
-
-        tokio::spawn(async move {
-            let mut interval = time::interval(Duration::from_millis(100));
-            interval.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
-            let mut shutdown_rx = shutdown_rx;
-
-            loop {
-                tokio::select! {
-                    _ = interval.tick() => {
-                        if !*timer_pause.borrow() {
-                            Self::process_expired_timers(
-                                &timer_redis_con,
-                                &timer_sender,
-                                &timer_pop_script,
-                            ).await;
+        let timer_pause = global_pause.subscribe();
+        let timer_keys = Arc::clone(&keys);
+        let expiry = Arc::new(Expiry {
+            keys: Arc::clone(&keys),
+            audit,
+            expire_action_apps,
+        });
+
+        let timer_task = supervisor::spawn("timeout scheduler", move || {
+            let timer_redis_con = Arc::clone(&timer_redis_con);
+            let timer_sender = timer_sender.clone();
+            let mut timer_pause = timer_pause.clone();
+            let timer_pop_script = pop_script.clone();
+            let timer_keys = Arc::clone(&timer_keys);
+            let expiry = Arc::clone(&expiry);
+
+            async move {
+                let mut interval = time::interval(Duration::from_millis(100));
+                interval.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
+
+                loop {
+                    tokio::select! {
+                        _ = interval.tick() => {
+                            let paused = *timer_pause.borrow();
+                            if !paused
+                                && let Err(e) = Self::process_expired_timers(
+                                    &timer_redis_con,
+                                    &timer_keys,
+                                    &timer_sender,
+                                    &timer_pop_script,
+                                    &expiry,
+                                ).await
+                            {
+                                log::error!("Failed to process expired timers: {}", e);
+                            }
                         }
+                        _ = timer_pause.changed() => {}
                     }
-                    _ = &mut shutdown_rx => {
-                        log::debug!("Timer background task shutting down");
-                        break;
-                    }
-                    _ = timer_pause.changed() => {}
                 }
             }
         });
@@ -62,38 +164,37 @@ impl TimeoutScheduler {
         Self {
             sender,
             redis_con,
-            shutdown_tx: Some(shutdown_tx),
+            keys,
+            timer_task,
         }
     }
 
     async fn process_expired_timers(
-        redis_con: &Arc<Mutex<redis::aio::MultiplexedConnection>>,
+        redis_con: &Arc<Mutex<redis::aio::ConnectionManager>>,
+        keys: &Keys,
         sender: &broadcast::Sender<(u32, String)>,
         pop_script: &redis::Script,
-    ) {
-        let now_ms = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as i64;
+        expiry: &Expiry,
+    ) -> error::Result<()> {
+        let now_ms = config::now_ms();
 
         let mut con = redis_con.lock().await;
 
         let expired_timers: Vec<String> = pop_script
-            .key("moxnotify:timers")
+            .key(&keys.timers)
             .arg(now_ms)
             .invoke_async::<Vec<String>>(&mut *con)
-            .await
-            .unwrap();
+            .await?;
 
         if expired_timers.is_empty() {
-            return;
+            return Ok(());
         }
 
         log::debug!("Processing {} expired timer(s)", expired_timers.len());
 
         for timer_id_str in expired_timers {
             if let Ok(id) = timer_id_str.parse::<u32>() {
-                let timer_key = format!("moxnotify:timer:{}", id);
+                let timer_key = keys.timer(id);
                 let uuid: Option<String> =
                     match AsyncTypedCommands::hget(&mut *con, &timer_key, "uuid").await {
                         Ok(uuid) => uuid,
@@ -107,6 +208,10 @@ impl TimeoutScheduler {
                 if let Some(uuid) = uuid {
                     let _ = AsyncTypedCommands::del::<&str>(&mut *con, &timer_key).await;
 
+                    if let Err(e) = expiry.invoke_action(&mut con, id, &uuid).await {
+                        log::error!("Failed to invoke action on expiry of {}: {}", id, e);
+                    }
+
                     if let Err(e) = sender.send((id, uuid)) {
                         log::warn!("Failed to send timer expiration event: {}", e);
                     }
@@ -117,25 +222,23 @@ impl TimeoutScheduler {
                 log::warn!("Invalid timer ID in Redis: {}", timer_id_str);
             }
         }
+
+        Ok(())
     }
 
     pub async fn start_timer(&self, id: u32, uuid: String, duration: Duration) {
-        let expiration_ms = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as i64
-            + duration.as_millis() as i64;
+        let expiration_ms = config::now_ms() + duration.as_millis() as i64;
 
         let mut con = self.redis_con.lock().await;
         let timer_id_str = id.to_string();
-        let timer_key = format!("moxnotify:timer:{}", id);
+        let timer_key = self.keys.timer(id);
 
         let _: Result<usize, _> =
-            AsyncTypedCommands::zrem(&mut *con, "moxnotify:timers", &timer_id_str).await;
+            AsyncTypedCommands::zrem(&mut *con, &self.keys.timers, &timer_id_str).await;
         let _ = AsyncTypedCommands::del::<&str>(&mut *con, &timer_key).await;
 
         let zadd_result: Result<usize, _> =
-            AsyncTypedCommands::zadd(&mut *con, "moxnotify:timers", &timer_id_str, expiration_ms)
+            AsyncTypedCommands::zadd(&mut *con, &self.keys.timers, &timer_id_str, expiration_ms)
                 .await;
 
         if let Err(e) = zadd_result {
@@ -152,7 +255,7 @@ impl TimeoutScheduler {
         {
             log::error!("Failed to store timer metadata for {}: {}", id, e);
             let _: Result<usize, _> =
-                AsyncTypedCommands::zrem(&mut *con, "moxnotify:timers", &timer_id_str).await;
+                AsyncTypedCommands::zrem(&mut *con, &self.keys.timers, &timer_id_str).await;
             return;
         }
 
@@ -163,6 +266,21 @@ impl TimeoutScheduler {
         );
     }
 
+    /// Start a timer for `id` unless one is already running, which keeps its
+    /// original expiration
+    pub async fn resume_timer(&self, id: u32, uuid: String, duration: Duration) {
+        let running = {
+            let mut con = self.redis_con.lock().await;
+            AsyncTypedCommands::zscore(&mut *con, &self.keys.timers, id.to_string()).await
+        };
+
+        match running {
+            Ok(Some(_)) => log::debug!("Keeping running timer for notification {}", id),
+            Ok(None) => self.start_timer(id, uuid, duration).await,
+            Err(e) => log::error!("Failed to look up timer {} in Redis: {}", id, e),
+        }
+    }
+
     pub fn receiver(&self) -> broadcast::Receiver<(u32, String)> {
         self.sender.subscribe()
     }
@@ -170,10 +288,10 @@ impl TimeoutScheduler {
     pub async fn stop(&self, id: u32) {
         let mut con = self.redis_con.lock().await;
         let timer_id_str = id.to_string();
-        let timer_key = format!("moxnotify:timer:{}", id);
+        let timer_key = self.keys.timer(id);
 
         let _: Result<usize, _> =
-            AsyncTypedCommands::zrem(&mut *con, "moxnotify:timers", &timer_id_str).await;
+            AsyncTypedCommands::zrem(&mut *con, &self.keys.timers, &timer_id_str).await;
         let _ = AsyncTypedCommands::del::<&str>(&mut *con, &timer_key).await;
 
         log::debug!("Stopped timer for notification {}", id);
@@ -182,8 +300,7 @@ impl TimeoutScheduler {
 
 impl Drop for TimeoutScheduler {
     fn drop(&mut self) {
-        if let Some(shutdown_tx) = self.shutdown_tx.take() {
-            let _ = shutdown_tx.send(());
-        }
+        log::debug!("Timer background task shutting down");
+        self.timer_task.abort();
     }
 }
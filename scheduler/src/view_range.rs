@@ -47,6 +47,14 @@ impl ViewRange {
         self.end = len;
     }
 
+    /// Keep a range restored from an earlier connection while it still fits `len`
+    /// notifications and the number the client shows, show the newest ones otherwise
+    pub fn resume(&mut self, len: usize) {
+        if self.end > len || self.width() > self.max_visible {
+            self.show_tail(len);
+        }
+    }
+
     pub fn show_head(&mut self) {
         self.start = 0;
         self.end = self.max_visible;
@@ -1,34 +1,42 @@
 use axum::Json;
 use axum::Router;
-use axum::extract::State;
-use axum::routing::post;
+use axum::extract::{self, Request, State};
+use axum::http::{StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
 use chrono::DateTime as ChronoDateTime;
 use clap::Parser;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher, RandomState};
+use std::io::{Read, Write};
 use std::ops::Bound as StdBound;
-use std::path::{Path, PathBuf};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 use tantivy::collector::TopDocs;
 use tantivy::directory::MmapDirectory;
-use tantivy::query::{BooleanQuery, Occur, QueryParser, RangeQuery};
+use tantivy::query::{
+    AllQuery, BooleanQuery, Occur, QueryClone, QueryParser, RangeQuery, TermQuery,
+};
 use tantivy::{
-    DateTime, DocAddress, Index, IndexReader, Order, ReloadPolicy, Term, doc, schema::*,
+    DateTime, DocAddress, Index, IndexReader, Order, ReloadPolicy, Searcher, Term, doc, schema::*,
 };
-use tower_http::cors::CorsLayer;
-
-fn path() -> PathBuf {
-    let path = std::env::var("XDG_DATA_HOME")
-        .map(|data_home| PathBuf::from(data_home).join("moxnotify"))
-        .or_else(|_| {
-            std::env::var("HOME").map(|home| PathBuf::from(home).join(".local/share/moxnotify"))
-        })
-        .unwrap_or_else(|_| PathBuf::from(""));
-
-    if let Some(dir) = path.parent() {
-        std::fs::create_dir_all(dir).ok();
-    }
-
-    path
-}
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+/// How often watches look for newly indexed notifications
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+/// Notifications can be indexed after ones received later than them, watches keep
+/// looking this far back so they aren't missed
+const WATCH_GRACE: Duration = Duration::from_secs(30);
+/// Most history entries returned at once
+const MAX_HISTORY_PAGE: u32 = 1000;
+/// How long a clear waits for the indexer to report back
+const CLEAR_TIMEOUT: Duration = Duration::from_secs(30);
 
 #[derive(Clone)]
 struct GlobalState {
@@ -36,6 +44,10 @@ struct GlobalState {
     parser: QueryParser,
     schema: Schema,
     timestamp_field: Field,
+    app_name_field: Field,
+    saved_searches: Arc<HashMap<String, String>>,
+    redis: redis::Client,
+    keys: Arc<config::keys::Keys>,
 }
 
 #[derive(Parser)]
@@ -59,7 +71,7 @@ async fn main() -> tantivy::Result<()> {
         .filter(Some("searcher"), config.searcher.log_level.into())
         .init();
 
-    let index_path = path();
+    let index_path = config.index_path(config.searcher.index_path.as_deref());
     log::info!("Opening index from: {:?}", index_path);
 
     let index = Index::open(MmapDirectory::open(&index_path).unwrap()).unwrap();
@@ -80,22 +92,50 @@ async fn main() -> tantivy::Result<()> {
     let mut query_parser = QueryParser::for_index(&index, vec![summary, body, app_name]);
     query_parser.set_field_boost(summary, 2.);
 
+    let redis = match redis::Client::open(&*config.redis.address) {
+        Ok(redis) => redis,
+        Err(e) => {
+            log::error!("Invalid Redis address: {e}");
+            std::process::exit(1);
+        }
+    };
+
     let state = GlobalState {
         reader,
         schema,
         parser: query_parser,
         timestamp_field,
+        app_name_field: app_name,
+        saved_searches: Arc::new(config.searcher.saved_searches.clone()),
+        redis,
+        keys: Arc::new(config.redis.keys()),
+    };
+
+    let token = if config.searcher.has_token() {
+        config.searcher.token().map_err(|e| e.to_string())
+    } else {
+        generate_token().map(Some).map_err(|e| e.to_string())
+    };
+    let token = match token {
+        Ok(Some(token)) => Arc::<str>::from(token),
+        Ok(None) => {
+            log::error!("searcher.token is empty");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            log::error!("Failed to set up the searcher's token: {e}");
+            std::process::exit(1);
+        }
     };
 
     let app = Router::new()
         .route("/api/search", post(search))
-        .layer(
-            CorsLayer::new()
-                .allow_origin(tower_http::cors::Any)
-                .allow_methods(tower_http::cors::Any)
-                .allow_headers(tower_http::cors::Any)
-                .allow_credentials(false)
-        )
+        .route("/api/history", post(history))
+        .route("/api/clear", post(clear))
+        .route("/api/watch", get(watch));
+    // Without CORS headers, pages open in a browser can't read responses either
+    let app = app
+        .route_layer(middleware::from_fn_with_state(token, authorize))
         .with_state(state);
 
     log::info!("Starting searcher server on {}", config.searcher.address);
@@ -108,6 +148,69 @@ async fn main() -> tantivy::Result<()> {
     Ok(())
 }
 
+/// Write a random token that only the user can read to where tools look for it. The
+/// token of an earlier start is kept, so clients that already read it stay authorized
+fn generate_token() -> std::io::Result<String> {
+    let path = config::SearcherConfig::generated_token_path()
+        .ok_or_else(|| std::io::Error::other("XDG_RUNTIME_DIR isn't set"))?;
+
+    if let Ok(metadata) = std::fs::symlink_metadata(&path)
+        && metadata.is_file()
+        && metadata.permissions().mode() & 0o077 == 0
+    {
+        let token = std::fs::read_to_string(&path)?.trim().to_string();
+        if !token.is_empty() {
+            log::info!("Using the token in {}", path.display());
+            return Ok(token);
+        }
+    }
+
+    let mut bytes = [0; 32];
+    std::fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    let token: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&path)?;
+    // An existing file keeps its permissions
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    file.write_all(token.as_bytes())?;
+    log::info!("Generated a token into {}", path.display());
+
+    Ok(token)
+}
+
+/// Reject requests without `Authorization: Bearer <token>`
+async fn authorize(State(token): State<Arc<str>>, request: Request, next: Next) -> Response {
+    let authorized = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| {
+            // Compare every byte so the time taken doesn't reveal a matching prefix
+            given.len() == token.len()
+                && given
+                    .bytes()
+                    .zip(token.bytes())
+                    .fold(0, |diff, (a, b)| diff | (a ^ b))
+                    == 0
+        });
+
+    if authorized {
+        next.run(request).await
+    } else {
+        log::warn!("Rejected unauthorized search request");
+        StatusCode::UNAUTHORIZED.into_response()
+    }
+}
+
 async fn search(
     State(state): State<GlobalState>,
     Json(payload): Json<Query>,
@@ -223,16 +326,289 @@ async fn search(
 
     let docs: Vec<serde_json::Value> = top_docs
         .into_iter()
-        .filter_map(|doc_addr| {
-            let doc = searcher.doc::<TantivyDocument>(doc_addr).unwrap();
-            serde_json::from_str::<serde_json::Value>(&doc.to_json(&state.schema)).ok()
-        })
+        .filter_map(|doc_addr| document(&state, &searcher, doc_addr))
         .collect();
 
     log::debug!("Returning {} documents", docs.len());
     Json(docs)
 }
 
+/// A stored document as JSON, with its category and repeat count filled in
+fn document(
+    state: &GlobalState,
+    searcher: &Searcher,
+    doc_addr: DocAddress,
+) -> Option<serde_json::Value> {
+    let doc = searcher.doc::<TantivyDocument>(doc_addr).ok()?;
+    let mut doc = serde_json::from_str::<serde_json::Value>(&doc.to_json(&state.schema)).ok()?;
+    if let Some(category) = category(&doc) {
+        doc["category"] = category.into();
+    }
+    // Repeats the indexer merged into this entry
+    let count = doc
+        .get("count")
+        .and_then(|count| count.get(0))
+        .and_then(serde_json::Value::as_u64)
+        .unwrap_or(1);
+    doc["count"] = count.into();
+
+    Some(doc)
+}
+
+/// A history entry with the fields of the notification collectors sent, as JSON
+/// that parses as one
+#[derive(Serialize)]
+struct HistoryEntry {
+    id: u64,
+    app_name: String,
+    app_icon: Option<String>,
+    summary: String,
+    body: String,
+    timeout: i64,
+    /// Milliseconds since the epoch
+    timestamp: i64,
+    hints: Option<serde_json::Value>,
+    /// Repeats the indexer merged into this entry
+    count: u64,
+}
+
+impl HistoryEntry {
+    /// Stored fields are lists of values, entries have the first of each
+    fn new(doc: &serde_json::Value) -> Option<Self> {
+        let first = |field: &str| doc.get(field).and_then(|values| values.get(0));
+        let text = |field: &str| {
+            first(field)
+                .and_then(serde_json::Value::as_str)
+                .map(str::to_string)
+        };
+
+        Some(Self {
+            id: first("id")?.as_u64()?,
+            app_name: text("app_name").unwrap_or_default(),
+            app_icon: text("app_icon"),
+            summary: text("summary").unwrap_or_default(),
+            body: text("body").unwrap_or_default(),
+            timeout: first("timeout")
+                .and_then(serde_json::Value::as_i64)
+                .unwrap_or_default(),
+            timestamp: ChronoDateTime::parse_from_rfc3339(&text("timestamp")?)
+                .ok()?
+                .timestamp_millis(),
+            hints: text("hints").and_then(|hints| serde_json::from_str(&hints).ok()),
+            count: doc
+                .get("count")
+                .and_then(serde_json::Value::as_u64)
+                .unwrap_or(1),
+        })
+    }
+}
+
+/// One page of the whole history, newest first, optionally of one app
+async fn history(
+    State(state): State<GlobalState>,
+    Json(payload): Json<HistoryPage>,
+) -> Result<Json<Vec<HistoryEntry>>, StatusCode> {
+    log::info!(
+        "Received history request: offset={}, limit={}, app_name={:?}",
+        payload.offset,
+        payload.limit,
+        payload.app_name
+    );
+
+    state.reader.reload().map_err(|e| {
+        log::error!("Failed to reload index reader: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let searcher = state.reader.searcher();
+    // Collecting allocates for every entry up to the offset
+    if u64::from(payload.offset) >= searcher.num_docs() {
+        return Ok(Json(Vec::new()));
+    }
+
+    let query: Box<dyn tantivy::query::Query> = match payload.app_name.as_deref() {
+        Some(app_name) => Box::new(TermQuery::new(
+            Term::from_field_text(state.app_name_field, app_name),
+            IndexRecordOption::Basic,
+        )),
+        None => Box::new(AllQuery),
+    };
+
+    let collector = TopDocs::with_limit(payload.limit.clamp(1, MAX_HISTORY_PAGE) as usize)
+        .and_offset(payload.offset as usize)
+        .order_by_fast_field::<DateTime>("timestamp", Order::Desc);
+    let top_docs = searcher.search(&query, &collector).map_err(|e| {
+        log::error!("History search failed: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(
+        top_docs
+            .into_iter()
+            .filter_map(|(_, doc_addr)| document(&state, &searcher, doc_addr))
+            .filter_map(|doc| HistoryEntry::new(&doc))
+            .collect(),
+    ))
+}
+
+/// Have the indexer, which owns the only writer of the index, delete the whole
+/// history. Returns how many entries were deleted
+async fn clear(State(state): State<GlobalState>) -> Result<Json<u64>, StatusCode> {
+    let unavailable = |e: redis::RedisError| {
+        log::error!("Failed to ask the indexer to clear history: {}", e);
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    let mut con = state
+        .redis
+        .get_multiplexed_async_connection()
+        .await
+        .map_err(unavailable)?;
+
+    let request = format!("{:016x}", RandomState::new().build_hasher().finish());
+    let reply = state.keys.clear_history_reply(&request);
+    let indexers: usize =
+        redis::AsyncCommands::publish(&mut con, &state.keys.pubsub_clear_history, &reply)
+            .await
+            .map_err(unavailable)?;
+    if indexers == 0 {
+        log::error!("Can't clear history, no indexer is running");
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    let outcome: Option<(String, String)> =
+        redis::AsyncCommands::blpop(&mut con, &reply, CLEAR_TIMEOUT.as_secs_f64())
+            .await
+            .map_err(unavailable)?;
+    let Some((_, outcome)) = outcome else {
+        log::error!("Indexer didn't clear history within {:?}", CLEAR_TIMEOUT);
+        return Err(StatusCode::GATEWAY_TIMEOUT);
+    };
+
+    match serde_json::from_str::<Result<u64, String>>(&outcome) {
+        Ok(Ok(count)) => {
+            log::info!("Cleared history of {} entries", count);
+            Ok(Json(count))
+        }
+        Ok(Err(e)) => {
+            log::error!("Indexer failed to clear history: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+        Err(e) => {
+            log::error!("Malformed reply of the indexer: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Notifications matching a watch, indexed since it last looked, oldest first
+fn watched(
+    state: &GlobalState,
+    query: &dyn tantivy::query::Query,
+    since: i64,
+) -> tantivy::Result<Vec<(i64, serde_json::Value)>> {
+    state.reader.reload()?;
+    let searcher = state.reader.searcher();
+
+    let since = Term::from_field_date(
+        state.timestamp_field,
+        DateTime::from_timestamp_millis(since),
+    );
+    let query = BooleanQuery::new(vec![
+        (Occur::Must, query.box_clone()),
+        (
+            Occur::Must,
+            Box::new(RangeQuery::new(
+                StdBound::Included(since),
+                StdBound::Unbounded,
+            )),
+        ),
+    ]);
+    let collector =
+        TopDocs::with_limit(1000).order_by_fast_field::<DateTime>("timestamp", Order::Asc);
+
+    Ok(searcher
+        .search(&query, &collector)?
+        .into_iter()
+        .filter_map(|(timestamp, doc_addr)| {
+            Some((
+                timestamp.into_timestamp_millis(),
+                document(state, &searcher, doc_addr)?,
+            ))
+        })
+        .collect())
+}
+
+/// Stream notifications matching `query`, or the saved search `search`, as they're
+/// indexed, as server-sent events of the same documents search returns
+async fn watch(
+    State(state): State<GlobalState>,
+    extract::Query(watch): extract::Query<Watch>,
+) -> Result<Sse<ReceiverStream<Result<Event, axum::Error>>>, StatusCode> {
+    let text = match (watch.search, watch.query) {
+        (Some(name), _) => state
+            .saved_searches
+            .get(&name)
+            .cloned()
+            .ok_or(StatusCode::NOT_FOUND)?,
+        (None, Some(query)) => query,
+        (None, None) => return Err(StatusCode::BAD_REQUEST),
+    };
+    let query = state.parser.parse_query(&text).map_err(|e| {
+        log::warn!("Failed to parse watched query '{}': {}", text, e);
+        StatusCode::BAD_REQUEST
+    })?;
+    log::info!("Watching for notifications matching '{}'", text);
+
+    let (events, receiver) = mpsc::channel(16);
+    tokio::spawn(async move {
+        let grace = WATCH_GRACE.as_millis() as i64;
+        let mut since = config::now_ms();
+        // Keys of the entries sent within the grace period, to not send them again.
+        // IDs alone aren't enough since they're reused across restarts and sources
+        let mut sent: HashMap<String, i64> = HashMap::new();
+        let mut interval = tokio::time::interval(WATCH_INTERVAL);
+
+        while !events.is_closed() {
+            interval.tick().await;
+            let docs = match watched(&state, query.as_ref(), since) {
+                Ok(docs) => docs,
+                Err(e) => {
+                    log::error!("Failed to look for watched notifications: {}", e);
+                    continue;
+                }
+            };
+
+            for (timestamp, doc) in docs {
+                let Some(key) = doc["key"].get(0).and_then(serde_json::Value::as_str) else {
+                    continue;
+                };
+                if sent.insert(key.to_string(), timestamp).is_some() {
+                    continue;
+                }
+                if events.send(Event::default().json_data(doc)).await.is_err() {
+                    break;
+                }
+            }
+
+            if let Some(newest) = sent.values().copied().max() {
+                since = since.max(newest - grace);
+                sent.retain(|_, timestamp| *timestamp >= since);
+            }
+        }
+
+        log::info!("Stopped watching for notifications matching '{}'", text);
+    });
+
+    Ok(Sse::new(ReceiverStream::new(receiver)).keep_alive(KeepAlive::default()))
+}
+
+/// Category hint of a search result, hints are stored as a JSON string
+fn category(doc: &serde_json::Value) -> Option<String> {
+    let hints = doc.get("hints")?.get(0)?.as_str()?;
+    let hints = serde_json::from_str::<serde_json::Value>(hints).ok()?;
+
+    hints.get("category")?.as_str().map(str::to_string)
+}
+
 #[derive(Deserialize)]
 struct Query {
     query: String,
@@ -243,6 +619,21 @@ struct Query {
     sort_order: Option<SortOrder>,
 }
 
+#[derive(Deserialize)]
+struct Watch {
+    query: Option<String>,
+    /// Name of a query in `searcher.saved_searches`
+    search: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct HistoryPage {
+    #[serde(default)]
+    offset: u32,
+    limit: u32,
+    app_name: Option<String>,
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "lowercase")]
 enum SortOrder {
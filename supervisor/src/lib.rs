@@ -0,0 +1,168 @@
+use std::any::Any;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher, RandomState};
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::os::fd::{FromRawFd, RawFd};
+use std::time::{Duration, Instant};
+use tokio::task::{AbortHandle, JoinHandle};
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// A task that stayed up for this long before panicking is considered
+/// healthy again, so its backoff starts over
+const HEALTHY_UPTIME: Duration = Duration::from_secs(60);
+/// First file descriptor systemd passes to socket activated services
+const LISTEN_FDS_START: RawFd = 3;
+
+/// Exponential delay between attempts to restart a task or reconnect to a service
+pub struct Backoff {
+    next: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            next: INITIAL_BACKOFF,
+        }
+    }
+}
+
+impl Backoff {
+    /// Delay before the next attempt, doubling each time up to a limit
+    pub fn delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * 2).min(MAX_BACKOFF);
+        delay
+    }
+
+    /// Somewhere between half and all of [`Backoff::delay`], so that clients which
+    /// lost a connection together don't all retry at the same moment
+    pub fn jittered_delay(&mut self) -> Duration {
+        let delay = self.delay();
+        let random = RandomState::new().build_hasher().finish();
+        delay / 2 + delay.mul_f64((random as f64 / u64::MAX as f64) / 2.0)
+    }
+
+    /// Start over after a successful attempt
+    pub fn reset(&mut self) {
+        self.next = INITIAL_BACKOFF;
+    }
+}
+
+/// Aborts the supervised task when the supervisor itself is aborted or dropped
+struct AbortOnDrop(AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Box<dyn Any>")
+}
+
+/// Spawn a task built by `task` and restart it with exponential backoff
+/// whenever it panics. The supervisor finishes once the task returns on its own
+/// or is cancelled, aborting the returned handle also aborts the task.
+pub fn spawn<F, Fut>(name: impl Into<String>, task: F) -> JoinHandle<()>
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let name = name.into();
+
+    tokio::spawn(async move {
+        let mut backoff = Backoff::default();
+
+        loop {
+            let started = Instant::now();
+            let mut handle = tokio::spawn(task());
+            let _guard = AbortOnDrop(handle.abort_handle());
+
+            match (&mut handle).await {
+                Ok(()) => {
+                    log::debug!("Task '{}' finished", name);
+                    return;
+                }
+                Err(e) if e.is_panic() => {
+                    let payload = e.into_panic();
+                    if started.elapsed() >= HEALTHY_UPTIME {
+                        backoff.reset();
+                    }
+
+                    let delay = backoff.delay();
+                    log::error!(
+                        "Task '{}' panicked: {}, restarting in {:?}",
+                        name,
+                        panic_message(&*payload),
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(_) => {
+                    log::debug!("Task '{}' was cancelled", name);
+                    return;
+                }
+            }
+        }
+    })
+}
+
+/// Log a panic of a task that can't be restarted, e.g. because it owns a
+/// connection that's gone once the task dies
+pub fn watch<T>(name: impl Into<String>, handle: JoinHandle<T>)
+where
+    T: Send + 'static,
+{
+    let name = name.into();
+
+    tokio::spawn(async move {
+        if let Err(e) = handle.await
+            && e.is_panic()
+        {
+            log::error!(
+                "Task '{}' panicked: {}",
+                name,
+                panic_message(&*e.into_panic())
+            );
+        }
+    });
+}
+
+/// Whether systemd passed sockets to this process, see sd_listen_fds(3)
+fn socket_activated() -> bool {
+    let var = |name| {
+        std::env::var(name)
+            .ok()
+            .and_then(|value| value.parse::<u32>().ok())
+    };
+
+    var("LISTEN_PID") == Some(std::process::id()) && var("LISTEN_FDS").is_some_and(|fds| fds > 0)
+}
+
+/// Listener on the socket systemd passed when the service was socket
+/// activated, bound to `address` otherwise. It's non-blocking so it can be
+/// handed to tokio, and can be cloned for every restart of a supervised server.
+pub fn listener(address: SocketAddr) -> io::Result<TcpListener> {
+    let listener = if socket_activated() {
+        // SAFETY: systemd passes the socket as the first descriptor after stdio,
+        // and nothing else in the process takes ownership of it
+        let listener = unsafe { TcpListener::from_raw_fd(LISTEN_FDS_START) };
+        log::info!(
+            "Using socket passed by systemd on {}",
+            listener.local_addr()?
+        );
+        listener
+    } else {
+        TcpListener::bind(address)?
+    };
+
+    listener.set_nonblocking(true)?;
+    Ok(listener)
+}
@@ -0,0 +1,10 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tonic_prost_build::configure()
+        .build_server(false)
+        .compile_protos(
+            &["../proto/types.proto", "../proto/client.proto"],
+            &["../proto"],
+        )?;
+
+    Ok(())
+}
@@ -0,0 +1,315 @@
+use crate::moxnotify::client::client_service_client::ClientServiceClient;
+use crate::moxnotify::client::viewport_navigation_request::Direction;
+use crate::moxnotify::client::{
+    ClientActionInvokedRequest, ClientNotificationClosedRequest, GetViewportRequest,
+    RestartTimersRequest, StopTimersRequest, ViewportNavigationRequest, ViewportNavigationResponse,
+};
+use crate::moxnotify::types::{ActionInvoked, CloseReason, NewNotification, NotificationClosed};
+use crate::ui;
+use crossterm::event::{Event as InputEvent, EventStream, KeyCode, KeyEvent, KeyEventKind};
+use futures_lite::StreamExt;
+use ratatui::DefaultTerminal;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tonic::Request;
+use tonic::transport::Channel;
+
+pub enum Event {
+    Connected,
+    Disconnected,
+    Notify(Box<NewNotification>),
+    CloseNotification(u32),
+}
+
+pub struct App {
+    client: ClientServiceClient<Channel>,
+    notifications: Vec<NewNotification>,
+    viewport: ViewportNavigationResponse,
+    /// Whether a notification is focused, expiration timers are stopped while it is
+    selected: bool,
+    connected: bool,
+    /// Set after the first `g` of a `gg` sequence, and the first `d` of `dd`
+    pending: Option<char>,
+    error: Option<String>,
+    quit: bool,
+    /// How often relative times are redrawn, `None` doesn't show them
+    timestamps: Option<Duration>,
+}
+
+impl App {
+    pub fn new(client: ClientServiceClient<Channel>, timestamps: Option<Duration>) -> Self {
+        Self {
+            client,
+            notifications: Vec::new(),
+            viewport: ViewportNavigationResponse::default(),
+            selected: false,
+            connected: false,
+            pending: None,
+            error: None,
+            quit: false,
+            timestamps,
+        }
+    }
+
+    pub async fn run(
+        mut self,
+        terminal: &mut DefaultTerminal,
+        mut events: mpsc::UnboundedReceiver<Event>,
+    ) -> anyhow::Result<()> {
+        let mut input = EventStream::new();
+        // Only polled while timestamps are shown
+        let mut tick = tokio::time::interval(self.timestamps.unwrap_or(Duration::from_secs(60)));
+
+        while !self.quit {
+            terminal.draw(|frame| ui::draw(frame, &self))?;
+
+            let result = tokio::select! {
+                Some(event) = events.recv() => self.handle_event(event).await,
+                Some(event) = input.next() => match event? {
+                    InputEvent::Key(key) if key.kind == KeyEventKind::Press => self.handle_key(key).await,
+                    _ => Ok(()),
+                },
+                _ = tick.tick(), if self.timestamps.is_some() => Ok(()),
+                else => break,
+            };
+
+            self.error = result.err().map(|status| status.message().to_string());
+        }
+
+        if self.selected {
+            self.client
+                .restart_timers(Request::new(RestartTimersRequest {}))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Notifications in the scheduler's viewport, in display order
+    pub fn visible(&self) -> impl Iterator<Item = &NewNotification> {
+        self.viewport.focused_ids.iter().filter_map(|id| {
+            self.notifications
+                .iter()
+                .find(|notification| notification.id == *id)
+        })
+    }
+
+    pub fn selected_id(&self) -> Option<u32> {
+        self.viewport.selected_id.filter(|_| self.selected)
+    }
+
+    pub fn before_count(&self) -> u32 {
+        self.viewport.before_count
+    }
+
+    pub fn after_count(&self) -> u32 {
+        self.viewport.after_count
+    }
+
+    pub fn connected(&self) -> bool {
+        self.connected
+    }
+
+    pub fn timestamps(&self) -> bool {
+        self.timestamps.is_some()
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    async fn handle_event(&mut self, event: Event) -> Result<(), tonic::Status> {
+        match event {
+            Event::Connected => self.connected = true,
+            Event::Disconnected => {
+                self.connected = false;
+                self.notifications.clear();
+                self.viewport = ViewportNavigationResponse::default();
+                return Ok(());
+            }
+            Event::Notify(notification) => {
+                match self
+                    .notifications
+                    .iter_mut()
+                    .find(|existing| existing.id == notification.id)
+                {
+                    Some(existing) => *existing = *notification,
+                    None => self.notifications.push(*notification),
+                }
+            }
+            Event::CloseNotification(id) => self.notifications.retain(|n| n.id != id),
+        }
+
+        self.refresh_viewport().await
+    }
+
+    async fn handle_key(&mut self, key: KeyEvent) -> Result<(), tonic::Status> {
+        let pending = self.pending.take();
+
+        match key.code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Char('j') | KeyCode::Down => self.navigate(Direction::Next).await?,
+            KeyCode::Char('k') | KeyCode::Up => self.navigate(Direction::Prev).await?,
+            KeyCode::Char('G') | KeyCode::End => self.navigate(Direction::Last).await?,
+            KeyCode::Home => self.navigate(Direction::First).await?,
+            KeyCode::Char('g') if pending == Some('g') => self.navigate(Direction::First).await?,
+            KeyCode::Char('d') if pending == Some('d') => self.dismiss_selected().await?,
+            KeyCode::Char(c @ ('g' | 'd')) => self.pending = Some(c),
+            KeyCode::Char('x') | KeyCode::Delete => self.dismiss_selected().await?,
+            KeyCode::Char('X') => self.dismiss_all().await?,
+            KeyCode::Enter => self.invoke_action(None).await?,
+            KeyCode::Char(c @ '1'..='9') => {
+                self.invoke_action(c.to_digit(10).map(|n| n as usize - 1))
+                    .await?
+            }
+            KeyCode::Esc => self.deselect().await?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    async fn navigate(&mut self, direction: Direction) -> Result<(), tonic::Status> {
+        if !self.selected {
+            self.client
+                .stop_timers(Request::new(StopTimersRequest {}))
+                .await?;
+            self.selected = true;
+        }
+
+        self.viewport = self
+            .client
+            .navigate_viewport(Request::new(ViewportNavigationRequest {
+                direction: direction as i32,
+                count: 1,
+            }))
+            .await?
+            .into_inner();
+
+        Ok(())
+    }
+
+    async fn deselect(&mut self) -> Result<(), tonic::Status> {
+        if !self.selected {
+            return Ok(());
+        }
+
+        self.selected = false;
+        self.client
+            .restart_timers(Request::new(RestartTimersRequest {}))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn refresh_viewport(&mut self) -> Result<(), tonic::Status> {
+        self.viewport = self
+            .client
+            .get_viewport(Request::new(GetViewportRequest {}))
+            .await?
+            .into_inner();
+
+        Ok(())
+    }
+
+    fn selected_notification(&self) -> Option<&NewNotification> {
+        let id = self.selected_id()?;
+        self.notifications
+            .iter()
+            .find(|notification| notification.id == id)
+    }
+
+    async fn dismiss(&mut self, id: u32, uuid: String) -> Result<(), tonic::Status> {
+        self.client
+            .notification_closed(Request::new(ClientNotificationClosedRequest {
+                notification_closed: Some(NotificationClosed {
+                    id,
+                    reason: CloseReason::ReasonDismissedByUser as i32,
+                    uuid,
+                }),
+            }))
+            .await?;
+        self.notifications.retain(|n| n.id != id);
+
+        Ok(())
+    }
+
+    async fn dismiss_selected(&mut self) -> Result<(), tonic::Status> {
+        let Some(notification) = self.selected_notification() else {
+            return Ok(());
+        };
+
+        let (id, uuid) = (notification.id, notification.uuid.clone());
+        self.dismiss(id, uuid).await?;
+
+        if self.notifications.is_empty() {
+            self.deselect().await?;
+        }
+
+        self.refresh_viewport().await
+    }
+
+    async fn dismiss_all(&mut self) -> Result<(), tonic::Status> {
+        let notifications: Vec<_> = self
+            .notifications
+            .iter()
+            .map(|notification| (notification.id, notification.uuid.clone()))
+            .collect();
+
+        for (id, uuid) in notifications {
+            self.dismiss(id, uuid).await?;
+        }
+
+        self.deselect().await?;
+        self.refresh_viewport().await
+    }
+
+    /// Invoke the action at `index`, or the default action if there's none
+    async fn invoke_action(&mut self, index: Option<usize>) -> Result<(), tonic::Status> {
+        let Some(notification) = self.selected_notification() else {
+            return Ok(());
+        };
+
+        let key = match index {
+            Some(index) => match notification.actions.get(index) {
+                Some(action) => action.key.clone(),
+                None => return Ok(()),
+            },
+            None => "default".to_string(),
+        };
+
+        let id = notification.id;
+        let uuid = notification.uuid.clone();
+        let resident = notification
+            .hints
+            .as_ref()
+            .is_some_and(|hints| hints.resident);
+
+        self.client
+            .action_invoked(Request::new(ClientActionInvokedRequest {
+                action_invoked: Some(ActionInvoked {
+                    id,
+                    action_key: key,
+                    token: String::new(),
+                    uuid: uuid.clone(),
+                }),
+            }))
+            .await?;
+
+        if !resident {
+            self.client
+                .notification_closed(Request::new(ClientNotificationClosedRequest {
+                    notification_closed: Some(NotificationClosed {
+                        id,
+                        reason: CloseReason::ReasonCloseNotificationCall as i32,
+                        uuid,
+                    }),
+                }))
+                .await?;
+            self.notifications.retain(|n| n.id != id);
+            self.refresh_viewport().await?;
+        }
+
+        Ok(())
+    }
+}
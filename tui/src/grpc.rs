@@ -0,0 +1,65 @@
+use crate::app::Event;
+use crate::moxnotify::client::client_service_client::ClientServiceClient;
+use crate::moxnotify::client::{ClientNotifyRequest, notification_message};
+use crate::moxnotify::types::{Capability, Hello};
+use futures_lite::stream::StreamExt;
+use tokio::sync::mpsc;
+use tokio::time;
+use tonic::Request;
+use tonic::transport::Channel;
+
+/// Subscribe to the scheduler and forward notifications to the UI, reconnecting with backoff
+pub async fn serve(
+    mut client: ClientServiceClient<Channel>,
+    event_sender: mpsc::UnboundedSender<Event>,
+    max_visible: u32,
+) {
+    let mut backoff = supervisor::Backoff::default();
+    loop {
+        let request = Request::new(ClientNotifyRequest {
+            max_visible,
+            // Images aren't shown, so blob keys save sending their data
+            hello: Some(Hello {
+                protocol_version: config::protocol::VERSION,
+                capabilities: vec![Capability::ImageBlobs as i32],
+                // Every run starts at the newest notifications
+                client_id: String::new(),
+            }),
+        });
+        if let Ok(mut stream) = client
+            .notify(request)
+            .await
+            .map(|response| response.into_inner())
+        {
+            if event_sender.send(Event::Connected).is_err() {
+                return;
+            }
+
+            while let Some(msg_result) = stream.next().await {
+                if let Ok(msg) = msg_result
+                    && let Some(message) = msg.message
+                {
+                    backoff.reset();
+                    let event = match message {
+                        notification_message::Message::Notification(notification) => {
+                            Event::Notify(Box::new(notification))
+                        }
+                        notification_message::Message::CloseNotification(close_notification) => {
+                            Event::CloseNotification(close_notification.id)
+                        }
+                    };
+
+                    if event_sender.send(event).is_err() {
+                        return;
+                    }
+                }
+            }
+
+            if event_sender.send(Event::Disconnected).is_err() {
+                return;
+            }
+        }
+
+        time::sleep(backoff.jittered_delay()).await;
+    }
+}
@@ -0,0 +1,60 @@
+pub mod moxnotify {
+    pub mod types {
+        tonic::include_proto!("moxnotify.types");
+    }
+    pub mod client {
+        tonic::include_proto!("moxnotify.client");
+    }
+}
+
+mod app;
+mod grpc;
+mod ui;
+
+use app::App;
+use clap::Parser;
+use config::types::Compression;
+use moxnotify::client::client_service_client::ClientServiceClient;
+use std::path::Path;
+use tokio::sync::mpsc;
+use tonic::codec::CompressionEncoding;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[arg(short, long, value_name = "FILE", help = "Path to the config file")]
+    config: Option<Box<Path>>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    let config = config::Config::load(cli.config.as_deref())?;
+    i18n::init(config.locale.as_deref(), &config.translations);
+
+    let mut client = ClientServiceClient::connect(format!("http://{}", config.scheduler.address))
+        .await?
+        .accept_compressed(CompressionEncoding::Zstd);
+    if config.client.compression == Compression::Zstd {
+        client = client.send_compressed(CompressionEncoding::Zstd);
+    }
+
+    let (event_sender, event_receiver) = mpsc::unbounded_channel();
+    tokio::spawn(grpc::serve(
+        client.clone(),
+        event_sender,
+        config.client.general.max_visible as u32,
+    ));
+
+    let mut terminal = ratatui::init();
+    let timestamps = &config.client.general.timestamps;
+    let timestamps = (timestamps.enabled && !timestamps.update_interval.is_zero())
+        .then_some(timestamps.update_interval);
+    let result = App::new(client, timestamps)
+        .run(&mut terminal, event_receiver)
+        .await;
+    ratatui::restore();
+
+    result
+}
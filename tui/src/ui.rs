@@ -0,0 +1,181 @@
+use crate::app::App;
+use crate::moxnotify::types::{NewNotification, Urgency};
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, BorderType, Paragraph, Wrap};
+use std::time::Duration;
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let [status, list, help] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    frame.render_widget(status_line(app), status);
+    draw_notifications(frame, app, list);
+    frame.render_widget(Line::from(i18n::tr!("tui-help")).dark_gray(), help);
+}
+
+fn status_line(app: &App) -> Line<'_> {
+    if let Some(error) = app.error() {
+        return Line::from(error).red();
+    }
+
+    if !app.connected() {
+        return Line::from(i18n::tr!("tui-connecting")).yellow();
+    }
+
+    let total = app.visible().count() as u32 + app.before_count() + app.after_count();
+    Line::from(i18n::tr!("tui-status", count = total)).bold()
+}
+
+fn draw_notifications(frame: &mut Frame, app: &App, area: Rect) {
+    let width = area.width.saturating_sub(2);
+    let notifications: Vec<_> = app.visible().collect();
+
+    let mut constraints = vec![Constraint::Length(1)];
+    constraints.extend(
+        notifications
+            .iter()
+            .map(|notification| Constraint::Length(height(notification, width))),
+    );
+    constraints.push(Constraint::Length(1));
+    constraints.push(Constraint::Min(0));
+
+    let areas = Layout::vertical(constraints).split(area);
+
+    if app.before_count() > 0 {
+        frame.render_widget(
+            Line::from(i18n::tr!("tui-more-before", count = app.before_count())).dark_gray(),
+            areas[0],
+        );
+    }
+
+    notifications
+        .iter()
+        .zip(areas.iter().skip(1))
+        .for_each(|(notification, area)| {
+            let selected = app.selected_id() == Some(notification.id);
+            frame.render_widget(
+                notification_widget(notification, selected, app.timestamps()),
+                *area,
+            );
+        });
+
+    if app.after_count() > 0 {
+        frame.render_widget(
+            Line::from(i18n::tr!("tui-more-after", count = app.after_count())).dark_gray(),
+            areas[notifications.len() + 1],
+        );
+    }
+}
+
+fn urgency(notification: &NewNotification) -> Urgency {
+    notification
+        .hints
+        .as_ref()
+        .map_or(Urgency::Normal, |hints| hints.urgency())
+}
+
+/// How long ago `notification` arrived, e.g. "2 min ago"
+fn relative_time(notification: &NewNotification) -> String {
+    let now = config::now_ms();
+
+    config::client::Timestamps::relative(Duration::from_millis(
+        (now - notification.timestamp).max(0) as u64,
+    ))
+}
+
+fn notification_widget(
+    notification: &NewNotification,
+    selected: bool,
+    timestamps: bool,
+) -> Paragraph<'_> {
+    let color = match urgency(notification) {
+        Urgency::Low => Color::DarkGray,
+        Urgency::Normal => Color::Reset,
+        Urgency::Critical => Color::Red,
+    };
+
+    let mut block = Block::bordered()
+        .title(
+            Span::from(
+                notification
+                    .display_name
+                    .as_deref()
+                    .unwrap_or(&notification.app_name),
+            )
+            .bold(),
+        )
+        .border_style(Style::new().fg(color));
+    if timestamps {
+        block = block.title(Line::from(relative_time(notification)).right_aligned());
+    }
+    if selected {
+        block = block
+            .border_type(BorderType::Thick)
+            .border_style(Style::new().fg(Color::Cyan));
+    }
+
+    let mut text = Text::from(Line::from(notification.summary.as_str()).bold());
+    let body = strip_markup(&notification.body);
+    if !body.is_empty() {
+        text.extend(Text::from(body));
+    }
+
+    let actions: Vec<_> = notification
+        .actions
+        .iter()
+        .enumerate()
+        .map(|(i, action)| {
+            Span::styled(
+                format!("[{}] {} ", i + 1, action.label),
+                Style::new().add_modifier(Modifier::REVERSED),
+            )
+        })
+        .collect();
+    if !actions.is_empty() {
+        text.push_line(Line::from(actions));
+    }
+
+    Paragraph::new(text).block(block).wrap(Wrap { trim: false })
+}
+
+/// Rows a notification takes up once wrapped to `width`, including borders
+fn height(notification: &NewNotification, width: u16) -> u16 {
+    let width = usize::from(width.max(1));
+    let rows = |line: &str| line.chars().count().div_ceil(width).max(1);
+
+    let body = strip_markup(&notification.body);
+    let body_rows = if body.is_empty() {
+        0
+    } else {
+        body.lines().map(rows).sum()
+    };
+    let action_rows = usize::from(!notification.actions.is_empty());
+
+    (rows(&notification.summary) + body_rows + action_rows + 2) as u16
+}
+
+/// Notification bodies may contain a small subset of HTML, terminals can't render it
+fn strip_markup(body: &str) -> String {
+    let mut text = String::with_capacity(body.len());
+    let mut in_tag = false;
+
+    body.chars().for_each(|c| match c {
+        '<' => in_tag = true,
+        '>' if in_tag => in_tag = false,
+        c if !in_tag => text.push(c),
+        _ => {}
+    });
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
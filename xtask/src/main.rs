@@ -0,0 +1,48 @@
+mod nix;
+mod trace;
+
+use clap::{Parser, Subcommand};
+use std::path::Path;
+
+#[derive(Parser)]
+#[command(about = "Development tasks for moxnotify", long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    task: Task,
+}
+
+#[derive(Subcommand)]
+enum Task {
+    #[command(about = "Generate the home-manager option types in nix/options.nix from the config")]
+    NixOptions {
+        #[arg(
+            long,
+            help = "Fail if nix/options.nix is out of date instead of writing it"
+        )]
+        check: bool,
+    },
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    match cli.task {
+        Task::NixOptions { check } => {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../nix/options.nix");
+            let options = nix::options(&trace::shape::<config::Config>())?;
+
+            if check {
+                if std::fs::read_to_string(&path).unwrap_or_default() != options {
+                    anyhow::bail!(
+                        "{} is out of date, run `cargo xtask nix-options`",
+                        path.display()
+                    );
+                }
+            } else {
+                std::fs::write(&path, options)?;
+            }
+        }
+    }
+
+    Ok(())
+}
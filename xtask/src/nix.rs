@@ -0,0 +1,89 @@
+//! Renders the traced config as the option types of the home-manager module
+
+use crate::trace::Shape;
+
+const PRELUDE: &str = "\
+# Generated by `cargo xtask nix-options` from the config structs, don't edit by hand
+{ lib }:
+let
+  inherit (lib) types;
+  # Options left unset are dropped from the config, so the defaults of the config structs apply
+  option =
+    type:
+    lib.mkOption {
+      type = types.nullOr type;
+      default = null;
+    };
+in
+{
+";
+
+fn indent(depth: usize) -> String {
+    "  ".repeat(depth)
+}
+
+/// Nix type of `shape`, lines it continues on are indented by `depth`
+fn nix_type(shape: &Shape, depth: usize) -> String {
+    match shape {
+        Shape::Bool => "types.bool".to_string(),
+        Shape::Int => "types.int".to_string(),
+        Shape::Float => "types.number".to_string(),
+        Shape::Str => "types.str".to_string(),
+        Shape::Enum(variants) => {
+            let variants = variants
+                .iter()
+                .map(|variant| format!("\"{variant}\""))
+                .collect::<Vec<_>>();
+            format!("types.enum [ {} ]", variants.join(" "))
+        }
+        Shape::Optional(shape) => format!("types.nullOr {}", argument(shape, depth)),
+        Shape::List(shape) => format!("types.listOf {}", argument(shape, depth)),
+        Shape::Attrs(shape) => format!("types.attrsOf {}", argument(shape, depth)),
+        Shape::Struct(fields) => submodule(fields, depth),
+        Shape::Any => "types.anything".to_string(),
+    }
+}
+
+/// `nix_type` parenthesized when it's more than a single identifier
+fn argument(shape: &Shape, depth: usize) -> String {
+    let nix_type = nix_type(shape, depth);
+    if nix_type.contains(' ') {
+        format!("({nix_type})")
+    } else {
+        nix_type
+    }
+}
+
+fn submodule(fields: &[(&str, Shape)], depth: usize) -> String {
+    let mut submodule = format!("types.submodule {{\n{}options = {{\n", indent(depth + 1));
+    for (name, shape) in fields {
+        // Every option is nullable already
+        let shape = match shape {
+            Shape::Optional(shape) => shape,
+            shape => shape,
+        };
+        submodule.push_str(&format!(
+            "{}{name} = option {};\n",
+            indent(depth + 2),
+            argument(shape, depth + 2)
+        ));
+    }
+    submodule.push_str(&format!("{}}};\n{}}}", indent(depth + 1), indent(depth)));
+
+    submodule
+}
+
+/// Option types of every section of the config, keyed by the section's name
+pub fn options(config: &Shape) -> anyhow::Result<String> {
+    let Shape::Struct(sections) = config else {
+        anyhow::bail!("The config isn't a struct");
+    };
+
+    let mut options = PRELUDE.to_string();
+    for (name, shape) in sections {
+        options.push_str(&format!("  {name} = {};\n", nix_type(shape, 1)));
+    }
+    options.push_str("}\n");
+
+    Ok(options)
+}
@@ -0,0 +1,333 @@
+//! Recovers the shape of a config type from its `Deserialize` impl, by handing it
+//! a deserializer that records what the impl asks for instead of providing data.
+
+use serde::de::value::StrDeserializer;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use std::cell::RefCell;
+use std::fmt;
+
+/// What a config value has to look like
+#[derive(Debug, PartialEq)]
+pub enum Shape {
+    Bool,
+    Int,
+    Float,
+    Str,
+    Enum(&'static [&'static str]),
+    Optional(Box<Shape>),
+    List(Box<Shape>),
+    /// Attribute set with arbitrary names
+    Attrs(Box<Shape>),
+    Struct(Vec<(&'static str, Shape)>),
+    /// Anything the impl inspects itself, e.g. through `deserialize_any`
+    Any,
+}
+
+/// What the impl asked for at the end of the traced path, containers are
+/// followed into by tracing their children one at a time
+enum Node {
+    Leaf(Shape),
+    Optional,
+    List,
+    Attrs,
+    Struct(&'static [&'static str]),
+}
+
+/// Returned once the node was recorded, there's never any data to deserialize
+#[derive(Debug)]
+struct Traced(String);
+
+impl fmt::Display for Traced {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Traced {}
+
+impl de::Error for Traced {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+/// Follows `path` into nested values, each step is the index of a struct's
+/// field or 0 for the single child of other containers
+#[derive(Clone, Copy)]
+struct Tracer<'a> {
+    path: &'a [usize],
+    node: &'a RefCell<Option<Node>>,
+}
+
+impl Tracer<'_> {
+    fn record<T>(self, node: Node) -> Result<T, Traced> {
+        if !self.path.is_empty() {
+            return Err(de::Error::custom("path continues past a leaf"));
+        }
+
+        *self.node.borrow_mut() = Some(node);
+        Err(de::Error::custom("traced"))
+    }
+}
+
+macro_rules! leaf {
+    ($($method:ident => $shape:expr,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Traced> {
+                self.record(Node::Leaf($shape))
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Tracer<'_> {
+    type Error = Traced;
+
+    leaf! {
+        deserialize_any => Shape::Any,
+        deserialize_bool => Shape::Bool,
+        deserialize_i8 => Shape::Int,
+        deserialize_i16 => Shape::Int,
+        deserialize_i32 => Shape::Int,
+        deserialize_i64 => Shape::Int,
+        deserialize_i128 => Shape::Int,
+        deserialize_u8 => Shape::Int,
+        deserialize_u16 => Shape::Int,
+        deserialize_u32 => Shape::Int,
+        deserialize_u64 => Shape::Int,
+        deserialize_u128 => Shape::Int,
+        deserialize_f32 => Shape::Float,
+        deserialize_f64 => Shape::Float,
+        deserialize_char => Shape::Str,
+        deserialize_str => Shape::Str,
+        deserialize_string => Shape::Str,
+        deserialize_bytes => Shape::Str,
+        deserialize_byte_buf => Shape::Str,
+        deserialize_identifier => Shape::Str,
+        deserialize_unit => Shape::Any,
+        deserialize_ignored_any => Shape::Any,
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _visitor: V,
+    ) -> Result<V::Value, Traced> {
+        self.record(Node::Leaf(Shape::Any))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value, Traced> {
+        self.record(Node::Leaf(Shape::Any))
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value, Traced> {
+        self.record(Node::Leaf(Shape::Any))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Traced> {
+        self.record(Node::Leaf(Shape::Enum(variants)))
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Traced> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Traced> {
+        match self.path.split_first() {
+            Some((_, path)) => visitor.visit_some(Tracer { path, ..self }),
+            None => self.record(Node::Optional),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Traced> {
+        match self.path.split_first() {
+            Some((_, path)) => visitor.visit_seq(Item(Some(Tracer { path, ..self }))),
+            None => self.record(Node::List),
+        }
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Traced> {
+        match self.path.split_first() {
+            Some((_, path)) => visitor.visit_map(Entry {
+                key: Some("name"),
+                value: Tracer { path, ..self },
+            }),
+            None => self.record(Node::Attrs),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Traced> {
+        match self.path.split_first() {
+            Some((&field, path)) => visitor.visit_map(Entry {
+                key: fields.get(field).copied(),
+                value: Tracer { path, ..self },
+            }),
+            None => self.record(Node::Struct(fields)),
+        }
+    }
+}
+
+/// A sequence holding only the traced element
+struct Item<'a>(Option<Tracer<'a>>);
+
+impl<'de> SeqAccess<'de> for Item<'_> {
+    type Error = Traced;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Traced> {
+        self.0
+            .take()
+            .map(|tracer| seed.deserialize(tracer))
+            .transpose()
+    }
+}
+
+/// A map holding only the traced entry
+struct Entry<'a> {
+    key: Option<&'static str>,
+    value: Tracer<'a>,
+}
+
+impl<'de> MapAccess<'de> for Entry<'_> {
+    type Error = Traced;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Traced> {
+        self.key
+            .take()
+            .map(|key| seed.deserialize(StrDeserializer::new(key)))
+            .transpose()
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Traced> {
+        seed.deserialize(self.value)
+    }
+}
+
+/// What `T` asks for at the end of `path`, `None` if it never got that far
+fn node<T: DeserializeOwned>(path: &[usize]) -> Option<Node> {
+    let node = RefCell::new(None);
+    // Always fails, either once the node is recorded or because T rejected the path
+    let _ = T::deserialize(Tracer { path, node: &node });
+    node.into_inner()
+}
+
+fn walk<T: DeserializeOwned>(path: &mut Vec<usize>) -> Shape {
+    let child = |path: &mut Vec<usize>, index| {
+        path.push(index);
+        let shape = walk::<T>(path);
+        path.pop();
+        shape
+    };
+
+    match node::<T>(path) {
+        Some(Node::Leaf(shape)) => shape,
+        Some(Node::Optional) => Shape::Optional(Box::new(child(path, 0))),
+        Some(Node::List) => Shape::List(Box::new(child(path, 0))),
+        Some(Node::Attrs) => Shape::Attrs(Box::new(child(path, 0))),
+        Some(Node::Struct(fields)) => Shape::Struct(
+            fields
+                .iter()
+                .enumerate()
+                .map(|(index, &field)| (field, child(path, index)))
+                .collect(),
+        ),
+        None => Shape::Any,
+    }
+}
+
+/// Shape of the values `T` deserializes from
+pub fn shape<T: DeserializeOwned>() -> Shape {
+    walk::<T>(&mut Vec::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Level {
+        Low,
+        High,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    #[serde(default)]
+    struct Inner {
+        enabled: bool,
+        #[serde(rename = "match")]
+        matches: Option<String>,
+    }
+
+    impl Default for Inner {
+        fn default() -> Self {
+            Self {
+                enabled: true,
+                matches: None,
+            }
+        }
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize)]
+    struct Outer {
+        size: u32,
+        scale: f64,
+        levels: Vec<Level>,
+        inner: HashMap<String, Inner>,
+    }
+
+    #[test]
+    fn traces_nested_structs() {
+        assert_eq!(
+            shape::<Outer>(),
+            Shape::Struct(vec![
+                ("size", Shape::Int),
+                ("scale", Shape::Float),
+                (
+                    "levels",
+                    Shape::List(Box::new(Shape::Enum(&["low", "high"])))
+                ),
+                (
+                    "inner",
+                    Shape::Attrs(Box::new(Shape::Struct(vec![
+                        ("enabled", Shape::Bool),
+                        ("match", Shape::Optional(Box::new(Shape::Str))),
+                    ])))
+                ),
+            ])
+        );
+    }
+}